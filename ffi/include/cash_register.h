@@ -0,0 +1,65 @@
+/*
+ * C API for the cash-register library. Link against libcash_register_ffi
+ * (built by `cargo build -p cash-register-ffi --release`).
+ *
+ * Every function returns a status: CR_OK, a positive library error code
+ * (11 for E011, "amount too large"), or a negative CR_ERR_* value when the
+ * call itself was malformed. Structs only ever grow at the end.
+ */
+
+#ifndef CASH_REGISTER_H
+#define CASH_REGISTER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define CR_OK 0
+#define CR_ERR_NULL (-1)     /* a required pointer was null */
+#define CR_ERR_UTF8 (-2)     /* a string argument wasn't valid UTF-8 */
+#define CR_ERR_PANIC (-3)    /* the library panicked */
+#define CR_ERR_STRATEGY (-4) /* unknown strategy */
+
+#define CR_STRATEGY_GREEDY 0 /* fewest pieces */
+#define CR_STRATEGY_RANDOM 1 /* random pieces, from seed if given */
+
+/* count pieces worth cents each. */
+typedef struct CrPiece {
+    uint32_t cents;
+    uint32_t count;
+} CrPiece;
+
+/* Change to hand over, largest denomination first. Owned by the library:
+ * release it with cr_free_breakdown. */
+typedef struct CrBreakdown {
+    CrPiece *pieces;
+    size_t len;
+} CrBreakdown;
+
+/* A parsed "owed,paid" line. */
+typedef struct CrTransaction {
+    uint64_t owed_cents;
+    uint64_t paid_cents;
+    uint64_t change_cents;
+} CrTransaction;
+
+/* Make change for cents in currency ("USD", "EUR") into out. seed may be
+ * NULL for an entropy-seeded random strategy. On failure out is empty. */
+int32_t cr_make_change(uint64_t cents, const char *currency, int32_t strategy,
+                       const uint64_t *seed, CrBreakdown *out);
+
+/* Parse one "owed,paid" input line into out. */
+int32_t cr_parse_line(const char *line, CrTransaction *out);
+
+/* Release a breakdown from cr_make_change and leave it empty. Safe to call
+ * on NULL or on an already-freed breakdown. */
+void cr_free_breakdown(CrBreakdown *breakdown);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CASH_REGISTER_H */
@@ -0,0 +1,293 @@
+//! C API for the cash-register library, for POS software that can't link
+//! Rust directly. The matching header is `include/cash_register.h`.
+//!
+//! Every function returns an `int32_t` status: `CR_OK` (0), the number of a
+//! library error code (`E011` is 11, see `CashRegisterError::code`), or a
+//! negative `CR_ERR_*` value when the call itself was malformed. Panics are
+//! caught at the boundary and reported as `CR_ERR_PANIC`.
+//!
+//! Structs are `#[repr(C)]` and only ever grow at the end.
+
+use std::ffi::{c_char, CStr};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use cash_register::currency::find_currency;
+use cash_register::error::CashRegisterError;
+use cash_register::parse::parse_line;
+use cash_register::strategy::{make_change, StrategyKind};
+
+pub const CR_OK: i32 = 0;
+/// A required pointer was null.
+pub const CR_ERR_NULL: i32 = -1;
+/// A string argument wasn't valid UTF-8.
+pub const CR_ERR_UTF8: i32 = -2;
+/// The library panicked; treat the register as unusable.
+pub const CR_ERR_PANIC: i32 = -3;
+/// `strategy` wasn't `CR_STRATEGY_GREEDY` or `CR_STRATEGY_RANDOM`.
+pub const CR_ERR_STRATEGY: i32 = -4;
+
+/// Fewest pieces.
+pub const CR_STRATEGY_GREEDY: i32 = 0;
+/// Random pieces, from `seed` if given.
+pub const CR_STRATEGY_RANDOM: i32 = 1;
+
+/// `count` pieces worth `cents` each.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrPiece {
+    pub cents: u32,
+    pub count: u32,
+}
+
+/// Change to hand over, largest denomination first. Owned by the library:
+/// release it with `cr_free_breakdown`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct CrBreakdown {
+    pub pieces: *mut CrPiece,
+    pub len: usize,
+}
+
+/// A parsed `owed,paid` line.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrTransaction {
+    pub owed_cents: u64,
+    pub paid_cents: u64,
+    pub change_cents: u64,
+}
+
+/// Make change for `cents` in `currency` (a code like `"USD"`) and write it
+/// to `out`. `seed` may be null for an entropy-seeded random strategy.
+///
+/// # Safety
+///
+/// `currency` must be a NUL-terminated string, `seed` null or valid for a
+/// read, and `out` valid for a write. On success `out` must later be passed
+/// to `cr_free_breakdown`; on failure it is left empty.
+#[no_mangle]
+pub unsafe extern "C" fn cr_make_change(
+    cents: u64,
+    currency: *const c_char,
+    strategy: i32,
+    seed: *const u64,
+    out: *mut CrBreakdown,
+) -> i32 {
+    if out.is_null() {
+        return CR_ERR_NULL;
+    }
+    out.write(CrBreakdown::empty());
+    let Some(currency) = c_str(currency) else {
+        return if currency.is_null() {
+            CR_ERR_NULL
+        } else {
+            CR_ERR_UTF8
+        };
+    };
+    let seed = seed.as_ref().copied();
+    let strategy = match strategy {
+        CR_STRATEGY_GREEDY => StrategyKind::Greedy,
+        CR_STRATEGY_RANDOM => StrategyKind::Random { seed },
+        _ => return CR_ERR_STRATEGY,
+    };
+
+    guarded(|| {
+        let currency = find_currency(currency)?;
+        let breakdown = make_change(cents, currency, strategy)?;
+        let pieces: Box<[CrPiece]> = currency
+            .pieces(&breakdown)
+            .map(|(d, count)| CrPiece {
+                cents: d.cents,
+                count,
+            })
+            .collect();
+        let len = pieces.len();
+        out.write(CrBreakdown {
+            pieces: Box::into_raw(pieces).cast(),
+            len,
+        });
+        Ok(())
+    })
+}
+
+/// Parse one `owed,paid` input line into `out`.
+///
+/// # Safety
+///
+/// `line` must be a NUL-terminated string and `out` valid for a write.
+#[no_mangle]
+pub unsafe extern "C" fn cr_parse_line(line: *const c_char, out: *mut CrTransaction) -> i32 {
+    if out.is_null() {
+        return CR_ERR_NULL;
+    }
+    let Some(line) = c_str(line) else {
+        return if line.is_null() {
+            CR_ERR_NULL
+        } else {
+            CR_ERR_UTF8
+        };
+    };
+
+    guarded(|| {
+        let tx = parse_line(line, 1)?;
+        out.write(CrTransaction {
+            owed_cents: tx.owed_cents().0,
+            paid_cents: tx.paid_cents().0,
+            change_cents: tx.change_cents().0,
+        });
+        Ok(())
+    })
+}
+
+/// Release a breakdown from `cr_make_change` and leave it empty. Null, and
+/// already-freed breakdowns, are ignored.
+///
+/// # Safety
+///
+/// `breakdown` must be null or point to a breakdown filled in by
+/// `cr_make_change` (or already freed by this function).
+#[no_mangle]
+pub unsafe extern "C" fn cr_free_breakdown(breakdown: *mut CrBreakdown) {
+    let Some(breakdown) = breakdown.as_mut() else {
+        return;
+    };
+    if !breakdown.pieces.is_null() {
+        let pieces = ptr::slice_from_raw_parts_mut(breakdown.pieces, breakdown.len);
+        drop(Box::from_raw(pieces));
+    }
+    *breakdown = CrBreakdown::empty();
+}
+
+impl CrBreakdown {
+    fn empty() -> Self {
+        Self {
+            pieces: ptr::null_mut(),
+            len: 0,
+        }
+    }
+}
+
+/// The status for a library error: its code's number.
+fn status(error: &CashRegisterError) -> i32 {
+    error.code()[1..]
+        .parse()
+        .expect("error codes are E followed by digits")
+}
+
+/// Run `f`, turning its error into a status and a panic into `CR_ERR_PANIC`.
+fn guarded(f: impl FnOnce() -> Result<(), CashRegisterError>) -> i32 {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => CR_OK,
+        Ok(Err(e)) => status(&e),
+        Err(_) => CR_ERR_PANIC,
+    }
+}
+
+/// A borrowed `&str` from a C string, or `None` if null or not UTF-8.
+unsafe fn c_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pieces(breakdown: &CrBreakdown) -> Vec<(u32, u32)> {
+        unsafe { std::slice::from_raw_parts(breakdown.pieces, breakdown.len) }
+            .iter()
+            .map(|p| (p.cents, p.count))
+            .collect()
+    }
+
+    #[test]
+    fn make_change_and_free() {
+        let mut out = CrBreakdown::empty();
+        let status = unsafe {
+            cr_make_change(
+                88,
+                c"USD".as_ptr(),
+                CR_STRATEGY_GREEDY,
+                ptr::null(),
+                &mut out,
+            )
+        };
+        assert_eq!(status, CR_OK);
+        assert_eq!(pieces(&out), [(25, 3), (10, 1), (1, 3)]);
+
+        unsafe { cr_free_breakdown(&mut out) };
+        assert!(out.pieces.is_null());
+        unsafe { cr_free_breakdown(&mut out) };
+    }
+
+    #[test]
+    fn seeded_random_change_is_repeatable() {
+        let seed = 7u64;
+        let run = || {
+            let mut out = CrBreakdown::empty();
+            let status = unsafe {
+                cr_make_change(167, c"EUR".as_ptr(), CR_STRATEGY_RANDOM, &seed, &mut out)
+            };
+            assert_eq!(status, CR_OK);
+            let result = pieces(&out);
+            unsafe { cr_free_breakdown(&mut out) };
+            result
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn errors_map_to_statuses() {
+        let mut out = CrBreakdown::empty();
+        let make = |currency: &CStr, strategy, out: &mut CrBreakdown| unsafe {
+            cr_make_change(100, currency.as_ptr(), strategy, ptr::null(), out)
+        };
+        assert_eq!(make(c"GBP", CR_STRATEGY_GREEDY, &mut out), 10);
+        assert_eq!(make(c"USD", 9, &mut out), CR_ERR_STRATEGY);
+        assert!(out.pieces.is_null());
+        let status = unsafe {
+            cr_make_change(
+                u64::MAX,
+                c"USD".as_ptr(),
+                CR_STRATEGY_GREEDY,
+                ptr::null(),
+                &mut out,
+            )
+        };
+        assert_eq!(status, 11);
+        let status =
+            unsafe { cr_make_change(1, ptr::null(), CR_STRATEGY_GREEDY, ptr::null(), &mut out) };
+        assert_eq!(status, CR_ERR_NULL);
+    }
+
+    #[test]
+    fn parse_line_fills_a_transaction() {
+        let mut tx = CrTransaction {
+            owed_cents: 0,
+            paid_cents: 0,
+            change_cents: 0,
+        };
+        assert_eq!(
+            unsafe { cr_parse_line(c"2.12,3.00".as_ptr(), &mut tx) },
+            CR_OK
+        );
+        assert_eq!(
+            tx,
+            CrTransaction {
+                owed_cents: 212,
+                paid_cents: 300,
+                change_cents: 88,
+            }
+        );
+        assert_eq!(unsafe { cr_parse_line(c"5.00,3.00".as_ptr(), &mut tx) }, 2);
+        assert_eq!(unsafe { cr_parse_line(c"oops".as_ptr(), &mut tx) }, 3);
+        let invalid = [0xffu8, 0];
+        assert_eq!(
+            unsafe { cr_parse_line(invalid.as_ptr().cast(), &mut tx) },
+            CR_ERR_UTF8
+        );
+    }
+}
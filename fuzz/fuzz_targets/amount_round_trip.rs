@@ -0,0 +1,31 @@
+//! Differential check: any amount or line the parser accepts must parse back
+//! to the same value after formatting, so what we print is what we read.
+
+#![no_main]
+
+use cash_register::parse::{parse_dollars_to_cents, parse_line};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+
+    if let Ok(cents) = parse_dollars_to_cents(input) {
+        let formatted = cents.to_string();
+        assert_eq!(
+            parse_dollars_to_cents(&formatted),
+            Ok(cents),
+            "{input:?} -> {formatted:?}"
+        );
+    }
+
+    if let Ok(tx) = parse_line(input, 1) {
+        let formatted = format!("{},{}", tx.owed_cents(), tx.paid_cents());
+        assert_eq!(
+            parse_line(&formatted, 1).ok(),
+            Some(tx),
+            "{input:?} -> {formatted:?}"
+        );
+    }
+});
@@ -0,0 +1,17 @@
+//! Arbitrary bytes into `parse_dollars_to_cents`: it may reject them, but
+//! must never panic, and anything it accepts fits one transaction.
+
+#![no_main]
+
+use cash_register::money::Cents;
+use cash_register::parse::parse_dollars_to_cents;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(cents) = parse_dollars_to_cents(input) {
+        assert!(cents <= Cents::MAX_AMOUNT, "{input:?} parsed to {cents}");
+    }
+});
@@ -0,0 +1,28 @@
+//! Arbitrary input lines into `parse_line` and the whole-file parser, as a
+//! hostile POS export might send them. Errors are fine; panics are not, and
+//! every accepted transaction must be internally consistent.
+
+#![no_main]
+
+use cash_register::parse::{parse_entries_with_warnings, parse_line, Entry};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let input = String::from_utf8_lossy(data);
+
+    for (i, line) in input.lines().enumerate() {
+        if let Ok(tx) = parse_line(line, i + 1) {
+            assert!(tx.owed_cents() <= tx.paid_cents(), "{line:?}");
+            assert_eq!(tx.owed_cents() + tx.change_cents(), tx.paid_cents());
+        }
+    }
+
+    for (line, entry, warnings) in parse_entries_with_warnings(&input) {
+        for warning in &warnings {
+            assert_eq!(warning.line(), line);
+        }
+        if let Ok(Entry::Transaction(tx, _)) = entry {
+            assert_eq!(tx.owed_cents() + tx.change_cents(), tx.paid_cents());
+        }
+    }
+});
@@ -0,0 +1,145 @@
+//! Proptest strategies for property-testing code built on this crate,
+//! behind the `proptest` feature.
+//!
+//! [`Cents`], [`Transaction`], and [`Currency`] implement `Arbitrary`, so
+//! `any::<Transaction>()` works. A breakdown is a type alias, so it comes
+//! from [`breakdown`] instead, for a given currency.
+
+use alloc::{format, vec::Vec};
+
+use proptest::prelude::*;
+use proptest::sample::{select, subsequence};
+
+use crate::currency::{Currency, Denomination, DenominationKind, CURRENCIES};
+use crate::money::Cents;
+use crate::parse::Transaction;
+use crate::strategy::Breakdown;
+
+/// Largest amount owed or paid in an `any::<Transaction>()`: $1000.00.
+pub const MAX_TRANSACTION: Cents = Cents(100_000);
+
+/// Amounts from zero to `max`.
+pub fn cents(max: Cents) -> impl Strategy<Value = Cents> {
+    (0..=max.0).prop_map(Cents)
+}
+
+/// Valid transactions with owed and paid both at most `max`, which must be
+/// within `Cents::MAX_AMOUNT`.
+pub fn transaction(max: Cents) -> impl Strategy<Value = Transaction> {
+    assert!(
+        max <= Cents::MAX_AMOUNT,
+        "{max} is over the transaction limit"
+    );
+    (cents(max), cents(max))
+        .prop_map(|(a, b)| Transaction::new(a.min(b), a.max(b)).expect("owed <= paid <= max"))
+}
+
+/// One of the built-in currencies.
+pub fn builtin_currency() -> impl Strategy<Value = Currency> {
+    select(CURRENCIES.to_vec()).prop_map(|currency| currency.clone())
+}
+
+/// A made-up currency (`XTS`, the ISO code reserved for testing) with up to
+/// seven denominations. It always has a one-cent piece, so any amount can be
+/// made.
+pub fn currency() -> impl Strategy<Value = Currency> {
+    (
+        prop::collection::btree_set(2u32..=10_000, 0..=6),
+        prop::collection::vec(1u32..=100, 7),
+    )
+        .prop_map(|(values, bundles)| {
+            let denominations: Vec<Denomination> = core::iter::once(1)
+                .chain(values)
+                .zip(bundles)
+                .map(|(cents, bundle)| {
+                    let kind = if cents >= 100 {
+                        DenominationKind::Bill
+                    } else {
+                        DenominationKind::Coin
+                    };
+                    Denomination::new(
+                        cents,
+                        format!("{cents}-cent piece"),
+                        format!("{cents}-cent pieces"),
+                        bundle,
+                        kind,
+                    )
+                })
+                .collect();
+            Currency::new("XTS", "¤", denominations).expect("values are distinct and non-zero")
+        })
+}
+
+/// Normalized breakdowns in `currency`: any subset of its denomination
+/// indices, largest first, with 1 to 20 of each.
+pub fn breakdown(currency: &Currency) -> impl Strategy<Value = Breakdown> {
+    let len = currency.denominations.len();
+    subsequence((0..len).collect::<Vec<_>>(), 0..=len)
+        .prop_flat_map(|picked| {
+            let counts = prop::collection::vec(1u32..=20, picked.len());
+            (Just(picked), counts)
+        })
+        .prop_map(|(picked, counts)| picked.into_iter().zip(counts).collect())
+}
+
+impl Arbitrary for Cents {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        cents(Cents::MAX_AMOUNT).boxed()
+    }
+}
+
+impl Arbitrary for Transaction {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        transaction(MAX_TRANSACTION).boxed()
+    }
+}
+
+impl Arbitrary for Currency {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        prop_oneof![builtin_currency(), currency()].boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strategy::breakdown::{normalize, total};
+    use crate::strategy::{greedy::GreedyStrategy, ChangeStrategy};
+
+    proptest! {
+        #[test]
+        fn transactions_are_valid(tx in any::<Transaction>()) {
+            prop_assert!(tx.paid_cents() <= MAX_TRANSACTION);
+            prop_assert_eq!(tx.owed_cents() + tx.change_cents(), tx.paid_cents());
+        }
+
+        #[test]
+        fn made_up_currencies_can_make_any_amount(
+            currency in currency(),
+            amount in cents(Cents(10_000)),
+        ) {
+            let change = GreedyStrategy.make_change(amount, &currency).unwrap();
+            prop_assert_eq!(total(&change, &currency), amount);
+        }
+
+        #[test]
+        fn breakdowns_are_normalized(
+            (currency, breakdown) in any::<Currency>()
+                .prop_flat_map(|c| (Just(c.clone()), breakdown(&c)))
+        ) {
+            prop_assert_eq!(normalize(&breakdown), breakdown.clone());
+            for (index, _) in &breakdown {
+                prop_assert!(*index < currency.denominations.len());
+            }
+        }
+    }
+}
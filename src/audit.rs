@@ -0,0 +1,142 @@
+//! Checking a saved run's output against the output re-computed from the
+//! same input and options, to show after the fact that a batch wasn't
+//! altered: every output line has to come out the same, in order.
+//!
+//! The caller re-computes each input line's output and hands it to
+//! [`Audit::check`], which takes the same number of lines from the saved
+//! output to compare it with.
+
+use alloc::{string::String, vec::Vec};
+use core::str::Lines;
+
+/// An input line whose recorded output doesn't match, or recorded output
+/// no input line accounts for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// The input line, or `None` for output not from one, like a CSV
+    /// header or lines left over at the end.
+    pub line: Option<usize>,
+    /// Where the recorded output starts, counting from 1.
+    pub output_line: usize,
+    /// `None` if the recorded output had something no input line made.
+    pub expected: Option<String>,
+    /// `None` if the recorded output ended first.
+    pub recorded: Option<String>,
+}
+
+/// A saved output being checked, one re-computed line at a time.
+pub struct Audit<'a> {
+    recorded: Lines<'a>,
+    /// Recorded lines taken so far.
+    taken: usize,
+    checked: usize,
+    mismatches: Vec<Mismatch>,
+}
+
+/// What an audit found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditReport {
+    /// Outputs compared, counting a CSV header.
+    pub checked: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+impl AuditReport {
+    pub fn is_clean(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+impl<'a> Audit<'a> {
+    /// Audit `recorded`, a run's saved stdout.
+    pub fn new(recorded: &'a str) -> Self {
+        Self {
+            recorded: recorded.lines(),
+            taken: 0,
+            checked: 0,
+            mismatches: Vec::new(),
+        }
+    }
+
+    /// Compare the output re-computed for input line `line` (`None` for
+    /// output like a header) with as many recorded lines as it has.
+    pub fn check(&mut self, line: Option<usize>, expected: &str) {
+        self.checked += 1;
+        let output_line = self.taken + 1;
+        let recorded: Vec<&str> = self
+            .recorded
+            .by_ref()
+            .take(expected.split('\n').count())
+            .collect();
+        self.taken += recorded.len();
+        let recorded = (!recorded.is_empty()).then(|| recorded.join("\n"));
+        if recorded.as_deref() != Some(expected) {
+            self.mismatches.push(Mismatch {
+                line,
+                output_line,
+                expected: Some(expected.into()),
+                recorded,
+            });
+        }
+    }
+
+    /// Finish, counting any recorded lines left over as mismatches.
+    pub fn finish(mut self) -> AuditReport {
+        for text in self.recorded {
+            self.taken += 1;
+            self.mismatches.push(Mismatch {
+                line: None,
+                output_line: self.taken,
+                expected: None,
+                recorded: Some(text.into()),
+            });
+        }
+        AuditReport {
+            checked: self.checked,
+            mismatches: self.mismatches,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_lines_that_differ_or_are_left_over() {
+        let mut audit = Audit::new("3 quarters\n3 pennies\n1 dollar\n");
+        audit.check(Some(1), "3 quarters");
+        audit.check(Some(3), "1 nickel");
+        let report = audit.finish();
+        assert_eq!(report.checked, 2);
+        assert_eq!(
+            report.mismatches,
+            [
+                Mismatch {
+                    line: Some(3),
+                    output_line: 2,
+                    expected: Some("1 nickel".into()),
+                    recorded: Some("3 pennies".into()),
+                },
+                Mismatch {
+                    line: None,
+                    output_line: 3,
+                    expected: None,
+                    recorded: Some("1 dollar".into()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn multi_line_outputs_take_as_many_recorded_lines() {
+        let mut audit = Audit::new("entry\n  a\n\nentry\n");
+        audit.check(Some(1), "entry\n  a\n");
+        audit.check(Some(2), "entry\n  b\n");
+        let report = audit.finish();
+        assert_eq!(report.mismatches.len(), 1);
+        assert_eq!(report.mismatches[0].output_line, 4);
+        assert_eq!(report.mismatches[0].recorded.as_deref(), Some("entry"));
+        assert!(!report.is_clean());
+    }
+}
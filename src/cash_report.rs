@@ -0,0 +1,141 @@
+//! Cash transactions at or above a reporting threshold, for the report a
+//! business files on large cash payments (in the US, IRS Form 8300 for
+//! $10,000.00 or more).
+//!
+//! Only cash counts: the cash part of a split tender, not what went on a
+//! card, and for a refund, the cash paid out. Each entry keeps the line's
+//! `id=` and `time=` fields, when it has them, to find the sale again.
+
+use alloc::{string::String, vec::Vec};
+
+use crate::money::Cents;
+use crate::parse::{Fields, Transaction};
+
+/// One transaction to report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reportable {
+    pub line: usize,
+    /// Cash received, or for a refund, paid out.
+    pub cash: Cents,
+    pub refund: bool,
+    /// The POS's transaction ID, from `id=`.
+    pub id: Option<String>,
+    /// When it was rung up, from `time=`, as written.
+    pub time: Option<String>,
+}
+
+/// Collects the transactions to report over a run.
+#[derive(Debug, Clone)]
+pub struct CashReport {
+    threshold: Cents,
+    entries: Vec<Reportable>,
+}
+
+impl CashReport {
+    /// Report cash transactions of `threshold` or more.
+    pub fn new(threshold: Cents) -> Self {
+        Self {
+            threshold,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn threshold(&self) -> Cents {
+        self.threshold
+    }
+
+    /// Count the transaction on `line`, a refund paid out as its change if
+    /// `refund`, and return whether it has to be reported.
+    pub fn check(
+        &mut self,
+        line: usize,
+        transaction: &Transaction,
+        refund: bool,
+        fields: &Fields,
+    ) -> bool {
+        let cash = if refund {
+            transaction.change_cents()
+        } else {
+            fields
+                .split
+                .as_ref()
+                .map_or(transaction.paid_cents(), |split| split.cash())
+        };
+        if cash < self.threshold {
+            return false;
+        }
+        self.entries.push(Reportable {
+            line,
+            cash,
+            refund,
+            id: fields.id.clone(),
+            time: fields.time.clone(),
+        });
+        true
+    }
+
+    /// Take back the transaction on `line`, voided, and return whether it
+    /// was one to report.
+    pub fn void(&mut self, line: usize) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.line != line);
+        self.entries.len() < before
+    }
+
+    /// The transactions to report, in input order.
+    pub fn entries(&self) -> &[Reportable] {
+        &self.entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_cash_at_or_over_the_threshold() {
+        let mut report = CashReport::new(Cents(1_000_000));
+        let sale = |owed, paid| Transaction::new(Cents(owed), Cents(paid)).unwrap();
+        let fields = Fields {
+            id: Some("T7".into()),
+            time: Some("2026-10-16T14:05:00Z".into()),
+            ..Fields::default()
+        };
+        assert!(!report.check(1, &sale(999_999, 999_999), false, &Fields::default()));
+        assert!(report.check(2, &sale(950_000, 1_000_000), false, &fields));
+        let refund = Transaction::new(Cents::ZERO, Cents(1_200_000)).unwrap();
+        assert!(report.check(3, &refund, true, &Fields::default()));
+        assert!(report.check(4, &sale(1_500_000, 1_500_000), false, &fields));
+        assert!(report.void(4));
+        assert!(!report.void(1));
+
+        assert_eq!(
+            report.entries(),
+            [
+                Reportable {
+                    line: 2,
+                    cash: Cents(1_000_000),
+                    refund: false,
+                    id: Some("T7".into()),
+                    time: Some("2026-10-16T14:05:00Z".into()),
+                },
+                Reportable {
+                    line: 3,
+                    cash: Cents(1_200_000),
+                    refund: true,
+                    id: None,
+                    time: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn only_the_cash_part_of_a_split_tender_counts() {
+        let mut report = CashReport::new(Cents(1_000_000));
+        let (transaction, fields) =
+            crate::parse::parse_record("12000.00,CARD:5000.00+CASH:7000.00", 1).unwrap();
+        assert!(!report.check(1, &transaction, false, &fields));
+        assert!(report.entries().is_empty());
+    }
+}
@@ -0,0 +1,323 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use crate::currency::{find_currency, Currency};
+use crate::error::CashRegisterError;
+use crate::exchange::{ChangeIn, ExchangeRate};
+use crate::pictogram::Glyphs;
+use crate::policy::{ChangePolicy, PolicySpec};
+use crate::register::CashRegister;
+use crate::rounding::RoundingMode;
+use crate::strategy::rng_from_seed;
+
+/// Which strategy makes change for a transaction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StrategyChoice {
+    /// Random when the amount owed is a multiple of the divisor, greedy
+    /// otherwise.
+    #[default]
+    Auto,
+    /// Always the fewest pieces.
+    Greedy,
+    /// Always random.
+    Random,
+}
+
+impl StrategyChoice {
+    /// Every strategy, in the order `--help` lists them.
+    pub const ALL: [Self; 3] = [Self::Auto, Self::Greedy, Self::Random];
+
+    /// The divisor the rules should use to get this strategy, given the
+    /// configured one: 0 never randomizes, and every amount is a multiple
+    /// of 1.
+    pub fn rules_divisor(self, divisor: u32) -> u32 {
+        match self {
+            Self::Auto => divisor,
+            Self::Greedy => 0,
+            Self::Random => 1,
+        }
+    }
+
+    /// The name it's parsed from and serialized as.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Greedy => "greedy",
+            Self::Random => "random",
+        }
+    }
+}
+
+impl core::str::FromStr for StrategyChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "greedy" => Ok(Self::Greedy),
+            "random" => Ok(Self::Random),
+            other => Err(format!(
+                "unknown strategy \"{other}\" (expected auto, greedy, or random)"
+            )),
+        }
+    }
+}
+
+/// How each transaction's result is written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// One line of text per transaction: `3 quarters,1 dime,3 pennies`.
+    #[default]
+    Text,
+    /// One JSON object per transaction (see `format::format_json`).
+    Json,
+    /// A double-entry ledger transaction per sale, for hledger or beancount
+    /// (see `format::format_ledger_entry`).
+    Ledger,
+    /// A CSV row per transaction with a count column per denomination, for
+    /// spreadsheets (see `format::format_csv`).
+    Csv,
+    /// The `Json` objects encoded as MessagePack, one after another with
+    /// nothing between them, for pipelines where parsing JSON is the
+    /// bottleneck.
+    Msgpack,
+    /// Sentences for text-to-speech, amounts and pieces in words (see
+    /// `format::format_spoken`).
+    Spoken,
+    /// Each denomination as pictures, `🪙🪙🪙 $0.25`, for customer-facing
+    /// displays (see `format::format_pictogram`).
+    Pictogram,
+}
+
+impl OutputFormat {
+    /// Every output format.
+    pub const ALL: [Self; 7] = [
+        Self::Text,
+        Self::Json,
+        Self::Ledger,
+        Self::Csv,
+        Self::Msgpack,
+        Self::Spoken,
+        Self::Pictogram,
+    ];
+
+    /// The name it's parsed from and serialized as.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::Json => "json",
+            Self::Ledger => "ledger",
+            Self::Csv => "csv",
+            Self::Msgpack => "msgpack",
+            Self::Spoken => "spoken",
+            Self::Pictogram => "pictogram",
+        }
+    }
+}
+
+impl core::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "ledger" => Ok(Self::Ledger),
+            "csv" => Ok(Self::Csv),
+            "msgpack" => Ok(Self::Msgpack),
+            "spoken" => Ok(Self::Spoken),
+            "pictogram" => Ok(Self::Pictogram),
+            other => Err(format!(
+                "unknown output format \"{other}\" (expected text, json, ledger, csv, msgpack, spoken, or pictogram)"
+            )),
+        }
+    }
+}
+
+/// Options shared by the CLI, config files, and embedders.
+///
+/// Serializes with kebab-case keys; missing keys take the CLI's defaults
+/// (USD, divisor 3, auto strategy, text output, unseeded).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// Currency code, e.g. `USD`.
+    pub currency: String,
+    /// Randomize change when the amount owed is a multiple of this (0 never
+    /// does). Only used by the `auto` strategy.
+    pub divisor: u32,
+    pub seed: Option<u64>,
+    pub verbose: bool,
+    pub strategy: StrategyChoice,
+    pub output: OutputFormat,
+    /// Write symbols that aren't ASCII as the currency code (`EUR 1.50`).
+    /// See [`Currency::with_ascii_symbol`].
+    pub ascii_symbols: bool,
+    /// How to round amounts that aren't whole cents, like a tax engine's
+    /// `2.125`. Unset, such amounts are rejected.
+    pub rounding: Option<RoundingMode>,
+    /// The rate for lines paid in another currency (`currency=EUR`) that
+    /// don't give a `rate=` of their own.
+    pub exchange_rate: Option<ExchangeRate>,
+    /// Which currency to give change in when a line was paid in another.
+    pub change_in: ChangeIn,
+    /// How `pictogram` output draws each denomination.
+    #[serde(skip_serializing_if = "Glyphs::is_empty")]
+    pub glyphs: Glyphs,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            currency: "USD".to_string(),
+            divisor: 3,
+            seed: None,
+            verbose: false,
+            strategy: StrategyChoice::Auto,
+            output: OutputFormat::Text,
+            ascii_symbols: false,
+            rounding: None,
+            exchange_rate: None,
+            change_in: ChangeIn::Register,
+            glyphs: Glyphs::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Check the options hang together: for now, that the currency exists.
+    pub fn validate(&self) -> Result<(), CashRegisterError> {
+        self.currency().map(|_| ())
+    }
+
+    /// The configured currency.
+    pub fn currency(&self) -> Result<&'static Currency, CashRegisterError> {
+        find_currency(&self.currency)
+    }
+
+    /// The divisor the rules should use to get the configured strategy
+    /// (see [`StrategyChoice::rules_divisor`]).
+    pub fn rules_divisor(&self) -> u32 {
+        self.strategy.rules_divisor(self.divisor)
+    }
+
+    /// The [`ChangePolicy`] these options describe, with every piece given.
+    pub fn policy(&self) -> Result<ChangePolicy, CashRegisterError> {
+        ChangePolicy::new(PolicySpec {
+            currency: self.currency.clone(),
+            divisor: self.divisor,
+            strategy: self.strategy,
+            rounding: self.rounding,
+            withhold: Vec::new(),
+        })
+    }
+
+    /// An RNG from the configured seed, or from entropy without one.
+    pub fn rng(&self) -> StdRng {
+        rng_from_seed(self.seed)
+    }
+
+    /// A [`CashRegister`] set up with these options.
+    pub fn register(&self) -> Result<CashRegister<'static>, CashRegisterError> {
+        let mut builder = CashRegister::builder()
+            .currency(self.currency()?)
+            .divisor(self.rules_divisor())
+            .verbose(self.verbose);
+        if let Some(seed) = self.seed {
+            builder = builder.seed(seed);
+        }
+        Ok(builder.build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_match_the_cli() {
+        let config = Config::default();
+        assert_eq!(config.currency().unwrap().name, "USD");
+        assert_eq!(config.rules_divisor(), 3);
+        assert_eq!(config.output, OutputFormat::Text);
+    }
+
+    #[test]
+    fn deserializes_with_defaults_for_missing_keys() {
+        let config: Config =
+            serde_json::from_str(r#"{"currency": "EUR", "strategy": "greedy", "seed": 7}"#)
+                .unwrap();
+        assert_eq!(config.currency, "EUR");
+        assert_eq!(config.strategy, StrategyChoice::Greedy);
+        assert_eq!(config.seed, Some(7));
+        assert_eq!(config.divisor, 3);
+        assert_eq!(config.rounding, None);
+        assert!(serde_json::from_str::<Config>(r#"{"colour": "red"}"#).is_err());
+        let config: Config = serde_json::from_str(r#"{"rounding": "half-even"}"#).unwrap();
+        assert_eq!(config.rounding, Some(RoundingMode::HalfEven));
+        let config: Config =
+            serde_json::from_str(r#"{"exchange-rate": "1.08", "change-in": "tendered"}"#).unwrap();
+        assert_eq!(config.exchange_rate, "1.08".parse().ok());
+        assert_eq!(config.change_in, ChangeIn::Tendered);
+        let config: Config = serde_json::from_str(r#"{"glyphs": {"coin": "o"}}"#).unwrap();
+        assert!(!config.glyphs.is_empty());
+        assert!(serde_json::from_str::<Config>(r#"{"exchange-rate": "0"}"#).is_err());
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(serde_json::from_str::<Config>(&json).unwrap(), config);
+    }
+
+    #[test]
+    fn strategy_sets_the_rules_divisor() {
+        let config = |strategy| Config {
+            strategy,
+            ..Config::default()
+        };
+        assert_eq!(config(StrategyChoice::Greedy).rules_divisor(), 0);
+        assert_eq!(config(StrategyChoice::Random).rules_divisor(), 1);
+        assert_eq!("Random".parse(), Ok(StrategyChoice::Random));
+        assert!("cheapest".parse::<StrategyChoice>().is_err());
+        assert_eq!("json".parse(), Ok(OutputFormat::Json));
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for strategy in StrategyChoice::ALL {
+            assert_eq!(strategy.as_str().parse(), Ok(strategy));
+            let json = serde_json::to_string(&strategy).unwrap();
+            assert_eq!(json, format!("\"{}\"", strategy.as_str()));
+        }
+        for output in OutputFormat::ALL {
+            assert_eq!(output.as_str().parse(), Ok(output));
+        }
+    }
+
+    #[test]
+    fn validates_the_currency() {
+        let config = Config {
+            currency: "GBP".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(config.validate().unwrap_err().code(), "E010");
+        assert!(config.register().is_err());
+        assert!(config.policy().is_err());
+
+        let mut register = Config {
+            strategy: StrategyChoice::Greedy,
+            ..Config::default()
+        }
+        .register()
+        .unwrap();
+        // 3.00 is a multiple of 3, but greedy never randomizes.
+        assert_eq!(
+            register.process("3.00,5.00\n")[0].as_deref().unwrap(),
+            "2 dollars"
+        );
+    }
+}
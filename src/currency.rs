@@ -1,105 +1,382 @@
-/// A single denomination: its value in cents and display names.
-#[derive(Debug, Clone, Copy)]
+use alloc::borrow::Cow;
+use alloc::{format, string::String, vec::Vec};
+
+use crate::error::CashRegisterError;
+use crate::money::Cents;
+use crate::weight::Physical;
+
+/// A single denomination: its value in cents, display names, and how the
+/// bank packages it.
+#[derive(Debug, Clone)]
 pub struct Denomination {
     pub cents: u32,
-    pub singular: &'static str,
-    pub plural: &'static str,
+    pub singular: Cow<'static, str>,
+    pub plural: Cow<'static, str>,
+    /// Pieces in one bank bundle (a roll of coins or a strap of bills).
+    pub bundle: u32,
+    pub kind: DenominationKind,
+    /// Weight and size of one piece, for weighing change; `None` if unknown.
+    pub physical: Option<Physical>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DenominationKind {
+    Coin,
+    Bill,
+}
+
+impl Denomination {
+    pub fn new(
+        cents: u32,
+        singular: impl Into<Cow<'static, str>>,
+        plural: impl Into<Cow<'static, str>>,
+        bundle: u32,
+        kind: DenominationKind,
+    ) -> Self {
+        Self {
+            cents,
+            singular: singular.into(),
+            plural: plural.into(),
+            bundle,
+            kind,
+            physical: None,
+        }
+    }
+
+    /// This denomination, with one piece weighing `milligrams` and taking
+    /// up `cubic_mm`.
+    pub fn with_physical(mut self, milligrams: u32, cubic_mm: u32) -> Self {
+        self.physical = Some(Physical {
+            milligrams,
+            cubic_mm,
+        });
+        self
+    }
+
+    pub fn value(&self) -> Cents {
+        Cents::from(self.cents)
+    }
+
+    /// What the bank calls a bundle of this denomination.
+    pub fn bundle_name(&self) -> &'static str {
+        match self.kind {
+            DenominationKind::Coin => "roll",
+            DenominationKind::Bill => "strap",
+        }
+    }
 }
 
 /// A currency configuration: a name, symbol, and denominations (largest first).
+///
+/// The built-in currencies borrow static tables; [`Currency::new`] builds
+/// an owned one at runtime, e.g. from a config file.
 #[derive(Debug, Clone)]
 pub struct Currency {
-    pub name: &'static str,
-    pub symbol: &'static str,
-    pub denominations: &'static [Denomination],
+    pub name: Cow<'static, str>,
+    pub symbol: Cow<'static, str>,
+    pub denominations: Cow<'static, [Denomination]>,
+}
+
+impl Currency {
+    /// Build a currency from denominations in any order; they are sorted
+    /// largest first. Rejects an empty list, zero values or bundle sizes,
+    /// and two denominations with the same value.
+    pub fn new(
+        name: impl Into<Cow<'static, str>>,
+        symbol: impl Into<Cow<'static, str>>,
+        mut denominations: Vec<Denomination>,
+    ) -> Result<Self, String> {
+        let name = name.into();
+        if denominations.is_empty() {
+            return Err(format!("{name} has no denominations"));
+        }
+        if let Some(d) = denominations.iter().find(|d| d.cents == 0) {
+            return Err(format!("{name}: {} is worth nothing", d.singular));
+        }
+        if let Some(d) = denominations.iter().find(|d| d.bundle == 0) {
+            return Err(format!("{name}: {} has an empty bundle", d.singular));
+        }
+        denominations.sort_by_key(|d| core::cmp::Reverse(d.cents));
+        if let Some(pair) = denominations.windows(2).find(|w| w[0].cents == w[1].cents) {
+            return Err(format!(
+                "{name}: {} and {} have the same value",
+                pair[0].singular, pair[1].singular
+            ));
+        }
+        Ok(Self {
+            name,
+            symbol: symbol.into(),
+            denominations: Cow::Owned(denominations),
+        })
+    }
+
+    /// A breakdown's pieces as `(denomination, count)`, with each index
+    /// looked up in this currency's table.
+    pub fn pieces<'a>(
+        &'a self,
+        breakdown: &'a [(usize, u32)],
+    ) -> impl Iterator<Item = (&'a Denomination, u32)> + 'a {
+        breakdown
+            .iter()
+            .map(|&(i, count)| (&self.denominations[i], count))
+    }
+
+    /// This currency with a symbol that isn't ASCII spelled as its code
+    /// instead (`EUR 1.50` rather than `€1.50`), for receipt printers and
+    /// terminals that mangle multi-byte symbols.
+    pub fn with_ascii_symbol(&self) -> Currency {
+        let mut currency = self.clone();
+        if !currency.symbol.is_ascii() {
+            currency.symbol = Cow::Owned(format!("{} ", currency.name));
+        }
+        currency
+    }
+
+    /// The index in [`denominations`](Currency::denominations) of the piece
+    /// worth `cents`.
+    pub fn index_of(&self, cents: u32) -> Option<usize> {
+        self.denominations.iter().position(|d| d.cents == cents)
+    }
 }
 
 pub static USD: Currency = Currency {
-    name: "USD",
-    symbol: "$",
-    denominations: &[
+    name: Cow::Borrowed("USD"),
+    symbol: Cow::Borrowed("$"),
+    denominations: Cow::Borrowed(&[
         Denomination {
             cents: 100,
-            singular: "dollar",
-            plural: "dollars",
+            singular: Cow::Borrowed("dollar"),
+            plural: Cow::Borrowed("dollars"),
+            bundle: 100,
+            kind: DenominationKind::Bill,
+            physical: Some(Physical {
+                milligrams: 1000,
+                cubic_mm: 1138,
+            }),
         },
         Denomination {
             cents: 25,
-            singular: "quarter",
-            plural: "quarters",
+            singular: Cow::Borrowed("quarter"),
+            plural: Cow::Borrowed("quarters"),
+            bundle: 40,
+            kind: DenominationKind::Coin,
+            physical: Some(Physical {
+                milligrams: 5670,
+                cubic_mm: 809,
+            }),
         },
         Denomination {
             cents: 10,
-            singular: "dime",
-            plural: "dimes",
+            singular: Cow::Borrowed("dime"),
+            plural: Cow::Borrowed("dimes"),
+            bundle: 50,
+            kind: DenominationKind::Coin,
+            physical: Some(Physical {
+                milligrams: 2268,
+                cubic_mm: 340,
+            }),
         },
         Denomination {
             cents: 5,
-            singular: "nickel",
-            plural: "nickels",
+            singular: Cow::Borrowed("nickel"),
+            plural: Cow::Borrowed("nickels"),
+            bundle: 40,
+            kind: DenominationKind::Coin,
+            physical: Some(Physical {
+                milligrams: 5000,
+                cubic_mm: 689,
+            }),
         },
         Denomination {
             cents: 1,
-            singular: "penny",
-            plural: "pennies",
+            singular: Cow::Borrowed("penny"),
+            plural: Cow::Borrowed("pennies"),
+            bundle: 50,
+            kind: DenominationKind::Coin,
+            physical: Some(Physical {
+                milligrams: 2500,
+                cubic_mm: 433,
+            }),
         },
-    ],
+    ]),
 };
 
 pub static EUR: Currency = Currency {
-    name: "EUR",
-    symbol: "€",
-    denominations: &[
+    name: Cow::Borrowed("EUR"),
+    symbol: Cow::Borrowed("€"),
+    denominations: Cow::Borrowed(&[
         Denomination {
             cents: 200,
-            singular: "2 euro coin",
-            plural: "2 euro coins",
+            singular: Cow::Borrowed("2 euro coin"),
+            plural: Cow::Borrowed("2 euro coins"),
+            bundle: 25,
+            kind: DenominationKind::Coin,
+            physical: Some(Physical {
+                milligrams: 8500,
+                cubic_mm: 1146,
+            }),
         },
         Denomination {
             cents: 100,
-            singular: "1 euro coin",
-            plural: "1 euro coins",
+            singular: Cow::Borrowed("1 euro coin"),
+            plural: Cow::Borrowed("1 euro coins"),
+            bundle: 25,
+            kind: DenominationKind::Coin,
+            physical: Some(Physical {
+                milligrams: 7500,
+                cubic_mm: 989,
+            }),
         },
         Denomination {
             cents: 50,
-            singular: "50 cent coin",
-            plural: "50 cent coins",
+            singular: Cow::Borrowed("50 cent coin"),
+            plural: Cow::Borrowed("50 cent coins"),
+            bundle: 40,
+            kind: DenominationKind::Coin,
+            physical: Some(Physical {
+                milligrams: 7800,
+                cubic_mm: 1099,
+            }),
         },
         Denomination {
             cents: 20,
-            singular: "20 cent coin",
-            plural: "20 cent coins",
+            singular: Cow::Borrowed("20 cent coin"),
+            plural: Cow::Borrowed("20 cent coins"),
+            bundle: 40,
+            kind: DenominationKind::Coin,
+            physical: Some(Physical {
+                milligrams: 5740,
+                cubic_mm: 832,
+            }),
         },
         Denomination {
             cents: 10,
-            singular: "10 cent coin",
-            plural: "10 cent coins",
+            singular: Cow::Borrowed("10 cent coin"),
+            plural: Cow::Borrowed("10 cent coins"),
+            bundle: 40,
+            kind: DenominationKind::Coin,
+            physical: Some(Physical {
+                milligrams: 4100,
+                cubic_mm: 591,
+            }),
         },
         Denomination {
             cents: 5,
-            singular: "5 cent coin",
-            plural: "5 cent coins",
+            singular: Cow::Borrowed("5 cent coin"),
+            plural: Cow::Borrowed("5 cent coins"),
+            bundle: 50,
+            kind: DenominationKind::Coin,
+            physical: Some(Physical {
+                milligrams: 3920,
+                cubic_mm: 592,
+            }),
         },
         Denomination {
             cents: 2,
-            singular: "2 cent coin",
-            plural: "2 cent coins",
+            singular: Cow::Borrowed("2 cent coin"),
+            plural: Cow::Borrowed("2 cent coins"),
+            bundle: 50,
+            kind: DenominationKind::Coin,
+            physical: Some(Physical {
+                milligrams: 3060,
+                cubic_mm: 461,
+            }),
         },
         Denomination {
             cents: 1,
-            singular: "1 cent coin",
-            plural: "1 cent coins",
+            singular: Cow::Borrowed("1 cent coin"),
+            plural: Cow::Borrowed("1 cent coins"),
+            bundle: 50,
+            kind: DenominationKind::Coin,
+            physical: Some(Physical {
+                milligrams: 2300,
+                cubic_mm: 346,
+            }),
         },
-    ],
+    ]),
 };
 
+/// Every built-in currency.
+pub static CURRENCIES: [&Currency; 2] = [&USD, &EUR];
+
+/// Look up a built-in currency by its code, ignoring case. An unknown code
+/// comes back with the closest codes as suggestions, so `ERU` or `US` gets
+/// a "did you mean".
+pub fn find_currency(name: &str) -> Result<&'static Currency, CashRegisterError> {
+    CURRENCIES
+        .iter()
+        .copied()
+        .find(|c| c.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| CashRegisterError::UnsupportedCurrency {
+            name: name.to_uppercase(),
+            supported: CURRENCIES
+                .iter()
+                .map(|c| c.name.as_ref())
+                .collect::<Vec<_>>()
+                .join(", "),
+            suggestions: closest_codes(&name.to_uppercase()),
+        })
+}
+
+/// Edits past which a code is too far off to be a typo of another.
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// The registered codes nearest `name`, in registry order, if any are
+/// within `MAX_SUGGESTION_DISTANCE` edits.
+fn closest_codes(name: &str) -> Vec<String> {
+    let distances: Vec<(usize, &str)> = CURRENCIES
+        .iter()
+        .map(|c| (edit_distance(name, &c.name), c.name.as_ref()))
+        .collect();
+    let Some(best) = distances.iter().map(|&(d, _)| d).min() else {
+        return Vec::new();
+    };
+    if best > MAX_SUGGESTION_DISTANCE {
+        return Vec::new();
+    }
+    distances
+        .into_iter()
+        .filter(|&(d, _)| d == best)
+        .map(|(_, code)| code.into())
+        .collect()
+}
+
+/// Edits (insertions, deletions, substitutions, and swaps of neighbouring
+/// characters) to turn `a` into `b`, so `ERU` is one edit from `EUR`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // rows[i][j]: edits between the first i chars of a and j of b.
+    let mut rows = alloc::vec![alloc::vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = usize::from(a[i - 1] != b[j - 1]);
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + substitution);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::{string::ToString, vec};
 
     #[test]
     fn usd_denominations_are_sorted_descending() {
-        let denoms = USD.denominations;
+        let denoms = &USD.denominations;
         for window in denoms.windows(2) {
             assert!(
                 window[0].cents > window[1].cents,
@@ -121,6 +398,87 @@ mod tests {
         );
     }
 
+    #[test]
+    fn every_denomination_has_a_bundle() {
+        for d in USD.denominations.iter().chain(EUR.denominations.iter()) {
+            assert!(d.bundle > 0, "{} has an empty bundle", d.singular);
+        }
+    }
+
+    #[test]
+    fn runtime_currency_sorts_and_validates() {
+        let coin = |cents, name: &str| {
+            Denomination::new(
+                cents,
+                name.to_string(),
+                format!("{name}s"),
+                20,
+                DenominationKind::Coin,
+            )
+        };
+        let gbp = Currency::new(
+            "GBP".to_string(),
+            "£",
+            vec![coin(1, "penny"), coin(50, "fifty"), coin(200, "two pound")],
+        )
+        .unwrap();
+        let values: Vec<u32> = gbp.denominations.iter().map(|d| d.cents).collect();
+        assert_eq!(values, [200, 50, 1]);
+        assert_eq!(gbp.denominations[1].plural, "fiftys");
+
+        assert!(Currency::new("X", "x", vec![]).is_err());
+        assert!(Currency::new("X", "x", vec![coin(0, "nothing")]).is_err());
+        let err = Currency::new("X", "x", vec![coin(5, "a"), coin(5, "b")]).unwrap_err();
+        assert_eq!(err, "X: a and b have the same value");
+    }
+
+    #[test]
+    fn pieces_look_up_indices() {
+        assert_eq!(USD.index_of(25), Some(USD.denominations.len() - 4));
+        assert_eq!(USD.index_of(3), None);
+        let quarters = [(USD.index_of(25).unwrap(), 3)];
+        let named: Vec<_> = USD
+            .pieces(&quarters)
+            .map(|(d, n)| (&*d.plural, n))
+            .collect();
+        assert_eq!(named, [("quarters", 3)]);
+    }
+
+    #[test]
+    fn find_currency_by_code() {
+        assert_eq!(find_currency("eur").unwrap().name, "EUR");
+        let err = find_currency("gbp").unwrap_err();
+        assert_eq!(err.code(), "E010");
+        assert_eq!(
+            err.to_string(),
+            "Unknown currency: GBP. Supported: USD, EUR"
+        );
+    }
+
+    #[test]
+    fn unknown_codes_get_suggestions() {
+        assert_eq!(
+            find_currency("eru").unwrap_err().to_string(),
+            "Unknown currency: ERU. Did you mean EUR?"
+        );
+        assert_eq!(
+            find_currency("US").unwrap_err().to_string(),
+            "Unknown currency: US. Did you mean USD?"
+        );
+        assert_eq!(edit_distance("EUR", "EUR"), 0);
+        assert_eq!(edit_distance("UDS", "USD"), 1);
+        assert_eq!(edit_distance("", "USD"), 3);
+        assert_eq!(closest_codes("XYZ"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn ascii_symbol_spells_out_the_code() {
+        let eur = EUR.with_ascii_symbol();
+        assert_eq!(Cents(150).with_symbol(&eur.symbol), "EUR 1.50");
+        assert_eq!(eur.denominations.len(), EUR.denominations.len());
+        assert_eq!(USD.with_ascii_symbol().symbol, "$");
+    }
+
     #[test]
     fn eur_smallest_denomination_is_one_cent() {
         let last = EUR.denominations.last().unwrap();
@@ -0,0 +1,280 @@
+//! A terminal dashboard for watching a batch as it runs, behind the `tui`
+//! feature: what's in each drawer, the latest transactions, and how many
+//! lines failed, by error code.
+//!
+//! [`Dashboard`] only holds what's on screen and draws it into a ratatui
+//! [`Frame`]; the caller owns the terminal and decides how often to redraw.
+//!
+//! ```ignore
+//! let mut dashboard = Dashboard::new(&USD, 10);
+//! dashboard.record_sale(1, "3 quarters,1 dime,3 pennies");
+//! dashboard.set_tills(registers.iter());
+//! terminal.draw(|frame| dashboard.render(frame))?;
+//! ```
+
+use std::collections::{BTreeMap, VecDeque};
+
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, List, ListItem, Paragraph, Row, Table};
+use ratatui::Frame;
+
+use crate::currency::Currency;
+use crate::error::CashRegisterError;
+use crate::till::Till;
+
+/// What the dashboard shows, updated as lines are processed.
+#[derive(Debug, Clone)]
+pub struct Dashboard {
+    currency: Currency,
+    /// The latest output lines, oldest first, at most `capacity` of them.
+    recent: VecDeque<(usize, String)>,
+    capacity: usize,
+    /// The last input line seen.
+    line: usize,
+    sales: u64,
+    errors: BTreeMap<&'static str, u64>,
+    last_error: Option<String>,
+    tills: Vec<(String, Till)>,
+    finished: bool,
+}
+
+impl Dashboard {
+    /// An empty dashboard listing the last `recent` transactions.
+    pub fn new(currency: &Currency, recent: usize) -> Self {
+        Self {
+            currency: currency.clone(),
+            recent: VecDeque::with_capacity(recent),
+            capacity: recent,
+            line: 0,
+            sales: 0,
+            errors: BTreeMap::new(),
+            last_error: None,
+            tills: Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Count a transaction on input line `line`, printed as `text`.
+    pub fn record_sale(&mut self, line: usize, text: &str) {
+        self.sales += 1;
+        self.line = self.line.max(line);
+        if self.capacity == 0 {
+            return;
+        }
+        if self.recent.len() == self.capacity {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((line, text.to_string()));
+    }
+
+    /// Count a failed line under its error code.
+    pub fn record_error(&mut self, error: &CashRegisterError) {
+        *self.errors.entry(error.code()).or_default() += 1;
+        if let Some(line) = error.line() {
+            self.line = self.line.max(line);
+        }
+        self.last_error = Some(error.to_string());
+    }
+
+    /// Show these drawers, as `(register, till)` pairs.
+    pub fn set_tills<'t>(&mut self, tills: impl IntoIterator<Item = (&'t str, &'t Till)>) {
+        self.tills = tills
+            .into_iter()
+            .map(|(register, till)| (register.to_string(), till.clone()))
+            .collect();
+    }
+
+    /// Mark the batch done, so the status line says so.
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// Lines that failed, across every code.
+    pub fn error_count(&self) -> u64 {
+        self.errors.values().sum()
+    }
+
+    /// Draw the dashboard over the whole frame: a status line, the drawers
+    /// on the left, and the latest transactions over the error counts on the
+    /// right.
+    pub fn render(&self, frame: &mut Frame) {
+        let [status, body] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(frame.area());
+        let [drawers, right] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(body);
+        let [recent, errors] =
+            Layout::vertical([Constraint::Min(3), Constraint::Length(self.errors_height())])
+                .areas(right);
+
+        frame.render_widget(Paragraph::new(self.status_line()), status);
+        self.render_drawers(frame, drawers);
+        self.render_recent(frame, recent);
+        self.render_errors(frame, errors);
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        let state = if self.finished {
+            "finished, q to quit".green()
+        } else {
+            "running, q to stop".yellow()
+        };
+        Line::from(vec![
+            format!(
+                " cash-register  line {}  {} sales  {} errors  ",
+                self.line,
+                self.sales,
+                self.error_count()
+            )
+            .bold(),
+            state,
+        ])
+    }
+
+    fn render_drawers(&self, frame: &mut Frame, area: Rect) {
+        if self.tills.is_empty() {
+            let block = Block::bordered().title(" Drawers ");
+            let text =
+                Paragraph::new("No drawer tracked (run with --till or --till-state)").block(block);
+            frame.render_widget(text, area);
+            return;
+        }
+        let areas = Layout::vertical(vec![Constraint::Fill(1); self.tills.len()]).split(area);
+        let sym = &self.currency.symbol;
+        for ((register, till), &area) in self.tills.iter().zip(areas.iter()) {
+            let rows = self.currency.denominations.iter().map(|denomination| {
+                let count = till.count(denomination.cents);
+                let row = Row::new([
+                    denomination.plural.to_string(),
+                    count.to_string(),
+                    (denomination.value() * count).with_symbol(sym),
+                ]);
+                // An empty slot is the one the next sale may be short of.
+                if count == 0 {
+                    row.style(Style::new().fg(Color::Red))
+                } else {
+                    row
+                }
+            });
+            let title = match register.as_str() {
+                "" => format!(" Drawer  {} ", till.total_cents().with_symbol(sym)),
+                name => format!(" Drawer {name}  {} ", till.total_cents().with_symbol(sym)),
+            };
+            let widths = [
+                Constraint::Fill(1),
+                Constraint::Length(8),
+                Constraint::Length(12),
+            ];
+            let table = Table::new(rows, widths)
+                .header(Row::new(["", "count", "value"]).bold())
+                .block(Block::bordered().title(title));
+            frame.render_widget(table, area);
+        }
+    }
+
+    fn render_recent(&self, frame: &mut Frame, area: Rect) {
+        // Newest at the bottom, like the output scrolling past.
+        let shown = usize::from(area.height.saturating_sub(2));
+        let items: Vec<ListItem> = self
+            .recent
+            .iter()
+            .skip(self.recent.len().saturating_sub(shown))
+            .map(|(line, text)| ListItem::new(format!("{line:>6}  {text}")))
+            .collect();
+        let list = List::new(items).block(Block::bordered().title(" Latest transactions "));
+        frame.render_widget(list, area);
+    }
+
+    fn render_errors(&self, frame: &mut Frame, area: Rect) {
+        let mut lines: Vec<Line> = self
+            .errors
+            .iter()
+            .map(|(code, count)| Line::from(format!("{code}  {count}")))
+            .collect();
+        match &self.last_error {
+            Some(last) => lines.push(Line::from(format!("last: {last}")).red()),
+            None => lines.push(Line::from("none")),
+        }
+        let title = format!(" Errors  {} ", self.error_count());
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(title)),
+            area,
+        );
+    }
+
+    /// One row per error code, one for the last error, and the borders.
+    fn errors_height(&self) -> u16 {
+        u16::try_from(self.errors.len())
+            .unwrap_or(u16::MAX)
+            .saturating_add(3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::USD;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn screen(dashboard: &Dashboard) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+        terminal.draw(|frame| dashboard.render(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let width = usize::from(buffer.area.width);
+        let symbols: Vec<&str> = buffer.content().iter().map(|cell| cell.symbol()).collect();
+        symbols
+            .chunks(width)
+            .map(|row| row.concat().trim_end().to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn keeps_the_latest_transactions_and_counts_errors() {
+        let mut dashboard = Dashboard::new(&USD, 2);
+        dashboard.record_sale(1, "3 quarters,1 dime,3 pennies");
+        dashboard.record_sale(2, "3 pennies");
+        dashboard.record_sale(4, "1 dollar");
+        let error = CashRegisterError::MalformedLine {
+            line: 3,
+            detail: "expected \"owed,paid\"".into(),
+        };
+        dashboard.record_error(&error);
+        dashboard.record_error(&error);
+        assert_eq!(dashboard.error_count(), 2);
+
+        let screen = screen(&dashboard);
+        assert!(
+            screen.contains("line 4  3 sales  2 errors  running, q to stop"),
+            "{screen}"
+        );
+        assert!(!screen.contains("3 quarters"), "{screen}");
+        assert!(screen.contains("     2  3 pennies"), "{screen}");
+        assert!(screen.contains("     4  1 dollar"), "{screen}");
+        assert!(screen.contains("E003  2"), "{screen}");
+        assert!(
+            screen.contains("last: line 3: expected \"owed,paid\""),
+            "{screen}"
+        );
+        assert!(screen.contains("No drawer tracked"), "{screen}");
+    }
+
+    #[test]
+    fn shows_each_drawer() {
+        let mut till = Till::new();
+        till.add(100, 5).unwrap();
+        till.add(25, 4).unwrap();
+        let mut dashboard = Dashboard::new(&USD, 5);
+        dashboard.set_tills([("", &till), ("R2", &Till::new())]);
+        dashboard.finish();
+
+        let screen = screen(&dashboard);
+        assert!(screen.contains("Drawer  $6.00"), "{screen}");
+        assert!(screen.contains("Drawer R2  $0.00"), "{screen}");
+        assert!(screen.contains("quarters"), "{screen}");
+        assert!(screen.contains("finished, q to quit"), "{screen}");
+    }
+}
@@ -0,0 +1,185 @@
+//! Conversions between [`Cents`] and `rust_decimal::Decimal`, behind the
+//! `decimal` feature, for services that already pass amounts around as
+//! decimals.
+//!
+//! ```ignore
+//! let tx = Transaction::new(Cents::try_from(owed)?, Cents::try_from(paid)?)?;
+//! let change = Decimal::from(tx.change_cents()); // 0.88
+//! ```
+//!
+//! The `rusty_money` feature converts `rusty_money::Money` too, a decimal
+//! plus its currency. A `Money` needs a currency to be made, so `Cents` go
+//! the other way with [`Cents::to_money`]:
+//!
+//! ```ignore
+//! let owed = Cents::try_from(&Money::from_str("2.12", iso::USD)?)?;
+//! let change = tx.change_cents().to_money(iso::USD); // $0.88
+//! ```
+
+use rust_decimal::{Decimal, RoundingStrategy};
+#[cfg(feature = "rusty_money")]
+use rusty_money::{FormattableCurrency, Money};
+
+use crate::error::DecimalError;
+use crate::money::Cents;
+use crate::rounding::RoundingMode;
+
+/// The amount in whole units with two decimal places: `Cents(213)` is `2.13`.
+impl From<Cents> for Decimal {
+    fn from(cents: Cents) -> Self {
+        Decimal::from_i128_with_scale(i128::from(cents.0), 2)
+    }
+}
+
+/// A decimal amount in whole units, like `2.13`. Like
+/// [`parse_dollars_to_cents`](crate::parse::parse_dollars_to_cents), it must
+/// be a whole number of cents from zero to `Cents::MAX_AMOUNT`; trailing
+/// zeros (`2.1300`) are fine.
+impl TryFrom<Decimal> for Cents {
+    type Error = DecimalError;
+
+    fn try_from(amount: Decimal) -> Result<Self, DecimalError> {
+        if amount.is_sign_negative() && !amount.is_zero() {
+            return Err(DecimalError::Negative(amount));
+        }
+        let cents = amount
+            .checked_mul(Decimal::ONE_HUNDRED)
+            .ok_or(DecimalError::TooLarge(amount))?
+            .normalize();
+        if cents.scale() != 0 {
+            return Err(DecimalError::FractionalCent(amount));
+        }
+        u64::try_from(cents.mantissa())
+            .map(Cents)
+            .ok()
+            .filter(|&cents| cents <= Cents::MAX_AMOUNT)
+            .ok_or(DecimalError::TooLarge(amount))
+    }
+}
+
+impl Cents {
+    /// Like `Cents::try_from(amount)`, but a fraction of a cent is rounded
+    /// away as `mode` says instead of rejected: `2.125` is 2.13 half-up.
+    pub fn from_decimal_rounded(amount: Decimal, mode: RoundingMode) -> Result<Self, DecimalError> {
+        let strategy = match mode {
+            RoundingMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            RoundingMode::HalfDown => RoundingStrategy::MidpointTowardZero,
+            RoundingMode::HalfEven => RoundingStrategy::MidpointNearestEven,
+            RoundingMode::TowardZero => RoundingStrategy::ToZero,
+        };
+        Cents::try_from(amount.round_dp_with_strategy(2, strategy)).map_err(|e| match e {
+            // Report the amount as given, not as rounded.
+            DecimalError::Negative(_) => DecimalError::Negative(amount),
+            DecimalError::TooLarge(_) => DecimalError::TooLarge(amount),
+            other => other,
+        })
+    }
+}
+
+/// A `Money` amount, as `Cents::try_from(*money.amount())` would take it,
+/// but only in a currency counted in hundredths: a yen is no cent.
+#[cfg(feature = "rusty_money")]
+impl<T: FormattableCurrency> TryFrom<&Money<'_, T>> for Cents {
+    type Error = DecimalError;
+
+    fn try_from(money: &Money<'_, T>) -> Result<Self, DecimalError> {
+        let currency = money.currency();
+        if currency.exponent() != 2 {
+            return Err(DecimalError::NotCents(currency.code()));
+        }
+        Cents::try_from(*money.amount())
+    }
+}
+
+#[cfg(feature = "rusty_money")]
+impl<T: FormattableCurrency> TryFrom<Money<'_, T>> for Cents {
+    type Error = DecimalError;
+
+    fn try_from(money: Money<'_, T>) -> Result<Self, DecimalError> {
+        Cents::try_from(&money)
+    }
+}
+
+#[cfg(feature = "rusty_money")]
+impl Cents {
+    /// The amount as `Money` in `currency`, which should be counted in
+    /// hundredths: `Cents(213).to_money(iso::USD)` is $2.13.
+    pub fn to_money<T: FormattableCurrency>(self, currency: &T) -> Money<'_, T> {
+        Money::from_decimal(Decimal::from(self), currency)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn converts_both_ways() {
+        assert_eq!(Decimal::from(Cents(213)), dec("2.13"));
+        assert_eq!(Decimal::from(Cents(0)).to_string(), "0.00");
+        assert_eq!(Cents::try_from(dec("2.13")), Ok(Cents(213)));
+        assert_eq!(Cents::try_from(dec("2.1300")), Ok(Cents(213)));
+        assert_eq!(Cents::try_from(dec("3")), Ok(Cents(300)));
+        assert_eq!(Cents::try_from(dec("-0.00")), Ok(Cents(0)));
+        let max = Decimal::from(Cents::MAX_AMOUNT);
+        assert_eq!(Cents::try_from(max), Ok(Cents::MAX_AMOUNT));
+    }
+
+    #[test]
+    fn rounds_fractions_of_a_cent_by_mode() {
+        let round = |s, mode| Cents::from_decimal_rounded(dec(s), mode);
+        assert_eq!(round("2.125", RoundingMode::HalfUp), Ok(Cents(213)));
+        assert_eq!(round("2.125", RoundingMode::HalfDown), Ok(Cents(212)));
+        assert_eq!(round("2.135", RoundingMode::HalfEven), Ok(Cents(214)));
+        assert_eq!(round("2.129", RoundingMode::TowardZero), Ok(Cents(212)));
+        assert_eq!(
+            round("-1.001", RoundingMode::HalfUp),
+            Err(DecimalError::Negative(dec("-1.001")))
+        );
+    }
+
+    #[test]
+    fn rejects_amounts_that_are_not_cents() {
+        assert_eq!(
+            Cents::try_from(dec("-1.00")),
+            Err(DecimalError::Negative(dec("-1.00")))
+        );
+        let err = Cents::try_from(dec("2.125")).unwrap_err();
+        assert_eq!(err, DecimalError::FractionalCent(dec("2.125")));
+        assert_eq!(err.to_string(), "2.125 has a fraction of a cent");
+        assert_eq!(err.code(), "E001");
+        let err = Cents::try_from(dec("42949672.96")).unwrap_err();
+        assert_eq!(err.code(), "E011");
+        assert!(Cents::try_from(Decimal::MAX).is_err());
+    }
+
+    #[cfg(feature = "rusty_money")]
+    #[test]
+    fn converts_money_in_cents() {
+        use rusty_money::iso;
+
+        let money = Money::from_str("2.13", iso::USD).unwrap();
+        assert_eq!(Cents::try_from(&money), Ok(Cents(213)));
+        assert_eq!(Cents::try_from(money), Ok(Cents(213)));
+        assert_eq!(
+            Cents(88).to_money(iso::EUR),
+            Money::from_minor(88, iso::EUR)
+        );
+        assert_eq!(Cents(88).to_money(iso::USD).to_string(), "$0.88");
+
+        let yen = Money::from_major(500, iso::JPY);
+        let err = Cents::try_from(&yen).unwrap_err();
+        assert_eq!(err, DecimalError::NotCents("JPY"));
+        assert_eq!(err.to_string(), "JPY amounts aren't counted in cents");
+        assert_eq!(err.code(), "E010");
+        let owed = Money::from_minor(-100, iso::USD);
+        assert_eq!(
+            Cents::try_from(&owed),
+            Err(DecimalError::Negative(dec("-1.00")))
+        );
+    }
+}
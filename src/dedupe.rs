@@ -0,0 +1,110 @@
+//! Duplicate transaction detection, for POS exports that get submitted
+//! twice. Lines carry the POS's transaction ID as `id=T1001`; a line whose
+//! ID was already rung up, earlier in the run or in an earlier run, is a
+//! duplicate.
+//!
+//! The IDs seen so far persist between runs as a plain text file, one ID
+//! per line (see [`SeenIds::from_lines`] and [`SeenIds::to_lines`]).
+
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::error::CashRegisterError;
+
+/// Every transaction ID seen so far, with the input line it was first seen
+/// on (`None` for IDs from an earlier run).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SeenIds {
+    first_line: BTreeMap<String, Option<usize>>,
+}
+
+impl SeenIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The IDs from a seen-set file: one per line, blank lines ignored.
+    pub fn from_lines(text: &str) -> Self {
+        let first_line = text
+            .lines()
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| (id.to_string(), None))
+            .collect();
+        Self { first_line }
+    }
+
+    /// Check `id` from input line `line`, failing if it was already seen.
+    pub fn check(&self, id: &str, line: usize) -> Result<(), CashRegisterError> {
+        match self.first_line.get(id) {
+            Some(&first_line) => Err(CashRegisterError::DuplicateTransaction {
+                line,
+                id: id.to_string(),
+                first_line,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Mark `id` as seen on input line `line`. Record a line only once it
+    /// has been rung up, so one that failed can be fixed and resubmitted.
+    pub fn record(&mut self, id: &str, line: usize) {
+        self.first_line.entry(id.to_string()).or_insert(Some(line));
+    }
+
+    /// Every ID seen, sorted, one per line, for the seen-set file.
+    pub fn to_lines(&self) -> String {
+        let ids: Vec<&str> = self.first_line.keys().map(String::as_str).collect();
+        ids.join("\n")
+    }
+
+    pub fn len(&self) -> usize {
+        self.first_line.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.first_line.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_ids_seen_earlier_in_the_run() {
+        let mut seen = SeenIds::new();
+        assert!(seen.check("T1", 1).is_ok());
+        seen.record("T1", 1);
+        seen.record("T2", 2);
+        seen.record("T1", 4);
+        match seen.check("T1", 5) {
+            Err(CashRegisterError::DuplicateTransaction {
+                line,
+                id,
+                first_line,
+            }) => {
+                assert_eq!((line, id.as_str(), first_line), (5, "T1", Some(1)));
+            }
+            other => panic!("expected DuplicateTransaction, got {other:?}"),
+        }
+        assert_eq!(seen.len(), 2);
+    }
+
+    #[test]
+    fn round_trips_through_a_seen_set_file() {
+        let mut seen = SeenIds::from_lines("T2\n\n  T1  \n");
+        let err = seen.check("T1", 3).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 3: duplicate transaction id \"T1\" (seen in an earlier run)"
+        );
+        seen.record("T3", 4);
+        assert_eq!(seen.to_lines(), "T1\nT2\nT3");
+        assert_eq!(SeenIds::from_lines(&seen.to_lines()).len(), 3);
+        assert!(SeenIds::new().is_empty());
+    }
+}
@@ -1,9 +1,32 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
 use thiserror::Error;
 
+use crate::currency::{Currency, Denomination};
+use crate::money::Cents;
+
+/// Everything that can go wrong processing a batch.
+///
+/// Each variant has a stable machine-readable code (see `code`) that never
+/// changes meaning or gets reused, so structured output and scripts can
+/// match on it instead of on the message text.
 #[derive(Debug, Error)]
 pub enum CashRegisterError {
-    #[error("line {line}: invalid dollar amount \"{input}\"")]
-    InvalidAmount { line: usize, input: String },
+    #[error("line {line}: invalid dollar amount \"{input}\" ({reason})")]
+    InvalidAmount {
+        line: usize,
+        input: String,
+        reason: String,
+    },
+
+    /// A transaction amount with a leading minus, which usually means
+    /// someone tried to enter a refund some other way.
+    #[error("line {line}: negative amount \"{input}\" (a refund is written REFUND,amount or with a negative owed amount and 0 paid)")]
+    NegativeAmount { line: usize, input: String },
 
     #[error("line {line}: paid ({paid}) is less than owed ({owed})")]
     Underpayment {
@@ -15,6 +38,597 @@ pub enum CashRegisterError {
     #[error("line {line}: {detail}")]
     MalformedLine { line: usize, detail: String },
 
+    #[error("line {line}: \"{input}\" is not a {currency} denomination")]
+    UnknownDenomination {
+        line: usize,
+        input: String,
+        currency: String,
+    },
+
+    #[error(
+        "line {line}: till has {available} {}, needed {needed}",
+        if *available == 1 { &denomination.singular } else { &denomination.plural }
+    )]
+    TillShortage {
+        line: usize,
+        denomination: Denomination,
+        needed: u32,
+        available: u32,
+    },
+
+    #[error("line {line}: tender refused: {reason}")]
+    RefusedTender { line: usize, reason: String },
+
+    #[error("invalid till state: {0}")]
+    InvalidState(String),
+
+    #[cfg(feature = "std")]
     #[error("{0}")]
     Io(#[from] std::io::Error),
+
+    #[error(
+        "{amount} can't be made exactly from {currency} denominations ({remainder} left over)"
+    )]
+    InfeasibleAmount {
+        amount: String,
+        remainder: String,
+        currency: String,
+    },
+
+    /// A currency code that isn't registered. `suggestions` are the
+    /// closest registered codes, if any are close enough to be a typo.
+    #[error("Unknown currency: {name}. {}", currency_hint(.suggestions, .supported))]
+    UnsupportedCurrency {
+        name: String,
+        supported: String,
+        suggestions: Vec<String>,
+    },
+
+    #[error("{amount} is over the {max} limit for one transaction")]
+    AmountTooLarge { amount: String, max: String },
+
+    #[error("line {line}: till can't hold {adding} more {denomination} on top of {on_hand}")]
+    TillOverflow {
+        line: usize,
+        denomination: String,
+        on_hand: u32,
+        adding: u32,
+    },
+
+    /// A line whose `id=` was already rung up, on `first_line` of this run
+    /// or in an earlier one.
+    #[error("line {line}: duplicate transaction id \"{id}\" ({})", seen_on(.first_line))]
+    DuplicateTransaction {
+        line: usize,
+        id: String,
+        first_line: Option<usize>,
+    },
+
+    /// An owed or paid amount over a configured sanity limit, usually a
+    /// typo.
+    #[error("line {line}: {amount} is over the {max} limit per amount")]
+    AmountOverLimit {
+        line: usize,
+        amount: String,
+        max: String,
+    },
+
+    /// A `VOID` of an ID no transaction in this run has, or of one that was
+    /// already voided, on `voided_on`.
+    #[error("line {line}: nothing to void with id \"{id}\"{}", already_voided(.voided_on))]
+    NothingToVoid {
+        line: usize,
+        id: String,
+        voided_on: Option<usize>,
+    },
+
+    /// A `--paranoid` self-check failed: the library produced change or a
+    /// drawer that breaks its own invariants. Always a bug.
+    #[error("line {line}: internal error: {detail}")]
+    InvariantViolated { line: usize, detail: String },
+
+    /// A [`ChangePolicy`](crate::policy::ChangePolicy) that can't be used,
+    /// like one withholding a piece its currency doesn't have.
+    #[error("invalid change policy: {0}")]
+    InvalidPolicy(String),
+}
+
+fn currency_hint(suggestions: &[String], supported: &str) -> String {
+    match suggestions {
+        [] => format!("Supported: {supported}"),
+        [only] => format!("Did you mean {only}?"),
+        [init @ .., last] => format!("Did you mean {} or {last}?", init.join(", ")),
+    }
+}
+
+fn already_voided(voided_on: &Option<usize>) -> String {
+    match voided_on {
+        Some(line) => format!(" (already voided on line {line})"),
+        None => String::new(),
+    }
+}
+
+fn seen_on(first_line: &Option<usize>) -> String {
+    match first_line {
+        Some(line) => format!("first seen on line {line}"),
+        None => "seen in an earlier run".into(),
+    }
+}
+
+/// Why [`Transaction::new`](crate::parse::Transaction::new) refused a pair
+/// of amounts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum TransactionError {
+    #[error("paid ({paid}) is less than owed ({owed})")]
+    Underpayment { owed: Cents, paid: Cents },
+
+    #[error("{0} is over the {max} limit for one transaction", max = Cents::MAX_AMOUNT)]
+    TooLarge(Cents),
+}
+
+impl TransactionError {
+    /// The code of the matching [`CashRegisterError`] variant.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Underpayment { .. } => "E002",
+            Self::TooLarge(_) => "E011",
+        }
+    }
+}
+
+/// Why a [`ChangeStrategy`](crate::strategy::ChangeStrategy) couldn't make
+/// change exactly: the currency has no piece small enough for the last
+/// `remainder` of `amount` (say, 3 cents in a currency whose smallest coin
+/// is 5 cents).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("{amount} can't be made exactly ({remainder} left over)")]
+pub struct InfeasibleChange {
+    pub amount: Cents,
+    pub remainder: Cents,
+}
+
+impl InfeasibleChange {
+    /// The code of the matching [`CashRegisterError`] variant.
+    pub fn code(&self) -> &'static str {
+        "E009"
+    }
+
+    /// The matching [`CashRegisterError`], with amounts in `currency`.
+    pub fn in_currency(self, currency: &Currency) -> CashRegisterError {
+        CashRegisterError::InfeasibleAmount {
+            amount: self.amount.with_symbol(&currency.symbol),
+            remainder: self.remainder.with_symbol(&currency.symbol),
+            currency: currency.name.to_string(),
+        }
+    }
+}
+
+/// Why [`parse_breakdown`](crate::parse::parse_breakdown) couldn't read a
+/// breakdown back.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum BreakdownError {
+    #[error("{0}")]
+    Malformed(String),
+
+    #[error("\"{name}\" is not a {currency} denomination")]
+    UnknownDenomination { name: String, currency: String },
+}
+
+impl BreakdownError {
+    /// The code of the matching [`CashRegisterError`] variant.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Malformed(_) => "E003",
+            Self::UnknownDenomination { .. } => "E004",
+        }
+    }
+}
+
+/// Why a `rust_decimal::Decimal` isn't an amount of [`Cents`].
+#[cfg(feature = "decimal")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum DecimalError {
+    #[error("{0} is negative")]
+    Negative(rust_decimal::Decimal),
+
+    #[error("{0} has a fraction of a cent")]
+    FractionalCent(rust_decimal::Decimal),
+
+    #[error("{0} is over the {max} limit for one transaction", max = Cents::MAX_AMOUNT)]
+    TooLarge(rust_decimal::Decimal),
+
+    /// A `rusty_money::Money` in a currency whose minor unit isn't a
+    /// hundredth, by its code.
+    #[cfg(feature = "rusty_money")]
+    #[error("{0} amounts aren't counted in cents")]
+    NotCents(&'static str),
+}
+
+#[cfg(feature = "decimal")]
+impl DecimalError {
+    /// The code of the matching [`CashRegisterError`] variant.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Negative(_) | Self::FractionalCent(_) => "E001",
+            Self::TooLarge(_) => "E011",
+            #[cfg(feature = "rusty_money")]
+            Self::NotCents(_) => "E010",
+        }
+    }
+}
+
+/// The kinds of per-line error a run can be told to handle differently
+/// (the CLI's `--on-error`). Errors that aren't about one line, such as I/O
+/// or a bad till file, have no category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    /// A line that isn't `owed,paid` (E003), names a piece the currency
+    /// doesn't have (E004), or voids an ID there's nothing to void for
+    /// (E017).
+    Malformed,
+    /// An amount that isn't a valid, non-negative, in-limit amount (E001,
+    /// E013, E015).
+    Invalid,
+    /// Paid less than owed (E002).
+    Underpayment,
+    /// The drawer couldn't make the change, or can't hold the tender (E005,
+    /// E012).
+    Till,
+    /// Tender refused by `--max-bill` or `--max-coins` (E006).
+    Refused,
+    /// A transaction id that was already rung up (E014).
+    Duplicate,
+}
+
+impl ErrorCategory {
+    pub const ALL: [Self; 6] = [
+        Self::Malformed,
+        Self::Invalid,
+        Self::Underpayment,
+        Self::Till,
+        Self::Refused,
+        Self::Duplicate,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Malformed => "malformed",
+            Self::Invalid => "invalid",
+            Self::Underpayment => "underpayment",
+            Self::Till => "till",
+            Self::Refused => "refused",
+            Self::Duplicate => "duplicate",
+        }
+    }
+}
+
+impl core::str::FromStr for ErrorCategory {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "malformed" => Ok(Self::Malformed),
+            "invalid" => Ok(Self::Invalid),
+            "underpayment" => Ok(Self::Underpayment),
+            "till" => Ok(Self::Till),
+            "refused" => Ok(Self::Refused),
+            "duplicate" => Ok(Self::Duplicate),
+            other => Err(format!(
+                "unknown error category \"{other}\" (expected malformed, invalid, underpayment, till, refused, or duplicate)"
+            )),
+        }
+    }
+}
+
+impl CashRegisterError {
+    /// Stable code identifying the kind of error: `E001`, `E002`, ...
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidAmount { .. } => "E001",
+            Self::Underpayment { .. } => "E002",
+            Self::MalformedLine { .. } => "E003",
+            Self::UnknownDenomination { .. } => "E004",
+            Self::TillShortage { .. } => "E005",
+            Self::RefusedTender { .. } => "E006",
+            Self::InvalidState(_) => "E007",
+            #[cfg(feature = "std")]
+            Self::Io(_) => "E008",
+            Self::InfeasibleAmount { .. } => "E009",
+            Self::UnsupportedCurrency { .. } => "E010",
+            Self::AmountTooLarge { .. } => "E011",
+            Self::TillOverflow { .. } => "E012",
+            Self::NegativeAmount { .. } => "E013",
+            Self::DuplicateTransaction { .. } => "E014",
+            Self::AmountOverLimit { .. } => "E015",
+            Self::InvariantViolated { .. } => "E016",
+            Self::NothingToVoid { .. } => "E017",
+            Self::InvalidPolicy(_) => "E018",
+        }
+    }
+
+    /// Process exit code the CLI uses when a run ends on this error:
+    /// 1 for bad setup (nothing was processed), 74 for I/O, 70 for an
+    /// internal error, 3 for refused tender, 4 for underpayment, 5 for a till
+    /// shortage, and 2 for any other per-line failure, such as a malformed
+    /// line.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::InvalidState(_) | Self::UnsupportedCurrency { .. } | Self::InvalidPolicy(_) => 1,
+            #[cfg(feature = "std")]
+            Self::Io(_) => 74,
+            Self::InvariantViolated { .. } => 70,
+            Self::RefusedTender { .. } => 3,
+            Self::Underpayment { .. } => 4,
+            Self::TillShortage { .. } => 5,
+            _ => 2,
+        }
+    }
+
+    /// Which kind of per-line error this is, or `None` for one that
+    /// isn't about a line's contents (I/O, setup, an internal error).
+    pub fn category(&self) -> Option<ErrorCategory> {
+        match self {
+            Self::MalformedLine { .. }
+            | Self::UnknownDenomination { .. }
+            | Self::NothingToVoid { .. } => Some(ErrorCategory::Malformed),
+            Self::InvalidAmount { .. }
+            | Self::NegativeAmount { .. }
+            | Self::AmountOverLimit { .. } => Some(ErrorCategory::Invalid),
+            Self::Underpayment { .. } => Some(ErrorCategory::Underpayment),
+            Self::TillShortage { .. } | Self::TillOverflow { .. } => Some(ErrorCategory::Till),
+            Self::RefusedTender { .. } => Some(ErrorCategory::Refused),
+            Self::DuplicateTransaction { .. } => Some(ErrorCategory::Duplicate),
+            _ => None,
+        }
+    }
+
+    /// The input line the error was found on, if it belongs to one.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Self::InvalidAmount { line, .. }
+            | Self::NegativeAmount { line, .. }
+            | Self::Underpayment { line, .. }
+            | Self::MalformedLine { line, .. }
+            | Self::UnknownDenomination { line, .. }
+            | Self::TillShortage { line, .. }
+            | Self::TillOverflow { line, .. }
+            | Self::RefusedTender { line, .. }
+            | Self::DuplicateTransaction { line, .. }
+            | Self::AmountOverLimit { line, .. }
+            | Self::NothingToVoid { line, .. }
+            | Self::InvariantViolated { line, .. } => Some(*line),
+            #[cfg(feature = "std")]
+            Self::Io(_) => None,
+            Self::InvalidState(_)
+            | Self::InfeasibleAmount { .. }
+            | Self::UnsupportedCurrency { .. }
+            | Self::AmountTooLarge { .. }
+            | Self::InvalidPolicy(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn codes_are_stable_and_unique() {
+        let errors = [
+            CashRegisterError::InvalidAmount {
+                line: 1,
+                input: "x".to_string(),
+                reason: "invalid dollar part".to_string(),
+            },
+            CashRegisterError::Underpayment {
+                line: 1,
+                owed: "2.00".to_string(),
+                paid: "1.00".to_string(),
+            },
+            CashRegisterError::MalformedLine {
+                line: 1,
+                detail: String::new(),
+            },
+            CashRegisterError::UnknownDenomination {
+                line: 1,
+                input: "0.03".to_string(),
+                currency: "USD".to_string(),
+            },
+            CashRegisterError::TillShortage {
+                line: 1,
+                denomination: crate::currency::USD.denominations[2].clone(),
+                needed: 1,
+                available: 0,
+            },
+            CashRegisterError::RefusedTender {
+                line: 1,
+                reason: String::new(),
+            },
+            CashRegisterError::InvalidState(String::new()),
+            CashRegisterError::Io(std::io::Error::other("disk")),
+            CashRegisterError::InfeasibleAmount {
+                amount: "0.03".to_string(),
+                remainder: "0.03".to_string(),
+                currency: "CHF".to_string(),
+            },
+            CashRegisterError::UnsupportedCurrency {
+                name: "GBP".to_string(),
+                supported: "USD, EUR".to_string(),
+                suggestions: Vec::new(),
+            },
+            CashRegisterError::AmountTooLarge {
+                amount: "$50000000.00".to_string(),
+                max: "$42949672.95".to_string(),
+            },
+            CashRegisterError::TillOverflow {
+                line: 1,
+                denomination: "quarters".to_string(),
+                on_hand: u32::MAX,
+                adding: 1,
+            },
+            CashRegisterError::NegativeAmount {
+                line: 1,
+                input: "-1.50".to_string(),
+            },
+            CashRegisterError::DuplicateTransaction {
+                line: 1,
+                id: "T1".to_string(),
+                first_line: None,
+            },
+            CashRegisterError::AmountOverLimit {
+                line: 1,
+                amount: "100000.00".to_string(),
+                max: "1000.00".to_string(),
+            },
+            CashRegisterError::InvariantViolated {
+                line: 1,
+                detail: String::new(),
+            },
+            CashRegisterError::NothingToVoid {
+                line: 1,
+                id: "T1".to_string(),
+                voided_on: None,
+            },
+            CashRegisterError::InvalidPolicy(String::new()),
+        ];
+        let codes: Vec<&str> = errors.iter().map(CashRegisterError::code).collect();
+        assert_eq!(
+            codes,
+            [
+                "E001", "E002", "E003", "E004", "E005", "E006", "E007", "E008", "E009", "E010",
+                "E011", "E012", "E013", "E014", "E015", "E016", "E017", "E018",
+            ]
+        );
+    }
+
+    #[test]
+    fn exit_codes_and_lines() {
+        let refused = CashRegisterError::RefusedTender {
+            line: 4,
+            reason: String::new(),
+        };
+        assert_eq!(refused.exit_code(), 3);
+        assert_eq!(refused.line(), Some(4));
+
+        let state = CashRegisterError::InvalidState("bad".to_string());
+        assert_eq!(state.exit_code(), 1);
+        assert_eq!(state.line(), None);
+
+        let malformed = CashRegisterError::MalformedLine {
+            line: 2,
+            detail: String::new(),
+        };
+        assert_eq!(malformed.exit_code(), 2);
+
+        let underpaid = CashRegisterError::Underpayment {
+            line: 3,
+            owed: "5.00".to_string(),
+            paid: "3.00".to_string(),
+        };
+        assert_eq!(underpaid.exit_code(), 4);
+        let short = CashRegisterError::TillShortage {
+            line: 3,
+            denomination: crate::currency::USD.denominations[2].clone(),
+            needed: 1,
+            available: 0,
+        };
+        assert_eq!(short.exit_code(), 5);
+        assert_eq!(short.to_string(), "line 3: till has 0 dimes, needed 1");
+        let one_left = CashRegisterError::TillShortage {
+            line: 3,
+            denomination: crate::currency::USD.denominations[2].clone(),
+            needed: 2,
+            available: 1,
+        };
+        assert_eq!(one_left.to_string(), "line 3: till has 1 dime, needed 2");
+        #[cfg(feature = "std")]
+        assert_eq!(
+            CashRegisterError::Io(std::io::Error::other("disk")).exit_code(),
+            74
+        );
+    }
+
+    #[test]
+    fn categories() {
+        let malformed = CashRegisterError::MalformedLine {
+            line: 2,
+            detail: String::new(),
+        };
+        assert_eq!(malformed.category(), Some(ErrorCategory::Malformed));
+        let negative = CashRegisterError::NegativeAmount {
+            line: 1,
+            input: "-1.50".to_string(),
+        };
+        assert_eq!(negative.category(), Some(ErrorCategory::Invalid));
+        let state = CashRegisterError::InvalidState("bad".to_string());
+        assert_eq!(state.category(), None);
+
+        for category in ErrorCategory::ALL {
+            assert_eq!(category.as_str().parse(), Ok(category));
+        }
+        assert_eq!("Till".parse(), Ok(ErrorCategory::Till));
+        assert!("shortage".parse::<ErrorCategory>().is_err());
+    }
+
+    #[test]
+    fn duplicate_transaction_messages() {
+        let again = CashRegisterError::DuplicateTransaction {
+            line: 9,
+            id: "T1001".to_string(),
+            first_line: Some(2),
+        };
+        assert_eq!(
+            again.to_string(),
+            "line 9: duplicate transaction id \"T1001\" (first seen on line 2)"
+        );
+        let earlier = CashRegisterError::DuplicateTransaction {
+            line: 1,
+            id: "T1001".to_string(),
+            first_line: None,
+        };
+        assert_eq!(
+            earlier.to_string(),
+            "line 1: duplicate transaction id \"T1001\" (seen in an earlier run)"
+        );
+        assert_eq!(earlier.exit_code(), 2);
+    }
+
+    #[test]
+    fn transaction_error_messages() {
+        let short = TransactionError::Underpayment {
+            owed: Cents(300),
+            paid: Cents(212),
+        };
+        assert_eq!(short.to_string(), "paid (2.12) is less than owed (3.00)");
+        assert_eq!(
+            TransactionError::TooLarge(Cents(u64::MAX)).to_string(),
+            "184467440737095516.15 is over the 42949672.95 limit for one transaction"
+        );
+        assert_eq!(short.code(), "E002");
+        assert_eq!(TransactionError::TooLarge(Cents(0)).code(), "E011");
+    }
+
+    #[test]
+    fn unsupported_currency_message() {
+        let err = CashRegisterError::UnsupportedCurrency {
+            name: "GBP".to_string(),
+            supported: "USD, EUR".to_string(),
+            suggestions: Vec::new(),
+        };
+        assert_eq!(
+            err.to_string(),
+            "Unknown currency: GBP. Supported: USD, EUR"
+        );
+        let err = CashRegisterError::UnsupportedCurrency {
+            name: "EU".to_string(),
+            supported: "USD, EUR, EUX".to_string(),
+            suggestions: vec!["EUR".to_string(), "EUX".to_string()],
+        };
+        assert_eq!(
+            err.to_string(),
+            "Unknown currency: EU. Did you mean EUR or EUX?"
+        );
+    }
 }
@@ -0,0 +1,143 @@
+//! Receipts as ESC/POS bytes, the command set most thermal receipt printers
+//! speak, so change can be printed straight to a printer's device file.
+//!
+//! Each receipt starts by resetting the printer and ends with a feed and a
+//! partial cut. Amounts use the currency's ASCII symbol (`EUR 1.50`), since
+//! printers decode text through a code page rather than UTF-8.
+
+use alloc::{format, vec::Vec};
+
+use crate::currency::Currency;
+use crate::money::Cents;
+use crate::parse::Transaction;
+
+/// `ESC @`: reset the printer to its defaults.
+pub const INIT: &[u8] = b"\x1b@";
+/// `ESC E 1`: start bold text.
+pub const BOLD_ON: &[u8] = b"\x1bE\x01";
+/// `ESC E 0`: end bold text.
+pub const BOLD_OFF: &[u8] = b"\x1bE\x00";
+/// `GS V 66 0`: feed to the cutter and cut, leaving a tab.
+pub const CUT: &[u8] = b"\x1dVB\x00";
+
+/// Characters per line on a 58 mm roll, the narrowest common paper.
+pub const RECEIPT_WIDTH: usize = 32;
+
+/// One sale's receipt: owed and paid, the change in bold, and the pieces
+/// handed back, one per line. Change the pieces don't cover (a till
+/// shortfall) is printed as still owed. Change `donated` to charity by
+/// `--roundup` gets a row of its own, and the change is what's left.
+///
+/// ```text
+/// Owed                       $2.12
+/// Paid                       $3.00
+/// Change                     $0.88   (bold)
+///   3 quarters               $0.75
+///   1 dime                   $0.10
+///   3 pennies                $0.03
+/// ```
+pub fn format_receipt(
+    transaction: &Transaction,
+    breakdown: &[(usize, u32)],
+    donated: Cents,
+    currency: &Currency,
+) -> Vec<u8> {
+    let currency = currency.with_ascii_symbol();
+    let amount = |cents: Cents| cents.with_symbol(&currency.symbol);
+    let mut out = Vec::from(INIT);
+    push_row(&mut out, "Owed", &amount(transaction.owed_cents()));
+    push_row(&mut out, "Paid", &amount(transaction.paid_cents()));
+    if !donated.is_zero() {
+        push_row(&mut out, "Donated", &amount(donated));
+    }
+    let change = transaction.change_cents().checked_sub(donated);
+    let change = change.unwrap_or_default();
+    out.extend_from_slice(BOLD_ON);
+    push_row(&mut out, "Change", &amount(change));
+    out.extend_from_slice(BOLD_OFF);
+
+    let mut given = Cents(0);
+    for (denomination, count) in currency.pieces(breakdown) {
+        let value = Cents::from(denomination.cents) * count;
+        given += value;
+        let name = if count == 1 {
+            &denomination.singular
+        } else {
+            &denomination.plural
+        };
+        push_row(&mut out, &format!("  {count} {name}"), &amount(value));
+    }
+    if let Some(owed) = change.checked_sub(given).filter(|&owed| owed > Cents(0)) {
+        push_row(&mut out, "Still owed", &amount(owed));
+    }
+    out.extend_from_slice(b"\n\n\n");
+    out.extend_from_slice(CUT);
+    out
+}
+
+/// A line with `label` on the left and `amount` on the right, at least one
+/// space apart.
+fn push_row(out: &mut Vec<u8>, label: &str, amount: &str) {
+    let gap = RECEIPT_WIDTH
+        .saturating_sub(label.chars().count() + amount.chars().count())
+        .max(1);
+    let line = format!("{label}{:gap$}{amount}\n", "");
+    out.extend_from_slice(line.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::{EUR, USD};
+
+    #[test]
+    fn receipt_itemizes_change_and_cuts() {
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        let breakdown = [(1, 3), (2, 1), (4, 3)];
+        let receipt = format_receipt(&tx, &breakdown, Cents::ZERO, &USD);
+        let mut expected = Vec::from(INIT);
+        expected.extend_from_slice(b"Owed                       $2.12\n");
+        expected.extend_from_slice(b"Paid                       $3.00\n");
+        expected.extend_from_slice(BOLD_ON);
+        expected.extend_from_slice(b"Change                     $0.88\n");
+        expected.extend_from_slice(BOLD_OFF);
+        expected.extend_from_slice(b"  3 quarters               $0.75\n");
+        expected.extend_from_slice(b"  1 dime                   $0.10\n");
+        expected.extend_from_slice(b"  3 pennies                $0.03\n");
+        expected.extend_from_slice(b"\n\n\n");
+        expected.extend_from_slice(CUT);
+        assert_eq!(receipt, expected);
+    }
+
+    #[test]
+    fn receipt_is_ascii_and_notes_a_shortfall() {
+        let tx = Transaction::new(Cents(150), Cents(200)).unwrap();
+        let receipt = format_receipt(&tx, &[], Cents::ZERO, &EUR);
+        assert!(receipt.is_ascii());
+        let text = alloc::string::String::from_utf8(receipt).unwrap();
+        assert!(
+            text.contains("Change                  EUR 0.50\n"),
+            "{text}"
+        );
+        assert!(
+            text.contains("Still owed              EUR 0.50\n"),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn receipt_lists_a_donation() {
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        let receipt = format_receipt(&tx, &[(1, 3)], Cents(13), &USD);
+        let text = alloc::string::String::from_utf8(receipt).unwrap();
+        assert!(
+            text.contains("Donated                    $0.13\n"),
+            "{text}"
+        );
+        assert!(
+            text.contains("Change                     $0.75\n"),
+            "{text}"
+        );
+        assert!(!text.contains("Still owed"), "{text}");
+    }
+}
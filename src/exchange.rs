@@ -0,0 +1,221 @@
+//! Exchange rates for tender in a currency other than the register's, like
+//! euros handed over for a dollar-priced sale.
+//!
+//! The rounding policy: a tendered amount is converted to the register's
+//! currency and rounded to a whole cent once, with the run's
+//! [`RoundingMode`] (half-up unless configured otherwise). The change is
+//! worked out in the register's currency from that rounded amount; given
+//! in the tendered currency instead, it is converted back and rounded the
+//! same way.
+
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+use core::fmt;
+use core::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::money::Cents;
+use crate::rounding::RoundingMode;
+
+/// How much of the register's currency one unit of the tendered currency
+/// buys, to six decimal places: `1.08` for euros at a dollar register.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ExchangeRate {
+    millionths: u64,
+}
+
+impl ExchangeRate {
+    /// Decimal places a rate may have.
+    pub const PLACES: usize = 6;
+    const SCALE: u128 = 1_000_000;
+
+    /// An amount in the tendered currency, in the register's, or `None` if
+    /// that's more than `Cents::MAX_AMOUNT`.
+    pub fn to_register(self, tendered: Cents, mode: RoundingMode) -> Option<Cents> {
+        let cents = mode.round_div(
+            u128::from(tendered.0) * u128::from(self.millionths),
+            Self::SCALE,
+        );
+        checked_cents(cents)
+    }
+
+    /// An amount in the register's currency, in the tendered one, or `None`
+    /// if that's more than `Cents::MAX_AMOUNT`.
+    pub fn to_tendered(self, register: Cents, mode: RoundingMode) -> Option<Cents> {
+        let cents = mode.round_div(
+            u128::from(register.0) * Self::SCALE,
+            u128::from(self.millionths),
+        );
+        checked_cents(cents)
+    }
+}
+
+fn checked_cents(cents: u128) -> Option<Cents> {
+    u64::try_from(cents)
+        .ok()
+        .map(Cents)
+        .filter(|&cents| cents <= Cents::MAX_AMOUNT)
+}
+
+impl FromStr for ExchangeRate {
+    type Err = String;
+
+    /// A positive decimal with at most six places: `1.08`, `0.925`, `150`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || format!("invalid exchange rate \"{s}\"");
+        let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || !digits(whole) || !digits(fraction) {
+            return Err(invalid());
+        }
+        if fraction.len() > Self::PLACES {
+            return Err(format!(
+                "exchange rate \"{s}\" has more than {} decimal places",
+                Self::PLACES
+            ));
+        }
+        let whole: u64 = match whole {
+            "" => 0,
+            whole => whole.parse().map_err(|_| invalid())?,
+        };
+        let fraction: u64 = format!("{fraction:0<6}").parse().map_err(|_| invalid())?;
+        let millionths = whole
+            .checked_mul(Self::SCALE as u64)
+            .and_then(|whole| whole.checked_add(fraction))
+            .ok_or_else(invalid)?;
+        if millionths == 0 {
+            return Err("the exchange rate must be more than zero".to_string());
+        }
+        Ok(Self { millionths })
+    }
+}
+
+impl TryFrom<String> for ExchangeRate {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<ExchangeRate> for String {
+    fn from(rate: ExchangeRate) -> Self {
+        rate.to_string()
+    }
+}
+
+/// Shortest form: `1.08`, `150`.
+impl fmt::Display for ExchangeRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scale = Self::SCALE as u64;
+        let (whole, fraction) = (self.millionths / scale, self.millionths % scale);
+        if fraction == 0 {
+            return write!(f, "{whole}");
+        }
+        let fraction = format!("{fraction:06}");
+        write!(f, "{whole}.{}", fraction.trim_end_matches('0'))
+    }
+}
+
+/// Which currency change is given in when the customer paid in another.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeIn {
+    /// The register's own currency, from its drawer.
+    #[default]
+    Register,
+    /// The currency the customer paid in: the change is converted back at
+    /// the line's rate.
+    Tendered,
+}
+
+impl ChangeIn {
+    /// Every choice, in the order `--help` lists them.
+    pub const ALL: [Self; 2] = [Self::Register, Self::Tendered];
+
+    /// The name it's parsed from and serialized as.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Register => "register",
+            Self::Tendered => "tendered",
+        }
+    }
+}
+
+impl FromStr for ChangeIn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "register" => Ok(Self::Register),
+            "tendered" => Ok(Self::Tendered),
+            other => Err(format!(
+                "unknown change currency \"{other}\" (expected register or tendered)"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_parse_and_print_shortest() {
+        let rate: ExchangeRate = "1.08".parse().unwrap();
+        assert_eq!(rate.to_string(), "1.08");
+        assert_eq!("150".parse::<ExchangeRate>().unwrap().to_string(), "150");
+        assert_eq!(".925".parse::<ExchangeRate>().unwrap().to_string(), "0.925");
+        assert!("0".parse::<ExchangeRate>().is_err());
+        assert!("1.0000001".parse::<ExchangeRate>().is_err());
+        assert!("-1.08".parse::<ExchangeRate>().is_err());
+        assert!("abc".parse::<ExchangeRate>().is_err());
+        let json = serde_json::to_string(&rate).unwrap();
+        assert_eq!(json, "\"1.08\"");
+        assert_eq!(serde_json::from_str::<ExchangeRate>(&json).unwrap(), rate);
+    }
+
+    #[test]
+    fn conversions_round_by_mode() {
+        let rate: ExchangeRate = "1.0825".parse().unwrap();
+        // 2.00 EUR is 2.165 USD.
+        assert_eq!(
+            rate.to_register(Cents(200), RoundingMode::HalfUp),
+            Some(Cents(217))
+        );
+        assert_eq!(
+            rate.to_register(Cents(200), RoundingMode::HalfEven),
+            Some(Cents(216))
+        );
+        // 0.04 USD is 0.03695 EUR.
+        assert_eq!(
+            rate.to_tendered(Cents(4), RoundingMode::HalfUp),
+            Some(Cents(4))
+        );
+        assert_eq!(
+            rate.to_tendered(Cents(4), RoundingMode::TowardZero),
+            Some(Cents(3))
+        );
+
+        let rate: ExchangeRate = "1000".parse().unwrap();
+        assert_eq!(
+            rate.to_register(Cents::MAX_AMOUNT, RoundingMode::HalfUp),
+            None
+        );
+    }
+
+    #[test]
+    fn change_in_names_round_trip() {
+        for choice in ChangeIn::ALL {
+            assert_eq!(choice.as_str().parse(), Ok(choice));
+            let json = serde_json::to_string(&choice).unwrap();
+            assert_eq!(json, format!("\"{}\"", choice.as_str()));
+        }
+        assert!("owed".parse::<ChangeIn>().is_err());
+    }
+}
@@ -0,0 +1,294 @@
+//! A day's cash sales as QIF or OFX, the formats most accounting packages
+//! import, so the drawer's takings can be booked without retyping them.
+//!
+//! Each sale is an entry for the cash that went into the drawer, split into
+//! the cash paid and the change handed back. Non-cash parts of a split
+//! payment never touch the drawer and are left out. A refund is an entry for
+//! the cash paid out.
+
+use alloc::{format, string::String, vec::Vec};
+
+use crate::currency::Currency;
+use crate::format::format_breakdown;
+use crate::money::Cents;
+use crate::parse::{SplitTender, Transaction};
+use crate::strategy::Breakdown;
+
+/// QIF category for the cash paid into the drawer.
+pub const QIF_SALES: &str = "Sales";
+/// QIF category for the change paid back out.
+pub const QIF_CHANGE: &str = "Change";
+/// QIF category for refunds paid out.
+pub const QIF_REFUNDS: &str = "Refunds";
+
+/// One sale to export: its input line, the change made for it, and the
+/// tenders if the payment was split.
+#[derive(Debug, Clone)]
+pub struct ExportSale {
+    pub line: usize,
+    pub transaction: Transaction,
+    pub breakdown: Breakdown,
+    pub split: Option<SplitTender>,
+}
+
+impl ExportSale {
+    /// Cash paid into the drawer.
+    fn cash(&self) -> Cents {
+        self.split
+            .as_ref()
+            .map_or(self.transaction.paid_cents(), SplitTender::cash)
+    }
+}
+
+/// One line to export: a sale, or a refund of `amount` paid out as
+/// `breakdown`.
+#[derive(Debug, Clone)]
+pub enum ExportEntry {
+    Sale(ExportSale),
+    Refund {
+        line: usize,
+        amount: Cents,
+        breakdown: Breakdown,
+    },
+}
+
+/// Sales as a QIF cash account, dated `date` (`YYYY-MM-DD`). Each entry's
+/// total is the cash the drawer kept, split into the cash paid and the
+/// change, with the pieces in the change split's memo:
+///
+/// ```text
+/// !Type:Cash
+/// D10/16/2026
+/// T2.12
+/// PSale
+/// Mline 1
+/// SSales
+/// $3.00
+/// SChange
+/// $-0.88
+/// E3 quarters,1 dime,3 pennies
+/// ^
+/// ```
+///
+/// Exact-change sales have no change split. A refund is an entry for the
+/// cash paid out, under `Refunds`, with the pieces as its memo.
+pub fn format_qif(sales: &[ExportEntry], date: &str, currency: &Currency) -> String {
+    let (year, month, day) = date_parts(date);
+    let mut out = String::from("!Type:Cash\n");
+    for entry in sales {
+        let sale = match entry {
+            ExportEntry::Sale(sale) => sale,
+            ExportEntry::Refund {
+                line,
+                amount,
+                breakdown,
+            } => {
+                out += &format!(
+                    "D{month}/{day}/{year}\nT-{amount}\nPRefund\nMline {line}\n\
+                     S{QIF_REFUNDS}\n$-{amount}\nE{}\n^\n",
+                    format_breakdown(breakdown, currency)
+                );
+                continue;
+            }
+        };
+        let cash = sale.cash();
+        let change = sale.transaction.change_cents();
+        let kept = cash.checked_sub(change).unwrap_or_default();
+        out += &format!(
+            "D{month}/{day}/{year}\nT{kept}\nPSale\nMline {}\n",
+            sale.line
+        );
+        out += &format!("S{QIF_SALES}\n${cash}\n");
+        if !change.is_zero() {
+            out += &format!(
+                "S{QIF_CHANGE}\n$-{change}\nE{}\n",
+                format_breakdown(&sale.breakdown, currency)
+            );
+        }
+        out += "^\n";
+    }
+    out
+}
+
+/// Sales as an OFX 2.2 statement for a cash account, dated `date`
+/// (`YYYY-MM-DD`). OFX has no splits, so each sale is a `CREDIT` for the
+/// cash paid and, unless it was exact, a `DEBIT` for the change, with the
+/// pieces as its memo; a refund is a `DEBIT` for the cash paid out. `FITID`s
+/// are `<date>-<line>-paid`, `<date>-<line>-change`, and
+/// `<date>-<line>-refund`, so importing the same day twice doesn't double
+/// it.
+pub fn format_ofx(sales: &[ExportEntry], date: &str, currency: &Currency) -> String {
+    let (year, month, day) = date_parts(date);
+    let posted = format!("{year}{month}{day}");
+    let mut entries = Vec::new();
+    let mut balance: i64 = 0;
+    for entry in sales {
+        let sale = match entry {
+            ExportEntry::Sale(sale) => sale,
+            ExportEntry::Refund {
+                line,
+                amount,
+                breakdown,
+            } => {
+                balance -= amount.0 as i64;
+                entries.push(ofx_entry(
+                    "DEBIT",
+                    &posted,
+                    &format!("-{amount}"),
+                    &format!("{posted}-{line}-refund"),
+                    &format!("Refund: {}", format_breakdown(breakdown, currency)),
+                ));
+                continue;
+            }
+        };
+        let line = sale.line;
+        let cash = sale.cash();
+        let change = sale.transaction.change_cents();
+        balance += cash.0 as i64 - change.0 as i64;
+        entries.push(ofx_entry(
+            "CREDIT",
+            &posted,
+            &format!("{cash}"),
+            &format!("{posted}-{line}-paid"),
+            &format!("Sale, line {line}"),
+        ));
+        if !change.is_zero() {
+            entries.push(ofx_entry(
+                "DEBIT",
+                &posted,
+                &format!("-{change}"),
+                &format!("{posted}-{line}-change"),
+                &format_breakdown(&sale.breakdown, currency),
+            ));
+        }
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n\
+         <?OFX OFXHEADER=\"200\" VERSION=\"220\" SECURITY=\"NONE\" OLDFILEUID=\"NONE\" NEWFILEUID=\"NONE\"?>\n\
+         <OFX>\n\
+         <SIGNONMSGSRSV1><SONRS><STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>\
+         <DTSERVER>{posted}</DTSERVER><LANGUAGE>ENG</LANGUAGE></SONRS></SIGNONMSGSRSV1>\n\
+         <BANKMSGSRSV1><STMTTRNRS><TRNUID>{posted}</TRNUID>\
+         <STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>\n\
+         <STMTRS><CURDEF>{}</CURDEF>\n\
+         <BANKACCTFROM><BANKID>CASH</BANKID><ACCTID>DRAWER</ACCTID><ACCTTYPE>CHECKING</ACCTTYPE></BANKACCTFROM>\n\
+         <BANKTRANLIST><DTSTART>{posted}</DTSTART><DTEND>{posted}</DTEND>\n\
+         {}\
+         </BANKTRANLIST>\n\
+         <LEDGERBAL><BALAMT>{}{}</BALAMT><DTASOF>{posted}</DTASOF></LEDGERBAL>\n\
+         </STMTRS></STMTTRNRS></BANKMSGSRSV1>\n\
+         </OFX>\n",
+        xml_escape(&currency.name),
+        entries.concat(),
+        if balance < 0 { "-" } else { "" },
+        Cents(balance.unsigned_abs()),
+    )
+}
+
+fn ofx_entry(kind: &str, posted: &str, amount: &str, id: &str, memo: &str) -> String {
+    format!(
+        "<STMTTRN><TRNTYPE>{kind}</TRNTYPE><DTPOSTED>{posted}</DTPOSTED>\
+         <TRNAMT>{amount}</TRNAMT><FITID>{id}</FITID><MEMO>{}</MEMO></STMTTRN>\n",
+        xml_escape(memo)
+    )
+}
+
+/// A `YYYY-MM-DD` date's year, month, and day.
+fn date_parts(date: &str) -> (&str, &str, &str) {
+    let mut parts = date.splitn(3, '-');
+    let mut next = || parts.next().unwrap_or_default();
+    (next(), next(), next())
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::USD;
+    use alloc::vec;
+    use smallvec::smallvec;
+
+    fn sales() -> Vec<ExportEntry> {
+        let sales = vec![
+            ExportSale {
+                line: 1,
+                transaction: Transaction::new(Cents(212), Cents(300)).unwrap(),
+                breakdown: smallvec![(1, 3), (2, 1), (4, 3)],
+                split: None,
+            },
+            ExportSale {
+                line: 2,
+                transaction: Transaction::new(Cents(500), Cents(500)).unwrap(),
+                breakdown: Breakdown::new(),
+                split: None,
+            },
+            ExportSale {
+                line: 3,
+                transaction: Transaction::new(Cents(212), Cents(300)).unwrap(),
+                breakdown: smallvec![(1, 3), (2, 1), (4, 3)],
+                split: Some(SplitTender {
+                    parts: vec![("card".into(), Cents(100)), ("cash".into(), Cents(200))],
+                    returned: Vec::new(),
+                }),
+            },
+        ];
+        sales.into_iter().map(ExportEntry::Sale).collect()
+    }
+
+    #[test]
+    fn qif_splits_cash_paid_and_change() {
+        let qif = format_qif(&sales(), "2026-10-16", &USD);
+        assert_eq!(
+            qif,
+            "!Type:Cash\n\
+             D10/16/2026\nT2.12\nPSale\nMline 1\nSSales\n$3.00\n\
+             SChange\n$-0.88\nE3 quarters,1 dime,3 pennies\n^\n\
+             D10/16/2026\nT5.00\nPSale\nMline 2\nSSales\n$5.00\n^\n\
+             D10/16/2026\nT1.12\nPSale\nMline 3\nSSales\n$2.00\n\
+             SChange\n$-0.88\nE3 quarters,1 dime,3 pennies\n^\n"
+        );
+
+        let refund = [ExportEntry::Refund {
+            line: 4,
+            amount: Cents(200),
+            breakdown: smallvec![(0, 2)],
+        }];
+        assert_eq!(
+            format_qif(&refund, "2026-10-16", &USD),
+            "!Type:Cash\n\
+             D10/16/2026\nT-2.00\nPRefund\nMline 4\nSRefunds\n$-2.00\nE2 dollars\n^\n"
+        );
+    }
+
+    #[test]
+    fn ofx_lists_paid_and_change_entries() {
+        let ofx = format_ofx(&sales(), "2026-10-16", &USD);
+        assert!(ofx.starts_with("<?xml"), "{ofx}");
+        assert!(ofx.contains("<CURDEF>USD</CURDEF>"), "{ofx}");
+        assert_eq!(ofx.matches("<STMTTRN>").count(), 5);
+        assert!(ofx.contains(
+            "<STMTTRN><TRNTYPE>DEBIT</TRNTYPE><DTPOSTED>20261016</DTPOSTED>\
+             <TRNAMT>-0.88</TRNAMT><FITID>20261016-1-change</FITID>\
+             <MEMO>3 quarters,1 dime,3 pennies</MEMO></STMTTRN>\n"
+        ));
+        assert!(ofx.contains("<TRNAMT>2.00</TRNAMT><FITID>20261016-3-paid</FITID>"));
+        assert!(ofx.contains("<BALAMT>8.24</BALAMT>"), "{ofx}");
+
+        let refund = [ExportEntry::Refund {
+            line: 4,
+            amount: Cents(200),
+            breakdown: smallvec![(0, 2)],
+        }];
+        let ofx = format_ofx(&refund, "2026-10-16", &USD);
+        assert!(ofx.contains(
+            "<TRNAMT>-2.00</TRNAMT><FITID>20261016-4-refund</FITID>\
+             <MEMO>Refund: 2 dollars</MEMO>"
+        ));
+        assert!(ofx.contains("<BALAMT>-2.00</BALAMT>"), "{ofx}");
+    }
+}
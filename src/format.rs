@@ -1,204 +1,2764 @@
-use crate::currency::Currency;
-use crate::parse::Transaction;
-use crate::strategy::Breakdown;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt;
 
-/// Format a breakdown into the output string.
+use crate::audit::AuditReport;
+use crate::cash_report::CashReport;
+use crate::currency::{Currency, DenominationKind};
+use crate::loyalty::{Earned, Loyalty};
+use crate::money::Cents;
+use crate::parse::{ForeignTender, Rounded, SplitTender, Transaction};
+use crate::pictogram::{Glyphs, MAX_REPEAT};
+use crate::session::SessionTotals;
+#[cfg(feature = "signing")]
+use crate::signing::{Problem, VerifyReport};
+use crate::simulate::{Quirk, SimulationReport};
+use crate::spoken::{amount_words, breakdown_words};
+use crate::stats::{ChangeStats, CHANGE_BUCKETS};
+use crate::suspicious::{Finding, Pattern};
+use crate::till::{Discrepancy, OrderLine, Skim, Till};
+use crate::totals::Totals;
+use crate::void::Voided;
+use crate::weight::{format_volume, format_weight, Heft};
+
+/// Format a breakdown of `currency` into the output string.
 ///
 /// Examples:
 /// - `[(quarter, 3), (dime, 1), (penny, 3)]` -> `"3 quarters,1 dime,3 pennies"`
 /// - `[]` -> `"no change"`
 ///
 /// Uses singular/plural from the denomination and joins with commas.
-pub fn format_breakdown(breakdown: &Breakdown) -> String {
+pub fn format_breakdown(breakdown: &[(usize, u32)], currency: &Currency) -> String {
+    let mut out = String::new();
+    write_breakdown(&mut out, breakdown, currency).expect("writing to a String can't fail");
+    out
+}
+
+/// Write a breakdown as `format_breakdown` formats it, without allocating,
+/// so a caller can reuse one buffer for every line.
+pub fn write_breakdown(
+    out: &mut impl fmt::Write,
+    breakdown: &[(usize, u32)],
+    currency: &Currency,
+) -> fmt::Result {
+    if breakdown.is_empty() {
+        return out.write_str("no change");
+    }
+
+    for (i, (denom, count)) in currency.pieces(breakdown).enumerate() {
+        let name = if count == 1 {
+            &denom.singular
+        } else {
+            &denom.plural
+        };
+        let sep = if i == 0 { "" } else { "," };
+        write!(out, "{sep}{count} {name}")?;
+    }
+    Ok(())
+}
+
+/// Describe how far dispensed change fell from the change due, as a suffix
+/// for the output line: `" (short $0.03)"`, `" (over $0.02)"`, or `""` if exact.
+///
+/// `shortfall_cents` is change due minus change given (see `till::Dispensed`).
+pub fn format_shortfall(shortfall_cents: i64, currency: &Currency) -> String {
+    let amount = |cents: i64| format_amount(Cents(cents.unsigned_abs()), &currency.symbol);
+    match shortfall_cents {
+        0 => String::new(),
+        short if short > 0 => format!(" (short {})", amount(short)),
+        over => format!(" (over {})", amount(over)),
+    }
+}
+
+/// Describe change owed to the customer as a suffix for the output line:
+/// `" (IOU $0.03)"`.
+pub fn format_iou(cents: u32, currency: &Currency) -> String {
+    format!(
+        " (IOU {})",
+        format_amount(Cents::from(cents), &currency.symbol)
+    )
+}
+
+/// Describe change rounded up for charity by `--roundup` as a suffix for
+/// the output line: `" (donated $0.08)"`, or `""` if none was.
+pub fn format_donation(donated: Cents, currency: &Currency) -> String {
+    if donated.is_zero() {
+        return String::new();
+    }
+    format!(" (donated {})", format_amount(donated, &currency.symbol))
+}
+
+/// Summarize a run's `--roundup` donations:
+/// `"Donated $0.42 from 5 of 7 sales, rounding change down to $0.10"`.
+pub fn format_donations(
+    donated: Cents,
+    donors: u64,
+    sales: u64,
+    increment: Cents,
+    currency: &Currency,
+) -> String {
+    let sym = &currency.symbol;
+    format!(
+        "Donated {} from {donors} of {sales} {}, rounding change down to {}",
+        format_amount(donated, sym),
+        if sales == 1 { "sale" } else { "sales" },
+        format_amount(increment, sym)
+    )
+}
+
+/// How many `--flag-suspicious` findings `format_findings` lists.
+const FINDINGS_SHOWN: usize = 10;
+
+/// List a run's `--flag-suspicious` findings by line, for the end of the
+/// run, or `None` if there are none:
+///
+/// ```text
+/// warning: 2 suspicious patterns for loss-prevention review
+///   $4.99 rung up 5 times in a row (lines 3-7)
+///   $9950.00 paid, just under the $10000.00 reporting threshold (line 9)
+/// ```
+pub fn format_findings(findings: &[Finding], currency: &Currency) -> Option<String> {
+    if findings.is_empty() {
+        return None;
+    }
+    let sym = &currency.symbol;
+    let mut sorted: Vec<&Finding> = findings.iter().collect();
+    sorted.sort_by_key(|finding| finding.first_line());
+    let noun = if findings.len() == 1 {
+        "pattern"
+    } else {
+        "patterns"
+    };
+    let mut out = format!(
+        "warning: {} suspicious {noun} for loss-prevention review",
+        findings.len()
+    );
+    for finding in sorted.iter().take(FINDINGS_SHOWN) {
+        let item = match finding {
+            Finding::Repeated {
+                amount,
+                refund,
+                count,
+                lines,
+            } => {
+                let what = if *refund {
+                    format!("refund of {} paid out", format_amount(*amount, sym))
+                } else {
+                    format!("{} rung up", format_amount(*amount, sym))
+                };
+                format!(
+                    "{what} {count} times in a row (lines {}-{})",
+                    lines.start(),
+                    lines.end()
+                )
+            }
+            Finding::UnderThreshold {
+                line,
+                paid,
+                threshold,
+            } => format!(
+                "{} paid, just under the {} reporting threshold (line {line})",
+                format_amount(*paid, sym),
+                format_amount(*threshold, sym)
+            ),
+        };
+        out.push_str("\n  ");
+        out.push_str(&item);
+    }
+    if findings.len() > FINDINGS_SHOWN {
+        out.push_str(&format!(
+            "\n  …and {} more",
+            findings.len() - FINDINGS_SHOWN
+        ));
+    }
+    Some(out)
+}
+
+/// Describe how heavy and bulky change is as a suffix for the output line:
+/// `" (26.8 g, 2.2 cm³)"`.
+pub fn format_heft(heft: Heft) -> String {
+    format!(
+        " ({}, {})",
+        format_weight(heft.milligrams),
+        format_volume(heft.cubic_mm)
+    )
+}
+
+/// List the cash transactions `--report-threshold` found, every one of them,
+/// for the end of the run; when there are none, say so:
+///
+/// ```text
+/// Cash transactions of $10000.00 or more to report: 2, $23500.00 in all
+///   line 4: $12000.00 received (id T1001, 2026-10-16T14:05:00Z)
+///   line 9: $11500.00 paid out for a refund
+/// ```
+pub fn format_cash_report(report: &CashReport, currency: &Currency) -> String {
+    let sym = &currency.symbol;
+    let threshold = format_amount(report.threshold(), sym);
+    let entries = report.entries();
+    if entries.is_empty() {
+        return format!("No cash transactions of {threshold} or more to report");
+    }
+    let total: Cents = entries.iter().map(|entry| entry.cash).sum();
+    let mut out = format!(
+        "Cash transactions of {threshold} or more to report: {}, {} in all",
+        entries.len(),
+        format_amount(total, sym)
+    );
+    for entry in entries {
+        let what = if entry.refund {
+            "paid out for a refund"
+        } else {
+            "received"
+        };
+        out += &format!(
+            "\n  line {}: {} {what}",
+            entry.line,
+            format_amount(entry.cash, sym)
+        );
+        let id = entry.id.as_ref().map(|id| format!("id {id}"));
+        let noted: Vec<String> = id.into_iter().chain(entry.time.clone()).collect();
+        if !noted.is_empty() {
+            out += &format!(" ({})", noted.join(", "));
+        }
+    }
+    out
+}
+
+/// The loyalty points a sale earned, or a refund took back, to follow its
+/// change: `" (12 points)"`.
+pub fn format_points(points: i64) -> String {
+    let noun = if points.abs() == 1 { "point" } else { "points" };
+    format!(" ({points} {noun})")
+}
+
+/// Summarize the loyalty points earned over a run, then by customer in ID
+/// order, with sales less refunds, and last those with no `customer=`:
+///
+/// ```text
+/// Loyalty points at 1 per $1.00: 17 on 4 transactions
+///   C1       12 points on 3 transactions ($12.50)
+///   (none)    5 points on 1 transaction ($5.00)
+/// ```
+pub fn format_loyalty(loyalty: &Loyalty, currency: &Currency) -> String {
+    let sym = &currency.symbol;
+    let transactions = |n: u64| {
+        format!(
+            "{n} {}",
+            if n == 1 {
+                "transaction"
+            } else {
+                "transactions"
+            }
+        )
+    };
+    let total = loyalty.total();
+    let mut out = format!(
+        "Loyalty points at {} per {}: {} on {}",
+        loyalty.rate,
+        format_amount(Cents(100), sym),
+        total.points,
+        transactions(total.transactions)
+    );
+    let mut rows: Vec<(&str, _)> = loyalty
+        .customers
+        .iter()
+        .map(|(customer, account)| (customer.as_str(), account))
+        .collect();
+    if loyalty.anonymous.transactions > 0 {
+        rows.push(("(none)", &loyalty.anonymous));
+    }
+    let name_width = rows.iter().map(|(name, _)| name.chars().count()).max();
+    let points_width = rows.iter().map(|(_, a)| a.points.to_string().len()).max();
+    for (name, account) in rows {
+        let spent = format_amount(Cents(account.spent.unsigned_abs()), sym);
+        let sign = if account.spent < 0 { "-" } else { "" };
+        let noun = if account.points.abs() == 1 {
+            "point"
+        } else {
+            "points"
+        };
+        out += &format!(
+            "\n  {name:<name_width$}  {:>points_width$} {noun} on {} ({sign}{spent})",
+            account.points,
+            transactions(account.transactions),
+            name_width = name_width.unwrap_or_default(),
+            points_width = points_width.unwrap_or_default(),
+        );
+    }
+    out
+}
+
+/// Summarize the weight of the change and payouts on a run's `lines`, and
+/// how many couldn't be weighed for want of a denomination's weight:
+/// `"Change handed over: 2.53 kg, 1.10 L on 120 lines"`.
+pub fn format_heft_total(heft: Heft, lines: u64, unweighed: u64) -> String {
+    let mut out = format!(
+        "Change handed over: {}, {} on {lines} {}",
+        format_weight(heft.milligrams),
+        format_volume(heft.cubic_mm),
+        if lines == 1 { "line" } else { "lines" }
+    );
+    if unweighed > 0 {
+        out += &format!(" ({unweighed} not weighed: no weight for a piece)");
+    }
+    out
+}
+
+/// Describe an underpaid line for the output, with the shortfall broken into
+/// the pieces still owed: `"insufficient payment: short $2.00 (2 dollars)"`.
+pub fn format_underpayment(
+    short: Cents,
+    breakdown: &[(usize, u32)],
+    currency: &Currency,
+) -> String {
+    format!(
+        "insufficient payment: short {} ({})",
+        format_amount(short, &currency.symbol),
+        format_breakdown(breakdown, currency)
+    )
+}
+
+/// An underpaid line as one line of JSON, in place of a `format_json` line:
+///
+/// `{"code":"E002","line":4,"owed":"5.00","paid":"3.00","short":"2.00","short_pieces":[{"cents":100,"count":2,"name":"dollars"}]}`
+///
+/// `code` is the underpayment's error code, so consumers can tell these
+/// lines apart from sales.
+pub fn format_underpayment_json(
+    line: usize,
+    owed: Cents,
+    paid: Cents,
+    breakdown: &[(usize, u32)],
+    currency: &Currency,
+) -> String {
+    let short = owed.checked_sub(paid).unwrap_or_default();
+    serde_json::json!({
+        "code": "E002",
+        "line": line,
+        "owed": owed.to_string(),
+        "paid": paid.to_string(),
+        "short": short.to_string(),
+        "short_pieces": breakdown_json(breakdown, currency),
+    })
+    .to_string()
+}
+
+/// Describe a refund for the output, with the pieces paid out of the
+/// drawer: `"Refund $2.12, pay out 2 dollars,1 dime,2 pennies"`.
+pub fn format_refund(amount: Cents, breakdown: &[(usize, u32)], currency: &Currency) -> String {
+    format!(
+        "Refund {}, pay out {}",
+        format_amount(amount, &currency.symbol),
+        format_breakdown(breakdown, currency)
+    )
+}
+
+/// A breakdown as pictures for a customer-facing display, each
+/// denomination's glyph once per piece and then its value:
+///
+/// `🪙🪙🪙 $0.25  🪙 $0.10  🪙🪙🪙 $0.01`
+///
+/// More than `MAX_REPEAT` pieces are drawn once with a count, `💵×12 $1.00`.
+/// An empty breakdown is `no change`, as in `format_breakdown`.
+pub fn format_pictogram(
+    breakdown: &[(usize, u32)],
+    currency: &Currency,
+    glyphs: &Glyphs,
+) -> String {
+    let mut out = String::new();
+    write_pictogram(&mut out, breakdown, currency, glyphs).expect("writing to a String can't fail");
+    out
+}
+
+/// Write a breakdown as `format_pictogram` formats it.
+pub fn write_pictogram(
+    out: &mut impl fmt::Write,
+    breakdown: &[(usize, u32)],
+    currency: &Currency,
+    glyphs: &Glyphs,
+) -> fmt::Result {
     if breakdown.is_empty() {
-        return "no change".to_string();
+        return out.write_str("no change");
+    }
+    for (i, (denom, count)) in currency.pieces(breakdown).enumerate() {
+        if i > 0 {
+            out.write_str("  ")?;
+        }
+        let glyph = glyphs.glyph(denom);
+        if count <= MAX_REPEAT {
+            for _ in 0..count {
+                out.write_str(glyph)?;
+            }
+        } else {
+            write!(out, "{glyph}×{count}")?;
+        }
+        write!(out, " {}", format_amount(denom.value(), &currency.symbol))?;
+    }
+    Ok(())
+}
+
+/// A refund as `format_refund` says it, with the payout as pictures:
+/// `Refund $2.12, pay out 💵💵 $1.00  🪙 $0.10  🪙🪙 $0.01`.
+pub fn format_pictogram_refund(
+    amount: Cents,
+    breakdown: &[(usize, u32)],
+    currency: &Currency,
+    glyphs: &Glyphs,
+) -> String {
+    format!(
+        "Refund {}, pay out {}",
+        format_amount(amount, &currency.symbol),
+        format_pictogram(breakdown, currency, glyphs)
+    )
+}
+
+/// A sale's change as sentences for text-to-speech, amounts in words:
+///
+/// `Your change is eighty-eight cents: three quarters, one dime, and three pennies.`
+///
+/// `change` is what was due in `currency`, the one `breakdown` is in. Change
+/// not handed over (a drawer shortfall or IOU) follows as `Three cents
+/// short.`, change rounded in the customer's favor as `One cent extra.`, and
+/// change rounded up for charity as `Thirteen cents donated.` With nothing
+/// due, it's just `No change.`
+pub fn format_spoken(
+    change: Cents,
+    breakdown: &[(usize, u32)],
+    not_given: i64,
+    donated: Cents,
+    currency: &Currency,
+) -> String {
+    let mut out = if change.is_zero() && breakdown.is_empty() {
+        "No change.".to_string()
+    } else {
+        format!(
+            "Your change is {}: {}.",
+            amount_words(change, currency),
+            breakdown_words(breakdown, currency)
+        )
+    };
+    out += &spoken_not_given(not_given, currency);
+    if !donated.is_zero() {
+        out += &format!(" {} donated.", sentence(&amount_words(donated, currency)));
+    }
+    out
+}
+
+/// What was owed and paid, spoken before the change under `--verbose`:
+/// `You owe two dollars and twelve cents and paid three dollars.`
+pub fn format_spoken_owed(transaction: &Transaction, currency: &Currency) -> String {
+    format!(
+        "You owe {} and paid {}.",
+        amount_words(transaction.owed_cents(), currency),
+        amount_words(transaction.paid_cents(), currency)
+    )
+}
+
+/// A refund as `format_spoken` says change: `Your refund is two dollars and
+/// twelve cents: two dollars, one dime, and two pennies.`
+pub fn format_spoken_refund(
+    amount: Cents,
+    breakdown: &[(usize, u32)],
+    not_given: i64,
+    currency: &Currency,
+) -> String {
+    format!(
+        "Your refund is {}: {}.{}",
+        amount_words(amount, currency),
+        breakdown_words(breakdown, currency),
+        spoken_not_given(not_given, currency)
+    )
+}
+
+/// Cents that weren't handed over, or with a negative count, extra that
+/// was, as a sentence with a leading space; empty for none.
+fn spoken_not_given(not_given: i64, currency: &Currency) -> String {
+    let amount = sentence(&amount_words(Cents(not_given.unsigned_abs()), currency));
+    match not_given {
+        0 => String::new(),
+        short if short > 0 => format!(" {amount} short."),
+        _ => format!(" {amount} extra."),
+    }
+}
+
+/// `text` with its first letter capitalized.
+fn sentence(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// A refund as one line of JSON, in place of a `format_json` line:
+///
+/// `{"iou_cents":0,"line":3,"payout":[{"cents":100,"count":2,"name":"dollars"},...],"refund":"2.12","shortfall_cents":0}`
+///
+/// The pieces are under `payout` rather than `change`, so consumers can't
+/// mistake money going out for a sale. With `heft`, the payout's weight and
+/// size are added as in `format_json`, and with `points`, the loyalty points
+/// taken back.
+#[allow(clippy::too_many_arguments)]
+pub fn format_refund_json(
+    line: usize,
+    amount: Cents,
+    breakdown: &[(usize, u32)],
+    currency: &Currency,
+    shortfall_cents: i64,
+    iou_cents: u32,
+    heft: Option<Heft>,
+    suspicious: &[Pattern],
+    points: Option<&Earned>,
+) -> String {
+    let mut json = serde_json::json!({
+        "line": line,
+        "refund": amount.to_string(),
+        "payout": breakdown_json(breakdown, currency),
+        "shortfall_cents": shortfall_cents,
+        "iou_cents": iou_cents,
+    });
+    if let Some(heft) = heft {
+        json["weight_mg"] = serde_json::json!(heft.milligrams);
+        json["volume_mm3"] = serde_json::json!(heft.cubic_mm);
+    }
+    if !suspicious.is_empty() {
+        json["suspicious"] = suspicious_json(suspicious);
+    }
+    if let Some(points) = points {
+        add_points_json(&mut json, points);
+    }
+    json.to_string()
+}
+
+/// The loyalty points a sale earned as `"points":12`, and whose they are as
+/// `"customer":"C42"` when it says.
+fn add_points_json(json: &mut serde_json::Value, earned: &Earned) {
+    json["points"] = serde_json::json!(earned.points);
+    if let Some(customer) = &earned.customer {
+        json["customer"] = serde_json::json!(customer);
+    }
+}
+
+/// The patterns `--flag-suspicious` matched, by name:
+/// `["repeated-amount"]`.
+fn suspicious_json(patterns: &[Pattern]) -> serde_json::Value {
+    patterns.iter().map(|p| p.as_str()).collect()
+}
+
+/// A line that failed with `code`, as one line of JSON in place of a
+/// `format_json` line, for runs that keep output lines matched to input
+/// lines:
+///
+/// `{"code":"E003","error":"line 2: expected owed,paid","line":2}`
+pub fn format_error_json(line: usize, code: &str, message: &str) -> String {
+    serde_json::json!({ "code": code, "error": message, "line": line }).to_string()
+}
+
+/// Carry a line's extra input fields (see
+/// [`ExtraFields::Metadata`](crate::parse::ExtraFields::Metadata)) through to
+/// the output line, as a tab-separated last column: `"\tnotes,aisle=4"`, or
+/// `""` if there are none.
+pub fn format_extra(extra: &[String]) -> String {
+    if extra.is_empty() {
+        return String::new();
+    }
+    format!("\t{}", extra.join(","))
+}
+
+/// Note the amounts a verbose line rounded to whole cents, as written:
+/// `" (owed 2.125 rounded half-up)"`.
+pub fn format_rounded(rounded: &Rounded) -> String {
+    let amounts: Vec<String> = [("owed", &rounded.owed), ("paid", &rounded.paid)]
+        .into_iter()
+        .filter_map(|(side, amount)| amount.as_ref().map(|amount| format!("{side} {amount}")))
+        .collect();
+    format!(
+        " ({} rounded {})",
+        amounts.join(", "),
+        rounded.mode.as_str()
+    )
+}
+
+/// Note how a verbose line's payment was split: `" (paid card $1.00 + cash
+/// $2.00)"`, and any store credit that went back onto its card: `" (paid
+/// gift $2.12 + cash $1.00, $0.88 back on gift)"`.
+pub fn format_split_tender(split: &SplitTender, currency: &Currency) -> String {
+    let sym = &currency.symbol;
+    let parts: Vec<String> = split
+        .parts
+        .iter()
+        .map(|(kind, amount)| format!("{kind} {sym}{amount}"))
+        .collect();
+    let returned: String = split
+        .returned
+        .iter()
+        .map(|(kind, amount)| format!(", {sym}{amount} back on {kind}"))
+        .collect();
+    format!(" (paid {}{returned})", parts.join(" + "))
+}
+
+/// Note what a verbose line was paid in another currency, given that
+/// currency's `symbol`: `" (paid €2.00 at 1.08)"`, and with change given in
+/// it, how much: `" (paid €2.00 at 1.08, change €0.04)"`.
+pub fn format_foreign_tender(
+    foreign: &ForeignTender,
+    symbol: &str,
+    change: Option<Cents>,
+) -> String {
+    let change = change.map_or(String::new(), |change| format!(", change {symbol}{change}"));
+    format!(
+        " (paid {symbol}{} at {}{change})",
+        foreign.paid, foreign.rate
+    )
+}
+
+/// Format a closing drawer report: count and value per denomination,
+/// the total left in the drawer, which denominations ran dry, and any skim
+/// suggestions and IOUs.
+/// The default register (`""`) is untitled; others are named in the heading.
+///
+/// Example:
+/// ```text
+/// Closing drawer (USD):
+///   10 dollars ($10.00)
+///   1 quarter ($0.25)
+///   0 dimes ($0.00)
+/// Total: $10.25
+/// Ran dry: dimes
+/// ```
+pub fn format_till_report(register: &str, till: &Till, currency: &Currency) -> String {
+    let sym = &currency.symbol;
+    let title = if register.is_empty() {
+        format!("Closing drawer ({}):", currency.name)
+    } else {
+        format!("Closing drawer {register} ({}):", currency.name)
+    };
+    let mut lines = vec![title];
+
+    for denom in currency.denominations.iter() {
+        let count = till.count(denom.cents);
+        let name = if count == 1 {
+            &denom.singular
+        } else {
+            &denom.plural
+        };
+        lines.push(format!(
+            "  {count} {name} ({})",
+            format_amount(denom.value() * count, sym)
+        ));
+    }
+
+    lines.push(format!("Total: {}", format_amount(till.total_cents(), sym)));
+
+    let dry: Vec<&str> = till
+        .ran_dry()
+        .filter_map(|cents| currency.denominations.iter().find(|d| d.cents == cents))
+        .map(|d| d.plural.as_ref())
+        .collect();
+    let dry = if dry.is_empty() {
+        "none".to_string()
+    } else {
+        dry.join(",")
+    };
+    lines.push(format!("Ran dry: {dry}"));
+
+    let skim = till.skim(currency);
+    if !skim.is_empty() {
+        lines.push(format!("Skim: {}", format_skim(&skim)));
+    }
+
+    if !till.ious().is_empty() {
+        let ious: Vec<String> = till
+            .ious()
+            .iter()
+            .map(|iou| {
+                format!(
+                    "{} (line {})",
+                    format_amount(Cents::from(iou.cents), sym),
+                    iou.line
+                )
+            })
+            .collect();
+        lines.push(format!("IOUs: {}", ious.join(",")));
+    }
+
+    lines.join("\n")
+}
+
+/// Describe what to skim from overfull slots, in whole bundles where the
+/// skim is bundled: `"remove 2 rolls of quarters,5 dimes"`.
+pub fn format_skim(skim: &[Skim]) -> String {
+    let items: Vec<String> = skim
+        .iter()
+        .map(|s| {
+            let d = &s.denomination;
+            if s.count % d.bundle == 0 {
+                let bundles = s.count / d.bundle;
+                let bundle = if bundles == 1 {
+                    d.bundle_name().to_string()
+                } else {
+                    format!("{}s", d.bundle_name())
+                };
+                format!("{bundles} {bundle} of {}", d.plural)
+            } else {
+                let name = if s.count == 1 { &d.singular } else { &d.plural };
+                format!("{} {name}", s.count)
+            }
+        })
+        .collect();
+    format!("remove {}", items.join(","))
+}
+
+/// Format the outcome of a simulated day:
+///
+/// ```text
+/// Simulated 200 sales (USD):
+///   pennies ran out at sale 23
+///   dimes ran out at sale 88
+/// Short of change on 14 sales
+/// ```
+pub fn format_simulation(report: &SimulationReport, currency: &Currency) -> String {
+    let mut lines = vec![format!(
+        "Simulated {} sales ({}):",
+        report.sales, currency.name
+    )];
+    for run_out in &report.ran_out {
+        lines.push(format!(
+            "  {} ran out at sale {}",
+            run_out.denomination.plural, run_out.sale
+        ));
+    }
+    if report.ran_out.is_empty() {
+        lines.push("  nothing ran out".to_string());
+    }
+    lines.push(format!("Short of change on {} sales", report.short_sales));
+    lines.join("\n")
+}
+
+/// Format a session's totals for an end-of-shift screen:
+///
+/// ```text
+/// Session (USD), 2 sales, 1 refund:
+///   Owed      $4.09
+///   Paid      $5.00
+///   Change    $0.91
+///   Refunded  $0.30
+///   Pieces       12 (4 quarters, 1 dime, 1 nickel, 6 pennies)
+/// ```
+///
+/// `Refunded` is left out without refunds, and failed lines are counted
+/// in the heading when there are any.
+pub fn format_session(totals: &SessionTotals, currency: &Currency) -> String {
+    let sym = &currency.symbol;
+    let plural = |count: u64, one: &str, many: &str| {
+        format!("{count} {}", if count == 1 { one } else { many })
+    };
+    let mut heading = plural(totals.sales, "sale", "sales");
+    if totals.refunds > 0 {
+        heading += &format!(", {}", plural(totals.refunds, "refund", "refunds"));
+    }
+    if totals.voids > 0 {
+        heading += &format!(", {} voided", totals.voids);
+    }
+    if totals.errors > 0 {
+        heading += &format!(", {}", plural(totals.errors, "error", "errors"));
+    }
+    let mut rows = vec![
+        ("Owed", format_amount(totals.owed, sym)),
+        ("Paid", format_amount(totals.paid, sym)),
+        ("Change", format_amount(totals.change, sym)),
+    ];
+    if totals.refunds > 0 {
+        rows.push(("Refunded", format_amount(totals.refunded, sym)));
+    }
+    rows.push(("Pieces", totals.piece_count().to_string()));
+    let width = rows
+        .iter()
+        .map(|(_, value)| value.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut lines = vec![format!("Session ({}), {heading}:", currency.name)];
+    for (label, value) in &rows {
+        lines.push(format!("  {label:<8}  {value:>width$}"));
+    }
+    let pieces: Vec<String> = currency
+        .denominations
+        .iter()
+        .zip(&totals.pieces)
+        .filter(|(_, &count)| count > 0)
+        .map(|(d, &count)| {
+            let name = if count == 1 { &d.singular } else { &d.plural };
+            format!("{count} {name}")
+        })
+        .collect();
+    if !pieces.is_empty() {
+        let last = lines.last_mut().expect("the pieces row");
+        *last += &format!(" ({})", pieces.join(", "));
+    }
+    lines.join("\n")
+}
+
+/// Format a run's voids, one per line:
+///
+/// ```text
+/// Voided 2 transactions:
+///   line 4: sale of $2.12 on line 1 (id T1)
+///   line 6: refund of $0.30 on line 5 (id T4)
+/// ```
+pub fn format_voids(voids: &[Voided], currency: &Currency) -> String {
+    let noun = if voids.len() == 1 {
+        "transaction"
+    } else {
+        "transactions"
+    };
+    let mut out = format!("Voided {} {noun}:", voids.len());
+    for void in voids {
+        let what = if void.refund { "refund" } else { "sale" };
+        out += &format!(
+            "\n  line {}: {what} of {} on line {} (id {})",
+            void.line,
+            format_amount(void.amount, &currency.symbol),
+            void.original,
+            void.id
+        );
+    }
+    out
+}
+
+/// Count the faults `generate --with-errors` put in `lines` lines, with
+/// `counts` indexed like `Quirk::ALL`:
+///
+/// ```text
+/// Quirks in 19 of 100 lines: 5 malformed, 4 underpaid, 6 whitespace, 4 encoding
+/// ```
+pub fn format_quirks(counts: &[usize; 4], lines: usize) -> String {
+    let items: Vec<String> = Quirk::ALL
+        .iter()
+        .zip(counts)
+        .map(|(quirk, count)| format!("{count} {}", quirk.as_str()))
+        .collect();
+    format!(
+        "Quirks in {} of {lines} lines: {}",
+        counts.iter().sum::<usize>(),
+        items.join(", ")
+    )
+}
+
+/// Widest bar in `format_change_stats`'s histogram.
+const HISTOGRAM_WIDTH: u64 = 40;
+
+/// Format change statistics: a histogram of change due, pieces per
+/// transaction, denominations by use, and the random rule's share.
+///
+/// ```text
+/// Change stats (USD), 3 transactions:
+/// Change due:
+///   no change        1  ########################################
+///   under $1.00      1  ########################################
+///   $1.00-$4.99      0
+///   $5.00-$9.99      0
+///   $10.00-$19.99    0
+///   $20.00 and up    1  ########################################
+/// Average pieces per transaction: 9.67
+/// Most used:
+///   dollars: 22 (75.9%)
+///   quarters: 3 (10.3%)
+/// Random rule: 1 of 3 transactions (33.3%)
+/// ```
+pub fn format_change_stats(stats: &ChangeStats, currency: &Currency) -> String {
+    let sym = &currency.symbol;
+    let percent = |part: u64, whole: u64| {
+        if whole == 0 {
+            0.0
+        } else {
+            part as f64 * 100.0 / whole as f64
+        }
+    };
+    let mut lines = vec![format!(
+        "Change stats ({}), {} {}:",
+        currency.name,
+        stats.transactions,
+        if stats.transactions == 1 {
+            "transaction"
+        } else {
+            "transactions"
+        }
+    )];
+
+    lines.push("Change due:".to_string());
+    let labels = (0..stats.histogram.len()).map(|i| match i {
+        0 => "no change".to_string(),
+        1 => format!("under {}", format_amount(CHANGE_BUCKETS[1], sym)),
+        i if i < CHANGE_BUCKETS.len() => format!(
+            "{}-{}",
+            format_amount(CHANGE_BUCKETS[i - 1], sym),
+            format_amount(CHANGE_BUCKETS[i] - Cents(1), sym)
+        ),
+        _ => format!("{} and up", format_amount(CHANGE_BUCKETS[i - 1], sym)),
+    });
+    let labels: Vec<String> = labels.collect();
+    let label_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    let count_width = stats
+        .histogram
+        .iter()
+        .map(|n| n.to_string().len())
+        .max()
+        .unwrap_or(1);
+    let tallest = stats.histogram.iter().copied().max().unwrap_or(0).max(1);
+    for (label, &count) in labels.iter().zip(&stats.histogram) {
+        let bar = "#".repeat((count * HISTOGRAM_WIDTH).div_ceil(tallest) as usize);
+        let line = format!("  {label:<label_width$}  {count:>count_width$}  {bar}");
+        lines.push(line.trim_end().to_string());
+    }
+
+    lines.push(format!(
+        "Average pieces per transaction: {:.2}",
+        stats.average_pieces()
+    ));
+    lines.push("Most used:".to_string());
+    let used = stats.most_used();
+    for &(cents, pieces) in &used {
+        let name = currency
+            .index_of(cents)
+            .map(|i| &currency.denominations[i].plural);
+        if let Some(name) = name {
+            lines.push(format!(
+                "  {name}: {pieces} ({:.1}%)",
+                percent(pieces, stats.pieces)
+            ));
+        }
+    }
+    if used.is_empty() {
+        lines.push("  no change given".to_string());
+    }
+    lines.push(format!(
+        "Random rule: {} of {} transactions ({:.1}%)",
+        stats.random,
+        stats.transactions,
+        percent(stats.random, stats.transactions)
+    ));
+    lines.join("\n")
+}
+
+/// Format run totals as a balance check:
+///
+/// ```text
+/// Totals (USD), 3 transactions:
+///   Paid           $10.00
+///   Owed            $7.42
+///   Paid - owed     $2.58
+///   Change given    $2.58
+///   Short           $0.00
+///   Over            $0.00
+/// Balanced: paid - owed = change given + short - over
+/// ```
+///
+/// `Short` is change due but not handed over (drawer shortfalls, IOUs, and
+/// rounding down) and `Over` is change rounded up. Change rounded up for
+/// charity adds a `Donated` row, and voided sales and refunds, which are
+/// left out of every row, are counted in the heading. Out of balance, the last
+/// line says so and each line that doesn't add up follows it:
+/// `  line 4: change due $0.88, accounted for $0.75`.
+pub fn format_totals(totals: &Totals, currency: &Currency) -> String {
+    let sym = &currency.symbol;
+    let signed = |cents: i64| {
+        let amount = format_amount(Cents(cents.unsigned_abs()), sym);
+        if cents < 0 {
+            format!("-{amount}")
+        } else {
+            amount
+        }
+    };
+    let mut rows = vec![
+        ("Paid", format_amount(totals.paid, sym)),
+        ("Owed", format_amount(totals.owed, sym)),
+        ("Paid - owed", format_amount(totals.paid - totals.owed, sym)),
+        ("Change given", format_amount(totals.given, sym)),
+        ("Short", format_amount(totals.short, sym)),
+        ("Over", format_amount(totals.over, sym)),
+    ];
+    if totals.refunds > 0 {
+        rows.insert(3, ("Refunded", format_amount(totals.refunded, sym)));
+    }
+    let donated = !totals.donated.is_zero();
+    if donated {
+        rows.push(("Donated", format_amount(totals.donated, sym)));
+    }
+    let width = rows
+        .iter()
+        .map(|(_, a)| a.chars().count())
+        .max()
+        .unwrap_or(0);
+    let plural = |count: u64, one: &str, many: &str| {
+        format!("{count} {}", if count == 1 { one } else { many })
+    };
+    let mut heading = plural(totals.transactions, "transaction", "transactions");
+    if totals.refunds > 0 {
+        heading += &format!(", {}", plural(totals.refunds, "refund", "refunds"));
+    }
+    if totals.voids > 0 {
+        heading += &format!(", {} voided", totals.voids);
+    }
+    let mut lines = vec![format!("Totals ({}), {heading}:", currency.name)];
+    for (label, amount) in &rows {
+        lines.push(format!("  {label:<12}  {amount:>width$}"));
+    }
+    let due = if totals.refunds > 0 {
+        "paid - owed + refunded"
+    } else {
+        "paid - owed"
+    };
+    let accounted = if donated {
+        "change given + short + donated - over"
+    } else {
+        "change given + short - over"
+    };
+    if totals.is_balanced() {
+        lines.push(format!("Balanced: {due} = {accounted}"));
+    } else {
+        lines.push(format!(
+            "Out of balance: {due} ({}) != {accounted} ({})",
+            format_amount(totals.paid - totals.owed + totals.refunded, sym),
+            signed(totals.accounted())
+        ));
+        for line in &totals.discrepancies {
+            lines.push(format!(
+                "  line {}: change due {}, accounted for {}",
+                line.line,
+                format_amount(line.due, sym),
+                signed(line.accounted)
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Format a bank order, listing only denominations with something to order:
+///
+/// ```text
+/// Bank order (USD):
+///   1 strap of dollars ($100.00)
+///   2 rolls of quarters ($20.00)
+/// Total: $120.00
+/// ```
+pub fn format_bank_order(order: &[OrderLine], currency: &Currency) -> String {
+    let sym = &currency.symbol;
+    let mut lines = vec![format!("Bank order ({}):", currency.name)];
+
+    for line in order.iter().filter(|line| line.bundles > 0) {
+        let bundle = if line.bundles == 1 {
+            line.denomination.bundle_name().to_string()
+        } else {
+            format!("{}s", line.denomination.bundle_name())
+        };
+        lines.push(format!(
+            "  {} {bundle} of {} ({})",
+            line.bundles,
+            line.denomination.plural,
+            format_amount(line.value(), sym)
+        ));
+    }
+    if lines.len() == 1 {
+        lines.push("  nothing to order".to_string());
+    }
+
+    let total = order.iter().map(OrderLine::value).sum();
+    lines.push(format!("Total: {}", format_amount(total, sym)));
+    lines.join("\n")
+}
+
+/// Format a drawer's contents as a bank deposit slip: bills, then coins
+/// split into full rolls and loose pieces, each with a subtotal, and the
+/// grand total. Only denominations the drawer holds are listed; the register
+/// is named as in `format_till_report`.
+///
+/// ```text
+/// Deposit slip (USD):
+/// Bills:
+///   5 dollars ($5.00)
+/// Coins:
+///   83 quarters: 2 rolls + 3 loose ($20.75)
+/// Bills total: $5.00
+/// Coins total: $20.75
+/// Total deposit: $25.75
+/// ```
+pub fn format_deposit_slip(register: &str, till: &Till, currency: &Currency) -> String {
+    let sym = &currency.symbol;
+    let mut lines = vec![if register.is_empty() {
+        format!("Deposit slip ({}):", currency.name)
+    } else {
+        format!("Deposit slip {register} ({}):", currency.name)
+    }];
+    let mut totals = [Cents(0); 2];
+    for (i, kind) in [DenominationKind::Bill, DenominationKind::Coin]
+        .into_iter()
+        .enumerate()
+    {
+        lines.push(match kind {
+            DenominationKind::Bill => "Bills:".to_string(),
+            DenominationKind::Coin => "Coins:".to_string(),
+        });
+        let held = currency
+            .denominations
+            .iter()
+            .filter(|d| d.kind == kind)
+            .map(|d| (d, till.count(d.cents)))
+            .filter(|&(_, count)| count > 0);
+        let before = lines.len();
+        for (denom, count) in held {
+            let value = denom.value() * count;
+            totals[i] += value;
+            let name = if count == 1 {
+                &denom.singular
+            } else {
+                &denom.plural
+            };
+            let amount = format_amount(value, sym);
+            lines.push(match kind {
+                DenominationKind::Bill => format!("  {count} {name} ({amount})"),
+                DenominationKind::Coin => {
+                    let rolls = count / denom.bundle;
+                    let roll = if rolls == 1 { "roll" } else { "rolls" };
+                    let loose = count % denom.bundle;
+                    format!("  {count} {name}: {rolls} {roll} + {loose} loose ({amount})")
+                }
+            });
+        }
+        if lines.len() == before {
+            lines.push("  none".to_string());
+        }
+    }
+    let [bills, coins] = totals;
+    lines.push(format!("Bills total: {}", format_amount(bills, sym)));
+    lines.push(format!("Coins total: {}", format_amount(coins, sym)));
+    lines.push(format!(
+        "Total deposit: {}",
+        format_amount(bills + coins, sym)
+    ));
+    lines.join("\n")
+}
+
+/// Compare two runs' output line by line, for `compare`: each output line
+/// that differs, as it was (`-`) and as it is (`+`), then how many did, or
+/// `None` if they're the same. A line only one side has shows just that
+/// side:
+///
+/// ```text
+/// output line 2:
+///   - 3 quarters,1 dime,3 pennies
+///   + 8 dimes,1 nickel,3 pennies
+/// 1 of 3 output lines changed
+/// ```
+pub fn format_output_diff(before: &str, after: &str) -> Option<String> {
+    let before: Vec<&str> = before.lines().collect();
+    let after: Vec<&str> = after.lines().collect();
+    let lines = before.len().max(after.len());
+    let mut out = String::new();
+    let mut changed = 0;
+    for i in 0..lines {
+        let (old, new) = (before.get(i), after.get(i));
+        if old == new {
+            continue;
+        }
+        changed += 1;
+        out += &format!("output line {}:\n", i + 1);
+        if let Some(old) = old {
+            out += &format!("  - {old}\n");
+        }
+        if let Some(new) = new {
+            out += &format!("  + {new}\n");
+        }
+    }
+    (changed > 0).then(|| {
+        let noun = if lines == 1 { "line" } else { "lines" };
+        format!("{out}{changed} of {lines} output {noun} changed")
+    })
+}
+
+/// Format what an audit found, for `audit`: each mismatch, as recorded
+/// and as re-computed, then how many there were:
+///
+/// ```text
+/// line 3, output line 2:
+///   recorded: 3 pennies
+///   expected: 1 nickel
+/// output line 4:
+///   recorded: 1 dollar
+///   expected: nothing
+/// 2 mismatches in 3 results
+/// ```
+pub fn format_audit(report: &AuditReport) -> String {
+    let results = if report.checked == 1 {
+        "result"
+    } else {
+        "results"
+    };
+    if report.is_clean() {
+        return format!("No mismatches in {} {results}", report.checked);
+    }
+    let mut out = String::new();
+    for mismatch in &report.mismatches {
+        if let Some(line) = mismatch.line {
+            out += &format!("line {line}, ");
+        }
+        // Continuation lines of a multi-line result line up under the first.
+        let show = |text: &Option<String>, missing: &str| {
+            text.as_deref().map_or(missing.to_string(), |text| {
+                text.replace('\n', "\n            ")
+            })
+        };
+        out += &format!(
+            "output line {}:\n  recorded: {}\n  expected: {}\n",
+            mismatch.output_line,
+            show(&mismatch.recorded, "nothing (the output ended)"),
+            show(&mismatch.expected, "nothing"),
+        );
+    }
+    let count = report.mismatches.len();
+    let noun = if count == 1 { "mismatch" } else { "mismatches" };
+    format!("{out}{count} {noun} in {} {results}", report.checked)
+}
+
+/// Format what `verify` found in a signed file, one flagged line each and
+/// then a count:
+///
+/// ```text
+/// line 2: altered (its signature doesn't match)
+/// line 4: out of sequence (expected #4, found #5): lines before it are missing or moved
+/// line 5: not signed
+/// line 7: starts a run that doesn't follow on from the line before it: runs before it are missing or moved
+/// 4 problems in 9 lines
+/// ```
+#[cfg(feature = "signing")]
+pub fn format_verify(report: &VerifyReport) -> String {
+    let lines = if report.lines == 1 { "line" } else { "lines" };
+    if report.is_clean() {
+        return format!("All {} {lines} verified", report.lines);
+    }
+    let mut out = String::new();
+    for flagged in &report.flagged {
+        let problem = match flagged.problem {
+            Problem::Unsigned => "not signed".to_string(),
+            Problem::Altered => "altered (its signature doesn't match)".to_string(),
+            Problem::OutOfSequence { expected, found } => format!(
+                "out of sequence (expected #{expected}, found #{found}): lines before it are missing or moved"
+            ),
+            Problem::Unlinked => {
+                "starts a run that doesn't follow on from the line before it: runs before it are missing or moved".to_string()
+            }
+            Problem::Unended => {
+                "its run has no end record: lines after it are missing, or the run was cut short".to_string()
+            }
+        };
+        out += &format!("line {}: {problem}\n", flagged.line);
+    }
+    let count = report.flagged.len();
+    let noun = if count == 1 { "problem" } else { "problems" };
+    format!("{out}{count} {noun} in {} {lines}", report.lines)
+}
+
+/// Format a drawer count checked against the expected till, naming the
+/// register like `format_till_report`:
+///
+/// ```text
+/// Reconciliation (USD):
+///   dollars: expected 5, counted 6 (over $1.00)
+///   quarters: expected 40, counted 38 (short $0.50)
+/// Net: over $0.50
+/// ```
+pub fn format_reconciliation(
+    register: &str,
+    discrepancies: &[Discrepancy],
+    currency: &Currency,
+) -> String {
+    let sym = &currency.symbol;
+    let mut lines = vec![if register.is_empty() {
+        format!("Reconciliation ({}):", currency.name)
+    } else {
+        format!("Reconciliation {register} ({}):", currency.name)
+    }];
+    let describe = |over: i64| match over {
+        0 => "balanced".to_string(),
+        over if over > 0 => format!("over {}", format_amount(Cents(over.unsigned_abs()), sym)),
+        short => format!("short {}", format_amount(Cents(short.unsigned_abs()), sym)),
+    };
+
+    for d in discrepancies {
+        lines.push(format!(
+            "  {}: expected {}, counted {} ({})",
+            d.denomination.plural,
+            d.expected,
+            d.counted,
+            describe(d.over_cents())
+        ));
+    }
+    if discrepancies.is_empty() {
+        lines.push("  every denomination matches".to_string());
+    }
+
+    let net: i64 = discrepancies.iter().map(Discrepancy::over_cents).sum();
+    lines.push(format!("Net: {}", describe(net)));
+    lines.join("\n")
+}
+
+/// Format cents with a currency symbol: 213, "$" -> "$2.13".
+fn format_amount(amount: Cents, symbol: &str) -> String {
+    amount.with_symbol(symbol)
+}
+
+/// Format a transaction with its breakdown for verbose output.
+///
+/// Example: "Owed $2.12, Paid $3.00 -> 3 quarters,1 dime,3 pennies"
+/// With randomization: "Owed $3.33, Paid $5.00 -> 1 dollar,2 quarters (random)"
+pub fn format_verbose(
+    transaction: &Transaction,
+    breakdown: &[(usize, u32)],
+    currency: &Currency,
+    is_random: bool,
+) -> String {
+    let mut out = String::new();
+    write_verbose(&mut out, transaction, breakdown, currency, is_random)
+        .expect("writing to a String can't fail");
+    out
+}
+
+/// Write a transaction as `format_verbose` formats it, without allocating.
+pub fn write_verbose(
+    out: &mut impl fmt::Write,
+    transaction: &Transaction,
+    breakdown: &[(usize, u32)],
+    currency: &Currency,
+    is_random: bool,
+) -> fmt::Result {
+    let sym = &currency.symbol;
+    write!(
+        out,
+        "Owed {sym}{}, Paid {sym}{} -> ",
+        transaction.owed_cents(),
+        transaction.paid_cents(),
+    )?;
+    write_breakdown(out, breakdown, currency)?;
+    if is_random {
+        out.write_str(" (random)")?;
+    }
+    Ok(())
+}
+
+/// Version of the `format_json` line layout. Bumped whenever a key is
+/// removed or changes meaning; new keys may appear without a bump.
+pub const JSON_SCHEMA_VERSION: u32 = 1;
+
+/// Format a transaction's result as one line of JSON:
+///
+/// `{"change":[{"cents":25,"count":3,"name":"quarters"},...],"iou_cents":0,"line":1,"owed":"2.12","paid":"3.00","random":false,"shortfall_cents":0}`
+///
+/// Amounts are decimal strings so no consumer parses money as a float.
+/// `shortfall_cents` is change due minus change given, as in `format_shortfall`.
+/// `extra` fields from the input line go in an `extra` array, left out when
+/// there are none, and `"overpaid":true` marks a line that drew a
+/// `LargeOverpayment` warning. A line with amounts rounded to whole cents
+/// says how, and what was written: `"rounded":{"mode":"half-up","owed":"2.125"}`.
+/// A split payment lists its parts, with `paid` as their total:
+/// `"tender":[{"amount":"1.00","kind":"card"},{"amount":"2.00","kind":"cash"}]`,
+/// with store credit that went back onto its card under `back_on_card`.
+/// A payment in another currency says what was handed over, with `paid`
+/// converted: `"foreign":{"currency":"EUR","paid":"2.00","rate":"1.08"}`,
+/// and `change_currency` is the code of `currency`, which the change is in.
+/// Change rounded up for charity is `"donated":"0.08"`, left out when none was,
+/// and the change's weight and size are `weight_mg` and `volume_mm3` when
+/// `heft` is given. Loyalty `points` are `"points":12`, with the customer
+/// they went to as `"customer":"C42"`.
+#[allow(clippy::too_many_arguments)]
+pub fn format_json(
+    line: usize,
+    transaction: &Transaction,
+    breakdown: &[(usize, u32)],
+    currency: &Currency,
+    is_random: bool,
+    shortfall_cents: i64,
+    iou_cents: u32,
+    extra: &[String],
+    overpaid: bool,
+    rounded: Option<&Rounded>,
+    split: Option<&SplitTender>,
+    foreign: Option<&ForeignTender>,
+    donated: Cents,
+    heft: Option<Heft>,
+    suspicious: &[Pattern],
+    points: Option<&Earned>,
+) -> String {
+    let mut json = serde_json::json!({
+        "line": line,
+        "owed": transaction.owed_cents().to_string(),
+        "paid": transaction.paid_cents().to_string(),
+        "random": is_random,
+        "change": breakdown_json(breakdown, currency),
+        "shortfall_cents": shortfall_cents,
+        "iou_cents": iou_cents,
+    });
+    if !extra.is_empty() {
+        json["extra"] = serde_json::json!(extra);
+    }
+    if overpaid {
+        json["overpaid"] = serde_json::json!(true);
+    }
+    if let Some(rounded) = rounded {
+        let mut note = serde_json::json!({ "mode": rounded.mode.as_str() });
+        for (side, amount) in [("owed", &rounded.owed), ("paid", &rounded.paid)] {
+            if let Some(amount) = amount {
+                note[side] = serde_json::json!(amount);
+            }
+        }
+        json["rounded"] = note;
+    }
+    if let Some(split) = split {
+        let parts: Vec<serde_json::Value> = split
+            .parts
+            .iter()
+            .map(|(kind, amount)| serde_json::json!({ "kind": kind, "amount": amount.to_string() }))
+            .collect();
+        json["tender"] = serde_json::json!(parts);
+        if !split.returned.is_empty() {
+            let returned: Vec<serde_json::Value> = split
+                .returned
+                .iter()
+                .map(|(kind, amount)| serde_json::json!({ "kind": kind, "amount": amount.to_string() }))
+                .collect();
+            json["back_on_card"] = serde_json::json!(returned);
+        }
+    }
+    if let Some(foreign) = foreign {
+        json["foreign"] = serde_json::json!({
+            "currency": foreign.currency,
+            "paid": foreign.paid.to_string(),
+            "rate": foreign.rate,
+        });
+        json["change_currency"] = serde_json::json!(currency.name);
+    }
+    if !donated.is_zero() {
+        json["donated"] = serde_json::json!(donated.to_string());
+    }
+    if let Some(heft) = heft {
+        json["weight_mg"] = serde_json::json!(heft.milligrams);
+        json["volume_mm3"] = serde_json::json!(heft.cubic_mm);
+    }
+    if !suspicious.is_empty() {
+        json["suspicious"] = suspicious_json(suspicious);
+    }
+    if let Some(points) = points {
+        add_points_json(&mut json, points);
+    }
+    json.to_string()
+}
+
+/// Ledger account the drawer's cash is booked to.
+pub const LEDGER_DRAWER: &str = "Assets:Cash:Drawer";
+/// Ledger account sales are credited to.
+pub const LEDGER_SALES: &str = "Income:Sales";
+/// Ledger account for change the drawer couldn't hand over (a shortfall or
+/// an IOU), which the store still owes the customer.
+pub const LEDGER_CHANGE_OWED: &str = "Liabilities:ChangeOwed";
+/// Ledger account for change rounded up for charity, held for the charity.
+pub const LEDGER_DONATIONS: &str = "Liabilities:Donations";
+
+/// Format a sale as a double-entry ledger transaction that both hledger and
+/// beancount read: the cash paid debits the drawer, the amount owed credits
+/// sales, and the change given is a contra line back out of the drawer.
+///
+/// ```text
+/// 2026-10-16 * "line 1"
+///     Assets:Cash:Drawer                    3.00 USD
+///     Assets:Cash:Drawer                   -0.88 USD
+///     Income:Sales                         -2.12 USD
+/// ```
+///
+/// Non-cash parts of a split payment debit `Assets:Tender:Card` (and so on)
+/// instead of the drawer. Change the drawer fell short by, `owed_to_customer`
+/// cents, is credited to `LEDGER_CHANGE_OWED` so the entry still balances,
+/// and change `donated` to charity is credited to `LEDGER_DONATIONS`.
+pub fn format_ledger_entry(
+    date: &str,
+    line: usize,
+    transaction: &Transaction,
+    split: Option<&SplitTender>,
+    owed_to_customer: i64,
+    donated: Cents,
+    currency: &Currency,
+) -> String {
+    let posting = |account: &str, cents: i64| ledger_posting(account, cents, currency);
+    let cents = |amount: Cents| amount.0 as i64;
+    let mut lines = vec![format!("{date} * \"line {line}\"")];
+    match split {
+        Some(split) => {
+            for (kind, amount) in &split.parts {
+                let account = if kind == "cash" {
+                    LEDGER_DRAWER.to_string()
+                } else {
+                    let mut name: Vec<char> = kind.chars().collect();
+                    name[0] = name[0].to_ascii_uppercase();
+                    format!("Assets:Tender:{}", name.into_iter().collect::<String>())
+                };
+                lines.push(posting(&account, cents(*amount)));
+            }
+        }
+        None => lines.push(posting(LEDGER_DRAWER, cents(transaction.paid_cents()))),
+    }
+    let given = cents(transaction.change_cents()) - owed_to_customer - cents(donated);
+    if given != 0 {
+        lines.push(posting(LEDGER_DRAWER, -given));
+    }
+    lines.push(posting(LEDGER_SALES, -cents(transaction.owed_cents())));
+    if owed_to_customer != 0 {
+        lines.push(posting(LEDGER_CHANGE_OWED, -owed_to_customer));
+    }
+    if !donated.is_zero() {
+        lines.push(posting(LEDGER_DONATIONS, -cents(donated)));
+    }
+    lines.join("\n")
+}
+
+/// Format a refund as a ledger transaction: the refund debits sales and the
+/// cash paid out credits the drawer, with anything the drawer fell short by,
+/// `owed_to_customer` cents, still owed under `LEDGER_CHANGE_OWED`.
+///
+/// ```text
+/// 2026-10-16 * "line 4 refund"
+///     Income:Sales                          2.12 USD
+///     Assets:Cash:Drawer                   -2.12 USD
+/// ```
+pub fn format_ledger_refund(
+    date: &str,
+    line: usize,
+    amount: Cents,
+    owed_to_customer: i64,
+    currency: &Currency,
+) -> String {
+    let posting = |account: &str, cents: i64| ledger_posting(account, cents, currency);
+    let mut lines = vec![
+        format!("{date} * \"line {line} refund\""),
+        posting(LEDGER_SALES, amount.0 as i64),
+    ];
+    let given = amount.0 as i64 - owed_to_customer;
+    if given != 0 {
+        lines.push(posting(LEDGER_DRAWER, -given));
+    }
+    if owed_to_customer != 0 {
+        lines.push(posting(LEDGER_CHANGE_OWED, -owed_to_customer));
+    }
+    lines.join("\n")
+}
+
+/// One posting line of a ledger entry: the account, then the signed amount
+/// right-aligned with its commodity.
+fn ledger_posting(account: &str, cents: i64, currency: &Currency) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let amount = format!("{sign}{}", Cents(cents.unsigned_abs()));
+    format!("    {account:<30}{amount:>12} {}", currency.name)
+}
+
+/// A breakdown as the JSON `change` array `format_json` writes:
+/// `[{"cents":25,"count":3,"name":"quarters"},...]`.
+pub fn breakdown_json(breakdown: &[(usize, u32)], currency: &Currency) -> serde_json::Value {
+    currency
+        .pieces(breakdown)
+        .map(|(denom, count)| {
+            let name = if count == 1 {
+                &denom.singular
+            } else {
+                &denom.plural
+            };
+            serde_json::json!({ "cents": denom.cents, "count": count, "name": name })
+        })
+        .collect()
+}
+
+/// The header for `--output csv`: the line, the amounts, a count column per
+/// denomination of `currency`, largest first, and an error code column:
+///
+/// `line,owed,paid,change,dollars,quarters,dimes,nickels,pennies,error`
+pub fn format_csv_header(currency: &Currency) -> String {
+    let mut out = String::from("line,owed,paid,change");
+    for denomination in currency.denominations.iter() {
+        out.push(',');
+        out.push_str(&csv_field(&denomination.plural));
+    }
+    out + ",error"
+}
+
+/// A sale as one `--output csv` row, with how many of each denomination
+/// its change took: `1,2.12,3.00,0.88,0,3,1,0,3,`.
+pub fn format_csv(
+    line: usize,
+    transaction: &Transaction,
+    breakdown: &[(usize, u32)],
+    currency: &Currency,
+) -> String {
+    let amounts = [
+        transaction.owed_cents().to_string(),
+        transaction.paid_cents().to_string(),
+        transaction.change_cents().to_string(),
+    ];
+    csv_row(line, &amounts, Some(breakdown), currency, "")
+}
+
+/// A refund as one `--output csv` row, written like the input does, as a
+/// negative owed amount with nothing paid: `4,-2.12,0.00,2.12,2,0,1,0,2,`.
+pub fn format_refund_csv(
+    line: usize,
+    amount: Cents,
+    breakdown: &[(usize, u32)],
+    currency: &Currency,
+) -> String {
+    let amounts = [
+        format!("-{amount}"),
+        Cents::ZERO.to_string(),
+        amount.to_string(),
+    ];
+    csv_row(line, &amounts, Some(breakdown), currency, "")
+}
+
+/// A line that failed with `code` as one `--output csv` row, with the
+/// amounts when they were read and no counts: `3,5.00,2.00,,,,,,,E002`.
+pub fn format_error_csv(
+    line: usize,
+    amounts: Option<(Cents, Cents)>,
+    code: &str,
+    currency: &Currency,
+) -> String {
+    let amounts = match amounts {
+        Some((owed, paid)) => [owed.to_string(), paid.to_string(), String::new()],
+        None => Default::default(),
+    };
+    csv_row(line, &amounts, None, currency, code)
+}
+
+fn csv_row(
+    line: usize,
+    amounts: &[String; 3],
+    breakdown: Option<&[(usize, u32)]>,
+    currency: &Currency,
+    error: &str,
+) -> String {
+    let mut counts = vec![0; currency.denominations.len()];
+    for &(index, count) in breakdown.unwrap_or_default() {
+        counts[index] += count;
+    }
+    let mut out = format!("{line},{}", amounts.join(","));
+    for count in counts {
+        out.push(',');
+        if breakdown.is_some() {
+            out += &count.to_string();
+        }
+    }
+    out + "," + error
+}
+
+/// A CSV field, quoted when it has a comma, quote, or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::Audit;
+    use crate::currency::{EUR, USD};
+    use crate::parse::Fields;
+    use crate::rounding::RoundingMode;
+
+    fn penny() -> usize {
+        USD.index_of(1).unwrap()
+    }
+
+    fn quarter() -> usize {
+        USD.index_of(25).unwrap()
+    }
+
+    fn dime() -> usize {
+        USD.index_of(10).unwrap()
+    }
+
+    fn dollar() -> usize {
+        USD.index_of(100).unwrap()
+    }
+
+    #[test]
+    fn sample_output_format() {
+        let breakdown = vec![(quarter(), 3), (dime(), 1), (penny(), 3)];
+        assert_eq!(
+            format_breakdown(&breakdown, &USD),
+            "3 quarters,1 dime,3 pennies"
+        );
+    }
+
+    #[test]
+    fn single_denomination_singular() {
+        let breakdown = vec![(dollar(), 1)];
+        assert_eq!(format_breakdown(&breakdown, &USD), "1 dollar");
+    }
+
+    #[test]
+    fn single_denomination_plural() {
+        let breakdown = vec![(penny(), 5)];
+        assert_eq!(format_breakdown(&breakdown, &USD), "5 pennies");
+    }
+
+    #[test]
+    fn empty_breakdown() {
+        assert_eq!(format_breakdown(&Vec::new(), &USD), "no change");
+    }
+
+    #[test]
+    fn matches_exact_sample_output() {
+        // "3 quarters,1 dime,3 pennies" — note: no spaces after commas
+        let breakdown = vec![(quarter(), 3), (dime(), 1), (penny(), 3)];
+        let output = format_breakdown(&breakdown, &USD);
+        assert!(
+            !output.contains(", "),
+            "output should not have spaces after commas"
+        );
+        assert_eq!(output, "3 quarters,1 dime,3 pennies");
+    }
+
+    #[test]
+    fn write_variants_append_to_a_reused_buffer() {
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        let breakdown = vec![(quarter(), 3), (dime(), 1), (penny(), 3)];
+        let mut out = String::from("> ");
+        write_breakdown(&mut out, &breakdown, &USD).unwrap();
+        assert_eq!(out, "> 3 quarters,1 dime,3 pennies");
+
+        out.clear();
+        write_verbose(&mut out, &tx, &breakdown, &USD, true).unwrap();
+        assert_eq!(
+            out,
+            "Owed $2.12, Paid $3.00 -> 3 quarters,1 dime,3 pennies (random)"
+        );
+    }
+
+    #[test]
+    fn format_amount_usd() {
+        assert_eq!(format_amount(Cents(213), "$"), "$2.13");
+        assert_eq!(format_amount(Cents(5), "$"), "$0.05");
+        assert_eq!(format_amount(Cents(300), "$"), "$3.00");
+        assert_eq!(format_amount(Cents(0), "$"), "$0.00");
+        assert_eq!(format_amount(Cents(10000), "$"), "$100.00");
+    }
+
+    #[test]
+    fn totals_balance_or_name_the_lines_out() {
+        let mut totals = Totals::new();
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        totals.record(1, &tx, &[(1, 3), (2, 1), (4, 3)], 0, Cents::ZERO, &USD);
+        let tx = Transaction::new(Cents(197), Cents(200)).unwrap();
+        totals.record(2, &tx, &[], 3, Cents::ZERO, &USD);
+        assert_eq!(
+            format_totals(&totals, &USD),
+            "Totals (USD), 2 transactions:\n\
+             \x20 Paid          $5.00\n\
+             \x20 Owed          $4.09\n\
+             \x20 Paid - owed   $0.91\n\
+             \x20 Change given  $0.88\n\
+             \x20 Short         $0.03\n\
+             \x20 Over          $0.00\n\
+             Balanced: paid - owed = change given + short - over"
+        );
+
+        totals.record(4, &tx, &[(4, 1)], 0, Cents::ZERO, &USD);
+        let text = format_totals(&totals, &USD);
+        assert!(
+            text.ends_with(
+                "Out of balance: paid - owed ($0.94) != change given + short - over ($0.92)\n\
+                 \x20 line 4: change due $0.03, accounted for $0.01"
+            ),
+            "{text}"
+        );
+
+        let mut totals = Totals::new();
+        totals.record_refund(5, Cents(200), &[(0, 2)], 0, &USD);
+        assert_eq!(
+            format_totals(&totals, &USD),
+            "Totals (USD), 0 transactions, 1 refund:\n\
+             \x20 Paid          $0.00\n\
+             \x20 Owed          $0.00\n\
+             \x20 Paid - owed   $0.00\n\
+             \x20 Refunded      $2.00\n\
+             \x20 Change given  $2.00\n\
+             \x20 Short         $0.00\n\
+             \x20 Over          $0.00\n\
+             Balanced: paid - owed + refunded = change given + short - over"
+        );
+    }
+
+    #[test]
+    fn pictograms_repeat_glyphs_up_to_a_limit() {
+        let glyphs = Glyphs::default();
+        assert_eq!(
+            format_pictogram(&[(quarter(), 3), (dime(), 1)], &USD, &glyphs),
+            "🪙🪙🪙 $0.25  🪙 $0.10"
+        );
+        assert_eq!(format_pictogram(&[(0, 12)], &USD, &glyphs), "💵×12 $1.00");
+        assert_eq!(format_pictogram(&[], &USD, &glyphs), "no change");
+
+        let mut glyphs = Glyphs::default();
+        glyphs.set("0.01", "c").unwrap();
+        assert_eq!(
+            format_pictogram_refund(Cents(102), &[(0, 1), (penny(), 2)], &USD, &glyphs),
+            "Refund $1.02, pay out 💵 $1.00  cc $0.01"
+        );
+    }
+
+    #[test]
+    fn spoken_lines_are_sentences() {
+        let breakdown = vec![(quarter(), 3)];
+        assert_eq!(
+            format_spoken(Cents(88), &breakdown, 13, Cents::ZERO, &USD),
+            "Your change is eighty-eight cents: three quarters. Thirteen cents short."
+        );
+        assert_eq!(
+            format_spoken(Cents(75), &breakdown, 0, Cents(13), &USD),
+            "Your change is seventy-five cents: three quarters. Thirteen cents donated."
+        );
+        assert_eq!(
+            format_spoken(Cents::ZERO, &[], 0, Cents::ZERO, &USD),
+            "No change."
+        );
+        assert_eq!(
+            format_spoken_refund(Cents(100), &[(0, 1)], -1, &USD),
+            "Your refund is one dollar: one dollar. One cent extra."
+        );
+    }
+
+    #[test]
+    fn voids_name_the_line_they_reversed() {
+        let voids = [
+            Voided {
+                line: 4,
+                original: 1,
+                id: "T1".into(),
+                amount: Cents(212),
+                refund: false,
+            },
+            Voided {
+                line: 6,
+                original: 5,
+                id: "T4".into(),
+                amount: Cents(30),
+                refund: true,
+            },
+        ];
+        assert_eq!(
+            format_voids(&voids, &USD),
+            "Voided 2 transactions:\n\
+             \x20 line 4: sale of $2.12 on line 1 (id T1)\n\
+             \x20 line 6: refund of $0.30 on line 5 (id T4)"
+        );
+
+        let mut totals = Totals::new();
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        totals.record(1, &tx, &[(1, 3), (2, 1), (4, 3)], 0, Cents::ZERO, &USD);
+        totals.record(2, &tx, &[(1, 3), (2, 1), (4, 3)], 0, Cents::ZERO, &USD);
+        totals.void(2, &tx, &[(1, 3), (2, 1), (4, 3)], 0, Cents::ZERO, &USD);
+        let text = format_totals(&totals, &USD);
+        assert!(
+            text.starts_with("Totals (USD), 1 transaction, 1 voided:\n"),
+            "{text}"
+        );
+    }
+
+    #[test]
+    fn format_amount_eur() {
+        assert_eq!(format_amount(Cents(150), "€"), "€1.50");
+        assert_eq!(format_amount(Cents(1), "€"), "€0.01");
+    }
+
+    #[test]
+    fn verbose_greedy() {
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        let breakdown = vec![(quarter(), 3), (dime(), 1), (penny(), 3)];
+        assert_eq!(
+            format_verbose(&tx, &breakdown, &USD, false),
+            "Owed $2.12, Paid $3.00 -> 3 quarters,1 dime,3 pennies",
+        );
+    }
+
+    #[test]
+    fn verbose_random() {
+        let tx = Transaction::new(Cents(333), Cents(500)).unwrap();
+        let breakdown = vec![(dollar(), 1), (quarter(), 2), (penny(), 17)];
+        assert_eq!(
+            format_verbose(&tx, &breakdown, &USD, true),
+            "Owed $3.33, Paid $5.00 -> 1 dollar,2 quarters,17 pennies (random)",
+        );
+    }
+
+    #[test]
+    fn verbose_no_change() {
+        let tx = Transaction::new(Cents(500), Cents(500)).unwrap();
+        assert_eq!(
+            format_verbose(&tx, &Vec::new(), &USD, false),
+            "Owed $5.00, Paid $5.00 -> no change",
+        );
+    }
+
+    #[test]
+    fn verbose_eur_uses_euro_symbol() {
+        let tx = Transaction::new(Cents(150), Cents(200)).unwrap();
+        let breakdown = vec![(EUR.index_of(50).unwrap(), 1)];
+        assert_eq!(
+            format_verbose(&tx, &breakdown, &EUR, false),
+            "Owed €1.50, Paid €2.00 -> 1 50 cent coin",
+        );
     }
 
-    breakdown
-        .iter()
-        .map(|(denom, count)| {
-            let name = if *count == 1 {
-                denom.singular
-            } else {
-                denom.plural
-            };
-            format!("{count} {name}")
-        })
-        .collect::<Vec<_>>()
-        .join(",")
-}
+    #[test]
+    fn shortfall_suffix() {
+        let usd = &USD;
+        assert_eq!(format_shortfall(0, usd), "");
+        assert_eq!(format_shortfall(3, usd), " (short $0.03)");
+        assert_eq!(format_shortfall(-2, usd), " (over $0.02)");
+        assert_eq!(format_shortfall(150, &EUR), " (short €1.50)");
+    }
 
-/// Format cents with a currency symbol: 213, "$" -> "$2.13".
-fn format_amount(cents: u32, symbol: &str) -> String {
-    format!("{symbol}{}.{:02}", cents / 100, cents % 100)
-}
+    #[test]
+    fn till_report_lists_every_denomination() {
+        let mut till = crate::till::parse_till("1.00,10\n0.25,1\n0.10,1\n", &USD).unwrap();
+        till.dispense(&[(dime(), 1)], &USD).unwrap();
+        assert_eq!(
+            format_till_report("", &till, &USD),
+            "Closing drawer (USD):\n\
+             \x20 10 dollars ($10.00)\n\
+             \x20 1 quarter ($0.25)\n\
+             \x20 0 dimes ($0.00)\n\
+             \x20 0 nickels ($0.00)\n\
+             \x20 0 pennies ($0.00)\n\
+             Total: $10.25\n\
+             Ran dry: dimes",
+        );
+    }
 
-/// Format a transaction with its breakdown for verbose output.
-///
-/// Example: "Owed $2.12, Paid $3.00 -> 3 quarters,1 dime,3 pennies"
-/// With randomization: "Owed $3.33, Paid $5.00 -> 1 dollar,2 quarters (random)"
-pub fn format_verbose(
-    transaction: &Transaction,
-    breakdown: &Breakdown,
-    currency: &Currency,
-    is_random: bool,
-) -> String {
-    let change = format_breakdown(breakdown);
-    let label = if is_random { " (random)" } else { "" };
-    let sym = currency.symbol;
-    format!(
-        "Owed {}, Paid {} -> {change}{label}",
-        format_amount(transaction.owed_cents, sym),
-        format_amount(transaction.paid_cents, sym),
-    )
-}
+    #[test]
+    fn till_report_nothing_ran_dry() {
+        let till = crate::till::parse_till("0.01,3\n", &USD).unwrap();
+        let report = format_till_report("", &till, &USD);
+        assert!(report.ends_with("Total: $0.03\nRan dry: none"), "{report}");
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::currency::Denomination;
+    #[test]
+    fn till_report_lists_ious() {
+        let mut till = Till::new();
+        till.record_iou(2, 3);
+        till.record_iou(5, 125);
+        let report = format_till_report("", &till, &USD);
+        assert!(
+            report.ends_with("Ran dry: none\nIOUs: $0.03 (line 2),$1.25 (line 5)"),
+            "{report}"
+        );
+        assert_eq!(format_iou(3, &USD), " (IOU $0.03)");
+    }
 
-    fn penny() -> Denomination {
-        Denomination {
-            cents: 1,
-            singular: "penny",
-            plural: "pennies",
-        }
+    #[test]
+    fn bank_order_lists_bundles() {
+        let order = [
+            OrderLine {
+                denomination: USD.denominations[dollar()].clone(),
+                bundles: 1,
+            },
+            OrderLine {
+                denomination: USD.denominations[quarter()].clone(),
+                bundles: 2,
+            },
+            OrderLine {
+                denomination: USD.denominations[dime()].clone(),
+                bundles: 0,
+            },
+        ];
+        assert_eq!(
+            format_bank_order(&order, &USD),
+            "Bank order (USD):\n  1 strap of dollars ($100.00)\n  2 rolls of quarters ($20.00)\nTotal: $120.00"
+        );
     }
 
-    fn quarter() -> Denomination {
-        Denomination {
-            cents: 25,
-            singular: "quarter",
-            plural: "quarters",
-        }
+    #[test]
+    fn skim_in_bundles_or_pieces() {
+        let skim = [
+            Skim {
+                denomination: USD.denominations[quarter()].clone(),
+                count: 80,
+            },
+            Skim {
+                denomination: USD.denominations[dollar()].clone(),
+                count: 100,
+            },
+            Skim {
+                denomination: USD.denominations[dime()].clone(),
+                count: 1,
+            },
+        ];
+        assert_eq!(
+            format_skim(&skim),
+            "remove 2 rolls of quarters,1 strap of dollars,1 dime"
+        );
     }
 
-    fn dime() -> Denomination {
-        Denomination {
-            cents: 10,
-            singular: "dime",
-            plural: "dimes",
-        }
+    #[test]
+    fn till_report_suggests_skim() {
+        let till = crate::till::parse_till("0.25,50,40\n", &USD).unwrap();
+        let report = format_till_report("", &till, &USD);
+        assert!(
+            report.ends_with("Ran dry: none\nSkim: remove 1 roll of quarters"),
+            "{report}"
+        );
     }
 
-    fn dollar() -> Denomination {
-        Denomination {
-            cents: 100,
-            singular: "dollar",
-            plural: "dollars",
-        }
+    #[test]
+    fn simulation_lists_run_outs() {
+        let report = SimulationReport {
+            sales: 200,
+            short_sales: 14,
+            ran_out: vec![
+                crate::simulate::RunOut {
+                    sale: 23,
+                    denomination: USD.denominations[penny()].clone(),
+                },
+                crate::simulate::RunOut {
+                    sale: 88,
+                    denomination: USD.denominations[dime()].clone(),
+                },
+            ],
+        };
+        assert_eq!(
+            format_simulation(&report, &USD),
+            "Simulated 200 sales (USD):\n  pennies ran out at sale 23\n  dimes ran out at sale 88\nShort of change on 14 sales"
+        );
+
+        let quiet = SimulationReport {
+            sales: 5,
+            ..SimulationReport::default()
+        };
+        assert_eq!(
+            format_simulation(&quiet, &USD),
+            "Simulated 5 sales (USD):\n  nothing ran out\nShort of change on 0 sales"
+        );
     }
 
     #[test]
-    fn sample_output_format() {
-        let breakdown = vec![(quarter(), 3), (dime(), 1), (penny(), 3)];
-        assert_eq!(format_breakdown(&breakdown), "3 quarters,1 dime,3 pennies");
+    fn session_totals_list_the_pieces() {
+        let mut session = crate::Session::new(crate::CashRegister::builder().divisor(0).build());
+        session.process("2.12,3.00\n1.97,2.00\nREFUND,0.30\n");
+        assert_eq!(
+            session.summary(),
+            "Session (USD), 2 sales, 1 refund:\n  Owed      $4.09\n  Paid      $5.00\n  Change    $0.91\n  Refunded  $0.30\n  Pieces       12 (4 quarters, 1 dime, 1 nickel, 6 pennies)"
+        );
+        let empty = crate::session::SessionTotals {
+            pieces: vec![0; 5],
+            voids: 1,
+            errors: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_session(&empty, &USD),
+            "Session (USD), 0 sales, 1 voided, 1 error:\n  Owed      $0.00\n  Paid      $0.00\n  Change    $0.00\n  Pieces        0"
+        );
     }
 
     #[test]
-    fn single_denomination_singular() {
-        let breakdown = vec![(dollar(), 1)];
-        assert_eq!(format_breakdown(&breakdown), "1 dollar");
+    fn quirks_are_counted_by_kind() {
+        assert_eq!(
+            format_quirks(&[5, 4, 6, 0], 100),
+            "Quirks in 15 of 100 lines: 5 malformed, 4 underpaid, 6 whitespace, 0 encoding"
+        );
     }
 
     #[test]
-    fn single_denomination_plural() {
-        let breakdown = vec![(penny(), 5)];
-        assert_eq!(format_breakdown(&breakdown), "5 pennies");
+    fn deposit_slip_rolls_coins() {
+        let till = crate::till::parse_till("1.00,5\n0.25,83\n0.01,50\n", &USD).unwrap();
+        assert_eq!(
+            format_deposit_slip("", &till, &USD),
+            "Deposit slip (USD):\n\
+             Bills:\n  5 dollars ($5.00)\n\
+             Coins:\n  83 quarters: 2 rolls + 3 loose ($20.75)\n  50 pennies: 1 roll + 0 loose ($0.50)\n\
+             Bills total: $5.00\nCoins total: $21.25\nTotal deposit: $26.25"
+        );
+        assert_eq!(
+            format_deposit_slip("R2", &Till::new(), &USD),
+            "Deposit slip R2 (USD):\nBills:\n  none\nCoins:\n  none\n\
+             Bills total: $0.00\nCoins total: $0.00\nTotal deposit: $0.00"
+        );
     }
 
     #[test]
-    fn empty_breakdown() {
-        assert_eq!(format_breakdown(&Vec::new()), "no change");
+    fn change_stats_report() {
+        let mut stats = ChangeStats::new();
+        let exact = Transaction::new(Cents(500), Cents(500)).unwrap();
+        stats.record(&exact, &[], false, &USD);
+        let small = Transaction::new(Cents(212), Cents(300)).unwrap();
+        stats.record(
+            &small,
+            &[(quarter(), 3), (dime(), 1), (penny(), 3)],
+            false,
+            &USD,
+        );
+        let large = Transaction::new(Cents(300), Cents(2500)).unwrap();
+        stats.record(&large, &[(dollar(), 22)], true, &USD);
+        assert_eq!(
+            format_change_stats(&stats, &USD),
+            "Change stats (USD), 3 transactions:\n\
+             Change due:\n\
+             \x20 no change      1  ########################################\n\
+             \x20 under $1.00    1  ########################################\n\
+             \x20 $1.00-$4.99    0\n\
+             \x20 $5.00-$9.99    0\n\
+             \x20 $10.00-$19.99  0\n\
+             \x20 $20.00 and up  1  ########################################\n\
+             Average pieces per transaction: 9.67\n\
+             Most used:\n\
+             \x20 dollars: 22 (75.9%)\n\
+             \x20 quarters: 3 (10.3%)\n\
+             \x20 pennies: 3 (10.3%)\n\
+             \x20 dimes: 1 (3.4%)\n\
+             Random rule: 1 of 3 transactions (33.3%)"
+        );
+        assert_eq!(
+            format_change_stats(&ChangeStats::new(), &USD)
+                .lines()
+                .skip(8)
+                .collect::<Vec<_>>(),
+            [
+                "Average pieces per transaction: 0.00",
+                "Most used:",
+                "  no change given",
+                "Random rule: 0 of 0 transactions (0.0%)"
+            ]
+        );
     }
 
     #[test]
-    fn matches_exact_sample_output() {
-        // "3 quarters,1 dime,3 pennies" — note: no spaces after commas
-        let breakdown = vec![(quarter(), 3), (dime(), 1), (penny(), 3)];
-        let output = format_breakdown(&breakdown);
-        assert!(
-            !output.contains(", "),
-            "output should not have spaces after commas"
+    fn ledger_entries_balance() {
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        assert_eq!(
+            format_ledger_entry("2026-10-16", 1, &tx, None, 0, Cents::ZERO, &USD),
+            "2026-10-16 * \"line 1\"\n\
+             \x20   Assets:Cash:Drawer                    3.00 USD\n\
+             \x20   Assets:Cash:Drawer                   -0.88 USD\n\
+             \x20   Income:Sales                         -2.12 USD"
+        );
+
+        let exact = Transaction::new(Cents(500), Cents(500)).unwrap();
+        let split = SplitTender {
+            parts: vec![
+                ("card".to_string(), Cents(300)),
+                ("cash".to_string(), Cents(200)),
+            ],
+            returned: Vec::new(),
+        };
+        assert_eq!(
+            format_ledger_entry("2026-10-16", 2, &exact, Some(&split), 0, Cents::ZERO, &USD),
+            "2026-10-16 * \"line 2\"\n\
+             \x20   Assets:Tender:Card                    3.00 USD\n\
+             \x20   Assets:Cash:Drawer                    2.00 USD\n\
+             \x20   Income:Sales                         -5.00 USD"
+        );
+
+        // The drawer was 3 cents short of the change due.
+        assert_eq!(
+            format_ledger_entry("2026-10-16", 3, &tx, None, 3, Cents::ZERO, &USD),
+            "2026-10-16 * \"line 3\"\n\
+             \x20   Assets:Cash:Drawer                    3.00 USD\n\
+             \x20   Assets:Cash:Drawer                   -0.85 USD\n\
+             \x20   Income:Sales                         -2.12 USD\n\
+             \x20   Liabilities:ChangeOwed               -0.03 USD"
+        );
+
+        // Eight cents rounded up for charity.
+        assert_eq!(
+            format_ledger_entry("2026-10-16", 4, &tx, None, 0, Cents(8), &USD),
+            "2026-10-16 * \"line 4\"\n\
+             \x20   Assets:Cash:Drawer                    3.00 USD\n\
+             \x20   Assets:Cash:Drawer                   -0.80 USD\n\
+             \x20   Income:Sales                         -2.12 USD\n\
+             \x20   Liabilities:Donations                -0.08 USD"
         );
-        assert_eq!(output, "3 quarters,1 dime,3 pennies");
     }
 
     #[test]
-    fn format_amount_usd() {
-        assert_eq!(format_amount(213, "$"), "$2.13");
-        assert_eq!(format_amount(5, "$"), "$0.05");
-        assert_eq!(format_amount(300, "$"), "$3.00");
-        assert_eq!(format_amount(0, "$"), "$0.00");
-        assert_eq!(format_amount(10000, "$"), "$100.00");
+    fn heft_suffix_and_total() {
+        let heft = Heft {
+            milligrams: 26_778,
+            cubic_mm: 4_066,
+        };
+        assert_eq!(format_heft(heft), " (26.8 g, 4.1 cm³)");
+        assert_eq!(
+            format_heft_total(heft, 1, 0),
+            "Change handed over: 26.8 g, 4.1 cm³ on 1 line"
+        );
+        assert_eq!(
+            format_heft_total(heft, 3, 2),
+            "Change handed over: 26.8 g, 4.1 cm³ on 3 lines (2 not weighed: no weight for a piece)"
+        );
     }
 
     #[test]
-    fn format_amount_eur() {
-        assert_eq!(format_amount(150, "€"), "€1.50");
-        assert_eq!(format_amount(1, "€"), "€0.01");
+    fn output_diff_lists_changed_lines() {
+        let before = "3 quarters,1 dime,3 pennies\n1 penny\nno change\n";
+        assert_eq!(format_output_diff(before, before), None);
+        assert_eq!(
+            format_output_diff(before, "8 dimes,1 nickel,3 pennies\n1 penny\n").unwrap(),
+            "output line 1:\n  \
+             - 3 quarters,1 dime,3 pennies\n  \
+             + 8 dimes,1 nickel,3 pennies\n\
+             output line 3:\n  \
+             - no change\n\
+             2 of 3 output lines changed"
+        );
     }
 
     #[test]
-    fn verbose_greedy() {
-        let tx = Transaction {
-            owed_cents: 212,
-            paid_cents: 300,
-            change_cents: 88,
-        };
-        let breakdown = vec![(quarter(), 3), (dime(), 1), (penny(), 3)];
+    fn audit_shows_each_mismatch_both_ways() {
+        let mut audit = Audit::new("3 quarters\n3 pennies\n");
+        audit.check(Some(1), "3 quarters");
+        let clean = Audit::new("3 quarters\n");
         assert_eq!(
-            format_verbose(&tx, &breakdown, &crate::currency::USD, false),
-            "Owed $2.12, Paid $3.00 -> 3 quarters,1 dime,3 pennies",
+            format_audit(&audit.finish()),
+            "output line 2:\n  \
+             recorded: 3 pennies\n  \
+             expected: nothing\n\
+             1 mismatch in 1 result"
+        );
+
+        let mut audit = clean;
+        audit.check(Some(1), "3 quarters");
+        audit.check(Some(4), "1 nickel");
+        assert_eq!(
+            format_audit(&audit.finish()),
+            "line 4, output line 2:\n  \
+             recorded: nothing (the output ended)\n  \
+             expected: 1 nickel\n\
+             1 mismatch in 2 results"
         );
+
+        let mut audit = Audit::new("1 penny\n");
+        audit.check(Some(1), "1 penny");
+        assert_eq!(format_audit(&audit.finish()), "No mismatches in 1 result");
     }
 
+    #[cfg(feature = "signing")]
     #[test]
-    fn verbose_random() {
-        let tx = Transaction {
-            owed_cents: 333,
-            paid_cents: 500,
-            change_cents: 167,
+    fn verify_lists_each_flagged_line() {
+        use crate::signing::Flagged;
+        let report = VerifyReport {
+            lines: 9,
+            flagged: vec![
+                Flagged {
+                    line: 2,
+                    problem: Problem::Altered,
+                },
+                Flagged {
+                    line: 4,
+                    problem: Problem::OutOfSequence {
+                        expected: 4,
+                        found: 5,
+                    },
+                },
+                Flagged {
+                    line: 7,
+                    problem: Problem::Unlinked,
+                },
+                Flagged {
+                    line: 9,
+                    problem: Problem::Unended,
+                },
+            ],
         };
-        let breakdown = vec![(dollar(), 1), (quarter(), 2), (penny(), 17)];
         assert_eq!(
-            format_verbose(&tx, &breakdown, &crate::currency::USD, true),
-            "Owed $3.33, Paid $5.00 -> 1 dollar,2 quarters,17 pennies (random)",
+            format_verify(&report),
+            "line 2: altered (its signature doesn't match)\n\
+             line 4: out of sequence (expected #4, found #5): lines before it are missing or moved\n\
+             line 7: starts a run that doesn't follow on from the line before it: runs before it are missing or moved\n\
+             line 9: its run has no end record: lines after it are missing, or the run was cut short\n\
+             4 problems in 9 lines"
         );
+        let clean = VerifyReport {
+            lines: 1,
+            flagged: Vec::new(),
+        };
+        assert_eq!(format_verify(&clean), "All 1 line verified");
     }
 
     #[test]
-    fn verbose_no_change() {
-        let tx = Transaction {
-            owed_cents: 500,
-            paid_cents: 500,
-            change_cents: 0,
+    fn csv_rows_pivot_the_breakdown() {
+        assert_eq!(
+            format_csv_header(&USD),
+            "line,owed,paid,change,dollars,quarters,dimes,nickels,pennies,error"
+        );
+        let transaction = Transaction::new(Cents(212), Cents(300)).unwrap();
+        assert_eq!(
+            format_csv(1, &transaction, &[(1, 3), (2, 1), (4, 3)], &USD),
+            "1,2.12,3.00,0.88,0,3,1,0,3,"
+        );
+        assert_eq!(
+            format_refund_csv(4, Cents(212), &[(0, 2), (2, 1), (4, 2)], &USD),
+            "4,-2.12,0.00,2.12,2,0,1,0,2,"
+        );
+        assert_eq!(
+            format_error_csv(3, Some((Cents(500), Cents(200))), "E002", &USD),
+            "3,5.00,2.00,,,,,,,E002"
+        );
+        assert_eq!(format_error_csv(5, None, "E003", &USD), "5,,,,,,,,,E003");
+        assert_eq!(csv_field("1 euro, coins"), "\"1 euro, coins\"");
+    }
+
+    #[test]
+    fn cash_report_lists_every_entry() {
+        let mut report = CashReport::new(Cents(1_000_000));
+        assert_eq!(
+            format_cash_report(&report, &USD),
+            "No cash transactions of $10000.00 or more to report"
+        );
+        let fields = Fields {
+            id: Some("T1001".into()),
+            time: Some("2026-10-16T14:05:00Z".into()),
+            ..Fields::default()
         };
+        let sale = Transaction::new(Cents(1_150_000), Cents(1_200_000)).unwrap();
+        report.check(4, &sale, false, &fields);
+        let refund = Transaction::new(Cents::ZERO, Cents(1_150_000)).unwrap();
+        report.check(9, &refund, true, &Fields::default());
         assert_eq!(
-            format_verbose(&tx, &Vec::new(), &crate::currency::USD, false),
-            "Owed $5.00, Paid $5.00 -> no change",
+            format_cash_report(&report, &USD),
+            "Cash transactions of $10000.00 or more to report: 2, $23500.00 in all\n  \
+             line 4: $12000.00 received (id T1001, 2026-10-16T14:05:00Z)\n  \
+             line 9: $11500.00 paid out for a refund"
         );
     }
 
     #[test]
-    fn verbose_eur_uses_euro_symbol() {
-        let tx = Transaction {
-            owed_cents: 150,
-            paid_cents: 200,
-            change_cents: 50,
-        };
-        let breakdown = vec![(
-            Denomination {
-                cents: 50,
-                singular: "50 cent coin",
-                plural: "50 cent coins",
+    fn loyalty_points_and_summary() {
+        assert_eq!(format_points(12), " (12 points)");
+        assert_eq!(format_points(-1), " (-1 point)");
+
+        let mut loyalty = Loyalty::new("1".parse().unwrap());
+        loyalty.record(Some("C42"), Cents(212), false);
+        loyalty.record(Some("C42"), Cents(500), true);
+        loyalty.record(Some("C7"), Cents(1_250), false);
+        loyalty.record(None, Cents(99), false);
+        assert_eq!(
+            format_loyalty(&loyalty, &USD),
+            "Loyalty points at 1 per $1.00: 9 on 4 transactions\n  \
+             C42     -3 points on 2 transactions (-$2.88)\n  \
+             C7      12 points on 1 transaction ($12.50)\n  \
+             (none)   0 points on 1 transaction ($0.99)"
+        );
+    }
+
+    #[test]
+    fn findings_are_listed_by_line() {
+        assert_eq!(format_findings(&[], &USD), None);
+        let findings = [
+            Finding::UnderThreshold {
+                line: 9,
+                paid: Cents(995_000),
+                threshold: Cents(1_000_000),
             },
+            Finding::Repeated {
+                amount: Cents(499),
+                refund: false,
+                count: 5,
+                lines: 3..=7,
+            },
+        ];
+        assert_eq!(
+            format_findings(&findings, &USD).unwrap(),
+            "warning: 2 suspicious patterns for loss-prevention review\n  \
+             $4.99 rung up 5 times in a row (lines 3-7)\n  \
+             $9950.00 paid, just under the $10000.00 reporting threshold (line 9)"
+        );
+
+        let tx = Transaction::new(Cents(499), Cents(500)).unwrap();
+        let json = format_json(
+            3,
+            &tx,
+            &[(4, 1)],
+            &USD,
+            false,
+            0,
+            0,
+            &[],
+            false,
+            None,
+            None,
+            None,
+            Cents::ZERO,
+            None,
+            &[Pattern::RepeatedAmount],
+            None,
+        );
+        assert!(
+            json.ends_with(r#""suspicious":["repeated-amount"]}"#),
+            "{json}"
+        );
+    }
+
+    #[test]
+    fn donations_are_noted_and_summed() {
+        assert_eq!(format_donation(Cents::ZERO, &USD), "");
+        assert_eq!(format_donation(Cents(8), &USD), " (donated $0.08)");
+        assert_eq!(
+            format_donations(Cents(42), 5, 7, Cents(10), &USD),
+            "Donated $0.42 from 5 of 7 sales, rounding change down to $0.10"
+        );
+
+        let mut totals = Totals::new();
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        totals.record(1, &tx, &[(1, 3)], 0, Cents(13), &USD);
+        let text = format_totals(&totals, &USD);
+        assert!(text.contains("  Donated       $0.13\n"), "{text}");
+        assert!(
+            text.ends_with("Balanced: paid - owed = change given + short + donated - over"),
+            "{text}"
+        );
+
+        let json = format_json(
             1,
-        )];
+            &tx,
+            &[(1, 3)],
+            &USD,
+            false,
+            0,
+            0,
+            &[],
+            false,
+            None,
+            None,
+            None,
+            Cents(13),
+            None,
+            &[],
+            None,
+        );
         assert_eq!(
-            format_verbose(&tx, &breakdown, &crate::currency::EUR, false),
-            "Owed €1.50, Paid €2.00 -> 1 50 cent coin",
+            json,
+            r#"{"change":[{"cents":25,"count":3,"name":"quarters"}],"donated":"0.13","iou_cents":0,"line":1,"owed":"2.12","paid":"3.00","random":false,"shortfall_cents":0}"#
+        );
+    }
+
+    #[test]
+    fn empty_bank_order() {
+        assert_eq!(
+            format_bank_order(&[], &USD),
+            "Bank order (USD):\n  nothing to order\nTotal: $0.00"
+        );
+    }
+
+    #[test]
+    fn reconciliation_lists_over_and_short() {
+        let discrepancies = [
+            Discrepancy {
+                denomination: USD.denominations[dollar()].clone(),
+                expected: 5,
+                counted: 6,
+            },
+            Discrepancy {
+                denomination: USD.denominations[quarter()].clone(),
+                expected: 40,
+                counted: 38,
+            },
+        ];
+        assert_eq!(
+            format_reconciliation("", &discrepancies, &USD),
+            "Reconciliation (USD):\n  dollars: expected 5, counted 6 (over $1.00)\n  \
+             quarters: expected 40, counted 38 (short $0.50)\nNet: over $0.50"
+        );
+        assert_eq!(
+            format_reconciliation("R2", &[], &USD),
+            "Reconciliation R2 (USD):\n  every denomination matches\nNet: balanced"
+        );
+    }
+
+    #[test]
+    fn till_report_names_register() {
+        let report = format_till_report("R2", &Till::new(), &EUR);
+        assert!(report.starts_with("Closing drawer R2 (EUR):\n"), "{report}");
+    }
+
+    #[test]
+    fn json_line() {
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        let breakdown = vec![(quarter(), 3), (penny(), 1)];
+        assert_eq!(
+            format_json(
+                4,
+                &tx,
+                &breakdown,
+                &USD,
+                false,
+                12,
+                0,
+                &[],
+                false,
+                None,
+                None,
+                None,
+                Cents::ZERO,
+                None,
+                &[],
+                None
+            ),
+            r#"{"change":[{"cents":25,"count":3,"name":"quarters"},{"cents":1,"count":1,"name":"penny"}],"iou_cents":0,"line":4,"owed":"2.12","paid":"3.00","random":false,"shortfall_cents":12}"#
+        );
+
+        let tx = Transaction::new(Cents(197), Cents(20_000)).unwrap();
+        assert_eq!(
+            format_json(
+                1,
+                &tx,
+                &[],
+                &USD,
+                false,
+                0,
+                0,
+                &[],
+                true,
+                None,
+                None,
+                None,
+                Cents::ZERO,
+                None,
+                &[],
+                None
+            ),
+            r#"{"change":[],"iou_cents":0,"line":1,"overpaid":true,"owed":"1.97","paid":"200.00","random":false,"shortfall_cents":0}"#
+        );
+    }
+
+    #[test]
+    fn underpayment_lines() {
+        let breakdown = vec![(dollar(), 2)];
+        assert_eq!(
+            format_underpayment(Cents(200), &breakdown, &USD),
+            "insufficient payment: short $2.00 (2 dollars)"
+        );
+        assert_eq!(
+            format_underpayment_json(4, Cents(500), Cents(300), &breakdown, &USD),
+            r#"{"code":"E002","line":4,"owed":"5.00","paid":"3.00","short":"2.00","short_pieces":[{"cents":100,"count":2,"name":"dollars"}]}"#
+        );
+    }
+
+    #[test]
+    fn error_placeholder_lines() {
+        assert_eq!(
+            format_error_json(2, "E003", "line 2: expected owed,paid"),
+            r#"{"code":"E003","error":"line 2: expected owed,paid","line":2}"#
+        );
+    }
+
+    #[test]
+    fn refunds_are_labeled_as_payouts() {
+        let breakdown = [(0, 2), (2, 1), (4, 2)];
+        assert_eq!(
+            format_refund(Cents(212), &breakdown, &USD),
+            "Refund $2.12, pay out 2 dollars,1 dime,2 pennies"
+        );
+        assert_eq!(
+            format_refund_json(3, Cents(212), &[(0, 2)], &USD, 12, 0, None, &[], None),
+            r#"{"iou_cents":0,"line":3,"payout":[{"cents":100,"count":2,"name":"dollars"}],"refund":"2.12","shortfall_cents":12}"#
+        );
+        // The drawer was 12 cents short of the refund.
+        assert_eq!(
+            format_ledger_refund("2026-10-16", 3, Cents(212), 12, &USD),
+            "2026-10-16 * \"line 3 refund\"\n\
+             \x20   Income:Sales                          2.12 USD\n\
+             \x20   Assets:Cash:Drawer                   -2.00 USD\n\
+             \x20   Liabilities:ChangeOwed               -0.12 USD"
+        );
+    }
+
+    #[test]
+    fn extra_fields_carried_through() {
+        let extra = ["notes".to_string(), "aisle=4".to_string()];
+        assert_eq!(format_extra(&extra), "\tnotes,aisle=4");
+        assert_eq!(format_extra(&[]), "");
+
+        let tx = Transaction::new(Cents(300), Cents(300)).unwrap();
+        assert_eq!(
+            format_json(
+                1,
+                &tx,
+                &[],
+                &USD,
+                false,
+                0,
+                0,
+                &extra,
+                false,
+                None,
+                None,
+                None,
+                Cents::ZERO,
+                None,
+                &[],
+                None
+            ),
+            r#"{"change":[],"extra":["notes","aisle=4"],"iou_cents":0,"line":1,"owed":"3.00","paid":"3.00","random":false,"shortfall_cents":0}"#
+        );
+    }
+
+    #[test]
+    fn rounded_amounts_are_noted() {
+        let rounded = Rounded {
+            mode: RoundingMode::HalfEven,
+            owed: Some("2.125".to_string()),
+            paid: None,
+        };
+        assert_eq!(format_rounded(&rounded), " (owed 2.125 rounded half-even)");
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        let json = format_json(
+            1,
+            &tx,
+            &[],
+            &USD,
+            false,
+            0,
+            0,
+            &[],
+            false,
+            Some(&rounded),
+            None,
+            None,
+            Cents::ZERO,
+            None,
+            &[],
+            None,
+        );
+        assert!(
+            json.contains(r#""rounded":{"mode":"half-even","owed":"2.125"}"#),
+            "{json}"
+        );
+    }
+
+    #[test]
+    fn split_tender_notes() {
+        let split = SplitTender {
+            parts: vec![
+                ("card".to_string(), Cents(100)),
+                ("cash".to_string(), Cents(200)),
+            ],
+            returned: Vec::new(),
+        };
+        assert_eq!(
+            format_split_tender(&split, &USD),
+            " (paid card $1.00 + cash $2.00)"
+        );
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        let json = format_json(
+            1,
+            &tx,
+            &[],
+            &USD,
+            false,
+            0,
+            0,
+            &[],
+            false,
+            None,
+            Some(&split),
+            None,
+            Cents::ZERO,
+            None,
+            &[],
+            None,
+        );
+        assert!(
+            json.contains(
+                r#""tender":[{"amount":"1.00","kind":"card"},{"amount":"2.00","kind":"cash"}]"#
+            ),
+            "{json}"
+        );
+        assert!(!json.contains("back_on_card"), "{json}");
+
+        let gift = SplitTender {
+            parts: vec![
+                ("gift".to_string(), Cents(212)),
+                ("cash".to_string(), Cents(100)),
+            ],
+            returned: vec![("gift".to_string(), Cents(88))],
+        };
+        assert_eq!(
+            format_split_tender(&gift, &USD),
+            " (paid gift $2.12 + cash $1.00, $0.88 back on gift)"
+        );
+        let json = format_json(
+            1,
+            &tx,
+            &[],
+            &USD,
+            false,
+            0,
+            0,
+            &[],
+            false,
+            None,
+            Some(&gift),
+            None,
+            Cents::ZERO,
+            None,
+            &[],
+            None,
+        );
+        assert!(
+            json.contains(r#""back_on_card":[{"amount":"0.88","kind":"gift"}]"#),
+            "{json}"
+        );
+    }
+
+    #[test]
+    fn foreign_tender_notes() {
+        let foreign = ForeignTender {
+            currency: "EUR".to_string(),
+            paid: Cents(200),
+            rate: "1.08".parse().unwrap(),
+        };
+        assert_eq!(
+            format_foreign_tender(&foreign, "€", None),
+            " (paid €2.00 at 1.08)"
+        );
+        assert_eq!(
+            format_foreign_tender(&foreign, "€", Some(Cents(4))),
+            " (paid €2.00 at 1.08, change €0.04)"
+        );
+        let tx = Transaction::new(Cents(212), Cents(216)).unwrap();
+        let json = format_json(
+            1,
+            &tx,
+            &[],
+            &EUR,
+            false,
+            0,
+            0,
+            &[],
+            false,
+            None,
+            None,
+            Some(&foreign),
+            Cents::ZERO,
+            None,
+            &[],
+            None,
+        );
+        assert!(
+            json.contains(
+                r#""change_currency":"EUR","foreign":{"currency":"EUR","paid":"2.00","rate":"1.08"}"#
+            ),
+            "{json}"
         );
     }
 }
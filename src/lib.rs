@@ -1,6 +1,80 @@
+//! Change-making for cash registers.
+//!
+//! With the default `std` feature off, the library is `no_std` + `alloc`:
+//! parsing, change-making, tills, and formatting all work, while file IO, the
+//! journal, `SharedTill`, and entropy-seeded RNGs need `std`.
+//!
+//! [`rpc`] drives a session over JSON-RPC, for hosts that keep the register
+//! running as a subprocess, and [`session`] keeps a register's history and
+//! running totals for hosts that embed it. [`escpos`] formats receipts for thermal
+//! printers, and [`export`] writes a day's sales as QIF or OFX.
+//!
+//! The `proptest` feature adds [`arbitrary`]: proptest strategies for
+//! transactions, currencies, and breakdowns. The `decimal` feature adds
+//! [`decimal`]: conversions between `Cents` and `rust_decimal::Decimal`,
+//! and the `rusty_money` feature between `Cents` and `rusty_money::Money`.
+//! The `tokio` feature adds [`stream`]: `CashRegister::process_stream` over
+//! an async reader. The `sqlite` feature adds [`log_db`]: every transaction,
+//! breakdown, error, and drawer change logged to an SQLite database. The `tui`
+//! feature adds [`dashboard`]: a ratatui view of the drawers, the latest
+//! transactions, and error counts. The `signing` feature, on with `cli`,
+//! adds [`signing`]: HMAC-chained signatures for output and journal lines.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+pub mod audit;
+pub mod cash_report;
+pub mod config;
 pub mod currency;
+#[cfg(feature = "tui")]
+pub mod dashboard;
+#[cfg(feature = "decimal")]
+pub mod decimal;
+pub mod dedupe;
 pub mod error;
+pub mod escpos;
+pub mod exchange;
+pub mod export;
 pub mod format;
+#[cfg(feature = "sqlite")]
+pub mod log_db;
+pub mod loyalty;
+pub mod metrics;
+pub mod money;
+#[cfg(feature = "parquet")]
+pub mod parquet_file;
 pub mod parse;
+pub mod pictogram;
+pub mod policy;
+pub mod register;
+pub mod rounding;
+pub mod rpc;
 pub mod rules;
+pub mod session;
+#[cfg(feature = "signing")]
+pub mod signing;
+pub mod simulate;
+pub mod spoken;
+pub mod stats;
 pub mod strategy;
+#[cfg(feature = "tokio")]
+pub mod stream;
+pub mod suspicious;
+pub mod tender;
+pub mod till;
+pub mod totals;
+pub mod verify;
+pub mod void;
+#[cfg(feature = "webhook")]
+pub mod webhook;
+pub mod weight;
+
+pub use config::Config;
+pub use policy::ChangePolicy;
+pub use register::{CashRegister, CashRegisterBuilder};
+pub use session::Session;
+pub use strategy::{make_change, StrategyKind};
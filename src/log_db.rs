@@ -0,0 +1,428 @@
+//! Transaction logging to SQLite, behind the `sqlite` feature, so a day's
+//! sales can be queried and reconciled without parsing output files.
+//!
+//! Each [`LogDb::open`] starts a new row in `runs`; everything logged after
+//! that carries its `run_id`. A refund is a transaction with a negative
+//! `owed_cents` and the amount paid out as its `change_cents`:
+//!
+//! ```text
+//! runs          (id, started_at, currency)
+//! transactions  (run_id, line, register, owed_cents, paid_cents, change_cents,
+//!                random, shortfall_cents)
+//! change_pieces (run_id, line, cents, count)
+//! errors        (run_id, line, code, message)
+//! till_deltas   (run_id, line, register, event, cents, delta)
+//! ```
+//!
+//! A `VOID` is a row of its own on the `VOID`'s line, the voided row with
+//! its amounts and pieces negated, so sums over a run net it out; `voids`
+//! says which line it took back:
+//!
+//! ```text
+//! voids         (run_id, line, voided_line)
+//! ```
+//!
+//! A run is one SQLite transaction, committed by [`LogDb::finish`], so a
+//! database never holds half a run.
+//!
+//! ```ignore
+//! let mut log = LogDb::open("register.db", &USD)?;
+//! log.record_sale(1, "", &tx, &[(25, 3), (10, 1), (1, 3)], false, 0)?;
+//! log.finish()?;
+//! ```
+
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+use rusqlite::{params, Connection};
+
+use crate::currency::Currency;
+use crate::error::CashRegisterError;
+use crate::money::Cents;
+use crate::parse::Transaction;
+use crate::till::{rfc3339_utc, JournalEvent};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS runs (
+        id INTEGER PRIMARY KEY,
+        started_at TEXT NOT NULL,
+        currency TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS transactions (
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        line INTEGER NOT NULL,
+        register TEXT NOT NULL,
+        owed_cents INTEGER NOT NULL,
+        paid_cents INTEGER NOT NULL,
+        change_cents INTEGER NOT NULL,
+        random INTEGER NOT NULL,
+        shortfall_cents INTEGER NOT NULL,
+        PRIMARY KEY (run_id, line)
+    );
+    CREATE TABLE IF NOT EXISTS change_pieces (
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        line INTEGER NOT NULL,
+        cents INTEGER NOT NULL,
+        count INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS errors (
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        line INTEGER,
+        code TEXT NOT NULL,
+        message TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS till_deltas (
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        line INTEGER NOT NULL,
+        register TEXT NOT NULL,
+        event TEXT NOT NULL,
+        cents INTEGER NOT NULL,
+        delta INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS voids (
+        run_id INTEGER NOT NULL REFERENCES runs(id),
+        line INTEGER NOT NULL,
+        voided_line INTEGER NOT NULL
+    );
+";
+
+/// An open log database with one run in progress.
+pub struct LogDb {
+    conn: Connection,
+    run_id: i64,
+}
+
+impl LogDb {
+    /// Open the database at `path`, creating it and its tables if needed,
+    /// and start a run in `currency`.
+    pub fn open(path: impl AsRef<Path>, currency: &Currency) -> Result<Self, CashRegisterError> {
+        let conn = Connection::open(path).map_err(sqlite_error)?;
+        conn.execute_batch(SCHEMA).map_err(sqlite_error)?;
+        conn.execute_batch("BEGIN").map_err(sqlite_error)?;
+        conn.execute(
+            "INSERT INTO runs (started_at, currency) VALUES (?1, ?2)",
+            params![rfc3339_utc(SystemTime::now()), currency.name.as_ref()],
+        )
+        .map_err(sqlite_error)?;
+        let run_id = conn.last_insert_rowid();
+        Ok(Self { conn, run_id })
+    }
+
+    /// This run's `runs.id`.
+    pub fn run_id(&self) -> i64 {
+        self.run_id
+    }
+
+    /// Log a sale and the change handed back, as `(cents, count)` pieces.
+    pub fn record_sale(
+        &mut self,
+        line: usize,
+        register: &str,
+        transaction: &Transaction,
+        change: &[(u32, u32)],
+        is_random: bool,
+        shortfall_cents: i64,
+    ) -> Result<(), CashRegisterError> {
+        let amounts = [
+            transaction.owed_cents().0 as i64,
+            transaction.paid_cents().0 as i64,
+            transaction.change_cents().0 as i64,
+        ];
+        self.insert_transaction(line, register, amounts, is_random, shortfall_cents)?;
+        self.insert_pieces(line, change)
+    }
+
+    /// Log a refund of `amount` and the pieces paid out for it.
+    pub fn record_refund(
+        &mut self,
+        line: usize,
+        register: &str,
+        amount: Cents,
+        payout: &[(u32, u32)],
+        shortfall_cents: i64,
+    ) -> Result<(), CashRegisterError> {
+        let amounts = [-(amount.0 as i64), 0, amount.0 as i64];
+        self.insert_transaction(line, register, amounts, false, shortfall_cents)?;
+        self.insert_pieces(line, payout)
+    }
+
+    /// Log the `VOID` on `line` taking back the sale or refund logged on
+    /// `voided`: that row and its pieces again, negated, on `line`.
+    pub fn record_void(&mut self, line: usize, voided: usize) -> Result<(), CashRegisterError> {
+        let args = params![self.run_id, line, voided];
+        for sql in [
+            "INSERT INTO transactions (run_id, line, register, owed_cents, paid_cents,
+             change_cents, random, shortfall_cents)
+             SELECT run_id, ?2, register, -owed_cents, -paid_cents, -change_cents, random,
+             -shortfall_cents FROM transactions WHERE run_id = ?1 AND line = ?3",
+            "INSERT INTO change_pieces (run_id, line, cents, count)
+             SELECT run_id, ?2, cents, -count FROM change_pieces WHERE run_id = ?1 AND line = ?3",
+            "INSERT INTO voids (run_id, line, voided_line) VALUES (?1, ?2, ?3)",
+        ] {
+            self.conn
+                .prepare_cached(sql)
+                .and_then(|mut insert| insert.execute(args))
+                .map_err(sqlite_error)?;
+        }
+        Ok(())
+    }
+
+    /// Insert a `transactions` row with `[owed, paid, change]` cents.
+    fn insert_transaction(
+        &mut self,
+        line: usize,
+        register: &str,
+        [owed, paid, change]: [i64; 3],
+        is_random: bool,
+        shortfall_cents: i64,
+    ) -> Result<(), CashRegisterError> {
+        self.conn
+            .prepare_cached(
+                "INSERT INTO transactions (run_id, line, register, owed_cents, paid_cents,
+                 change_cents, random, shortfall_cents) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            )
+            .and_then(|mut insert| {
+                insert.execute(params![
+                    self.run_id,
+                    line,
+                    register,
+                    owed,
+                    paid,
+                    change,
+                    is_random,
+                    shortfall_cents,
+                ])
+            })
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    fn insert_pieces(
+        &mut self,
+        line: usize,
+        change: &[(u32, u32)],
+    ) -> Result<(), CashRegisterError> {
+        let mut insert = self
+            .conn
+            .prepare_cached(
+                "INSERT INTO change_pieces (run_id, line, cents, count) VALUES (?1, ?2, ?3, ?4)",
+            )
+            .map_err(sqlite_error)?;
+        for &(cents, count) in change {
+            insert
+                .execute(params![self.run_id, line, cents, count])
+                .map_err(sqlite_error)?;
+        }
+        Ok(())
+    }
+
+    /// Log a failed line, or an error that isn't about one.
+    pub fn record_error(&mut self, error: &CashRegisterError) -> Result<(), CashRegisterError> {
+        self.conn
+            .prepare_cached(
+                "INSERT INTO errors (run_id, line, code, message) VALUES (?1, ?2, ?3, ?4)",
+            )
+            .and_then(|mut insert| {
+                insert.execute(params![
+                    self.run_id,
+                    error.line(),
+                    error.code(),
+                    error.to_string()
+                ])
+            })
+            .map_err(sqlite_error)?;
+        Ok(())
+    }
+
+    /// Log a drawer mutation, one row per denomination it moved: pieces in
+    /// count up, pieces out count down.
+    pub fn record_till(
+        &mut self,
+        line: usize,
+        register: &str,
+        event: JournalEvent,
+    ) -> Result<(), CashRegisterError> {
+        let mut deltas: Vec<(u32, i64)> = Vec::new();
+        match event {
+            JournalEvent::Sale { tendered, change } => {
+                deltas.extend(tendered.iter().map(|&(c, n)| (c, i64::from(n))));
+                deltas.extend(change.iter().map(|&(c, n)| (c, -i64::from(n))));
+            }
+            JournalEvent::Refund { paid_out } => {
+                deltas.extend(paid_out.iter().map(|&(c, n)| (c, -i64::from(n))));
+            }
+            JournalEvent::Restock { cents, count } => deltas.push((cents, i64::from(count))),
+            JournalEvent::Pickup { cents, count } => deltas.push((cents, -i64::from(count))),
+            JournalEvent::Void { tendered, change } => {
+                deltas.extend(change.iter().map(|&(c, n)| (c, i64::from(n))));
+                deltas.extend(tendered.iter().map(|&(c, n)| (c, -i64::from(n))));
+            }
+        }
+        let mut insert = self
+            .conn
+            .prepare_cached(
+                "INSERT INTO till_deltas (run_id, line, register, event, cents, delta)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )
+            .map_err(sqlite_error)?;
+        for (cents, delta) in deltas {
+            insert
+                .execute(params![
+                    self.run_id,
+                    line,
+                    register,
+                    event.name(),
+                    cents,
+                    delta
+                ])
+                .map_err(sqlite_error)?;
+        }
+        Ok(())
+    }
+
+    /// Commit the run. Dropping a `LogDb` without finishing rolls it back.
+    pub fn finish(self) -> Result<(), CashRegisterError> {
+        self.conn.execute_batch("COMMIT").map_err(sqlite_error)
+    }
+}
+
+/// SQLite failures are I/O errors as far as callers are concerned.
+fn sqlite_error(e: rusqlite::Error) -> CashRegisterError {
+    CashRegisterError::Io(io::Error::other(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::USD;
+
+    fn count(log: &LogDb, table: &str) -> i64 {
+        log.conn
+            .query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn logs_sales_errors_and_till_deltas() {
+        let mut log = LogDb::open(":memory:", &USD).unwrap();
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        let change = [(25, 3), (10, 1), (1, 3)];
+        log.record_sale(1, "", &tx, &change, false, 0).unwrap();
+        log.record_till(
+            1,
+            "",
+            JournalEvent::Sale {
+                tendered: &[(100, 3)],
+                change: &change,
+            },
+        )
+        .unwrap();
+        log.record_till(
+            2,
+            "",
+            JournalEvent::Pickup {
+                cents: 100,
+                count: 2,
+            },
+        )
+        .unwrap();
+        let error = CashRegisterError::MalformedLine {
+            line: 3,
+            detail: "expected \"owed,paid\"".into(),
+        };
+        log.record_error(&error).unwrap();
+        log.record_refund(4, "", Cents(200), &[(100, 2)], 0)
+            .unwrap();
+        let refund = JournalEvent::Refund {
+            paid_out: &[(100, 2)],
+        };
+        log.record_till(4, "", refund).unwrap();
+
+        assert_eq!(count(&log, "transactions"), 2);
+        assert_eq!(count(&log, "change_pieces"), 4);
+        assert_eq!(count(&log, "till_deltas"), 6);
+        let (owed, random): (i64, bool) = log
+            .conn
+            .query_row(
+                "SELECT owed_cents, random FROM transactions WHERE line = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((owed, random), (212, false));
+        let net: i64 = log
+            .conn
+            .query_row(
+                "SELECT SUM(cents * delta) FROM till_deltas WHERE run_id = ?1",
+                [log.run_id()],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(net, 300 - 88 - 200 - 200);
+        let event: String = log
+            .conn
+            .query_row("SELECT event FROM till_deltas WHERE line = 4", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(event, "refund");
+        let (line, code): (usize, String) = log
+            .conn
+            .query_row("SELECT line, code FROM errors", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((line, code.as_str()), (3, "E003"));
+        let (owed, change): (i64, i64) = log
+            .conn
+            .query_row(
+                "SELECT owed_cents, change_cents FROM transactions WHERE line = 4",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((owed, change), (-200, 200));
+
+        log.record_void(5, 1).unwrap();
+        let sums: (i64, i64, i64) = log
+            .conn
+            .query_row(
+                "SELECT SUM(owed_cents), SUM(paid_cents), SUM(change_cents) FROM transactions",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(sums, (-200, 0, 200));
+        let pieces: i64 = log
+            .conn
+            .query_row("SELECT SUM(cents * count) FROM change_pieces", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(pieces, 200);
+        assert_eq!(count(&log, "voids"), 1);
+    }
+
+    #[test]
+    fn runs_commit_on_finish_and_roll_back_otherwise() {
+        let path = std::env::temp_dir().join(format!("log_db_test_{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let tx = Transaction::new(Cents(100), Cents(100)).unwrap();
+
+        let mut log = LogDb::open(&path, &USD).unwrap();
+        log.record_sale(1, "", &tx, &[], false, 0).unwrap();
+        log.finish().unwrap();
+        let mut abandoned = LogDb::open(&path, &USD).unwrap();
+        abandoned.record_sale(1, "", &tx, &[], false, 0).unwrap();
+        drop(abandoned);
+
+        let log = LogDb::open(&path, &USD).unwrap();
+        assert_eq!(log.run_id(), 2);
+        assert_eq!(count(&log, "transactions"), 1);
+        drop(log);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
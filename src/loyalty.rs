@@ -0,0 +1,208 @@
+//! Loyalty points earned on each sale, at a rate per whole unit of the
+//! currency spent, and tallied per customer from the `customer=` field.
+//!
+//! Points are whole and rounded down per transaction: at 1 point per
+//! dollar, a $2.99 sale earns 2. A refund takes back what the same amount
+//! would have earned, so a returned sale nets to nothing.
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+};
+use core::fmt;
+use core::str::FromStr;
+
+use crate::money::Cents;
+
+/// Points per whole unit spent, to two decimal places: `1` per dollar,
+/// `2.5` per euro.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PointsRate {
+    hundredths: u32,
+}
+
+impl PointsRate {
+    /// Points earned on `amount`, rounded down.
+    pub fn points(self, amount: Cents) -> u64 {
+        (u128::from(amount.0) * u128::from(self.hundredths) / 10_000) as u64
+    }
+}
+
+impl FromStr for PointsRate {
+    type Err = String;
+
+    /// A positive decimal with at most two places: `1`, `2.5`, `0.25`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || format!("invalid points rate \"{s}\"");
+        let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if (whole.is_empty() && fraction.is_empty()) || !digits(whole) || !digits(fraction) {
+            return Err(invalid());
+        }
+        if fraction.len() > 2 {
+            return Err(format!(
+                "points rate \"{s}\" has more than 2 decimal places"
+            ));
+        }
+        let whole: u32 = match whole {
+            "" => 0,
+            whole => whole.parse().map_err(|_| invalid())?,
+        };
+        let fraction: u32 = format!("{fraction:0<2}").parse().map_err(|_| invalid())?;
+        let hundredths = whole
+            .checked_mul(100)
+            .and_then(|whole| whole.checked_add(fraction))
+            .ok_or_else(invalid)?;
+        if hundredths == 0 {
+            return Err("the points rate must be more than zero".to_string());
+        }
+        Ok(Self { hundredths })
+    }
+}
+
+/// Shortest form: `1`, `2.5`.
+impl fmt::Display for PointsRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (whole, fraction) = (self.hundredths / 100, self.hundredths % 100);
+        match fraction {
+            0 => write!(f, "{whole}"),
+            f10 if f10 % 10 == 0 => write!(f, "{whole}.{}", f10 / 10),
+            _ => write!(f, "{whole}.{fraction:02}"),
+        }
+    }
+}
+
+/// Points earned on one transaction, and whose they are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Earned {
+    /// Negative for a refund.
+    pub points: i64,
+    pub customer: Option<String>,
+}
+
+/// One customer's transactions, spending, and points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Account {
+    pub transactions: u64,
+    /// Sales less refunds, in cents.
+    pub spent: i64,
+    pub points: i64,
+}
+
+/// Points built up over a run.
+#[derive(Debug, Clone)]
+pub struct Loyalty {
+    pub rate: PointsRate,
+    /// By customer ID, in ID order.
+    pub customers: BTreeMap<String, Account>,
+    /// Transactions without a `customer=`.
+    pub anonymous: Account,
+}
+
+impl Loyalty {
+    pub fn new(rate: PointsRate) -> Self {
+        Self {
+            rate,
+            customers: BTreeMap::new(),
+            anonymous: Account::default(),
+        }
+    }
+
+    /// Count a sale of `amount`, or with `refund`, a refund of it, towards
+    /// `customer`, and return the points earned.
+    pub fn record(&mut self, customer: Option<&str>, amount: Cents, refund: bool) -> Earned {
+        let points = self.rate.points(amount) as i64;
+        let (points, spent) = if refund {
+            (-points, -(amount.0 as i64))
+        } else {
+            (points, amount.0 as i64)
+        };
+        let account = match customer {
+            Some(customer) => self.customers.entry(customer.to_string()).or_default(),
+            None => &mut self.anonymous,
+        };
+        account.transactions += 1;
+        account.spent += spent;
+        account.points += points;
+        Earned {
+            points,
+            customer: customer.map(ToString::to_string),
+        }
+    }
+
+    /// Take back a transaction of `amount` that earned `earned`, voided:
+    /// what [`Loyalty::record`] counted comes off the customer's account.
+    pub fn void(&mut self, earned: &Earned, amount: Cents, refund: bool) {
+        let spent = if refund {
+            -(amount.0 as i64)
+        } else {
+            amount.0 as i64
+        };
+        let account = match &earned.customer {
+            Some(customer) => self.customers.entry(customer.clone()).or_default(),
+            None => &mut self.anonymous,
+        };
+        account.transactions -= 1;
+        account.spent -= spent;
+        account.points -= earned.points;
+    }
+
+    /// Points across every customer, and without one.
+    pub fn total(&self) -> Account {
+        self.customers.values().chain([&self.anonymous]).fold(
+            Account::default(),
+            |total, account| Account {
+                transactions: total.transactions + account.transactions,
+                spent: total.spent + account.spent,
+                points: total.points + account.points,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_parse_and_round_points_down() {
+        let rate: PointsRate = "1".parse().unwrap();
+        assert_eq!(rate.points(Cents(299)), 2);
+        assert_eq!(rate.points(Cents(99)), 0);
+        let rate: PointsRate = "2.5".parse().unwrap();
+        assert_eq!(rate.to_string(), "2.5");
+        assert_eq!(rate.points(Cents(1_000)), 25);
+        assert_eq!("0.25".parse::<PointsRate>().unwrap().to_string(), "0.25");
+        assert!("0".parse::<PointsRate>().is_err());
+        assert!("1.005".parse::<PointsRate>().is_err());
+        assert!("-1".parse::<PointsRate>().is_err());
+    }
+
+    #[test]
+    fn tallies_points_per_customer() {
+        let mut loyalty = Loyalty::new("1".parse().unwrap());
+        assert_eq!(
+            loyalty.record(Some("C1"), Cents(1_250), false),
+            Earned {
+                points: 12,
+                customer: Some("C1".into()),
+            }
+        );
+        loyalty.record(Some("C1"), Cents(300), false);
+        loyalty.record(Some("C1"), Cents(300), true);
+        loyalty.record(None, Cents(500), false);
+
+        let c1 = loyalty.customers["C1"];
+        assert_eq!((c1.transactions, c1.spent, c1.points), (3, 1_250, 12));
+        assert_eq!(loyalty.anonymous.points, 5);
+        assert_eq!(loyalty.total().points, 17);
+
+        let earned = loyalty.record(Some("C1"), Cents(1_000), false);
+        loyalty.void(&earned, Cents(1_000), false);
+        let refunded = loyalty.record(Some("C1"), Cents(300), true);
+        loyalty.void(&refunded, Cents(300), true);
+        assert_eq!(loyalty.customers["C1"], c1);
+    }
+}
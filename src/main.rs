@@ -1,85 +1,4224 @@
+use std::collections::BTreeMap;
 use std::env;
-use std::fs;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::num::{NonZeroU64, NonZeroUsize};
+use std::path::{Path, PathBuf};
 use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
 
+use clap::builder::BoolishValueParser;
+use clap::error::ErrorKind;
+use clap::{ArgGroup, Args, CommandFactory, Parser, Subcommand};
 use rand::rngs::StdRng;
-use rand::SeedableRng;
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "tui")]
+use ratatui::backend::CrosstermBackend;
+#[cfg(feature = "tui")]
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+#[cfg(feature = "tui")]
+use ratatui::crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+#[cfg(feature = "tui")]
+use ratatui::Terminal;
+use serde_json::json;
+use tracing::{info, info_span, warn};
+use tracing_subscriber::filter::LevelFilter;
 
-use cash_register::currency::{EUR, USD};
-use cash_register::format::{format_breakdown, format_verbose};
-use cash_register::parse::parse_input;
-use cash_register::rules::make_change_for;
+use cash_register::audit::Audit;
+use cash_register::cash_report::CashReport;
+use cash_register::config::{Config, OutputFormat, StrategyChoice};
+use cash_register::currency::{Currency, CURRENCIES};
+#[cfg(feature = "tui")]
+use cash_register::dashboard::Dashboard;
+use cash_register::dedupe::SeenIds;
+use cash_register::error::{CashRegisterError, ErrorCategory, TransactionError};
+use cash_register::escpos::format_receipt;
+use cash_register::exchange::{ChangeIn, ExchangeRate};
+use cash_register::export::{format_ofx, format_qif, ExportEntry, ExportSale};
+use cash_register::format::{
+    format_audit, format_bank_order, format_cash_report, format_change_stats, format_csv,
+    format_csv_header, format_deposit_slip, format_donation, format_donations, format_error_csv,
+    format_error_json, format_extra, format_findings, format_foreign_tender, format_heft,
+    format_heft_total, format_iou, format_json, format_ledger_entry, format_ledger_refund,
+    format_loyalty, format_output_diff, format_pictogram_refund, format_points, format_quirks,
+    format_reconciliation, format_refund, format_refund_csv, format_refund_json, format_rounded,
+    format_shortfall, format_simulation, format_split_tender, format_spoken, format_spoken_owed,
+    format_spoken_refund, format_till_report, format_totals, format_underpayment,
+    format_underpayment_json, format_verify, format_voids, write_breakdown, write_pictogram,
+    write_verbose, JSON_SCHEMA_VERSION,
+};
+#[cfg(feature = "sqlite")]
+use cash_register::log_db::LogDb;
+use cash_register::loyalty::{Earned, Loyalty, PointsRate};
+use cash_register::metrics::Metrics;
+use cash_register::money::Cents;
+#[cfg(feature = "parquet")]
+use cash_register::parquet_file::ParquetFile;
+use cash_register::parse::{
+    parse_dollars_to_cents, parse_dollars_to_cents_with, parse_entries, read_entries_with, Entry,
+    ExtraFields, ForeignTender, OverpaymentThreshold, ParseOptions, ParseWarning, Rounded,
+    SplitTender, Transaction,
+};
+use cash_register::pictogram::Glyphs;
+use cash_register::rounding::RoundingMode;
+use cash_register::rpc::RpcSession;
+use cash_register::rules::make_change_with;
+use cash_register::signing::{Signer, Verifier};
+use cash_register::simulate::{
+    format_sale_line, generate_sales, run_sales, sale_generator, AmountDistribution, ErrorMix,
+    PaymentMix, Quirk,
+};
+use cash_register::spoken::amount_words;
+use cash_register::stats::ChangeStats;
+use cash_register::strategy::greedy::GreedyTable;
+use cash_register::strategy::{Breakdown, ChangeStrategy};
+use cash_register::suspicious::{Detector, Pattern};
+use cash_register::tender::TenderPolicy;
+use cash_register::till::{
+    parse_till, rfc3339_utc, FloatDemand, Journal, JournalEvent, Registers, ShortagePolicy, Till,
+    TillCommand, STATE_VERSION,
+};
+use cash_register::totals::Totals;
+use cash_register::verify::{check_breakdown, check_settlement};
+use cash_register::void::{Voided, Voids};
+#[cfg(feature = "webhook")]
+use cash_register::webhook::Webhook;
+use cash_register::weight::Heft;
+
+/// Make change for a file of transactions, one line of change per line.
+#[derive(Parser)]
+#[command(
+    name = "cash-register",
+    version,
+    arg_required_else_help = true,
+    disable_version_flag = true,
+    after_help = EXIT_CODES_HELP
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Print version
+    #[arg(short = 'V', long)]
+    version: bool,
+    /// With --version, print the version, currencies, strategies, and output
+    /// schema as JSON, for tooling to check compatibility
+    #[arg(long, requires = "version")]
+    json: bool,
+    /// Serve JSON-RPC 2.0 on stdin/stdout, one message per line, starting
+    /// from the configuration files
+    #[arg(long, conflicts_with = "version")]
+    rpc: bool,
+    /// With --rpc, serve Prometheus metrics at http://ADDR/metrics
+    #[arg(
+        long,
+        value_name = "ADDR",
+        requires = "rpc",
+        env = "CASH_REGISTER_METRICS_ADDR"
+    )]
+    metrics_addr: Option<SocketAddr>,
+    /// Log library events to stderr at this level and above
+    #[arg(
+        long,
+        global = true,
+        value_name = "off|error|warn|info|debug|trace",
+        default_value = "off",
+        env = "CASH_REGISTER_LOG_LEVEL"
+    )]
+    log_level: LevelFilter,
+    /// Write log events as JSON lines instead of text
+    #[arg(long, global = true, env = "CASH_REGISTER_LOG_JSON", value_parser = BoolishValueParser::new())]
+    log_json: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Make change for each transaction in a file (the default when the
+    /// first argument is a file).
+    #[command(after_help = EXIT_CODES_HELP)]
+    Run(Box<RunArgs>),
+    /// Make change for one transaction given on the command line:
+    /// `cash-register change 2.12 3.00`.
+    Change(ChangeArgs),
+    /// Check a transaction file for errors and refused tenders without
+    /// making change.
+    #[command(after_help = EXIT_CODES_HELP)]
+    Validate(ValidateArgs),
+    /// Ring up a day of synthetic sales against a float and report when each
+    /// denomination ran out.
+    Simulate(SimulateArgs),
+    /// Print synthetic `owed,paid` lines, for benchmarks and till
+    /// simulations without real customer data.
+    Generate(GenerateArgs),
+    /// Replay a day of sales and print the rolls and straps to order from
+    /// the bank.
+    RecommendFloat(RecommendFloatArgs),
+    /// Compare a physical count of a drawer against its saved till state.
+    Reconcile(ReconcileArgs),
+    /// Print a bank deposit slip for the drawers in a saved till state.
+    DepositSlip(DepositSlipArgs),
+    /// Make change for a file and summarize it: change due, pieces per
+    /// transaction, denominations used, and the random rule's share.
+    Stats(StatsArgs),
+    /// Make change for a file and write its sales as QIF or OFX for an
+    /// accounting package.
+    Export(ExportArgs),
+    /// Run a file and list the output lines that differ from a saved
+    /// output, or from a run with other options.
+    Compare(CompareArgs),
+    /// Re-compute a file's output from its input and options and check a
+    /// saved copy against it, line by line.
+    Audit(AuditArgs),
+    /// Check the signatures on a file written with `--sign-key`: run
+    /// output or a journal.
+    Verify(VerifyArgs),
+    /// Inspect the configuration read from `cash-register.toml`.
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print the effective configuration: config files, then environment
+    /// variables, then flags.
+    Show(ConfigArgs),
+}
+
+/// The options shared with the library (see `Config`). Each flag can also be
+/// set with a `CASH_REGISTER_*` environment variable, which the flag
+/// overrides; unset ones keep the value from `cash-register.toml`, or the
+/// config's defaults.
+#[derive(Args)]
+struct ConfigArgs {
+    /// Currency code [default: USD]
+    #[arg(long, value_name = "USD|EUR", env = "CASH_REGISTER_CURRENCY")]
+    currency: Option<String>,
+    /// Randomize change when the amount owed is a multiple of N; 0 never does
+    /// [default: 3]
+    #[arg(long, value_name = "N", env = "CASH_REGISTER_DIVISOR")]
+    divisor: Option<u32>,
+    /// Seed the RNG for reproducible random change
+    #[arg(long, value_name = "N", env = "CASH_REGISTER_SEED")]
+    seed: Option<u64>,
+    /// How change is made [default: auto]
+    #[arg(
+        long,
+        value_name = "auto|greedy|random",
+        env = "CASH_REGISTER_STRATEGY"
+    )]
+    strategy: Option<StrategyChoice>,
+    /// How each result is written [default: text]
+    #[arg(
+        long,
+        value_name = "text|json|ledger|csv|msgpack|spoken|pictogram",
+        env = "CASH_REGISTER_OUTPUT"
+    )]
+    output: Option<OutputFormat>,
+    /// Draw coins, bills, or one denomination (`0.25`) as GLYPH under
+    /// `--output pictogram`; repeatable
+    #[arg(long = "glyph", value_name = "KEY=GLYPH", value_parser = parse_glyph)]
+    glyphs: Vec<(String, String)>,
+    /// Show the amounts owed and paid with each result
+    #[arg(long, env = "CASH_REGISTER_VERBOSE", value_parser = BoolishValueParser::new())]
+    verbose: bool,
+    /// Write symbols as the currency code (`EUR 1.50`), for printers and
+    /// terminals that mangle `€`
+    #[arg(long, env = "CASH_REGISTER_ASCII_SYMBOLS", value_parser = BoolishValueParser::new())]
+    ascii_symbols: bool,
+}
+
+/// How input lines are read (see `ParseOptions`).
+#[derive(Args)]
+struct ParseArgs {
+    /// What to do with unrecognized fields after owed,paid
+    #[arg(long, value_name = "error|ignore|metadata", default_value = "error")]
+    extra_fields: ExtraFields,
+    /// Warn about lines with at least AMOUNT in change, or paid more than N
+    /// times owed (`10x`)
+    #[arg(
+        long,
+        value_name = "AMOUNT|Nx",
+        default_value = "100.00",
+        env = "CASH_REGISTER_WARN_OVERPAYMENT"
+    )]
+    warn_overpayment: OverpaymentThreshold,
+    /// Round amounts with more than two decimal places to whole cents
+    /// instead of rejecting them [default: `rounding` in the config file]
+    #[arg(
+        long,
+        value_name = "half-up|half-down|half-even|toward-zero",
+        env = "CASH_REGISTER_ROUND_INPUT"
+    )]
+    round_input: Option<RoundingMode>,
+    /// Convert the paid amount of `currency=` lines without a `rate=` at
+    /// RATE units of this register's currency per unit of theirs
+    /// [default: `exchange-rate` in the config file]
+    #[arg(long, value_name = "RATE", env = "CASH_REGISTER_EXCHANGE_RATE")]
+    exchange_rate: Option<ExchangeRate>,
+}
+
+/// Limits on the cash a customer may hand over.
+#[derive(Args)]
+struct TenderArgs {
+    /// Refuse bills larger than AMOUNT
+    #[arg(long, value_name = "AMOUNT", value_parser = parse_amount, env = "CASH_REGISTER_MAX_BILL")]
+    max_bill: Option<Cents>,
+    /// Refuse more than N coins in one payment
+    #[arg(long, value_name = "N", env = "CASH_REGISTER_MAX_COINS")]
+    max_coins: Option<u32>,
+}
+
+#[derive(Args)]
+#[command(group(ArgGroup::new("drawer").args(["till", "till_state"]).multiple(true)))]
+struct RunArgs {
+    /// Transaction file: `owed,paid` per line
+    input: String,
+    #[command(flatten)]
+    config: ConfigArgs,
+    #[command(flatten)]
+    tender: TenderArgs,
+    /// Track a drawer, opening with this till file's float
+    #[arg(long, value_name = "FILE")]
+    till: Option<String>,
+    /// Write the closing drawer report here instead of stdout
+    #[arg(long, value_name = "FILE")]
+    till_report: Option<String>,
+    /// Continue from, and save, the drawers in this state file
+    #[arg(long, value_name = "FILE")]
+    till_state: Option<String>,
+    /// What to do when the drawer can't make the change
+    #[arg(
+        long,
+        value_name = "error|partial|substitute",
+        default_value = "error",
+        env = "CASH_REGISTER_SHORTAGE_POLICY"
+    )]
+    shortage_policy: ShortagePolicy,
+    /// Append every drawer change to this journal
+    #[arg(long, value_name = "FILE")]
+    journal: Option<String>,
+    /// Sign each output and journal line with an HMAC chained to the line
+    /// before, for `verify`
+    #[arg(
+        long,
+        value_name = "KEY",
+        env = "CASH_REGISTER_SIGN_KEY",
+        hide_env_values = true
+    )]
+    sign_key: Option<String>,
+    /// Read the signing key from this file, which keeps it out of the
+    /// process list and environment; wins over `--sign-key`
+    #[arg(long, value_name = "FILE")]
+    sign_key_file: Option<String>,
+    /// Chain the signed output onto this signed file's last line, for
+    /// output appended to it
+    #[arg(long, value_name = "FILE")]
+    sign_after: Option<String>,
+    /// Log every sale, error, and drawer change to this SQLite database
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "FILE", conflicts_with = "jobs")]
+    log_db: Option<String>,
+    /// Write each sale's and refund's amounts and change to this Parquet
+    /// file (not in a dry run)
+    #[cfg(feature = "parquet")]
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "jobs",
+        env = "CASH_REGISTER_PARQUET"
+    )]
+    parquet: Option<String>,
+    /// Print an ESC/POS receipt for each sale to this file or printer device
+    #[arg(long, value_name = "PATH", conflicts_with = "jobs")]
+    receipt: Option<String>,
+    /// Ask on the terminal how to resolve each shortage
+    #[arg(long)]
+    interactive: bool,
+    /// Watch the drawers, the latest transactions, and error counts on a
+    /// terminal dashboard while the batch runs
+    #[cfg(feature = "tui")]
+    #[arg(long, conflicts_with_all = ["interactive", "pipeline", "jobs"])]
+    dashboard: bool,
+    /// Preview the batch: report on the drawer but save nothing
+    #[arg(long, requires = "drawer")]
+    dry_run: bool,
+    /// Parse, make change, and print on separate threads
+    #[arg(long, conflicts_with = "interactive")]
+    pipeline: bool,
+    /// Make change on N worker threads; output stays in input order
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["till", "till_state", "journal", "interactive", "pipeline"]
+    )]
+    jobs: Option<NonZeroUsize>,
+    /// Print paid, owed, and change totals at the end and check they balance
+    #[arg(long, conflicts_with = "jobs")]
+    totals: bool,
+    /// Print throughput and peak memory to stderr at the end
+    #[arg(long)]
+    stats: bool,
+    /// Re-check every breakdown and drawer, and abort if one is wrong
+    #[arg(long, conflicts_with = "jobs")]
+    paranoid: bool,
+    /// Print only the first N line errors, then how many more there were
+    #[arg(long, value_name = "N")]
+    max_error_reports: Option<u64>,
+    /// Stop the run once N lines have failed
+    #[arg(long, value_name = "N")]
+    max_errors: Option<NonZeroU64>,
+    #[command(flatten)]
+    parse: ParseArgs,
+    /// Reject lines where owed or paid is over AMOUNT
+    #[arg(long, value_name = "AMOUNT", value_parser = parse_positive_amount, env = "CASH_REGISTER_MAX_AMOUNT")]
+    max_amount: Option<Cents>,
+    /// Give change for `currency=` lines in this register's currency or
+    /// the one they paid in [default: `change-in` in the config file]
+    #[arg(
+        long,
+        value_name = "register|tendered",
+        env = "CASH_REGISTER_CHANGE_IN"
+    )]
+    change_in: Option<ChangeIn>,
+    /// Round each sale's change down to a multiple of AMOUNT and donate the
+    /// rest to charity
+    #[arg(long, value_name = "AMOUNT", value_parser = parse_positive_amount, env = "CASH_REGISTER_ROUNDUP")]
+    roundup: Option<Cents>,
+    /// Add the weight and size of each line's change, and the run's total
+    #[arg(long, conflicts_with = "jobs")]
+    weights: bool,
+    /// Flag runs of one amount and payments just under a reporting
+    /// threshold, for loss-prevention review
+    #[arg(long)]
+    flag_suspicious: bool,
+    /// With --flag-suspicious, flag N or more transactions in a row for the
+    /// same amount
+    #[arg(long, value_name = "N", default_value_t = Detector::DEFAULT_REPEATS, value_parser = clap::value_parser!(u32).range(2..), requires = "flag_suspicious")]
+    suspicious_repeats: u32,
+    /// With --flag-suspicious, flag payments within a tenth of AMOUNT under
+    /// it [default: 10000.00]
+    #[arg(long, value_name = "AMOUNT", value_parser = parse_positive_amount, requires = "flag_suspicious")]
+    suspicious_threshold: Option<Cents>,
+    /// Award RATE loyalty points per whole unit spent, per sale and to the
+    /// line's `customer=`, with a summary at the end
+    #[arg(long, value_name = "RATE", env = "CASH_REGISTER_LOYALTY_RATE")]
+    loyalty_rate: Option<PointsRate>,
+    /// List cash transactions of AMOUNT or more at the end, for large-cash
+    /// reporting
+    #[arg(long, value_name = "AMOUNT", value_parser = parse_positive_amount, env = "CASH_REGISTER_REPORT_THRESHOLD")]
+    report_threshold: Option<Cents>,
+    /// Run this shell command after each transaction, with its JSON output
+    /// line on stdin (not in a dry run)
+    #[arg(
+        long,
+        value_name = "CMD",
+        conflicts_with = "jobs",
+        env = "CASH_REGISTER_EXEC_HOOK"
+    )]
+    exec_hook: Option<String>,
+    /// When an --exec-hook command fails: carry on silently, report it and
+    /// carry on, or stop the run there
+    #[arg(long, value_name = "ignore|warn|abort", default_value = "warn")]
+    hook_failure: HookFailure,
+    /// POST each transaction's JSON output line to this URL (not in a dry
+    /// run)
+    #[cfg(feature = "webhook")]
+    #[arg(
+        long,
+        value_name = "URL",
+        conflicts_with = "jobs",
+        env = "CASH_REGISTER_WEBHOOK"
+    )]
+    webhook: Option<String>,
+    /// With --webhook, POST N transactions at a time, as a JSON array
+    #[cfg(feature = "webhook")]
+    #[arg(long, value_name = "N", default_value = "1", requires = "webhook")]
+    webhook_batch: NonZeroUsize,
+    /// With --webhook, retry a failed POST up to N times, waiting twice as
+    /// long each time
+    #[cfg(feature = "webhook")]
+    #[arg(long, value_name = "N", default_value = "3", requires = "webhook")]
+    webhook_retries: u32,
+    /// Also print a line in the output for each underpaid line; the same
+    /// as `--on-error underpayment=placeholder`
+    #[arg(long, value_name = "error|report", default_value = "error")]
+    on_underpayment: OnUnderpayment,
+    /// Skip, abort on, or print a placeholder for each kind of failed line,
+    /// e.g. underpayment=abort,malformed=skip [default: all skip]
+    #[arg(
+        long,
+        value_name = "CATEGORY=ACTION,...",
+        env = "CASH_REGISTER_ON_ERROR"
+    )]
+    on_error: Option<ErrorPolicies>,
+    /// Flag or skip lines whose `id=` was already rung up
+    #[arg(long, value_name = "flag|skip")]
+    dedupe: Option<Dedupe>,
+    /// With --dedupe, also check against, and save, the IDs from earlier runs
+    #[arg(long, value_name = "FILE", requires = "dedupe")]
+    seen_ids: Option<String>,
+}
+
+/// `--dedupe`: what to do with a line whose `id=` was already rung up.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Dedupe {
+    /// Report it as an error and leave it unrung.
+    Flag,
+    /// Leave it unrung, noting it on stderr without failing the run.
+    Skip,
+}
+
+impl FromStr for Dedupe {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "flag" => Ok(Self::Flag),
+            "skip" => Ok(Self::Skip),
+            other => Err(format!(
+                "unknown dedupe mode \"{other}\" (expected flag or skip)"
+            )),
+        }
+    }
+}
+
+/// `--hook-failure`: what to do when an `--exec-hook` command exits
+/// non-zero or can't be started.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HookFailure {
+    /// Carry on as if it had succeeded.
+    Ignore,
+    /// Report it on stderr, carry on, and exit with `EXIT_HOOK`.
+    Warn,
+    /// Report it and stop the run after that transaction.
+    Abort,
+}
+
+impl FromStr for HookFailure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ignore" => Ok(Self::Ignore),
+            "warn" => Ok(Self::Warn),
+            "abort" => Ok(Self::Abort),
+            other => Err(format!(
+                "unknown hook failure policy \"{other}\" (expected ignore, warn, or abort)"
+            )),
+        }
+    }
+}
+
+/// `--on-underpayment`: whether an underpaid line also gets an output line.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OnUnderpayment {
+    /// Only report it on stderr.
+    Error,
+    /// Also print `insufficient payment: short $2.00 (2 dollars)` in its
+    /// place in the output, so output lines match input lines.
+    Report,
+}
+
+impl FromStr for OnUnderpayment {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(Self::Error),
+            "report" => Ok(Self::Report),
+            other => Err(format!(
+                "unknown underpayment mode \"{other}\" (expected error or report)"
+            )),
+        }
+    }
+}
+
+/// `--on-error`: what to do with a failed line, besides reporting it.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+enum OnError {
+    /// Go on to the next line.
+    #[default]
+    Skip,
+    /// Stop the run there, still printing and saving what came before.
+    Abort,
+    /// Print `error: E003` (or a JSON line with the code and message) in its
+    /// place in the output, so output lines match input lines.
+    Placeholder,
+}
+
+impl FromStr for OnError {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(Self::Skip),
+            "abort" => Ok(Self::Abort),
+            "placeholder" => Ok(Self::Placeholder),
+            other => Err(format!(
+                "unknown error action \"{other}\" (expected skip, abort, or placeholder)"
+            )),
+        }
+    }
+}
+
+/// `--on-error`'s table: an `OnError` per error category, parsed from
+/// `underpayment=abort,malformed=skip`. Categories left out are skipped.
+#[derive(Clone, Copy, Default)]
+struct ErrorPolicies([OnError; ErrorCategory::ALL.len()]);
+
+impl ErrorPolicies {
+    fn set(&mut self, category: ErrorCategory, action: OnError) {
+        self.0[category as usize] = action;
+    }
+
+    fn get(&self, category: ErrorCategory) -> OnError {
+        self.0[category as usize]
+    }
+
+    /// What to do about `error`; errors without a category are skipped.
+    fn for_error(&self, error: &CashRegisterError) -> OnError {
+        error
+            .category()
+            .map_or(OnError::Skip, |category| self.get(category))
+    }
+}
+
+impl FromStr for ErrorPolicies {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut policies = Self::default();
+        for entry in s.split(',') {
+            let (category, action) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected CATEGORY=ACTION, got \"{entry}\""))?;
+            policies.set(category.trim().parse()?, action.trim().parse()?);
+        }
+        Ok(policies)
+    }
+}
+
+#[derive(Args)]
+struct ChangeArgs {
+    /// Amount owed, like 2.12
+    #[arg(value_parser = parse_amount)]
+    owed: Cents,
+    /// Amount paid, like 3.00
+    #[arg(value_parser = parse_amount)]
+    paid: Cents,
+    #[command(flatten)]
+    config: ConfigArgs,
+}
+
+#[derive(Args)]
+struct ValidateArgs {
+    /// Transaction file to check
+    input: String,
+    /// Currency the tendered cash is in [default: USD]
+    #[arg(long, value_name = "USD|EUR", env = "CASH_REGISTER_CURRENCY")]
+    currency: Option<String>,
+    #[command(flatten)]
+    tender: TenderArgs,
+    /// Print only the first N line errors, then how many more there were
+    #[arg(long, value_name = "N")]
+    max_error_reports: Option<u64>,
+    #[command(flatten)]
+    parse: ParseArgs,
+    /// Reject lines where owed or paid is over AMOUNT
+    #[arg(long, value_name = "AMOUNT", value_parser = parse_positive_amount, env = "CASH_REGISTER_MAX_AMOUNT")]
+    max_amount: Option<Cents>,
+}
+
+#[derive(Args)]
+struct SimulateArgs {
+    /// Number of sales to ring up
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    sales: usize,
+    /// Largest amount owed on a sale
+    #[arg(long, value_name = "AMOUNT", default_value = "20.00", value_parser = parse_positive_amount)]
+    max_owed: Cents,
+    /// How customers pay, e.g. exact=1,round-up=2,overpay=1
+    #[arg(long, value_name = "HABIT=WEIGHT,...")]
+    payment_mix: Option<PaymentMix>,
+    /// Opening float
+    #[arg(long, value_name = "FILE")]
+    till: Option<String>,
+    #[command(flatten)]
+    config: ConfigArgs,
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    /// Number of lines to print
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    sales: usize,
+    /// Largest amount owed on a sale
+    #[arg(long, value_name = "AMOUNT", default_value = "20.00", value_parser = parse_positive_amount)]
+    max_owed: Cents,
+    /// How the amounts owed are spread up to --max-owed
+    #[arg(
+        long,
+        value_name = "uniform|skewed|shelf-prices",
+        default_value = "uniform"
+    )]
+    amounts: AmountDistribution,
+    /// How customers pay, e.g. exact=1,round-up=2,overpay=1,next-bill=1
+    #[arg(long, value_name = "HABIT=WEIGHT,...")]
+    payment_mix: Option<PaymentMix>,
+    /// Add a `tendered=` field with the pieces each customer handed over
+    #[arg(long)]
+    tendered: bool,
+    /// Put faults in this percentage of lines, to test error handling
+    /// against [default: malformed=5,underpaid=5,whitespace=5,encoding=5]
+    #[arg(
+        long,
+        value_name = "QUIRK=PERCENT,...",
+        num_args = 0..=1,
+        default_missing_value = "malformed=5,underpaid=5,whitespace=5,encoding=5"
+    )]
+    with_errors: Option<ErrorMix>,
+    #[command(flatten)]
+    config: ConfigArgs,
+}
+
+#[derive(Args)]
+struct RecommendFloatArgs {
+    /// A day of transactions to replay
+    history: String,
+    /// What the drawer already holds; only the rest is ordered
+    #[arg(long, value_name = "FILE")]
+    till: Option<String>,
+    #[command(flatten)]
+    config: ConfigArgs,
+}
+
+#[derive(Args)]
+struct ReconcileArgs {
+    /// The counted drawer, in till file format
+    count: String,
+    /// State file holding the expected drawers
+    #[arg(long, value_name = "FILE")]
+    till_state: String,
+    /// Which register was counted [default: the unnamed one]
+    #[arg(long, value_name = "ID", default_value = "")]
+    register: String,
+    /// Currency of the state file and count [default: USD]
+    #[arg(long, value_name = "USD|EUR", env = "CASH_REGISTER_CURRENCY")]
+    currency: Option<String>,
+}
+
+#[derive(Args)]
+struct StatsArgs {
+    /// Transaction file to summarize
+    input: String,
+    #[command(flatten)]
+    config: ConfigArgs,
+}
+
+#[derive(Args)]
+struct ExportArgs {
+    /// Transaction file to export
+    input: String,
+    /// File format to write
+    #[arg(long, value_name = "qif|ofx")]
+    format: ExportFormat,
+    /// Date to book the sales on [default: today, UTC]
+    #[arg(long, value_name = "YYYY-MM-DD", value_parser = parse_date)]
+    date: Option<String>,
+    #[command(flatten)]
+    config: ConfigArgs,
+}
+
+#[derive(Args)]
+struct CompareArgs {
+    /// Transaction file to run
+    input: String,
+    /// Output saved from an earlier run, to compare this run against
+    #[arg(long, value_name = "FILE", required_unless_present = "with")]
+    against: Option<String>,
+    /// Compare against a second run with these options added, split on
+    /// whitespace: '--strategy random --seed 7'
+    #[arg(
+        long,
+        value_name = "OPTIONS",
+        allow_hyphen_values = true,
+        conflicts_with = "against"
+    )]
+    with: Option<String>,
+    /// `run` options for both runs, after `--`
+    #[arg(last = true, value_name = "RUN OPTIONS")]
+    options: Vec<String>,
+}
+
+#[derive(Args)]
+struct AuditArgs {
+    /// Transaction file the output was made from
+    input: String,
+    /// Output saved from the run
+    recorded: String,
+    /// Date the run's `--output ledger` entries were booked on [default:
+    /// today, UTC]
+    #[arg(long, value_name = "YYYY-MM-DD", value_parser = parse_date)]
+    date: Option<String>,
+    #[command(flatten)]
+    config: ConfigArgs,
+    #[command(flatten)]
+    parse: ParseArgs,
+}
+
+#[derive(Args)]
+struct VerifyArgs {
+    /// Signed output or journal
+    file: String,
+    /// The key it was signed with
+    #[arg(
+        long,
+        value_name = "KEY",
+        env = "CASH_REGISTER_SIGN_KEY",
+        hide_env_values = true,
+        required_unless_present = "sign_key_file"
+    )]
+    sign_key: Option<String>,
+    /// Read the key from this file; wins over `--sign-key`
+    #[arg(long, value_name = "FILE")]
+    sign_key_file: Option<String>,
+}
+
+/// `export --format`: which accounting file format to write.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ExportFormat {
+    Qif,
+    Ofx,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "qif" => Ok(Self::Qif),
+            "ofx" => Ok(Self::Ofx),
+            other => Err(format!(
+                "unknown export format \"{other}\" (expected qif or ofx)"
+            )),
+        }
+    }
+}
+
+#[derive(Args)]
+struct DepositSlipArgs {
+    /// State file holding the drawers to deposit
+    till_state: String,
+    /// Only this register's drawer [default: every register]
+    #[arg(long, value_name = "ID")]
+    register: Option<String>,
+    /// Currency of the state file [default: USD]
+    #[arg(long, value_name = "USD|EUR", env = "CASH_REGISTER_CURRENCY")]
+    currency: Option<String>,
+}
+
+/// The config file name, looked for in the current and XDG config dirs.
+const CONFIG_FILE: &str = "cash-register.toml";
+
+/// The CLI only offers built-in currencies, and each has a 1-cent piece, so
+/// any amount can be made exactly.
+const EXACT_CHANGE: &str = "built-in currencies make any amount exactly";
+
+/// Subcommand names, so a bare `cash-register <file>` still means `run`.
+const SUBCOMMANDS: &[&str] = &[
+    "run",
+    "change",
+    "validate",
+    "simulate",
+    "generate",
+    "recommend-float",
+    "reconcile",
+    "deposit-slip",
+    "stats",
+    "export",
+    "compare",
+    "audit",
+    "verify",
+    "config",
+    "help",
+];
+
+/// Exit codes that don't come from a line's `CashRegisterError::exit_code`,
+/// following sysexits(3) where it has one.
+const EXIT_SETUP: i32 = 1;
+const EXIT_USAGE: i32 = 64;
+/// An `--exec-hook` command failed: EX_UNAVAILABLE.
+const EXIT_HOOK: i32 = 69;
+const EXIT_SOFTWARE: i32 = 70;
+const EXIT_IO: i32 = 74;
+/// Stopped early by SIGINT or SIGTERM, as a shell reports Ctrl-C.
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// Lines for stderr held back while `--dashboard` has the terminal, printed
+/// once it's closed; `None` when nothing is holding them.
+static HELD_STDERR: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// `eprintln!`, or hold the line in `HELD_STDERR` while the dashboard is up.
+macro_rules! eprintln_or_hold {
+    ($($arg:tt)*) => {{
+        let line = format!($($arg)*);
+        match HELD_STDERR.lock().expect("a thread panicked holding stderr").as_mut() {
+            Some(held) => held.push(line),
+            None => eprintln!("{line}"),
+        }
+    }};
+}
+
+/// A run carries on past failed lines, then exits with the most serious
+/// kind of failure it saw, first here first.
+const EXIT_PRECEDENCE: [i32; 9] = [
+    EXIT_INTERRUPTED,
+    EXIT_SOFTWARE,
+    EXIT_IO,
+    EXIT_HOOK,
+    EXIT_SETUP,
+    2,
+    5,
+    4,
+    3,
+];
+
+const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0   success
+  130 stopped by Ctrl-C or SIGTERM (the lines before it were saved)
+  64  usage error: unknown subcommand or flag, or a bad flag value
+  70  internal error caught by --paranoid (the run was aborted)
+  74  an input, output, journal, or state file couldn't be read or written
+  69  an --exec-hook command failed (unless --hook-failure ignore)
+  1   bad setup: config, till, or state file contents (nothing was processed)
+  2   malformed or invalid lines
+  5   till shortages
+  4   underpayments
+  3   refused tenders
+When a run hits several kinds of failure, the first listed wins.";
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    if args
+        .get(1)
+        .is_some_and(|a| !a.starts_with('-') && !SUBCOMMANDS.contains(&a.as_str()))
+    {
+        args.insert(1, "run".to_string());
+    }
+
+    let cli = Cli::try_parse_from(&args).unwrap_or_else(|e| {
+        // Help and version go to stdout and succeed.
+        let _ = e.print();
+        process::exit(if e.use_stderr() { EXIT_USAGE } else { 0 });
+    });
+
+    init_logging(cli.log_level, cli.log_json);
+
+    if cli.version {
+        if cli.json {
+            println!("{}", build_info());
+        } else {
+            println!("cash-register {}", env!("CARGO_PKG_VERSION"));
+        }
+        return;
+    }
+    if cli.rpc {
+        return serve_rpc(cli.metrics_addr);
+    }
+
+    let Some(command) = cli.command else {
+        let _ = Cli::command()
+            .error(ErrorKind::MissingSubcommand, "no subcommand given")
+            .print();
+        process::exit(EXIT_USAGE);
+    };
+    match command {
+        Command::Run(args) => run(*args),
+        Command::Change(args) => change(args),
+        Command::Validate(args) => validate(args),
+        Command::Simulate(args) => simulate(args),
+        Command::Generate(args) => generate(args),
+        Command::RecommendFloat(args) => recommend_float(args),
+        Command::Reconcile(args) => reconcile(args),
+        Command::DepositSlip(args) => deposit_slip(args),
+        Command::Stats(args) => stats(args),
+        Command::Export(args) => export(args),
+        Command::Compare(args) => compare(args),
+        Command::Audit(args) => audit(args),
+        Command::Verify(args) => verify(args),
+        Command::Config(ConfigCommand::Show(args)) => config_show(args),
+    }
+}
+
+/// `--version --json`: what this build supports, so orchestration can check
+/// compatibility before launching a batch.
+fn build_info() -> serde_json::Value {
+    json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "currencies": CURRENCIES.iter().map(|c| c.name.as_ref()).collect::<Vec<_>>(),
+        "strategies": StrategyChoice::ALL.map(StrategyChoice::as_str),
+        "output_formats": OutputFormat::ALL.map(OutputFormat::as_str),
+        "rounding_modes": RoundingMode::ALL.map(RoundingMode::as_str),
+        "output_schema_version": JSON_SCHEMA_VERSION,
+        "till_state_version": STATE_VERSION,
+    })
+}
+
+/// `--rpc`: answer each request line on stdout until stdin closes. On
+/// SIGINT or SIGTERM, the request in hand is answered before exiting.
+fn serve_rpc(metrics_addr: Option<SocketAddr>) {
+    let mut session = RpcSession::new(load_config()).unwrap_or_else(|e| {
+        eprintln!("Error: {e}");
+        process::exit(EXIT_SETUP);
+    });
+    let metrics = metrics_addr.map(serve_metrics);
+    let answering = Arc::new(Mutex::new(()));
+    let idle = Arc::clone(&answering);
+    stop_on_interrupt(move || {
+        let _idle = idle.lock();
+        process::exit(EXIT_INTERRUPTED);
+    });
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let _answering = answering.lock();
+        let line = line.unwrap_or_else(|e| {
+            eprintln!("Error: reading stdin: {e}");
+            process::exit(EXIT_IO);
+        });
+        if line.trim().is_empty() {
+            continue;
+        }
+        let started = Instant::now();
+        let response = session.handle(&line);
+        if let Some(metrics) = &metrics {
+            session
+                .metrics_mut()
+                .record_latency(started.elapsed().as_secs_f64());
+            metrics
+                .lock()
+                .expect("the metrics server panicked")
+                .clone_from(session.metrics());
+        }
+        if let Some(response) = response {
+            // A host that hung up has nothing left to read.
+            if writeln!(stdout, "{response}")
+                .and_then(|()| stdout.flush())
+                .is_err()
+            {
+                return;
+            }
+        }
+    }
+}
 
-    if args.len() < 2 {
-        eprintln!("Usage: cash-register <input-file> [--divisor N] [--seed N] [--currency USD|EUR] [--verbose]");
-        process::exit(1);
+/// `--metrics-addr`: listen on `addr` and answer `GET /metrics` with the
+/// latest copy of the session's metrics, on a thread of its own, one
+/// scrape at a time. Exits if the address can't be bound.
+fn serve_metrics(addr: SocketAddr) -> Arc<Mutex<Metrics>> {
+    let listener = TcpListener::bind(addr).unwrap_or_else(|e| {
+        eprintln!("Error: can't listen on {addr}: {e}");
+        process::exit(EXIT_SETUP);
+    });
+    // With port 0, this is how the caller finds out which port it got.
+    if let Ok(addr) = listener.local_addr() {
+        eprintln!("Serving metrics on http://{addr}/metrics");
     }
+    let metrics = Arc::new(Mutex::new(Metrics::new()));
+    let shared = Arc::clone(&metrics);
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            // Each on its own thread, so a client that stalls doesn't hold
+            // up the scrapes behind it.
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                // A scraper that stalls or hangs up is its own problem.
+                let _ = answer_scrape(stream, &shared);
+            });
+        }
+    });
+    metrics
+}
 
-    let file_path = &args[1];
-    let divisor: u32 = parse_flag(&args, "--divisor").unwrap_or(3);
-    let seed: Option<u64> = parse_flag(&args, "--seed");
-    let currency_name: String = parse_flag(&args, "--currency").unwrap_or("USD".to_string());
-    let verbose = args.iter().any(|a| a == "--verbose");
+/// How long a client gets to send its request, however it trickles in.
+const SCRAPE_DEADLINE: Duration = Duration::from_secs(5);
+/// The most of a request that's read; a scraper's request line and
+/// headers are far shorter.
+const MAX_SCRAPE_REQUEST: u64 = 8192;
 
-    let currency = match currency_name.to_uppercase().as_str() {
-        "USD" => &USD,
-        "EUR" => &EUR,
-        other => {
-            eprintln!("Unknown currency: {other}. Supported: USD, EUR");
-            process::exit(1);
+/// Read one HTTP request and answer it: the metrics for `GET /metrics`,
+/// 404 for anything else.
+fn answer_scrape(stream: TcpStream, metrics: &Mutex<Metrics>) -> io::Result<()> {
+    stream.set_write_timeout(Some(SCRAPE_DEADLINE))?;
+    let until = Deadline {
+        deadline: Instant::now() + SCRAPE_DEADLINE,
+        stream,
+    };
+    let mut reader = BufReader::new(until.take(MAX_SCRAPE_REQUEST));
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // The headers don't matter, but the client expects them read.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let mut parts = request.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let body = metrics.lock().expect("the session panicked").render();
+            ("200 OK", "text/plain; version=0.0.4", body)
         }
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
     };
+    let mut stream = reader.into_inner().into_inner().stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
 
-    let input = match fs::read_to_string(file_path) {
-        Ok(content) => content,
-        Err(e) => {
-            eprintln!("Error reading {file_path}: {e}");
-            process::exit(1);
+/// A connection whose reads fail once `deadline` has passed, rather than
+/// each waiting its own timeout.
+struct Deadline {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        self.stream.read(buf)
+    }
+}
+
+/// `run`: make change for every transaction in the input file, optionally
+/// against a tracked drawer.
+fn run(args: RunArgs) {
+    let config = args.config.to_config();
+    let currency = &currency_or_exit(&config);
+    let divisor = config.rules_divisor();
+    let interactive = args.interactive;
+    let till_path = args.till;
+    let till_report_path = args.till_report;
+    let till_state_path = args.till_state;
+    // A dry run works on an in-memory copy of the drawers and leaves the
+    // state file and journal alone.
+    let dry_run = args.dry_run;
+    let journal_path = args.journal.filter(|_| !dry_run);
+    let policy = args.shortage_policy;
+    let tender_policy = args.tender.to_policy();
+    if config.output == OutputFormat::Msgpack && args.totals {
+        eprintln!("error: --output msgpack can't be used with --totals, which prints text");
+        process::exit(EXIT_USAGE);
+    }
+    let sign_key = sign_key_or_exit(args.sign_key.as_deref(), args.sign_key_file.as_deref());
+    if args.sign_after.is_some() && sign_key.is_none() {
+        eprintln!("error: --sign-after needs --sign-key or --sign-key-file");
+        process::exit(EXIT_USAGE);
+    }
+    if config.output == OutputFormat::Msgpack && sign_key.is_some() {
+        eprintln!("error: --output msgpack is binary and can't be signed line by line");
+        process::exit(EXIT_USAGE);
+    }
+    let change_in = args.change_in.unwrap_or(config.change_in);
+    if change_in == ChangeIn::Tendered {
+        // Change in another currency never comes out of this register's
+        // drawer or books.
+        #[cfg(feature = "sqlite")]
+        let log_db = args.log_db.is_some();
+        #[cfg(not(feature = "sqlite"))]
+        let log_db = false;
+        #[cfg(feature = "parquet")]
+        let parquet = args.parquet.is_some();
+        #[cfg(not(feature = "parquet"))]
+        let parquet = false;
+        let tracked = [
+            (till_path.is_some(), "--till"),
+            (till_state_path.is_some(), "--till-state"),
+            (args.totals, "--totals"),
+            (args.receipt.is_some(), "--receipt"),
+            (log_db, "--log-db"),
+            (parquet, "--parquet"),
+            // Its columns are the register's denominations.
+            (config.output == OutputFormat::Csv, "--output csv"),
+        ];
+        if let Some((_, flag)) = tracked.iter().find(|(set, _)| *set) {
+            eprintln_or_hold!("error: change in the tendered currency can't be used with {flag}");
+            process::exit(EXIT_USAGE);
         }
+    }
+    let file_path = &args.input;
+    let _span = info_span!("run", input = %file_path).entered();
+    info!(?config, "starting batch");
+
+    let started = Instant::now();
+    let bytes_read = Arc::new(AtomicU64::new(0));
+    let input = CountingReader {
+        inner: open_or_exit(file_path),
+        bytes: Arc::clone(&bytes_read),
+    };
+    let parse_options = args.parse.to_options(&config);
+    let entries: Box<dyn Iterator<Item = ParsedLine>> = if args.pipeline {
+        Box::new(parse_stage(input, parse_options))
+    } else {
+        Box::new(read_entries_with(input, parse_options))
     };
 
-    let mut had_error = false;
+    // Each register opens with the till file's float (if any), unless a saved
+    // state file continues the previous run's drawers.
+    let mut registers = (till_path.is_some() || till_state_path.is_some()).then(|| {
+        let opening = match &till_path {
+            Some(path) => till_or_exit(path, currency),
+            None => Till::new(),
+        };
+        match &till_state_path {
+            Some(path) if Path::new(path).exists() => {
+                Registers::from_state_json(&read_or_exit(path), currency, opening).unwrap_or_else(
+                    |e| {
+                        eprintln_or_hold!("{path}: {e}");
+                        process::exit(EXIT_SETUP);
+                    },
+                )
+            }
+            _ => Registers::new(opening),
+        }
+    });
+
+    let mut journal = journal_path.as_ref().map(|path| {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap_or_else(|e| {
+                eprintln_or_hold!("Error opening {path}: {e}");
+                process::exit(EXIT_IO);
+            });
+        match &sign_key {
+            Some(key) => Journal::new(file).signed(signer_after(key, path, false)),
+            None => Journal::new(file),
+        }
+    });
+
+    #[cfg(feature = "sqlite")]
+    let mut log = RunLog::open(args.log_db.as_deref().filter(|_| !dry_run), currency);
+    #[cfg(not(feature = "sqlite"))]
+    let mut log = RunLog::default();
+    #[cfg(feature = "parquet")]
+    let mut export = Export::open(args.parquet.as_deref().filter(|_| !dry_run), currency);
+    #[cfg(not(feature = "parquet"))]
+    let mut export = Export::default();
+
+    // A dry run hands nothing over, so it prints no receipts.
+    let mut receipts = args.receipt.as_ref().filter(|_| !dry_run).map(|path| {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap_or_else(|e| {
+                eprintln_or_hold!("Error opening {path}: {e}");
+                process::exit(EXIT_IO);
+            })
+    });
 
-    // Use a concrete StdRng regardless — seeded or from entropy.
-    // This avoids Box<dyn Rng> and keeps everything monomorphized.
-    let mut rng = match seed {
-        Some(s) => StdRng::seed_from_u64(s),
-        None => StdRng::from_entropy(),
+    // IDs from earlier runs; a missing file means there weren't any.
+    let mut seen = match &args.seen_ids {
+        Some(path) if Path::new(path).exists() => SeenIds::from_lines(&read_or_exit(path)),
+        _ => SeenIds::new(),
+    };
+
+    let mut status = ExitStatus::default();
+    let mut transactions: u64 = 0;
+    let mut totals = args.totals.then(Totals::new);
+    let mut overpaid_lines = Vec::new();
+    // `--roundup`: the total donated, by how many of how many sales.
+    let mut donated = Cents::ZERO;
+    let mut donors: u64 = 0;
+    let mut rounded_sales: u64 = 0;
+    // `--weights`: the change handed over, on how many lines.
+    let mut heft = Heft::default();
+    let mut weighed_lines: u64 = 0;
+    let mut unweighed: u64 = 0;
+    let mut detector = args.flag_suspicious.then(|| {
+        let threshold = args
+            .suspicious_threshold
+            .unwrap_or(Detector::DEFAULT_THRESHOLD);
+        Detector::new(args.suspicious_repeats, threshold)
+    });
+    let mut loyalty = args.loyalty_rate.map(Loyalty::new);
+    let mut cash_report = args.report_threshold.map(CashReport::new);
+    let mut voids: Voids<Undo> = Voids::new();
+    let mut reports = ErrorReports::new(args.max_error_reports);
+    let mut policies = args.on_error.unwrap_or_default();
+    if args.on_underpayment == OnUnderpayment::Report {
+        policies.set(ErrorCategory::Underpayment, OnError::Placeholder);
+    }
+    let mut rng = config.rng();
+    let mut greedy = GreedyTable::new(currency);
+    let mut printer = Printer::new(&config, currency);
+    printer.weights = args.weights;
+    printer.stdout.signer = sign_key.as_ref().map(|key| {
+        let signer = match &args.sign_after {
+            Some(path) => signer_after(key, path, true),
+            None => Signer::new(key),
+        };
+        Arc::new(Mutex::new(signer))
+    });
+    let stdout = printer.stdout.clone();
+    let mut hook = args
+        .exec_hook
+        .as_deref()
+        .filter(|_| !dry_run)
+        .map(|command| Hook {
+            command: command.to_string(),
+            on_failure: args.hook_failure,
+            printer: Printer {
+                output: OutputFormat::Json,
+                ..printer.clone()
+            },
+        });
+    #[cfg(feature = "webhook")]
+    let mut notifier = Notifier::open(
+        args.webhook.as_deref().filter(|_| !dry_run),
+        args.webhook_batch,
+        args.webhook_retries,
+        &printer,
+    );
+    #[cfg(not(feature = "webhook"))]
+    let mut notifier = Notifier::default();
+    let conversion = parse_options.rounding.unwrap_or_default();
+    let tendered_currencies = printer.currencies.clone();
+    if printer.output == OutputFormat::Csv {
+        stdout.emit(&format_csv_header(currency), false);
+    }
+    let mut sales = match args.jobs {
+        Some(jobs) => SaleSink::parallel(printer, jobs.get()),
+        #[cfg(feature = "tui")]
+        None if args.dashboard => SaleSink::Dashboard {
+            printer,
+            screen: Box::new(Screen::open(currency)),
+        },
+        None => SaleSink::new(printer, args.pipeline),
     };
 
-    for result in parse_input(&input) {
+    stop_on_interrupt(|| {});
+    for (line, result, warnings) in entries {
+        sales.tick(registers.as_ref());
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            eprintln_or_hold!("Interrupted; stopped before line {line}");
+            break;
+        }
+        // Nowhere to print the rest; the error is reported once the
+        // drawers are saved.
+        if stdout.failed().is_some() {
+            break;
+        }
+        if let Some(max) = args.max_errors {
+            if reports.total >= max.get() {
+                let noun = if max.get() == 1 { "line" } else { "lines" };
+                eprintln_or_hold!(
+                    "Aborted before line {line}: {max} {noun} failed (--max-errors {max})"
+                );
+                break;
+            }
+        }
+        let _line = info_span!("line", line).entered();
+        for warning in &warnings {
+            eprintln_or_hold!("{warning}");
+        }
+        let overpaid = is_overpaid(&warnings);
+        if overpaid {
+            overpaid_lines.push(line);
+        }
+        // A refund is paid out like the change on a sale with nothing paid
+        // in; `refund` keeps it labeled as money going out.
+        let (result, refund) = match result {
+            Ok(Entry::Refund(amount, fields)) => {
+                (Ok(Entry::Transaction(payout(amount), fields)), true)
+            }
+            other => (other, false),
+        };
         match result {
-            Ok(transaction) => {
-                let breakdown = make_change_for(&transaction, currency, divisor, &mut rng);
-                if verbose {
-                    let is_random = divisor > 0 && transaction.owed_cents.is_multiple_of(divisor);
-                    println!(
-                        "{}",
-                        format_verbose(&transaction, &breakdown, currency, is_random)
-                    );
+            Ok(Entry::Till(command, fields)) => {
+                // Directives are no-ops unless a till is being tracked.
+                if let Some(registers) = registers.as_mut() {
+                    let till = registers.till_mut(fields.register());
+                    match till.apply(command, currency, line) {
+                        Ok(()) => {
+                            let event = match command {
+                                TillCommand::Add { cents, count } => {
+                                    JournalEvent::Restock { cents, count }
+                                }
+                                TillCommand::Remove { cents, count } => {
+                                    JournalEvent::Pickup { cents, count }
+                                }
+                            };
+                            log.till(line, fields.register(), event);
+                            if let Some(journal) = journal.as_mut() {
+                                let result =
+                                    journal.record(line, fields.register(), event, till, currency);
+                                if report_journal_error(result, &journal_path) {
+                                    status.fail(EXIT_IO);
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            if fail_line(
+                                e,
+                                line,
+                                &policies,
+                                &mut status,
+                                &mut reports,
+                                &mut sales,
+                                &mut log,
+                            ) {
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+            Ok(Entry::Void(id)) => {
+                let (original, undo) = match voids.take(&id, line) {
+                    Ok(open) => open,
+                    Err(e) => {
+                        if fail_line(
+                            e,
+                            line,
+                            &policies,
+                            &mut status,
+                            &mut reports,
+                            &mut sales,
+                            &mut log,
+                        ) {
+                            break;
+                        }
+                        continue;
+                    }
+                };
+                if let Some(registers) = registers.as_mut() {
+                    let till = registers.till_mut(&undo.register);
+                    let voided =
+                        till.void(original, &undo.tendered, &undo.breakdown, currency, line);
+                    if let Err(e) = voided {
+                        voids.remember(&id, original, undo);
+                        if fail_line(
+                            e,
+                            line,
+                            &policies,
+                            &mut status,
+                            &mut reports,
+                            &mut sales,
+                            &mut log,
+                        ) {
+                            break;
+                        }
+                        continue;
+                    }
+                    let pieces_back: Vec<(u32, u32)> = currency
+                        .pieces(&undo.breakdown)
+                        .map(|(d, c)| (d.cents, c))
+                        .collect();
+                    let event = JournalEvent::Void {
+                        tendered: &undo.tendered,
+                        change: &pieces_back,
+                    };
+                    log.till(line, &undo.register, event);
+                    if let Some(journal) = journal.as_mut() {
+                        let result = journal.record(line, &undo.register, event, till, currency);
+                        if report_journal_error(result, &journal_path) {
+                            status.fail(EXIT_IO);
+                        }
+                    }
+                }
+                let transaction = &undo.transaction;
+                match totals.as_mut() {
+                    Some(totals) if undo.refund => totals.void_refund(
+                        original,
+                        transaction.change_cents(),
+                        &undo.breakdown,
+                        undo.not_given,
+                        currency,
+                    ),
+                    Some(totals) => totals.void(
+                        original,
+                        transaction,
+                        &undo.breakdown,
+                        undo.not_given,
+                        undo.donation,
+                        currency,
+                    ),
+                    None => {}
+                }
+                let amount = if undo.refund {
+                    transaction.change_cents()
+                } else {
+                    transaction.owed_cents()
+                };
+                if undo.rounded {
+                    rounded_sales -= 1;
+                    if !undo.donation.is_zero() {
+                        donated -= undo.donation;
+                        donors -= 1;
+                    }
+                }
+                if let Some(weight) = undo.heft {
+                    weighed_lines -= 1;
+                    match weight {
+                        Some(weight) => heft -= weight,
+                        None => unweighed -= 1,
+                    }
+                }
+                if let (Some(loyalty), Some(earned)) = (loyalty.as_mut(), &undo.points) {
+                    loyalty.void(earned, amount, undo.refund);
+                }
+                if let Some(report) = cash_report.as_mut() {
+                    report.void(original);
+                }
+                if let Some(detector) = detector.as_mut() {
+                    detector.void(original);
+                }
+                log.void(line, original);
+                export.void(line, &undo);
+                voids.record(Voided {
+                    line,
+                    original,
+                    id,
+                    amount,
+                    refund: undo.refund,
+                });
+            }
+            Ok(Entry::Refund(..)) => unreachable!("refunds are paid out as transactions"),
+            Ok(Entry::Transaction(transaction, fields)) => {
+                transactions += 1;
+                if let Some(max) = args.max_amount {
+                    if let Err(e) = transaction.check_max_amount(max, line) {
+                        if fail_line(
+                            e,
+                            line,
+                            &policies,
+                            &mut status,
+                            &mut reports,
+                            &mut sales,
+                            &mut log,
+                        ) {
+                            break;
+                        }
+                        continue;
+                    }
+                }
+                if let (Some(dedupe), Some(id)) = (args.dedupe, &fields.id) {
+                    if let Err(e) = seen.check(id, line) {
+                        match dedupe {
+                            Dedupe::Flag => {
+                                if fail_line(
+                                    e,
+                                    line,
+                                    &policies,
+                                    &mut status,
+                                    &mut reports,
+                                    &mut sales,
+                                    &mut log,
+                                ) {
+                                    break;
+                                }
+                            }
+                            Dedupe::Skip => eprintln_or_hold!("{e}; skipped"),
+                        }
+                        continue;
+                    }
+                }
+                if let Some(tendered) = &fields.tendered {
+                    if let Err(e) = tender_policy.check(tendered, currency, line) {
+                        if fail_line(
+                            e,
+                            line,
+                            &policies,
+                            &mut status,
+                            &mut reports,
+                            &mut sales,
+                            &mut log,
+                        ) {
+                            break;
+                        }
+                        continue;
+                    }
+                }
+                // Change in the tendered currency is converted back from the
+                // change due, and always the fewest pieces.
+                let mut foreign_change = None;
+                if let Some(foreign) = &fields.foreign {
+                    let tendered = tendered_currencies
+                        .iter()
+                        .find(|c| c.name.eq_ignore_ascii_case(&foreign.currency));
+                    let checked =
+                        foreign
+                            .check(currency, line)
+                            .and_then(|()| match (change_in, tendered) {
+                                (ChangeIn::Register, _) => Ok(None),
+                                (ChangeIn::Tendered, Some(tendered)) => {
+                                    let change = transaction.change_cents();
+                                    match foreign.rate.to_tendered(change, conversion) {
+                                        Some(converted) => Ok(Some((tendered, converted))),
+                                        None => Err(CashRegisterError::MalformedLine {
+                                            line,
+                                            detail: format!(
+                                                "change of {change} is more than {} in {}",
+                                                Cents::MAX_AMOUNT,
+                                                foreign.currency
+                                            ),
+                                        }),
+                                    }
+                                }
+                                (ChangeIn::Tendered, None) => {
+                                    Err(CashRegisterError::MalformedLine {
+                                        line,
+                                        detail: format!(
+                                            "can't give change in {}, which has no denominations",
+                                            foreign.currency
+                                        ),
+                                    })
+                                }
+                            });
+                    match checked {
+                        Ok(change) => foreign_change = change,
+                        Err(e) => {
+                            if fail_line(
+                                e,
+                                line,
+                                &policies,
+                                &mut status,
+                                &mut reports,
+                                &mut sales,
+                                &mut log,
+                            ) {
+                                break;
+                            }
+                            continue;
+                        }
+                    }
+                }
+                let is_random = !refund
+                    && foreign_change.is_none()
+                    && divisor > 0
+                    && transaction.owed_cents().is_multiple_of(divisor);
+                // Round-up is for sales with change from this drawer; what
+                // it leaves off the change stays in the drawer for charity.
+                let donation = match args.roundup {
+                    Some(increment) if !refund && foreign_change.is_none() => {
+                        transaction.change_cents() % increment
+                    }
+                    _ => Cents::ZERO,
+                };
+                let change_due = transaction.change_cents() - donation;
+                // With `--jobs`, the workers make greedy change; random change
+                // is still drawn here, in input order, so the seed gives the
+                // same output.
+                let mut breakdown = if let Some((tendered, change)) = foreign_change {
+                    greedy.make_change(change, tendered).expect(EXACT_CHANGE)
+                } else if matches!(sales, SaleSink::Parallel { .. }) && !is_random {
+                    Breakdown::new()
+                } else if refund {
+                    // The random rule is about what was owed, and nothing was.
+                    greedy
+                        .make_change(transaction.change_cents(), currency)
+                        .expect(EXACT_CHANGE)
+                } else if !donation.is_zero() {
+                    // Same owed, so the same random rule, with less change.
+                    let rounded = Transaction::new(
+                        transaction.owed_cents(),
+                        transaction.paid_cents() - donation,
+                    )
+                    .expect("less change still covers owed");
+                    make_change_with(&rounded, currency, divisor, &mut rng, &mut greedy)
+                        .expect(EXACT_CHANGE)
                 } else {
-                    println!("{}", format_breakdown(&breakdown));
+                    make_change_with(&transaction, currency, divisor, &mut rng, &mut greedy)
+                        .expect(EXACT_CHANGE)
+                };
+                if args.paranoid {
+                    let (change_currency, change) =
+                        foreign_change.unwrap_or((currency, change_due));
+                    let checked = check_breakdown(&breakdown, change, change_currency, line);
+                    if let Err(e) = checked {
+                        abort_internal_error(e, sales);
+                    }
+                }
+                let mut shortfall = 0;
+                let mut iou = 0;
+                if let Some(registers) = registers.as_mut() {
+                    let till = registers.till_mut(fields.register());
+                    let tendered = fields.tendered.as_deref().unwrap_or_default();
+                    let before = till.total_cents();
+                    let mut settled = till.settle(tendered, &breakdown, currency, policy, line);
+
+                    // Settling is atomic, so after a shortage the drawer is
+                    // untouched and can be settled again the operator's way.
+                    let choice = match &settled {
+                        Err(e @ CashRegisterError::TillShortage { .. }) if interactive => {
+                            prompt_shortage(e)
+                        }
+                        _ => None,
+                    };
+                    let mut write_iou = false;
+                    match choice {
+                        Some(ShortageChoice::Substitute) => {
+                            settled = till.settle(
+                                tendered,
+                                &breakdown,
+                                currency,
+                                ShortagePolicy::Substitute,
+                                line,
+                            );
+                        }
+                        Some(ShortageChoice::Iou) => {
+                            settled = till.settle(
+                                tendered,
+                                &breakdown,
+                                currency,
+                                ShortagePolicy::Partial,
+                                line,
+                            );
+                            write_iou = true;
+                        }
+                        Some(ShortageChoice::Skip) => {
+                            eprintln_or_hold!("line {line}: skipped");
+                            continue;
+                        }
+                        None => {}
+                    }
+
+                    match settled {
+                        Ok(dispensed) => {
+                            if args.paranoid {
+                                let checked = check_settlement(
+                                    before, tendered, change_due, &dispensed, till, currency, line,
+                                );
+                                if let Err(e) = checked {
+                                    abort_internal_error(e, sales);
+                                }
+                            }
+                            breakdown = dispensed.breakdown;
+                            shortfall = dispensed.shortfall_cents;
+                            if write_iou && shortfall > 0 {
+                                iou = shortfall as u32;
+                                shortfall = 0;
+                                till.record_iou(line, iou);
+                            }
+                            let pieces_out: Vec<(u32, u32)> = currency
+                                .pieces(&breakdown)
+                                .map(|(d, c)| (d.cents, c))
+                                .collect();
+                            let event = if refund {
+                                JournalEvent::Refund {
+                                    paid_out: &pieces_out,
+                                }
+                            } else {
+                                JournalEvent::Sale {
+                                    tendered,
+                                    change: &pieces_out,
+                                }
+                            };
+                            log.till(line, fields.register(), event);
+                            if let Some(journal) = journal.as_mut() {
+                                let result =
+                                    journal.record(line, fields.register(), event, till, currency);
+                                if report_journal_error(result, &journal_path) {
+                                    status.fail(EXIT_IO);
+                                }
+                            }
+                        }
+                        Err(e) => {
+                            if fail_line(
+                                e,
+                                line,
+                                &policies,
+                                &mut status,
+                                &mut reports,
+                                &mut sales,
+                                &mut log,
+                            ) {
+                                break;
+                            }
+                            continue;
+                        }
+                    }
+                }
+                if let (Some(_), Some(id)) = (args.dedupe, &fields.id) {
+                    seen.record(id, line);
+                }
+                // Receipts are for sales; a refund has its own paperwork.
+                if let (Some(printer), false) = (receipts.as_mut(), refund) {
+                    let receipt = format_receipt(&transaction, &breakdown, donation, currency);
+                    if let Err(e) = printer.write_all(&receipt) {
+                        // Don't try every remaining sale against a dead printer.
+                        eprintln_or_hold!(
+                            "Error writing {}: {e}",
+                            args.receipt.as_deref().unwrap_or_default()
+                        );
+                        status.fail(EXIT_IO);
+                        receipts = None;
+                    }
+                }
+                let not_given = shortfall + i64::from(iou);
+                match totals.as_mut() {
+                    Some(totals) if refund => {
+                        let amount = transaction.change_cents();
+                        totals.record_refund(line, amount, &breakdown, not_given, currency);
+                    }
+                    Some(totals) => {
+                        totals.record(
+                            line,
+                            &transaction,
+                            &breakdown,
+                            not_given,
+                            donation,
+                            currency,
+                        );
+                    }
+                    None => {}
+                }
+                let rounded = args.roundup.is_some() && !refund && foreign_change.is_none();
+                if rounded {
+                    rounded_sales += 1;
+                    if !donation.is_zero() {
+                        donated += donation;
+                        donors += 1;
+                    }
+                }
+                let weight = args.weights.then(|| {
+                    let change_currency = foreign_change.map_or(currency, |(c, _)| c);
+                    Heft::of(&breakdown, change_currency)
+                });
+                if let Some(weight) = weight {
+                    weighed_lines += 1;
+                    match weight {
+                        Some(weight) => heft += weight,
+                        None => unweighed += 1,
+                    }
+                }
+                log.sale(
+                    line,
+                    fields.register(),
+                    &transaction,
+                    &breakdown,
+                    is_random,
+                    shortfall,
+                    donation,
+                    refund,
+                );
+                export.sale(line, &transaction, &breakdown, is_random, donation, refund);
+                let suspicious = detector
+                    .as_mut()
+                    .map(|detector| detector.check(line, &transaction, refund))
+                    .unwrap_or_default();
+                if let Some(report) = cash_report.as_mut() {
+                    report.check(line, &transaction, refund, &fields);
+                }
+                let points = loyalty.as_mut().map(|loyalty| {
+                    let amount = if refund {
+                        transaction.change_cents()
+                    } else {
+                        transaction.owed_cents()
+                    };
+                    loyalty.record(fields.customer.as_deref(), amount, refund)
+                });
+                if let Some(id) = &fields.id {
+                    let undo = Undo {
+                        register: fields.register().to_string(),
+                        tendered: fields.tendered.clone().unwrap_or_default(),
+                        transaction: transaction.clone(),
+                        breakdown: breakdown.clone(),
+                        not_given,
+                        donation,
+                        refund,
+                        rounded,
+                        heft: weight,
+                        points: points.clone(),
+                    };
+                    voids.remember(id, line, undo);
                 }
+                let output = Output::Sale(Box::new(Sale {
+                    line,
+                    transaction,
+                    breakdown,
+                    is_random,
+                    shortfall,
+                    iou,
+                    extra: fields.extra,
+                    overpaid,
+                    rounded: fields.rounded,
+                    split: fields.split,
+                    foreign: fields.foreign.map(Box::new),
+                    foreign_change: foreign_change.map(|(c, change)| (c.clone(), change)),
+                    donated: donation,
+                    refund,
+                    suspicious,
+                    points,
+                }));
+                let stop = hook
+                    .as_mut()
+                    .is_some_and(|hook| hook.run(line, &output, &mut status));
+                notifier.send(&output);
+                sales.send(output);
+                if stop {
+                    break;
+                }
+            }
+            // The lines before it were processed; still save the drawers.
+            Err(e @ CashRegisterError::Io(_)) => {
+                eprintln_or_hold!("Error reading {file_path}: {e}");
+                status.fail(e.exit_code());
+                log.error(&e);
+                sales.record_error(&e);
+            }
+            Err(CashRegisterError::Underpayment { owed, paid, .. })
+                if policies.get(ErrorCategory::Underpayment) == OnError::Placeholder =>
+            {
+                // Both amounts parsed; paid just didn't cover owed.
+                let amount = |s: &str| {
+                    let (cents, _) = parse_dollars_to_cents_with(s, parse_options.rounding)
+                        .expect("already parsed");
+                    cents
+                };
+                let (owed_cents, paid_cents) = (amount(&owed), amount(&paid));
+                let short = owed_cents.checked_sub(paid_cents).unwrap_or_default();
+                sales.send(Output::Underpaid(Underpaid {
+                    line,
+                    owed: owed_cents,
+                    paid: paid_cents,
+                    breakdown: greedy.make_change(short, currency).expect(EXACT_CHANGE),
+                }));
+                let e = CashRegisterError::Underpayment { line, owed, paid };
+                status.fail(e.exit_code());
+                log.error(&e);
+                sales.record_error(&e);
+                reports.report(e);
             }
             Err(e) => {
-                eprintln!("{e}");
-                had_error = true;
+                if fail_line(
+                    e,
+                    line,
+                    &policies,
+                    &mut status,
+                    &mut reports,
+                    &mut sales,
+                    &mut log,
+                ) {
+                    break;
+                }
             }
         }
     }
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        status.fail(EXIT_INTERRUPTED);
+    }
+    // Every sale is printed before the drawer report.
+    sales.tick(registers.as_ref());
+    sales.finish();
+    reports.finish();
+    if !overpaid_lines.is_empty() {
+        eprintln_or_hold!(
+            "{}",
+            format_overpayments(&overpaid_lines, args.parse.warn_overpayment)
+        );
+    }
+    if let Some(increment) = args.roundup {
+        eprintln_or_hold!(
+            "{}",
+            format_donations(donated, donors, rounded_sales, increment, currency)
+        );
+    }
+    if args.weights {
+        eprintln_or_hold!("{}", format_heft_total(heft, weighed_lines, unweighed));
+    }
+    if let Some(findings) = detector.and_then(|d| format_findings(d.findings(), currency)) {
+        eprintln_or_hold!("{findings}");
+    }
+    if let Some(loyalty) = &loyalty {
+        eprintln_or_hold!("{}", format_loyalty(loyalty, currency));
+    }
+    if let Some(report) = &cash_report {
+        eprintln_or_hold!("{}", format_cash_report(report, currency));
+    }
+    if !voids.entries().is_empty() {
+        eprintln_or_hold!("{}", format_voids(voids.entries(), currency));
+    }
 
-    if had_error {
-        process::exit(2);
+    if let Some(totals) = &totals {
+        stdout.emit(&format!("\n{}", format_totals(totals, currency)), false);
+        if !totals.is_balanced() {
+            status.fail(EXIT_SOFTWARE);
+        }
+    }
+
+    if let Some(registers) = registers.as_mut() {
+        if registers.is_empty() {
+            // Nothing was rung up; still report the opening float.
+            registers.till_mut("");
+        }
+        let report = registers
+            .iter()
+            .map(|(register, till)| format_till_report(register, till, currency))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        match &till_report_path {
+            Some(path) => {
+                if let Err(e) = fs::write(path, format!("{report}\n")) {
+                    eprintln_or_hold!("Error writing {path}: {e}");
+                    status.fail(EXIT_IO);
+                }
+            }
+            // MessagePack output is binary, with no room for text.
+            None if config.output == OutputFormat::Msgpack => eprintln_or_hold!("{report}"),
+            None => stdout.emit(&format!("\n{report}"), false),
+        }
+
+        match &till_state_path {
+            Some(path) if dry_run => eprintln_or_hold!("dry run: {path} not updated"),
+            Some(path) => {
+                if let Err(e) = write_atomically(path, &registers.to_state_json(currency)) {
+                    eprintln_or_hold!("Error writing {path}: {e}");
+                    status.fail(EXIT_IO);
+                }
+            }
+            None => {}
+        }
+    }
+
+    match &args.seen_ids {
+        Some(path) if dry_run => eprintln_or_hold!("dry run: {path} not updated"),
+        Some(path) => {
+            if let Err(e) = write_atomically(path, &seen.to_lines()) {
+                eprintln_or_hold!("Error writing {path}: {e}");
+                status.fail(EXIT_IO);
+            }
+        }
+        None => {}
+    }
+
+    if let Some(journal) = journal.as_mut() {
+        if report_journal_error(journal.finish(), &journal_path) {
+            status.fail(EXIT_IO);
+        }
+    }
+    stdout.end();
+    match stdout.failed() {
+        // Whoever was reading stopped, as `head` does: not an error.
+        Some(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+        Some(e) => {
+            eprintln_or_hold!("Error writing output: {e}");
+            status.fail(EXIT_IO);
+        }
+        None => {}
+    }
+
+    log.finish(&mut status);
+    export.finish(&mut status);
+    notifier.finish(&mut status);
+
+    if args.stats {
+        eprintln_or_hold!(
+            "{}",
+            format_stats(
+                started.elapsed().as_secs_f64(),
+                transactions,
+                bytes_read.load(Ordering::Relaxed),
+                peak_memory_kib(),
+            )
+        );
+    }
+
+    info!(exit_code = ?status.0, "batch finished");
+    status.exit();
+}
+
+/// What a `VOID` line needs to reverse a sale or refund with an `id=`.
+struct Undo {
+    register: String,
+    /// The cash the customer handed over, when the line said.
+    tendered: Vec<(u32, u32)>,
+    transaction: Transaction,
+    /// The change or refund handed over.
+    breakdown: Breakdown,
+    not_given: i64,
+    donation: Cents,
+    refund: bool,
+    /// Whether it counted towards `--roundup`'s sales.
+    rounded: bool,
+    /// What `--weights` weighed it at, when it was weighed: `None` inside
+    /// if its pieces couldn't be.
+    heft: Option<Option<Heft>>,
+    /// The `--loyalty-rate` points it earned.
+    points: Option<Earned>,
+}
+
+/// Handle a failed line as `--on-error` says: report it, print a
+/// placeholder in its place if asked, and return whether to stop the run.
+fn fail_line(
+    e: CashRegisterError,
+    line: usize,
+    policies: &ErrorPolicies,
+    status: &mut ExitStatus,
+    reports: &mut ErrorReports,
+    sales: &mut SaleSink,
+    log: &mut RunLog,
+) -> bool {
+    status.fail(e.exit_code());
+    log.error(&e);
+    sales.record_error(&e);
+    let action = policies.for_error(&e);
+    if action == OnError::Placeholder {
+        sales.send(Output::Failed(Failed {
+            line,
+            code: e.code(),
+            message: e.to_string(),
+        }));
+    }
+    reports.report(&e);
+    if action == OnError::Abort {
+        let category = e.category().expect("only categorized errors abort");
+        eprintln_or_hold!(
+            "Aborted at line {line} (--on-error {}=abort)",
+            category.as_str()
+        );
+    }
+    action == OnError::Abort
+}
+
+/// `--exec-hook`: a shell command run after each transaction, with the
+/// transaction's `--output json` line on stdin. Its stdout goes to stderr,
+/// so it can't interleave with the run's output.
+struct Hook {
+    command: String,
+    on_failure: HookFailure,
+    /// Renders the JSON line, whatever `--output` is.
+    printer: Printer,
+}
+
+impl Hook {
+    /// Run the command for the transaction on `line`, handle a failure as
+    /// `--hook-failure` says, and return whether to stop the run.
+    fn run(&mut self, line: usize, output: &Output, status: &mut ExitStatus) -> bool {
+        let json = format!("{}\n", self.printer.render(output));
+        let Err(e) = self.spawn(line, &json) else {
+            return false;
+        };
+        if self.on_failure == HookFailure::Ignore {
+            return false;
+        }
+        eprintln_or_hold!("line {line}: {e}");
+        status.fail(EXIT_HOOK);
+        if self.on_failure == HookFailure::Abort {
+            eprintln_or_hold!("Aborted at line {line} (--hook-failure abort)");
+        }
+        self.on_failure == HookFailure::Abort
+    }
+
+    fn spawn(&self, line: usize, json: &str) -> Result<(), String> {
+        let (shell, flag) = if cfg!(windows) {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+        let command = &self.command;
+        let mut child = process::Command::new(shell)
+            .args([flag, command])
+            .env("CASH_REGISTER_LINE", line.to_string())
+            .stdin(process::Stdio::piped())
+            .stdout(io::stderr())
+            .spawn()
+            .map_err(|e| format!("can't run hook `{command}`: {e}"))?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        match stdin.write_all(json.as_bytes()) {
+            // A hook that doesn't read its input is fine.
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+                let _ = child.wait();
+                return Err(format!("can't write to hook `{command}`: {e}"));
+            }
+            _ => drop(stdin),
+        }
+        match child.wait() {
+            Ok(exit) if exit.success() => Ok(()),
+            Ok(exit) => Err(format!("hook `{command}` failed ({exit})")),
+            Err(e) => Err(format!("can't run hook `{command}`: {e}")),
+        }
+    }
+}
+
+/// Stop a `--paranoid` run on a failed self-check. The lines before it are
+/// still printed; the drawers and seen IDs are left unsaved, since they
+/// can't be trusted.
+fn abort_internal_error(e: CashRegisterError, sales: SaleSink) -> ! {
+    sales.finish();
+    eprintln!("{e}");
+    eprintln!(
+        "This is a bug in cash-register; the run was aborted and the drawers were not saved."
+    );
+    process::exit(e.exit_code());
+}
+
+/// The exit code so far for a command that keeps going after failures.
+#[derive(Default)]
+struct ExitStatus(Option<i32>);
+
+impl ExitStatus {
+    /// Note a failure, keeping whichever code comes first in
+    /// `EXIT_PRECEDENCE`.
+    fn fail(&mut self, code: i32) {
+        let rank = |code| {
+            EXIT_PRECEDENCE
+                .iter()
+                .position(|&c| c == code)
+                .unwrap_or(EXIT_PRECEDENCE.len())
+        };
+        if self.0.is_none_or(|current| rank(code) < rank(current)) {
+            self.0 = Some(code);
+        }
+    }
+
+    /// Exit with the code, if anything failed; otherwise return.
+    fn exit(self) {
+        if let Some(code) = self.0 {
+            process::exit(code);
+        }
+    }
+}
+
+/// Set by the first SIGINT or SIGTERM.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Catch SIGINT and SIGTERM so a run stops between lines instead of in the
+/// middle of one. The first sets `INTERRUPTED` and calls `on_first` (on the
+/// handler's own thread); a second exits at once, for a run stuck waiting on
+/// input.
+fn stop_on_interrupt(on_first: impl Fn() + Send + 'static) {
+    let handled = ctrlc::set_handler(move || {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            process::exit(EXIT_INTERRUPTED);
+        }
+        on_first();
+    });
+    if let Err(e) = handled {
+        warn!(error = %e, "can't catch interrupts; Ctrl-C will stop the run mid-line");
+    }
+}
+
+/// Prints line errors to stderr as they happen, or with
+/// `--max-error-reports N` only the first N, so millions of bad lines don't
+/// flood the terminal. Every error is still counted.
+struct ErrorReports {
+    limit: Option<u64>,
+    total: u64,
+}
+
+impl ErrorReports {
+    fn new(limit: Option<u64>) -> Self {
+        Self { limit, total: 0 }
     }
+
+    fn report(&mut self, error: impl std::fmt::Display) {
+        self.total += 1;
+        if self.limit.is_none_or(|limit| self.total <= limit) {
+            eprintln_or_hold!("{error}");
+        }
+    }
+
+    /// Say how many errors went unprinted, if any.
+    fn finish(&self) {
+        let unprinted = self
+            .limit
+            .map_or(0, |limit| self.total.saturating_sub(limit));
+        if unprinted > 0 {
+            let noun = if unprinted == 1 { "error" } else { "errors" };
+            eprintln_or_hold!("…and {} more {noun}", with_thousands(unprinted));
+        }
+    }
+}
+
+/// The `--log-db` database for a run, if there is one. Without the `sqlite`
+/// feature there never is, and logging does nothing.
+///
+/// A failed write is reported once and stops the logging, so every
+/// remaining line doesn't fail the same way; the run carries on and exits
+/// with `EXIT_IO`.
+#[derive(Default)]
+struct RunLog {
+    #[cfg(feature = "sqlite")]
+    db: Option<(LogDb, String, Currency)>,
+    failed: bool,
+}
+
+#[cfg_attr(not(feature = "sqlite"), allow(unused_variables, clippy::unused_self))]
+impl RunLog {
+    /// Open the database at `path`, if given, or report the error and exit.
+    #[cfg(feature = "sqlite")]
+    fn open(path: Option<&str>, currency: &Currency) -> Self {
+        let db = path.map(|path| {
+            let db = LogDb::open(path, currency).unwrap_or_else(|e| {
+                eprintln_or_hold!("Error opening {path}: {e}");
+                process::exit(EXIT_IO);
+            });
+            (db, path.to_string(), currency.clone())
+        });
+        Self { db, failed: false }
+    }
+
+    /// Log a sale, or with `refund`, a refund paid out as the change on
+    /// `transaction`. Change `donated` by `--roundup` is logged as owed, so
+    /// `change_cents` is still the change due.
+    #[allow(clippy::too_many_arguments)]
+    fn sale(
+        &mut self,
+        line: usize,
+        register: &str,
+        transaction: &Transaction,
+        breakdown: &Breakdown,
+        is_random: bool,
+        shortfall: i64,
+        donated: Cents,
+        refund: bool,
+    ) {
+        #[cfg(feature = "sqlite")]
+        self.write(|db, currency| {
+            let change: Vec<(u32, u32)> = currency
+                .pieces(breakdown)
+                .map(|(d, count)| (d.cents, count))
+                .collect();
+            if refund {
+                let amount = transaction.change_cents();
+                db.record_refund(line, register, amount, &change, shortfall)
+            } else {
+                let charged = charged(transaction, donated);
+                db.record_sale(line, register, &charged, &change, is_random, shortfall)
+            }
+        });
+    }
+
+    /// Log the `VOID` on `line` taking back what was logged on `original`.
+    fn void(&mut self, line: usize, original: usize) {
+        #[cfg(feature = "sqlite")]
+        self.write(|db, _| db.record_void(line, original));
+    }
+
+    fn error(&mut self, error: &CashRegisterError) {
+        #[cfg(feature = "sqlite")]
+        self.write(|db, _| db.record_error(error));
+    }
+
+    fn till(&mut self, line: usize, register: &str, event: JournalEvent) {
+        #[cfg(feature = "sqlite")]
+        self.write(|db, _| db.record_till(line, register, event));
+    }
+
+    #[cfg(feature = "sqlite")]
+    fn write(&mut self, f: impl FnOnce(&mut LogDb, &Currency) -> Result<(), CashRegisterError>) {
+        if let Some((db, path, currency)) = self.db.as_mut() {
+            if let Err(e) = f(db, currency) {
+                eprintln_or_hold!("Error writing {path}: {e}");
+                self.db = None;
+                self.failed = true;
+            }
+        }
+    }
+
+    /// Commit the run's rows.
+    fn finish(self, status: &mut ExitStatus) {
+        #[cfg(feature = "sqlite")]
+        if let Some((db, path, _)) = self.db {
+            if let Err(e) = db.finish() {
+                eprintln_or_hold!("Error writing {path}: {e}");
+                status.fail(EXIT_IO);
+            }
+        }
+        if self.failed {
+            status.fail(EXIT_IO);
+        }
+    }
+}
+
+/// `--webhook`, when the `webhook` feature is on: each transaction's JSON
+/// line POSTed as it's made.
+///
+/// A POST that still fails after its retries is reported once and stops
+/// the posting, as for `RunLog`; the run carries on and exits with
+/// `EXIT_IO`.
+#[derive(Default)]
+struct Notifier {
+    /// The webhook, its URL, and a printer for the JSON lines.
+    #[cfg(feature = "webhook")]
+    webhook: Option<(Webhook, String, Printer)>,
+    failed: bool,
+}
+
+#[cfg_attr(not(feature = "webhook"), allow(unused_variables, clippy::unused_self))]
+impl Notifier {
+    /// POST to `url`, if given, or report a bad URL and exit.
+    #[cfg(feature = "webhook")]
+    fn open(url: Option<&str>, batch: NonZeroUsize, retries: u32, printer: &Printer) -> Self {
+        let webhook = url.map(|url| {
+            let webhook = Webhook::new(url, batch, retries).unwrap_or_else(|e| {
+                eprintln_or_hold!("Error: --webhook {e}");
+                process::exit(EXIT_USAGE);
+            });
+            let printer = Printer {
+                output: OutputFormat::Json,
+                ..printer.clone()
+            };
+            (webhook, url.to_string(), printer)
+        });
+        Self {
+            webhook,
+            failed: false,
+        }
+    }
+
+    fn send(&mut self, output: &Output) {
+        #[cfg(feature = "webhook")]
+        if let Some((webhook, url, printer)) = self.webhook.as_mut() {
+            let json = printer.render(output).to_string();
+            if let Err(e) = webhook.send(json) {
+                eprintln_or_hold!("Error posting to {url}: {e}");
+                self.webhook = None;
+                self.failed = true;
+            }
+        }
+    }
+
+    /// POST whatever is left of the last batch.
+    fn finish(self, status: &mut ExitStatus) {
+        #[cfg(feature = "webhook")]
+        if let Some((mut webhook, url, _)) = self.webhook {
+            if let Err(e) = webhook.flush() {
+                eprintln_or_hold!("Error posting to {url}: {e}");
+                status.fail(EXIT_IO);
+            }
+        }
+        if self.failed {
+            status.fail(EXIT_IO);
+        }
+    }
+}
+
+/// `--parquet`, when the `parquet` feature is on: a row for each sale and
+/// refund, written out when the run ends. A failed write stops the export,
+/// as for `RunLog`, and the run exits with `EXIT_IO`.
+#[derive(Default)]
+struct Export {
+    #[cfg(feature = "parquet")]
+    file: Option<(ParquetFile, String)>,
+    failed: bool,
+}
+
+#[cfg_attr(not(feature = "parquet"), allow(unused_variables, clippy::unused_self))]
+impl Export {
+    /// Create the file at `path`, if given, or report the error and exit.
+    #[cfg(feature = "parquet")]
+    fn open(path: Option<&str>, currency: &Currency) -> Self {
+        let file = path.map(|path| {
+            let file = ParquetFile::create(path, currency).unwrap_or_else(|e| {
+                eprintln_or_hold!("Error opening {path}: {e}");
+                process::exit(EXIT_IO);
+            });
+            (file, path.to_string())
+        });
+        Self {
+            file,
+            failed: false,
+        }
+    }
+
+    /// Add a sale, or with `refund`, a refund paid out as the change on
+    /// `transaction`. Change `donated` by `--roundup` counts as owed, as in
+    /// `RunLog::sale`.
+    fn sale(
+        &mut self,
+        line: usize,
+        transaction: &Transaction,
+        breakdown: &Breakdown,
+        is_random: bool,
+        donated: Cents,
+        refund: bool,
+    ) {
+        #[cfg(feature = "parquet")]
+        self.write(|file| {
+            if refund {
+                file.record_refund(line, transaction.change_cents(), breakdown)
+            } else {
+                file.record_sale(line, &charged(transaction, donated), breakdown, is_random)
+            }
+        });
+    }
+
+    /// Add the `VOID` on `line` taking back `undo`'s sale or refund.
+    fn void(&mut self, line: usize, undo: &Undo) {
+        #[cfg(feature = "parquet")]
+        self.write(|file| {
+            let transaction = charged(&undo.transaction, undo.donation);
+            file.record_void(line, &transaction, &undo.breakdown, undo.refund)
+        });
+    }
+
+    #[cfg(feature = "parquet")]
+    fn write(&mut self, f: impl FnOnce(&mut ParquetFile) -> Result<(), CashRegisterError>) {
+        if let Some((file, path)) = self.file.as_mut() {
+            if let Err(e) = f(file) {
+                eprintln_or_hold!("Error writing {path}: {e}");
+                self.file = None;
+                self.failed = true;
+            }
+        }
+    }
+
+    /// Write the last rows and the file's footer.
+    fn finish(self, status: &mut ExitStatus) {
+        #[cfg(feature = "parquet")]
+        if let Some((file, path)) = self.file {
+            if let Err(e) = file.finish() {
+                eprintln_or_hold!("Error writing {path}: {e}");
+                status.fail(EXIT_IO);
+            }
+        }
+        if self.failed {
+            status.fail(EXIT_IO);
+        }
+    }
+}
+
+/// A sale with change `donated` by `--roundup` counted as owed, as the
+/// `--log-db` and `--parquet` rows have it.
+#[cfg(any(feature = "sqlite", feature = "parquet"))]
+fn charged(transaction: &Transaction, donated: Cents) -> Transaction {
+    if donated.is_zero() {
+        return transaction.clone();
+    }
+    let owed = transaction.owed_cents() + donated;
+    Transaction::new(owed, transaction.paid_cents()).expect("the donation came out of the change")
+}
+
+/// A refund of `amount` as the drawer sees it: nothing paid in, and the
+/// amount paid out as change.
+fn payout(amount: Cents) -> Transaction {
+    Transaction::new(Cents(0), amount).expect("a parsed amount fits")
+}
+
+/// Whether a line drew a `--warn-overpayment` warning.
+fn is_overpaid(warnings: &[ParseWarning]) -> bool {
+    warnings
+        .iter()
+        .any(|w| matches!(w, ParseWarning::LargeOverpayment { .. }))
+}
+
+/// How many lines of the overpaid lines `format_overpayments` names.
+const OVERPAID_LINES_SHOWN: usize = 10;
+
+/// The end-of-run reminder about overpaid lines, which are usually a typo
+/// in the paid amount: `warning: 2 transactions overpaid past 10x (lines 4,
+/// 9); check the paid amounts`.
+fn format_overpayments(lines: &[usize], threshold: OverpaymentThreshold) -> String {
+    let noun = if lines.len() == 1 {
+        "transaction"
+    } else {
+        "transactions"
+    };
+    let mut shown = lines
+        .iter()
+        .take(OVERPAID_LINES_SHOWN)
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ");
+    if lines.len() > OVERPAID_LINES_SHOWN {
+        shown.push_str(", …");
+    }
+    let label = if lines.len() == 1 { "line" } else { "lines" };
+    format!(
+        "warning: {} {noun} overpaid past {threshold} ({label} {shown}); check the paid amounts",
+        with_thousands(lines.len() as u64)
+    )
+}
+
+/// `14203` as `14,203`.
+fn with_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+/// The `--stats` line: `key=value` pairs, so a harness can track them
+/// across releases.
+fn format_stats(elapsed: f64, transactions: u64, bytes: u64, peak_kib: Option<u64>) -> String {
+    let per_second = |n: u64| {
+        if elapsed > 0.0 {
+            n as f64 / elapsed
+        } else {
+            0.0
+        }
+    };
+    let peak = peak_kib.map_or_else(|| "unknown".to_string(), |kib| kib.to_string());
+    format!(
+        "stats: elapsed_secs={elapsed:.3} transactions={transactions} \
+         transactions_per_sec={:.0} bytes={bytes} bytes_per_sec={:.0} peak_rss_kib={peak}",
+        per_second(transactions),
+        per_second(bytes),
+    )
+}
+
+/// The process's peak resident memory, where the OS reports it (Linux).
+fn peak_memory_kib() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// A `BufRead` that counts the bytes consumed from it, for `--stats`.
+struct CountingReader<R> {
+    inner: R,
+    bytes: Arc<AtomicU64>,
+}
+
+impl<R: io::Read> io::Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for CountingReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.bytes.fetch_add(amount as u64, Ordering::Relaxed);
+        self.inner.consume(amount);
+    }
+}
+
+/// `validate`: parse a transaction file and check its tenders, reporting
+/// every problem without making change. Exits 2 on malformed lines and 3 on
+/// refused tenders, like `run`.
+fn validate(args: ValidateArgs) {
+    let mut config = load_config();
+    if let Some(currency) = args.currency {
+        config.currency = currency;
+    }
+    let currency = &currency_or_exit(&config);
+    let tender_policy = args.tender.to_policy();
+
+    let mut transactions = 0;
+    let mut errors = 0;
+    let mut refused = 0;
+    let mut overpaid = 0;
+    let mut status = ExitStatus::default();
+    let mut reports = ErrorReports::new(args.max_error_reports);
+    let input = open_or_exit(&args.input);
+    for (line, result, warnings) in read_entries_with(input, args.parse.to_options(&config)) {
+        for warning in &warnings {
+            eprintln!("{warning}");
+        }
+        if is_overpaid(&warnings) {
+            overpaid += 1;
+        }
+        match result {
+            Ok(Entry::Till(..) | Entry::Void(_)) => {}
+            Ok(Entry::Refund(amount, _)) => {
+                transactions += 1;
+                if let Some(max) = args.max_amount {
+                    if let Err(e) = payout(amount).check_max_amount(max, line) {
+                        status.fail(e.exit_code());
+                        reports.report(e);
+                        errors += 1;
+                    }
+                }
+            }
+            Ok(Entry::Transaction(transaction, fields)) => {
+                transactions += 1;
+                if let Some(max) = args.max_amount {
+                    if let Err(e) = transaction.check_max_amount(max, line) {
+                        status.fail(e.exit_code());
+                        reports.report(e);
+                        errors += 1;
+                        continue;
+                    }
+                }
+                if let Some(tendered) = &fields.tendered {
+                    if let Err(e) = tender_policy.check(tendered, currency, line) {
+                        status.fail(e.exit_code());
+                        reports.report(e);
+                        refused += 1;
+                    }
+                }
+            }
+            Err(e @ CashRegisterError::Io(_)) => {
+                eprintln!("Error reading {}: {e}", args.input);
+                status.fail(e.exit_code());
+                errors += 1;
+            }
+            Err(e) => {
+                status.fail(e.exit_code());
+                reports.report(e);
+                errors += 1;
+            }
+        }
+    }
+    reports.finish();
+
+    println!(
+        "{}: {transactions} transactions, {errors} errors, {refused} refused, {overpaid} overpaid",
+        args.input
+    );
+    status.exit();
+}
+
+/// `recommend-float`: replay a day of sales (real history or a simulated
+/// batch) and print the rolls and straps to order from the bank so the drawer
+/// never runs dry. With `--till`, only what the drawer doesn't already hold
+/// is ordered.
+fn recommend_float(args: RecommendFloatArgs) {
+    let config = args.config.to_config();
+    let currency = &currency_or_exit(&config);
+    let divisor = config.rules_divisor();
+    let mut rng = config.rng();
+    let mut greedy = GreedyTable::new(currency);
+
+    let on_hand = match &args.till {
+        Some(path) => till_or_exit(path, currency),
+        None => Till::new(),
+    };
+
+    let mut status = ExitStatus::default();
+    let mut demand = FloatDemand::new();
+    for (_, result) in parse_entries(&read_or_exit(&args.history)) {
+        match result {
+            Ok(Entry::Till(command, _)) => demand.record_command(command),
+            Ok(Entry::Void(_)) => {}
+            Ok(Entry::Refund(amount, _)) => {
+                let breakdown = greedy.make_change(amount, currency).expect(EXACT_CHANGE);
+                demand.record_sale(&[], &breakdown, currency);
+            }
+            Ok(Entry::Transaction(transaction, fields)) => {
+                let breakdown =
+                    make_change_with(&transaction, currency, divisor, &mut rng, &mut greedy)
+                        .expect(EXACT_CHANGE);
+                demand.record_sale(
+                    fields.tendered.as_deref().unwrap_or_default(),
+                    &breakdown,
+                    currency,
+                );
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                status.fail(e.exit_code());
+            }
+        }
+    }
+
+    println!(
+        "{}",
+        format_bank_order(&demand.order(currency, &on_hand), currency)
+    );
+    status.exit();
+}
+
+/// `change`: make change for one transaction, as `run` would for a file
+/// holding just that line, and print it in the `--output` format.
+fn change(args: ChangeArgs) {
+    let config = args.config.to_config();
+    let currency = &currency_or_exit(&config);
+    let transaction = Transaction::new(args.owed, args.paid).unwrap_or_else(|e| {
+        eprintln!("error: {e}");
+        process::exit(match e {
+            TransactionError::Underpayment { .. } => 4,
+            TransactionError::TooLarge(_) => 2,
+        });
+    });
+    let divisor = config.rules_divisor();
+    let is_random = divisor > 0 && transaction.owed_cents().is_multiple_of(divisor);
+    let breakdown = make_change_with(
+        &transaction,
+        currency,
+        divisor,
+        &mut config.rng(),
+        &mut GreedyTable::new(currency),
+    )
+    .expect(EXACT_CHANGE);
+
+    if config.output == OutputFormat::Csv {
+        println!("{}", format_csv_header(currency));
+    }
+    let mut printer = Printer::new(&config, currency);
+    printer.print(&Output::Sale(Box::new(Sale {
+        line: 1,
+        transaction,
+        breakdown,
+        is_random,
+        shortfall: 0,
+        iou: 0,
+        extra: Vec::new(),
+        overpaid: false,
+        rounded: None,
+        split: None,
+        foreign: None,
+        foreign_change: None,
+        donated: Cents::ZERO,
+        refund: false,
+        suspicious: Vec::new(),
+        points: None,
+    })));
+}
+
+/// `stats`: make change for every transaction in a file, as `run` would
+/// without a drawer, and summarize it. Refunds aren't change and bad lines
+/// are reported; both are left out.
+fn stats(args: StatsArgs) {
+    let config = args.config.to_config();
+    let currency = &currency_or_exit(&config);
+    let divisor = config.rules_divisor();
+    let mut rng = config.rng();
+    let mut greedy = GreedyTable::new(currency);
+
+    let mut status = ExitStatus::default();
+    let mut stats = ChangeStats::new();
+    for (_, result) in parse_entries(&read_or_exit(&args.input)) {
+        match result {
+            Ok(Entry::Till(..) | Entry::Refund(..) | Entry::Void(_)) => {}
+            Ok(Entry::Transaction(transaction, _)) => {
+                let is_random = divisor > 0 && transaction.owed_cents().is_multiple_of(divisor);
+                let breakdown =
+                    make_change_with(&transaction, currency, divisor, &mut rng, &mut greedy)
+                        .expect(EXACT_CHANGE);
+                stats.record(&transaction, &breakdown, is_random, currency);
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                status.fail(e.exit_code());
+            }
+        }
+    }
+
+    println!("{}", format_change_stats(&stats, currency));
+    status.exit();
+}
+
+/// `export`: make change for every transaction in a file, as `run` would
+/// without a drawer, and print its sales and refunds as QIF or OFX. Bad
+/// lines are reported and left out, and so are voided sales and refunds.
+fn export(args: ExportArgs) {
+    let config = args.config.to_config();
+    let currency = &currency_or_exit(&config);
+    let divisor = config.rules_divisor();
+    let mut rng = config.rng();
+    let mut greedy = GreedyTable::new(currency);
+
+    let mut status = ExitStatus::default();
+    // Voided sales and refunds are left out: `None` in their place.
+    let mut sales: Vec<Option<ExportEntry>> = Vec::new();
+    let mut voids: Voids<usize> = Voids::new();
+    for (line, result) in parse_entries(&read_or_exit(&args.input)) {
+        let (entry, id) = match result {
+            Ok(Entry::Till(..)) => continue,
+            Ok(Entry::Void(id)) => {
+                let voided = voids.take(&id, line).map(|(original, index)| {
+                    let (amount, refund) = match sales[index].take() {
+                        Some(ExportEntry::Sale(sale)) => (sale.transaction.owed_cents(), false),
+                        Some(ExportEntry::Refund { amount, .. }) => (amount, true),
+                        None => unreachable!("each entry is voided once"),
+                    };
+                    Voided {
+                        line,
+                        original,
+                        id,
+                        amount,
+                        refund,
+                    }
+                });
+                match voided {
+                    Ok(voided) => voids.record(voided),
+                    Err(e) => {
+                        eprintln!("{e}");
+                        status.fail(e.exit_code());
+                    }
+                }
+                continue;
+            }
+            Ok(Entry::Refund(amount, fields)) => {
+                let refund = ExportEntry::Refund {
+                    line,
+                    amount,
+                    breakdown: greedy.make_change(amount, currency).expect(EXACT_CHANGE),
+                };
+                (refund, fields.id)
+            }
+            Ok(Entry::Transaction(transaction, fields)) => {
+                let breakdown =
+                    make_change_with(&transaction, currency, divisor, &mut rng, &mut greedy)
+                        .expect(EXACT_CHANGE);
+                let sale = ExportEntry::Sale(ExportSale {
+                    line,
+                    transaction,
+                    breakdown,
+                    split: fields.split,
+                });
+                (sale, fields.id)
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                status.fail(e.exit_code());
+                continue;
+            }
+        };
+        if let Some(id) = id {
+            voids.remember(&id, line, sales.len());
+        }
+        sales.push(Some(entry));
+    }
+    let sales: Vec<ExportEntry> = sales.into_iter().flatten().collect();
+
+    let date = args
+        .date
+        .unwrap_or_else(|| rfc3339_utc(SystemTime::now())[..10].to_string());
+    let exported = match args.format {
+        ExportFormat::Qif => format_qif(&sales, &date, currency),
+        ExportFormat::Ofx => format_ofx(&sales, &date, currency),
+    };
+    print!("{exported}");
+    status.exit();
+}
+
+/// `simulate`: ring up a day of synthetic sales against the `--till` float
+/// and report when each denomination ran out, to size a new store's float.
+fn simulate(args: SimulateArgs) {
+    let config = args.config.to_config();
+    let currency = &currency_or_exit(&config);
+    let divisor = config.rules_divisor();
+    let mut rng = config.rng();
+    let mut till = match &args.till {
+        Some(path) => till_or_exit(path, currency),
+        None => Till::new(),
+    };
+
+    let mix = args.payment_mix.unwrap_or_default();
+    let day = generate_sales(args.sales, args.max_owed, mix, currency, &mut rng);
+    let report = run_sales(&day, &mut till, currency, divisor, &mut rng);
+    println!("{}", format_simulation(&report, currency));
+    println!("\n{}", format_till_report("", &till, currency));
+}
+
+/// `generate`: print `--sales` synthetic transaction lines, drawn the way
+/// `simulate` draws its day, with `--with-errors`' faults in some of them.
+fn generate(args: GenerateArgs) {
+    let config = args.config.to_config();
+    let currency = &currency_or_exit(&config);
+    let mut rng = config.rng();
+    let mix = args.payment_mix.unwrap_or_default();
+    // Faults are drawn apart from the sales, so the lines left alone are
+    // the ones a plain `generate` with the same seed prints.
+    let mut quirk_rng = StdRng::seed_from_u64(config.rng().gen());
+    let mut quirks = [0; Quirk::ALL.len()];
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let sales = sale_generator(args.amounts, args.max_owed, mix, currency, &mut rng);
+    // A write only fails once the reader has stopped, as `generate | head`
+    // does, and then there's no one left to tell.
+    let written = sales
+        .take(args.sales)
+        .try_for_each(|sale| {
+            let line = format_sale_line(&sale, args.tendered);
+            match args.with_errors.and_then(|mix| mix.pick(&mut quirk_rng)) {
+                Some(quirk) => {
+                    quirks[quirk as usize] += 1;
+                    writeln!(out, "{}", quirk.apply(&line, &sale, &mut quirk_rng))
+                }
+                None => writeln!(out, "{line}"),
+            }
+        })
+        .and_then(|()| out.flush());
+    if written.is_ok() && args.with_errors.is_some() {
+        eprintln!("{}", format_quirks(&quirks, args.sales));
+    }
+}
+
+/// `compare`: run a file as `run` would, and list the output lines that
+/// differ from `--against`'s saved output or from a second run with
+/// `--with`'s options added. Exits 2 when any do.
+fn compare(args: CompareArgs) {
+    let run = |extra| run_for_output(&args.input, &args.options, extra);
+    let before = match &args.against {
+        Some(path) => read_or_exit(path),
+        None => run(None),
+    };
+    let after = run(args.with.as_deref());
+    match format_output_diff(&before, &after) {
+        Some(diff) => {
+            println!("{diff}");
+            process::exit(2);
+        }
+        None => {
+            let lines = after.lines().count();
+            let noun = if lines == 1 { "line" } else { "lines" };
+            println!("No differences in {lines} output {noun}");
+        }
+    }
+}
+
+/// `audit`: make change for every line of a file, as `run` would without
+/// a drawer, and check the saved output line by line against what it
+/// prints. Lines that fail make no output, as in a run without
+/// placeholders. Exits 2 when any line doesn't match.
+fn audit(args: AuditArgs) {
+    let config = args.config.to_config();
+    if config.output == OutputFormat::Msgpack {
+        eprintln!("error: --output msgpack output is binary and can't be audited");
+        process::exit(EXIT_USAGE);
+    }
+    let currency = &currency_or_exit(&config);
+    let divisor = config.rules_divisor();
+    let mut rng = config.rng();
+    let mut greedy = GreedyTable::new(currency);
+    let mut printer = Printer::new(&config, currency);
+    if let Some(date) = args.date {
+        printer.date = date;
+    }
+
+    let recorded = read_or_exit(&args.recorded);
+    let mut audit = Audit::new(&recorded);
+    if config.output == OutputFormat::Csv {
+        audit.check(None, &format_csv_header(currency));
+    }
+    let input = open_or_exit(&args.input);
+    for (line, result, warnings) in read_entries_with(input, args.parse.to_options(&config)) {
+        let (transaction, fields, refund) = match result {
+            Ok(Entry::Transaction(transaction, fields)) => (transaction, fields, false),
+            Ok(Entry::Refund(amount, fields)) => (payout(amount), fields, true),
+            Ok(Entry::Till(..) | Entry::Void(_)) | Err(_) => continue,
+        };
+        let is_random = !refund && divisor > 0 && transaction.owed_cents().is_multiple_of(divisor);
+        let breakdown = if refund {
+            greedy.make_change(transaction.change_cents(), currency)
+        } else {
+            make_change_with(&transaction, currency, divisor, &mut rng, &mut greedy)
+        }
+        .expect(EXACT_CHANGE);
+        let output = Output::Sale(Box::new(Sale {
+            line,
+            transaction,
+            breakdown,
+            is_random,
+            shortfall: 0,
+            iou: 0,
+            extra: fields.extra,
+            overpaid: is_overpaid(&warnings),
+            rounded: fields.rounded,
+            split: fields.split,
+            foreign: fields.foreign.map(Box::new),
+            foreign_change: None,
+            donated: Cents::ZERO,
+            refund,
+            suspicious: Vec::new(),
+            points: None,
+        }));
+        audit.check(Some(line), printer.render(&output));
+    }
+
+    let report = audit.finish();
+    println!("{}", format_audit(&report));
+    if !report.is_clean() {
+        process::exit(2);
+    }
+}
+
+/// `verify`: check each line of a file signed by `--sign-key` and list
+/// the ones that were altered, moved, or never signed. Exits 2 when any
+/// line doesn't verify.
+fn verify(args: VerifyArgs) {
+    let signed = read_or_exit(&args.file);
+    let key = sign_key_or_exit(args.sign_key.as_deref(), args.sign_key_file.as_deref())
+        .expect("clap requires a key");
+    let mut verifier = Verifier::new(&key);
+    for line in signed.lines() {
+        verifier.check(line);
+    }
+    let report = verifier.finish();
+    println!("{}", format_verify(&report));
+    if !report.is_clean() {
+        process::exit(2);
+    }
+}
+
+/// The stdout of `run` on `input` with `options`, plus `extra` split on
+/// whitespace. A run that can't start (bad options, a missing file) is
+/// reported and exits with its code; lines that fail are part of the output.
+fn run_for_output(input: &str, options: &[String], extra: Option<&str>) -> String {
+    let exe = env::current_exe().unwrap_or_else(|e| {
+        eprintln!("Error finding this program to run it: {e}");
+        process::exit(EXIT_SETUP);
+    });
+    let output = process::Command::new(exe)
+        .arg("run")
+        .arg(input)
+        .args(options)
+        .args(extra.into_iter().flat_map(str::split_whitespace))
+        .stdin(process::Stdio::null())
+        .output()
+        .unwrap_or_else(|e| {
+            eprintln!("Error running {input}: {e}");
+            process::exit(EXIT_SETUP);
+        });
+    if let Some(code @ (EXIT_SETUP | EXIT_USAGE | EXIT_IO)) = output.status.code() {
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        process::exit(code);
+    }
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// `reconcile`: check a counted drawer against the saved state of its
+/// register. Exits 2 when they don't match.
+fn reconcile(args: ReconcileArgs) {
+    let mut config = load_config();
+    if let Some(currency) = args.currency {
+        config.currency = currency;
+    }
+    let currency = &currency_or_exit(&config);
+    let registers =
+        Registers::from_state_json(&read_or_exit(&args.till_state), currency, Till::new())
+            .unwrap_or_else(|e| {
+                eprintln!("{}: {e}", args.till_state);
+                process::exit(EXIT_SETUP);
+            });
+    let Some(expected) = registers.get(&args.register) else {
+        eprintln!(
+            "{}: no register \"{}\" in the saved state",
+            args.till_state, args.register
+        );
+        process::exit(EXIT_SETUP);
+    };
+    let counted = till_or_exit(&args.count, currency);
+
+    let discrepancies = expected.reconcile(&counted, currency);
+    println!(
+        "{}",
+        format_reconciliation(&args.register, &discrepancies, currency)
+    );
+    if !discrepancies.is_empty() {
+        process::exit(2);
+    }
+}
+
+fn deposit_slip(args: DepositSlipArgs) {
+    let mut config = load_config();
+    if let Some(currency) = args.currency {
+        config.currency = currency;
+    }
+    let currency = &currency_or_exit(&config);
+    let registers =
+        Registers::from_state_json(&read_or_exit(&args.till_state), currency, Till::new())
+            .unwrap_or_else(|e| {
+                eprintln!("{}: {e}", args.till_state);
+                process::exit(EXIT_SETUP);
+            });
+    let slips: Vec<String> = match &args.register {
+        Some(register) => {
+            let Some(till) = registers.get(register) else {
+                eprintln!(
+                    "{}: no register \"{register}\" in the saved state",
+                    args.till_state
+                );
+                process::exit(EXIT_SETUP);
+            };
+            vec![format_deposit_slip(register, till, currency)]
+        }
+        None => registers
+            .iter()
+            .map(|(register, till)| format_deposit_slip(register, till, currency))
+            .collect(),
+    };
+    println!("{}", slips.join("\n\n"));
+}
+
+/// `config show`: print the effective configuration as TOML, after config
+/// files, environment variables, and flags, noting which files it came from.
+fn config_show(args: ConfigArgs) {
+    for path in config_files() {
+        println!("# from {}", path.display());
+    }
+    let config = args.to_config();
+    print!(
+        "{}",
+        toml::to_string(&config).expect("config serializes to TOML")
+    );
+}
+
+impl ConfigArgs {
+    /// The config files' options with these flags on top.
+    fn to_config(&self) -> Config {
+        let mut config = load_config();
+        if let Some(currency) = &self.currency {
+            config.currency = currency.clone();
+        }
+        if let Some(divisor) = self.divisor {
+            config.divisor = divisor;
+        }
+        if self.seed.is_some() {
+            config.seed = self.seed;
+        }
+        config.verbose |= self.verbose;
+        config.ascii_symbols |= self.ascii_symbols;
+        if let Some(strategy) = self.strategy {
+            config.strategy = strategy;
+        }
+        if let Some(output) = self.output {
+            config.output = output;
+        }
+        for (key, glyph) in &self.glyphs {
+            config
+                .glyphs
+                .set(key, glyph)
+                .expect("checked by parse_glyph");
+        }
+        config
+    }
+}
+
+impl ParseArgs {
+    /// The parse options, rounding and converting as `--round-input` and
+    /// `--exchange-rate` say or else as `config` does.
+    fn to_options(&self, config: &Config) -> ParseOptions {
+        ParseOptions {
+            extra: self.extra_fields,
+            overpayment: self.warn_overpayment,
+            rounding: self.round_input.or(config.rounding),
+            exchange_rate: self.exchange_rate.or(config.exchange_rate),
+        }
+    }
+}
+
+impl TenderArgs {
+    fn to_policy(&self) -> TenderPolicy {
+        TenderPolicy {
+            max_bill: self.max_bill,
+            max_coins: self.max_coins,
+        }
+    }
+}
+
+/// Parse a `--flag` amount like `20.00`.
+fn parse_amount(s: &str) -> Result<Cents, String> {
+    parse_dollars_to_cents(s)
+}
+
+/// Parse a `--flag` amount that must be more than zero.
+fn parse_positive_amount(s: &str) -> Result<Cents, String> {
+    match parse_amount(s)? {
+        cents if cents.is_zero() => Err("must be more than zero".to_string()),
+        cents => Ok(cents),
+    }
+}
+
+/// A `--glyph` value, `coin=o`, `bill=[$]`, or `0.25=(Q)`.
+fn parse_glyph(s: &str) -> Result<(String, String), String> {
+    let (key, glyph) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid glyph \"{s}\" (expected KEY=GLYPH)"))?;
+    Glyphs::default().set(key, glyph)?;
+    Ok((key.to_string(), glyph.to_string()))
+}
+
+/// A `--date` value: a calendar date written `YYYY-MM-DD`.
+fn parse_date(s: &str) -> Result<String, String> {
+    let invalid = || format!("invalid date \"{s}\" (expected YYYY-MM-DD)");
+    let parts: Vec<&str> = s.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return Err(invalid());
+    };
+    let number = |part: &str, len: usize| {
+        (part.len() == len && part.bytes().all(|b| b.is_ascii_digit()))
+            .then(|| part.parse::<u32>().ok())
+            .flatten()
+    };
+    match (number(year, 4), number(month, 2), number(day, 2)) {
+        (Some(_), Some(1..=12), Some(1..=31)) => Ok(s.to_string()),
+        _ => Err(invalid()),
+    }
+}
+
+/// Read and parse a till file, or report the error and exit.
+fn till_or_exit(path: &str, currency: &Currency) -> Till {
+    parse_till(&read_or_exit(path), currency).unwrap_or_else(|e| {
+        eprintln!("{path}: {e}");
+        process::exit(EXIT_SETUP);
+    })
+}
+
+/// Send `tracing` events at `level` and above to stderr, as text or JSON
+/// lines. Off by default, so stderr holds only errors and warnings.
+fn init_logging(level: LevelFilter, json: bool) {
+    if level == LevelFilter::OFF {
+        return;
+    }
+    let logs = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(io::stderr);
+    if json {
+        logs.json().init();
+    } else {
+        logs.with_ansi(false).init();
+    }
+}
+
+/// Where `cash-register.toml` is looked for, lowest precedence first: the
+/// XDG config dir (`$XDG_CONFIG_HOME`, else `~/.config`), then the current
+/// dir. Only files that exist are returned.
+fn config_files() -> Vec<PathBuf> {
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+    config_dir
+        .into_iter()
+        .chain([PathBuf::from(".")])
+        .map(|dir| dir.join(CONFIG_FILE))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Defaults merged from every config file, later files overriding earlier
+/// ones key by key; exits on a file that can't be read or parsed.
+fn load_config() -> Config {
+    let mut merged = toml::Table::new();
+    for path in config_files() {
+        let display = path.display().to_string();
+        let text = read_or_exit(&display);
+        // Parse as a `Config` first, so unknown keys and bad values are
+        // reported against the file they're in.
+        let table = toml::from_str::<Config>(&text)
+            .and_then(|_| toml::from_str::<toml::Table>(&text))
+            .unwrap_or_else(|e| {
+                eprintln!("{display}: {e}");
+                process::exit(EXIT_SETUP);
+            });
+        merged.extend(table);
+    }
+    toml::Value::Table(merged)
+        .try_into()
+        .expect("merged config files stay valid")
+}
+
+/// The configured currency's denomination set, or exit.
+/// How many lines a `--pipeline` stage may run ahead of the next one before
+/// it blocks.
+const PIPELINE_DEPTH: usize = 1024;
+
+type ParsedLine = (usize, Result<Entry, CashRegisterError>, Vec<ParseWarning>);
+
+/// The `--pipeline` parse stage: read and parse `input` on its own thread,
+/// handing lines over as they're ready.
+fn parse_stage(
+    input: impl BufRead + Send + 'static,
+    options: ParseOptions,
+) -> impl Iterator<Item = ParsedLine> {
+    let (queue, parsed) = sync_channel(PIPELINE_DEPTH);
+    thread::spawn(move || {
+        for entry in read_entries_with(input, options) {
+            if queue.send(entry).is_err() {
+                break;
+            }
+        }
+    });
+    parsed.into_iter()
+}
+
+/// A sale that's been settled and is ready to print.
+struct Sale {
+    line: usize,
+    transaction: Transaction,
+    breakdown: Breakdown,
+    is_random: bool,
+    shortfall: i64,
+    iou: u32,
+    /// Input fields carried through by `--extra-fields metadata`.
+    extra: Vec<String>,
+    /// Paid past `--warn-overpayment`.
+    overpaid: bool,
+    /// Amounts `--round-input` rounded, as written.
+    rounded: Option<Rounded>,
+    /// How the payment was split across tenders, like card plus cash.
+    split: Option<SplitTender>,
+    /// What was handed over, when it was another currency. Boxed, as most
+    /// sales have none.
+    foreign: Option<Box<ForeignTender>>,
+    /// The change in the tendered currency, when it's given in that:
+    /// `breakdown` is in that currency's denominations.
+    foreign_change: Option<(Currency, Cents)>,
+    /// Change `--roundup` left off for charity: `breakdown` is the rest.
+    donated: Cents,
+    /// A refund: `transaction` paid nothing in and its change is the amount
+    /// paid out.
+    refund: bool,
+    /// What `--flag-suspicious` flagged it for.
+    suspicious: Vec<Pattern>,
+    /// The points `--loyalty-rate` awarded, or for a refund, took back.
+    points: Option<Earned>,
+}
+
+/// An underpaid line under `--on-underpayment report`.
+struct Underpaid {
+    line: usize,
+    owed: Cents,
+    paid: Cents,
+    /// The shortfall, in the fewest pieces.
+    breakdown: Breakdown,
+}
+
+/// A failed line under `--on-error CATEGORY=placeholder`.
+struct Failed {
+    line: usize,
+    code: &'static str,
+    message: String,
+}
+
+/// One line of `run` output, in input order. A sale is boxed, as it's
+/// several times the size of the others.
+enum Output {
+    Sale(Box<Sale>),
+    Underpaid(Underpaid),
+    Failed(Failed),
+}
+
+/// Formats and prints sales as `run` output lines.
+#[derive(Clone)]
+struct Printer {
+    output: OutputFormat,
+    verbose: bool,
+    currency: Currency,
+    /// Every currency, with the symbols `--ascii-symbols` calls for, for
+    /// naming what a `currency=` line was paid in.
+    currencies: Vec<Currency>,
+    /// Today's UTC date, `YYYY-MM-DD`, for `--output ledger` entries.
+    date: String,
+    /// Add the change's weight and size, for `--weights`.
+    weights: bool,
+    /// Print the JSON lines as MessagePack, for `--output msgpack`.
+    msgpack: bool,
+    /// Draw the change as pictures, for `--output pictogram`.
+    glyphs: Option<Glyphs>,
+    /// Where output lines are printed.
+    stdout: Stdout,
+    // One buffer for every text output line.
+    out: String,
+}
+
+impl Printer {
+    fn new(config: &Config, currency: &Currency) -> Self {
+        Self {
+            output: config.output,
+            verbose: config.verbose,
+            currency: currency.clone(),
+            currencies: CURRENCIES
+                .iter()
+                .map(|c| for_display(c, config.ascii_symbols))
+                .collect(),
+            date: rfc3339_utc(SystemTime::now())[..10].to_string(),
+            weights: false,
+            // MessagePack is the JSON re-encoded as it's printed.
+            msgpack: config.output == OutputFormat::Msgpack,
+            glyphs: (config.output == OutputFormat::Pictogram).then(|| config.glyphs.clone()),
+            stdout: Stdout::default(),
+            out: String::new(),
+        }
+    }
+
+    fn print(&mut self, output: &Output) {
+        // `render` leaves the line in `self.out`.
+        self.render(output);
+        self.stdout.emit(&self.out, self.msgpack);
+    }
+
+    /// The output line, without its newline. Under `--output msgpack`, the
+    /// JSON it's encoded from.
+    fn render(&mut self, output: &Output) -> &str {
+        match output {
+            Output::Sale(sale) => self.render_sale(sale),
+            Output::Underpaid(underpaid) => self.render_underpaid(underpaid),
+            Output::Failed(failed) => self.render_failed(failed),
+        }
+    }
+
+    fn render_failed(&mut self, failed: &Failed) -> &str {
+        self.out = match self.output {
+            OutputFormat::Json | OutputFormat::Msgpack => {
+                format_error_json(failed.line, failed.code, &failed.message)
+            }
+            OutputFormat::Text | OutputFormat::Pictogram => format!("error: {}", failed.code),
+            OutputFormat::Spoken => format!("Error {}.", failed.code),
+            OutputFormat::Ledger => format!("; line {}: error {}\n", failed.line, failed.code),
+            OutputFormat::Csv => format_error_csv(failed.line, None, failed.code, &self.currency),
+        };
+        &self.out
+    }
+
+    fn render_underpaid(&mut self, underpaid: &Underpaid) -> &str {
+        let Underpaid {
+            line,
+            owed,
+            paid,
+            ref breakdown,
+        } = *underpaid;
+        self.out = match self.output {
+            OutputFormat::Json | OutputFormat::Msgpack => {
+                format_underpayment_json(line, owed, paid, breakdown, &self.currency)
+            }
+            OutputFormat::Csv => format_error_csv(line, Some((owed, paid)), "E002", &self.currency),
+            OutputFormat::Ledger => {
+                let short = owed.checked_sub(paid).unwrap_or_default();
+                let text = format_underpayment(short, breakdown, &self.currency);
+                format!("; line {line}: {text}\n")
+            }
+            OutputFormat::Spoken => {
+                let short = owed.checked_sub(paid).unwrap_or_default();
+                format!(
+                    "Insufficient payment: {} short.",
+                    amount_words(short, &self.currency)
+                )
+            }
+            OutputFormat::Text | OutputFormat::Pictogram => {
+                let short = owed.checked_sub(paid).unwrap_or_default();
+                let text = format_underpayment(short, breakdown, &self.currency);
+                if self.verbose {
+                    let sym = &self.currency.symbol;
+                    format!("Owed {sym}{owed}, Paid {sym}{paid} -> {text}")
+                } else {
+                    text
+                }
+            }
+        };
+        &self.out
+    }
+
+    fn render_sale(&mut self, sale: &Sale) -> &str {
+        let Sale {
+            line,
+            ref transaction,
+            ref breakdown,
+            is_random,
+            shortfall,
+            iou,
+            ref extra,
+            overpaid,
+            ref rounded,
+            ref split,
+            ref foreign,
+            ref foreign_change,
+            donated,
+            refund,
+            ref suspicious,
+            ref points,
+        } = *sale;
+        if refund {
+            return self.render_refund(sale);
+        }
+        let change_currency = foreign_change
+            .as_ref()
+            .map_or(&self.currency, |(currency, _)| currency);
+        let heft = self.heft(breakdown, change_currency);
+        if matches!(self.output, OutputFormat::Json | OutputFormat::Msgpack) {
+            self.out = format_json(
+                line,
+                transaction,
+                breakdown,
+                change_currency,
+                is_random,
+                shortfall,
+                iou,
+                extra,
+                overpaid,
+                rounded.as_ref(),
+                split.as_ref(),
+                foreign.as_deref(),
+                donated,
+                heft,
+                suspicious,
+                points.as_ref(),
+            );
+            return &self.out;
+        }
+        if self.output == OutputFormat::Csv {
+            self.out = format_csv(line, transaction, breakdown, &self.currency);
+            return &self.out;
+        }
+        if self.output == OutputFormat::Ledger {
+            // A blank line after each entry keeps them apart.
+            let owed_to_customer = shortfall + i64::from(iou);
+            self.out = format_ledger_entry(
+                &self.date,
+                line,
+                transaction,
+                split.as_ref(),
+                owed_to_customer,
+                donated,
+                &self.currency,
+            ) + "\n";
+            return &self.out;
+        }
+        if self.output == OutputFormat::Spoken {
+            let change = foreign_change
+                .as_ref()
+                .map_or(transaction.change_cents() - donated, |&(_, c)| c);
+            let spoken = format_spoken(
+                change,
+                breakdown,
+                shortfall + i64::from(iou),
+                donated,
+                change_currency,
+            );
+            self.out = if self.verbose {
+                format_spoken_owed(transaction, &self.currency) + " " + &spoken
+            } else {
+                spoken
+            };
+            return &self.out;
+        }
+        self.out.clear();
+        if self.verbose && (foreign_change.is_some() || self.glyphs.is_some()) {
+            let sym = &self.currency.symbol;
+            self.out += &format!(
+                "Owed {sym}{}, Paid {sym}{} -> ",
+                transaction.owed_cents(),
+                transaction.paid_cents()
+            );
+            write_change(&mut self.out, breakdown, change_currency, &self.glyphs)
+        } else if self.verbose {
+            write_verbose(
+                &mut self.out,
+                transaction,
+                breakdown,
+                &self.currency,
+                is_random,
+            )
+        } else {
+            write_change(&mut self.out, breakdown, change_currency, &self.glyphs)
+        }
+        .expect("writing to a String can't fail");
+        self.out.push_str(&if iou > 0 {
+            format_iou(iou, &self.currency)
+        } else {
+            format_shortfall(shortfall, &self.currency)
+        });
+        self.out.push_str(&format_donation(donated, &self.currency));
+        if let Some(heft) = heft {
+            self.out.push_str(&format_heft(heft));
+        }
+        if let Some(points) = points {
+            self.out.push_str(&format_points(points.points));
+        }
+        if let (true, Some(rounded)) = (self.verbose, rounded) {
+            self.out.push_str(&format_rounded(rounded));
+        }
+        if let (true, Some(split)) = (self.verbose, split) {
+            self.out
+                .push_str(&format_split_tender(split, &self.currency));
+        }
+        if let (true, Some(foreign)) = (self.verbose, foreign) {
+            let symbol = match self.currencies.iter().find(|c| c.name == foreign.currency) {
+                Some(tendered) => tendered.symbol.to_string(),
+                None => format!("{} ", foreign.currency),
+            };
+            let change = foreign_change.as_ref().map(|&(_, change)| change);
+            self.out
+                .push_str(&format_foreign_tender(foreign, &symbol, change));
+        }
+        self.out.push_str(&format_extra(extra));
+        &self.out
+    }
+
+    /// The weight and size of `breakdown` under `--weights`, if every piece
+    /// in it has them.
+    fn heft(&self, breakdown: &[(usize, u32)], currency: &Currency) -> Option<Heft> {
+        self.weights
+            .then(|| Heft::of(breakdown, currency))
+            .flatten()
+    }
+
+    /// A refund, labeled as a payout in every output format.
+    fn render_refund(&mut self, sale: &Sale) -> &str {
+        let amount = sale.transaction.change_cents();
+        let heft = self.heft(&sale.breakdown, &self.currency);
+        let owed_to_customer = sale.shortfall + i64::from(sale.iou);
+        self.out = match self.output {
+            OutputFormat::Json | OutputFormat::Msgpack => format_refund_json(
+                sale.line,
+                amount,
+                &sale.breakdown,
+                &self.currency,
+                sale.shortfall,
+                sale.iou,
+                heft,
+                &sale.suspicious,
+                sale.points.as_ref(),
+            ),
+            OutputFormat::Csv => {
+                format_refund_csv(sale.line, amount, &sale.breakdown, &self.currency)
+            }
+            OutputFormat::Ledger => {
+                format_ledger_refund(
+                    &self.date,
+                    sale.line,
+                    amount,
+                    owed_to_customer,
+                    &self.currency,
+                ) + "\n"
+            }
+            OutputFormat::Spoken => {
+                format_spoken_refund(amount, &sale.breakdown, owed_to_customer, &self.currency)
+            }
+            OutputFormat::Text | OutputFormat::Pictogram => {
+                let mut out = match &self.glyphs {
+                    Some(glyphs) => {
+                        format_pictogram_refund(amount, &sale.breakdown, &self.currency, glyphs)
+                    }
+                    None => format_refund(amount, &sale.breakdown, &self.currency),
+                };
+                out.push_str(&if sale.iou > 0 {
+                    format_iou(sale.iou, &self.currency)
+                } else {
+                    format_shortfall(sale.shortfall, &self.currency)
+                });
+                if let Some(heft) = heft {
+                    out.push_str(&format_heft(heft));
+                }
+                if let Some(points) = &sale.points {
+                    out.push_str(&format_points(points.points));
+                }
+                out + &format_extra(&sale.extra)
+            }
+        };
+        &self.out
+    }
+}
+
+/// Write `breakdown` as words, or with `glyphs` as `--output pictogram`'s
+/// pictures.
+fn write_change(
+    out: &mut String,
+    breakdown: &[(usize, u32)],
+    currency: &Currency,
+    glyphs: &Option<Glyphs>,
+) -> fmt::Result {
+    match glyphs {
+        Some(glyphs) => write_pictogram(out, breakdown, currency, glyphs),
+        None => write_breakdown(out, breakdown, currency),
+    }
+}
+
+/// `run`'s stdout, shared by whichever thread prints.
+///
+/// Once a write fails, as it does for a full disk or a reader that has
+/// gone away, nothing more is written and the error is kept for `run` to
+/// report when it finishes.
+#[derive(Clone, Default)]
+struct Stdout {
+    /// Under `--sign-key`, the chain every line is signed into.
+    signer: Option<Arc<Mutex<Signer>>>,
+    failed: Arc<OnceLock<io::Error>>,
+}
+
+impl Stdout {
+    /// Print one output line with its newline, or under `--output msgpack`,
+    /// its JSON object as MessagePack, with nothing after it. Under
+    /// `--sign-key`, each line of a multi-line output is signed on its own.
+    fn emit(&self, line: &str, msgpack: bool) {
+        if self.failed.get().is_some() {
+            return;
+        }
+        if let Err(e) = self.write(line, msgpack) {
+            // Another thread may have failed first; either error will do.
+            let _ = self.failed.set(e);
+        }
+    }
+
+    fn write(&self, line: &str, msgpack: bool) -> io::Result<()> {
+        let mut out = io::stdout().lock();
+        if let Some(signer) = &self.signer {
+            let mut signer = signer.lock().expect("a thread panicked while signing");
+            for line in line.split('\n') {
+                writeln!(out, "{}", signer.sign(line))?;
+            }
+            return Ok(());
+        }
+        if !msgpack {
+            return writeln!(out, "{line}");
+        }
+        let json: serde_json::Value = serde_json::from_str(line).expect("output lines are JSON");
+        let bytes = rmp_serde::to_vec(&json).expect("JSON always encodes as MessagePack");
+        // With no newline to flush it, a failed write would only show up
+        // at exit, where it's ignored.
+        out.write_all(&bytes)?;
+        out.flush()
+    }
+
+    /// Under `--sign-key`, print the record ending the run's chain.
+    fn end(&self) {
+        let Some(signer) = &self.signer else {
+            return;
+        };
+        let end = signer
+            .lock()
+            .expect("a thread panicked while signing")
+            .end();
+        if let (Some(end), None) = (end, self.failed.get()) {
+            if let Err(e) = writeln!(io::stdout().lock(), "{end}") {
+                let _ = self.failed.set(e);
+            }
+        }
+    }
+
+    /// The error that stopped output, once one has.
+    fn failed(&self) -> Option<&io::Error> {
+        self.failed.get()
+    }
+}
+
+/// How many sales `--jobs` workers may have in flight at once. Lines that
+/// finish early wait in the reorder buffer for the ones before them, so this
+/// also bounds it: at most this many formatted lines.
+const REORDER_WINDOW: usize = 1024;
+
+/// Where `run` sends settled sales: printed right away; with `--pipeline`,
+/// queued for a format stage on its own thread; or with `--jobs`, spread
+/// over worker threads and put back in input order before printing.
+enum SaleSink {
+    Inline(Printer),
+    Staged {
+        queue: SyncSender<Output>,
+        stage: JoinHandle<()>,
+    },
+    Parallel {
+        queue: SyncSender<(u64, Output)>,
+        /// One token per free slot in the reorder window.
+        credits: Receiver<()>,
+        next: u64,
+        stages: Vec<JoinHandle<()>>,
+    },
+    /// Printed, and shown on the `--dashboard`.
+    #[cfg(feature = "tui")]
+    Dashboard {
+        printer: Printer,
+        screen: Box<Screen>,
+    },
+}
+
+impl SaleSink {
+    fn new(mut printer: Printer, pipeline: bool) -> Self {
+        if !pipeline {
+            return Self::Inline(printer);
+        }
+        let (queue, outputs) = sync_channel::<Output>(PIPELINE_DEPTH);
+        let stage = thread::spawn(move || {
+            for output in outputs {
+                printer.print(&output);
+            }
+        });
+        Self::Staged { queue, stage }
+    }
+
+    /// `jobs` workers making greedy change for the sales that aren't random
+    /// and formatting them, then a writer printing their lines in the order
+    /// the sales were sent.
+    fn parallel(printer: Printer, jobs: usize) -> Self {
+        let (queue, sales) = sync_channel::<(u64, Output)>(REORDER_WINDOW);
+        let sales = Arc::new(Mutex::new(sales));
+        let (done, lines) = sync_channel::<(u64, String)>(REORDER_WINDOW);
+        let (credit, credits) = sync_channel(REORDER_WINDOW);
+        for _ in 0..REORDER_WINDOW {
+            credit.send(()).expect("the window has room");
+        }
+
+        let mut stages: Vec<JoinHandle<()>> = (0..jobs)
+            .map(|_| {
+                let sales = Arc::clone(&sales);
+                let done = done.clone();
+                let mut printer = printer.clone();
+                thread::spawn(move || {
+                    // The table borrows the currency the printer also needs.
+                    let currency = printer.currency.clone();
+                    let mut greedy = GreedyTable::new(&currency);
+                    loop {
+                        let next = sales.lock().expect("a worker panicked").recv();
+                        let Ok((seq, mut output)) = next else {
+                            break;
+                        };
+                        if let Output::Sale(sale) = &mut output {
+                            if !sale.is_random && sale.foreign_change.is_none() {
+                                let change = sale.transaction.change_cents() - sale.donated;
+                                sale.breakdown =
+                                    greedy.make_change(change, &currency).expect(EXACT_CHANGE);
+                            }
+                        }
+                        let line = printer.render(&output).to_string();
+                        if done.send((seq, line)).is_err() {
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect();
+        drop(done);
+
+        stages.push(thread::spawn(move || {
+            let mut reorder = BTreeMap::new();
+            let mut next = 0;
+            for (seq, line) in lines {
+                reorder.insert(seq, line);
+                while let Some(line) = reorder.remove(&next) {
+                    printer.stdout.emit(&line, printer.msgpack);
+                    next += 1;
+                    // The run may already be finishing; nobody needs the credit.
+                    credit.send(()).ok();
+                }
+            }
+        }));
+        Self::Parallel {
+            queue,
+            credits,
+            next: 0,
+            stages,
+        }
+    }
+
+    fn send(&mut self, output: Output) {
+        match self {
+            Self::Inline(printer) => printer.print(&output),
+            #[cfg(feature = "tui")]
+            Self::Dashboard { printer, screen } => screen.show(printer, &output),
+            Self::Staged { queue, .. } => queue.send(output).expect("format stage stopped early"),
+            Self::Parallel {
+                queue,
+                credits,
+                next,
+                ..
+            } => {
+                credits.recv().expect("output stage stopped early");
+                queue.send((*next, output)).expect("workers stopped early");
+                *next += 1;
+            }
+        }
+    }
+
+    /// Wait for every queued sale to be printed.
+    fn finish(self) {
+        let stages = match self {
+            Self::Inline(_) => return,
+            #[cfg(feature = "tui")]
+            Self::Dashboard { screen, .. } => return screen.close(),
+            Self::Staged { queue, stage } => {
+                drop(queue);
+                vec![stage]
+            }
+            Self::Parallel { queue, stages, .. } => {
+                drop(queue);
+                stages
+            }
+        };
+        for stage in stages {
+            if stage.join().is_err() {
+                process::exit(1);
+            }
+        }
+    }
+
+    /// Count a failed line on the dashboard, if there is one.
+    #[cfg_attr(not(feature = "tui"), allow(unused_variables, clippy::unused_self))]
+    fn record_error(&mut self, error: &CashRegisterError) {
+        #[cfg(feature = "tui")]
+        if let Self::Dashboard { screen, .. } = self {
+            screen.dashboard.record_error(error);
+        }
+    }
+
+    /// Between lines: redraw the dashboard, if there is one, with the
+    /// drawers as they stand, and stop the run if asked to.
+    #[cfg_attr(not(feature = "tui"), allow(unused_variables, clippy::unused_self))]
+    fn tick(&mut self, registers: Option<&Registers>) {
+        #[cfg(feature = "tui")]
+        if let Self::Dashboard { screen, .. } = self {
+            screen.tick(registers);
+        }
+    }
+}
+
+/// The `--dashboard` on the terminal: drawn on stderr's alternate screen,
+/// with anything else for stderr held back until it closes. Output lines
+/// still go to stdout when it's redirected.
+#[cfg(feature = "tui")]
+struct Screen {
+    terminal: Terminal<CrosstermBackend<io::Stderr>>,
+    dashboard: Dashboard,
+    drawn: Option<Instant>,
+    /// Whether output lines also go to stdout, which isn't the terminal.
+    echo: bool,
+}
+
+#[cfg(feature = "tui")]
+impl Screen {
+    /// How often the dashboard is redrawn while lines are processed.
+    const REDRAW: Duration = Duration::from_millis(50);
+    /// How many of the latest transactions are kept for the list.
+    const RECENT: usize = 200;
+
+    /// Take over the terminal, or exit if stderr isn't one.
+    fn open(currency: &Currency) -> Self {
+        use std::io::IsTerminal;
+
+        if !io::stderr().is_terminal() {
+            eprintln!("error: --dashboard needs stderr to be a terminal");
+            process::exit(EXIT_USAGE);
+        }
+        let terminal = terminal::enable_raw_mode()
+            .and_then(|()| ratatui::crossterm::execute!(io::stderr(), EnterAlternateScreen))
+            .and_then(|()| Terminal::new(CrosstermBackend::new(io::stderr())))
+            .unwrap_or_else(|e| {
+                terminal::disable_raw_mode().ok();
+                eprintln!("Error opening the dashboard: {e}");
+                process::exit(EXIT_IO);
+            });
+        *HELD_STDERR
+            .lock()
+            .expect("a thread panicked holding stderr") = Some(Vec::new());
+        Self {
+            terminal,
+            dashboard: Dashboard::new(currency, Self::RECENT),
+            drawn: None,
+            echo: !io::stdout().is_terminal(),
+        }
+    }
+
+    fn show(&mut self, printer: &mut Printer, output: &Output) {
+        printer.render(output);
+        if let Output::Sale(sale) = output {
+            self.dashboard.record_sale(sale.line, &printer.out);
+        }
+        if self.echo {
+            printer.stdout.emit(&printer.out, printer.msgpack);
+        }
+    }
+
+    /// Redraw if it's been long enough, and treat `q`, Esc, or Ctrl-C as an
+    /// interrupt: in raw mode, Ctrl-C is a key, not a signal.
+    fn tick(&mut self, registers: Option<&Registers>) {
+        while event::poll(Duration::ZERO).unwrap_or(false) {
+            if event::read().is_ok_and(|event| is_quit(&event)) {
+                INTERRUPTED.store(true, Ordering::SeqCst);
+            }
+        }
+        if self
+            .drawn
+            .is_some_and(|drawn| drawn.elapsed() < Self::REDRAW)
+        {
+            return;
+        }
+        if let Some(registers) = registers {
+            self.dashboard.set_tills(registers.iter());
+        }
+        self.draw();
+    }
+
+    fn draw(&mut self) {
+        let dashboard = &self.dashboard;
+        // A failed draw only costs a frame; the run's output is elsewhere.
+        self.terminal.draw(|frame| dashboard.render(frame)).ok();
+        self.drawn = Some(Instant::now());
+    }
+
+    /// Show the finished batch until the operator quits, unless they
+    /// already stopped it, then give the terminal back and print what
+    /// stderr missed.
+    fn close(mut self) {
+        self.dashboard.finish();
+        self.draw();
+        while !INTERRUPTED.load(Ordering::SeqCst) {
+            match event::read() {
+                Ok(event) if is_quit(&event) => break,
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+        terminal::disable_raw_mode().ok();
+        ratatui::crossterm::execute!(io::stderr(), LeaveAlternateScreen).ok();
+        self.terminal.show_cursor().ok();
+        let held = HELD_STDERR
+            .lock()
+            .expect("a thread panicked holding stderr")
+            .take();
+        for line in held.unwrap_or_default() {
+            eprintln!("{line}");
+        }
+    }
+}
+
+/// Whether a terminal event is `q`, Esc, or Ctrl-C.
+#[cfg(feature = "tui")]
+fn is_quit(event: &Event) -> bool {
+    let Event::Key(key) = event else {
+        return false;
+    };
+    key.kind == KeyEventKind::Press
+        && match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => true,
+            KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+            _ => false,
+        }
+}
+
+fn currency_or_exit(config: &Config) -> Currency {
+    let currency = config.currency().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        process::exit(e.exit_code());
+    });
+    for_display(currency, config.ascii_symbols)
+}
+
+/// `currency`, with its symbol spelled as its code under `--ascii-symbols`.
+fn for_display(currency: &Currency, ascii_symbols: bool) -> Currency {
+    if ascii_symbols {
+        return currency.with_ascii_symbol();
+    }
+    currency.clone()
+}
+
+/// How the operator resolves a till shortage in `--interactive` mode.
+enum ShortageChoice {
+    /// Hand over the nearest amount the drawer can make.
+    Substitute,
+    /// Hand over what the drawer can and owe the customer the rest.
+    Iou,
+    /// Leave the sale unrung and carry on with the batch.
+    Skip,
+}
+
+/// Ask the operator on stderr how to resolve `shortage`, reading the answer
+/// from stdin. Returns `None` once stdin is exhausted.
+fn prompt_shortage(shortage: &CashRegisterError) -> Option<ShortageChoice> {
+    let mut stdin = io::stdin().lock();
+    loop {
+        eprint!("{shortage}: [s]ubstitute, [i]OU, or s[k]ip? ");
+        let mut answer = String::new();
+        if stdin.read_line(&mut answer).ok()? == 0 {
+            eprintln!();
+            return None;
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "s" | "substitute" => return Some(ShortageChoice::Substitute),
+            "i" | "iou" => return Some(ShortageChoice::Iou),
+            "k" | "skip" => return Some(ShortageChoice::Skip),
+            other => eprintln!("Unknown choice \"{other}\""),
+        }
+    }
+}
+
+/// Report a failed journal write; returns whether there was an error.
+fn report_journal_error(result: std::io::Result<()>, path: &Option<String>) -> bool {
+    match result {
+        Ok(()) => false,
+        Err(e) => {
+            eprintln_or_hold!("Error writing {}: {e}", path.as_deref().unwrap_or_default());
+            true
+        }
+    }
+}
+
+/// The key in `--sign-key-file` without its trailing newline, or else the
+/// `--sign-key` key. Exits when the file can't be read or holds no key.
+fn sign_key_or_exit(key: Option<&str>, file: Option<&str>) -> Option<Vec<u8>> {
+    let Some(path) = file else {
+        return key.map(|key| key.as_bytes().to_vec());
+    };
+    let mut key = fs::read(path).unwrap_or_else(|e| {
+        eprintln!("Error reading {path}: {e}");
+        process::exit(EXIT_IO);
+    });
+    while key.last().is_some_and(|&b| b == b'\n' || b == b'\r') {
+        key.pop();
+    }
+    if key.is_empty() {
+        eprintln!("error: {path} holds no key");
+        process::exit(EXIT_USAGE);
+    }
+    Some(key)
+}
+
+/// A signer chaining onto the last line of the signed file at `path`, or
+/// starting a new chain when it's empty, missing, or unsigned. With
+/// `required`, a missing or unreadable file is reported and exits.
+fn signer_after(key: &[u8], path: &str, required: bool) -> Signer {
+    // The signature is at the end of the line, so the file's tail is
+    // enough even when its last line is longer.
+    const TAIL: u64 = 4096;
+    let tail = File::open(path).and_then(|mut file| {
+        let len = file.metadata()?.len();
+        file.seek(io::SeekFrom::Start(len.saturating_sub(TAIL)))?;
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail)?;
+        Ok(tail)
+    });
+    let tail = match tail {
+        Ok(tail) => tail,
+        Err(e) if required || e.kind() != io::ErrorKind::NotFound => {
+            eprintln!("Error reading {path}: {e}");
+            process::exit(EXIT_IO);
+        }
+        Err(_) => Vec::new(),
+    };
+    String::from_utf8_lossy(&tail)
+        .lines()
+        .last()
+        .and_then(|last| Signer::after(key, last))
+        .unwrap_or_else(|| Signer::new(key))
+}
+
+/// Read a file to a string, or report the error and exit.
+fn read_or_exit(path: &str) -> String {
+    fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Error reading {path}: {e}");
+        process::exit(EXIT_IO);
+    })
+}
+
+/// Open an input file for reading line by line.
+fn open_or_exit(path: &str) -> BufReader<File> {
+    File::open(path).map(BufReader::new).unwrap_or_else(|e| {
+        eprintln!("Error reading {path}: {e}");
+        process::exit(EXIT_IO);
+    })
 }
 
-/// Parse a `--flag value` pair from command-line args.
-fn parse_flag<T: std::str::FromStr>(args: &[String], flag: &str) -> Option<T> {
-    args.iter()
-        .position(|a| a == flag)
-        .and_then(|i| args.get(i + 1))
-        .and_then(|v| v.parse().ok())
+/// Write via a temporary file and rename, so an interrupted write never
+/// leaves a truncated state file behind.
+fn write_atomically(path: &str, contents: &str) -> std::io::Result<()> {
+    let tmp = format!("{path}.tmp");
+    fs::write(&tmp, format!("{contents}\n"))?;
+    fs::rename(&tmp, path)
 }
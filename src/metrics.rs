@@ -0,0 +1,222 @@
+//! Counters and histograms for a long-running register, in the Prometheus
+//! text exposition format, for `cash-register --rpc --metrics-addr`.
+//!
+//! [`Metrics`] only counts; serving [`Metrics::render`] over HTTP is up to
+//! the caller, as is timing requests, since `no_std` builds have no clock.
+//!
+//! ```text
+//! cash_register_transactions_total{selection="greedy"} 41
+//! cash_register_transactions_total{selection="random"} 2
+//! cash_register_errors_total{kind="E002"} 1
+//! cash_register_change_cents_bucket{le="100"} 38
+//! ```
+
+use alloc::{collections::BTreeMap, string::String, vec, vec::Vec};
+use core::fmt::Write;
+
+use crate::money::Cents;
+
+/// Bucket bounds for the change handed back, in cents.
+const CHANGE_CENTS: [f64; 12] = [
+    1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0,
+];
+/// Bucket bounds for the pieces in one breakdown.
+const PIECES: [f64; 10] = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 8.0, 10.0, 15.0];
+/// Bucket bounds for how long a request took, in seconds.
+const LATENCY_SECONDS: [f64; 10] = [
+    0.0001, 0.00025, 0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1,
+];
+
+/// Observations counted into buckets, each `le` (less than or equal to) a
+/// bound, plus their count and sum.
+#[derive(Debug, Clone, PartialEq)]
+struct Histogram {
+    bounds: &'static [f64],
+    /// Observations per bucket, not yet cumulative; the last is `+Inf`.
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: vec![0; bounds.len() + 1],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        let bucket = self.bounds.partition_point(|&bound| bound < value);
+        self.buckets[bucket] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} histogram");
+        let mut cumulative = 0;
+        for (bound, count) in self.bounds.iter().zip(&self.buckets) {
+            cumulative += count;
+            let _ = writeln!(out, "{name}_bucket{{le=\"{bound}\"}} {cumulative}");
+        }
+        let _ = writeln!(out, "{name}_bucket{{le=\"+Inf\"}} {}", self.count);
+        let _ = writeln!(out, "{name}_sum {}\n{name}_count {}", self.sum, self.count);
+    }
+}
+
+/// What a register has done since it started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metrics {
+    greedy: u64,
+    random: u64,
+    /// Failed requests by error code (`E002`) or protocol error
+    /// (`invalid_params`).
+    errors: BTreeMap<String, u64>,
+    change_cents: Histogram,
+    pieces: Histogram,
+    latency_seconds: Histogram,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self {
+            greedy: 0,
+            random: 0,
+            errors: BTreeMap::new(),
+            change_cents: Histogram::new(&CHANGE_CENTS),
+            pieces: Histogram::new(&PIECES),
+            latency_seconds: Histogram::new(&LATENCY_SECONDS),
+        }
+    }
+
+    /// Count a transaction whose `change` came out as `pieces` pieces,
+    /// chosen at random or the fewest.
+    pub fn record_change(&mut self, change: Cents, pieces: u32, random: bool) {
+        if random {
+            self.random += 1;
+        } else {
+            self.greedy += 1;
+        }
+        self.change_cents.observe(change.0 as f64);
+        self.pieces.observe(f64::from(pieces));
+    }
+
+    /// Count a failure of this kind.
+    pub fn record_error(&mut self, kind: &str) {
+        *self.errors.entry(kind.into()).or_default() += 1;
+    }
+
+    /// Count a request that took `seconds` to answer.
+    pub fn record_latency(&mut self, seconds: f64) {
+        self.latency_seconds.observe(seconds);
+    }
+
+    /// Transactions made, random and greedy.
+    pub fn transactions(&self) -> u64 {
+        self.greedy + self.random
+    }
+
+    /// Failures of this kind so far.
+    pub fn errors(&self, kind: &str) -> u64 {
+        self.errors.get(kind).copied().unwrap_or_default()
+    }
+
+    /// Every metric in the Prometheus text format, version 0.0.4.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        out.push_str(
+            "# HELP cash_register_transactions_total Transactions made, by how the change was chosen.\n\
+             # TYPE cash_register_transactions_total counter\n",
+        );
+        for (selection, count) in [("greedy", self.greedy), ("random", self.random)] {
+            let _ = writeln!(
+                out,
+                "cash_register_transactions_total{{selection=\"{selection}\"}} {count}"
+            );
+        }
+        out.push_str(
+            "# HELP cash_register_errors_total Failed requests, by error code or protocol error.\n\
+             # TYPE cash_register_errors_total counter\n",
+        );
+        for (kind, count) in &self.errors {
+            let _ = writeln!(out, "cash_register_errors_total{{kind=\"{kind}\"}} {count}");
+        }
+        self.change_cents.render(
+            &mut out,
+            "cash_register_change_cents",
+            "Change handed back per transaction, in cents.",
+        );
+        self.pieces.render(
+            &mut out,
+            "cash_register_change_pieces",
+            "Pieces handed back per transaction.",
+        );
+        self.latency_seconds.render(
+            &mut out,
+            "cash_register_request_duration_seconds",
+            "Time to answer a request.",
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+
+    #[test]
+    fn histograms_are_cumulative() {
+        let mut histogram = Histogram::new(&PIECES);
+        for pieces in [0.0, 3.0, 3.0, 7.0, 40.0] {
+            histogram.observe(pieces);
+        }
+        let mut out = String::new();
+        histogram.render(&mut out, "pieces", "Pieces.");
+        for line in [
+            r#"pieces_bucket{le="0"} 1"#,
+            r#"pieces_bucket{le="2"} 1"#,
+            r#"pieces_bucket{le="3"} 3"#,
+            r#"pieces_bucket{le="8"} 4"#,
+            r#"pieces_bucket{le="15"} 4"#,
+            r#"pieces_bucket{le="+Inf"} 5"#,
+        ] {
+            assert!(out.contains(&format!("{line}\n")), "{line} in:\n{out}");
+        }
+        assert!(out.contains("pieces_sum 53\npieces_count 5\n"), "{out}");
+    }
+
+    #[test]
+    fn renders_every_metric() {
+        let mut metrics = Metrics::new();
+        metrics.record_change(Cents(88), 7, false);
+        metrics.record_change(Cents(200), 2, true);
+        metrics.record_error("E002");
+        metrics.record_error("E002");
+        metrics.record_latency(0.0003);
+        assert_eq!(metrics.transactions(), 2);
+
+        let out = metrics.render();
+        for line in [
+            r#"cash_register_transactions_total{selection="greedy"} 1"#,
+            r#"cash_register_transactions_total{selection="random"} 1"#,
+            r#"cash_register_errors_total{kind="E002"} 2"#,
+            r#"cash_register_change_cents_bucket{le="100"} 1"#,
+            "cash_register_change_cents_count 2",
+            r#"cash_register_change_pieces_bucket{le="2"} 1"#,
+            r#"cash_register_request_duration_seconds_bucket{le="0.0005"} 1"#,
+        ] {
+            assert!(out.contains(&format!("{line}\n")), "{line} in:\n{out}");
+        }
+        assert!(out.contains("# TYPE cash_register_change_cents histogram\n"));
+    }
+}
@@ -0,0 +1,185 @@
+use alloc::{format, string::String};
+use core::fmt;
+use core::iter::Sum;
+use core::ops::{Add, AddAssign, Div, Mul, Rem, Sub, SubAssign};
+
+/// An amount of money in the currency's smallest unit (cents).
+///
+/// A newtype so amounts can't be mixed up with the counts and divisors that
+/// sit next to them: `Cents + u32` doesn't compile, while `Cents * u32`
+/// (a count of pieces) and `Cents / Cents` (how many pieces fit) do.
+///
+/// The operators panic on overflow (or going below zero) in every build,
+/// rather than wrapping in release builds; the `checked_` methods return
+/// `None` instead.
+///
+/// Displays as a decimal amount without a symbol: `Cents(213)` is `2.13`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Cents(pub u64);
+
+impl Cents {
+    pub const ZERO: Cents = Cents(0);
+
+    /// The largest amount a single transaction may carry, so a breakdown's
+    /// piece counts always fit in a `u32` (even if every piece is a penny).
+    /// Sums across transactions are free to go higher.
+    pub const MAX_AMOUNT: Cents = Cents(u32::MAX as u64);
+
+    /// Whole units (dollars, euros) in the amount.
+    pub fn whole(self) -> u64 {
+        self.0 / 100
+    }
+
+    /// The cents left over after the whole units.
+    pub fn fraction(self) -> u64 {
+        self.0 % 100
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether the amount in cents divides evenly by `divisor`.
+    pub fn is_multiple_of(self, divisor: u32) -> bool {
+        self.0.is_multiple_of(u64::from(divisor))
+    }
+
+    pub fn checked_add(self, rhs: Cents) -> Option<Cents> {
+        self.0.checked_add(rhs.0).map(Cents)
+    }
+
+    pub fn checked_sub(self, rhs: Cents) -> Option<Cents> {
+        self.0.checked_sub(rhs.0).map(Cents)
+    }
+
+    /// `count` pieces of this value, or `None` on overflow.
+    pub fn checked_mul(self, count: u32) -> Option<Cents> {
+        self.0.checked_mul(u64::from(count)).map(Cents)
+    }
+
+    /// Format with a currency symbol in front: `$2.13`.
+    pub fn with_symbol(self, symbol: &str) -> String {
+        format!("{symbol}{self}")
+    }
+}
+
+impl fmt::Display for Cents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}", self.whole(), self.fraction())
+    }
+}
+
+impl From<u32> for Cents {
+    fn from(cents: u32) -> Self {
+        Cents(u64::from(cents))
+    }
+}
+
+impl Add for Cents {
+    type Output = Cents;
+
+    fn add(self, rhs: Cents) -> Cents {
+        self.checked_add(rhs).expect("amount overflows u64")
+    }
+}
+
+impl AddAssign for Cents {
+    fn add_assign(&mut self, rhs: Cents) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Cents {
+    type Output = Cents;
+
+    fn sub(self, rhs: Cents) -> Cents {
+        self.checked_sub(rhs).expect("amount goes below zero")
+    }
+}
+
+impl SubAssign for Cents {
+    fn sub_assign(&mut self, rhs: Cents) {
+        *self = *self - rhs;
+    }
+}
+
+/// `count` pieces of this value.
+impl Mul<u32> for Cents {
+    type Output = Cents;
+
+    fn mul(self, count: u32) -> Cents {
+        self.checked_mul(count).expect("amount overflows u64")
+    }
+}
+
+/// How many whole `rhs` fit in the amount.
+impl Div for Cents {
+    type Output = u64;
+
+    fn div(self, rhs: Cents) -> u64 {
+        self.0 / rhs.0
+    }
+}
+
+impl Rem for Cents {
+    type Output = Cents;
+
+    fn rem(self, rhs: Cents) -> Cents {
+        Cents(self.0 % rhs.0)
+    }
+}
+
+impl Sum for Cents {
+    fn sum<I: Iterator<Item = Cents>>(iter: I) -> Cents {
+        iter.fold(Cents::ZERO, Add::add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn displays_as_decimal() {
+        assert_eq!(Cents(213).to_string(), "2.13");
+        assert_eq!(Cents(5).to_string(), "0.05");
+        assert_eq!(Cents(10_000).with_symbol("€"), "€100.00");
+    }
+
+    #[test]
+    fn arithmetic() {
+        let quarter = Cents(25);
+        assert_eq!(quarter * 3 + Cents(13), Cents(88));
+        assert_eq!(Cents(88) - quarter, Cents(63));
+        assert_eq!(Cents(88) / quarter, 3);
+        assert_eq!(Cents(88) % quarter, Cents(13));
+        assert_eq!([Cents(1), Cents(2)].into_iter().sum::<Cents>(), Cents(3));
+        assert_eq!(Cents(1).checked_sub(Cents(2)), None);
+    }
+
+    #[test]
+    fn multiples() {
+        assert!(Cents(333).is_multiple_of(3));
+        assert!(!Cents(212).is_multiple_of(3));
+    }
+
+    #[test]
+    fn sums_past_u32() {
+        let total: Cents = core::iter::repeat_n(Cents::MAX_AMOUNT, 3).sum();
+        assert_eq!(total.0, 3 * u64::from(u32::MAX));
+    }
+
+    #[test]
+    fn overflow_is_caught_not_wrapped() {
+        let max = Cents(u64::MAX);
+        assert_eq!(max.checked_add(Cents(1)), None);
+        assert_eq!(max.checked_mul(2), None);
+        assert_eq!(Cents(3).checked_mul(4), Some(Cents(12)));
+        #[cfg(feature = "std")]
+        {
+            assert!(std::panic::catch_unwind(|| max + Cents(1)).is_err());
+            assert!(std::panic::catch_unwind(|| Cents(1) - Cents(2)).is_err());
+        }
+    }
+}
@@ -0,0 +1,339 @@
+//! A run's results as a Parquet file, behind the `parquet` feature, so a
+//! data warehouse can load a day's sales as-is instead of parsing output.
+//!
+//! One row per sale or refund, Snappy-compressed:
+//!
+//! ```text
+//! line      INT64
+//! owed      INT64 DECIMAL(18,2)   negative for a refund, as in `log_db`
+//! paid      INT64 DECIMAL(18,2)
+//! change    INT64 DECIMAL(18,2)   the change due, or the refund paid out
+//! strategy  BYTE_ARRAY UTF8       "greedy", "random", or "void"
+//! dollars   INT32                 one count column per denomination,
+//! quarters  INT32                 largest first, named by its plural
+//! ...
+//! ```
+//!
+//! A `VOID` is a row on its own line with the voided row's amounts and
+//! counts negated and the strategy `void`, so sums over a file net it out.
+//!
+//! Rows are written in row groups of [`ROW_GROUP_ROWS`]; nothing is
+//! readable until [`ParquetFile::finish`] writes the footer.
+//!
+//! ```ignore
+//! let mut file = ParquetFile::create("sales.parquet", &USD)?;
+//! file.record_sale(1, &tx, &[(1, 3), (2, 1), (4, 3)], false)?;
+//! file.finish()?;
+//! ```
+
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::sync::Arc;
+
+use parquet::basic::{Compression, LogicalType, Repetition, Type as PhysicalType};
+use parquet::data_type::{ByteArray, ByteArrayType, Int32Type, Int64Type};
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::types::{PrimitiveTypeBuilder, Type};
+
+use crate::currency::Currency;
+use crate::error::CashRegisterError;
+use crate::money::Cents;
+use crate::parse::Transaction;
+
+/// Rows buffered before they're written out as a row group.
+pub const ROW_GROUP_ROWS: usize = 65_536;
+
+/// The columns of the rows not yet written.
+#[derive(Default)]
+struct Columns {
+    line: Vec<i64>,
+    owed: Vec<i64>,
+    paid: Vec<i64>,
+    change: Vec<i64>,
+    strategy: Vec<&'static str>,
+    /// One per denomination, in the currency's order.
+    counts: Vec<Vec<i32>>,
+}
+
+/// A Parquet file being written.
+pub struct ParquetFile {
+    writer: SerializedFileWriter<BufWriter<File>>,
+    pending: Columns,
+}
+
+impl ParquetFile {
+    /// Create (or replace) the file at `path`, with a count column for each
+    /// of `currency`'s denominations.
+    pub fn create(path: &str, currency: &Currency) -> Result<Self, CashRegisterError> {
+        let file = File::create(path)?;
+        let props = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let writer = SerializedFileWriter::new(
+            BufWriter::new(file),
+            Arc::new(schema(currency)?),
+            Arc::new(props),
+        )
+        .map_err(parquet_error)?;
+        let pending = Columns {
+            counts: vec![Vec::new(); currency.denominations.len()],
+            ..Columns::default()
+        };
+        Ok(Self { writer, pending })
+    }
+
+    /// Add a sale whose change is `breakdown`, `(denomination index,
+    /// count)` pairs, chosen at random or the fewest.
+    pub fn record_sale(
+        &mut self,
+        line: usize,
+        transaction: &Transaction,
+        breakdown: &[(usize, u32)],
+        random: bool,
+    ) -> Result<(), CashRegisterError> {
+        let cents = |amount: Cents| amount.0 as i64;
+        self.push(
+            line,
+            [
+                cents(transaction.owed_cents()),
+                cents(transaction.paid_cents()),
+                cents(transaction.change_cents()),
+            ],
+            breakdown,
+            strategy(random),
+        )
+    }
+
+    /// Add a refund of `amount`, paid out as `breakdown`.
+    pub fn record_refund(
+        &mut self,
+        line: usize,
+        amount: Cents,
+        breakdown: &[(usize, u32)],
+    ) -> Result<(), CashRegisterError> {
+        let amount = amount.0 as i64;
+        self.push(line, [-amount, 0, amount], breakdown, strategy(false))
+    }
+
+    /// Add the `VOID` on `line` taking back a sale, or with `refund`, a
+    /// refund: the row [`record_sale`](Self::record_sale) or
+    /// [`record_refund`](Self::record_refund) wrote for it, negated.
+    pub fn record_void(
+        &mut self,
+        line: usize,
+        transaction: &Transaction,
+        breakdown: &[(usize, u32)],
+        refund: bool,
+    ) -> Result<(), CashRegisterError> {
+        let cents = |amount: Cents| amount.0 as i64;
+        let amounts = if refund {
+            let amount = cents(transaction.change_cents());
+            [amount, 0, -amount]
+        } else {
+            [
+                -cents(transaction.owed_cents()),
+                -cents(transaction.paid_cents()),
+                -cents(transaction.change_cents()),
+            ]
+        };
+        self.push(line, amounts, breakdown, "void")
+    }
+
+    /// Add a row; a `void` row's counts are negated.
+    fn push(
+        &mut self,
+        line: usize,
+        [owed, paid, change]: [i64; 3],
+        breakdown: &[(usize, u32)],
+        strategy: &'static str,
+    ) -> Result<(), CashRegisterError> {
+        let sign = if strategy == "void" { -1 } else { 1 };
+        let pending = &mut self.pending;
+        pending.line.push(line as i64);
+        pending.owed.push(owed);
+        pending.paid.push(paid);
+        pending.change.push(change);
+        pending.strategy.push(strategy);
+        for counts in &mut pending.counts {
+            counts.push(0);
+        }
+        for &(index, count) in breakdown {
+            let counts = &mut pending.counts[index];
+            *counts.last_mut().expect("just pushed") += sign * count as i32;
+        }
+        if pending.line.len() >= ROW_GROUP_ROWS {
+            self.write_row_group()?;
+        }
+        Ok(())
+    }
+
+    fn write_row_group(&mut self) -> Result<(), CashRegisterError> {
+        if self.pending.line.is_empty() {
+            return Ok(());
+        }
+        let mut row_group = self.writer.next_row_group().map_err(parquet_error)?;
+        let columns = &mut self.pending;
+        let mut i = 0;
+        while let Some(mut column) = row_group.next_column().map_err(parquet_error)? {
+            match i {
+                0..=3 => {
+                    let values = [&columns.line, &columns.owed, &columns.paid, &columns.change][i];
+                    column.typed::<Int64Type>().write_batch(values, None, None)
+                }
+                4 => {
+                    let strategies: Vec<ByteArray> =
+                        columns.strategy.iter().map(|&s| s.into()).collect();
+                    column
+                        .typed::<ByteArrayType>()
+                        .write_batch(&strategies, None, None)
+                }
+                _ => column
+                    .typed::<Int32Type>()
+                    .write_batch(&columns.counts[i - 5], None, None),
+            }
+            .map_err(parquet_error)?;
+            column.close().map_err(parquet_error)?;
+            i += 1;
+        }
+        row_group.close().map_err(parquet_error)?;
+
+        let denominations = columns.counts.len();
+        *columns = Columns {
+            counts: vec![Vec::new(); denominations],
+            ..Columns::default()
+        };
+        Ok(())
+    }
+
+    /// Write the rows still buffered and the footer.
+    pub fn finish(mut self) -> Result<(), CashRegisterError> {
+        self.write_row_group()?;
+        self.writer.close().map_err(parquet_error)?;
+        Ok(())
+    }
+}
+
+/// How a sale's change was chosen, as the `strategy` column says it.
+fn strategy(random: bool) -> &'static str {
+    if random {
+        "random"
+    } else {
+        "greedy"
+    }
+}
+
+/// The file's schema, with `currency`'s denominations as count columns.
+fn schema(currency: &Currency) -> Result<Type, CashRegisterError> {
+    let decimal = |name: &str| {
+        column(name, PhysicalType::INT64)
+            .with_logical_type(Some(LogicalType::decimal(2, 18)))
+            .with_precision(18)
+            .with_scale(2)
+            .build()
+    };
+    let mut fields = vec![
+        column("line", PhysicalType::INT64).build(),
+        decimal("owed"),
+        decimal("paid"),
+        decimal("change"),
+        column("strategy", PhysicalType::BYTE_ARRAY)
+            .with_logical_type(Some(LogicalType::String))
+            .build(),
+    ];
+    for denomination in currency.denominations.iter() {
+        fields.push(column(&denomination.plural, PhysicalType::INT32).build());
+    }
+    let fields = fields
+        .into_iter()
+        .map(|field| field.map(Arc::new))
+        .collect::<Result<_, _>>()
+        .map_err(parquet_error)?;
+    Type::group_type_builder("cash_register")
+        .with_fields(fields)
+        .build()
+        .map_err(parquet_error)
+}
+
+fn column(name: &str, physical: PhysicalType) -> PrimitiveTypeBuilder<'_> {
+    Type::primitive_type_builder(name, physical).with_repetition(Repetition::REQUIRED)
+}
+
+/// Parquet errors are I/O errors as far as callers are concerned.
+fn parquet_error(e: ParquetError) -> CashRegisterError {
+    CashRegisterError::Io(io::Error::other(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::USD;
+    use parquet::data_type::Decimal;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use parquet::record::RowAccessor;
+
+    #[test]
+    fn writes_a_row_per_transaction() {
+        let path =
+            std::env::temp_dir().join(format!("cash-register-{}.parquet", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut file = ParquetFile::create(path, &USD).unwrap();
+        let sale = Transaction::new(Cents(212), Cents(300)).unwrap();
+        file.record_sale(1, &sale, &[(1, 3), (2, 1), (4, 3)], false)
+            .unwrap();
+        file.record_refund(2, Cents(150), &[(0, 1), (1, 2)])
+            .unwrap();
+        file.record_void(3, &sale, &[(1, 3), (2, 1), (4, 3)], false)
+            .unwrap();
+        file.finish().unwrap();
+
+        let reader = SerializedFileReader::new(File::open(path).unwrap()).unwrap();
+        let columns: Vec<String> = reader
+            .metadata()
+            .file_metadata()
+            .schema_descr()
+            .columns()
+            .iter()
+            .map(|c| c.name().to_string())
+            .collect();
+        assert_eq!(
+            columns,
+            [
+                "line", "owed", "paid", "change", "strategy", "dollars", "quarters", "dimes",
+                "nickels", "pennies",
+            ]
+        );
+        let rows: Vec<_> = reader
+            .get_row_iter(None)
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        std::fs::remove_file(path).ok();
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].get_long(0).unwrap(), 1);
+        assert_eq!(
+            rows[0].get_decimal(1).unwrap(),
+            &Decimal::from_i64(212, 18, 2)
+        );
+        assert_eq!(rows[0].get_string(4).unwrap(), "greedy");
+        let counts: Vec<i32> = (5..10).map(|i| rows[0].get_int(i).unwrap()).collect();
+        assert_eq!(counts, [0, 3, 1, 0, 3]);
+        assert_eq!(
+            rows[1].get_decimal(1).unwrap(),
+            &Decimal::from_i64(-150, 18, 2)
+        );
+        assert_eq!(
+            rows[1].get_decimal(3).unwrap(),
+            &Decimal::from_i64(150, 18, 2)
+        );
+        assert_eq!(rows[2].get_string(4).unwrap(), "void");
+        assert_eq!(
+            rows[2].get_decimal(2).unwrap(),
+            &Decimal::from_i64(-300, 18, 2)
+        );
+        let counts: Vec<i32> = (5..10).map(|i| rows[2].get_int(i).unwrap()).collect();
+        assert_eq!(counts, [0, -3, -1, 0, -3]);
+    }
+}
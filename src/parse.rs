@@ -1,58 +1,894 @@
-use crate::error::CashRegisterError;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
 
-/// A validated transaction: how much was owed and how much was paid, in cents.
+use tracing::{debug, trace};
+
+use crate::currency::{find_currency, Currency};
+use crate::error::{BreakdownError, CashRegisterError, TransactionError};
+use crate::exchange::ExchangeRate;
+use crate::money::Cents;
+use crate::rounding::RoundingMode;
+use crate::strategy::breakdown::normalize;
+use crate::strategy::Breakdown;
+use crate::till::TillCommand;
+
+/// A validated transaction: how much was owed and how much was paid.
+///
+/// Only built through [`Transaction::new`], so the change due always equals
+/// paid minus owed and every amount fits within `Cents::MAX_AMOUNT`.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Transaction {
-    pub owed_cents: u32,
-    pub paid_cents: u32,
-    pub change_cents: u32,
+    owed_cents: Cents,
+    paid_cents: Cents,
+    change_cents: Cents,
+}
+
+impl Transaction {
+    /// Check that `paid` covers `owed` and neither is over
+    /// `Cents::MAX_AMOUNT`, and work out the change due.
+    pub fn new(owed: Cents, paid: Cents) -> Result<Transaction, TransactionError> {
+        for amount in [owed, paid] {
+            if amount > Cents::MAX_AMOUNT {
+                return Err(TransactionError::TooLarge(amount));
+            }
+        }
+        let change = paid
+            .checked_sub(owed)
+            .ok_or(TransactionError::Underpayment { owed, paid })?;
+        Ok(Transaction {
+            owed_cents: owed,
+            paid_cents: paid,
+            change_cents: change,
+        })
+    }
+
+    pub fn owed_cents(&self) -> Cents {
+        self.owed_cents
+    }
+
+    pub fn paid_cents(&self) -> Cents {
+        self.paid_cents
+    }
+
+    pub fn change_cents(&self) -> Cents {
+        self.change_cents
+    }
+
+    /// Check both amounts against a sanity ceiling, far below
+    /// `Cents::MAX_AMOUNT`, that catches fat-fingered lines like
+    /// `100000.00,100001.00` before anyone is handed a stack of bills.
+    pub fn check_max_amount(&self, max: Cents, line: usize) -> Result<(), CashRegisterError> {
+        match [self.owed_cents, self.paid_cents]
+            .into_iter()
+            .find(|&amount| amount > max)
+        {
+            Some(amount) => Err(CashRegisterError::AmountOverLimit {
+                line,
+                amount: amount.to_string(),
+                max: max.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The amounts on a line as written, where [`ParseOptions::rounding`]
+/// rounded them to whole cents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rounded {
+    pub mode: RoundingMode,
+    /// The owed amount as written, if it was rounded.
+    pub owed: Option<String>,
+    /// The paid amount as written, if it was rounded.
+    pub paid: Option<String>,
+}
+
+/// Tender kinds that are store credit, like a gift card: they pay only up
+/// to what's owed, and whatever they would pay beyond that stays on the card
+/// instead of coming back as cash.
+pub const STORE_CREDIT_KINDS: [&str; 2] = ["gift", "store"];
+
+/// A payment split across kinds of tender, from a paid column like
+/// `CARD:1.00+CASH:2.00`. Only the cash can be given back, so change never
+/// exceeds [`cash`](Self::cash).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitTender {
+    /// Each tender's kind, lowercased (`"card"`, `"cash"`), and amount, in
+    /// input order. Store credit is the amount charged to it, not the amount
+    /// presented.
+    pub parts: Vec<(String, Cents)>,
+    /// Store credit presented but not needed, by kind, which goes back onto
+    /// the card: `GIFT:3.00` against 2.12 owed charges 2.12 and returns 0.88.
+    pub returned: Vec<(String, Cents)>,
+}
+
+impl SplitTender {
+    /// Charge store credit for what the other non-cash tender leaves owing,
+    /// in input order, and move the rest of it to
+    /// [`returned`](Self::returned). Cash covers whatever is still owed, so
+    /// only cash is ever given back as change.
+    pub fn apply_store_credit(&mut self, owed: Cents) {
+        let other_non_cash: Cents = self
+            .parts
+            .iter()
+            .filter(|(kind, _)| kind != "cash" && !is_store_credit(kind))
+            .map(|&(_, amount)| amount)
+            .sum();
+        let mut left = owed.checked_sub(other_non_cash).unwrap_or_default();
+        for (kind, amount) in &mut self.parts {
+            if !is_store_credit(kind) {
+                continue;
+            }
+            let charged = (*amount).min(left);
+            left -= charged;
+            if charged < *amount {
+                self.returned.push((kind.clone(), *amount - charged));
+                *amount = charged;
+            }
+        }
+    }
+
+    /// The part paid in cash, which change comes out of.
+    pub fn cash(&self) -> Cents {
+        self.parts
+            .iter()
+            .filter(|(kind, _)| kind == "cash")
+            .map(|&(_, amount)| amount)
+            .sum()
+    }
+
+    /// Everything paid, cash or not.
+    pub fn total(&self) -> Cents {
+        self.parts.iter().map(|&(_, amount)| amount).sum()
+    }
+}
+
+fn is_store_credit(kind: &str) -> bool {
+    STORE_CREDIT_KINDS.contains(&kind)
 }
 
 /// Parse a dollar-amount string like "2.13" into cents (213).
 ///
-/// Uses string manipulation to avoid floating-point imprecision.
-/// Accepts whole numbers ("3") and decimal numbers with 1-2 decimal places.
-pub fn parse_dollars_to_cents(s: &str) -> Result<u32, String> {
+/// Reads the digits directly, without floating point or intermediate
+/// strings. Accepts whole numbers ("3") and decimal numbers with 1-2 decimal
+/// places, up to `Cents::MAX_AMOUNT`. Either side of the point may be left
+/// off, as some exporters do: ".50" is 50 cents and "5." is $5.00. The
+/// amount must be the whole string
+/// apart from surrounding whitespace: "2.13abc" and "2.13 3" are rejected.
+/// The error says what was wrong, without repeating the input.
+pub fn parse_dollars_to_cents(s: &str) -> Result<Cents, String> {
+    parse_dollars_to_cents_with(s, None).map(|(cents, _)| cents)
+}
+
+/// Like [`parse_dollars_to_cents`], but with `rounding`, an amount with more
+/// than two decimal places ("2.125") is rounded to whole cents instead of
+/// rejected. Also says whether the amount was rounded; trailing zeros
+/// ("2.130") don't count.
+pub fn parse_dollars_to_cents_with(
+    s: &str,
+    rounding: Option<RoundingMode>,
+) -> Result<(Cents, bool), String> {
     let s = s.trim();
     if s.is_empty() {
         return Err("empty string".to_string());
     }
+    if s.starts_with('-') {
+        return Err("negative amount".to_string());
+    }
 
-    match s.split_once('.') {
-        None => {
-            // Whole number: "3" -> 300
-            let dollars: u32 = s
-                .parse()
-                .map_err(|_| format!("not a valid number: \"{s}\""))?;
-            Ok(dollars * 100)
+    // Like `u64::from_str`, allow a leading '+'.
+    let bytes = s.as_bytes();
+    let unsigned = bytes.strip_prefix(b"+").unwrap_or(bytes);
+    let (dollar_digits, rest) = split_digits(unsigned);
+    let (cent_digits, rest) = match rest.strip_prefix(b".") {
+        // Whole number: "3" -> 300
+        None => (&b""[..], rest),
+        Some(after_dot) => match split_digits(after_dot) {
+            ([], rest) if !rest.is_empty() => return Err("invalid cents part".to_string()),
+            split => split,
+        },
+    };
+
+    if dollar_digits.is_empty() && cent_digits.is_empty() {
+        return Err("invalid dollar part".to_string());
+    }
+    let (cent_digits, fraction) = match rounding {
+        Some(_) if cent_digits.len() > 2 => cent_digits.split_at(2),
+        None if cent_digits.len() > 2 => return Err("too many decimal places".to_string()),
+        _ => (cent_digits, &b""[..]),
+    };
+    if !rest.is_empty() {
+        return Err("unexpected characters after amount".to_string());
+    }
+
+    let too_large = || "amount too large".to_string();
+    let dollars = match dollar_digits {
+        [] => 0,
+        digits => digits_value(digits).ok_or_else(too_large)?,
+    };
+    // "3.1" means 10 cents, not 1 cent
+    let cents = match cent_digits {
+        [] => 0,
+        [tenths] => u64::from(tenths - b'0') * 10,
+        digits => digits_value(digits).expect("two digits"),
+    };
+    let round_up = rounding.is_some_and(|rounding| rounding.rounds_up_digits(cents, fraction));
+
+    dollars
+        .checked_mul(100)
+        .and_then(|whole| whole.checked_add(cents + u64::from(round_up)))
+        .map(Cents)
+        .filter(|&amount| amount <= Cents::MAX_AMOUNT)
+        .map(|amount| (amount, fraction.iter().any(|&d| d != b'0')))
+        .ok_or_else(too_large)
+}
+
+/// Split `bytes` after its leading ASCII digits.
+fn split_digits(bytes: &[u8]) -> (&[u8], &[u8]) {
+    let len = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+    bytes.split_at(len)
+}
+
+/// The value of a non-empty run of ASCII digits, or `None` if it's empty,
+/// has anything else in it, or overflows.
+fn digits_value(digits: &[u8]) -> Option<u64> {
+    if digits.is_empty() {
+        return None;
+    }
+    digits.iter().try_fold(0u64, |value, &b| {
+        let digit = b.checked_sub(b'0').filter(|&d| d < 10)?;
+        value.checked_mul(10)?.checked_add(u64::from(digit))
+    })
+}
+
+/// Parse a denomination value like "0.25" into cents, the way the currency
+/// tables store them.
+pub(crate) fn parse_value_cents(s: &str) -> Result<u32, String> {
+    parse_dollars_to_cents(s)
+        .map(|c| u32::try_from(c.0).expect("parsed amounts are within Cents::MAX_AMOUNT"))
+}
+
+/// One meaningful line of input: a transaction, a refund, a till
+/// directive marking a cash drop or pickup at that point in the run, or a
+/// void of an earlier transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Entry {
+    Transaction(Transaction, Fields),
+    /// An amount paid out of the drawer to the customer, from `REFUND,2.12`
+    /// or a negative owed amount, `-2.12,0`.
+    Refund(Cents, Fields),
+    Till(TillCommand, Fields),
+    /// The `id=` of a transaction to reverse, from `VOID T1001`.
+    Void(String),
+}
+
+/// Optional `key=value` fields that may follow `owed,paid` on a transaction line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Fields {
+    /// Cash the customer handed over, as `(value in cents, count)` pairs,
+    /// from `tendered=1.00x2+0.50x2`. Always sums to the cash paid.
+    pub tendered: Option<Vec<(u32, u32)>>,
+    /// Which register's drawer the line belongs to, from `register=R1`.
+    pub register: Option<String>,
+    /// The POS's transaction ID, from `id=T1001`, for spotting a line
+    /// submitted twice.
+    pub id: Option<String>,
+    /// The loyalty customer the sale counts towards, from `customer=C42`.
+    pub customer: Option<String>,
+    /// When the POS rang the sale up, as written, from
+    /// `time=2026-10-16T14:05:00Z`.
+    pub time: Option<String>,
+    /// Any other fields, as written, when parsed with
+    /// [`ExtraFields::Metadata`].
+    pub extra: Vec<String>,
+    /// The owed or paid amount as written, when it had to be rounded to
+    /// whole cents.
+    pub rounded: Option<Rounded>,
+    /// How the payment was split, when the paid column was written like
+    /// `CARD:1.00+CASH:2.00`.
+    pub split: Option<SplitTender>,
+    /// The paid amount as written in another currency, from
+    /// `currency=EUR`, when the paid column is in that currency.
+    pub foreign: Option<ForeignTender>,
+}
+
+impl Fields {
+    /// The register ID, or the default register (`""`) when none was given.
+    pub fn register(&self) -> &str {
+        self.register.as_deref().unwrap_or_default()
+    }
+}
+
+/// A paid amount tendered in a currency other than the register's, like
+/// euros for a dollar-priced sale: `2.12,2.00,currency=EUR,rate=1.08`.
+///
+/// The transaction's paid amount is `paid` converted at `rate` and rounded
+/// once to a whole cent, as the [`exchange`](crate::exchange) module
+/// describes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForeignTender {
+    /// The currency's code, upper-cased.
+    pub currency: String,
+    /// The amount handed over, in that currency.
+    pub paid: Cents,
+    pub rate: ExchangeRate,
+}
+
+impl ForeignTender {
+    /// The tendered currency's table, if it has one, for giving change in it.
+    pub fn tendered_currency(&self) -> Option<&'static Currency> {
+        find_currency(&self.currency).ok()
+    }
+
+    /// Check the line wasn't paid "in another currency" that's the
+    /// register's own.
+    pub fn check(&self, register: &Currency, line: usize) -> Result<(), CashRegisterError> {
+        if self.currency.eq_ignore_ascii_case(&register.name) {
+            return Err(CashRegisterError::MalformedLine {
+                line,
+                detail: format!(
+                    "currency={} is the register's own currency, so needs no rate",
+                    self.currency
+                ),
+            });
         }
-        Some((dollars_str, cents_str)) => {
-            if cents_str.len() > 2 {
-                return Err(format!("too many decimal places: \"{s}\""));
+        Ok(())
+    }
+}
+
+/// The `currency=` and `rate=` fields of a line, before they're checked
+/// against the paid column.
+#[derive(Debug, Default)]
+struct ForeignFields {
+    currency: Option<String>,
+    rate: Option<ExchangeRate>,
+}
+
+impl ForeignFields {
+    fn is_empty(&self) -> bool {
+        self.currency.is_none() && self.rate.is_none()
+    }
+
+    /// The currency and rate to convert the paid column at, falling back to
+    /// `default_rate` when the line gave none.
+    fn resolve(
+        self,
+        default_rate: Option<ExchangeRate>,
+        line_number: usize,
+    ) -> Result<Option<(String, ExchangeRate)>, CashRegisterError> {
+        let malformed = |detail: String| CashRegisterError::MalformedLine {
+            line: line_number,
+            detail,
+        };
+        match (self.currency, self.rate.or(default_rate)) {
+            (None, _) if self.rate.is_some() => Err(malformed(
+                "rate= needs a currency= to convert from".to_string(),
+            )),
+            (None, _) => Ok(None),
+            (Some(currency), None) => Err(malformed(format!(
+                "no exchange rate for {currency} (give rate= or --exchange-rate)"
+            ))),
+            (Some(currency), Some(rate)) => Ok(Some((currency, rate))),
+        }
+    }
+}
+
+/// What to do with a trailing field that isn't `tendered=`, `register=`,
+/// `id=`, `currency=`, or `rate=`, like the `notes` in `2.12,3.00,notes`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExtraFields {
+    /// Reject the line.
+    #[default]
+    Error,
+    /// Drop the field.
+    Ignore,
+    /// Keep the field in [`Fields::extra`] so it can be carried through to
+    /// the output.
+    Metadata,
+}
+
+impl core::str::FromStr for ExtraFields {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(Self::Error),
+            "ignore" => Ok(Self::Ignore),
+            "metadata" => Ok(Self::Metadata),
+            other => Err(format!(
+                "unknown extra fields policy \"{other}\" (expected error, ignore, or metadata)"
+            )),
+        }
+    }
+}
+
+/// Change at or above which a transaction draws a `LargeOverpayment`
+/// warning by default: $100.00, more than a till usually hands back in one
+/// go.
+pub const LARGE_OVERPAYMENT: Cents = Cents(10_000);
+
+/// How far paid may run past owed before the line draws a
+/// `LargeOverpayment` warning. Parsed from an amount (`100.00`) or a
+/// multiple (`10x`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverpaymentThreshold {
+    /// Change due of at least this much.
+    Change(Cents),
+    /// Paid more than this many times what's owed, like $200.00 for a
+    /// $1.97 item.
+    Multiple(u32),
+}
+
+impl Default for OverpaymentThreshold {
+    fn default() -> Self {
+        Self::Change(LARGE_OVERPAYMENT)
+    }
+}
+
+impl OverpaymentThreshold {
+    /// Whether `transaction` was overpaid past this threshold.
+    pub fn is_exceeded_by(self, transaction: &Transaction) -> bool {
+        match self {
+            Self::Change(min) => transaction.change_cents() >= min,
+            Self::Multiple(times) => {
+                u128::from(transaction.paid_cents().0)
+                    > u128::from(transaction.owed_cents().0) * u128::from(times)
             }
+        }
+    }
+}
 
-            let dollars: u32 = dollars_str
-                .parse()
-                .map_err(|_| format!("invalid dollar part: \"{s}\""))?;
+impl fmt::Display for OverpaymentThreshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Change(min) => write!(f, "{min}"),
+            Self::Multiple(times) => write!(f, "{times}x"),
+        }
+    }
+}
 
-            // Pad single digit: "3.1" means 10 cents, not 1 cent
-            let padded = if cents_str.len() == 1 {
-                format!("{cents_str}0")
-            } else {
-                cents_str.to_string()
-            };
+impl core::str::FromStr for OverpaymentThreshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.strip_suffix(['x', 'X']) {
+            Some(times) => match times.parse() {
+                Ok(times) if times >= 2 => Ok(Self::Multiple(times)),
+                _ => Err(format!(
+                    "invalid multiple \"{s}\" (expected a whole number of at least 2, like 10x)"
+                )),
+            },
+            None => match parse_dollars_to_cents(s)? {
+                Cents(0) => Err("the threshold must be more than zero".to_string()),
+                min => Ok(Self::Change(min)),
+            },
+        }
+    }
+}
+
+/// How [`read_entries_with`] reads lines beyond the basic `owed,paid`
+/// format. The defaults match [`read_entries`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// What to do with unrecognized trailing fields.
+    pub extra: ExtraFields,
+    /// How far past owed paid may run before a `LargeOverpayment` warning.
+    pub overpayment: OverpaymentThreshold,
+    /// How to round an amount with more than two decimal places; `None`
+    /// rejects it.
+    pub rounding: Option<RoundingMode>,
+    /// The rate for a `currency=` line with no `rate=` of its own.
+    pub exchange_rate: Option<ExchangeRate>,
+}
+
+/// Something odd about a line that doesn't stop it from being processed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWarning {
+    /// A trailing field was empty or only whitespace, e.g. `2.12,3.00, `.
+    EmptyField { line: usize },
+    /// The change due is at least `LARGE_OVERPAYMENT`, which is more often
+    /// a typo in the paid amount than a real sale.
+    LargeOverpayment { line: usize, change: Cents },
+}
+
+impl ParseWarning {
+    pub fn line(&self) -> usize {
+        match self {
+            Self::EmptyField { line } | Self::LargeOverpayment { line, .. } => *line,
+        }
+    }
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyField { line } => write!(f, "line {line}: warning: empty field ignored"),
+            Self::LargeOverpayment { line, change } => {
+                write!(
+                    f,
+                    "line {line}: warning: unusually large change of {change}"
+                )
+            }
+        }
+    }
+}
+
+/// Parse comma-separated `key=value` fields, skipping (and warning about)
+/// empty ones. Unrecognized fields are handled as `extra` says.
+fn parse_fields(
+    rest: Option<&str>,
+    line_number: usize,
+    extra: ExtraFields,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<(Fields, ForeignFields), CashRegisterError> {
+    let mut fields = Fields::default();
+    let mut foreign = ForeignFields::default();
+
+    for field in rest.into_iter().flat_map(|rest| rest.split(',')) {
+        let field = field.trim();
+        if field.is_empty() {
+            warnings.push(ParseWarning::EmptyField { line: line_number });
+            continue;
+        }
+        match field.split_once('=') {
+            Some(("tendered", value)) => {
+                fields.tendered = Some(parse_tendered(value, line_number)?)
+            }
+            Some(("register", value)) if !value.trim().is_empty() => {
+                fields.register = Some(value.trim().to_string())
+            }
+            Some(("id", value)) if !value.trim().is_empty() => {
+                fields.id = Some(value.trim().to_string())
+            }
+            Some(("customer", value)) if !value.trim().is_empty() => {
+                fields.customer = Some(value.trim().to_string())
+            }
+            Some(("time", value)) if !value.trim().is_empty() => {
+                fields.time = Some(value.trim().to_string())
+            }
+            Some(("currency", value))
+                if !value.trim().is_empty()
+                    && value.trim().chars().all(|c| c.is_ascii_alphabetic()) =>
+            {
+                foreign.currency = Some(value.trim().to_ascii_uppercase())
+            }
+            Some(("rate", value)) => {
+                foreign.rate =
+                    Some(
+                        value
+                            .parse()
+                            .map_err(|detail| CashRegisterError::MalformedLine {
+                                line: line_number,
+                                detail,
+                            })?,
+                    )
+            }
+            _ => match extra {
+                ExtraFields::Error => {
+                    return Err(CashRegisterError::MalformedLine {
+                        line: line_number,
+                        detail: format!("unexpected field \"{field}\""),
+                    })
+                }
+                ExtraFields::Ignore => {}
+                ExtraFields::Metadata => fields.extra.push(field.to_string()),
+            },
+        }
+    }
+
+    Ok((fields, foreign))
+}
+
+/// Split a line after its second comma: the two leading columns, and the
+/// trailing fields if there are any.
+fn split_fields(line: &str) -> (&str, Option<&str>) {
+    match line.match_indices(',').nth(1) {
+        Some((i, _)) => (&line[..i], Some(&line[i + 1..])),
+        None => (line, None),
+    }
+}
+
+/// Parse a till directive like "till add 0.25,40" or "till remove 1.00,5,register=R1".
+pub fn parse_till_command(
+    line: &str,
+    line_number: usize,
+) -> Result<(TillCommand, Fields), CashRegisterError> {
+    parse_till_command_warn(line, line_number, ExtraFields::Error, &mut Vec::new())
+}
+
+fn parse_till_command_warn(
+    line: &str,
+    line_number: usize,
+    extra: ExtraFields,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<(TillCommand, Fields), CashRegisterError> {
+    let malformed = |detail: String| CashRegisterError::MalformedLine {
+        line: line_number,
+        detail,
+    };
+
+    let rest = line
+        .trim()
+        .strip_prefix("till")
+        .ok_or_else(|| malformed(format!("expected a till directive but got \"{line}\"")))?
+        .trim_start();
+    let (action, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let (args, rest) = split_fields(args);
+
+    let (value_str, count_str) = args.split_once(',').ok_or_else(|| {
+        malformed(format!(
+            "expected \"till add|remove value,count\" but got \"{}\"",
+            line.trim()
+        ))
+    })?;
 
-            let cents: u32 = padded
-                .parse()
-                .map_err(|_| format!("invalid cents part: \"{s}\""))?;
+    let cents =
+        parse_value_cents(value_str).map_err(|reason| CashRegisterError::InvalidAmount {
+            line: line_number,
+            input: value_str.trim().to_string(),
+            reason,
+        })?;
+    let count: u32 = count_str
+        .trim()
+        .parse()
+        .map_err(|_| malformed(format!("invalid count \"{}\"", count_str.trim())))?;
+
+    let (fields, foreign) = parse_fields(rest, line_number, extra, warnings)?;
+    if fields.tendered.is_some() {
+        return Err(malformed(
+            "till directives take no tendered cash".to_string(),
+        ));
+    }
+    if !foreign.is_empty() {
+        return Err(malformed(
+            "till directives take no currency= or rate=".to_string(),
+        ));
+    }
+
+    match action {
+        "add" => Ok((TillCommand::Add { cents, count }, fields)),
+        "remove" => Ok((TillCommand::Remove { cents, count }, fields)),
+        other => Err(malformed(format!(
+            "unknown till action \"{other}\" (expected add or remove)"
+        ))),
+    }
+}
+
+/// Parse a tendered-cash list like "1.00x2+0.50x2" into `(cents, count)` pairs.
+pub fn parse_tendered(s: &str, line_number: usize) -> Result<Vec<(u32, u32)>, CashRegisterError> {
+    s.split('+')
+        .map(|item| {
+            let item = item.trim();
+            let (value_str, count_str) =
+                item.split_once('x')
+                    .ok_or_else(|| CashRegisterError::MalformedLine {
+                        line: line_number,
+                        detail: format!("expected \"valuexcount\" but got \"{item}\""),
+                    })?;
+            let cents = parse_value_cents(value_str).map_err(|reason| {
+                CashRegisterError::InvalidAmount {
+                    line: line_number,
+                    input: value_str.trim().to_string(),
+                    reason,
+                }
+            })?;
+            let count: u32 =
+                count_str
+                    .trim()
+                    .parse()
+                    .map_err(|_| CashRegisterError::MalformedLine {
+                        line: line_number,
+                        detail: format!("invalid count \"{}\"", count_str.trim()),
+                    })?;
+            Ok((cents, count))
+        })
+        .collect()
+}
+
+/// Parse a refund line with optional trailing fields, like
+/// "REFUND,2.12,register=R1" or "-2.12,0", into the amount to pay out.
+pub fn parse_refund(line: &str, line_number: usize) -> Result<(Cents, Fields), CashRegisterError> {
+    parse_refund_warn(line, line_number, ParseOptions::default(), &mut Vec::new())
+}
 
-            Ok(dollars * 100 + cents)
+fn parse_refund_warn(
+    line: &str,
+    line_number: usize,
+    options: ParseOptions,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<(Cents, Fields), CashRegisterError> {
+    let malformed = |detail: String| CashRegisterError::MalformedLine {
+        line: line_number,
+        detail,
+    };
+    let (head, rest) = split_fields(line.trim());
+    let (first, second) = head.split_once(',').ok_or_else(|| {
+        malformed(format!(
+            "expected \"REFUND,amount\" but got \"{}\"",
+            line.trim()
+        ))
+    })?;
+    let as_written = |rounded: bool, s: &str| rounded.then(|| s.trim().to_string());
+    let (amount, owed, paid) = match first.trim().strip_prefix('-') {
+        // A negative owed amount: nothing is paid, and the store owes it.
+        Some(owed_str) => {
+            let (paid, _) = parse_amount(second, line_number, options.rounding)?;
+            if !paid.is_zero() {
+                return Err(malformed(format!(
+                    "a refund's paid amount must be 0 but got \"{}\"",
+                    second.trim()
+                )));
+            }
+            let (amount, rounded) = parse_amount(owed_str, line_number, options.rounding)?;
+            (amount, as_written(rounded, first), None)
+        }
+        None => {
+            let (amount, rounded) = parse_amount(second, line_number, options.rounding)?;
+            (amount, None, as_written(rounded, second))
         }
+    };
+    let (mut fields, foreign) = parse_fields(rest, line_number, options.extra, warnings)?;
+    if fields.tendered.is_some() {
+        return Err(malformed("refunds take no tendered cash".to_string()));
+    }
+    if !foreign.is_empty() {
+        return Err(malformed(
+            "refunds are paid out in the register's currency, so take no currency= or rate="
+                .to_string(),
+        ));
     }
+    if let Some(mode) = options
+        .rounding
+        .filter(|_| owed.is_some() || paid.is_some())
+    {
+        fields.rounded = Some(Rounded { mode, owed, paid });
+    }
+    Ok((amount, fields))
+}
+
+/// Parse a void line, `VOID T1001`, into the ID of the transaction to
+/// reverse.
+pub fn parse_void(line: &str, line_number: usize) -> Result<String, CashRegisterError> {
+    let id = line
+        .trim()
+        .get(4..)
+        .filter(|rest| is_void(line) && !rest.contains(','))
+        .map(str::trim)
+        .filter(|id| !id.is_empty());
+    id.map(ToString::to_string)
+        .ok_or_else(|| CashRegisterError::MalformedLine {
+            line: line_number,
+            detail: format!(
+                "expected \"VOID transaction-id\" but got \"{}\"",
+                line.trim()
+            ),
+        })
+}
+
+/// Whether a line is a void: `VOID` as its first word.
+fn is_void(line: &str) -> bool {
+    let first = line
+        .trim()
+        .split(char::is_whitespace)
+        .next()
+        .unwrap_or_default();
+    first.eq_ignore_ascii_case("void")
+}
+
+/// Whether a line is a refund: `REFUND` in the owed column, or a negative
+/// owed amount.
+fn is_refund(line: &str) -> bool {
+    let owed = line.split(',').next().unwrap_or_default().trim();
+    owed.eq_ignore_ascii_case("refund") || owed.starts_with('-')
+}
+
+/// Parse a transaction line with optional trailing fields,
+/// like "2.12,3.00,tendered=1.00x3,register=R1".
+pub fn parse_record(
+    line: &str,
+    line_number: usize,
+) -> Result<(Transaction, Fields), CashRegisterError> {
+    parse_record_warn(line, line_number, ParseOptions::default(), &mut Vec::new())
+}
+
+fn parse_record_warn(
+    line: &str,
+    line_number: usize,
+    options: ParseOptions,
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<(Transaction, Fields), CashRegisterError> {
+    let (head, rest) = split_fields(line.trim());
+    // The fields are read first, for a rate to convert the paid column at,
+    // but an error in the owed and paid columns still comes first.
+    let mut field_warnings = Vec::new();
+    let parsed = parse_fields(rest, line_number, options.extra, &mut field_warnings).and_then(
+        |(fields, foreign)| Ok((fields, foreign.resolve(options.exchange_rate, line_number)?)),
+    );
+    let rate = match &parsed {
+        Ok((_, Some((_, rate)))) => Some(*rate),
+        _ => None,
+    };
+    let head = parse_line_with(head, line_number, options.rounding, rate);
+    let parsed = match (&head, parsed) {
+        // Left unconverted for want of a rate, paid may only look short.
+        (Err(CashRegisterError::Underpayment { .. }), Err(e)) => return Err(e),
+        (_, parsed) => parsed,
+    };
+    let (transaction, rounded, split, foreign_paid) = head?;
+    warnings.append(&mut field_warnings);
+    let (mut fields, foreign) = parsed?;
+    fields.rounded = rounded;
+    fields.split = split;
+    if let Some((currency, rate)) = foreign {
+        let paid = foreign_paid
+            .filter(|_| fields.tendered.is_none())
+            .ok_or_else(|| CashRegisterError::MalformedLine {
+                line: line_number,
+                detail: format!("a payment in {currency} takes no tendered cash"),
+            })?;
+        fields.foreign = Some(ForeignTender {
+            currency,
+            paid,
+            rate,
+        });
+    }
+
+    if let Some(tendered) = &fields.tendered {
+        let total: Cents = tendered
+            .iter()
+            .map(|&(cents, count)| Cents::from(cents) * count)
+            .sum();
+        let (cash, paid) = match &fields.split {
+            Some(split) => (split.cash(), "cash paid"),
+            None => (transaction.paid_cents(), "paid"),
+        };
+        if total != cash {
+            return Err(CashRegisterError::MalformedLine {
+                line: line_number,
+                detail: format!("tendered cash totals {total} but {paid} is {cash}"),
+            });
+        }
+    }
+
+    if options.overpayment.is_exceeded_by(&transaction) {
+        warnings.push(ParseWarning::LargeOverpayment {
+            line: line_number,
+            change: transaction.change_cents(),
+        });
+    }
+
+    Ok((transaction, fields))
 }
 
 /// Parse a single line like "2.13,3.00" into a Transaction.
 pub fn parse_line(line: &str, line_number: usize) -> Result<Transaction, CashRegisterError> {
+    parse_line_with(line, line_number, None, None).map(|(transaction, ..)| transaction)
+}
+
+/// Like `parse_line`, rounding over-precise amounts as `rounding` says and
+/// noting the ones it rounded, and reading a split paid column. With a
+/// `rate`, a plain paid column is in another currency: it's converted,
+/// rounding with `rounding` or half-up, and returned as written too.
+#[allow(clippy::type_complexity)]
+fn parse_line_with(
+    line: &str,
+    line_number: usize,
+    rounding: Option<RoundingMode>,
+    rate: Option<ExchangeRate>,
+) -> Result<
+    (
+        Transaction,
+        Option<Rounded>,
+        Option<SplitTender>,
+        Option<Cents>,
+    ),
+    CashRegisterError,
+> {
     let line = line.trim();
 
     let (owed_str, paid_str) =
@@ -62,33 +898,180 @@ pub fn parse_line(line: &str, line_number: usize) -> Result<Transaction, CashReg
                 detail: format!("expected \"owed,paid\" but got \"{line}\""),
             })?;
 
-    let owed_cents =
-        parse_dollars_to_cents(owed_str).map_err(|_| CashRegisterError::InvalidAmount {
-            line: line_number,
-            input: owed_str.trim().to_string(),
-        })?;
+    let (owed_cents, owed_rounded) = parse_amount(owed_str, line_number, rounding)?;
+    let (paid_cents, paid_rounded, split) = if paid_str.contains(':') {
+        let (mut split, rounded) = parse_split_tender(paid_str, line_number, rounding)?;
+        split.apply_store_credit(owed_cents);
+        (split.total(), rounded, Some(split))
+    } else {
+        let (cents, rounded) = parse_amount(paid_str, line_number, rounding)?;
+        (cents, rounded, None)
+    };
+    let (paid_cents, foreign_paid) = match rate {
+        Some(_) if split.is_some() => {
+            return Err(CashRegisterError::MalformedLine {
+                line: line_number,
+                detail: "a payment in another currency can't be split".to_string(),
+            })
+        }
+        Some(rate) => {
+            let converted = rate
+                .to_register(paid_cents, rounding.unwrap_or_default())
+                .ok_or_else(|| CashRegisterError::InvalidAmount {
+                    line: line_number,
+                    input: paid_str.trim().to_string(),
+                    reason: format!("is more than {} at {rate}", Cents::MAX_AMOUNT),
+                })?;
+            (converted, Some(paid_cents))
+        }
+        None => (paid_cents, None),
+    };
 
-    let paid_cents =
-        parse_dollars_to_cents(paid_str).map_err(|_| CashRegisterError::InvalidAmount {
+    // Parsed amounts are already within range, so the only way this fails
+    // is an underpayment.
+    let transaction =
+        Transaction::new(owed_cents, paid_cents).map_err(|_| CashRegisterError::Underpayment {
             line: line_number,
-            input: paid_str.trim().to_string(),
+            owed: owed_str.trim().to_string(),
+            // A split or converted paid amount isn't what was written.
+            paid: if split.is_some() || rate.is_some() {
+                paid_cents.to_string()
+            } else {
+                paid_str.trim().to_string()
+            },
         })?;
+    if let Some(split) = &split {
+        if transaction.change_cents() > split.cash() {
+            return Err(CashRegisterError::RefusedTender {
+                line: line_number,
+                reason: format!(
+                    "non-cash tender of {} is more than the {owed_cents} owed, and change only comes from cash",
+                    split.total() - split.cash()
+                ),
+            });
+        }
+    }
+    let as_written = |rounded: bool, s: &str| rounded.then(|| s.trim().to_string());
+    let rounded = match rounding {
+        Some(mode) if owed_rounded || paid_rounded => Some(Rounded {
+            mode,
+            owed: as_written(owed_rounded, owed_str),
+            paid: as_written(paid_rounded, paid_str),
+        }),
+        _ => None,
+    };
+    Ok((transaction, rounded, split, foreign_paid))
+}
 
-    if paid_cents < owed_cents {
-        return Err(CashRegisterError::Underpayment {
+/// Parse a split paid column like `CARD:1.00+CASH:2.00` into its parts, and
+/// whether any amount was rounded. Kinds are letters, in any case; `CASH`
+/// may appear more than once.
+fn parse_split_tender(
+    s: &str,
+    line_number: usize,
+    rounding: Option<RoundingMode>,
+) -> Result<(SplitTender, bool), CashRegisterError> {
+    let mut parts = Vec::new();
+    let mut rounded = false;
+    for part in s.split('+') {
+        let part = part.trim();
+        let (kind, amount) = part
+            .split_once(':')
+            .filter(|(kind, _)| !kind.is_empty() && kind.chars().all(|c| c.is_ascii_alphabetic()))
+            .ok_or_else(|| CashRegisterError::MalformedLine {
+                line: line_number,
+                detail: format!("expected \"KIND:amount\" but got \"{part}\""),
+            })?;
+        let (cents, was_rounded) = parse_amount(amount, line_number, rounding)?;
+        rounded |= was_rounded;
+        parts.push((kind.to_ascii_lowercase(), cents));
+    }
+    let split = SplitTender {
+        parts,
+        returned: Vec::new(),
+    };
+    if split.total() > Cents::MAX_AMOUNT {
+        return Err(CashRegisterError::InvalidAmount {
             line: line_number,
-            owed: owed_str.trim().to_string(),
-            paid: paid_str.trim().to_string(),
+            input: s.trim().to_string(),
+            reason: format!("tenders total more than {}", Cents::MAX_AMOUNT),
         });
     }
+    Ok((split, rounded))
+}
 
-    Ok(Transaction {
-        owed_cents,
-        paid_cents,
-        change_cents: paid_cents - owed_cents,
+/// Parse one side of a transaction, telling a negative amount (an attempted
+/// refund) apart from one that just isn't a number.
+fn parse_amount(
+    s: &str,
+    line_number: usize,
+    rounding: Option<RoundingMode>,
+) -> Result<(Cents, bool), CashRegisterError> {
+    parse_dollars_to_cents_with(s, rounding).map_err(|reason| {
+        let input = s.trim().to_string();
+        if input.starts_with('-') {
+            CashRegisterError::NegativeAmount {
+                line: line_number,
+                input,
+            }
+        } else {
+            CashRegisterError::InvalidAmount {
+                line: line_number,
+                input,
+                reason,
+            }
+        }
     })
 }
 
+/// Parse change written by [`format_breakdown`](crate::format::format_breakdown),
+/// like "3 quarters,1 dime,3 pennies" or "no change", back into a breakdown.
+///
+/// Names match either the singular or plural, ignoring ASCII case, and items
+/// may come in any order; the result is normalized, largest first. Each
+/// denomination may appear only once, with a count of at least 1.
+pub fn parse_breakdown(s: &str, currency: &Currency) -> Result<Breakdown, BreakdownError> {
+    let s = s.trim();
+    if s.eq_ignore_ascii_case("no change") {
+        return Ok(Breakdown::new());
+    }
+
+    let mut breakdown: Breakdown = Breakdown::new();
+    for item in s.split(',') {
+        let item = item.trim();
+        let (count_str, name) = item.split_once(' ').ok_or_else(|| {
+            BreakdownError::Malformed(format!("expected \"count name\" but got \"{item}\""))
+        })?;
+        let count: u32 = match count_str.parse() {
+            Ok(count) if count > 0 => count,
+            _ => {
+                return Err(BreakdownError::Malformed(format!(
+                    "invalid count \"{count_str}\""
+                )))
+            }
+        };
+        let name = name.trim();
+        let index = currency
+            .denominations
+            .iter()
+            .position(|d| {
+                d.singular.eq_ignore_ascii_case(name) || d.plural.eq_ignore_ascii_case(name)
+            })
+            .ok_or_else(|| BreakdownError::UnknownDenomination {
+                name: name.to_string(),
+                currency: currency.name.to_string(),
+            })?;
+        if breakdown.iter().any(|&(i, _)| i == index) {
+            return Err(BreakdownError::Malformed(format!(
+                "{} listed more than once",
+                currency.denominations[index].plural
+            )));
+        }
+        breakdown.push((index, count));
+    }
+    Ok(normalize(&breakdown))
+}
+
 /// Parse all lines from input text, skipping blank lines.
 /// Returns a Vec of Results so one bad line doesn't prevent processing others.
 pub fn parse_input(input: &str) -> Vec<Result<Transaction, CashRegisterError>> {
@@ -100,34 +1083,147 @@ pub fn parse_input(input: &str) -> Vec<Result<Transaction, CashRegisterError>> {
         .collect()
 }
 
+/// Like `parse_input`, but also recognizes till directives and trailing fields, and lazily yields each
+/// entry with its 1-indexed line number so later-stage errors (e.g. till shortages)
+/// can be reported against the input.
+pub fn parse_entries(
+    input: &str,
+) -> impl Iterator<Item = (usize, Result<Entry, CashRegisterError>)> + '_ {
+    parse_entries_with_warnings(input).map(|(line, entry, _)| (line, entry))
+}
+
+/// Like `parse_entries`, but also yields each line's non-fatal warnings.
+/// A line can carry warnings whether or not it parsed.
+pub fn parse_entries_with_warnings(
+    input: &str,
+) -> impl Iterator<Item = (usize, Result<Entry, CashRegisterError>, Vec<ParseWarning>)> + '_ {
+    parse_entries_with(input, ParseOptions::default())
+}
+
+/// Like `parse_entries_with_warnings`, with lines read as `options` says,
+/// as `read_entries_with` reads them.
+pub fn parse_entries_with(
+    input: &str,
+    options: ParseOptions,
+) -> impl Iterator<Item = (usize, Result<Entry, CashRegisterError>, Vec<ParseWarning>)> + '_ {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(move |(i, line)| {
+            let (entry, warnings) = parse_entry(line, i + 1, options);
+            (i + 1, entry, warnings)
+        })
+}
+
+/// Like `parse_entries_with_warnings`, but reads the lines from `reader` as
+/// it goes, so an input of any size is parsed in constant memory.
+///
+/// A read error (including invalid UTF-8) is yielded as
+/// `CashRegisterError::Io` with the number of the line it hit, and ends the
+/// iteration.
+#[cfg(feature = "std")]
+pub fn read_entries<R: std::io::BufRead>(
+    reader: R,
+) -> impl Iterator<Item = (usize, Result<Entry, CashRegisterError>, Vec<ParseWarning>)> {
+    read_entries_with(reader, ParseOptions::default())
+}
+
+/// Like `read_entries`, with unrecognized trailing fields, overpayment
+/// warnings, and over-precise amounts handled as `options` says.
+#[cfg(feature = "std")]
+pub fn read_entries_with<R: std::io::BufRead>(
+    reader: R,
+    options: ParseOptions,
+) -> impl Iterator<Item = (usize, Result<Entry, CashRegisterError>, Vec<ParseWarning>)> {
+    let mut lines = reader.lines().enumerate();
+    let mut failed = false;
+    core::iter::from_fn(move || {
+        if failed {
+            return None;
+        }
+        for (i, line) in lines.by_ref() {
+            match line {
+                Ok(line) if line.trim().is_empty() => {}
+                Ok(line) => {
+                    let (entry, warnings) = parse_entry(&line, i + 1, options);
+                    return Some((i + 1, entry, warnings));
+                }
+                Err(e) => {
+                    failed = true;
+                    return Some((i + 1, Err(e.into()), Vec::new()));
+                }
+            }
+        }
+        None
+    })
+}
+
+/// Parse one non-blank input line: a till directive, a void, a refund, or
+/// a transaction.
+pub(crate) fn parse_entry(
+    line: &str,
+    line_number: usize,
+    options: ParseOptions,
+) -> (Result<Entry, CashRegisterError>, Vec<ParseWarning>) {
+    let mut warnings = Vec::new();
+    let entry = if line.trim_start().starts_with("till") {
+        parse_till_command_warn(line, line_number, options.extra, &mut warnings)
+            .map(|(cmd, fields)| Entry::Till(cmd, fields))
+    } else if is_void(line) {
+        parse_void(line, line_number).map(Entry::Void)
+    } else if is_refund(line) {
+        parse_refund_warn(line, line_number, options, &mut warnings)
+            .map(|(amount, fields)| Entry::Refund(amount, fields))
+    } else {
+        parse_record_warn(line, line_number, options, &mut warnings)
+            .map(|(tx, fields)| Entry::Transaction(tx, fields))
+    };
+    match &entry {
+        Ok(_) => trace!(line = line_number, warnings = warnings.len(), "parsed line"),
+        Err(e) => debug!(line = line_number, error = %e, "rejected line"),
+    }
+    (entry, warnings)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::vec;
 
     #[test]
     fn parse_whole_dollars() {
-        assert_eq!(parse_dollars_to_cents("3"), Ok(300));
-        assert_eq!(parse_dollars_to_cents("0"), Ok(0));
-        assert_eq!(parse_dollars_to_cents("100"), Ok(10_000));
+        assert_eq!(parse_dollars_to_cents("3"), Ok(Cents(300)));
+        assert_eq!(parse_dollars_to_cents("0"), Ok(Cents(0)));
+        assert_eq!(parse_dollars_to_cents("100"), Ok(Cents(10_000)));
     }
 
     #[test]
     fn parse_dollars_and_cents() {
-        assert_eq!(parse_dollars_to_cents("2.13"), Ok(213));
-        assert_eq!(parse_dollars_to_cents("3.00"), Ok(300));
-        assert_eq!(parse_dollars_to_cents("0.01"), Ok(1));
-        assert_eq!(parse_dollars_to_cents("0.50"), Ok(50));
+        assert_eq!(parse_dollars_to_cents("2.13"), Ok(Cents(213)));
+        assert_eq!(parse_dollars_to_cents("3.00"), Ok(Cents(300)));
+        assert_eq!(parse_dollars_to_cents("0.01"), Ok(Cents(1)));
+        assert_eq!(parse_dollars_to_cents("0.50"), Ok(Cents(50)));
     }
 
     #[test]
     fn parse_single_decimal_digit() {
         // "3.1" means $3.10
-        assert_eq!(parse_dollars_to_cents("3.1"), Ok(310));
+        assert_eq!(parse_dollars_to_cents("3.1"), Ok(Cents(310)));
+    }
+
+    #[test]
+    fn parse_without_a_zero_beside_the_point() {
+        assert_eq!(parse_dollars_to_cents(".50"), Ok(Cents(50)));
+        assert_eq!(parse_dollars_to_cents(".5"), Ok(Cents(50)));
+        assert_eq!(parse_dollars_to_cents("5."), Ok(Cents(500)));
+        assert_eq!(parse_dollars_to_cents("+.07"), Ok(Cents(7)));
+        assert_eq!(parse_line(".75,1.", 1).unwrap().change_cents(), Cents(25));
     }
 
     #[test]
     fn parse_with_whitespace() {
-        assert_eq!(parse_dollars_to_cents("  2.13  "), Ok(213));
+        assert_eq!(parse_dollars_to_cents("  2.13  "), Ok(Cents(213)));
     }
 
     #[test]
@@ -146,24 +1242,72 @@ mod tests {
         assert!(parse_dollars_to_cents("1.ab").is_err());
     }
 
+    #[test]
+    fn parse_reports_which_part_is_bad() {
+        let err = |s| parse_dollars_to_cents(s).unwrap_err();
+        assert_eq!(err("3.-1"), "invalid cents part");
+        assert_eq!(err("."), "invalid dollar part");
+        assert_eq!(err("abc"), "invalid dollar part");
+        assert_eq!(err("2.123"), "too many decimal places");
+        assert_eq!(err("٣.00"), "invalid dollar part");
+        assert_eq!(err("-1.50"), "negative amount");
+        assert_eq!(err("99999999999999999999"), "amount too large");
+        assert_eq!(parse_dollars_to_cents("+3.05"), Ok(Cents(305)));
+        assert_eq!(parse_dollars_to_cents("007.5"), Ok(Cents(750)));
+    }
+
+    #[test]
+    fn parse_rejects_trailing_garbage() {
+        let err = |s| parse_dollars_to_cents(s).unwrap_err();
+        for input in ["2.13abc", "2.13 3", "3abc", "1.2.3", "1.5.", "2.13,"] {
+            assert_eq!(err(input), "unexpected characters after amount", "{input}");
+        }
+    }
+
+    #[test]
+    fn parse_rejects_amounts_over_max() {
+        assert_eq!(parse_dollars_to_cents("42949672.95"), Ok(Cents::MAX_AMOUNT));
+        assert!(parse_dollars_to_cents("42949672.96").is_err());
+        assert!(parse_dollars_to_cents("99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn transaction_new_enforces_invariants() {
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        assert_eq!(tx.change_cents(), Cents(88));
+        assert_eq!(
+            Transaction::new(Cents(300), Cents(212)),
+            Err(TransactionError::Underpayment {
+                owed: Cents(300),
+                paid: Cents(212)
+            })
+        );
+        let too_much = Cents::MAX_AMOUNT + Cents(1);
+        assert_eq!(
+            Transaction::new(Cents(1), too_much),
+            Err(TransactionError::TooLarge(too_much))
+        );
+        assert!(Transaction::new(Cents::ZERO, Cents::MAX_AMOUNT).is_ok());
+    }
+
     #[test]
     fn parse_line_valid() {
         let tx = parse_line("2.12,3.00", 1).unwrap();
-        assert_eq!(tx.owed_cents, 212);
-        assert_eq!(tx.paid_cents, 300);
-        assert_eq!(tx.change_cents, 88);
+        assert_eq!(tx.owed_cents(), Cents(212));
+        assert_eq!(tx.paid_cents(), Cents(300));
+        assert_eq!(tx.change_cents(), Cents(88));
     }
 
     #[test]
     fn parse_line_with_whitespace() {
         let tx = parse_line("  2.12 , 3.00  ", 1).unwrap();
-        assert_eq!(tx.change_cents, 88);
+        assert_eq!(tx.change_cents(), Cents(88));
     }
 
     #[test]
     fn parse_line_exact_payment() {
         let tx = parse_line("5.00,5.00", 1).unwrap();
-        assert_eq!(tx.change_cents, 0);
+        assert_eq!(tx.change_cents(), Cents(0));
     }
 
     #[test]
@@ -184,6 +1328,134 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn parse_line_negative_amount() {
+        let err = parse_line("-1.50,3.00", 4).unwrap_err();
+        assert!(matches!(
+            &err,
+            CashRegisterError::NegativeAmount { line: 4, input } if input == "-1.50"
+        ));
+        assert_eq!(err.code(), "E013");
+        assert!(err.to_string().contains("refund"), "{err}");
+        assert!(matches!(
+            parse_line("1.50, -3.00", 1),
+            Err(CashRegisterError::NegativeAmount { .. })
+        ));
+        assert!(matches!(
+            parse_line("1-50,3.00", 1),
+            Err(CashRegisterError::InvalidAmount { .. })
+        ));
+    }
+
+    #[test]
+    fn voids_name_a_transaction_id() {
+        let entry = |line: &str| parse_entry(line, 3, ParseOptions::default()).0;
+        assert_eq!(entry("VOID T1001").unwrap(), Entry::Void("T1001".into()));
+        assert_eq!(entry("  void\tT 7 ").unwrap(), Entry::Void("T 7".into()));
+        let err = entry("VOID").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 3: expected \"VOID transaction-id\" but got \"VOID\""
+        );
+        assert_eq!(entry("VOID T1,register=R1").unwrap_err().code(), "E003");
+        // Only the whole first word counts.
+        assert_eq!(entry("VOIDED").unwrap_err().code(), "E003");
+        assert!(matches!(
+            entry("voided,1.00"),
+            Err(CashRegisterError::InvalidAmount { .. })
+        ));
+    }
+
+    #[test]
+    fn refunds_are_entries_of_their_own() {
+        let entry = |line: &str| parse_entry(line, 2, ParseOptions::default()).0;
+        let (amount, fields) = parse_refund("REFUND,2.12,register=R1", 1).unwrap();
+        assert_eq!(amount, Cents(212));
+        assert_eq!(fields.register(), "R1");
+        assert_eq!(
+            entry("refund, 2.12").unwrap(),
+            Entry::Refund(Cents(212), Fields::default())
+        );
+        assert_eq!(
+            entry("-2.12,0.00").unwrap(),
+            Entry::Refund(Cents(212), Fields::default())
+        );
+
+        let err = entry("-2.12,1.00").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: a refund's paid amount must be 0 but got \"1.00\""
+        );
+        assert_eq!(entry("REFUND,-2.12").unwrap_err().code(), "E013");
+        assert_eq!(
+            entry("REFUND,2.12,tendered=1.00x2").unwrap_err().code(),
+            "E003"
+        );
+        assert_eq!(entry("REFUND").unwrap_err().code(), "E003");
+
+        let options = ParseOptions {
+            rounding: Some(RoundingMode::HalfUp),
+            ..ParseOptions::default()
+        };
+        let (entry, _) = parse_entry("-2.125,0", 1, options);
+        let Ok(Entry::Refund(amount, fields)) = entry else {
+            panic!("expected a refund, got {entry:?}");
+        };
+        assert_eq!(amount, Cents(213));
+        assert_eq!(fields.rounded.unwrap().owed.as_deref(), Some("-2.125"));
+    }
+
+    #[test]
+    fn foreign_tender_is_converted_at_the_rate() {
+        let entry = |line: &str, options| parse_entry(line, 2, options).0;
+        let (tx, fields) = parse_record("2.12,2.00,currency=eur,rate=1.0825", 1).unwrap();
+        // 2.165 rounds half-up.
+        assert_eq!(tx.paid_cents(), Cents(217));
+        assert_eq!(
+            fields.foreign,
+            Some(ForeignTender {
+                currency: "EUR".to_string(),
+                paid: Cents(200),
+                rate: "1.0825".parse().unwrap(),
+            })
+        );
+        assert_eq!(
+            fields.foreign.unwrap().tendered_currency().unwrap().name,
+            "EUR"
+        );
+
+        let options = ParseOptions {
+            rounding: Some(RoundingMode::HalfEven),
+            exchange_rate: "1.0825".parse().ok(),
+            ..ParseOptions::default()
+        };
+        let Ok(Entry::Transaction(tx, _)) = entry("2.12,2.00,currency=EUR", options) else {
+            panic!("expected a transaction");
+        };
+        assert_eq!(tx.paid_cents(), Cents(216));
+
+        let err = entry("2.12,2.00,currency=EUR", ParseOptions::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: no exchange rate for EUR (give rate= or --exchange-rate)"
+        );
+        let err = entry("2.50,2.00,currency=EUR,rate=1.08", options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: paid (2.16) is less than owed (2.50)"
+        );
+        for line in [
+            "2.12,2.00,rate=1.08",
+            "2.12,2.00,currency=EUR,rate=0",
+            "2.12,CASH:2.00,currency=EUR,rate=1.08",
+            "2.12,2.00,currency=EUR,rate=1.08,tendered=2.00x1",
+            "REFUND,2.12,currency=EUR",
+            "till add 0.25,40,rate=1.08",
+        ] {
+            assert_eq!(entry(line, options).unwrap_err().code(), "E003", "{line}");
+        }
+    }
+
     #[test]
     fn parse_input_skips_blank_lines() {
         let input = "2.12,3.00\n\n1.97,2.00\n";
@@ -203,4 +1475,383 @@ mod tests {
             other => panic!("expected MalformedLine, got {:?}", other),
         }
     }
+
+    #[test]
+    fn parse_entries_reports_source_lines() {
+        let input = "2.12,3.00\n\n1.97,2.00\n";
+        let numbers: Vec<usize> = parse_entries(input).map(|(n, _)| n).collect();
+        assert_eq!(numbers, vec![1, 3]);
+    }
+
+    #[test]
+    fn parse_entries_recognizes_till_directives() {
+        let input = "2.12,3.00\ntill add 0.25,40\n  till remove 1.00,5\n";
+        let entries: Vec<Entry> = parse_entries(input).map(|(_, e)| e.unwrap()).collect();
+        assert_eq!(
+            entries[1..],
+            [
+                Entry::Till(
+                    TillCommand::Add {
+                        cents: 25,
+                        count: 40
+                    },
+                    Fields::default()
+                ),
+                Entry::Till(
+                    TillCommand::Remove {
+                        cents: 100,
+                        count: 5
+                    },
+                    Fields::default()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_record_without_fields() {
+        let (tx, fields) = parse_record("2.12,3.00", 1).unwrap();
+        assert_eq!(tx.change_cents(), Cents(88));
+        assert_eq!(fields, Fields::default());
+    }
+
+    #[test]
+    fn parse_record_tendered() {
+        let (tx, fields) = parse_record("2.12, 3.00, tendered=1.00x2+0.50x2", 1).unwrap();
+        assert_eq!(tx.paid_cents(), Cents(300));
+        assert_eq!(fields.tendered, Some(vec![(100, 2), (50, 2)]));
+    }
+
+    #[test]
+    fn parse_record_split_tender() {
+        let (tx, fields) = parse_record("2.12,CARD:1.00+cash:2.00,tendered=1.00x2", 1).unwrap();
+        assert_eq!(tx.paid_cents(), Cents(300));
+        assert_eq!(tx.change_cents(), Cents(88));
+        let split = fields.split.unwrap();
+        assert_eq!(
+            split.parts,
+            [
+                ("card".to_string(), Cents(100)),
+                ("cash".to_string(), Cents(200))
+            ]
+        );
+        assert_eq!(split.cash(), Cents(200));
+
+        // Card alone covers it, and only what's left over in cash comes back.
+        let tx = parse_line("2.12,CARD:2.12+CASH:5.00", 1).unwrap();
+        assert_eq!(tx.change_cents(), Cents(500));
+
+        let err = parse_line("2.12,CARD:5.00+CASH:1.00", 3).unwrap_err();
+        assert_eq!(err.code(), "E006");
+        assert_eq!(
+            err.to_string(),
+            "line 3: tender refused: non-cash tender of 5.00 is more than the 2.12 owed, and change only comes from cash"
+        );
+        // Store credit pays what the card leaves, and the rest stays on it.
+        let (tx, fields) = parse_record("2.12,CARD:1.00+GIFT:5.00+CASH:1.00", 1).unwrap();
+        assert_eq!(tx.paid_cents(), Cents(312));
+        assert_eq!(tx.change_cents(), Cents(100));
+        let split = fields.split.unwrap();
+        assert_eq!(split.parts[1], ("gift".to_string(), Cents(112)));
+        assert_eq!(split.returned, [("gift".to_string(), Cents(388))]);
+        // It never becomes change: the cash comes back instead.
+        let tx = parse_line("2.12,STORE:3.00+CASH:1.00", 1).unwrap();
+        assert_eq!(tx.change_cents(), Cents(100));
+        let tx = parse_line("2.12,GIFT:1.00+CASH:2.00", 1).unwrap();
+        assert_eq!(tx.change_cents(), Cents(88));
+
+        let err = parse_line("5.00,CARD:1.00+CASH:2.00", 2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: paid (3.00) is less than owed (5.00)"
+        );
+        assert_eq!(parse_line("2.12,CARD 1.00", 1).unwrap_err().code(), "E001");
+        assert_eq!(
+            parse_line("2.12,:1.00+CASH:2.00", 1).unwrap_err().code(),
+            "E003"
+        );
+        assert_eq!(
+            parse_record("2.12,CARD:1.00+CASH:2.00,tendered=1.00x3", 1)
+                .unwrap_err()
+                .to_string(),
+            "line 1: tendered cash totals 3.00 but cash paid is 2.00"
+        );
+    }
+
+    #[test]
+    fn parse_record_tendered_must_match_paid() {
+        let result = parse_record("2.12,3.00,tendered=1.00x2", 4);
+        match result {
+            Err(CashRegisterError::MalformedLine { line, detail }) => {
+                assert_eq!(line, 4);
+                assert_eq!(detail, "tendered cash totals 2.00 but paid is 3.00");
+            }
+            other => panic!("expected MalformedLine, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_record_register() {
+        let (_, fields) = parse_record("2.12,3.00,register=R2", 1).unwrap();
+        assert_eq!(fields.register(), "R2");
+        let (_, fields) = parse_record("2.12,3.00", 1).unwrap();
+        assert_eq!(fields.register(), "");
+        assert!(parse_record("2.12,3.00,register=", 1).is_err());
+    }
+
+    #[test]
+    fn max_amount_caps_owed_and_paid() {
+        let tx = parse_line("100000.00,100001.00", 3).unwrap();
+        let err = tx.check_max_amount(Cents(100_000), 3).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 3: 100000.00 is over the 1000.00 limit per amount"
+        );
+        assert_eq!(err.code(), "E015");
+        let tx = parse_line("999.00,1001.00", 1).unwrap();
+        assert!(tx.check_max_amount(Cents(100_000), 1).is_err());
+        assert!(tx.check_max_amount(Cents(100_100), 1).is_ok());
+    }
+
+    #[test]
+    fn parse_record_id() {
+        let (_, fields) = parse_record("2.12,3.00,id=T1001,register=R2", 1).unwrap();
+        assert_eq!(fields.id.as_deref(), Some("T1001"));
+        let (_, fields) = parse_record("2.12,3.00", 1).unwrap();
+        assert_eq!(fields.id, None);
+        assert!(parse_record("2.12,3.00,id=", 1).is_err());
+        let (_, fields) = parse_record("2.12,3.00,customer= C42 ,id=T1", 1).unwrap();
+        assert_eq!(fields.customer.as_deref(), Some("C42"));
+        assert!(parse_record("2.12,3.00,customer=", 1).is_err());
+        let (_, fields) = parse_record("2.12,3.00,time=2026-10-16T14:05:00Z", 1).unwrap();
+        assert_eq!(fields.time.as_deref(), Some("2026-10-16T14:05:00Z"));
+    }
+
+    #[test]
+    fn parse_till_command_for_register() {
+        let (command, fields) = parse_till_command("till add 0.25,40,register=R1", 1).unwrap();
+        assert_eq!(
+            command,
+            TillCommand::Add {
+                cents: 25,
+                count: 40
+            }
+        );
+        assert_eq!(fields.register(), "R1");
+        assert!(parse_till_command("till add 0.25,4,tendered=0.25x4", 1).is_err());
+    }
+
+    #[test]
+    fn parse_record_rejects_unknown_field() {
+        assert!(matches!(
+            parse_record("2.12,3.00,notes", 1),
+            Err(CashRegisterError::MalformedLine { .. })
+        ));
+        assert!(matches!(
+            parse_record("2.12,3.00,tendered=1.00", 1),
+            Err(CashRegisterError::MalformedLine { .. })
+        ));
+    }
+
+    #[test]
+    fn extra_fields_policy() {
+        let line = "2.12,3.00,notes,register=R1,aisle=4";
+        let entry = |extra| {
+            let options = ParseOptions {
+                extra,
+                ..ParseOptions::default()
+            };
+            parse_entry(line, 1, options).0
+        };
+        assert!(matches!(
+            entry(ExtraFields::Error),
+            Err(CashRegisterError::MalformedLine { .. })
+        ));
+        let Ok(Entry::Transaction(_, fields)) = entry(ExtraFields::Ignore) else {
+            panic!("ignored fields should parse");
+        };
+        assert!(fields.extra.is_empty());
+        assert_eq!(fields.register(), "R1");
+        let Ok(Entry::Transaction(_, fields)) = entry(ExtraFields::Metadata) else {
+            panic!("kept fields should parse");
+        };
+        assert_eq!(fields.extra, ["notes", "aisle=4"]);
+
+        assert_eq!("Metadata".parse(), Ok(ExtraFields::Metadata));
+        assert!("keep".parse::<ExtraFields>().is_err());
+    }
+
+    #[test]
+    fn warnings_are_reported_alongside_entries() {
+        let input = "2.12,3.00, \n1.00,200.00\ntill add 0.25,4,,register=R1\nabc,\n2.12,3.00\n";
+        let parsed: Vec<_> = parse_entries_with_warnings(input).collect();
+        assert_eq!(parsed[0].2, [ParseWarning::EmptyField { line: 1 }]);
+        assert!(parsed[0].1.is_ok());
+        assert_eq!(
+            parsed[1].2,
+            [ParseWarning::LargeOverpayment {
+                line: 2,
+                change: Cents(19_900)
+            }]
+        );
+        assert_eq!(parsed[2].2, [ParseWarning::EmptyField { line: 3 }]);
+        assert!(parsed[3].1.is_err());
+        assert!(parsed[4].2.is_empty());
+        assert_eq!(
+            parsed[1].2[0].to_string(),
+            "line 2: warning: unusually large change of 199.00"
+        );
+    }
+
+    #[test]
+    fn rounding_brings_amounts_to_whole_cents() {
+        let round = |s, mode| parse_dollars_to_cents_with(s, Some(mode)).unwrap();
+        assert_eq!(round("2.125", RoundingMode::HalfUp), (Cents(213), true));
+        assert_eq!(round("2.125", RoundingMode::HalfEven), (Cents(212), true));
+        assert_eq!(round("2.135", RoundingMode::HalfEven), (Cents(214), true));
+        assert_eq!(round("2.125", RoundingMode::HalfDown), (Cents(212), true));
+        assert_eq!(round("2.1251", RoundingMode::HalfDown), (Cents(213), true));
+        assert_eq!(round("2.1251", RoundingMode::HalfEven), (Cents(213), true));
+        assert_eq!(round("2.129", RoundingMode::TowardZero), (Cents(212), true));
+        assert_eq!(round("2.995", RoundingMode::HalfUp), (Cents(300), true));
+        assert_eq!(round("2.130", RoundingMode::HalfUp), (Cents(213), false));
+        assert_eq!(round("2.1", RoundingMode::HalfUp), (Cents(210), false));
+        assert_eq!(
+            parse_dollars_to_cents("2.125"),
+            Err("too many decimal places".to_string())
+        );
+        assert_eq!(
+            parse_dollars_to_cents_with("42949672.955", Some(RoundingMode::HalfUp)),
+            Err("amount too large".to_string())
+        );
+        assert_eq!("Half-Even".parse(), Ok(RoundingMode::HalfEven));
+        assert!("bankers".parse::<RoundingMode>().is_err());
+
+        let options = ParseOptions {
+            rounding: Some(RoundingMode::HalfUp),
+            ..ParseOptions::default()
+        };
+        let parsed: Vec<_> = parse_entries_with("2.125,3.00\n2.12,3.00\n", options)
+            .map(|(_, entry, _)| entry.unwrap())
+            .collect();
+        let Entry::Transaction(tx, fields) = &parsed[0] else {
+            panic!("expected a transaction");
+        };
+        assert_eq!(tx.owed_cents(), Cents(213));
+        assert_eq!(
+            fields.rounded,
+            Some(Rounded {
+                mode: RoundingMode::HalfUp,
+                owed: Some("2.125".to_string()),
+                paid: None,
+            })
+        );
+        let Entry::Transaction(_, fields) = &parsed[1] else {
+            panic!("expected a transaction");
+        };
+        assert_eq!(fields.rounded, None);
+    }
+
+    #[test]
+    fn overpayment_threshold_is_an_amount_or_a_multiple() {
+        let tx = parse_line("1.97,200.00", 1).unwrap();
+        let threshold = |s: &str| s.parse::<OverpaymentThreshold>().unwrap();
+        assert!(threshold("100.00").is_exceeded_by(&tx));
+        assert!(!threshold("500").is_exceeded_by(&tx));
+        assert!(threshold("10x").is_exceeded_by(&tx));
+        assert!(!threshold("102X").is_exceeded_by(&tx));
+        assert_eq!(threshold("10x").to_string(), "10x");
+        assert_eq!(threshold("50").to_string(), "50.00");
+        assert!("1x".parse::<OverpaymentThreshold>().is_err());
+        assert!("0".parse::<OverpaymentThreshold>().is_err());
+        assert!("tenx".parse::<OverpaymentThreshold>().is_err());
+
+        let input = "1.97,20.00\n50.00,60.00\n";
+        let options = ParseOptions {
+            overpayment: OverpaymentThreshold::Multiple(5),
+            ..ParseOptions::default()
+        };
+        let parsed: Vec<_> = parse_entries_with(input, options).collect();
+        assert_eq!(
+            parsed[0].2,
+            [ParseWarning::LargeOverpayment {
+                line: 1,
+                change: Cents(1803)
+            }]
+        );
+        assert!(parsed[1].2.is_empty());
+    }
+
+    #[test]
+    fn parse_till_command_rejects_unknown_action() {
+        assert!(matches!(
+            parse_till_command("till borrow 0.25,4", 7),
+            Err(CashRegisterError::MalformedLine { line: 7, .. })
+        ));
+    }
+
+    #[test]
+    fn parse_till_command_rejects_missing_count() {
+        assert!(matches!(
+            parse_till_command("till add 0.25", 1),
+            Err(CashRegisterError::MalformedLine { .. })
+        ));
+        assert!(matches!(
+            parse_till_command("till add x,4", 1),
+            Err(CashRegisterError::InvalidAmount { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_breakdown_reads_formatted_change() {
+        use crate::currency::{EUR, USD};
+        use crate::format::format_breakdown;
+
+        let breakdown = parse_breakdown("3 quarters,1 dime,3 pennies", &USD).unwrap();
+        assert_eq!(
+            format_breakdown(&breakdown, &USD),
+            "3 quarters,1 dime,3 pennies"
+        );
+        assert_eq!(
+            format_breakdown(&parse_breakdown("1 Penny, 2 DOLLARS", &USD).unwrap(), &USD),
+            "2 dollars,1 penny"
+        );
+        assert_eq!(
+            format_breakdown(&parse_breakdown("2 2 euro coins", &EUR).unwrap(), &EUR),
+            "2 2 euro coins"
+        );
+        assert!(parse_breakdown("no change", &USD).unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_breakdown_rejects_bad_items() {
+        use crate::currency::USD;
+
+        let err = parse_breakdown("3 quarters,1 loonie", &USD).unwrap_err();
+        assert_eq!(err.to_string(), "\"loonie\" is not a USD denomination");
+        assert_eq!(err.code(), "E004");
+        for bad in ["", "quarters", "0 quarters", "x dimes", "1 dime,2 dimes"] {
+            let err = parse_breakdown(bad, &USD).unwrap_err();
+            assert_eq!(err.code(), "E003", "{bad:?}: {err}");
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn read_entries_streams_lines_and_stops_at_bad_bytes() {
+        let input: &[u8] = b"2.12,3.00\n\ntill add 0.25,4\n1.00,2.00\n\xff\n3.00,4.00\n";
+        let entries: Vec<_> = read_entries(input).collect();
+        let lines: Vec<usize> = entries.iter().map(|(line, ..)| *line).collect();
+        assert_eq!(lines, [1, 3, 4, 5]);
+        assert!(matches!(entries[1].1, Ok(Entry::Till(..))));
+        assert!(matches!(entries[3].1, Err(CashRegisterError::Io(_))));
+
+        let streamed: Vec<_> = read_entries("2.12,3.00\nx\n".as_bytes())
+            .map(|(line, entry, _)| (line, entry.is_ok()))
+            .collect();
+        let buffered: Vec<_> = parse_entries("2.12,3.00\nx\n")
+            .map(|(line, entry)| (line, entry.is_ok()))
+            .collect();
+        assert_eq!(streamed, buffered);
+    }
 }
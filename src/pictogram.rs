@@ -0,0 +1,155 @@
+//! Pictures for denominations, for `--output pictogram`: customer-facing
+//! displays where `🪙🪙🪙 $0.25` reads faster than `3 quarters`.
+//!
+//! Every coin is drawn as [`COIN`] and every bill as [`BILL`] unless
+//! [`Glyphs`] says otherwise, for coins or bills as a whole or for one
+//! denomination by its value:
+//!
+//! ```
+//! use cash_register::currency::USD;
+//! use cash_register::pictogram::Glyphs;
+//!
+//! let mut glyphs = Glyphs::default();
+//! glyphs.set("0.25", "(Q)").unwrap();
+//! glyphs.set("bill", "[$]").unwrap();
+//! assert_eq!(glyphs.glyph(&USD.denominations[0]), "[$]");
+//! assert_eq!(glyphs.glyph(&USD.denominations[1]), "(Q)");
+//! assert_eq!(glyphs.glyph(&USD.denominations[2]), "🪙");
+//! ```
+
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::currency::{Denomination, DenominationKind};
+use crate::money::Cents;
+use crate::parse::parse_value_cents;
+
+/// Drawn for a coin with no glyph of its own.
+pub const COIN: &str = "🪙";
+/// Drawn for a bill with no glyph of its own.
+pub const BILL: &str = "💵";
+
+/// Pieces up to this many are drawn one glyph each; more are drawn once
+/// with a count, `🪙×12`.
+pub const MAX_REPEAT: u32 = 5;
+
+/// Which glyph each denomination is drawn as.
+///
+/// Serializes as a map from `coin`, `bill`, or a denomination's value
+/// (`"0.25"`) to its glyph. A value no denomination of the currency in use
+/// has is ignored, so one config can cover several currencies.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(
+    try_from = "BTreeMap<String, String>",
+    into = "BTreeMap<String, String>"
+)]
+pub struct Glyphs {
+    coin: Option<String>,
+    bill: Option<String>,
+    /// By value in cents.
+    pieces: BTreeMap<u32, String>,
+}
+
+impl Glyphs {
+    /// Draw `key`, `coin`, `bill`, or a denomination's value like `0.25`,
+    /// as `glyph`. Fails for any other key, or an empty glyph.
+    pub fn set(&mut self, key: &str, glyph: &str) -> Result<(), String> {
+        if glyph.is_empty() {
+            return Err(format!("empty glyph for \"{key}\""));
+        }
+        let glyph = glyph.to_string();
+        match key.trim().to_lowercase().as_str() {
+            "coin" => self.coin = Some(glyph),
+            "bill" => self.bill = Some(glyph),
+            value => {
+                let cents = parse_value_cents(value)
+                    .ok()
+                    .filter(|&cents| cents > 0)
+                    .ok_or_else(|| {
+                        format!("invalid glyph key \"{key}\" (expected coin, bill, or a value like 0.25)")
+                    })?;
+                self.pieces.insert(cents, glyph);
+            }
+        }
+        Ok(())
+    }
+
+    /// The glyph `denomination` is drawn as.
+    pub fn glyph(&self, denomination: &Denomination) -> &str {
+        if let Some(glyph) = self.pieces.get(&denomination.cents) {
+            return glyph;
+        }
+        match denomination.kind {
+            DenominationKind::Coin => self.coin.as_deref().unwrap_or(COIN),
+            DenominationKind::Bill => self.bill.as_deref().unwrap_or(BILL),
+        }
+    }
+
+    /// Whether every denomination is drawn the default way.
+    pub fn is_empty(&self) -> bool {
+        self.coin.is_none() && self.bill.is_none() && self.pieces.is_empty()
+    }
+}
+
+impl TryFrom<BTreeMap<String, String>> for Glyphs {
+    type Error = String;
+
+    fn try_from(map: BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        let mut glyphs = Self::default();
+        for (key, glyph) in &map {
+            glyphs.set(key, glyph)?;
+        }
+        Ok(glyphs)
+    }
+}
+
+impl From<Glyphs> for BTreeMap<String, String> {
+    fn from(glyphs: Glyphs) -> Self {
+        let kinds = [("coin", glyphs.coin), ("bill", glyphs.bill)]
+            .into_iter()
+            .filter_map(|(key, glyph)| Some((key.to_string(), glyph?)));
+        let pieces = glyphs
+            .pieces
+            .into_iter()
+            .map(|(cents, glyph)| (Cents::from(cents).to_string(), glyph));
+        kinds.chain(pieces).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::{EUR, USD};
+
+    #[test]
+    fn keys_name_kinds_or_values() {
+        let mut glyphs = Glyphs::default();
+        assert!(glyphs.is_empty());
+        glyphs.set("Coin", "o").unwrap();
+        glyphs.set("2.00", "(2)").unwrap();
+        assert_eq!(glyphs.glyph(&USD.denominations[0]), BILL);
+        assert_eq!(glyphs.glyph(&USD.denominations[4]), "o");
+        let two_euro = &EUR.denominations[EUR.index_of(200).unwrap()];
+        assert_eq!(glyphs.glyph(two_euro), "(2)");
+
+        assert!(glyphs.set("quarter", "q").is_err());
+        assert!(glyphs.set("0", "q").is_err());
+        assert!(glyphs.set("0.25", "").is_err());
+    }
+
+    #[test]
+    fn round_trips_as_a_map() {
+        let glyphs: Glyphs =
+            serde_json::from_str(r#"{"bill": "[$]", "0.25": "(Q)", ".10": "(D)"}"#).unwrap();
+        assert_eq!(glyphs.glyph(&USD.denominations[2]), "(D)");
+        let json = serde_json::to_string(&glyphs).unwrap();
+        assert_eq!(json, r#"{"0.10":"(D)","0.25":"(Q)","bill":"[$]"}"#);
+        assert_eq!(serde_json::from_str::<Glyphs>(&json).unwrap(), glyphs);
+        assert!(serde_json::from_str::<Glyphs>(r#"{"penny": "p"}"#).is_err());
+    }
+}
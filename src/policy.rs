@@ -0,0 +1,237 @@
+//! Everything that decides what change a transaction gets, in one place:
+//! the currency, the strategy and its divisor, how over-precise amounts are
+//! rounded, and which pieces the register won't hand out.
+//!
+//! A [`ChangePolicy`] is checked when it's built or deserialized, so an
+//! embedder that has one can pass it to [`make_change_with_policy`] or
+//! [`CashRegisterBuilder::policy`] without checking it again:
+//!
+//! ```
+//! use cash_register::policy::{ChangePolicy, PolicySpec};
+//! use cash_register::CashRegister;
+//!
+//! let policy: ChangePolicy =
+//!     serde_json::from_str(r#"{"strategy": "greedy", "withhold": ["1.00"]}"#).unwrap();
+//! let mut register = CashRegister::builder().policy(&policy).build();
+//! assert_eq!(register.process("3.00,5.00\n")[0].as_deref().unwrap(), "8 quarters");
+//! assert!(ChangePolicy::new(PolicySpec {
+//!     withhold: vec!["0.03".into()],
+//!     ..PolicySpec::default()
+//! })
+//! .is_err());
+//! ```
+//!
+//! [`make_change_with_policy`]: crate::rules::make_change_with_policy
+//! [`CashRegisterBuilder::policy`]: crate::register::CashRegisterBuilder::policy
+
+use alloc::borrow::Cow;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::StrategyChoice;
+use crate::currency::{find_currency, Currency};
+use crate::error::CashRegisterError;
+use crate::parse::{parse_value_cents, ParseOptions};
+use crate::rounding::RoundingMode;
+
+/// A change policy as written, before it's checked.
+///
+/// Serializes with kebab-case keys; missing keys take the CLI's defaults
+/// (USD, divisor 3, auto strategy, no rounding, every piece given).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PolicySpec {
+    /// Currency code, e.g. `USD`.
+    pub currency: String,
+    /// Randomize change when the amount owed is a multiple of this (0 never
+    /// does). Only used by the `auto` strategy.
+    pub divisor: u32,
+    pub strategy: StrategyChoice,
+    /// How to round amounts that aren't whole cents. Unset, such amounts
+    /// are rejected.
+    pub rounding: Option<RoundingMode>,
+    /// Pieces never given as change, by value: `["1.00"]` for a drawer
+    /// whose dollar bills are kept for the bank.
+    pub withhold: Vec<String>,
+}
+
+impl Default for PolicySpec {
+    fn default() -> Self {
+        Self {
+            currency: "USD".to_string(),
+            divisor: 3,
+            strategy: StrategyChoice::Auto,
+            rounding: None,
+            withhold: Vec::new(),
+        }
+    }
+}
+
+/// A checked [`PolicySpec`], with the currency it gives change in.
+///
+/// That currency is the configured one without the withheld pieces, so
+/// breakdowns made under the policy index into
+/// [`currency()`](ChangePolicy::currency), not the built-in table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "PolicySpec", into = "PolicySpec")]
+pub struct ChangePolicy {
+    spec: PolicySpec,
+    currency: Currency,
+}
+
+impl ChangePolicy {
+    /// Check `spec`: the currency must exist, and each withheld piece must
+    /// be one of its denominations, with at least one left to give.
+    pub fn new(spec: PolicySpec) -> Result<Self, CashRegisterError> {
+        let configured = find_currency(&spec.currency)?;
+        let mut withheld = Vec::with_capacity(spec.withhold.len());
+        for value in &spec.withhold {
+            let cents = parse_value_cents(value)
+                .ok()
+                .filter(|&cents| configured.index_of(cents).is_some())
+                .ok_or_else(|| {
+                    CashRegisterError::InvalidPolicy(format!(
+                        "\"{value}\" is not a {} denomination",
+                        configured.name
+                    ))
+                })?;
+            withheld.push(cents);
+        }
+        let given: Vec<_> = configured
+            .denominations
+            .iter()
+            .filter(|d| !withheld.contains(&d.cents))
+            .cloned()
+            .collect();
+        if given.is_empty() {
+            return Err(CashRegisterError::InvalidPolicy(format!(
+                "every {} denomination is withheld",
+                configured.name
+            )));
+        }
+        let currency = Currency {
+            denominations: Cow::Owned(given),
+            ..configured.clone()
+        };
+        Ok(Self { spec, currency })
+    }
+
+    /// The policy as written.
+    pub fn spec(&self) -> &PolicySpec {
+        &self.spec
+    }
+
+    /// The currency change is made in, without the withheld pieces.
+    pub fn currency(&self) -> &Currency {
+        &self.currency
+    }
+
+    pub fn strategy(&self) -> StrategyChoice {
+        self.spec.strategy
+    }
+
+    /// The divisor the rules should use to get the policy's strategy.
+    pub fn rules_divisor(&self) -> u32 {
+        self.spec.strategy.rules_divisor(self.spec.divisor)
+    }
+
+    pub fn rounding(&self) -> Option<RoundingMode> {
+        self.spec.rounding
+    }
+
+    /// Parse options that round over-precise amounts as the policy says,
+    /// and otherwise match the defaults.
+    pub fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            rounding: self.spec.rounding,
+            ..ParseOptions::default()
+        }
+    }
+}
+
+impl Default for ChangePolicy {
+    fn default() -> Self {
+        Self::new(PolicySpec::default()).expect("the default policy is valid")
+    }
+}
+
+impl TryFrom<PolicySpec> for ChangePolicy {
+    type Error = CashRegisterError;
+
+    fn try_from(spec: PolicySpec) -> Result<Self, Self::Error> {
+        Self::new(spec)
+    }
+}
+
+impl From<ChangePolicy> for PolicySpec {
+    fn from(policy: ChangePolicy) -> Self {
+        policy.spec
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(currency: &str, withhold: &[&str]) -> PolicySpec {
+        PolicySpec {
+            currency: currency.to_string(),
+            withhold: withhold.iter().map(ToString::to_string).collect(),
+            ..PolicySpec::default()
+        }
+    }
+
+    #[test]
+    fn withheld_pieces_leave_the_currency() {
+        let policy = ChangePolicy::new(spec("USD", &["1.00", "0.05"])).unwrap();
+        let cents: Vec<u32> = policy
+            .currency()
+            .denominations
+            .iter()
+            .map(|d| d.cents)
+            .collect();
+        assert_eq!(cents, [25, 10, 1]);
+        assert_eq!(policy.currency().name, "USD");
+        assert_eq!(ChangePolicy::default().currency().denominations.len(), 5);
+    }
+
+    #[test]
+    fn rejects_what_it_cant_use() {
+        let unknown = ChangePolicy::new(spec("GBP", &[])).unwrap_err();
+        assert_eq!(unknown.code(), "E010");
+        let not_a_piece = ChangePolicy::new(spec("USD", &["0.03"])).unwrap_err();
+        assert_eq!(not_a_piece.code(), "E018");
+        assert_eq!(
+            not_a_piece.to_string(),
+            "invalid change policy: \"0.03\" is not a USD denomination"
+        );
+        let everything = ["1.00", "0.25", "0.10", "0.05", "0.01"];
+        assert!(ChangePolicy::new(spec("USD", &everything)).is_err());
+    }
+
+    #[test]
+    fn round_trips_through_serde() {
+        let policy: ChangePolicy = serde_json::from_str(
+            r#"{"currency": "EUR", "strategy": "random", "rounding": "half-even", "withhold": ["2.00"]}"#,
+        )
+        .unwrap();
+        assert_eq!(policy.rules_divisor(), 1);
+        assert_eq!(
+            policy.parse_options().rounding,
+            Some(RoundingMode::HalfEven)
+        );
+        assert_eq!(policy.currency().index_of(200), None);
+
+        let json = serde_json::to_string(&policy).unwrap();
+        let again: ChangePolicy = serde_json::from_str(&json).unwrap();
+        assert_eq!(again.spec(), policy.spec());
+        let err = serde_json::from_str::<ChangePolicy>(r#"{"withhold": ["0.02"]}"#).unwrap_err();
+        assert!(err.to_string().contains("not a USD denomination"));
+        assert!(serde_json::from_str::<ChangePolicy>(r#"{"colour": "red"}"#).is_err());
+    }
+}
@@ -0,0 +1,276 @@
+use alloc::{string::String, vec::Vec};
+
+use rand::rngs::StdRng;
+
+use crate::currency::{Currency, USD};
+use crate::error::CashRegisterError;
+use crate::format::{format_breakdown, format_refund, format_verbose};
+use crate::money::Cents;
+use crate::parse::{parse_entries_with, Entry, ParseOptions, Transaction};
+use crate::policy::ChangePolicy;
+use crate::rules::make_change_with;
+use crate::strategy::greedy::GreedyTable;
+use crate::strategy::{rng_from_seed, Breakdown, ChangeStrategy};
+
+/// Makes change the way the CLI does, without wiring parse, rules, and
+/// format together by hand:
+///
+/// ```
+/// use cash_register::currency::EUR;
+/// use cash_register::CashRegister;
+///
+/// let mut register = CashRegister::builder().currency(&EUR).divisor(3).seed(42).build();
+/// let lines = register.process("0.37,1.00\n");
+/// assert_eq!(
+///     lines[0].as_deref().unwrap(),
+///     "1 50 cent coin,1 10 cent coin,1 2 cent coin,1 1 cent coin"
+/// );
+/// ```
+pub struct CashRegister<'a> {
+    currency: &'a Currency,
+    divisor: u32,
+    verbose: bool,
+    options: ParseOptions,
+    rng: StdRng,
+    greedy: GreedyTable<'a>,
+}
+
+/// Configures a [`CashRegister`]. Defaults match the CLI: USD, divisor 3,
+/// terse output, and an unseeded RNG (seed 0 without the `std` feature).
+#[derive(Debug, Clone)]
+pub struct CashRegisterBuilder<'a> {
+    currency: &'a Currency,
+    divisor: u32,
+    verbose: bool,
+    seed: Option<u64>,
+    options: ParseOptions,
+}
+
+impl Default for CashRegisterBuilder<'_> {
+    fn default() -> Self {
+        Self {
+            currency: &USD,
+            divisor: 3,
+            verbose: false,
+            seed: None,
+            options: ParseOptions::default(),
+        }
+    }
+}
+
+impl<'a> CashRegisterBuilder<'a> {
+    pub fn currency(mut self, currency: &'a Currency) -> Self {
+        self.currency = currency;
+        self
+    }
+
+    /// Randomize change when the amount owed is a multiple of `divisor`
+    /// (0 disables randomization).
+    pub fn divisor(mut self, divisor: u32) -> Self {
+        self.divisor = divisor;
+        self
+    }
+
+    /// Make change as `policy` says: in its currency, without the withheld
+    /// pieces, with its strategy, and rounding over-precise amounts in
+    /// [`CashRegister::process`] its way.
+    pub fn policy(mut self, policy: &'a ChangePolicy) -> Self {
+        self.currency = policy.currency();
+        self.divisor = policy.rules_divisor();
+        self.options = policy.parse_options();
+        self
+    }
+
+    /// Seed the RNG for reproducible random breakdowns.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Format lines like `--verbose`: with the transaction and a random label.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    pub fn build(self) -> CashRegister<'a> {
+        CashRegister {
+            currency: self.currency,
+            divisor: self.divisor,
+            verbose: self.verbose,
+            options: self.options,
+            rng: rng_from_seed(self.seed),
+            greedy: GreedyTable::new(self.currency),
+        }
+    }
+}
+
+impl<'a> CashRegister<'a> {
+    pub fn builder() -> CashRegisterBuilder<'a> {
+        CashRegisterBuilder::default()
+    }
+
+    pub fn currency(&self) -> &'a Currency {
+        self.currency
+    }
+
+    /// Make change for one transaction. Fails with `InfeasibleAmount` only
+    /// for a currency that can't make the change exactly.
+    pub fn process_transaction(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<Breakdown, CashRegisterError> {
+        make_change_with(
+            transaction,
+            self.currency,
+            self.divisor,
+            &mut self.rng,
+            &mut self.greedy,
+        )
+        .map_err(|e| e.in_currency(self.currency))
+    }
+
+    /// Make the pieces to pay out for a refund of `amount`. The random rule
+    /// is about what a customer owed, so refunds always get the fewest
+    /// pieces.
+    pub fn process_refund(&mut self, amount: Cents) -> Result<Breakdown, CashRegisterError> {
+        self.greedy
+            .make_change(amount, self.currency)
+            .map_err(|e| e.in_currency(self.currency))
+    }
+
+    /// Process input text: one formatted output line (or error) per
+    /// transaction or refund line, in order. Till directives, `VOID` lines,
+    /// and `tendered=` fields are accepted but, with no till or totals to
+    /// apply them to, have no effect.
+    pub fn process(&mut self, input: &str) -> Vec<Result<String, CashRegisterError>> {
+        parse_entries_with(input, self.options)
+            .filter_map(|(_, entry, _)| match entry {
+                Ok(Entry::Transaction(transaction, _)) => Some(self.format(&transaction)),
+                Ok(Entry::Refund(amount, _)) => Some(self.format_refund(amount)),
+                Ok(Entry::Till(..) | Entry::Void(_)) => None,
+                Err(e) => Some(Err(e)),
+            })
+            .collect()
+    }
+
+    /// How [`process`](Self::process) reads its input.
+    pub(crate) fn options(&self) -> ParseOptions {
+        self.options
+    }
+
+    pub(crate) fn format(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<String, CashRegisterError> {
+        let breakdown = self.process_transaction(transaction)?;
+        Ok(self.render(transaction, &breakdown))
+    }
+
+    pub(crate) fn format_refund(&mut self, amount: Cents) -> Result<String, CashRegisterError> {
+        let breakdown = self.process_refund(amount)?;
+        Ok(self.render_refund(amount, &breakdown))
+    }
+
+    /// The output line for `transaction`'s change, `breakdown`.
+    pub(crate) fn render(&self, transaction: &Transaction, breakdown: &Breakdown) -> String {
+        if self.verbose {
+            let is_random =
+                self.divisor > 0 && transaction.owed_cents().is_multiple_of(self.divisor);
+            format_verbose(transaction, breakdown, self.currency, is_random)
+        } else {
+            format_breakdown(breakdown, self.currency)
+        }
+    }
+
+    pub(crate) fn render_refund(&self, amount: Cents, breakdown: &Breakdown) -> String {
+        format_refund(amount, breakdown, self.currency)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::EUR;
+    use crate::parse::parse_line;
+    use alloc::vec;
+
+    #[test]
+    fn defaults_match_the_cli() {
+        let mut register = CashRegister::builder().build();
+        let lines = register.process("2.12,3.00\n\n1.97,2.00\n");
+        let lines: Vec<String> = lines.into_iter().map(Result::unwrap).collect();
+        assert_eq!(lines, vec!["3 quarters,1 dime,3 pennies", "3 pennies"]);
+    }
+
+    #[test]
+    fn errors_are_reported_per_line() {
+        let mut register = CashRegister::builder().build();
+        let lines = register.process("abc\n1.97,2.00\ntill add 0.25,4\n");
+        assert_eq!(lines.len(), 2);
+        assert!(matches!(
+            lines[0],
+            Err(CashRegisterError::MalformedLine { line: 1, .. })
+        ));
+        assert_eq!(lines[1].as_deref().unwrap(), "3 pennies");
+    }
+
+    #[test]
+    fn refunds_are_paid_out_greedily() {
+        let mut register = CashRegister::builder().divisor(1).build();
+        let lines = register.process("REFUND,2.12\n-0.30,0\n");
+        let lines: Vec<String> = lines.into_iter().map(Result::unwrap).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "Refund $2.12, pay out 2 dollars,1 dime,2 pennies",
+                "Refund $0.30, pay out 1 quarter,1 nickel",
+            ]
+        );
+    }
+
+    #[test]
+    fn seeded_registers_agree() {
+        let input = "3.33,5.00\n3.00,10.00\n";
+        let mut a = CashRegister::builder().seed(42).build();
+        let mut b = CashRegister::builder().seed(42).build();
+        let a: Vec<_> = a.process(input).into_iter().map(Result::unwrap).collect();
+        let b: Vec<_> = b.process(input).into_iter().map(Result::unwrap).collect();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn configured_currency_divisor_and_verbosity() {
+        let mut register = CashRegister::builder()
+            .currency(&EUR)
+            .divisor(0)
+            .verbose(true)
+            .build();
+        let tx = parse_line("1.50,2.00", 1).unwrap();
+        let breakdown = register.process_transaction(&tx).unwrap();
+        assert_eq!(breakdown.len(), 1);
+        assert_eq!(breakdown[0], (EUR.index_of(50).unwrap(), 1));
+        assert_eq!(
+            register.process("1.50,2.00\n")[0].as_deref().unwrap(),
+            "Owed €1.50, Paid €2.00 -> 1 50 cent coin"
+        );
+    }
+
+    #[test]
+    fn built_from_a_policy() {
+        let policy: ChangePolicy = serde_json::from_str(
+            r#"{"currency": "EUR", "strategy": "greedy", "rounding": "half-up", "withhold": ["0.50"]}"#,
+        )
+        .unwrap();
+        let mut register = CashRegister::builder().policy(&policy).build();
+        assert_eq!(register.currency().index_of(50), None);
+        let lines = register.process(
+            "1.495,2.00
+",
+        );
+        assert_eq!(
+            lines[0].as_deref().unwrap(),
+            "2 20 cent coins,1 10 cent coin"
+        );
+    }
+}
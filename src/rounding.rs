@@ -0,0 +1,131 @@
+//! How an amount that falls between two whole cents is brought to one.
+//!
+//! Every place the library rounds money takes a [`RoundingMode`]: amounts
+//! written with more than two decimal places (`ParseOptions::rounding`),
+//! and results of division, like a rate or a percentage, through
+//! [`RoundingMode::round_div`].
+
+use alloc::{format, string::String};
+use core::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+/// Which way an amount between two cents goes. Amounts are never negative,
+/// so toward zero is always down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoundingMode {
+    /// Half a cent or more rounds up: 2.125 is 2.13.
+    #[default]
+    HalfUp,
+    /// More than half a cent rounds up: 2.125 is 2.12, 2.1251 is 2.13.
+    HalfDown,
+    /// Exactly half a cent rounds to the even cent: 2.125 is 2.12 and
+    /// 2.135 is 2.14.
+    HalfEven,
+    /// The fraction of a cent is dropped: 2.129 is 2.12.
+    TowardZero,
+}
+
+impl RoundingMode {
+    /// Every mode, in the order `--help` lists them.
+    pub const ALL: [Self; 4] = [
+        Self::HalfUp,
+        Self::HalfDown,
+        Self::HalfEven,
+        Self::TowardZero,
+    ];
+
+    /// The name it's parsed from and serialized as.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::HalfUp => "half-up",
+            Self::HalfDown => "half-down",
+            Self::HalfEven => "half-even",
+            Self::TowardZero => "toward-zero",
+        }
+    }
+
+    /// `numerator / denominator`, rounded to a whole number.
+    ///
+    /// # Panics
+    ///
+    /// If `denominator` is zero.
+    pub fn round_div(self, numerator: u128, denominator: u128) -> u128 {
+        let quotient = numerator / denominator;
+        let remainder = numerator % denominator;
+        let half = (remainder * 2).cmp(&denominator);
+        quotient + u128::from(self.rounds_up(half, remainder > 0, quotient % 2 == 1))
+    }
+
+    /// Whether `cents` followed by the further decimal digits `fraction`
+    /// rounds up to the next cent.
+    pub(crate) fn rounds_up_digits(self, cents: u64, fraction: &[u8]) -> bool {
+        let Some((&first, rest)) = fraction.split_first() else {
+            return false;
+        };
+        let half = match first.cmp(&b'5') {
+            Ordering::Equal if rest.iter().any(|&d| d != b'0') => Ordering::Greater,
+            other => other,
+        };
+        let inexact = fraction.iter().any(|&d| d != b'0');
+        self.rounds_up(half, inexact, cents % 2 == 1)
+    }
+
+    /// Whether to round up, given how the dropped fraction compares with
+    /// one half, whether there is any fraction at all, and whether the
+    /// amount rounded down is odd.
+    fn rounds_up(self, half: Ordering, inexact: bool, odd: bool) -> bool {
+        inexact
+            && match self {
+                Self::HalfUp => half != Ordering::Less,
+                Self::HalfDown => half == Ordering::Greater,
+                Self::HalfEven => half == Ordering::Greater || (half == Ordering::Equal && odd),
+                Self::TowardZero => false,
+            }
+    }
+}
+
+impl core::str::FromStr for RoundingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "half-up" => Ok(Self::HalfUp),
+            "half-down" => Ok(Self::HalfDown),
+            "half-even" => Ok(Self::HalfEven),
+            // `truncate` is what `--round-input` first called it.
+            "toward-zero" | "truncate" => Ok(Self::TowardZero),
+            other => Err(format!(
+                "unknown rounding mode \"{other}\" (expected half-up, half-down, half-even, or toward-zero)"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_div_breaks_ties_by_mode() {
+        let round = |mode: RoundingMode| {
+            [(25, 10), (35, 10), (26, 10), (24, 10), (20, 10)].map(|(n, d)| mode.round_div(n, d))
+        };
+        assert_eq!(round(RoundingMode::HalfUp), [3, 4, 3, 2, 2]);
+        assert_eq!(round(RoundingMode::HalfDown), [2, 3, 3, 2, 2]);
+        assert_eq!(round(RoundingMode::HalfEven), [2, 4, 3, 2, 2]);
+        assert_eq!(round(RoundingMode::TowardZero), [2, 3, 2, 2, 2]);
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for mode in RoundingMode::ALL {
+            assert_eq!(mode.as_str().parse(), Ok(mode));
+            let json = serde_json::to_string(&mode).unwrap();
+            assert_eq!(json, format!("\"{}\"", mode.as_str()));
+        }
+        assert_eq!("truncate".parse(), Ok(RoundingMode::TowardZero));
+        assert!("bankers".parse::<RoundingMode>().is_err());
+    }
+}
@@ -0,0 +1,364 @@
+//! JSON-RPC 2.0, one message per line, so a host can keep the register
+//! running as a subprocess (`cash-register --rpc`) instead of spawning it
+//! per transaction.
+//!
+//! Methods:
+//! - `make_change` `{"owed": "2.12", "paid": "3.00"}` returns
+//!   `{"change": [...], "random": false, "text": "3 quarters,1 dime,3 pennies"}`,
+//!   with `change` as in `format::format_json`.
+//! - `parse_line` `{"line": "2.12,3.00"}` returns
+//!   `{"owed": "2.12", "paid": "3.00", "change": "0.88"}`.
+//! - `set_config` takes any [`Config`] keys, applies them on top of the
+//!   session's config, and returns the whole effective config.
+//!
+//! Library errors come back with code `-32000`, the error's message, and its
+//! stable code as `data.code` (`"E002"`).
+//!
+//! The session counts what it does in [`Metrics`]: every change made, and
+//! every error by its `data.code` or, for protocol errors, its name
+//! (`invalid_params`).
+
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+
+use rand::rngs::StdRng;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::config::Config;
+use crate::currency::Currency;
+use crate::error::CashRegisterError;
+use crate::format::{breakdown_json, format_breakdown};
+use crate::metrics::Metrics;
+use crate::parse::{parse_dollars_to_cents, parse_line, Transaction};
+use crate::rules::make_change_for;
+
+/// The request wasn't valid JSON.
+pub const PARSE_ERROR: i64 = -32700;
+/// The JSON wasn't a JSON-RPC 2.0 request.
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// The library refused the request; `data.code` says why.
+pub const APP_ERROR: i64 = -32000;
+
+/// A JSON-RPC session: the config and RNG that persist between requests.
+pub struct RpcSession {
+    config: Config,
+    currency: &'static Currency,
+    rng: StdRng,
+    metrics: Metrics,
+}
+
+/// An error response's `code`, `message`, and optional `data`.
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    fn app(message: String, code: &str) -> Self {
+        Self {
+            code: APP_ERROR,
+            message,
+            data: Some(json!({ "code": code })),
+        }
+    }
+
+    /// The library's error code, or the protocol error's name, for metrics.
+    fn kind(&self) -> String {
+        let app_code = self.data.as_ref().and_then(|data| data["code"].as_str());
+        match (self.code, app_code) {
+            (_, Some(code)) => code.to_string(),
+            (PARSE_ERROR, None) => "parse_error".to_string(),
+            (INVALID_REQUEST, None) => "invalid_request".to_string(),
+            (METHOD_NOT_FOUND, None) => "method_not_found".to_string(),
+            (INVALID_PARAMS, None) => "invalid_params".to_string(),
+            (code, None) => code.to_string(),
+        }
+    }
+}
+
+impl From<CashRegisterError> for RpcError {
+    fn from(e: CashRegisterError) -> Self {
+        Self::app(e.to_string(), e.code())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MakeChangeParams {
+    owed: String,
+    paid: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ParseLineParams {
+    line: String,
+}
+
+impl RpcSession {
+    /// A session starting from `config`; fails if its currency is unknown.
+    pub fn new(config: Config) -> Result<Self, CashRegisterError> {
+        Ok(Self {
+            currency: config.currency()?,
+            rng: config.rng(),
+            config,
+            metrics: Metrics::new(),
+        })
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// What the session has done so far.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// For a host counting what the session can't, like request latency.
+    pub fn metrics_mut(&mut self) -> &mut Metrics {
+        &mut self.metrics
+    }
+
+    /// Handle one request line and return the response line, or `None` for
+    /// a notification (a request without an `id`).
+    pub fn handle(&mut self, request: &str) -> Option<String> {
+        let request: Value = match serde_json::from_str(request) {
+            Ok(request) => request,
+            Err(e) => {
+                self.metrics.record_error("parse_error");
+                return Some(respond(
+                    Value::Null,
+                    Err(RpcError::new(PARSE_ERROR, e.to_string())),
+                ));
+            }
+        };
+        let id = request.get("id").cloned();
+        let method = request.get("method").and_then(Value::as_str);
+        let result = match method {
+            Some(method) if request.get("jsonrpc") == Some(&json!("2.0")) => {
+                let params = request.get("params").cloned().unwrap_or(Value::Null);
+                self.call(method, params)
+            }
+            _ => Err(RpcError::new(INVALID_REQUEST, "not a JSON-RPC 2.0 request")),
+        };
+        if let Err(e) = &result {
+            self.metrics.record_error(&e.kind());
+        }
+        // Notifications get no response, even on error, unless the request
+        // was too broken to tell.
+        match (id, &result) {
+            (Some(id), _) => Some(respond(id, result)),
+            (None, Err(e)) if e.code == INVALID_REQUEST => Some(respond(Value::Null, result)),
+            (None, _) => None,
+        }
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "make_change" => {
+                let params: MakeChangeParams = params_from(params)?;
+                let amount = |s: &str| {
+                    parse_dollars_to_cents(s).map_err(|e| {
+                        RpcError::new(INVALID_PARAMS, format!("invalid amount \"{s}\": {e}"))
+                    })
+                };
+                let tx = Transaction::new(amount(&params.owed)?, amount(&params.paid)?)
+                    .map_err(|e| RpcError::app(e.to_string(), e.code()))?;
+                let divisor = self.config.rules_divisor();
+                let breakdown = make_change_for(&tx, self.currency, divisor, &mut self.rng)
+                    .map_err(|e| e.in_currency(self.currency))?;
+                let random = divisor > 0 && tx.owed_cents().is_multiple_of(divisor);
+                let pieces = breakdown.iter().map(|&(_, count)| count).sum();
+                self.metrics
+                    .record_change(tx.change_cents(), pieces, random);
+                Ok(json!({
+                    "change": breakdown_json(&breakdown, self.currency),
+                    "random": random,
+                    "text": format_breakdown(&breakdown, self.currency),
+                }))
+            }
+            "parse_line" => {
+                let params: ParseLineParams = params_from(params)?;
+                let tx = parse_line(&params.line, 1)?;
+                Ok(json!({
+                    "owed": tx.owed_cents().to_string(),
+                    "paid": tx.paid_cents().to_string(),
+                    "change": tx.change_cents().to_string(),
+                }))
+            }
+            "set_config" => {
+                let Value::Object(changes) = params else {
+                    return Err(RpcError::new(
+                        INVALID_PARAMS,
+                        "expected an object of config keys",
+                    ));
+                };
+                let mut merged = serde_json::to_value(&self.config).expect("config serializes");
+                if let Value::Object(current) = &mut merged {
+                    current.extend(changes);
+                }
+                let config: Config = params_from(merged)?;
+                let reseed = config.seed != self.config.seed;
+                self.currency = config.currency()?;
+                if reseed {
+                    self.rng = config.rng();
+                }
+                self.config = config;
+                Ok(serde_json::to_value(&self.config).expect("config serializes"))
+            }
+            other => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method \"{other}\""),
+            )),
+        }
+    }
+}
+
+fn params_from<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn respond(id: Value, result: Result<Value, RpcError>) -> String {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => {
+            let mut error = json!({ "code": e.code, "message": e.message });
+            if let Some(data) = e.data {
+                error["data"] = data;
+            }
+            json!({ "jsonrpc": "2.0", "id": id, "error": error })
+        }
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::StrategyChoice;
+
+    fn session() -> RpcSession {
+        RpcSession::new(Config {
+            seed: Some(1),
+            ..Config::default()
+        })
+        .unwrap()
+    }
+
+    fn call(session: &mut RpcSession, request: &str) -> Value {
+        serde_json::from_str(&session.handle(request).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn make_change_returns_pieces_and_text() {
+        let response = call(
+            &mut session(),
+            r#"{"jsonrpc":"2.0","id":1,"method":"make_change","params":{"owed":"2.12","paid":"3.00"}}"#,
+        );
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"]["text"], "3 quarters,1 dime,3 pennies");
+        assert_eq!(response["result"]["random"], false);
+        assert_eq!(response["result"]["change"][0]["cents"], 25);
+    }
+
+    #[test]
+    fn errors_carry_codes() {
+        let mut session = session();
+        let underpaid = call(
+            &mut session,
+            r#"{"jsonrpc":"2.0","id":"a","method":"make_change","params":{"owed":"3.00","paid":"2.00"}}"#,
+        );
+        assert_eq!(underpaid["error"]["code"], APP_ERROR);
+        assert_eq!(underpaid["error"]["data"]["code"], "E002");
+
+        let malformed = call(
+            &mut session,
+            r#"{"jsonrpc":"2.0","id":2,"method":"parse_line","params":{"line":"abc"}}"#,
+        );
+        assert_eq!(malformed["error"]["data"]["code"], "E003");
+
+        let bad_params = call(
+            &mut session,
+            r#"{"jsonrpc":"2.0","id":3,"method":"make_change","params":{"owed":"x","paid":"1"}}"#,
+        );
+        assert_eq!(bad_params["error"]["code"], INVALID_PARAMS);
+
+        let unknown = call(
+            &mut session,
+            r#"{"jsonrpc":"2.0","id":4,"method":"refund"}"#,
+        );
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+
+        let garbage = call(&mut session, "{not json");
+        assert_eq!(garbage["error"]["code"], PARSE_ERROR);
+        assert_eq!(garbage["id"], Value::Null);
+
+        let metrics = session.metrics();
+        assert_eq!(metrics.transactions(), 0);
+        assert_eq!(metrics.errors("E002"), 1);
+        assert_eq!(metrics.errors("invalid_params"), 1);
+        assert_eq!(metrics.errors("parse_error"), 1);
+    }
+
+    #[test]
+    fn parse_line_returns_amounts() {
+        let response = call(
+            &mut session(),
+            r#"{"jsonrpc":"2.0","id":1,"method":"parse_line","params":{"line":"2.12,3.00"}}"#,
+        );
+        assert_eq!(
+            response["result"],
+            json!({ "owed": "2.12", "paid": "3.00", "change": "0.88" })
+        );
+    }
+
+    #[test]
+    fn set_config_applies_to_later_calls() {
+        let mut session = session();
+        let response = call(
+            &mut session,
+            r#"{"jsonrpc":"2.0","id":1,"method":"set_config","params":{"strategy":"greedy","currency":"EUR"}}"#,
+        );
+        assert_eq!(response["result"]["currency"], "EUR");
+        assert_eq!(response["result"]["seed"], 1);
+        assert_eq!(session.config().strategy, StrategyChoice::Greedy);
+
+        let change = call(
+            &mut session,
+            r#"{"jsonrpc":"2.0","id":2,"method":"make_change","params":{"owed":"3.00","paid":"5.00"}}"#,
+        );
+        assert_eq!(change["result"]["text"], "1 2 euro coin");
+
+        let rejected = call(
+            &mut session,
+            r#"{"jsonrpc":"2.0","id":3,"method":"set_config","params":{"currency":"GBP"}}"#,
+        );
+        assert_eq!(rejected["error"]["data"]["code"], "E010");
+        assert_eq!(session.config().currency, "EUR");
+    }
+
+    #[test]
+    fn notifications_get_no_response() {
+        let mut session = session();
+        assert!(session
+            .handle(r#"{"jsonrpc":"2.0","method":"set_config","params":{"divisor":0}}"#)
+            .is_none());
+        assert_eq!(session.config().divisor, 0);
+    }
+}
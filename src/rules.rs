@@ -1,7 +1,10 @@
 use rand::Rng;
+use tracing::debug;
 
 use crate::currency::Currency;
+use crate::error::InfeasibleChange;
 use crate::parse::Transaction;
+use crate::policy::ChangePolicy;
 use crate::strategy::greedy::GreedyStrategy;
 use crate::strategy::random::RandomStrategy;
 use crate::strategy::{Breakdown, ChangeStrategy};
@@ -9,37 +12,70 @@ use crate::strategy::{Breakdown, ChangeStrategy};
 /// Determine change for a transaction, dispatching to the appropriate strategy.
 ///
 /// If `owed_cents` is divisible by `divisor`, uses randomized denominations.
-/// Otherwise, uses the greedy (minimum count) algorithm.
-pub fn make_change_for<R: Rng>(
+/// Otherwise, uses the greedy (minimum count) algorithm. Fails only for a
+/// currency that can't make the change exactly.
+///
+/// `rng` may be a trait object (`&mut dyn RngCore`) as well as a concrete RNG.
+pub fn make_change_for<R: Rng + ?Sized>(
+    transaction: &Transaction,
+    currency: &Currency,
+    divisor: u32,
+    rng: &mut R,
+) -> Result<Breakdown, InfeasibleChange> {
+    make_change_with(transaction, currency, divisor, rng, &mut GreedyStrategy)
+}
+
+/// Like [`make_change_for`], with `greedy` making the non-random change:
+/// typically a [`GreedyTable`](crate::strategy::greedy::GreedyTable) built
+/// once for a whole batch.
+pub fn make_change_with<R: Rng + ?Sized>(
     transaction: &Transaction,
     currency: &Currency,
     divisor: u32,
     rng: &mut R,
-) -> Breakdown {
-    if transaction.change_cents == 0 {
-        return Vec::new();
+    greedy: &mut impl ChangeStrategy,
+) -> Result<Breakdown, InfeasibleChange> {
+    if transaction.change_cents().is_zero() {
+        return Ok(Breakdown::new());
     }
 
-    if divisor > 0 && transaction.owed_cents.is_multiple_of(divisor) {
-        RandomStrategy::new(rng).make_change(transaction.change_cents, currency)
+    let random = divisor > 0 && transaction.owed_cents().is_multiple_of(divisor);
+    debug!(
+        owed = %transaction.owed_cents(),
+        change = %transaction.change_cents(),
+        divisor,
+        strategy = if random { "random" } else { "greedy" },
+        "selected strategy"
+    );
+    if random {
+        RandomStrategy::new(rng).make_change(transaction.change_cents(), currency)
     } else {
-        GreedyStrategy.make_change(transaction.change_cents, currency)
+        greedy.make_change(transaction.change_cents(), currency)
     }
 }
 
+/// Like [`make_change_for`], with the currency and strategy taken from
+/// `policy`. The breakdown indexes into `policy.currency()`, which leaves
+/// out the withheld pieces.
+pub fn make_change_with_policy<R: Rng + ?Sized>(
+    transaction: &Transaction,
+    policy: &ChangePolicy,
+    rng: &mut R,
+) -> Result<Breakdown, InfeasibleChange> {
+    make_change_for(transaction, policy.currency(), policy.rules_divisor(), rng)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::currency::USD;
+    use crate::money::Cents;
+    use alloc::{vec, vec::Vec};
     use rand::rngs::StdRng;
     use rand::SeedableRng;
 
-    fn tx(owed: u32, paid: u32) -> Transaction {
-        Transaction {
-            owed_cents: owed,
-            paid_cents: paid,
-            change_cents: paid - owed,
-        }
+    fn tx(owed: u64, paid: u64) -> Transaction {
+        Transaction::new(Cents(owed), Cents(paid)).unwrap()
     }
 
     #[test]
@@ -47,8 +83,8 @@ mod tests {
         let mut rng = StdRng::seed_from_u64(42);
 
         // 333 is divisible by 3
-        let random_result = make_change_for(&tx(333, 500), &USD, 3, &mut rng);
-        let total: u32 = random_result.iter().map(|(d, c)| d.cents * c).sum();
+        let random_result = make_change_for(&tx(333, 500), &USD, 3, &mut rng).unwrap();
+        let total: u32 = USD.pieces(&random_result).map(|(d, c)| d.cents * c).sum();
         assert_eq!(total, 167);
     }
 
@@ -57,8 +93,11 @@ mod tests {
         let mut rng = StdRng::seed_from_u64(42);
 
         // 212 is not divisible by 3
-        let result = make_change_for(&tx(212, 300), &USD, 3, &mut rng);
-        let named: Vec<(&str, u32)> = result.iter().map(|(d, c)| (d.singular, *c)).collect();
+        let result = make_change_for(&tx(212, 300), &USD, 3, &mut rng).unwrap();
+        let named: Vec<(&str, u32)> = USD
+            .pieces(&result)
+            .map(|(d, c)| (d.singular.as_ref(), c))
+            .collect();
         assert_eq!(named, vec![("quarter", 3), ("dime", 1), ("penny", 3)]);
     }
 
@@ -67,8 +106,11 @@ mod tests {
         let mut rng = StdRng::seed_from_u64(42);
 
         // Even though 300 is divisible by 3, divisor is 0 so greedy is used
-        let result = make_change_for(&tx(300, 500), &USD, 0, &mut rng);
-        let named: Vec<(&str, u32)> = result.iter().map(|(d, c)| (d.singular, *c)).collect();
+        let result = make_change_for(&tx(300, 500), &USD, 0, &mut rng).unwrap();
+        let named: Vec<(&str, u32)> = USD
+            .pieces(&result)
+            .map(|(d, c)| (d.singular.as_ref(), c))
+            .collect();
         assert_eq!(named, vec![("dollar", 2)]);
     }
 
@@ -77,15 +119,38 @@ mod tests {
         let mut rng = StdRng::seed_from_u64(42);
 
         // 500 is divisible by 5 -> random
-        let result = make_change_for(&tx(500, 700), &USD, 5, &mut rng);
-        let total: u32 = result.iter().map(|(d, c)| d.cents * c).sum();
+        let result = make_change_for(&tx(500, 700), &USD, 5, &mut rng).unwrap();
+        let total: u32 = USD.pieces(&result).map(|(d, c)| d.cents * c).sum();
         assert_eq!(total, 200);
     }
 
     #[test]
     fn exact_payment_returns_empty() {
         let mut rng = StdRng::seed_from_u64(42);
-        let result = make_change_for(&tx(300, 300), &USD, 3, &mut rng);
+        let result = make_change_for(&tx(300, 300), &USD, 3, &mut rng).unwrap();
         assert!(result.is_empty());
     }
+
+    #[test]
+    fn accepts_a_dyn_rng() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let rng: &mut dyn rand::RngCore = &mut rng;
+        let result = make_change_for(&tx(333, 500), &USD, 3, rng).unwrap();
+        let total: u32 = USD.pieces(&result).map(|(d, c)| d.cents * c).sum();
+        assert_eq!(total, 167);
+    }
+
+    #[test]
+    fn a_policy_never_gives_withheld_pieces() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let policy: ChangePolicy =
+            serde_json::from_str(r#"{"divisor": 0, "withhold": ["0.25"]}"#).unwrap();
+        let result = make_change_with_policy(&tx(212, 300), &policy, &mut rng).unwrap();
+        let named: Vec<(&str, u32)> = policy
+            .currency()
+            .pieces(&result)
+            .map(|(d, c)| (d.singular.as_ref(), c))
+            .collect();
+        assert_eq!(named, vec![("dime", 8), ("nickel", 1), ("penny", 3)]);
+    }
 }
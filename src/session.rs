@@ -0,0 +1,327 @@
+//! A register that remembers: every sale and refund it has made change for,
+//! running totals, and lookup by transaction ID, for a kiosk's end-of-shift
+//! screen without a database behind it.
+//!
+//! ```
+//! use cash_register::{CashRegister, Session};
+//!
+//! let mut session = Session::new(CashRegister::builder().divisor(0).build());
+//! session.process("2.12,3.00,id=T1\nREFUND,1.00,id=T2\n1.00,2.00,id=T3\nVOID T3\n");
+//! assert_eq!(session.totals().change.to_string(), "0.88");
+//! assert!(session.find("T2").unwrap().is_refund());
+//! assert_eq!(session.find("T3").unwrap().voided_on, Some(4));
+//! ```
+
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::currency::Currency;
+use crate::error::CashRegisterError;
+use crate::format::format_session;
+use crate::money::Cents;
+use crate::parse::{parse_entries_with, Entry, Transaction};
+use crate::register::CashRegister;
+use crate::strategy::Breakdown;
+
+/// What a [`Record`] made change for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Processed {
+    Sale(Transaction),
+    /// An amount paid out to the customer.
+    Refund(Cents),
+}
+
+/// One sale or refund in a session's history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// The POS's transaction ID, if it gave one.
+    pub id: Option<String>,
+    pub processed: Processed,
+    /// The pieces handed over.
+    pub breakdown: Breakdown,
+    /// The input line of the `VOID` that reversed it, if one has. A voided
+    /// record stays in the history but is out of the totals.
+    pub voided_on: Option<usize>,
+}
+
+impl Record {
+    pub fn is_refund(&self) -> bool {
+        matches!(self.processed, Processed::Refund(_))
+    }
+
+    /// The change due on a sale, or the amount of a refund.
+    pub fn paid_out(&self) -> Cents {
+        match &self.processed {
+            Processed::Sale(transaction) => transaction.change_cents(),
+            Processed::Refund(amount) => *amount,
+        }
+    }
+}
+
+/// A session's running totals.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionTotals {
+    pub sales: u64,
+    pub refunds: u64,
+    /// Sales and refunds voided; they aren't in any other total.
+    pub voids: u64,
+    /// Lines or calls that failed; they aren't in any other total.
+    pub errors: u64,
+    /// Over the sales.
+    pub owed: Cents,
+    pub paid: Cents,
+    pub change: Cents,
+    /// Over the refunds.
+    pub refunded: Cents,
+    /// Pieces handed over for sales and refunds, indexed like
+    /// `currency.denominations`.
+    pub pieces: Vec<u64>,
+}
+
+impl SessionTotals {
+    /// Every piece handed over.
+    pub fn piece_count(&self) -> u64 {
+        self.pieces.iter().sum()
+    }
+}
+
+/// A [`CashRegister`] that keeps a history of what it processed.
+pub struct Session<'a> {
+    register: CashRegister<'a>,
+    history: Vec<Record>,
+    /// The latest record for each ID.
+    ids: BTreeMap<String, usize>,
+    totals: SessionTotals,
+}
+
+impl<'a> Session<'a> {
+    pub fn new(register: CashRegister<'a>) -> Self {
+        let totals = SessionTotals {
+            pieces: vec![0; register.currency().denominations.len()],
+            ..SessionTotals::default()
+        };
+        Self {
+            register,
+            history: Vec::new(),
+            ids: BTreeMap::new(),
+            totals,
+        }
+    }
+
+    pub fn currency(&self) -> &'a Currency {
+        self.register.currency()
+    }
+
+    /// Make change for a sale and add it to the history.
+    pub fn sale(
+        &mut self,
+        id: Option<&str>,
+        transaction: &Transaction,
+    ) -> Result<&Record, CashRegisterError> {
+        let breakdown = self.counted(|register| register.process_transaction(transaction))?;
+        let totals = &mut self.totals;
+        totals.sales += 1;
+        totals.owed += transaction.owed_cents();
+        totals.paid += transaction.paid_cents();
+        totals.change += transaction.change_cents();
+        Ok(self.push(id, Processed::Sale(transaction.clone()), breakdown))
+    }
+
+    /// Make the pieces for a refund of `amount` and add it to the history.
+    pub fn refund(
+        &mut self,
+        id: Option<&str>,
+        amount: Cents,
+    ) -> Result<&Record, CashRegisterError> {
+        let breakdown = self.counted(|register| register.process_refund(amount))?;
+        self.totals.refunds += 1;
+        self.totals.refunded += amount;
+        Ok(self.push(id, Processed::Refund(amount), breakdown))
+    }
+
+    /// Reverse the latest sale or refund with `id`, for a `VOID` on input
+    /// line `line`: it's taken out of the totals and marked voided in the
+    /// history. As in the CLI, each can be voided once.
+    pub fn void(&mut self, id: &str, line: usize) -> Result<&Record, CashRegisterError> {
+        let latest = self.ids.get(id).copied();
+        let index = match latest {
+            Some(index) if self.history[index].voided_on.is_none() => index,
+            _ => {
+                self.totals.errors += 1;
+                return Err(CashRegisterError::NothingToVoid {
+                    line,
+                    id: id.to_string(),
+                    voided_on: latest.and_then(|index| self.history[index].voided_on),
+                });
+            }
+        };
+        let record = &mut self.history[index];
+        record.voided_on = Some(line);
+        let totals = &mut self.totals;
+        totals.voids += 1;
+        match &record.processed {
+            Processed::Sale(transaction) => {
+                totals.sales -= 1;
+                totals.owed -= transaction.owed_cents();
+                totals.paid -= transaction.paid_cents();
+                totals.change -= transaction.change_cents();
+            }
+            Processed::Refund(amount) => {
+                totals.refunds -= 1;
+                totals.refunded -= *amount;
+            }
+        }
+        for &(index, count) in &record.breakdown {
+            totals.pieces[index] -= u64::from(count);
+        }
+        Ok(record)
+    }
+
+    /// Process input text as [`CashRegister::process`] does, adding each
+    /// sale and refund to the history under its `id=` field and reversing
+    /// each `VOID` with [`void`](Self::void). A void that goes through has no
+    /// output line.
+    pub fn process(&mut self, input: &str) -> Vec<Result<String, CashRegisterError>> {
+        parse_entries_with(input, self.register.options())
+            .filter_map(|(line, entry, _)| match entry {
+                Ok(Entry::Transaction(transaction, fields)) => Some(
+                    self.sale(fields.id.as_deref(), &transaction)
+                        .map(|record| record.breakdown.clone())
+                        .map(|breakdown| self.register.render(&transaction, &breakdown)),
+                ),
+                Ok(Entry::Refund(amount, fields)) => Some(
+                    self.refund(fields.id.as_deref(), amount)
+                        .map(|record| record.breakdown.clone())
+                        .map(|breakdown| self.register.render_refund(amount, &breakdown)),
+                ),
+                Ok(Entry::Void(id)) => self.void(&id, line).err().map(Err),
+                Ok(Entry::Till(..)) => None,
+                Err(e) => {
+                    self.totals.errors += 1;
+                    Some(Err(e))
+                }
+            })
+            .collect()
+    }
+
+    /// Everything processed so far, in order.
+    pub fn history(&self) -> &[Record] {
+        &self.history
+    }
+
+    /// The latest sale or refund with `id`, even if it was voided.
+    pub fn find(&self, id: &str) -> Option<&Record> {
+        self.ids.get(id).map(|&index| &self.history[index])
+    }
+
+    pub fn totals(&self) -> &SessionTotals {
+        &self.totals
+    }
+
+    /// The totals as `format::format_session` prints them.
+    pub fn summary(&self) -> String {
+        format_session(&self.totals, self.currency())
+    }
+
+    fn counted(
+        &mut self,
+        make: impl FnOnce(&mut CashRegister<'a>) -> Result<Breakdown, CashRegisterError>,
+    ) -> Result<Breakdown, CashRegisterError> {
+        make(&mut self.register).inspect_err(|_| self.totals.errors += 1)
+    }
+
+    fn push(&mut self, id: Option<&str>, processed: Processed, breakdown: Breakdown) -> &Record {
+        for &(index, count) in &breakdown {
+            self.totals.pieces[index] += u64::from(count);
+        }
+        if let Some(id) = id {
+            self.ids.insert(id.to_string(), self.history.len());
+        }
+        self.history.push(Record {
+            id: id.map(ToString::to_string),
+            processed,
+            breakdown,
+            voided_on: None,
+        });
+        self.history.last().expect("just pushed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::USD;
+
+    fn session() -> Session<'static> {
+        Session::new(CashRegister::builder().divisor(0).build())
+    }
+
+    #[test]
+    fn keeps_running_totals() {
+        let mut session = session();
+        let lines = session.process("2.12,3.00\n1.97,2.00\nabc\nREFUND,0.30\n");
+        assert_eq!(lines.len(), 4);
+        assert!(lines[2].is_err());
+
+        let totals = session.totals();
+        assert_eq!((totals.sales, totals.refunds, totals.errors), (2, 1, 1));
+        assert_eq!(totals.owed, Cents(409));
+        assert_eq!(totals.paid, Cents(500));
+        assert_eq!(totals.change, Cents(91));
+        assert_eq!(totals.refunded, Cents(30));
+        // 3 quarters, 1 dime, 3 pennies; 3 pennies; 1 quarter, 1 nickel.
+        assert_eq!(totals.pieces, [0, 4, 1, 1, 6]);
+        assert_eq!(totals.piece_count(), 12);
+
+        let exact = Transaction::new(Cents(100), Cents(100)).unwrap();
+        session.sale(None, &exact).unwrap();
+        assert_eq!(session.totals().sales, 3);
+        assert_eq!(session.history().len(), 4);
+    }
+
+    #[test]
+    fn finds_the_latest_record_by_id() {
+        let mut session = session();
+        session.process("2.12,3.00,id=T1\n1.00,5.00,id=T2\n1.97,2.00,id=T1\n");
+        let t1 = session.find("T1").unwrap();
+        assert_eq!(t1.paid_out(), Cents(3));
+        assert_eq!(t1.breakdown.as_slice(), [(USD.index_of(1).unwrap(), 3)]);
+        assert!(!t1.is_refund());
+        assert_eq!(session.find("T2").unwrap().paid_out(), Cents(400));
+        assert!(session.find("T3").is_none());
+    }
+
+    #[test]
+    fn a_void_comes_out_of_the_totals() {
+        let mut session = session();
+        let lines = session.process(
+            "2.12,3.00,id=T1\n1.97,2.00,id=T2\nREFUND,0.30,id=T3\nVOID T1\nVOID T3\nVOID T1\n",
+        );
+        assert_eq!(lines.len(), 4);
+        match &lines[3] {
+            Err(CashRegisterError::NothingToVoid {
+                line: 6, voided_on, ..
+            }) => assert_eq!(*voided_on, Some(4)),
+            other => panic!("expected NothingToVoid, got {other:?}"),
+        }
+
+        let totals = session.totals();
+        assert_eq!(
+            (totals.sales, totals.refunds, totals.voids, totals.errors),
+            (1, 0, 2, 1)
+        );
+        assert_eq!(
+            (totals.owed, totals.paid, totals.change, totals.refunded),
+            (Cents(197), Cents(200), Cents(3), Cents(0))
+        );
+        // Only T2's 3 pennies are still out.
+        assert_eq!(totals.pieces, [0, 0, 0, 0, 3]);
+        assert_eq!(session.history().len(), 3);
+        assert_eq!(session.find("T1").unwrap().voided_on, Some(4));
+        assert!(session.void("T9", 7).is_err());
+    }
+}
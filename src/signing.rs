@@ -0,0 +1,426 @@
+//! Tamper-evident lines for `--sign-key`: each output or journal line gets
+//! an HMAC-SHA256 tag, so an auditor holding the key can prove a
+//! cash-handling log wasn't edited after the fact.
+//!
+//! A signed line is the line, a tab, its sequence number, and the tag in
+//! hex:
+//!
+//! ```text
+//! 3 quarters,1 dime,3 pennies\t1:9f2c...
+//! ```
+//!
+//! The tag covers the sequence number, the previous line's tag, and the
+//! line, so a line can't be edited, dropped, or moved without the key:
+//! [`Verifier`] flags the edited line, or the first line after a gap.
+//!
+//! Each run's lines are framed by signed records. The first, numbered 1,
+//! is `#chain start`, or when the run appends to a signed file,
+//! `#chain start after <tag>` naming that file's last tag, so the runs in
+//! a journal form one chain: a run deleted, moved, or pasted in from
+//! elsewhere leaves a start that doesn't follow on from the line before
+//! it. The last is `#chain end`, so lines cut from the end of a run are
+//! flagged too. Whole runs cut from the end of a file still leave a file
+//! that verifies; keep its last tag somewhere else to catch that.
+//!
+//! ```
+//! use cash_register::signing::{Problem, Signer, Verifier};
+//!
+//! let mut signer = Signer::new(b"secret");
+//! let start_and_first = signer.sign("3 pennies");
+//! let (start, first) = start_and_first.split_once('\n').unwrap();
+//! let second = signer.sign("1 nickel");
+//!
+//! let mut verifier = Verifier::new(b"secret");
+//! assert_eq!(verifier.check(start), None);
+//! assert_eq!(verifier.check(first), None);
+//! assert_eq!(verifier.check(&second.replace("nickel", "dime")), Some(Problem::Altered));
+//! ```
+
+use alloc::{format, string::String, vec::Vec};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Bytes in a tag.
+const TAG_LEN: usize = 32;
+
+/// The record that starts a run's chain.
+const START: &str = "#chain start";
+
+/// The record that ends a run's chain.
+const END: &str = "#chain end";
+
+/// Signs lines in order, each chained to the one before.
+#[derive(Clone)]
+pub struct Signer {
+    mac: HmacSha256,
+    previous: [u8; TAG_LEN],
+    seq: u64,
+    /// The tag this run's chain continues from, if any.
+    after: Option<[u8; TAG_LEN]>,
+}
+
+impl Signer {
+    /// A signer starting a new chain with `key`.
+    pub fn new(key: &[u8]) -> Self {
+        Self {
+            mac: keyed(key),
+            previous: [0; TAG_LEN],
+            seq: 0,
+            after: None,
+        }
+    }
+
+    /// A signer continuing the chain that ends with `last`, the last line
+    /// of a signed file, so lines appended to the file verify with the
+    /// ones before them. `None` when `last` isn't signed.
+    pub fn after(key: &[u8], last: &str) -> Option<Self> {
+        let (_, _, tag) = split_signed(last)?;
+        Some(Self {
+            after: Some(tag),
+            ..Self::new(key)
+        })
+    }
+
+    /// `line` with its signature appended. `line` shouldn't end with a
+    /// newline; a tab in it is fine. The first call returns the run's
+    /// start record before it, on a line of its own.
+    pub fn sign(&mut self, line: &str) -> String {
+        if self.seq > 0 {
+            return self.next(line);
+        }
+        let start = match &self.after {
+            Some(tag) => format!("{START} after {}", hex(tag)),
+            None => START.into(),
+        };
+        self.previous = self.after.unwrap_or([0; TAG_LEN]);
+        let start = self.next(&start);
+        format!("{start}\n{}", self.next(line))
+    }
+
+    /// The signed record ending the run, once it has signed anything.
+    pub fn end(&mut self) -> Option<String> {
+        (self.seq > 0).then(|| self.next(END))
+    }
+
+    fn next(&mut self, line: &str) -> String {
+        self.seq += 1;
+        self.previous = tag(&self.mac, self.seq, &self.previous, line);
+        format!("{line}\t{}:{}", self.seq, hex(&self.previous))
+    }
+}
+
+/// What's wrong with a signed line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    /// It has no signature, or one that isn't `seq:tag`.
+    Unsigned,
+    /// Its tag doesn't match: the line was changed, or signed with another
+    /// key.
+    Altered,
+    /// Its sequence number isn't the one after the previous line's: lines
+    /// before it were removed or moved.
+    OutOfSequence { expected: u64, found: u64 },
+    /// It's numbered 1 but isn't a start record following on from the
+    /// line before it: runs before it were removed or moved, or it was
+    /// pasted in from another file.
+    Unlinked,
+    /// The file stops after it without the record ending its run: lines
+    /// after it were cut off, or the run never finished.
+    Unended,
+}
+
+/// A line that didn't verify.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Flagged {
+    /// 1-indexed line of the signed file.
+    pub line: usize,
+    pub problem: Problem,
+}
+
+/// What [`Verifier::finish`] found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Lines checked.
+    pub lines: usize,
+    pub flagged: Vec<Flagged>,
+}
+
+impl VerifyReport {
+    pub fn is_clean(&self) -> bool {
+        self.flagged.is_empty()
+    }
+}
+
+/// Checks signed lines in the order they were written.
+///
+/// After a flagged line, the chain carries on from that line's own tag, so
+/// one edit flags one line rather than everything after it.
+#[derive(Clone)]
+pub struct Verifier {
+    mac: HmacSha256,
+    previous: [u8; TAG_LEN],
+    seq: u64,
+    /// The last signed line, and whether it was an end record.
+    last_signed: usize,
+    ended: bool,
+    report: VerifyReport,
+}
+
+impl Verifier {
+    pub fn new(key: &[u8]) -> Self {
+        Self {
+            mac: keyed(key),
+            previous: [0; TAG_LEN],
+            seq: 0,
+            last_signed: 0,
+            ended: false,
+            report: VerifyReport::default(),
+        }
+    }
+
+    /// Check the next line, without its newline, and return what's wrong
+    /// with it, if anything.
+    pub fn check(&mut self, signed: &str) -> Option<Problem> {
+        self.report.lines += 1;
+        let problem = self.problem(signed);
+        if let Some(problem) = problem {
+            self.report.flagged.push(Flagged {
+                line: self.report.lines,
+                problem,
+            });
+        }
+        problem
+    }
+
+    fn problem(&mut self, signed: &str) -> Option<Problem> {
+        let Some((line, seq, stored)) = split_signed(signed) else {
+            return Some(Problem::Unsigned);
+        };
+        self.last_signed = self.report.lines;
+        self.ended = line == END;
+        let (previous, linked) = if seq == 1 {
+            // A new chain is only at the top of the file, and a continued
+            // one only straight after the tag it names.
+            match start_after(line) {
+                Some(None) => ([0; TAG_LEN], self.seq == 0),
+                Some(Some(after)) => (after, self.seq > 0 && after == self.previous),
+                None => ([0; TAG_LEN], false),
+            }
+        } else if seq != self.seq + 1 {
+            let expected = self.seq + 1;
+            (self.seq, self.previous) = (seq, stored);
+            return Some(Problem::OutOfSequence {
+                expected,
+                found: seq,
+            });
+        } else {
+            (self.previous, true)
+        };
+        let mut mac = self.mac.clone();
+        update(&mut mac, seq, &previous, line);
+        (self.seq, self.previous) = (seq, stored);
+        if mac.verify_slice(&stored).is_err() {
+            Some(Problem::Altered)
+        } else if !linked {
+            Some(Problem::Unlinked)
+        } else {
+            None
+        }
+    }
+
+    /// Every line checked and what was wrong with them, including a run
+    /// left without its end record at the bottom of the file.
+    pub fn finish(mut self) -> VerifyReport {
+        if self.seq > 0 && !self.ended {
+            self.report.flagged.push(Flagged {
+                line: self.last_signed,
+                problem: Problem::Unended,
+            });
+        }
+        self.report
+    }
+}
+
+fn keyed(key: &[u8]) -> HmacSha256 {
+    HmacSha256::new_from_slice(key).expect("HMAC takes a key of any length")
+}
+
+fn update(mac: &mut HmacSha256, seq: u64, previous: &[u8; TAG_LEN], line: &str) {
+    mac.update(&seq.to_be_bytes());
+    mac.update(previous);
+    mac.update(line.as_bytes());
+}
+
+fn tag(mac: &HmacSha256, seq: u64, previous: &[u8; TAG_LEN], line: &str) -> [u8; TAG_LEN] {
+    let mut mac = mac.clone();
+    update(&mut mac, seq, previous, line);
+    mac.finalize().into_bytes().into()
+}
+
+/// What a start record continues from: `Some(None)` for a new chain,
+/// `Some(Some(tag))` for one continuing after `tag`, and `None` when `line`
+/// isn't a start record.
+fn start_after(line: &str) -> Option<Option<[u8; TAG_LEN]>> {
+    match line.strip_prefix(START)? {
+        "" => Some(None),
+        rest => rest.strip_prefix(" after ").and_then(unhex).map(Some),
+    }
+}
+
+/// A signed line's content, sequence number, and tag.
+fn split_signed(signed: &str) -> Option<(&str, u64, [u8; TAG_LEN])> {
+    let (line, signature) = signed.rsplit_once('\t')?;
+    let (seq, tag) = signature.split_once(':')?;
+    let seq = seq.parse().ok().filter(|&seq| seq > 0)?;
+    Some((line, seq, unhex(tag)?))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn unhex(s: &str) -> Option<[u8; TAG_LEN]> {
+    let mut bytes = [0; TAG_LEN];
+    if s.len() != TAG_LEN * 2 || !s.is_ascii() {
+        return None;
+    }
+    for (byte, pair) in bytes.iter_mut().zip(s.as_bytes().chunks(2)) {
+        let pair = core::str::from_utf8(pair).ok()?;
+        *byte = u8::from_str_radix(pair, 16).ok()?;
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    /// One run signing `lines`, start and end records included.
+    fn signed(lines: &[&str]) -> Vec<String> {
+        run(Signer::new(b"key"), lines)
+    }
+
+    fn run(mut signer: Signer, lines: &[&str]) -> Vec<String> {
+        let mut out: Vec<String> = lines
+            .iter()
+            .flat_map(|line| {
+                signer
+                    .sign(line)
+                    .lines()
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        out.extend(signer.end());
+        out
+    }
+
+    /// Another run appended to `lines`.
+    fn appended(lines: &[String], run_lines: &[&str]) -> Vec<String> {
+        let signer = Signer::after(b"key", lines.last().unwrap()).unwrap();
+        let mut all = lines.to_vec();
+        all.extend(run(signer, run_lines));
+        all
+    }
+
+    fn verify(lines: &[String]) -> VerifyReport {
+        let mut verifier = Verifier::new(b"key");
+        for line in lines {
+            verifier.check(line);
+        }
+        verifier.finish()
+    }
+
+    fn problems(lines: &[String]) -> Vec<(usize, Problem)> {
+        let report = verify(lines);
+        report
+            .flagged
+            .iter()
+            .map(|flagged| (flagged.line, flagged.problem))
+            .collect()
+    }
+
+    #[test]
+    fn an_untouched_file_verifies() {
+        let lines = signed(&["3 pennies", "", "a\tb"]);
+        assert!(lines[0].starts_with("#chain start\t1:"));
+        assert!(lines[1].starts_with("3 pennies\t2:"));
+        assert_eq!(lines[1].len(), "3 pennies\t2:".len() + 64);
+        assert!(lines[4].starts_with("#chain end\t5:"));
+        let report = verify(&lines);
+        assert!(report.is_clean());
+        assert_eq!(report.lines, 5);
+
+        let two_runs = appended(&lines, &["1 nickel"]);
+        assert!(two_runs[5].starts_with("#chain start after "));
+        assert!(verify(&two_runs).is_clean());
+        assert!(verify(&[]).is_clean());
+        let mut wrong_key = Verifier::new(b"other");
+        assert_eq!(wrong_key.check(&lines[0]), Some(Problem::Altered));
+    }
+
+    #[test]
+    fn flags_edits_gaps_and_unsigned_lines() {
+        let mut lines = signed(&["a", "b", "c", "d", "e"]);
+        lines[2] = lines[2].replacen('b', "B", 1);
+        lines.remove(4);
+        lines.push("tacked on".to_string());
+        assert_eq!(
+            problems(&lines),
+            [
+                (3, Problem::Altered),
+                (
+                    5,
+                    Problem::OutOfSequence {
+                        expected: 5,
+                        found: 6,
+                    },
+                ),
+                (7, Problem::Unsigned),
+            ]
+        );
+
+        let mut swapped = signed(&["a", "b", "c"]);
+        swapped.swap(2, 3);
+        assert_eq!(verify(&swapped).flagged.len(), 3);
+    }
+
+    #[test]
+    fn flags_runs_removed_moved_or_cut_short() {
+        let first = signed(&["a", "b"]);
+        let second = appended(&first, &["c"]);
+        let three = appended(&second, &["d"]);
+
+        // The middle run deleted: the third starts after a tag that's gone.
+        let mut deleted = first.clone();
+        deleted.extend_from_slice(&three[second.len()..]);
+        assert_eq!(problems(&deleted), [(5, Problem::Unlinked)]);
+
+        // The first run deleted: the file opens partway through a chain.
+        assert_eq!(problems(&second[first.len()..]), [(1, Problem::Unlinked)]);
+
+        // Runs swapped, or a separately signed run pasted onto the end.
+        let mut moved = three[second.len()..].to_vec();
+        moved.extend_from_slice(&second);
+        assert_eq!(
+            problems(&moved)[..2],
+            [(1, Problem::Unlinked), (4, Problem::Unlinked)]
+        );
+        let mut pasted = first.clone();
+        pasted.extend(signed(&["x"]));
+        assert_eq!(problems(&pasted), [(5, Problem::Unlinked)]);
+
+        // Lines cut from the end of the last run.
+        assert_eq!(problems(&three[..three.len() - 2]), [(8, Problem::Unended)]);
+
+        // A run that died without its end record is fine once another
+        // follows on from it.
+        let mut crashed = first[..first.len() - 1].to_vec();
+        crashed = appended(&crashed, &["c"]);
+        assert!(verify(&crashed).is_clean());
+    }
+}
@@ -0,0 +1,644 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use rand::Rng;
+
+use crate::currency::{Currency, Denomination};
+use crate::money::Cents;
+use crate::parse::Transaction;
+use crate::rules::make_change_for;
+use crate::strategy::greedy::GreedyStrategy;
+use crate::strategy::ChangeStrategy;
+use crate::till::Till;
+
+/// How a simulated customer pays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaymentHabit {
+    /// Counts out the exact amount: no change due.
+    Exact,
+    /// Rounds up to the next largest-denomination piece (the next dollar).
+    RoundUp,
+    /// Rounds up, then hands over one to four more largest pieces.
+    Overpay,
+    /// Hands over the smallest round sum that covers it, 5, 10, 20, 50, or
+    /// 100 largest pieces (a $20 for $12.34), like paying with a bill.
+    NextBill,
+}
+
+/// The round sums `NextBill` pays with, in largest pieces.
+const BILLS: [u32; 5] = [5, 10, 20, 50, 100];
+
+/// Relative weights of each payment habit, e.g. `exact=20,round-up=60,overpay=20`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaymentMix {
+    pub exact: u32,
+    pub round_up: u32,
+    pub overpay: u32,
+    pub next_bill: u32,
+}
+
+impl Default for PaymentMix {
+    fn default() -> Self {
+        Self {
+            exact: 20,
+            round_up: 60,
+            overpay: 20,
+            next_bill: 0,
+        }
+    }
+}
+
+impl core::str::FromStr for PaymentMix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mix = PaymentMix {
+            exact: 0,
+            round_up: 0,
+            overpay: 0,
+            next_bill: 0,
+        };
+        for part in s.split(',') {
+            let (habit, weight) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected \"habit=weight\" but got \"{part}\""))?;
+            let weight: u32 = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid weight \"{}\"", weight.trim()))?;
+            match habit.trim().to_lowercase().as_str() {
+                "exact" => mix.exact = weight,
+                "round-up" => mix.round_up = weight,
+                "overpay" => mix.overpay = weight,
+                "next-bill" => mix.next_bill = weight,
+                other => {
+                    return Err(format!(
+                        "unknown payment habit \"{other}\" \
+                         (expected exact, round-up, overpay, or next-bill)"
+                    ))
+                }
+            }
+        }
+        if mix.total() == 0 {
+            return Err("payment mix has no weight".to_string());
+        }
+        Ok(mix)
+    }
+}
+
+impl PaymentMix {
+    fn total(&self) -> u32 {
+        self.exact + self.round_up + self.overpay + self.next_bill
+    }
+
+    fn pick<R: Rng + ?Sized>(&self, rng: &mut R) -> PaymentHabit {
+        let roll = rng.gen_range(0..self.total());
+        if roll < self.exact {
+            PaymentHabit::Exact
+        } else if roll < self.exact + self.round_up {
+            PaymentHabit::RoundUp
+        } else if roll < self.exact + self.round_up + self.overpay {
+            PaymentHabit::Overpay
+        } else {
+            PaymentHabit::NextBill
+        }
+    }
+}
+
+/// How the amounts owed on synthetic sales are spread up to the largest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AmountDistribution {
+    /// Evenly over `0.01..=max`.
+    #[default]
+    Uniform,
+    /// The smallest of three even draws: mostly small sales, a few large.
+    Skewed,
+    /// Whole units ending in .99, .95, or .49, like shelf prices.
+    ShelfPrices,
+}
+
+impl AmountDistribution {
+    /// Every distribution, in the order `--help` lists them.
+    pub const ALL: [Self; 3] = [Self::Uniform, Self::Skewed, Self::ShelfPrices];
+
+    /// The name it's parsed from.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Uniform => "uniform",
+            Self::Skewed => "skewed",
+            Self::ShelfPrices => "shelf-prices",
+        }
+    }
+
+    /// Draw an amount owed of at least a cent and at most `max`.
+    pub fn draw<R: Rng + ?Sized>(self, max: Cents, rng: &mut R) -> Cents {
+        let max = max.0.max(1);
+        match self {
+            Self::Uniform => Cents(rng.gen_range(1..=max)),
+            Self::Skewed => Cents(
+                (0..3)
+                    .map(|_| rng.gen_range(1..=max))
+                    .min()
+                    .expect("three draws"),
+            ),
+            Self::ShelfPrices => {
+                let ending = [99, 95, 49][rng.gen_range(0..3)];
+                match max.checked_sub(ending) {
+                    Some(room) => Cents(rng.gen_range(0..=room / 100) * 100 + ending),
+                    // Too small for a shelf price; any amount will do.
+                    None => Cents(rng.gen_range(1..=max)),
+                }
+            }
+        }
+    }
+}
+
+impl core::str::FromStr for AmountDistribution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|d| d.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                format!(
+                    "unknown amount distribution \"{s}\" (expected uniform, skewed, or shelf-prices)"
+                )
+            })
+    }
+}
+
+/// A synthetic sale: the transaction and the cash tendered for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sale {
+    pub transaction: Transaction,
+    pub tendered: Vec<(u32, u32)>,
+}
+
+/// Generate `count` sales with amounts owed spread evenly over
+/// `0.01..=max_owed`, paid according to `mix`.
+pub fn generate_sales<R: Rng + ?Sized>(
+    count: usize,
+    max_owed: Cents,
+    mix: PaymentMix,
+    currency: &Currency,
+    rng: &mut R,
+) -> Vec<Sale> {
+    sale_generator(AmountDistribution::Uniform, max_owed, mix, currency, rng)
+        .take(count)
+        .collect()
+}
+
+/// An endless stream of sales with amounts owed drawn from `amounts` up to
+/// `max_owed`, paid according to `mix`, so a big batch needn't fit in
+/// memory.
+pub fn sale_generator<'a, R: Rng + ?Sized>(
+    amounts: AmountDistribution,
+    max_owed: Cents,
+    mix: PaymentMix,
+    currency: &'a Currency,
+    rng: &'a mut R,
+) -> impl Iterator<Item = Sale> + 'a {
+    let unit = &currency.denominations[0];
+    core::iter::repeat(()).map(move |()| {
+        let owed = amounts.draw(max_owed, rng);
+        let whole_units = u32::try_from(owed.0.div_ceil(unit.value().0))
+            .expect("owed is within Cents::MAX_AMOUNT");
+        let tendered = match mix.pick(rng) {
+            PaymentHabit::Exact => match GreedyStrategy.make_change(owed, currency) {
+                Ok(exact) => currency.pieces(&exact).map(|(d, n)| (d.cents, n)).collect(),
+                // Amounts the coins can't make exactly get rounded up.
+                Err(_) => vec![(unit.cents, whole_units)],
+            },
+            PaymentHabit::RoundUp => vec![(unit.cents, whole_units)],
+            PaymentHabit::Overpay => vec![(unit.cents, whole_units + rng.gen_range(1..=4))],
+            PaymentHabit::NextBill => {
+                let bill = BILLS.into_iter().find(|&bill| bill >= whole_units);
+                vec![(unit.cents, bill.unwrap_or(whole_units))]
+            }
+        };
+        let paid = tendered
+            .iter()
+            .map(|&(cents, n)| Cents::from(cents) * n)
+            .sum();
+        Sale {
+            transaction: Transaction::new(owed, paid).expect("paid covers owed"),
+            tendered,
+        }
+    })
+}
+
+/// A sale as an input line, `owed,paid`, with the cash handed over as a
+/// `tendered=` field when `with_tendered` is set: `12.34,20.00,tendered=1.00x20`.
+pub fn format_sale_line(sale: &Sale, with_tendered: bool) -> String {
+    let mut line = format!(
+        "{},{}",
+        sale.transaction.owed_cents(),
+        sale.transaction.paid_cents()
+    );
+    if with_tendered && !sale.tendered.is_empty() {
+        let items: Vec<String> = sale
+            .tendered
+            .iter()
+            .map(|&(cents, count)| format!("{}x{count}", Cents::from(cents)))
+            .collect();
+        line += &format!(",tendered={}", items.join("+"));
+    }
+    line
+}
+
+/// A fault `generate --with-errors` puts in a line, like the ones real POS
+/// exports turn up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quirk {
+    /// Too many decimal places, a stray letter, a missing field, or the
+    /// wrong separator: a line error.
+    Malformed,
+    /// Paid less than owed: an underpayment error.
+    Underpaid,
+    /// Spaces and tabs around the fields, or a CRLF ending: still valid.
+    Whitespace,
+    /// A byte order mark, a zero-width or non-breaking space, or a
+    /// full-width digit or comma. Only the non-breaking space parses. Kept
+    /// to valid UTF-8, since anything else stops the read of the whole file.
+    Encoding,
+}
+
+impl Quirk {
+    /// Every quirk, in the order `--help` lists them.
+    pub const ALL: [Self; 4] = [
+        Self::Malformed,
+        Self::Underpaid,
+        Self::Whitespace,
+        Self::Encoding,
+    ];
+
+    /// The name its share is set by.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Malformed => "malformed",
+            Self::Underpaid => "underpaid",
+            Self::Whitespace => "whitespace",
+            Self::Encoding => "encoding",
+        }
+    }
+
+    /// `line`, the input line for `sale`, with this fault in it.
+    pub fn apply<R: Rng + ?Sized>(self, line: &str, sale: &Sale, rng: &mut R) -> String {
+        let owed = sale.transaction.owed_cents();
+        let paid = sale.transaction.paid_cents();
+        match self {
+            Self::Malformed => match rng.gen_range(0..4) {
+                0 => format!("{owed}{},{paid}", rng.gen_range(1..10)),
+                1 => format!("{owed}x,{paid}"),
+                2 => owed.to_string(),
+                _ => format!("{owed};{paid}"),
+            },
+            Self::Underpaid => format!("{owed},{}", Cents(rng.gen_range(0..owed.0))),
+            Self::Whitespace => {
+                let mut pad = || [" ", "  ", "\t", " \t"][rng.gen_range(0..4)];
+                let padded: Vec<String> = line
+                    .split(',')
+                    .map(|field| format!("{}{field}{}", pad(), pad()))
+                    .collect();
+                let mut line = padded.join(",");
+                if rng.gen_bool(0.5) {
+                    line.push('\r');
+                }
+                line
+            }
+            Self::Encoding => {
+                let (owed, rest) = line.split_once(',').expect("a generated line has a comma");
+                match rng.gen_range(0..5) {
+                    0 => format!("\u{feff}{line}"),
+                    1 => format!("{owed},\u{a0}{rest}"),
+                    2 => format!("{owed}\u{200b},{rest}"),
+                    3 => {
+                        // The first digit, full-width: '2' is U+FF12.
+                        let digit = owed.as_bytes()[0] - b'0';
+                        let wide = char::from_u32(0xff10 + u32::from(digit)).expect("a digit");
+                        format!("{wide}{},{rest}", &owed[1..])
+                    }
+                    _ => format!("{owed}\u{ff0c}{rest}"),
+                }
+            }
+        }
+    }
+}
+
+/// The percentage of lines given each quirk, e.g.
+/// `malformed=5,underpaid=5`; the rest are left alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorMix {
+    /// Indexed like `Quirk::ALL`.
+    pub percent: [u32; 4],
+}
+
+impl Default for ErrorMix {
+    /// 5% of each, so one line in five has something wrong with it.
+    fn default() -> Self {
+        Self { percent: [5; 4] }
+    }
+}
+
+impl core::str::FromStr for ErrorMix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mix = ErrorMix { percent: [0; 4] };
+        for part in s.split(',') {
+            let (quirk, percent) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected \"quirk=percent\" but got \"{part}\""))?;
+            let percent: u32 = percent
+                .trim()
+                .trim_end_matches('%')
+                .parse()
+                .map_err(|_| format!("invalid percentage \"{}\"", percent.trim()))?;
+            let quirk = quirk.trim().to_lowercase();
+            let index = Quirk::ALL
+                .iter()
+                .position(|q| q.as_str() == quirk)
+                .ok_or_else(|| {
+                    format!(
+                        "unknown quirk \"{quirk}\" \
+                         (expected malformed, underpaid, whitespace, or encoding)"
+                    )
+                })?;
+            mix.percent[index] = percent;
+        }
+        let total: u32 = mix.percent.iter().sum();
+        if total > 100 {
+            return Err(format!("the quirks add up to {total}%, more than 100%"));
+        }
+        Ok(mix)
+    }
+}
+
+impl ErrorMix {
+    /// The quirk for the next line, if it gets one.
+    pub fn pick<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Quirk> {
+        let mut roll = rng.gen_range(0..100);
+        for (quirk, &percent) in Quirk::ALL.into_iter().zip(&self.percent) {
+            if roll < percent {
+                return Some(quirk);
+            }
+            roll -= percent;
+        }
+        None
+    }
+}
+
+/// A denomination the drawer ran out of, and the (1-based) sale where it happened.
+#[derive(Debug, Clone)]
+pub struct RunOut {
+    pub sale: usize,
+    pub denomination: Denomination,
+}
+
+/// What happened to the drawer over a simulated day.
+#[derive(Debug, Clone, Default)]
+pub struct SimulationReport {
+    pub sales: usize,
+    /// Sales the drawer couldn't make change for (or, with a slot full to
+    /// `u32::MAX`, take the cash for).
+    pub short_sales: usize,
+    /// First time each denomination ran out, in order.
+    pub ran_out: Vec<RunOut>,
+}
+
+impl SimulationReport {
+    fn note_run_out(&mut self, sale: usize, denomination: &Denomination) {
+        if !self
+            .ran_out
+            .iter()
+            .any(|r| r.denomination.cents == denomination.cents)
+        {
+            self.ran_out.push(RunOut {
+                sale,
+                denomination: denomination.clone(),
+            });
+        }
+    }
+}
+
+/// Ring up `sales` against `till`, noting when each denomination first runs
+/// out: emptied by a dispense, or short for a sale. A sale that can't be
+/// covered hands the customer's cash back and leaves the drawer unchanged.
+pub fn run_sales<R: Rng + ?Sized>(
+    sales: &[Sale],
+    till: &mut Till,
+    currency: &Currency,
+    divisor: u32,
+    rng: &mut R,
+) -> SimulationReport {
+    let mut report = SimulationReport {
+        sales: sales.len(),
+        ..SimulationReport::default()
+    };
+    for (i, sale) in sales.iter().enumerate() {
+        let Ok(breakdown) = make_change_for(&sale.transaction, currency, divisor, rng) else {
+            // No drawer could make this change exactly.
+            report.short_sales += 1;
+            continue;
+        };
+        let deposited = sale
+            .tendered
+            .iter()
+            .take_while(|&&(cents, count)| till.add(cents, count).is_ok())
+            .count();
+        if deposited < sale.tendered.len() {
+            // A slot too full to take the customer's cash turns the sale away.
+            till.take_back(&sale.tendered[..deposited]);
+            report.short_sales += 1;
+            continue;
+        }
+        match till.dispense(&breakdown, currency) {
+            Ok(()) => {
+                for (denomination, _) in currency.pieces(&breakdown) {
+                    if till.count(denomination.cents) == 0 {
+                        report.note_run_out(i + 1, denomination);
+                    }
+                }
+            }
+            Err(shortage) => {
+                report.short_sales += 1;
+                report.note_run_out(i + 1, &shortage.denomination);
+                till.take_back(&sale.tendered);
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::USD;
+    use crate::error::CashRegisterError;
+    use crate::till::parse_till;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn payment_mix_parses() {
+        let mix: PaymentMix = "exact=1, round-up=2".parse().unwrap();
+        assert_eq!(
+            mix,
+            PaymentMix {
+                exact: 1,
+                round_up: 2,
+                overpay: 0,
+                next_bill: 0,
+            }
+        );
+        assert_eq!("next-bill=1".parse::<PaymentMix>().unwrap().next_bill, 1);
+        assert!("exact=0".parse::<PaymentMix>().is_err());
+        assert!("cheque=5".parse::<PaymentMix>().is_err());
+        assert!("exact".parse::<PaymentMix>().is_err());
+    }
+
+    #[test]
+    fn generated_sales_follow_habits() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let exact = PaymentMix {
+            exact: 1,
+            round_up: 0,
+            overpay: 0,
+            next_bill: 0,
+        };
+        for sale in generate_sales(50, Cents(2000), exact, &USD, &mut rng) {
+            assert!((Cents(1)..=Cents(2000)).contains(&sale.transaction.owed_cents()));
+            assert!(sale.transaction.change_cents().is_zero());
+        }
+
+        let round_up = PaymentMix {
+            exact: 0,
+            round_up: 1,
+            overpay: 0,
+            next_bill: 0,
+        };
+        for sale in generate_sales(50, Cents(2000), round_up, &USD, &mut rng) {
+            assert!(sale.transaction.change_cents() < Cents(100));
+            assert!(sale.transaction.paid_cents().is_multiple_of(100));
+        }
+    }
+
+    #[test]
+    fn amounts_follow_their_distribution_and_bills_cover_them() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let next_bill = PaymentMix {
+            exact: 0,
+            round_up: 0,
+            overpay: 0,
+            next_bill: 1,
+        };
+        let shelf = AmountDistribution::ShelfPrices;
+        for sale in sale_generator(shelf, Cents(5000), next_bill, &USD, &mut rng).take(50) {
+            let owed = sale.transaction.owed_cents();
+            assert!(owed <= Cents(5000));
+            assert!([99, 95, 49].contains(&owed.fraction()), "{owed}");
+            let paid = sale.transaction.paid_cents();
+            assert!([500, 1000, 2000, 5000].contains(&paid.0), "{paid}");
+            assert_eq!(sale.tendered, [(100, (paid.0 / 100) as u32)]);
+        }
+
+        // Mostly small: the smallest of three draws is under a fifth of the
+        // max about half the time.
+        let small = (0..1000)
+            .filter(|_| AmountDistribution::Skewed.draw(Cents(1000), &mut rng) < Cents(200))
+            .count();
+        assert!((350..650).contains(&small), "{small}");
+        assert_eq!(
+            AmountDistribution::Uniform.draw(Cents(1), &mut rng),
+            Cents(1)
+        );
+        assert!(AmountDistribution::ShelfPrices.draw(Cents(30), &mut rng) <= Cents(30));
+
+        for amounts in AmountDistribution::ALL {
+            assert_eq!(amounts.as_str().parse(), Ok(amounts));
+        }
+        assert!("normal".parse::<AmountDistribution>().is_err());
+    }
+
+    #[test]
+    fn sales_print_as_input_lines() {
+        let sale = Sale {
+            transaction: Transaction::new(Cents(1234), Cents(2000)).unwrap(),
+            tendered: vec![(100, 20)],
+        };
+        assert_eq!(format_sale_line(&sale, false), "12.34,20.00");
+        assert_eq!(
+            format_sale_line(&sale, true),
+            "12.34,20.00,tendered=1.00x20"
+        );
+    }
+
+    #[test]
+    fn error_mix_parses_and_picks_by_percentage() {
+        let mix: ErrorMix = "malformed=10, encoding=5%".parse().unwrap();
+        assert_eq!(mix.percent, [10, 0, 0, 5]);
+        assert!("malformed=60,underpaid=50".parse::<ErrorMix>().is_err());
+        assert!("typos=5".parse::<ErrorMix>().is_err());
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let picks: Vec<_> = (0..10_000).filter_map(|_| mix.pick(&mut rng)).collect();
+        let malformed = picks.iter().filter(|&&q| q == Quirk::Malformed).count();
+        assert!((900..1_100).contains(&malformed), "{malformed}");
+        assert!((400..600).contains(&(picks.len() - malformed)));
+        assert!(picks
+            .iter()
+            .all(|&q| matches!(q, Quirk::Malformed | Quirk::Encoding)));
+    }
+
+    #[test]
+    fn quirks_break_lines_the_way_they_say() {
+        let sale = Sale {
+            transaction: Transaction::new(Cents(212), Cents(300)).unwrap(),
+            tendered: vec![],
+        };
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..50 {
+            let parse = |quirk: Quirk, rng: &mut StdRng| {
+                crate::parse::parse_record(&quirk.apply("2.12,3.00", &sale, rng), 1)
+            };
+            assert!(parse(Quirk::Malformed, &mut rng).is_err());
+            assert!(matches!(
+                parse(Quirk::Underpaid, &mut rng),
+                Err(CashRegisterError::Underpayment { .. })
+            ));
+            let (spaced, _) = parse(Quirk::Whitespace, &mut rng).unwrap();
+            assert_eq!(spaced, sale.transaction);
+            assert_ne!(
+                Quirk::Encoding.apply("2.12,3.00", &sale, &mut rng),
+                "2.12,3.00"
+            );
+        }
+    }
+
+    #[test]
+    fn reports_when_denominations_run_out() {
+        let sale = |owed: u32, paid: u32| Sale {
+            transaction: Transaction::new(Cents::from(owed), Cents::from(paid)).unwrap(),
+            tendered: vec![(100, paid / 100)],
+        };
+        // 3 pennies each time; the drawer holds 5.
+        let sales = vec![sale(97, 100), sale(197, 200), sale(297, 300)];
+        let mut till = parse_till("0.01,5\n", &USD).unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+
+        let report = run_sales(&sales, &mut till, &USD, 0, &mut rng);
+        assert_eq!(report.sales, 3);
+        assert_eq!(report.short_sales, 2);
+        assert_eq!(report.ran_out.len(), 1);
+        assert_eq!(report.ran_out[0].sale, 2);
+        assert_eq!(report.ran_out[0].denomination.cents, 1);
+        // Refused sales handed their dollars back.
+        assert_eq!(till.count(100), 1);
+        assert_eq!(till.count(1), 2);
+    }
+}
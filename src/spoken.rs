@@ -0,0 +1,225 @@
+//! Numbers, amounts, and breakdowns in words, for text-to-speech on
+//! self-checkout units: `3 quarters,1 dime,3 pennies` is read out as
+//! "three quarters, one dime, and three pennies", and $2.12 as "two
+//! dollars and twelve cents".
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::currency::Currency;
+use crate::money::Cents;
+
+const ONES: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+
+const TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+
+/// Each power of a thousand, from a thousand up; `u64::MAX` is about 18
+/// quintillion.
+const SCALES: [&str; 6] = [
+    "thousand",
+    "million",
+    "billion",
+    "trillion",
+    "quadrillion",
+    "quintillion",
+];
+
+/// `n` in words, as it's said in US English: `2_012` is "two thousand
+/// twelve", `45` is "forty-five".
+pub fn number_words(n: u64) -> String {
+    if n == 0 {
+        return ONES[0].to_string();
+    }
+    let mut groups = Vec::new();
+    let mut rest = n;
+    let mut scale = 0;
+    while rest > 0 {
+        let group = (rest % 1000) as usize;
+        if group > 0 {
+            let words = hundreds_words(group);
+            groups.push(match scale {
+                0 => words,
+                _ => format!("{words} {}", SCALES[scale - 1]),
+            });
+        }
+        rest /= 1000;
+        scale += 1;
+    }
+    groups.reverse();
+    groups.join(" ")
+}
+
+/// 1 to 999 in words.
+fn hundreds_words(n: usize) -> String {
+    let (hundreds, rest) = (n / 100, n % 100);
+    let tens = match rest {
+        0 => String::new(),
+        1..=19 => ONES[rest].to_string(),
+        _ if rest % 10 == 0 => TENS[rest / 10].to_string(),
+        _ => format!("{}-{}", TENS[rest / 10], ONES[rest % 10]),
+    };
+    match (hundreds, tens.is_empty()) {
+        (0, _) => tens,
+        (_, true) => format!("{} hundred", ONES[hundreds]),
+        (_, false) => format!("{} hundred {tens}", ONES[hundreds]),
+    }
+}
+
+/// What a currency's major and minor units are called, singular and
+/// plural. Currencies without built-in names are read out by code.
+fn unit_names(currency: &Currency) -> [&str; 4] {
+    match currency.name.as_ref() {
+        "USD" => ["dollar", "dollars", "cent", "cents"],
+        "EUR" => ["euro", "euros", "cent", "cents"],
+        code => [code, code, "cent", "cents"],
+    }
+}
+
+/// `count` of something in words: "one dime", "three pennies".
+fn counted(count: u64, singular: &str, plural: &str) -> String {
+    let noun = if count == 1 { singular } else { plural };
+    format!("{} {noun}", number_words(count))
+}
+
+/// An amount in words: "two dollars and twelve cents", "three euros",
+/// "twelve cents", or for nothing at all, "zero dollars".
+pub fn amount_words(amount: Cents, currency: &Currency) -> String {
+    let [major, majors, minor, minors] = unit_names(currency);
+    let (whole, cents) = (amount.0 / 100, amount.0 % 100);
+    match (whole, cents) {
+        (0, 0) => counted(0, major, majors),
+        (0, _) => counted(cents, minor, minors),
+        (_, 0) => counted(whole, major, majors),
+        _ => format!(
+            "{} and {}",
+            counted(whole, major, majors),
+            counted(cents, minor, minors)
+        ),
+    }
+}
+
+/// A breakdown as a spoken list: "three quarters, one dime, and three
+/// pennies", "one dollar and two pennies", or "no change". Numerals in a
+/// denomination's name are spelled out too, so EUR's `2 euro coins` are
+/// "two euro coins".
+pub fn breakdown_words(breakdown: &[(usize, u32)], currency: &Currency) -> String {
+    let items: Vec<String> = currency
+        .pieces(breakdown)
+        .map(|(denomination, count)| {
+            let name = if count == 1 {
+                &denomination.singular
+            } else {
+                &denomination.plural
+            };
+            format!(
+                "{} {}",
+                number_words(u64::from(count)),
+                spell_numerals(name)
+            )
+        })
+        .collect();
+    match items.as_slice() {
+        [] => "no change".to_string(),
+        [one] => one.clone(),
+        [first, second] => format!("{first} and {second}"),
+        [rest @ .., last] => format!("{}, and {last}", rest.join(", ")),
+    }
+}
+
+/// `text` with every word that's a whole number spelled out.
+fn spell_numerals(text: &str) -> String {
+    let words: Vec<String> = text
+        .split(' ')
+        .map(|word| match word.parse::<u64>() {
+            Ok(n) => number_words(n),
+            Err(_) => word.to_string(),
+        })
+        .collect();
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::{EUR, USD};
+
+    #[test]
+    fn numbers_read_as_words() {
+        let cases = [
+            (0, "zero"),
+            (7, "seven"),
+            (13, "thirteen"),
+            (40, "forty"),
+            (45, "forty-five"),
+            (100, "one hundred"),
+            (212, "two hundred twelve"),
+            (2_012, "two thousand twelve"),
+            (1_000_001, "one million one"),
+            (
+                u64::MAX,
+                "eighteen quintillion four hundred forty-six quadrillion seven hundred \
+                 forty-four trillion seventy-three billion seven hundred nine million \
+                 five hundred fifty-one thousand six hundred fifteen",
+            ),
+        ];
+        for (n, words) in cases {
+            assert_eq!(number_words(n), words, "{n}");
+        }
+    }
+
+    #[test]
+    fn amounts_and_breakdowns_read_as_speech() {
+        assert_eq!(
+            amount_words(Cents(212), &USD),
+            "two dollars and twelve cents"
+        );
+        assert_eq!(amount_words(Cents(100), &USD), "one dollar");
+        assert_eq!(amount_words(Cents(1), &USD), "one cent");
+        assert_eq!(amount_words(Cents::ZERO, &USD), "zero dollars");
+        assert_eq!(amount_words(Cents(300), &EUR), "three euros");
+
+        let quarter = USD.index_of(25).unwrap();
+        let dime = USD.index_of(10).unwrap();
+        let penny = USD.index_of(1).unwrap();
+        assert_eq!(
+            breakdown_words(&[(quarter, 3), (dime, 1), (penny, 3)], &USD),
+            "three quarters, one dime, and three pennies"
+        );
+        assert_eq!(
+            breakdown_words(&[(dime, 1), (penny, 1)], &USD),
+            "one dime and one penny"
+        );
+        assert_eq!(breakdown_words(&[], &USD), "no change");
+        let two_euro = EUR.index_of(200).unwrap();
+        assert_eq!(
+            breakdown_words(&[(two_euro, 2)], &EUR),
+            "two two euro coins"
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Distribution statistics over a file's change: how much change is due,
+//! how many pieces it takes, which denominations do the work, and how often
+//! the random rule applies.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::currency::Currency;
+use crate::money::Cents;
+use crate::parse::Transaction;
+
+/// Upper bounds (exclusive) of the change histogram's buckets: no change,
+/// under 1.00, under 5.00, under 10.00, under 20.00, and the rest.
+pub const CHANGE_BUCKETS: [Cents; 5] = [Cents(1), Cents(100), Cents(500), Cents(1000), Cents(2000)];
+
+/// Change statistics built up one transaction at a time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeStats {
+    pub transactions: u64,
+    /// Transactions the random rule applied to.
+    pub random: u64,
+    /// Pieces handed out, all denominations together.
+    pub pieces: u64,
+    /// Pieces handed out per denomination, by value in cents.
+    pub by_denomination: BTreeMap<u32, u64>,
+    /// Transactions per bucket: one for each of `CHANGE_BUCKETS`, then one
+    /// for change of the last bound and over.
+    pub histogram: [u64; CHANGE_BUCKETS.len() + 1],
+}
+
+impl ChangeStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one transaction and the change made for it.
+    pub fn record(
+        &mut self,
+        transaction: &Transaction,
+        breakdown: &[(usize, u32)],
+        is_random: bool,
+        currency: &Currency,
+    ) {
+        self.transactions += 1;
+        self.random += u64::from(is_random);
+        let bucket = CHANGE_BUCKETS
+            .iter()
+            .position(|&bound| transaction.change_cents() < bound)
+            .unwrap_or(CHANGE_BUCKETS.len());
+        self.histogram[bucket] += 1;
+        for (denomination, count) in currency.pieces(breakdown) {
+            self.pieces += u64::from(count);
+            *self.by_denomination.entry(denomination.cents).or_insert(0) += u64::from(count);
+        }
+    }
+
+    /// Mean pieces per transaction, or 0 with none recorded.
+    pub fn average_pieces(&self) -> f64 {
+        if self.transactions == 0 {
+            return 0.0;
+        }
+        self.pieces as f64 / self.transactions as f64
+    }
+
+    /// Denominations by pieces handed out, most first, as `(cents, pieces)`.
+    /// Ties go to the larger denomination.
+    pub fn most_used(&self) -> Vec<(u32, u64)> {
+        let mut used: Vec<(u32, u64)> = self
+            .by_denomination
+            .iter()
+            .map(|(&cents, &pieces)| (cents, pieces))
+            .collect();
+        used.sort_by(|a, b| b.1.cmp(&a.1).then(b.0.cmp(&a.0)));
+        used
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::USD;
+
+    #[test]
+    fn records_buckets_pieces_and_random_share() {
+        let mut stats = ChangeStats::new();
+        let exact = Transaction::new(Cents(500), Cents(500)).unwrap();
+        stats.record(&exact, &[], false, &USD);
+        let small = Transaction::new(Cents(212), Cents(300)).unwrap();
+        stats.record(&small, &[(1, 3), (2, 1), (4, 3)], false, &USD);
+        let large = Transaction::new(Cents(300), Cents(2500)).unwrap();
+        stats.record(&large, &[(0, 22)], true, &USD);
+
+        assert_eq!(stats.transactions, 3);
+        assert_eq!(stats.random, 1);
+        assert_eq!(stats.histogram, [1, 1, 0, 0, 0, 1]);
+        assert_eq!(stats.pieces, 29);
+        assert!((stats.average_pieces() - 29.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.most_used(), [(100, 22), (25, 3), (1, 3), (10, 1)]);
+        assert_eq!(ChangeStats::new().average_pieces(), 0.0);
+    }
+}
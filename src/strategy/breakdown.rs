@@ -0,0 +1,100 @@
+//! Arithmetic on breakdowns, so callers can add dispensed change together or
+//! take it out of an inventory without tallying counts by hand.
+//!
+//! Breakdowns being combined must index the same currency's table. Results
+//! are normalized: one entry per denomination, largest first, with no zero
+//! counts.
+
+use alloc::collections::BTreeMap;
+
+use super::Breakdown;
+use crate::currency::Currency;
+use crate::money::Cents;
+
+/// Total value of the pieces in a breakdown of `currency`.
+pub fn total(breakdown: &[(usize, u32)], currency: &Currency) -> Cents {
+    currency.pieces(breakdown).map(|(d, n)| d.value() * n).sum()
+}
+
+/// Combine repeated denominations, drop zero counts, and sort largest first.
+pub fn normalize(breakdown: &[(usize, u32)]) -> Breakdown {
+    merge(breakdown, &[])
+}
+
+/// Every piece in `a` plus every piece in `b`.
+///
+/// Panics if a denomination's combined count overflows a `u32`.
+pub fn merge(a: &[(usize, u32)], b: &[(usize, u32)]) -> Breakdown {
+    let mut counts: BTreeMap<usize, u32> = BTreeMap::new();
+    for &(index, count) in a.iter().chain(b) {
+        let entry = counts.entry(index).or_insert(0);
+        *entry = entry
+            .checked_add(count)
+            .expect("merged piece count overflows u32");
+    }
+    counts.into_iter().filter(|&(_, n)| n > 0).collect()
+}
+
+/// The pieces of `a` left after taking out `b`, or `None` if `b` needs more
+/// of some denomination than `a` has.
+pub fn checked_sub(a: &[(usize, u32)], b: &[(usize, u32)]) -> Option<Breakdown> {
+    let mut left = normalize(a);
+    for (index, count) in normalize(b) {
+        let slot = left.iter_mut().find(|(i, _)| *i == index)?;
+        slot.1 = slot.1.checked_sub(count)?;
+    }
+    left.retain(|(_, n)| *n > 0);
+    Some(left)
+}
+
+/// Whether two breakdowns hold the same pieces, in whatever order.
+pub fn same_pieces(a: &[(usize, u32)], b: &[(usize, u32)]) -> bool {
+    normalize(a) == normalize(b)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+    use crate::currency::USD;
+
+    fn usd(cents: u32, count: u32) -> (usize, u32) {
+        (USD.index_of(cents).unwrap(), count)
+    }
+
+    fn counts(breakdown: &[(usize, u32)]) -> Vec<(u32, u32)> {
+        USD.pieces(breakdown).map(|(d, n)| (d.cents, n)).collect()
+    }
+
+    #[test]
+    fn normalize_combines_and_sorts() {
+        let messy = [usd(1, 2), usd(25, 1), usd(10, 0), usd(1, 1)];
+        assert_eq!(counts(&normalize(&messy)), [(25, 1), (1, 3)]);
+        assert_eq!(total(&messy, &USD), Cents(28));
+    }
+
+    #[test]
+    fn merge_adds_counts() {
+        let a = [usd(25, 3), usd(1, 3)];
+        let b = [usd(10, 1), usd(1, 2)];
+        assert_eq!(counts(&merge(&a, &b)), [(25, 3), (10, 1), (1, 5)]);
+    }
+
+    #[test]
+    fn checked_sub_needs_enough_of_each() {
+        let drawer = [usd(25, 4), usd(10, 2)];
+        let change = [usd(25, 3), usd(10, 2)];
+        assert_eq!(counts(&checked_sub(&drawer, &change).unwrap()), [(25, 1)]);
+        assert!(checked_sub(&drawer, &[usd(25, 5)]).is_none());
+        assert!(checked_sub(&drawer, &[usd(1, 1)]).is_none());
+    }
+
+    #[test]
+    fn same_pieces_ignores_order_and_splits() {
+        let a = [usd(1, 3), usd(25, 1)];
+        let b = [usd(25, 1), usd(1, 1), usd(1, 2)];
+        assert!(same_pieces(&a, &b));
+        assert!(!same_pieces(&a, &[usd(25, 1)]));
+    }
+}
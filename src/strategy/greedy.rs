@@ -1,5 +1,9 @@
-use super::{Breakdown, ChangeStrategy};
+use alloc::vec::Vec;
+
+use super::{exact, piece_count, Breakdown, ChangeStrategy};
 use crate::currency::Currency;
+use crate::error::InfeasibleChange;
+use crate::money::Cents;
 
 /// Greedy algorithm: use the fewest coins/bills possible.
 ///
@@ -9,21 +13,104 @@ use crate::currency::Currency;
 pub struct GreedyStrategy;
 
 impl ChangeStrategy for GreedyStrategy {
-    fn make_change(&mut self, mut cents: u32, currency: &Currency) -> Breakdown {
-        let mut result = Vec::new();
+    fn make_change(
+        &mut self,
+        amount: Cents,
+        currency: &Currency,
+    ) -> Result<Breakdown, InfeasibleChange> {
+        let mut result = Breakdown::new();
+        let mut left = amount;
+
+        for (i, denom) in currency.denominations.iter().enumerate() {
+            if left.is_zero() {
+                break;
+            }
+            let count = piece_count(left / denom.value());
+            if count > 0 {
+                result.push((i, count));
+                left -= denom.value() * count;
+            }
+        }
+
+        exact(result, amount, left)
+    }
+}
+
+/// [`GreedyStrategy`] for one currency, with the change for every sub-unit
+/// remainder (0-99 cents) worked out once up front, so each transaction only
+/// walks the bills and unit coins.
+///
+/// The split is only exact when the currency has a one-unit piece and every
+/// larger denomination is a whole number of units, as in USD and EUR. For
+/// any other currency, or a different currency than the table was built
+/// for, it walks the denominations like `GreedyStrategy`. Either way the
+/// change is the same.
+pub struct GreedyTable<'a> {
+    currency: &'a Currency,
+    /// Where the sub-unit denominations start.
+    split: usize,
+    /// The change for each remainder, and what's left of it that the
+    /// sub-unit pieces can't make.
+    remainders: Option<Vec<(Breakdown, u32)>>,
+}
+
+impl<'a> GreedyTable<'a> {
+    pub fn new(currency: &'a Currency) -> Self {
+        let denominations = &currency.denominations;
+        let split = denominations.partition_point(|d| d.cents >= 100);
+        let splits_exactly = split > 0
+            && denominations[split - 1].cents == 100
+            && denominations[..split].iter().all(|d| d.cents % 100 == 0);
+        let remainders = splits_exactly.then(|| {
+            (0..100)
+                .map(|mut left| {
+                    let mut pieces = Breakdown::new();
+                    for (i, denom) in denominations.iter().enumerate().skip(split) {
+                        if left >= denom.cents {
+                            pieces.push((i, left / denom.cents));
+                            left %= denom.cents;
+                        }
+                    }
+                    (pieces, left)
+                })
+                .collect()
+        });
+        Self {
+            currency,
+            split,
+            remainders,
+        }
+    }
+}
 
-        for &denom in currency.denominations {
-            if cents == 0 {
+impl ChangeStrategy for GreedyTable<'_> {
+    fn make_change(
+        &mut self,
+        amount: Cents,
+        currency: &Currency,
+    ) -> Result<Breakdown, InfeasibleChange> {
+        let remainders = match &self.remainders {
+            Some(remainders) if core::ptr::eq(currency, self.currency) => remainders,
+            _ => return GreedyStrategy.make_change(amount, currency),
+        };
+        let denominations = &currency.denominations;
+        let mut result = Breakdown::new();
+
+        let mut whole = Cents(amount.0 - amount.fraction());
+        for (i, denom) in denominations[..self.split].iter().enumerate() {
+            if whole.is_zero() {
                 break;
             }
-            let count = cents / denom.cents;
+            let count = piece_count(whole / denom.value());
             if count > 0 {
-                result.push((denom, count));
-                cents -= count * denom.cents;
+                result.push((i, count));
+                whole -= denom.value() * count;
             }
         }
+        let (pieces, left) = &remainders[amount.fraction() as usize];
+        result.extend_from_slice(pieces);
 
-        result
+        exact(result, amount, Cents::from(*left))
     }
 }
 
@@ -31,14 +118,18 @@ impl ChangeStrategy for GreedyStrategy {
 mod tests {
     use super::*;
     use crate::currency::{EUR, USD};
+    use alloc::vec;
 
     #[test]
     fn sample_output_88_cents() {
         // 3.00 - 2.12 = 0.88 -> 3 quarters, 1 dime, 3 pennies
         let mut strategy = GreedyStrategy;
-        let breakdown = strategy.make_change(88, &USD);
+        let breakdown = strategy.make_change(Cents(88), &USD).unwrap();
 
-        let named: Vec<(&str, u32)> = breakdown.iter().map(|(d, c)| (d.singular, *c)).collect();
+        let named: Vec<(&str, u32)> = USD
+            .pieces(&breakdown)
+            .map(|(d, c)| (d.singular.as_ref(), c))
+            .collect();
         assert_eq!(named, vec![("quarter", 3), ("dime", 1), ("penny", 3)],);
     }
 
@@ -46,25 +137,31 @@ mod tests {
     fn sample_output_3_cents() {
         // 2.00 - 1.97 = 0.03 -> 3 pennies
         let mut strategy = GreedyStrategy;
-        let breakdown = strategy.make_change(3, &USD);
+        let breakdown = strategy.make_change(Cents(3), &USD).unwrap();
 
-        let named: Vec<(&str, u32)> = breakdown.iter().map(|(d, c)| (d.singular, *c)).collect();
+        let named: Vec<(&str, u32)> = USD
+            .pieces(&breakdown)
+            .map(|(d, c)| (d.singular.as_ref(), c))
+            .collect();
         assert_eq!(named, vec![("penny", 3)]);
     }
 
     #[test]
     fn exact_dollar_amount() {
         let mut strategy = GreedyStrategy;
-        let breakdown = strategy.make_change(300, &USD);
+        let breakdown = strategy.make_change(Cents(300), &USD).unwrap();
 
-        let named: Vec<(&str, u32)> = breakdown.iter().map(|(d, c)| (d.singular, *c)).collect();
+        let named: Vec<(&str, u32)> = USD
+            .pieces(&breakdown)
+            .map(|(d, c)| (d.singular.as_ref(), c))
+            .collect();
         assert_eq!(named, vec![("dollar", 3)]);
     }
 
     #[test]
     fn zero_change() {
         let mut strategy = GreedyStrategy;
-        let breakdown = strategy.make_change(0, &USD);
+        let breakdown = strategy.make_change(Cents(0), &USD).unwrap();
         assert!(breakdown.is_empty());
     }
 
@@ -72,9 +169,12 @@ mod tests {
     fn uses_all_denominations() {
         // 141 = 100 + 25 + 10 + 5 + 1
         let mut strategy = GreedyStrategy;
-        let breakdown = strategy.make_change(141, &USD);
+        let breakdown = strategy.make_change(Cents(141), &USD).unwrap();
 
-        let named: Vec<(&str, u32)> = breakdown.iter().map(|(d, c)| (d.singular, *c)).collect();
+        let named: Vec<(&str, u32)> = USD
+            .pieces(&breakdown)
+            .map(|(d, c)| (d.singular.as_ref(), c))
+            .collect();
         assert_eq!(
             named,
             vec![
@@ -90,8 +190,11 @@ mod tests {
     #[test]
     fn single_penny() {
         let mut strategy = GreedyStrategy;
-        let breakdown = strategy.make_change(1, &USD);
-        let named: Vec<(&str, u32)> = breakdown.iter().map(|(d, c)| (d.singular, *c)).collect();
+        let breakdown = strategy.make_change(Cents(1), &USD).unwrap();
+        let named: Vec<(&str, u32)> = USD
+            .pieces(&breakdown)
+            .map(|(d, c)| (d.singular.as_ref(), c))
+            .collect();
         assert_eq!(named, vec![("penny", 1)]);
     }
 
@@ -99,11 +202,14 @@ mod tests {
     fn large_amount() {
         // $99.99 = 9999 cents
         let mut strategy = GreedyStrategy;
-        let breakdown = strategy.make_change(9999, &USD);
-        let total: u32 = breakdown.iter().map(|(d, c)| d.cents * c).sum();
+        let breakdown = strategy.make_change(Cents(9999), &USD).unwrap();
+        let total: u32 = USD.pieces(&breakdown).map(|(d, c)| d.cents * c).sum();
         assert_eq!(total, 9999);
         // Should be 99 dollars, 3 quarters, 2 dimes, 4 pennies
-        let named: Vec<(&str, u32)> = breakdown.iter().map(|(d, c)| (d.singular, *c)).collect();
+        let named: Vec<(&str, u32)> = USD
+            .pieces(&breakdown)
+            .map(|(d, c)| (d.singular.as_ref(), c))
+            .collect();
         assert_eq!(
             named,
             vec![("dollar", 99), ("quarter", 3), ("dime", 2), ("penny", 4)]
@@ -114,9 +220,12 @@ mod tests {
     fn eur_greedy_63_cents() {
         // 63 = 50 + 10 + 2 + 1
         let mut strategy = GreedyStrategy;
-        let breakdown = strategy.make_change(63, &EUR);
+        let breakdown = strategy.make_change(Cents(63), &EUR).unwrap();
 
-        let named: Vec<(&str, u32)> = breakdown.iter().map(|(d, c)| (d.singular, *c)).collect();
+        let named: Vec<(&str, u32)> = EUR
+            .pieces(&breakdown)
+            .map(|(d, c)| (d.singular.as_ref(), c))
+            .collect();
         assert_eq!(
             named,
             vec![
@@ -132,9 +241,12 @@ mod tests {
     fn eur_greedy_387_cents() {
         // 387 = 200 + 100 + 50 + 20 + 10 + 5 + 2
         let mut strategy = GreedyStrategy;
-        let breakdown = strategy.make_change(387, &EUR);
+        let breakdown = strategy.make_change(Cents(387), &EUR).unwrap();
 
-        let named: Vec<(&str, u32)> = breakdown.iter().map(|(d, c)| (d.singular, *c)).collect();
+        let named: Vec<(&str, u32)> = EUR
+            .pieces(&breakdown)
+            .map(|(d, c)| (d.singular.as_ref(), c))
+            .collect();
         assert_eq!(
             named,
             vec![
@@ -148,4 +260,51 @@ mod tests {
             ],
         );
     }
+
+    #[test]
+    fn table_matches_plain_greedy() {
+        for currency in [&USD, &EUR] {
+            let mut table = GreedyTable::new(currency);
+            assert!(table.remainders.is_some(), "{}", currency.name);
+            for cents in (0..2_000).chain([Cents::MAX_AMOUNT.0]) {
+                assert_eq!(
+                    table.make_change(Cents(cents), currency),
+                    GreedyStrategy.make_change(Cents(cents), currency),
+                    "{} {cents}",
+                    currency.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn table_falls_back_when_it_cant_split() {
+        use crate::currency::{Denomination, DenominationKind};
+
+        // A 1.50 piece means the sub-unit part depends on the whole part.
+        let odd = Currency::new(
+            "XTS",
+            "¤",
+            [150, 100, 25, 1]
+                .map(|cents| {
+                    Denomination::new(cents, "piece", "pieces", 10, DenominationKind::Coin)
+                })
+                .to_vec(),
+        )
+        .unwrap();
+        let mut table = GreedyTable::new(&odd);
+        assert!(table.remainders.is_none());
+        let change = table.make_change(Cents(175), &odd).unwrap();
+        assert_eq!(
+            odd.pieces(&change)
+                .map(|(d, n)| (d.cents, n))
+                .collect::<Vec<_>>(),
+            [(150, 1), (25, 1)]
+        );
+
+        // Built for USD, handed EUR.
+        let mut usd = GreedyTable::new(&USD);
+        let change = usd.make_change(Cents(388), &EUR).unwrap();
+        assert_eq!(EUR.denominations[change[0].0].cents, 200);
+    }
 }
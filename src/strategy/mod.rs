@@ -1,13 +1,193 @@
+pub mod breakdown;
 pub mod greedy;
 pub mod random;
 
-use crate::currency::{Currency, Denomination};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use smallvec::SmallVec;
+use tracing::debug;
 
-/// A breakdown of change: pairs of (denomination, count).
-/// Only includes denominations with count > 0.
-pub type Breakdown = Vec<(Denomination, u32)>;
+use crate::currency::Currency;
+use crate::error::{CashRegisterError, InfeasibleChange};
+use crate::money::Cents;
+
+use greedy::GreedyStrategy;
+use random::RandomStrategy;
+
+/// A breakdown of change: pairs of (denomination index, count), where the
+/// index is into the currency's `denominations`, so indices ascend from the
+/// largest piece. Only includes denominations with count > 0.
+/// [`Currency::pieces`] looks the denominations up.
+///
+/// Stored inline for up to eight denominations, which covers every built-in
+/// currency, so making change doesn't touch the heap. Functions that only
+/// read a breakdown take a slice.
+pub type Breakdown = SmallVec<[(usize, u32); 8]>;
 
 /// A strategy for making change.
 pub trait ChangeStrategy {
-    fn make_change(&mut self, cents: u32, currency: &Currency) -> Breakdown;
+    /// Break `amount` into `currency`'s denominations. Fails, rather than
+    /// returning change that doesn't add up, when the pieces can't land on
+    /// `amount` exactly.
+    fn make_change(
+        &mut self,
+        amount: Cents,
+        currency: &Currency,
+    ) -> Result<Breakdown, InfeasibleChange>;
+}
+
+/// The change a strategy built for `amount`, or an error if `left` of it
+/// couldn't be made.
+fn exact(breakdown: Breakdown, amount: Cents, left: Cents) -> Result<Breakdown, InfeasibleChange> {
+    if left.is_zero() {
+        Ok(breakdown)
+    } else {
+        Err(InfeasibleChange {
+            amount,
+            remainder: left,
+        })
+    }
+}
+
+/// Narrow a count of pieces for a `Breakdown`. Amounts are capped at
+/// `Cents::MAX_AMOUNT`, so even an all-penny breakdown fits in a `u32`.
+fn piece_count(count: u64) -> u32 {
+    u32::try_from(count).expect("amount exceeds Cents::MAX_AMOUNT")
+}
+
+/// An RNG seeded with `seed`, or from OS entropy when there isn't one.
+/// Without the `std` feature there's no entropy source, so unseeded RNGs all
+/// start from seed 0.
+pub(crate) fn rng_from_seed(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(s) => StdRng::seed_from_u64(s),
+        #[cfg(feature = "std")]
+        None => StdRng::from_entropy(),
+        #[cfg(not(feature = "std"))]
+        None => StdRng::seed_from_u64(0),
+    }
+}
+
+/// Which strategy [`make_change`] should use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrategyKind {
+    /// Fewest pieces.
+    Greedy,
+    /// Random counts, from a seeded RNG when `seed` is given.
+    Random { seed: Option<u64> },
+}
+
+/// Break `cents` into `currency`'s denominations, without building a
+/// `Transaction` or an RNG first:
+///
+/// ```
+/// use cash_register::currency::USD;
+/// use cash_register::{make_change, StrategyKind};
+///
+/// let breakdown = make_change(88, &USD, StrategyKind::Greedy).unwrap();
+/// let counts: Vec<_> = USD.pieces(&breakdown).map(|(d, n)| (d.cents, n)).collect();
+/// assert_eq!(counts, [(25, 3), (10, 1), (1, 3)]);
+/// ```
+///
+/// Fails with `AmountTooLarge` above `Cents::MAX_AMOUNT`, and with
+/// `InfeasibleAmount` when the strategy can't land on the amount exactly
+/// (a currency without a 1-cent piece).
+pub fn make_change(
+    cents: u64,
+    currency: &Currency,
+    strategy: StrategyKind,
+) -> Result<Breakdown, CashRegisterError> {
+    let amount = Cents(cents);
+    if amount > Cents::MAX_AMOUNT {
+        return Err(CashRegisterError::AmountTooLarge {
+            amount: amount.with_symbol(&currency.symbol),
+            max: Cents::MAX_AMOUNT.with_symbol(&currency.symbol),
+        });
+    }
+    let breakdown = match strategy {
+        StrategyKind::Greedy => GreedyStrategy.make_change(amount, currency),
+        StrategyKind::Random { seed } => {
+            RandomStrategy::new(rng_from_seed(seed)).make_change(amount, currency)
+        }
+    };
+    breakdown.map_err(|e| {
+        debug!(%amount, remainder = %e.remainder, currency = %currency.name, "no exact change");
+        e.in_currency(currency)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::{Denomination, DenominationKind, EUR};
+    use alloc::{string::ToString, vec};
+
+    #[test]
+    fn seeded_random_is_reproducible_and_exact() {
+        let kind = StrategyKind::Random { seed: Some(7) };
+        let a = make_change(388, &EUR, kind).unwrap();
+        let b = make_change(388, &EUR, kind).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(
+            EUR.pieces(&a).map(|(d, n)| d.value() * n).sum::<Cents>(),
+            Cents(388)
+        );
+    }
+
+    #[test]
+    fn builtin_breakdowns_stay_inline() {
+        for currency in crate::currency::CURRENCIES {
+            let kind = StrategyKind::Random { seed: Some(1) };
+            let change = make_change(999_999, currency, kind).unwrap();
+            assert!(!change.spilled(), "{} spilled", currency.name);
+        }
+    }
+
+    #[test]
+    fn rejects_amounts_it_cant_make() {
+        let err = make_change(Cents::MAX_AMOUNT.0 + 1, &EUR, StrategyKind::Greedy).unwrap_err();
+        assert_eq!(err.code(), "E011");
+
+        let nickels = Currency::new(
+            "NCK",
+            "N",
+            vec![Denomination::new(
+                5,
+                "nickel",
+                "nickels",
+                40,
+                DenominationKind::Coin,
+            )],
+        )
+        .unwrap();
+        let err = make_change(7, &nickels, StrategyKind::Greedy).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "N0.07 can't be made exactly from NCK denominations (N0.02 left over)"
+        );
+        assert!(make_change(10, &nickels, StrategyKind::Greedy).is_ok());
+    }
+
+    #[test]
+    fn strategies_report_the_remainder() {
+        let coin = |cents, name: &'static str| {
+            Denomination::new(cents, name, name, 40, DenominationKind::Coin)
+        };
+        let no_pennies =
+            Currency::new("NOP", "N", vec![coin(100, "unit"), coin(5, "nickel")]).unwrap();
+        let infeasible = Err(InfeasibleChange {
+            amount: Cents(307),
+            remainder: Cents(2),
+        });
+
+        let mut table = greedy::GreedyTable::new(&no_pennies);
+        assert_eq!(table.make_change(Cents(307), &no_pennies), infeasible);
+        assert_eq!(
+            GreedyStrategy.make_change(Cents(307), &no_pennies),
+            infeasible
+        );
+        let mut random = RandomStrategy::new(StdRng::seed_from_u64(3));
+        assert_eq!(random.make_change(Cents(307), &no_pennies), infeasible);
+        assert!(table.make_change(Cents(305), &no_pennies).is_ok());
+    }
 }
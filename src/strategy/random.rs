@@ -1,15 +1,20 @@
-use rand::Rng;
+use rand::{Rng, RngCore};
 
-use super::{Breakdown, ChangeStrategy};
+use super::{exact, piece_count, Breakdown, ChangeStrategy};
 use crate::currency::Currency;
+use crate::error::InfeasibleChange;
+use crate::money::Cents;
 
 /// Randomized change algorithm: pick random counts for each denomination.
 ///
 /// For each denomination (largest to smallest), picks a random count between
-/// 0 and the maximum possible. The final denomination (1 cent) absorbs the
-/// remainder, guaranteeing the total is always exact.
+/// 0 and the maximum possible. The final denomination absorbs as much of the
+/// remainder as it can: with a 1-cent piece, as in USD and EUR, the total is
+/// always exact.
 ///
 /// Generic over `R: Rng` so tests can inject a seeded RNG for determinism.
+/// Callers that can't be generic (trait objects, FFI) use
+/// [`DynRandomStrategy`] instead.
 pub struct RandomStrategy<R: Rng> {
     rng: R,
 }
@@ -20,17 +25,26 @@ impl<R: Rng> RandomStrategy<R> {
     }
 }
 
-impl<R: Rng> ChangeStrategy for RandomStrategy<R> {
-    fn make_change(&mut self, mut cents: u32, currency: &Currency) -> Breakdown {
-        let mut result = Vec::new();
-        let denoms = currency.denominations;
+/// A `RandomStrategy` over a borrowed `dyn RngCore`, so the RNG's type
+/// doesn't leak into the caller's types.
+pub type DynRandomStrategy<'a> = RandomStrategy<&'a mut dyn RngCore>;
 
-        for (i, &denom) in denoms.iter().enumerate() {
-            if cents == 0 {
+impl<R: Rng> ChangeStrategy for RandomStrategy<R> {
+    fn make_change(
+        &mut self,
+        amount: Cents,
+        currency: &Currency,
+    ) -> Result<Breakdown, InfeasibleChange> {
+        let mut result = Breakdown::new();
+        let denoms = &currency.denominations;
+        let mut left = amount;
+
+        for (i, denom) in denoms.iter().enumerate() {
+            if left.is_zero() {
                 break;
             }
 
-            let max_count = cents / denom.cents;
+            let max_count = piece_count(left / denom.value());
             if max_count == 0 {
                 continue;
             }
@@ -44,12 +58,12 @@ impl<R: Rng> ChangeStrategy for RandomStrategy<R> {
             };
 
             if count > 0 {
-                result.push((denom, count));
-                cents -= count * denom.cents;
+                result.push((i, count));
+                left -= denom.value() * count;
             }
         }
 
-        result
+        exact(result, amount, left)
     }
 }
 
@@ -57,6 +71,7 @@ impl<R: Rng> ChangeStrategy for RandomStrategy<R> {
 mod tests {
     use super::*;
     use crate::currency::{EUR, USD};
+    use alloc::vec::Vec;
     use rand::rngs::StdRng;
     use rand::SeedableRng;
 
@@ -70,8 +85,8 @@ mod tests {
             let mut strategy = seeded_strategy(seed);
             let target = 167u32; // $1.67
 
-            let breakdown = strategy.make_change(target, &USD);
-            let total: u32 = breakdown.iter().map(|(d, c)| d.cents * c).sum();
+            let breakdown = strategy.make_change(Cents::from(target), &USD).unwrap();
+            let total: u32 = USD.pieces(&breakdown).map(|(d, c)| d.cents * c).sum();
 
             assert_eq!(
                 total, target,
@@ -84,11 +99,11 @@ mod tests {
     fn random_change_all_counts_positive() {
         for seed in 0..100 {
             let mut strategy = seeded_strategy(seed);
-            let breakdown = strategy.make_change(250, &USD);
+            let breakdown = strategy.make_change(Cents(250), &USD).unwrap();
 
-            for (denom, count) in &breakdown {
+            for (denom, count) in USD.pieces(&breakdown) {
                 assert!(
-                    *count > 0,
+                    count > 0,
                     "seed {seed}: {} has count 0 but appeared in breakdown",
                     denom.singular
                 );
@@ -99,14 +114,27 @@ mod tests {
     #[test]
     fn random_change_zero() {
         let mut strategy = seeded_strategy(42);
-        let breakdown = strategy.make_change(0, &USD);
+        let breakdown = strategy.make_change(Cents(0), &USD).unwrap();
         assert!(breakdown.is_empty());
     }
 
     #[test]
     fn deterministic_with_same_seed() {
-        let b1 = seeded_strategy(42).make_change(167, &USD);
-        let b2 = seeded_strategy(42).make_change(167, &USD);
+        let b1 = seeded_strategy(42).make_change(Cents(167), &USD).unwrap();
+        let b2 = seeded_strategy(42).make_change(Cents(167), &USD).unwrap();
+
+        let counts1: Vec<u32> = b1.iter().map(|(_, c)| *c).collect();
+        let counts2: Vec<u32> = b2.iter().map(|(_, c)| *c).collect();
+        assert_eq!(counts1, counts2);
+    }
+
+    #[test]
+    fn dyn_rng_matches_the_generic_strategy() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let dyn_rng: &mut dyn RngCore = &mut rng;
+        let mut strategy: DynRandomStrategy = RandomStrategy::new(dyn_rng);
+        let b1 = strategy.make_change(Cents(167), &USD).unwrap();
+        let b2 = seeded_strategy(42).make_change(Cents(167), &USD).unwrap();
 
         let counts1: Vec<u32> = b1.iter().map(|(_, c)| *c).collect();
         let counts2: Vec<u32> = b2.iter().map(|(_, c)| *c).collect();
@@ -116,11 +144,11 @@ mod tests {
     #[test]
     fn different_seeds_can_produce_different_results() {
         // Not guaranteed, but with enough seeds at least one should differ
-        let baseline = seeded_strategy(0).make_change(500, &USD);
+        let baseline = seeded_strategy(0).make_change(Cents(500), &USD).unwrap();
         let baseline_counts: Vec<u32> = baseline.iter().map(|(_, c)| *c).collect();
 
         let any_different = (1..50).any(|seed| {
-            let b = seeded_strategy(seed).make_change(500, &USD);
+            let b = seeded_strategy(seed).make_change(Cents(500), &USD).unwrap();
             let counts: Vec<u32> = b.iter().map(|(_, c)| *c).collect();
             counts != baseline_counts
         });
@@ -136,8 +164,8 @@ mod tests {
         for seed in 0..100 {
             let mut strategy = seeded_strategy(seed);
             let target = 263u32; // EUR has 2-cent coins and 20-cent coins — different structure
-            let breakdown = strategy.make_change(target, &EUR);
-            let total: u32 = breakdown.iter().map(|(d, c)| d.cents * c).sum();
+            let breakdown = strategy.make_change(Cents::from(target), &EUR).unwrap();
+            let total: u32 = EUR.pieces(&breakdown).map(|(d, c)| d.cents * c).sum();
             assert_eq!(
                 total, target,
                 "seed {seed}: EUR breakdown sums to {total}, expected {target}"
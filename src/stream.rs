@@ -0,0 +1,112 @@
+//! Async input, behind the `tokio` feature, for consumers that already run
+//! on a tokio runtime and shouldn't have to wrap the sync pipeline in
+//! `spawn_blocking`.
+//!
+//! ```ignore
+//! let file = tokio::io::BufReader::new(tokio::fs::File::open(path).await?);
+//! let mut lines = CashRegister::builder().build().process_stream(file);
+//! while let Some(line) = lines.next().await {
+//!     println!("{}", line?);
+//! }
+//! ```
+
+use alloc::{string::String, vec::Vec};
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+use tokio_stream::wrappers::LinesStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::error::CashRegisterError;
+use crate::parse::{parse_entry, Entry, ParseOptions, ParseWarning};
+use crate::register::CashRegister;
+
+/// Like [`read_entries`](crate::parse::read_entries), but reads the lines
+/// from an async `reader`: each non-blank line's number, entry, and warnings.
+///
+/// A read error (including invalid UTF-8) is yielded as
+/// `CashRegisterError::Io` with the number of the line it hit, and ends the
+/// stream.
+pub fn stream_entries<R: AsyncBufRead + Unpin>(
+    reader: R,
+) -> impl Stream<Item = (usize, Result<Entry, CashRegisterError>, Vec<ParseWarning>)> {
+    let mut line_number = 0;
+    let mut failed = false;
+    LinesStream::new(reader.lines())
+        .map_while(move |line| {
+            if failed {
+                return None;
+            }
+            line_number += 1;
+            failed = line.is_err();
+            Some((line_number, line))
+        })
+        .filter_map(|(line_number, line)| match line {
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => {
+                let (entry, warnings) = parse_entry(&line, line_number, ParseOptions::default());
+                Some((line_number, entry, warnings))
+            }
+            Err(e) => Some((line_number, Err(e.into()), Vec::new())),
+        })
+}
+
+impl<'a> CashRegister<'a> {
+    /// Like [`process`](CashRegister::process), but reads the input from an
+    /// async `reader` and yields each output line (or error) as soon as its
+    /// input line arrives. A read error is yielded last.
+    pub fn process_stream<R: AsyncBufRead + Unpin + 'a>(
+        mut self,
+        reader: R,
+    ) -> impl Stream<Item = Result<String, CashRegisterError>> + 'a {
+        stream_entries(reader).filter_map(move |(_, entry, _)| match entry {
+            Ok(Entry::Transaction(transaction, _)) => Some(self.format(&transaction)),
+            Ok(Entry::Refund(amount, _)) => Some(self.format_refund(amount)),
+            Ok(Entry::Till(..) | Entry::Void(_)) => None,
+            Err(e) => Some(Err(e)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::EUR;
+
+    async fn collect<T>(stream: impl Stream<Item = T>) -> Vec<T> {
+        stream.collect().await
+    }
+
+    #[tokio::test]
+    async fn streams_match_the_sync_pipeline() {
+        let input = "3.33,5.00\nabc\n\ntill add 0.25,4\n1.97,2.00\n";
+        let sync = CashRegister::builder().seed(42).build().process(input);
+        let streamed = collect(
+            CashRegister::builder()
+                .seed(42)
+                .build()
+                .process_stream(input.as_bytes()),
+        )
+        .await;
+        assert_eq!(streamed.len(), 3);
+        assert_eq!(streamed[0].as_deref().ok(), sync[0].as_deref().ok());
+        assert!(matches!(
+            streamed[1],
+            Err(CashRegisterError::MalformedLine { line: 2, .. })
+        ));
+        assert_eq!(streamed[2].as_deref().unwrap(), "3 pennies");
+
+        let register = CashRegister::builder().currency(&EUR).divisor(0).build();
+        let lines = collect(register.process_stream(&b"1.50,2.00\n"[..])).await;
+        assert_eq!(lines[0].as_deref().unwrap(), "1 50 cent coin");
+    }
+
+    #[tokio::test]
+    async fn read_errors_end_the_stream() {
+        let input: &[u8] = b"2.12,3.00\n\ntill add 0.25,4\n\xff\n3.00,4.00\n";
+        let entries = collect(stream_entries(input)).await;
+        let lines: Vec<usize> = entries.iter().map(|(line, ..)| *line).collect();
+        assert_eq!(lines, [1, 3, 4]);
+        assert!(matches!(entries[1].1, Ok(Entry::Till(..))));
+        assert!(matches!(entries[2].1, Err(CashRegisterError::Io(_))));
+    }
+}
@@ -0,0 +1,282 @@
+//! Opt-in heuristics that flag transactions for loss-prevention review:
+//! the same amount rung up again and again, and cash paid just under a
+//! reporting threshold, as when a large payment is split to stay under it.
+//!
+//! A flag is only a reason to look: a busy till sells plenty of $1.00
+//! coffees in a row. [`Detector::check`] names the patterns a transaction
+//! matches as it's made, and [`Detector::findings`] lists them all for the
+//! end of the run.
+
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+use crate::money::Cents;
+use crate::parse::Transaction;
+
+/// A pattern a transaction matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pattern {
+    /// The same amount as the transactions just before it.
+    RepeatedAmount,
+    /// Paid within a tenth of the reporting threshold, under it.
+    UnderThreshold,
+}
+
+impl Pattern {
+    /// The name it's reported under in JSON output.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::RepeatedAmount => "repeated-amount",
+            Self::UnderThreshold => "under-threshold",
+        }
+    }
+}
+
+/// Something worth a look, for the end-of-run summary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Finding {
+    /// `count` transactions in a row, on `lines`, for the same amount: what
+    /// was owed, or for refunds, what was paid out.
+    Repeated {
+        amount: Cents,
+        refund: bool,
+        count: u32,
+        lines: RangeInclusive<usize>,
+    },
+    /// `paid` on `line` was just under `threshold`; for a refund, the
+    /// amount paid out.
+    UnderThreshold {
+        line: usize,
+        paid: Cents,
+        threshold: Cents,
+    },
+}
+
+impl Finding {
+    /// The line it starts on, which findings are listed by.
+    pub fn first_line(&self) -> usize {
+        match self {
+            Self::Repeated { lines, .. } => *lines.start(),
+            Self::UnderThreshold { line, .. } => *line,
+        }
+    }
+}
+
+/// Transactions in a row for the same amount, so far.
+#[derive(Debug, Clone)]
+struct Run {
+    amount: Cents,
+    refund: bool,
+    count: u32,
+    first: usize,
+    /// Its entry in `findings`, once it's long enough to be one.
+    finding: Option<usize>,
+}
+
+/// Watches transactions in input order.
+#[derive(Debug, Clone)]
+pub struct Detector {
+    repeats: u32,
+    threshold: Cents,
+    run: Option<Run>,
+    findings: Vec<Finding>,
+}
+
+impl Detector {
+    /// How many transactions in a row for one amount are flagged, unless
+    /// told otherwise.
+    pub const DEFAULT_REPEATS: u32 = 3;
+    /// The reporting threshold, unless told otherwise: $10,000.00, where US
+    /// businesses file a report on cash received.
+    pub const DEFAULT_THRESHOLD: Cents = Cents(1_000_000);
+
+    /// Flag `repeats` or more transactions in a row for the same amount (at
+    /// least two), and payments within a tenth of `threshold` under it.
+    pub fn new(repeats: u32, threshold: Cents) -> Self {
+        Self {
+            repeats: repeats.max(2),
+            threshold,
+            run: None,
+            findings: Vec::new(),
+        }
+    }
+
+    /// Count the transaction on `line`, a refund paid out as its change if
+    /// `refund`, and return the patterns it matches.
+    pub fn check(&mut self, line: usize, transaction: &Transaction, refund: bool) -> Vec<Pattern> {
+        let mut patterns = Vec::new();
+        let amount = if refund {
+            transaction.change_cents()
+        } else {
+            transaction.owed_cents()
+        };
+        let run = match self.run.take() {
+            Some(mut run) if run.amount == amount && run.refund == refund => {
+                run.count += 1;
+                run
+            }
+            _ => Run {
+                amount,
+                refund,
+                count: 1,
+                first: line,
+                finding: None,
+            },
+        };
+        let run = self.run.insert(run);
+        if run.count >= self.repeats {
+            patterns.push(Pattern::RepeatedAmount);
+            let finding = Finding::Repeated {
+                amount,
+                refund,
+                count: run.count,
+                lines: run.first..=line,
+            };
+            match run.finding {
+                Some(i) => self.findings[i] = finding,
+                None => {
+                    run.finding = Some(self.findings.len());
+                    self.findings.push(finding);
+                }
+            }
+        }
+
+        let paid = if refund {
+            transaction.change_cents()
+        } else {
+            transaction.paid_cents()
+        };
+        let floor = self.threshold - Cents(self.threshold.0 / 10);
+        if paid >= floor && paid < self.threshold {
+            patterns.push(Pattern::UnderThreshold);
+            self.findings.push(Finding::UnderThreshold {
+                line,
+                paid,
+                threshold: self.threshold,
+            });
+        }
+        patterns
+    }
+
+    /// Take back the transaction on `line`, voided: it no longer counts
+    /// towards a run of one amount, and isn't flagged under the threshold.
+    pub fn void(&mut self, line: usize) {
+        self.findings.retain(
+            |finding| !matches!(finding, Finding::UnderThreshold { line: l, .. } if *l == line),
+        );
+        if let Some(run) = self.run.as_mut().filter(|run| run.first <= line) {
+            run.count -= 1;
+            if run.count == 0 {
+                self.run = None;
+            }
+        }
+        let repeated = self.findings.iter().position(
+            |finding| matches!(finding, Finding::Repeated { lines, .. } if lines.contains(&line)),
+        );
+        if let Some(i) = repeated {
+            if let Finding::Repeated { count, .. } = &mut self.findings[i] {
+                *count -= 1;
+                if *count < self.repeats {
+                    self.findings.remove(i);
+                }
+            }
+        }
+        // Findings may have moved; the run's is found again by its start.
+        if let Some(run) = self.run.as_mut() {
+            run.finding = self.findings.iter().position(
+                |finding| matches!(finding, Finding::Repeated { lines, .. } if *lines.start() == run.first),
+            );
+        }
+    }
+
+    /// Everything flagged so far, in the order it was found.
+    pub fn findings(&self) -> &[Finding] {
+        &self.findings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sale(owed: u64, paid: u64) -> Transaction {
+        Transaction::new(Cents(owed), Cents(paid)).unwrap()
+    }
+
+    #[test]
+    fn flags_runs_of_one_amount() {
+        let mut detector = Detector::new(3, Detector::DEFAULT_THRESHOLD);
+        assert!(detector.check(1, &sale(499, 500), false).is_empty());
+        assert!(detector.check(2, &sale(499, 1000), false).is_empty());
+        assert_eq!(
+            detector.check(4, &sale(499, 500), false),
+            [Pattern::RepeatedAmount]
+        );
+        assert_eq!(
+            detector.check(5, &sale(499, 500), false),
+            [Pattern::RepeatedAmount]
+        );
+        // A refund of the same amount starts a run of its own.
+        let refund = Transaction::new(Cents::ZERO, Cents(499)).unwrap();
+        assert!(detector.check(6, &refund, true).is_empty());
+        assert!(detector.check(7, &sale(499, 500), false).is_empty());
+
+        assert_eq!(
+            detector.findings(),
+            [Finding::Repeated {
+                amount: Cents(499),
+                refund: false,
+                count: 4,
+                lines: 1..=5,
+            }]
+        );
+    }
+
+    #[test]
+    fn voids_come_out_of_the_findings() {
+        let mut detector = Detector::new(3, Cents(1_000_000));
+        detector.check(1, &sale(950_000, 990_000), false);
+        for line in 2..=4 {
+            detector.check(line, &sale(499, 500), false);
+        }
+        detector.void(1);
+        detector.void(3);
+        assert!(detector.findings().is_empty());
+
+        // The run carries on from where the void left it.
+        assert_eq!(
+            detector.check(5, &sale(499, 500), false),
+            [Pattern::RepeatedAmount]
+        );
+        assert_eq!(
+            detector.findings(),
+            [Finding::Repeated {
+                amount: Cents(499),
+                refund: false,
+                count: 3,
+                lines: 2..=5,
+            }]
+        );
+    }
+
+    #[test]
+    fn flags_payments_just_under_the_threshold() {
+        let mut detector = Detector::new(3, Cents(1_000_000));
+        assert!(detector.check(1, &sale(899_999, 899_999), false).is_empty());
+        assert_eq!(
+            detector.check(2, &sale(950_000, 990_000), false),
+            [Pattern::UnderThreshold]
+        );
+        assert!(detector
+            .check(3, &sale(950_000, 1_000_000), false)
+            .is_empty());
+        assert_eq!(
+            detector.findings(),
+            [Finding::UnderThreshold {
+                line: 2,
+                paid: Cents(990_000),
+                threshold: Cents(1_000_000),
+            }]
+        );
+    }
+}
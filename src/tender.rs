@@ -0,0 +1,106 @@
+use alloc::{format, string::String};
+
+use crate::currency::{Currency, DenominationKind};
+use crate::error::CashRegisterError;
+use crate::money::Cents;
+
+/// Store rules for what cash a customer may hand over, checked against a
+/// line's `tendered=` field. The default accepts everything.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TenderPolicy {
+    /// Refuse any bill worth more than this.
+    pub max_bill: Option<Cents>,
+    /// Refuse a payment of more than this many coins in total.
+    pub max_coins: Option<u32>,
+}
+
+impl TenderPolicy {
+    /// Check tendered `(cents, count)` pieces from input line `line`.
+    ///
+    /// Values that aren't denominations of `currency` are left for the till
+    /// to reject.
+    pub fn check(
+        &self,
+        tendered: &[(u32, u32)],
+        currency: &Currency,
+        line: usize,
+    ) -> Result<(), CashRegisterError> {
+        let refuse = |reason: String| Err(CashRegisterError::RefusedTender { line, reason });
+        let mut coins = 0;
+
+        for &(cents, count) in tendered {
+            let Some(d) = currency.denominations.iter().find(|d| d.cents == cents) else {
+                continue;
+            };
+            match d.kind {
+                DenominationKind::Coin => coins = count.saturating_add(coins),
+                DenominationKind::Bill => {
+                    if let Some(max) = self.max_bill.filter(|&max| d.value() > max) {
+                        return refuse(format!(
+                            "{} exceed the {} bill limit",
+                            d.plural,
+                            max.with_symbol(&currency.symbol)
+                        ));
+                    }
+                }
+            }
+        }
+
+        match self.max_coins {
+            Some(max) if coins > max => refuse(format!("{coins} coins tendered, limit is {max}")),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::{EUR, USD};
+    use alloc::string::ToString;
+
+    #[test]
+    fn default_accepts_everything() {
+        let policy = TenderPolicy::default();
+        assert!(policy.check(&[(100, 500), (1, 1000)], &USD, 1).is_ok());
+    }
+
+    #[test]
+    fn refuses_large_bills() {
+        let policy = TenderPolicy {
+            max_bill: Some(Cents(50)),
+            ..TenderPolicy::default()
+        };
+        let err = policy.check(&[(25, 1), (100, 1)], &USD, 4).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 4: tender refused: dollars exceed the $0.50 bill limit"
+        );
+        // Coins are never refused as large bills, whatever their value.
+        assert!(policy.check(&[(200, 3)], &EUR, 1).is_ok());
+    }
+
+    #[test]
+    fn limits_coins_across_denominations() {
+        let policy = TenderPolicy {
+            max_coins: Some(50),
+            ..TenderPolicy::default()
+        };
+        assert!(policy
+            .check(&[(25, 30), (10, 20), (100, 9)], &USD, 1)
+            .is_ok());
+        assert!(matches!(
+            policy.check(&[(25, 30), (10, 21)], &USD, 2),
+            Err(CashRegisterError::RefusedTender { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn unknown_values_are_left_to_the_till() {
+        let policy = TenderPolicy {
+            max_bill: Some(Cents::ZERO),
+            max_coins: Some(0),
+        };
+        assert!(policy.check(&[(3, 1)], &USD, 1).is_ok());
+    }
+}
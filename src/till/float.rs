@@ -0,0 +1,154 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use super::{Till, TillCommand};
+use crate::currency::{Currency, Denomination};
+use crate::money::Cents;
+
+/// How deep a day of sales draws into each denomination.
+///
+/// Feed it every sale (and cash drop or pickup) in order; for each
+/// denomination it tracks the running net outflow and its peak, which is the
+/// smallest opening count that would never have run dry.
+#[derive(Debug, Clone, Default)]
+pub struct FloatDemand {
+    net: BTreeMap<u32, i64>,
+    peak: BTreeMap<u32, u32>,
+}
+
+/// One line of a bank order: whole bundles of a denomination.
+#[derive(Debug, Clone)]
+pub struct OrderLine {
+    pub denomination: Denomination,
+    pub bundles: u32,
+}
+
+impl OrderLine {
+    pub fn pieces(&self) -> u64 {
+        u64::from(self.bundles) * u64::from(self.denomination.bundle)
+    }
+
+    pub fn value(&self) -> Cents {
+        self.denomination.value() * self.bundles * self.denomination.bundle
+    }
+}
+
+impl FloatDemand {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a sale: tendered cash goes in first, then change (in
+    /// `currency`) comes out.
+    pub fn record_sale(
+        &mut self,
+        tendered: &[(u32, u32)],
+        change: &[(usize, u32)],
+        currency: &Currency,
+    ) {
+        for &(cents, count) in tendered {
+            self.move_pieces(cents, -i64::from(count));
+        }
+        for (denomination, count) in currency.pieces(change) {
+            self.move_pieces(denomination.cents, i64::from(count));
+        }
+    }
+
+    /// Record a cash drop (`till add`) or pickup (`till remove`).
+    pub fn record_command(&mut self, command: TillCommand) {
+        match command {
+            TillCommand::Add { cents, count } => self.move_pieces(cents, -i64::from(count)),
+            TillCommand::Remove { cents, count } => self.move_pieces(cents, i64::from(count)),
+        }
+    }
+
+    /// The most of `cents` the drawer had to have on hand at once.
+    pub fn peak(&self, cents: u32) -> u32 {
+        self.peak.get(&cents).copied().unwrap_or(0)
+    }
+
+    /// Whole bundles to order from the bank, on top of what `on_hand`
+    /// already holds, so the day's peak draw never empties a slot. Lists
+    /// every denomination of `currency`, largest first.
+    pub fn order(&self, currency: &Currency, on_hand: &Till) -> Vec<OrderLine> {
+        currency
+            .denominations
+            .iter()
+            .map(|denomination| {
+                let short = self
+                    .peak(denomination.cents)
+                    .saturating_sub(on_hand.count(denomination.cents));
+                OrderLine {
+                    denomination: denomination.clone(),
+                    bundles: short.div_ceil(denomination.bundle),
+                }
+            })
+            .collect()
+    }
+
+    fn move_pieces(&mut self, cents: u32, out: i64) {
+        let net = self.net.entry(cents).or_insert(0);
+        *net += out;
+        if *net > 0 {
+            let peak = self.peak.entry(cents).or_insert(0);
+            *peak = (*peak).max(u32::try_from(*net).unwrap_or(u32::MAX));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::USD;
+    use crate::till::parse_till;
+    use alloc::vec;
+
+    fn denom(cents: u32) -> usize {
+        USD.index_of(cents).unwrap()
+    }
+
+    #[test]
+    fn peak_is_the_deepest_running_draw() {
+        let mut demand = FloatDemand::new();
+        demand.record_sale(&[], &[(denom(25), 3)], &USD);
+        demand.record_sale(&[(25, 4)], &[(denom(25), 2)], &USD);
+        demand.record_sale(&[], &[(denom(25), 5)], &USD);
+        // Running draw: 3, 3 - 4 + 2 = 1, 6.
+        assert_eq!(demand.peak(25), 6);
+        assert_eq!(demand.peak(10), 0);
+    }
+
+    #[test]
+    fn tendered_cash_is_credited_before_change() {
+        let mut demand = FloatDemand::new();
+        demand.record_sale(&[(100, 1)], &[(denom(100), 1)], &USD);
+        assert_eq!(demand.peak(100), 0);
+    }
+
+    #[test]
+    fn drops_and_pickups_move_the_draw() {
+        let mut demand = FloatDemand::new();
+        demand.record_command(TillCommand::Remove { cents: 1, count: 7 });
+        demand.record_command(TillCommand::Add {
+            cents: 1,
+            count: 10,
+        });
+        demand.record_sale(&[], &[(denom(1), 5)], &USD);
+        assert_eq!(demand.peak(1), 7);
+    }
+
+    #[test]
+    fn order_rounds_up_to_whole_bundles_net_of_on_hand() {
+        let mut demand = FloatDemand::new();
+        demand.record_sale(&[], &[(denom(25), 57), (denom(1), 50)], &USD);
+        demand.record_sale(&[], &[(denom(100), 3)], &USD);
+        let on_hand = parse_till("0.01,60\n", &USD).unwrap();
+
+        let order = demand.order(&USD, &on_hand);
+        let bundles: Vec<u32> = order.iter().map(|line| line.bundles).collect();
+        // Dollars: 3 of a 100 strap; quarters: 57 of 40-rolls; pennies covered.
+        assert_eq!(bundles, vec![1, 2, 0, 0, 0]);
+        assert_eq!(order[1].pieces(), 80);
+        assert_eq!(order[1].value(), Cents(2000));
+    }
+}
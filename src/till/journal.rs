@@ -0,0 +1,323 @@
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use super::{DenominationCount, Till};
+use crate::currency::Currency;
+#[cfg(feature = "signing")]
+use crate::signing::Signer;
+
+/// A till mutation to journal, with the pieces it moved as `(cents, count)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalEvent<'a> {
+    /// A cash sale: tendered cash in, change out.
+    Sale {
+        tendered: &'a [(u32, u32)],
+        change: &'a [(u32, u32)],
+    },
+    /// A refund: cash paid out, nothing taken in.
+    Refund { paid_out: &'a [(u32, u32)] },
+    /// A cash drop (`till add`).
+    Restock { cents: u32, count: u32 },
+    /// A pickup (`till remove`).
+    Pickup { cents: u32, count: u32 },
+    /// A voided sale: its change back in, its tendered cash back out.
+    Void {
+        tendered: &'a [(u32, u32)],
+        change: &'a [(u32, u32)],
+    },
+}
+
+impl JournalEvent<'_> {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::Sale { .. } => "sale",
+            Self::Refund { .. } => "refund",
+            Self::Restock { .. } => "restock",
+            Self::Pickup { .. } => "pickup",
+            Self::Void { .. } => "void",
+        }
+    }
+}
+
+/// One line of the audit journal.
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalEntry<'a> {
+    /// When the entry was written, as an RFC 3339 UTC timestamp.
+    pub timestamp: String,
+    /// Input line that caused the mutation.
+    pub line: usize,
+    /// Register whose drawer changed (`""` for the default register).
+    pub register: &'a str,
+    /// `sale`, `refund`, `restock`, `pickup`, or `void`.
+    pub event: &'static str,
+    /// Pieces that went into the drawer.
+    #[serde(rename = "in")]
+    pub pieces_in: Vec<DenominationCount>,
+    /// Pieces that came out of the drawer.
+    #[serde(rename = "out")]
+    pub pieces_out: Vec<DenominationCount>,
+    /// Total value in the drawer afterwards, in cents.
+    pub balance_cents: u64,
+    /// Count of every denomination in the drawer afterwards.
+    pub counts: Vec<DenominationCount>,
+    /// Pieces to skim from slots now over capacity; omitted when none are.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skim: Vec<DenominationCount>,
+}
+
+/// Append-only audit trail of till mutations, one JSON object per line.
+///
+/// Entries are written (and flushed) as they happen and never rewritten, so
+/// the journal survives a crash mid-run and can be diffed against the
+/// drawer's closing counts.
+pub struct Journal<W: Write> {
+    out: W,
+    #[cfg(feature = "signing")]
+    signer: Option<Signer>,
+}
+
+impl<W: Write> Journal<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            #[cfg(feature = "signing")]
+            signer: None,
+        }
+    }
+
+    /// Sign each entry with `signer`, as `--sign-key` does. Pass
+    /// [`Signer::after`] the journal's last line to chain onto the runs
+    /// already in it.
+    #[cfg(feature = "signing")]
+    pub fn signed(mut self, signer: Signer) -> Self {
+        self.signer = Some(signer);
+        self
+    }
+
+    /// Write the record ending a signed journal's run, once it has any
+    /// entries. Does nothing for an unsigned journal.
+    #[cfg(feature = "signing")]
+    pub fn finish(&mut self) -> io::Result<()> {
+        match self.signer.as_mut().and_then(Signer::end) {
+            Some(end) => {
+                self.out.write_all(end.as_bytes())?;
+                self.out.write_all(b"\n")?;
+                self.out.flush()
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Record a mutation of `till`, which must already reflect it.
+    pub fn record(
+        &mut self,
+        line: usize,
+        register: &str,
+        event: JournalEvent,
+        till: &Till,
+        currency: &Currency,
+    ) -> io::Result<()> {
+        let slots = |pieces: &[(u32, u32)]| -> Vec<DenominationCount> {
+            pieces
+                .iter()
+                .map(|&(cents, count)| DenominationCount { cents, count })
+                .collect()
+        };
+        let (pieces_in, pieces_out) = match event {
+            JournalEvent::Sale { tendered, change } => (slots(tendered), slots(change)),
+            JournalEvent::Refund { paid_out } => (Vec::new(), slots(paid_out)),
+            JournalEvent::Restock { cents, count } => (slots(&[(cents, count)]), Vec::new()),
+            JournalEvent::Pickup { cents, count } => (Vec::new(), slots(&[(cents, count)])),
+            JournalEvent::Void { tendered, change } => (slots(change), slots(tendered)),
+        };
+
+        let entry = JournalEntry {
+            timestamp: rfc3339_utc(SystemTime::now()),
+            line,
+            register,
+            event: event.name(),
+            pieces_in,
+            pieces_out,
+            balance_cents: till.total_cents().0,
+            counts: currency
+                .denominations
+                .iter()
+                .map(|d| DenominationCount {
+                    cents: d.cents,
+                    count: till.count(d.cents),
+                })
+                .collect(),
+            skim: till
+                .skim(currency)
+                .iter()
+                .map(|s| DenominationCount {
+                    cents: s.denomination.cents,
+                    count: s.count,
+                })
+                .collect(),
+        };
+
+        #[cfg(feature = "signing")]
+        if let Some(signer) = &mut self.signer {
+            let json = serde_json::to_string(&entry)?;
+            self.out.write_all(signer.sign(&json).as_bytes())?;
+            self.out.write_all(b"\n")?;
+            return self.out.flush();
+        }
+        serde_json::to_writer(&mut self.out, &entry)?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()
+    }
+}
+
+/// Format a time as `YYYY-MM-DDTHH:MM:SSZ` without pulling in a date crate.
+///
+/// Uses Howard Hinnant's days-to-civil algorithm.
+pub fn rfc3339_utc(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3_600,
+        rem % 3_600 / 60,
+        rem % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::USD;
+    use crate::till::parse_till;
+    use std::time::Duration;
+
+    #[test]
+    fn timestamps_are_rfc3339() {
+        assert_eq!(rfc3339_utc(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        let t = UNIX_EPOCH + Duration::from_secs(951_827_696);
+        assert_eq!(rfc3339_utc(t), "2000-02-29T12:34:56Z");
+        let t = UNIX_EPOCH + Duration::from_secs(1_792_108_800);
+        assert_eq!(rfc3339_utc(t), "2026-10-16T00:00:00Z");
+    }
+
+    #[test]
+    fn record_appends_one_json_line_per_mutation() {
+        let till = parse_till("1.00,2\n0.25,3\n", &USD).unwrap();
+        let mut buf = Vec::new();
+        {
+            let mut journal = Journal::new(&mut buf);
+            let sale = JournalEvent::Sale {
+                tendered: &[(100, 1)],
+                change: &[(25, 1)],
+            };
+            journal.record(3, "R1", sale, &till, &USD).unwrap();
+            let restock = JournalEvent::Restock {
+                cents: 25,
+                count: 4,
+            };
+            journal.record(4, "", restock, &till, &USD).unwrap();
+        }
+
+        let text = String::from_utf8(buf).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+
+        assert_eq!(lines[0]["line"], 3);
+        assert_eq!(lines[0]["register"], "R1");
+        assert_eq!(lines[0]["event"], "sale");
+        assert_eq!(lines[0]["in"][0]["cents"], 100);
+        assert_eq!(lines[0]["out"][0]["count"], 1);
+        assert_eq!(lines[0]["balance_cents"], 275);
+        assert_eq!(lines[0]["counts"].as_array().unwrap().len(), 5);
+        assert!(lines[0]["timestamp"].as_str().unwrap().ends_with('Z'));
+
+        assert_eq!(lines[1]["event"], "restock");
+        assert_eq!(lines[1]["out"], serde_json::json!([]));
+        assert!(lines[1].get("skim").is_none());
+    }
+
+    #[test]
+    fn refunds_are_journaled_as_refunds() {
+        let till = parse_till("1.00,2\n0.25,3\n", &USD).unwrap();
+        let mut buf = Vec::new();
+        let refund = JournalEvent::Refund {
+            paid_out: &[(100, 1), (25, 1)],
+        };
+        Journal::new(&mut buf)
+            .record(5, "", refund, &till, &USD)
+            .unwrap();
+        let entry: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(entry["event"], "refund");
+        assert_eq!(entry["in"], serde_json::json!([]));
+        assert_eq!(
+            entry["out"],
+            serde_json::json!([{"cents": 100, "count": 1}, {"cents": 25, "count": 1}])
+        );
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn signed_entries_verify() {
+        let till = parse_till("1.00,2\n", &USD).unwrap();
+        let mut buf = Vec::new();
+        for run in 0..2 {
+            let signer = match String::from_utf8_lossy(&buf).lines().last() {
+                Some(last) => Signer::after(b"key", last).unwrap(),
+                None => Signer::new(b"key"),
+            };
+            let mut journal = Journal::new(&mut buf).signed(signer);
+            for cents in [100, 25] {
+                let restock = JournalEvent::Restock { cents, count: 1 };
+                journal.record(run, "", restock, &till, &USD).unwrap();
+            }
+            journal.finish().unwrap();
+        }
+
+        let text = String::from_utf8(buf).unwrap();
+        let mut verifier = crate::signing::Verifier::new(b"key");
+        for line in text.lines() {
+            let (json, _) = line.split_once('\t').unwrap();
+            if !json.starts_with("#chain") {
+                assert!(serde_json::from_str::<serde_json::Value>(json).is_ok());
+            }
+            assert_eq!(verifier.check(line), None);
+        }
+        let report = verifier.finish();
+        assert!(report.is_clean());
+        assert_eq!(report.lines, 8);
+    }
+
+    #[test]
+    fn record_suggests_skim_for_overfull_slots() {
+        let till = parse_till("0.25,45,40\n", &USD).unwrap();
+        let mut buf = Vec::new();
+        let restock = JournalEvent::Restock {
+            cents: 25,
+            count: 10,
+        };
+        Journal::new(&mut buf)
+            .record(1, "", restock, &till, &USD)
+            .unwrap();
+        let entry: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(
+            entry["skim"],
+            serde_json::json!([{"cents": 25, "count": 40}])
+        );
+    }
+}
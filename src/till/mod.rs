@@ -0,0 +1,1125 @@
+mod float;
+#[cfg(feature = "std")]
+mod journal;
+mod registers;
+#[cfg(feature = "std")]
+mod shared;
+
+pub use float::{FloatDemand, OrderLine};
+#[cfg(feature = "std")]
+pub use journal::{rfc3339_utc, Journal, JournalEntry, JournalEvent};
+pub use registers::{DenominationCount, Registers, TillState, STATE_VERSION};
+#[cfg(feature = "std")]
+pub use shared::SharedTill;
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use tracing::{debug, debug_span};
+
+use crate::currency::{Currency, Denomination};
+use crate::error::CashRegisterError;
+use crate::money::Cents;
+use crate::parse::parse_value_cents;
+use crate::strategy::breakdown::total;
+use crate::strategy::Breakdown;
+
+/// A simulated cash drawer: how many of each denomination are on hand.
+///
+/// Counts are keyed by denomination value in cents, so a till is only
+/// meaningful alongside the currency it was loaded for.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Till {
+    counts: BTreeMap<u32, u32>,
+    ran_dry: BTreeSet<u32>,
+    ious: Vec<Iou>,
+    capacity: BTreeMap<u32, u32>,
+}
+
+/// Pieces to move from an overfull slot to the safe.
+#[derive(Debug, Clone)]
+pub struct Skim {
+    pub denomination: Denomination,
+    pub count: u32,
+}
+
+/// A denomination where a counted drawer doesn't match the expected one.
+#[derive(Debug, Clone)]
+pub struct Discrepancy {
+    pub denomination: Denomination,
+    pub expected: u32,
+    pub counted: u32,
+}
+
+impl Discrepancy {
+    /// Counted minus expected value: positive when the drawer is over.
+    pub fn over_cents(&self) -> i64 {
+        (i64::from(self.counted) - i64::from(self.expected))
+            .saturating_mul(i64::from(self.denomination.cents))
+    }
+}
+
+/// Change the drawer couldn't hand over, recorded as owed to the customer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Iou {
+    /// Input line of the sale.
+    pub line: usize,
+    pub cents: u32,
+}
+
+/// The till could not cover a breakdown: too few of one denomination.
+#[derive(Debug, Clone)]
+pub struct Shortage {
+    pub denomination: Denomination,
+    pub needed: u32,
+    pub available: u32,
+}
+
+/// The till can't take more of a denomination: its count would pass
+/// `u32::MAX`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Overflow {
+    pub cents: u32,
+    pub on_hand: u32,
+    pub adding: u32,
+}
+
+/// What to do when the till can't cover the breakdown a strategy produced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ShortagePolicy {
+    /// Fail the transaction and leave the drawer untouched.
+    #[default]
+    Error,
+    /// Hand over as much as the drawer allows without exceeding the change
+    /// due, and report the shortfall.
+    Partial,
+    /// Make the same amount from other denominations; if no exact mix is on
+    /// hand, round to the nearest amount that is (ties favor the customer).
+    Substitute,
+}
+
+impl core::str::FromStr for ShortagePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(Self::Error),
+            "partial" => Ok(Self::Partial),
+            "substitute" => Ok(Self::Substitute),
+            other => Err(format!(
+                "unknown shortage policy \"{other}\" (expected error, partial, or substitute)"
+            )),
+        }
+    }
+}
+
+/// Change actually handed over under a shortage policy.
+#[derive(Debug, Clone)]
+pub struct Dispensed {
+    pub breakdown: Breakdown,
+    /// Change due minus change given: positive when the customer was
+    /// short-changed, negative when rounding favored them, zero when exact.
+    pub shortfall_cents: i64,
+}
+
+/// A cash drop or pickup marked in the input (`till add 0.25,40`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TillCommand {
+    Add { cents: u32, count: u32 },
+    Remove { cents: u32, count: u32 },
+}
+
+impl Overflow {
+    /// Attach the input line that overfilled the slot.
+    pub fn at_line(self, line: usize, currency: &Currency) -> CashRegisterError {
+        let denomination = match find_denomination(currency, self.cents) {
+            Some(d) => d.plural.to_string(),
+            None => format!("{}.{:02} pieces", self.cents / 100, self.cents % 100),
+        };
+        CashRegisterError::TillOverflow {
+            line,
+            denomination,
+            on_hand: self.on_hand,
+            adding: self.adding,
+        }
+    }
+}
+
+impl Shortage {
+    /// Attach the input line that triggered the shortage.
+    pub fn at_line(self, line: usize) -> CashRegisterError {
+        CashRegisterError::TillShortage {
+            line,
+            denomination: self.denomination,
+            needed: self.needed,
+            available: self.available,
+        }
+    }
+}
+
+impl Till {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many of the denomination worth `cents` are in the drawer.
+    pub fn count(&self, cents: u32) -> u32 {
+        self.counts.get(&cents).copied().unwrap_or(0)
+    }
+
+    /// Put `count` more of the denomination worth `cents` into the drawer.
+    /// Fails, leaving the drawer as it was, if the count would overflow.
+    pub fn add(&mut self, cents: u32, count: u32) -> Result<(), Overflow> {
+        let slot = self.counts.entry(cents).or_insert(0);
+        *slot = slot.checked_add(count).ok_or(Overflow {
+            cents,
+            on_hand: *slot,
+            adding: count,
+        })?;
+        Ok(())
+    }
+
+    /// Take `count` of the denomination worth `cents` out of the drawer.
+    pub fn remove(&mut self, denomination: Denomination, count: u32) -> Result<(), Shortage> {
+        let available = self.count(denomination.cents);
+        if available < count {
+            return Err(Shortage {
+                denomination,
+                needed: count,
+                available,
+            });
+        }
+        self.counts.insert(denomination.cents, available - count);
+        Ok(())
+    }
+
+    /// Apply a restock or pickup directive from input line `line`.
+    pub fn apply(
+        &mut self,
+        command: TillCommand,
+        currency: &Currency,
+        line: usize,
+    ) -> Result<(), CashRegisterError> {
+        let (TillCommand::Add { cents, count } | TillCommand::Remove { cents, count }) = command;
+        debug!(line, ?command, "till directive");
+        let denomination = find_denomination(currency, cents).ok_or_else(|| {
+            CashRegisterError::UnknownDenomination {
+                line,
+                input: format!("{}.{:02}", cents / 100, cents % 100),
+                currency: currency.name.to_string(),
+            }
+        })?;
+
+        match command {
+            TillCommand::Add { .. } => self
+                .add(cents, count)
+                .map_err(|overflow| overflow.at_line(line, currency)),
+            TillCommand::Remove { .. } => self
+                .remove(denomination, count)
+                .map_err(|shortage| shortage.at_line(line)),
+        }
+    }
+
+    /// Remove a breakdown's worth of coins and bills from the drawer.
+    ///
+    /// All-or-nothing: if any denomination is short, nothing is removed.
+    pub fn dispense(
+        &mut self,
+        breakdown: &[(usize, u32)],
+        currency: &Currency,
+    ) -> Result<(), Shortage> {
+        for (denomination, needed) in currency.pieces(breakdown) {
+            let available = self.count(denomination.cents);
+            if available < needed {
+                return Err(Shortage {
+                    denomination: denomination.clone(),
+                    needed,
+                    available,
+                });
+            }
+        }
+
+        for (denomination, count) in currency.pieces(breakdown) {
+            let slot = self.counts.entry(denomination.cents).or_insert(0);
+            *slot -= count;
+            if *slot == 0 {
+                self.ran_dry.insert(denomination.cents);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Settle a cash sale on input line `line`: credit the customer's tendered
+    /// cash, then dispense change under `policy`. If change can't be made, the
+    /// tendered cash is handed back and the drawer is left as it was.
+    pub fn settle(
+        &mut self,
+        tendered: &[(u32, u32)],
+        breakdown: &[(usize, u32)],
+        currency: &Currency,
+        policy: ShortagePolicy,
+        line: usize,
+    ) -> Result<Dispensed, CashRegisterError> {
+        let _span = debug_span!("settle", line).entered();
+        for &(cents, _) in tendered {
+            if find_denomination(currency, cents).is_none() {
+                return Err(CashRegisterError::UnknownDenomination {
+                    line,
+                    input: format!("{}.{:02}", cents / 100, cents % 100),
+                    currency: currency.name.to_string(),
+                });
+            }
+        }
+
+        for (i, &(cents, count)) in tendered.iter().enumerate() {
+            if let Err(overflow) = self.add(cents, count) {
+                self.take_back(&tendered[..i]);
+                return Err(overflow.at_line(line, currency));
+            }
+        }
+
+        self.dispense_with(breakdown, currency, policy)
+            .map_err(|shortage| {
+                self.take_back(tendered);
+                shortage.at_line(line)
+            })
+    }
+
+    /// Reverse the sale settled on input line `sale`, for a void on line
+    /// `line`: the change it handed over, `breakdown`, goes back into the
+    /// drawer, the `tendered` cash goes back out, and its IOU, if it wrote
+    /// one, is dropped.
+    ///
+    /// All-or-nothing: fails, leaving the drawer as it was, if the tendered
+    /// pieces have since gone out as change.
+    pub fn void(
+        &mut self,
+        sale: usize,
+        tendered: &[(u32, u32)],
+        breakdown: &[(usize, u32)],
+        currency: &Currency,
+        line: usize,
+    ) -> Result<(), CashRegisterError> {
+        let _span = debug_span!("void", sale, line).entered();
+        let mut net: BTreeMap<u32, i64> = BTreeMap::new();
+        for (denomination, count) in currency.pieces(breakdown) {
+            *net.entry(denomination.cents).or_default() += i64::from(count);
+        }
+        for &(cents, count) in tendered {
+            *net.entry(cents).or_default() -= i64::from(count);
+        }
+
+        for (&cents, &delta) in &net {
+            let on_hand = self.count(cents);
+            let after = i64::from(on_hand) + delta;
+            if after < 0 {
+                let denomination = find_denomination(currency, cents)
+                    .expect("tendered pieces were checked when the sale was settled");
+                return Err(Shortage {
+                    denomination,
+                    needed: u32::try_from(-delta).unwrap_or(u32::MAX),
+                    available: on_hand,
+                }
+                .at_line(line));
+            }
+            if u32::try_from(after).is_err() {
+                return Err(Overflow {
+                    cents,
+                    on_hand,
+                    adding: u32::try_from(delta).unwrap_or(u32::MAX),
+                }
+                .at_line(line, currency));
+            }
+        }
+
+        for (cents, delta) in net {
+            let slot = self.counts.entry(cents).or_insert(0);
+            *slot = u32::try_from(i64::from(*slot) + delta).expect("checked above");
+        }
+        self.ious.retain(|iou| iou.line != sale);
+        Ok(())
+    }
+
+    /// Hand back tendered cash that was just added.
+    pub(crate) fn take_back(&mut self, tendered: &[(u32, u32)]) {
+        for &(cents, count) in tendered {
+            *self.counts.entry(cents).or_insert(0) -= count;
+        }
+    }
+
+    /// Dispense a breakdown, falling back to `policy` if the drawer can't cover it.
+    ///
+    /// Only `ShortagePolicy::Error` can fail; the others always find something
+    /// to hand over, even if that is nothing at all.
+    pub fn dispense_with(
+        &mut self,
+        breakdown: &[(usize, u32)],
+        currency: &Currency,
+        policy: ShortagePolicy,
+    ) -> Result<Dispensed, Shortage> {
+        let shortage = match self.dispense(breakdown, currency) {
+            Ok(()) => {
+                return Ok(Dispensed {
+                    breakdown: breakdown.into(),
+                    shortfall_cents: 0,
+                })
+            }
+            Err(shortage) => shortage,
+        };
+        debug!(
+            denomination = %shortage.denomination.plural,
+            needed = shortage.needed,
+            available = shortage.available,
+            ?policy,
+            "drawer short"
+        );
+
+        // Inventory searches go up to u32::MAX cents, which is all a single
+        // transaction's change can be.
+        let Ok(due) = u32::try_from(total(breakdown, currency).0) else {
+            return Err(shortage);
+        };
+        let on_hand = self.total_cents().0;
+        let amount = match policy {
+            ShortagePolicy::Error => return Err(shortage),
+            // Nothing else comes as close as the whole drawer, or goes over.
+            _ if u64::from(due) >= on_hand => on_hand as u32,
+            ShortagePolicy::Partial => {
+                let reach = reachable(due as usize, &self.slots(currency));
+                (0..=due)
+                    .rev()
+                    .find(|&amount| reach[amount as usize])
+                    .expect("nothing at all is always reachable")
+            }
+            ShortagePolicy::Substitute => {
+                // Going over by more than `due` is further off than nothing.
+                let limit = on_hand.min(2 * u64::from(due)) as usize;
+                let reach = reachable(limit, &self.slots(currency));
+                let reached = |amount: u64| reach.get(amount as usize).copied().unwrap_or(false);
+                (0..=u64::from(due))
+                    .find_map(|delta| {
+                        let over = u64::from(due) + delta;
+                        let under = u64::from(due) - delta;
+                        [over, under].into_iter().find(|&amount| reached(amount))
+                    })
+                    .expect("nothing at all is always reachable") as u32
+            }
+        };
+        let breakdown = self
+            .change_from_inventory(amount, currency)
+            .expect("the amount was reachable");
+        let shortfall_cents = i64::from(due) - i64::from(amount);
+        debug!(shortfall_cents, "dispensed under shortage policy");
+        self.dispense(&breakdown, currency)
+            .expect("breakdown was built from inventory on hand");
+        Ok(Dispensed {
+            breakdown,
+            shortfall_cents,
+        })
+    }
+
+    /// Each of `currency`'s denominations, largest first, with how many
+    /// are on hand, as `(cents, count)`.
+    fn slots(&self, currency: &Currency) -> Vec<(u32, u32)> {
+        currency
+            .denominations
+            .iter()
+            .map(|d| (d.cents, self.count(d.cents)))
+            .collect()
+    }
+
+    /// Make exactly `cents` from what is in the drawer, if any mix can.
+    fn change_from_inventory(&self, cents: u32, currency: &Currency) -> Option<Breakdown> {
+        let counts = bounded_change(cents, &self.slots(currency))?;
+
+        Some(
+            counts
+                .into_iter()
+                .enumerate()
+                .filter(|&(_, count)| count > 0)
+                .collect(),
+        )
+    }
+
+    /// Total value of everything in the drawer.
+    pub fn total_cents(&self) -> Cents {
+        self.counts
+            .iter()
+            .map(|(&cents, &count)| Cents::from(cents) * count)
+            .sum()
+    }
+
+    /// Denomination values (in cents) that were emptied by a dispense, largest first.
+    pub fn ran_dry(&self) -> impl Iterator<Item = u32> + '_ {
+        self.ran_dry.iter().rev().copied()
+    }
+
+    /// Note that the customer on input line `line` is owed `cents` of change.
+    pub fn record_iou(&mut self, line: usize, cents: u32) {
+        self.ious.push(Iou { line, cents });
+    }
+
+    /// IOUs written this run, in the order they were recorded.
+    pub fn ious(&self) -> &[Iou] {
+        &self.ious
+    }
+
+    /// Limit how many of the denomination worth `cents` the slot holds.
+    ///
+    /// Deposits are never refused for lack of room; an overfull slot shows
+    /// up in `skim` instead.
+    pub fn set_capacity(&mut self, cents: u32, capacity: u32) {
+        self.capacity.insert(cents, capacity);
+    }
+
+    pub fn capacity(&self, cents: u32) -> Option<u32> {
+        self.capacity.get(&cents).copied()
+    }
+
+    /// An empty drawer with the same slot capacities.
+    pub fn emptied(&self) -> Till {
+        Till {
+            capacity: self.capacity.clone(),
+            ..Till::default()
+        }
+    }
+
+    /// Compare a physical count of the drawer against what the till expects,
+    /// largest denomination first. Empty when they match.
+    pub fn reconcile(&self, counted: &Till, currency: &Currency) -> Vec<Discrepancy> {
+        currency
+            .denominations
+            .iter()
+            .filter_map(|denomination| {
+                let expected = self.count(denomination.cents);
+                let counted = counted.count(denomination.cents);
+                (expected != counted).then(|| Discrepancy {
+                    denomination: denomination.clone(),
+                    expected,
+                    counted,
+                })
+            })
+            .collect()
+    }
+
+    /// What to skim from each overfull slot, largest denomination first.
+    ///
+    /// Suggests whole bank bundles (enough to get back under capacity) when
+    /// the slot holds that many, and otherwise just the excess.
+    pub fn skim(&self, currency: &Currency) -> Vec<Skim> {
+        currency
+            .denominations
+            .iter()
+            .filter_map(|denomination| {
+                let capacity = self.capacity(denomination.cents)?;
+                let on_hand = self.count(denomination.cents);
+                let excess = on_hand.checked_sub(capacity).filter(|&e| e > 0)?;
+                let bundled = excess.div_ceil(denomination.bundle) * denomination.bundle;
+                let count = if bundled <= on_hand { bundled } else { excess };
+                Some(Skim {
+                    denomination: denomination.clone(),
+                    count,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Parse a till file: one `value,count` line per denomination (e.g. `0.25,40`),
+/// optionally followed by the slot's capacity (`0.25,40,120`).
+///
+/// Blank lines are skipped. Every value must be a denomination of `currency`;
+/// denominations that aren't listed start at zero.
+pub fn parse_till(input: &str, currency: &Currency) -> Result<Till, CashRegisterError> {
+    let mut till = Till::new();
+
+    for (i, line) in input.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (value_str, count_str) =
+            line.split_once(',')
+                .ok_or_else(|| CashRegisterError::MalformedLine {
+                    line: line_number,
+                    detail: format!("expected \"value,count\" but got \"{line}\""),
+                })?;
+
+        let cents =
+            parse_value_cents(value_str).map_err(|reason| CashRegisterError::InvalidAmount {
+                line: line_number,
+                input: value_str.trim().to_string(),
+                reason,
+            })?;
+
+        if find_denomination(currency, cents).is_none() {
+            return Err(CashRegisterError::UnknownDenomination {
+                line: line_number,
+                input: value_str.trim().to_string(),
+                currency: currency.name.to_string(),
+            });
+        }
+
+        let (count_str, capacity_str) = match count_str.split_once(',') {
+            Some((count, capacity)) => (count, Some(capacity.trim())),
+            None => (count_str, None),
+        };
+
+        let count: u32 =
+            count_str
+                .trim()
+                .parse()
+                .map_err(|_| CashRegisterError::MalformedLine {
+                    line: line_number,
+                    detail: format!("invalid count \"{}\"", count_str.trim()),
+                })?;
+
+        till.add(cents, count)
+            .map_err(|overflow| overflow.at_line(line_number, currency))?;
+
+        if let Some(capacity_str) = capacity_str {
+            let capacity = capacity_str
+                .parse()
+                .map_err(|_| CashRegisterError::MalformedLine {
+                    line: line_number,
+                    detail: format!("invalid capacity \"{capacity_str}\""),
+                })?;
+            till.set_capacity(cents, capacity);
+        }
+    }
+
+    Ok(till)
+}
+
+/// Find counts (parallel to `slots`, each `(value, on hand)`, largest first)
+/// that sum exactly to `amount`. Larger denominations are tried first, so the
+/// first solution found uses few pieces.
+///
+/// Depth-first search, pruned whenever the remaining slots can't reach the
+/// amount or can't hit it modulo their GCD (e.g. odd cents with no pennies).
+fn bounded_change(amount: u32, slots: &[(u32, u32)]) -> Option<Vec<u32>> {
+    fn gcd(a: u32, b: u32) -> u32 {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+
+    fn search(amount: u32, slots: &[(u32, u32)], counts: &mut Vec<u32>) -> bool {
+        if amount == 0 {
+            counts.resize(counts.len() + slots.len(), 0);
+            return true;
+        }
+        let Some((&(value, on_hand), rest)) = slots.split_first() else {
+            return false;
+        };
+
+        let reachable: u64 = slots
+            .iter()
+            .map(|&(v, c)| u64::from(v) * u64::from(c))
+            .sum();
+        let step = slots
+            .iter()
+            .filter(|&&(_, c)| c > 0)
+            .fold(0, |g, &(v, _)| gcd(g, v));
+        if reachable < u64::from(amount) || step == 0 || !amount.is_multiple_of(step) {
+            return false;
+        }
+
+        for count in (0..=on_hand.min(amount / value)).rev() {
+            counts.push(count);
+            if search(amount - count * value, rest, counts) {
+                return true;
+            }
+            counts.pop();
+        }
+        false
+    }
+
+    let mut counts = Vec::with_capacity(slots.len());
+    search(amount, slots, &mut counts).then_some(counts)
+}
+
+/// Which amounts from 0 to `limit` cents some mix of `slots` (each `(value,
+/// on hand)`) makes exactly, indexed by amount.
+///
+/// A bounded-knapsack pass, O(`limit`) per denomination: an amount is newly
+/// reachable with a denomination when the amount one piece less was, using
+/// fewer of that denomination than are on hand.
+fn reachable(limit: usize, slots: &[(u32, u32)]) -> Vec<bool> {
+    let mut reach = vec![false; limit + 1];
+    reach[0] = true;
+    let mut used = vec![0u32; limit + 1];
+    for &(value, on_hand) in slots {
+        let value = value as usize;
+        if on_hand == 0 || value == 0 || value > limit {
+            continue;
+        }
+        used.fill(0);
+        for amount in value..=limit {
+            let from = amount - value;
+            if !reach[amount] && reach[from] && used[from] < on_hand {
+                reach[amount] = true;
+                used[amount] = used[from] + 1;
+            }
+        }
+    }
+    reach
+}
+
+pub(crate) fn find_denomination(currency: &Currency, cents: u32) -> Option<Denomination> {
+    currency
+        .denominations
+        .iter()
+        .find(|d| d.cents == cents)
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::{EUR, USD};
+
+    fn denom(cents: u32) -> usize {
+        USD.index_of(cents).unwrap()
+    }
+
+    #[test]
+    fn parse_till_file() {
+        let till = parse_till("1.00,10\n0.25,40\n\n0.01,50\n", &USD).unwrap();
+        assert_eq!(till.count(100), 10);
+        assert_eq!(till.count(25), 40);
+        assert_eq!(till.count(10), 0);
+        assert_eq!(till.count(1), 50);
+        assert_eq!(till.total_cents(), Cents(1000 + 1000 + 50));
+    }
+
+    #[test]
+    fn parse_till_rejects_unknown_denomination() {
+        let result = parse_till("0.25,40\n0.03,10\n", &USD);
+        match result {
+            Err(CashRegisterError::UnknownDenomination { line, .. }) => assert_eq!(line, 2),
+            other => panic!("expected UnknownDenomination, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_till_rejects_bad_count() {
+        assert!(matches!(
+            parse_till("0.25,many\n", &USD),
+            Err(CashRegisterError::MalformedLine { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn parse_till_eur_two_cent_coins() {
+        let till = parse_till("0.02,25\n", &EUR).unwrap();
+        assert_eq!(till.count(2), 25);
+    }
+
+    #[test]
+    fn dispense_deducts_counts() {
+        let mut till = parse_till("0.25,10\n0.10,10\n0.01,10\n", &USD).unwrap();
+        till.dispense(&[(denom(25), 3), (denom(10), 1), (denom(1), 3)], &USD)
+            .unwrap();
+        assert_eq!(till.count(25), 7);
+        assert_eq!(till.count(10), 9);
+        assert_eq!(till.count(1), 7);
+        assert_eq!(till.ran_dry().count(), 0);
+    }
+
+    #[test]
+    fn dispense_shortage_leaves_till_untouched() {
+        let mut till = parse_till("0.25,10\n0.01,2\n", &USD).unwrap();
+        let before = till.clone();
+
+        let shortage = till
+            .dispense(&[(denom(25), 3), (denom(1), 3)], &USD)
+            .unwrap_err();
+        assert_eq!(shortage.denomination.cents, 1);
+        assert_eq!(shortage.needed, 3);
+        assert_eq!(shortage.available, 2);
+        assert_eq!(till, before);
+    }
+
+    #[test]
+    fn dispense_records_denominations_that_ran_dry() {
+        let mut till = parse_till("1.00,1\n0.25,4\n0.01,10\n", &USD).unwrap();
+        till.dispense(&[(denom(100), 1), (denom(1), 1)], &USD)
+            .unwrap();
+        till.dispense(&[(denom(25), 4)], &USD).unwrap();
+        assert_eq!(till.ran_dry().collect::<Vec<_>>(), vec![100, 25]);
+    }
+
+    #[test]
+    fn apply_restock_and_pickup() {
+        let mut till = parse_till("0.25,10\n", &USD).unwrap();
+        till.apply(
+            TillCommand::Add {
+                cents: 25,
+                count: 40,
+            },
+            &USD,
+            3,
+        )
+        .unwrap();
+        assert_eq!(till.count(25), 50);
+
+        till.apply(
+            TillCommand::Remove {
+                cents: 25,
+                count: 45,
+            },
+            &USD,
+            4,
+        )
+        .unwrap();
+        assert_eq!(till.count(25), 5);
+        assert_eq!(till.ran_dry().count(), 0);
+    }
+
+    #[test]
+    fn apply_remove_more_than_on_hand() {
+        let mut till = parse_till("1.00,2\n", &USD).unwrap();
+        let result = till.apply(
+            TillCommand::Remove {
+                cents: 100,
+                count: 3,
+            },
+            &USD,
+            9,
+        );
+        assert!(matches!(
+            result,
+            Err(CashRegisterError::TillShortage {
+                line: 9,
+                needed: 3,
+                available: 2,
+                ..
+            })
+        ));
+        assert_eq!(till.count(100), 2);
+    }
+
+    fn named(breakdown: &Breakdown) -> Vec<(&str, u32)> {
+        USD.pieces(breakdown)
+            .map(|(d, c)| (d.singular.as_ref(), c))
+            .collect()
+    }
+
+    #[test]
+    fn policy_from_str() {
+        assert_eq!("error".parse(), Ok(ShortagePolicy::Error));
+        assert_eq!("Partial".parse(), Ok(ShortagePolicy::Partial));
+        assert_eq!("substitute".parse(), Ok(ShortagePolicy::Substitute));
+        assert!("lenient".parse::<ShortagePolicy>().is_err());
+    }
+
+    #[test]
+    fn policy_error_fails_on_shortage() {
+        let mut till = parse_till("0.10,5\n", &USD).unwrap();
+        let result = till.dispense_with(&[(denom(25), 1)], &USD, ShortagePolicy::Error);
+        assert_eq!(result.unwrap_err().denomination.cents, 25);
+        assert_eq!(till.count(10), 5);
+    }
+
+    #[test]
+    fn policy_covered_breakdown_is_unchanged() {
+        let mut till = parse_till("0.25,5\n", &USD).unwrap();
+        for policy in [ShortagePolicy::Partial, ShortagePolicy::Substitute] {
+            let dispensed = till.dispense_with(&[(denom(25), 1)], &USD, policy).unwrap();
+            assert_eq!(named(&dispensed.breakdown), vec![("quarter", 1)]);
+            assert_eq!(dispensed.shortfall_cents, 0);
+        }
+    }
+
+    #[test]
+    fn policy_substitute_uses_smaller_denominations() {
+        // No quarters: 30c from 3 dimes rather than failing
+        let mut till = parse_till("0.10,3\n0.01,2\n", &USD).unwrap();
+        let dispensed = till
+            .dispense_with(
+                &[(denom(25), 1), (denom(5), 1)],
+                &USD,
+                ShortagePolicy::Substitute,
+            )
+            .unwrap();
+        assert_eq!(named(&dispensed.breakdown), vec![("dime", 3)]);
+        assert_eq!(dispensed.shortfall_cents, 0);
+        assert_eq!(till.count(10), 0);
+    }
+
+    #[test]
+    fn policy_substitute_rounds_to_nearest() {
+        // 3c due with no pennies: a nickel (+2c) is closer than nothing (-3c)
+        let mut till = parse_till("0.05,1\n", &USD).unwrap();
+        let dispensed = till
+            .dispense_with(&[(denom(1), 3)], &USD, ShortagePolicy::Substitute)
+            .unwrap();
+        assert_eq!(named(&dispensed.breakdown), vec![("nickel", 1)]);
+        assert_eq!(dispensed.shortfall_cents, -2);
+    }
+
+    #[test]
+    fn policy_partial_never_overpays() {
+        let mut till = parse_till("0.05,1\n0.01,1\n", &USD).unwrap();
+        let dispensed = till
+            .dispense_with(&[(denom(1), 3)], &USD, ShortagePolicy::Partial)
+            .unwrap();
+        assert_eq!(named(&dispensed.breakdown), vec![("penny", 1)]);
+        assert_eq!(dispensed.shortfall_cents, 2);
+    }
+
+    #[test]
+    fn policy_partial_empty_drawer_gives_nothing() {
+        let mut till = Till::new();
+        let dispensed = till
+            .dispense_with(&[(denom(100), 2)], &USD, ShortagePolicy::Partial)
+            .unwrap();
+        assert!(dispensed.breakdown.is_empty());
+        assert_eq!(dispensed.shortfall_cents, 200);
+    }
+
+    #[test]
+    fn policies_settle_a_huge_shortage_at_once() {
+        // Five pennies against a million dollars, and against the most
+        // change a line can have: no search a cent at a time.
+        for dollars in [1_000_000, u32::MAX / 100] {
+            for policy in [ShortagePolicy::Partial, ShortagePolicy::Substitute] {
+                let mut till = parse_till("0.01,5\n", &USD).unwrap();
+                let dispensed = till
+                    .dispense_with(&[(denom(100), dollars)], &USD, policy)
+                    .unwrap();
+                assert_eq!(named(&dispensed.breakdown), vec![("penny", 5)]);
+                assert_eq!(dispensed.shortfall_cents, i64::from(dollars) * 100 - 5);
+            }
+        }
+
+        // A drawer worth more than what's due is only searched up to it.
+        let mut till = parse_till("1.00,100000\n0.05,1\n", &USD).unwrap();
+        let dispensed = till
+            .dispense_with(
+                &[(denom(100), 1), (denom(1), 3)],
+                &USD,
+                ShortagePolicy::Partial,
+            )
+            .unwrap();
+        assert_eq!(named(&dispensed.breakdown), vec![("dollar", 1)]);
+        assert_eq!(dispensed.shortfall_cents, 3);
+    }
+
+    #[test]
+    fn reachable_counts_what_is_on_hand() {
+        let reach = reachable(12, &[(5, 2), (2, 1)]);
+        let amounts: Vec<usize> = (0..=12).filter(|&a| reach[a]).collect();
+        assert_eq!(amounts, [0, 2, 5, 7, 10, 12]);
+    }
+
+    #[test]
+    fn settle_credits_tendered_cash_first() {
+        // Drawer has no quarters, but the customer pays with four of them
+        let mut till = parse_till("1.00,5\n", &USD).unwrap();
+        let dispensed = till
+            .settle(
+                &[(25, 4)],
+                &[(denom(25), 1)],
+                &USD,
+                ShortagePolicy::Error,
+                1,
+            )
+            .unwrap();
+        assert_eq!(named(&dispensed.breakdown), vec![("quarter", 1)]);
+        assert_eq!(till.count(25), 3);
+        assert_eq!(till.count(100), 5);
+    }
+
+    #[test]
+    fn settle_hands_tendered_cash_back_on_shortage() {
+        let mut till = parse_till("0.01,1\n", &USD).unwrap();
+        let before = till.clone();
+        let result = till.settle(
+            &[(100, 1)],
+            &[(denom(25), 3)],
+            &USD,
+            ShortagePolicy::Error,
+            2,
+        );
+        assert!(matches!(
+            result,
+            Err(CashRegisterError::TillShortage { line: 2, .. })
+        ));
+        assert_eq!(till.count(100), before.count(100));
+        assert_eq!(till.count(1), 1);
+    }
+
+    #[test]
+    fn adding_past_u32_max_is_refused() {
+        let mut till = parse_till("1.00,4294967290\n", &USD).unwrap();
+        let err = till
+            .apply(
+                TillCommand::Add {
+                    cents: 100,
+                    count: 10,
+                },
+                &USD,
+                4,
+            )
+            .unwrap_err();
+        assert_eq!(err.code(), "E012");
+        assert_eq!(
+            err.to_string(),
+            "line 4: till can't hold 10 more dollars on top of 4294967290"
+        );
+        assert_eq!(till.count(100), 4_294_967_290);
+        assert!(matches!(
+            parse_till("1.00,4294967295\n1.00,1\n", &USD),
+            Err(CashRegisterError::TillOverflow { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn settle_rolls_back_tendered_cash_on_overflow() {
+        let mut till = parse_till("0.25,4294967295\n", &USD).unwrap();
+        let before = till.clone();
+        let result = till.settle(&[(100, 1), (25, 1)], &[], &USD, ShortagePolicy::Error, 7);
+        assert!(matches!(
+            result,
+            Err(CashRegisterError::TillOverflow { line: 7, .. })
+        ));
+        assert_eq!(till.count(100), before.count(100));
+        assert_eq!(till.count(25), u32::MAX);
+    }
+
+    #[test]
+    fn settle_rejects_unknown_tendered_denomination() {
+        let mut till = Till::new();
+        let result = till.settle(&[(3, 1)], &Vec::new(), &USD, ShortagePolicy::Error, 1);
+        assert!(matches!(
+            result,
+            Err(CashRegisterError::UnknownDenomination { .. })
+        ));
+        assert_eq!(till, Till::new());
+    }
+
+    #[test]
+    fn bounded_change_backtracks_past_greedy() {
+        // Greedy would take the quarter and get stuck at 5c
+        assert_eq!(
+            bounded_change(30, &[(25, 1), (10, 3), (1, 0)]),
+            Some(vec![0, 3, 0])
+        );
+        assert_eq!(bounded_change(3, &[(5, 10), (2, 1)]), None);
+        assert_eq!(bounded_change(0, &[(5, 0)]), Some(vec![0]));
+    }
+
+    #[test]
+    fn parse_till_reads_capacity() {
+        let till = parse_till("0.25,40,120\n0.10,5\n", &USD).unwrap();
+        assert_eq!(till.capacity(25), Some(120));
+        assert_eq!(till.capacity(10), None);
+        assert!(matches!(
+            parse_till("0.25,40,lots\n", &USD),
+            Err(CashRegisterError::MalformedLine { line: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn skim_suggests_whole_bundles() {
+        let mut till = parse_till("0.25,150,100\n0.10,30,25\n1.00,5,10\n", &USD).unwrap();
+        let skim: Vec<(u32, u32)> = till
+            .skim(&USD)
+            .iter()
+            .map(|s| (s.denomination.cents, s.count))
+            .collect();
+        // 50 quarters over: two 40-rolls. 5 dimes over, but only 30 on hand:
+        // not a whole 50-roll, so just the excess.
+        assert_eq!(skim, vec![(25, 80), (10, 5)]);
+
+        till.remove(USD.denominations[denom(25)].clone(), 50)
+            .unwrap();
+        till.remove(USD.denominations[denom(10)].clone(), 5)
+            .unwrap();
+        assert!(till.skim(&USD).is_empty());
+    }
+
+    #[test]
+    fn reconcile_lists_mismatched_denominations() {
+        let expected = parse_till("0.25,40\n0.10,20\n1.00,5\n", &USD).unwrap();
+        let counted = parse_till("0.25,38\n0.10,20\n1.00,6\n", &USD).unwrap();
+        let found: Vec<(u32, u32, u32, i64)> = expected
+            .reconcile(&counted, &USD)
+            .iter()
+            .map(|d| (d.denomination.cents, d.expected, d.counted, d.over_cents()))
+            .collect();
+        assert_eq!(found, vec![(100, 5, 6, 100), (25, 40, 38, -50)]);
+        assert!(expected.reconcile(&expected, &USD).is_empty());
+    }
+
+    #[test]
+    fn emptied_keeps_capacities() {
+        let till = parse_till("0.25,150,100\n", &USD).unwrap();
+        let empty = till.emptied();
+        assert_eq!(empty.total_cents(), Cents(0));
+        assert_eq!(empty.capacity(25), Some(100));
+    }
+
+    #[test]
+    fn ious_are_recorded_in_order() {
+        let mut till = Till::new();
+        till.record_iou(4, 3);
+        till.record_iou(9, 50);
+        assert_eq!(
+            till.ious(),
+            &[Iou { line: 4, cents: 3 }, Iou { line: 9, cents: 50 }]
+        );
+    }
+
+    #[test]
+    fn void_reverses_a_settled_sale() {
+        let mut till = Till::new();
+        till.add(25, 4).unwrap();
+        till.add(1, 3).unwrap();
+        let tendered = [(100, 3)];
+        let change = [(1, 3), (4, 3)];
+        till.settle(&tendered, &change, &USD, ShortagePolicy::Error, 1)
+            .unwrap();
+        till.record_iou(1, 2);
+        let settled = till.clone();
+
+        till.void(1, &tendered, &change, &USD, 4).unwrap();
+        assert_eq!((till.count(100), till.count(25), till.count(1)), (0, 4, 3));
+        assert!(till.ious().is_empty());
+
+        // The dollars went out as change since: nothing to hand back.
+        let mut spent = settled.clone();
+        spent.dispense(&[(0, 2)], &USD).unwrap();
+        match spent.void(1, &tendered, &change, &USD, 4) {
+            Err(CashRegisterError::TillShortage {
+                line, available, ..
+            }) => {
+                assert_eq!((line, available), (4, 1));
+            }
+            other => panic!("expected TillShortage, got {other:?}"),
+        }
+        assert_eq!(spent.count(25), 1, "drawer left as it was");
+    }
+
+    #[test]
+    fn apply_rejects_unknown_denomination() {
+        let mut till = Till::new();
+        let result = till.apply(TillCommand::Add { cents: 3, count: 1 }, &USD, 1);
+        match result {
+            Err(CashRegisterError::UnknownDenomination { input, .. }) => assert_eq!(input, "0.03"),
+            other => panic!("expected UnknownDenomination, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use alloc::collections::BTreeMap;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{find_denomination, Till};
+use crate::currency::Currency;
+use crate::error::CashRegisterError;
+
+/// Version of the till state file format written by `Registers::to_state_json`.
+///
+/// - 1: a single drawer (`"denominations": [...]`)
+/// - 2: one drawer per register (`"registers": {"R1": [...]}`)
+///
+/// Version 1 files still load, as the default register.
+pub const STATE_VERSION: u32 = 2;
+
+/// On-disk till state, so consecutive runs continue from the same drawers.
+///
+/// ```json
+/// {"version":2,"currency":"USD","registers":{"":[{"cents":100,"count":19}]}}
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TillState {
+    pub version: u32,
+    pub currency: String,
+    pub registers: BTreeMap<String, Vec<DenominationCount>>,
+}
+
+/// One slot of a persisted till.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DenominationCount {
+    pub cents: u32,
+    pub count: u32,
+}
+
+/// Version 1 state: a single drawer, before registers existed.
+#[derive(Deserialize)]
+struct TillStateV1 {
+    currency: String,
+    denominations: Vec<DenominationCount>,
+}
+
+#[derive(Deserialize)]
+struct VersionProbe {
+    version: u32,
+}
+
+/// Independent drawers for each register in a store, keyed by register ID.
+///
+/// Lines without a `register=` field use the default register, `""`.
+#[derive(Debug, Clone, Default)]
+pub struct Registers {
+    opening: Till,
+    tills: BTreeMap<String, Till>,
+}
+
+impl Registers {
+    /// Each register opens with its own copy of `opening` the first time it's used.
+    pub fn new(opening: Till) -> Self {
+        Self {
+            opening,
+            tills: BTreeMap::new(),
+        }
+    }
+
+    /// The drawer for `register`, opening it if this is its first use.
+    pub fn till_mut(&mut self, register: &str) -> &mut Till {
+        if !self.tills.contains_key(register) {
+            self.tills
+                .insert(register.to_string(), self.opening.clone());
+        }
+        self.tills
+            .get_mut(register)
+            .expect("register was just opened")
+    }
+
+    /// The drawer for `register`, if it has been used.
+    pub fn get(&self, register: &str) -> Option<&Till> {
+        self.tills.get(register)
+    }
+
+    /// Every register used so far, ordered by ID (default register first).
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Till)> {
+        self.tills.iter().map(|(id, till)| (id.as_str(), till))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tills.is_empty()
+    }
+
+    /// Snapshot every drawer's counts for persistence. Which denominations ran
+    /// dry is a per-run observation and is not carried over.
+    pub fn to_state(&self, currency: &Currency) -> TillState {
+        TillState {
+            version: STATE_VERSION,
+            currency: currency.name.to_string(),
+            registers: self
+                .iter()
+                .map(|(id, till)| {
+                    let slots = currency
+                        .denominations
+                        .iter()
+                        .map(|d| DenominationCount {
+                            cents: d.cents,
+                            count: till.count(d.cents),
+                        })
+                        .collect();
+                    (id.to_string(), slots)
+                })
+                .collect(),
+        }
+    }
+
+    /// Restore drawers from a snapshot, checking it belongs to `currency`.
+    /// Registers not in the snapshot will open with `opening`; every drawer
+    /// keeps `opening`'s slot capacities, which aren't persisted.
+    pub fn from_state(
+        state: &TillState,
+        currency: &Currency,
+        opening: Till,
+    ) -> Result<Self, CashRegisterError> {
+        if state.version != STATE_VERSION {
+            return Err(CashRegisterError::InvalidState(format!(
+                "unsupported version {} (expected {STATE_VERSION})",
+                state.version
+            )));
+        }
+        if state.currency != currency.name {
+            return Err(CashRegisterError::InvalidState(format!(
+                "drawer holds {} but the run uses {}",
+                state.currency, currency.name
+            )));
+        }
+
+        let mut registers = Registers::new(opening);
+        for (id, slots) in &state.registers {
+            let mut till = registers.opening.emptied();
+            for slot in slots {
+                if find_denomination(currency, slot.cents).is_none() {
+                    return Err(CashRegisterError::InvalidState(format!(
+                        "{} cents is not a {} denomination",
+                        slot.cents, currency.name
+                    )));
+                }
+                till.add(slot.cents, slot.count).map_err(|_| {
+                    CashRegisterError::InvalidState(format!(
+                        "register \"{id}\" lists more than {} of {} cents",
+                        u32::MAX,
+                        slot.cents
+                    ))
+                })?;
+            }
+            registers.tills.insert(id.clone(), till);
+        }
+        Ok(registers)
+    }
+
+    /// Serialize every drawer as a till state JSON document.
+    pub fn to_state_json(&self, currency: &Currency) -> String {
+        serde_json::to_string_pretty(&self.to_state(currency))
+            .expect("till state is always serializable")
+    }
+
+    /// Load drawers from a till state JSON document, upgrading older versions.
+    pub fn from_state_json(
+        json: &str,
+        currency: &Currency,
+        opening: Till,
+    ) -> Result<Self, CashRegisterError> {
+        let invalid = |e: serde_json::Error| CashRegisterError::InvalidState(e.to_string());
+
+        let state: TillState = match serde_json::from_str::<VersionProbe>(json)
+            .map_err(invalid)?
+            .version
+        {
+            1 => {
+                let v1: TillStateV1 = serde_json::from_str(json).map_err(invalid)?;
+                TillState {
+                    version: STATE_VERSION,
+                    currency: v1.currency,
+                    registers: BTreeMap::from([(String::new(), v1.denominations)]),
+                }
+            }
+            _ => serde_json::from_str(json).map_err(invalid)?,
+        };
+        Registers::from_state(&state, currency, opening)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::{EUR, USD};
+    use crate::till::parse_till;
+    use alloc::vec;
+
+    #[test]
+    fn registers_open_with_their_own_float() {
+        let opening = parse_till("0.25,4\n", &USD).unwrap();
+        let mut registers = Registers::new(opening);
+
+        registers
+            .till_mut("R1")
+            .remove(USD.denominations[1].clone(), 3)
+            .unwrap();
+        assert_eq!(registers.till_mut("R2").count(25), 4);
+        assert_eq!(registers.get("R1").unwrap().count(25), 1);
+        assert!(registers.get("R3").is_none());
+
+        let ids: Vec<&str> = registers.iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec!["R1", "R2"]);
+    }
+
+    #[test]
+    fn state_json_round_trip() {
+        let mut registers = Registers::new(parse_till("1.00,3\n0.01,7\n", &USD).unwrap());
+        registers.till_mut("");
+        registers.till_mut("R2").add(25, 2).unwrap();
+
+        let json = registers.to_state_json(&USD);
+        let restored = Registers::from_state_json(&json, &USD, Till::new()).unwrap();
+        assert_eq!(restored.get("").unwrap().count(100), 3);
+        assert_eq!(restored.get("R2").unwrap().count(25), 2);
+        assert_eq!(restored.get("R2").unwrap().count(1), 7);
+        assert!(restored.get("R3").is_none());
+    }
+
+    #[test]
+    fn restored_drawers_keep_opening_capacities() {
+        let mut registers = Registers::default();
+        registers.till_mut("").add(25, 3).unwrap();
+        let json = registers.to_state_json(&USD);
+
+        let opening = parse_till("0.25,0,100\n", &USD).unwrap();
+        let restored = Registers::from_state_json(&json, &USD, opening).unwrap();
+        assert_eq!(restored.get("").unwrap().capacity(25), Some(100));
+        assert_eq!(restored.get("").unwrap().count(25), 3);
+    }
+
+    #[test]
+    fn state_lists_every_denomination() {
+        let mut registers = Registers::default();
+        registers.till_mut("");
+        let state = registers.to_state(&EUR);
+        assert_eq!(state.version, STATE_VERSION);
+        assert_eq!(state.currency, "EUR");
+        assert_eq!(state.registers[""].len(), EUR.denominations.len());
+    }
+
+    #[test]
+    fn state_version_1_loads_as_default_register() {
+        let json = r#"{"version":1,"currency":"USD","denominations":[{"cents":25,"count":8}]}"#;
+        let registers = Registers::from_state_json(json, &USD, Till::new()).unwrap();
+        assert_eq!(registers.get("").unwrap().count(25), 8);
+    }
+
+    #[test]
+    fn state_rejects_other_version() {
+        let json = r#"{"version":99,"currency":"USD","registers":{}}"#;
+        assert!(matches!(
+            Registers::from_state_json(json, &USD, Till::new()),
+            Err(CashRegisterError::InvalidState(_))
+        ));
+    }
+
+    #[test]
+    fn state_rejects_currency_mismatch() {
+        let json = Registers::default().to_state_json(&EUR);
+        let err = Registers::from_state_json(&json, &USD, Till::new()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid till state: drawer holds EUR but the run uses USD"
+        );
+    }
+
+    #[test]
+    fn state_rejects_garbage() {
+        assert!(matches!(
+            Registers::from_state_json("not json", &USD, Till::new()),
+            Err(CashRegisterError::InvalidState(_))
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use super::{Dispensed, Shortage, ShortagePolicy, Till, TillCommand};
+use crate::currency::Currency;
+use crate::error::CashRegisterError;
+use crate::money::Cents;
+
+/// A drawer shared between threads, for embedding the crate in a
+/// long-running service that makes change concurrently against one till.
+///
+/// Clones are handles to the same drawer. Every mutation holds the write
+/// lock for its whole check-then-withdraw, so a multi-denomination
+/// withdrawal either happens completely or not at all, and two requests can
+/// never both take the last coin.
+#[derive(Debug, Clone, Default)]
+pub struct SharedTill {
+    inner: Arc<RwLock<Till>>,
+}
+
+impl SharedTill {
+    pub fn new(till: Till) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(till)),
+        }
+    }
+
+    /// See [`Till::dispense`].
+    pub fn dispense(
+        &self,
+        breakdown: &[(usize, u32)],
+        currency: &Currency,
+    ) -> Result<(), Shortage> {
+        self.write().dispense(breakdown, currency)
+    }
+
+    /// See [`Till::dispense_with`].
+    pub fn dispense_with(
+        &self,
+        breakdown: &[(usize, u32)],
+        currency: &Currency,
+        policy: ShortagePolicy,
+    ) -> Result<Dispensed, Shortage> {
+        self.write().dispense_with(breakdown, currency, policy)
+    }
+
+    /// See [`Till::settle`].
+    pub fn settle(
+        &self,
+        tendered: &[(u32, u32)],
+        breakdown: &[(usize, u32)],
+        currency: &Currency,
+        policy: ShortagePolicy,
+        line: usize,
+    ) -> Result<Dispensed, CashRegisterError> {
+        self.write()
+            .settle(tendered, breakdown, currency, policy, line)
+    }
+
+    /// See [`Till::apply`].
+    pub fn apply(
+        &self,
+        command: TillCommand,
+        currency: &Currency,
+        line: usize,
+    ) -> Result<(), CashRegisterError> {
+        self.write().apply(command, currency, line)
+    }
+
+    pub fn count(&self, cents: u32) -> u32 {
+        self.read().count(cents)
+    }
+
+    pub fn total_cents(&self) -> Cents {
+        self.read().total_cents()
+    }
+
+    /// A consistent copy of the drawer at this instant, e.g. for a report.
+    pub fn snapshot(&self) -> Till {
+        self.read().clone()
+    }
+
+    // Till operations are all-or-nothing, so a panic in another holder
+    // can't have left the drawer half-updated; keep serving from it.
+    fn read(&self) -> RwLockReadGuard<'_, Till> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Till> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl From<Till> for SharedTill {
+    fn from(till: Till) -> Self {
+        Self::new(till)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::USD;
+    use crate::till::parse_till;
+    use std::thread;
+
+    #[test]
+    fn concurrent_withdrawals_never_overdraw() {
+        // 50 sales of 1 quarter + 1 dime, but only 40 dimes to go round.
+        let shared = SharedTill::new(parse_till("0.25,60\n0.10,40\n", &USD).unwrap());
+        let breakdown = vec![(1, 1), (2, 1)];
+
+        let handles: Vec<_> = (0..10)
+            .map(|_| {
+                let shared = shared.clone();
+                let breakdown = breakdown.clone();
+                thread::spawn(move || {
+                    (0..5)
+                        .filter(|_| shared.dispense(&breakdown, &USD).is_ok())
+                        .count()
+                })
+            })
+            .collect();
+        let served: usize = handles.into_iter().map(|h| h.join().unwrap()).sum();
+
+        assert_eq!(served, 40);
+        // Failed sales took nothing: no quarter left without its dime.
+        assert_eq!(shared.count(25), 20);
+        assert_eq!(shared.count(10), 0);
+    }
+
+    #[test]
+    fn clones_share_one_drawer() {
+        let a = SharedTill::from(parse_till("1.00,2\n", &USD).unwrap());
+        let b = a.clone();
+        b.apply(
+            TillCommand::Remove {
+                cents: 100,
+                count: 1,
+            },
+            &USD,
+            1,
+        )
+        .unwrap();
+        assert_eq!(a.total_cents(), Cents(100));
+        assert_eq!(a.snapshot(), b.snapshot());
+    }
+
+    #[test]
+    fn survives_a_poisoned_lock() {
+        let shared = SharedTill::new(parse_till("0.05,1\n", &USD).unwrap());
+        let poisoner = shared.clone();
+        thread::spawn(move || {
+            let _guard = poisoner.write();
+            panic!("holder panicked");
+        })
+        .join()
+        .unwrap_err();
+        assert_eq!(shared.count(5), 1);
+    }
+}
@@ -0,0 +1,266 @@
+//! Run totals for the books: what was paid, what was owed, and where the
+//! difference went, so a batch can be shown to balance.
+//!
+//! The change due on every sale is paid minus owed, so Σpaid − Σowed is the
+//! total change due. It has to equal the change actually handed over plus
+//! what a drawer shortage or rounding left out of it: change short-changed
+//! or written as an IOU, or given to charity by `--roundup`, less change
+//! rounded up in the customer's favor.
+//! Refunds are paid out the same way, so they are added to the change due.
+//! A line where it doesn't is a discrepancy.
+//! A voided sale or refund comes back out of every total it went into.
+
+use alloc::vec::Vec;
+
+use crate::currency::Currency;
+use crate::money::Cents;
+use crate::parse::Transaction;
+
+/// A sale whose change doesn't account for paid minus owed, or a refund
+/// whose payout doesn't account for the refund.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfBalance {
+    pub line: usize,
+    /// Paid minus owed, or the amount refunded.
+    pub due: Cents,
+    /// Change handed over plus change not handed over, in cents.
+    pub accounted: i64,
+}
+
+/// Totals built up one sale at a time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Totals {
+    pub transactions: u64,
+    pub paid: Cents,
+    pub owed: Cents,
+    pub refunds: u64,
+    pub refunded: Cents,
+    /// Change and refunds handed over, by the value of the pieces.
+    pub given: Cents,
+    /// Change due but not handed over: drawer shortfalls, IOUs, and
+    /// rounding down.
+    pub short: Cents,
+    /// Change handed over beyond what was due, from rounding up.
+    pub over: Cents,
+    /// Change the customer rounded up for charity.
+    pub donated: Cents,
+    /// Sales and refunds voided later in the run; they're not in the other
+    /// totals.
+    pub voids: u64,
+    pub discrepancies: Vec<OutOfBalance>,
+}
+
+impl Totals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count one sale: the change handed over in `breakdown`, `not_given`
+    /// cents of change due but not handed over (negative when rounding gave
+    /// the customer more), and `donated`, the change rounded up for charity.
+    pub fn record(
+        &mut self,
+        line: usize,
+        transaction: &Transaction,
+        breakdown: &[(usize, u32)],
+        not_given: i64,
+        donated: Cents,
+        currency: &Currency,
+    ) {
+        self.transactions += 1;
+        self.paid += transaction.paid_cents();
+        self.owed += transaction.owed_cents();
+        self.donated += donated;
+        let due = transaction.change_cents().checked_sub(donated);
+        self.hand_over(
+            line,
+            due.unwrap_or_default(),
+            breakdown,
+            not_given,
+            currency,
+        );
+    }
+
+    /// Count one refund of `amount`, paid out in `breakdown`, with
+    /// `not_given` as for [`record`](Self::record).
+    pub fn record_refund(
+        &mut self,
+        line: usize,
+        amount: Cents,
+        breakdown: &[(usize, u32)],
+        not_given: i64,
+        currency: &Currency,
+    ) {
+        self.refunds += 1;
+        self.refunded += amount;
+        self.hand_over(line, amount, breakdown, not_given, currency);
+    }
+
+    /// Take back a sale counted by [`record`](Self::record) with the same
+    /// arguments, when it is voided.
+    pub fn void(
+        &mut self,
+        line: usize,
+        transaction: &Transaction,
+        breakdown: &[(usize, u32)],
+        not_given: i64,
+        donated: Cents,
+        currency: &Currency,
+    ) {
+        self.transactions -= 1;
+        self.paid -= transaction.paid_cents();
+        self.owed -= transaction.owed_cents();
+        self.donated -= donated;
+        self.take_back(line, breakdown, not_given, currency);
+    }
+
+    /// Take back a refund counted by [`record_refund`](Self::record_refund)
+    /// with the same arguments, when it is voided.
+    pub fn void_refund(
+        &mut self,
+        line: usize,
+        amount: Cents,
+        breakdown: &[(usize, u32)],
+        not_given: i64,
+        currency: &Currency,
+    ) {
+        self.refunds -= 1;
+        self.refunded -= amount;
+        self.take_back(line, breakdown, not_given, currency);
+    }
+
+    /// Undo [`hand_over`](Self::hand_over) for the sale or refund on `line`.
+    fn take_back(
+        &mut self,
+        line: usize,
+        breakdown: &[(usize, u32)],
+        not_given: i64,
+        currency: &Currency,
+    ) {
+        self.voids += 1;
+        self.given -= given(breakdown, currency);
+        match not_given {
+            short if short > 0 => self.short -= Cents(short.unsigned_abs()),
+            over => self.over -= Cents(over.unsigned_abs()),
+        }
+        self.discrepancies.retain(|out| out.line != line);
+    }
+
+    /// Count `due` handed over as `breakdown` plus `not_given`, and note the
+    /// line if they don't match.
+    fn hand_over(
+        &mut self,
+        line: usize,
+        due: Cents,
+        breakdown: &[(usize, u32)],
+        not_given: i64,
+        currency: &Currency,
+    ) {
+        let given = given(breakdown, currency);
+        self.given += given;
+        match not_given {
+            short if short > 0 => self.short += Cents(short.unsigned_abs()),
+            over => self.over += Cents(over.unsigned_abs()),
+        }
+        let accounted = given.0 as i64 + not_given;
+        if accounted != due.0 as i64 {
+            self.discrepancies.push(OutOfBalance {
+                line,
+                due,
+                accounted,
+            });
+        }
+    }
+
+    /// Whether Σpaid − Σowed + Σrefunded equals the change given, plus
+    /// short and donated, less over, with no line out.
+    pub fn is_balanced(&self) -> bool {
+        self.discrepancies.is_empty()
+            && (self.paid - self.owed + self.refunded).0 as i64 == self.accounted()
+    }
+
+    /// The change given, plus short and donated, less over, in cents.
+    pub fn accounted(&self) -> i64 {
+        (self.given + self.short + self.donated).0 as i64 - self.over.0 as i64
+    }
+}
+
+/// The value of the pieces in `breakdown`.
+fn given(breakdown: &[(usize, u32)], currency: &Currency) -> Cents {
+    currency
+        .pieces(breakdown)
+        .map(|(denomination, count)| Cents::from(denomination.cents) * count)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::USD;
+
+    #[test]
+    fn balances_change_against_paid_minus_owed() {
+        let mut totals = Totals::new();
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        totals.record(1, &tx, &[(1, 3), (2, 1), (4, 3)], 0, Cents::ZERO, &USD);
+        // Three pennies short, then a nickel for four cents.
+        let tx = Transaction::new(Cents(197), Cents(200)).unwrap();
+        totals.record(2, &tx, &[], 3, Cents::ZERO, &USD);
+        let tx = Transaction::new(Cents(196), Cents(200)).unwrap();
+        totals.record(3, &tx, &[(3, 1)], -1, Cents::ZERO, &USD);
+
+        assert_eq!(totals.transactions, 3);
+        assert_eq!(totals.paid - totals.owed, Cents(95));
+        assert_eq!(totals.given, Cents(93));
+        assert_eq!((totals.short, totals.over), (Cents(3), Cents(1)));
+        assert!(totals.is_balanced());
+
+        // A refund paid out in full, then one the drawer was a dime short of.
+        totals.record_refund(4, Cents(212), &[(0, 2), (2, 1), (4, 2)], 0, &USD);
+        totals.record_refund(5, Cents(110), &[(0, 1)], 10, &USD);
+        assert_eq!((totals.refunds, totals.refunded), (2, Cents(322)));
+        assert_eq!(totals.given, Cents(405));
+        assert!(totals.is_balanced());
+
+        // Eight cents of $0.88 rounded up for charity.
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        totals.record(6, &tx, &[(1, 3), (3, 1)], 0, Cents(8), &USD);
+        assert_eq!(totals.donated, Cents(8));
+        assert!(totals.is_balanced());
+    }
+
+    #[test]
+    fn voids_come_back_out_of_the_totals() {
+        let mut totals = Totals::new();
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        totals.record(1, &tx, &[(1, 3), (2, 1), (4, 3)], 0, Cents::ZERO, &USD);
+        let kept = totals.clone();
+        // Out by a dime, then voided: the discrepancy goes with it.
+        let short = Transaction::new(Cents(190), Cents(200)).unwrap();
+        totals.record(2, &short, &[], 0, Cents::ZERO, &USD);
+        totals.record_refund(3, Cents(30), &[(1, 1)], 5, &USD);
+        assert!(!totals.is_balanced());
+
+        totals.void(2, &short, &[], 0, Cents::ZERO, &USD);
+        totals.void_refund(3, Cents(30), &[(1, 1)], 5, &USD);
+        assert_eq!(totals.voids, 2);
+        assert_eq!(totals, Totals { voids: 2, ..kept });
+        assert!(totals.is_balanced());
+    }
+
+    #[test]
+    fn flags_lines_that_do_not_add_up() {
+        let mut totals = Totals::new();
+        let tx = Transaction::new(Cents(212), Cents(300)).unwrap();
+        totals.record(4, &tx, &[(1, 3)], 0, Cents::ZERO, &USD);
+        assert!(!totals.is_balanced());
+        assert_eq!(
+            totals.discrepancies,
+            [OutOfBalance {
+                line: 4,
+                due: Cents(88),
+                accounted: 75,
+            }]
+        );
+    }
+}
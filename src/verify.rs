@@ -0,0 +1,177 @@
+//! Self-checks for `--paranoid` runs: re-validate each breakdown and drawer
+//! against what change-making is supposed to guarantee, so a bug shows up as
+//! an internal error instead of as wrong change handed to a customer.
+//!
+//! Sums are taken in `u128`, so even a corrupted breakdown or drawer is
+//! reported rather than overflowing.
+
+use alloc::format;
+
+use crate::currency::Currency;
+use crate::error::CashRegisterError;
+use crate::money::Cents;
+use crate::till::{Dispensed, Till};
+
+/// Check that `breakdown` is well-formed change of `amount` in `currency`:
+/// only the currency's denominations, each at most once, every count
+/// positive, and summing to `amount`.
+pub fn check_breakdown(
+    breakdown: &[(usize, u32)],
+    amount: Cents,
+    currency: &Currency,
+    line: usize,
+) -> Result<(), CashRegisterError> {
+    let violated = |detail| Err(CashRegisterError::InvariantViolated { line, detail });
+    let mut sum = 0u128;
+    for (i, &(index, count)) in breakdown.iter().enumerate() {
+        let Some(d) = currency.denominations.get(index) else {
+            return violated(format!(
+                "breakdown has denomination #{index}, but {} has {}",
+                currency.name,
+                currency.denominations.len()
+            ));
+        };
+        if count == 0 {
+            return violated(format!("breakdown has 0 {}", d.plural));
+        }
+        if breakdown[..i].iter().any(|&(seen, _)| seen == index) {
+            return violated(format!("breakdown lists {} twice", d.plural));
+        }
+        sum += u128::from(d.cents) * u128::from(count);
+    }
+    if sum != u128::from(amount.0) {
+        return violated(format!(
+            "breakdown totals {sum} cents, but the change is {} cents",
+            amount.0
+        ));
+    }
+    Ok(())
+}
+
+/// Check a sale settled against `till`: the change handed over is
+/// well-formed and makes up `change` less the shortfall, and the drawer
+/// holds exactly what it held before (`before`, its `total_cents`), plus the
+/// `tendered` cash, minus that change. A count that went below zero and
+/// wrapped shows up here.
+pub fn check_settlement(
+    before: Cents,
+    tendered: &[(u32, u32)],
+    change: Cents,
+    dispensed: &Dispensed,
+    till: &Till,
+    currency: &Currency,
+    line: usize,
+) -> Result<(), CashRegisterError> {
+    let given = i128::from(change.0) - i128::from(dispensed.shortfall_cents);
+    let Ok(given) = u64::try_from(given) else {
+        return Err(CashRegisterError::InvariantViolated {
+            line,
+            detail: format!(
+                "shortfall of {} cents is more than the change",
+                dispensed.shortfall_cents
+            ),
+        });
+    };
+    check_breakdown(&dispensed.breakdown, Cents(given), currency, line)?;
+
+    let tendered: u128 = tendered
+        .iter()
+        .map(|&(cents, count)| u128::from(cents) * u128::from(count))
+        .sum();
+    let dispensed: u128 = currency
+        .pieces(&dispensed.breakdown)
+        .map(|(d, count)| u128::from(d.cents) * u128::from(count))
+        .sum();
+    let after = u128::from(till.total_cents().0);
+    let expected = (u128::from(before.0) + tendered).checked_sub(dispensed);
+    if expected != Some(after) {
+        return Err(CashRegisterError::InvariantViolated {
+            line,
+            detail: format!(
+                "drawer holds {after} cents after the sale; expected {} + {tendered} tendered - {dispensed} dispensed",
+                before.0
+            ),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::USD;
+    use crate::till::ShortagePolicy;
+    use alloc::string::ToString;
+
+    fn index(cents: u32) -> usize {
+        USD.index_of(cents).unwrap()
+    }
+
+    #[test]
+    fn accepts_good_change() {
+        let breakdown = [(index(25), 3), (index(10), 1), (index(1), 3)];
+        assert!(check_breakdown(&breakdown, Cents(88), &USD, 1).is_ok());
+        assert!(check_breakdown(&[], Cents(0), &USD, 1).is_ok());
+    }
+
+    #[test]
+    fn reports_broken_breakdowns() {
+        let detail = |breakdown: &[(usize, u32)], amount| match check_breakdown(
+            breakdown,
+            Cents(amount),
+            &USD,
+            7,
+        ) {
+            Err(CashRegisterError::InvariantViolated { line: 7, detail }) => detail,
+            other => panic!("expected InvariantViolated, got {other:?}"),
+        };
+        assert_eq!(
+            detail(&[(index(25), 3)], 88),
+            "breakdown totals 75 cents, but the change is 88 cents"
+        );
+        assert_eq!(detail(&[(index(25), 0)], 0), "breakdown has 0 quarters");
+        assert_eq!(
+            detail(&[(index(1), 1), (index(1), 1)], 2),
+            "breakdown lists pennies twice"
+        );
+        assert!(detail(&[(99, 1)], 1).starts_with("breakdown has denomination #99"));
+
+        let err = check_breakdown(&[(index(25), 3)], Cents(88), &USD, 7).unwrap_err();
+        assert_eq!(err.code(), "E016");
+        assert_eq!(err.exit_code(), 70);
+    }
+
+    #[test]
+    fn checks_the_drawer_balances() {
+        let mut till = Till::new();
+        till.add(25, 10).unwrap();
+        till.add(100, 1).unwrap();
+        let before = till.total_cents();
+        let tendered = [(100, 1)];
+        let change = [(index(25), 2)];
+        let mut dispensed = till
+            .settle(&tendered, &change, &USD, ShortagePolicy::Error, 1)
+            .unwrap();
+        let check = |dispensed: &Dispensed| {
+            check_settlement(before, &tendered, Cents(50), dispensed, &till, &USD, 1)
+        };
+        assert!(check(&dispensed).is_ok());
+
+        // Claiming more change went out than left the drawer doesn't balance.
+        dispensed.breakdown = [(index(25), 3)].into_iter().collect();
+        dispensed.shortfall_cents = -25;
+        let err = check(&dispensed).unwrap_err();
+        assert!(err.to_string().contains("drawer holds 400 cents"), "{err}");
+
+        // Nor does change that doesn't add up to what the shortfall says.
+        dispensed.shortfall_cents = 0;
+        let err = check(&dispensed).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("breakdown totals 75 cents, but the change is 50 cents"),
+            "{err}"
+        );
+        dispensed.shortfall_cents = 60;
+        assert!(check(&dispensed).is_err());
+    }
+}
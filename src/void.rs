@@ -0,0 +1,129 @@
+//! Voids: a `VOID T1` line reverses the sale or refund rung up under
+//! `id=T1` earlier in the run, as if it had never been.
+//!
+//! [`Voids`] holds whatever the caller needs to undo each transaction with
+//! an ID until it's voided, and a record of every void for the run's
+//! summary. Only the latest transaction with an ID can be voided, and only
+//! once.
+
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::error::CashRegisterError;
+use crate::money::Cents;
+
+/// A void that went through.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Voided {
+    /// Input line of the `VOID`.
+    pub line: usize,
+    /// Input line of the transaction it reversed.
+    pub original: usize,
+    pub id: String,
+    /// The sale's owed amount, or the refund's.
+    pub amount: Cents,
+    pub refund: bool,
+}
+
+/// Transactions that can still be voided, and the voids so far.
+#[derive(Debug, Clone)]
+pub struct Voids<T> {
+    /// By ID: the input line and how to undo it.
+    open: BTreeMap<String, (usize, T)>,
+    /// By ID: the line that voided it, until the ID is rung up again.
+    voided: BTreeMap<String, usize>,
+    entries: Vec<Voided>,
+}
+
+impl<T> Default for Voids<T> {
+    fn default() -> Self {
+        Self {
+            open: BTreeMap::new(),
+            voided: BTreeMap::new(),
+            entries: Vec::new(),
+        }
+    }
+}
+
+impl<T> Voids<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep `undo` for the transaction with `id` rung up on input line
+    /// `line`, replacing any earlier one with the same ID.
+    pub fn remember(&mut self, id: &str, line: usize, undo: T) {
+        self.voided.remove(id);
+        self.open.insert(id.to_string(), (line, undo));
+    }
+
+    /// Take the line and undo for `id`, for a `VOID` on input line `line`.
+    /// If the undo can't be applied after all, [`remember`](Self::remember)
+    /// it again.
+    pub fn take(&mut self, id: &str, line: usize) -> Result<(usize, T), CashRegisterError> {
+        self.open
+            .remove(id)
+            .ok_or_else(|| CashRegisterError::NothingToVoid {
+                line,
+                id: id.to_string(),
+                voided_on: self.voided.get(id).copied(),
+            })
+    }
+
+    /// Note a void that went through.
+    pub fn record(&mut self, voided: Voided) {
+        self.voided.insert(voided.id.clone(), voided.line);
+        self.entries.push(voided);
+    }
+
+    /// Every void so far, in input order.
+    pub fn entries(&self) -> &[Voided] {
+        &self.entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn voided(line: usize, original: usize, id: &str) -> Voided {
+        Voided {
+            line,
+            original,
+            id: id.to_string(),
+            amount: Cents(212),
+            refund: false,
+        }
+    }
+
+    #[test]
+    fn voids_the_latest_transaction_once() {
+        let mut voids = Voids::new();
+        voids.remember("T1", 1, "first");
+        voids.remember("T1", 2, "second");
+        assert_eq!(voids.take("T1", 3).unwrap(), (2, "second"));
+        voids.record(voided(3, 2, "T1"));
+
+        match voids.take("T1", 4) {
+            Err(CashRegisterError::NothingToVoid { id, voided_on, .. }) => {
+                assert_eq!((id.as_str(), voided_on), ("T1", Some(3)));
+            }
+            other => panic!("expected NothingToVoid, got {other:?}"),
+        }
+        assert!(matches!(
+            voids.take("T9", 5),
+            Err(CashRegisterError::NothingToVoid {
+                voided_on: None,
+                ..
+            })
+        ));
+
+        // Rung up again, it can be voided again.
+        voids.remember("T1", 6, "third");
+        assert_eq!(voids.take("T1", 7).unwrap(), (6, "third"));
+        assert_eq!(voids.entries(), [voided(3, 2, "T1")]);
+    }
+}
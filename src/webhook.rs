@@ -0,0 +1,215 @@
+//! POSTing each transaction's JSON to an HTTP endpoint, behind the
+//! `webhook` feature, so a remote dashboard gets results as a batch runs
+//! without a separate log shipper.
+//!
+//! With a batch size of one, each POST body is one `--output json` object;
+//! with more, it's a JSON array of up to that many, the last one sent by
+//! [`Webhook::flush`]. A POST that can't connect, or gets a 5xx or 429
+//! back, is retried after a pause that doubles each time; any other status
+//! outside 2xx fails at once.
+//!
+//! ```ignore
+//! let mut webhook = Webhook::new("https://example.com/sales", NonZeroUsize::MIN, 3)?;
+//! webhook.send(r#"{"change":[],"line":1,"owed":"1.00","paid":"1.00"}"#.to_string())?;
+//! webhook.flush()?;
+//! ```
+
+use std::io;
+use std::num::NonZeroUsize;
+use std::thread;
+use std::time::Duration;
+
+use ureq::Agent;
+
+use crate::error::CashRegisterError;
+
+/// How long one POST may take, connecting included.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An HTTP endpoint transactions are POSTed to.
+pub struct Webhook {
+    url: String,
+    agent: Agent,
+    batch: usize,
+    retries: u32,
+    backoff: Duration,
+    pending: Vec<String>,
+}
+
+impl Webhook {
+    /// POST to `url` in batches of `batch` objects, retrying a failed POST
+    /// up to `retries` times. The URL must be `http://` or `https://`.
+    pub fn new(url: &str, batch: NonZeroUsize, retries: u32) -> Result<Self, CashRegisterError> {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(webhook_error(format!(
+                "\"{url}\" is not an http:// or https:// URL"
+            )));
+        }
+        let agent = Agent::config_builder()
+            .timeout_global(Some(TIMEOUT))
+            .http_status_as_error(false)
+            .build()
+            .into();
+        Ok(Self {
+            url: url.to_string(),
+            agent,
+            batch: batch.get(),
+            retries,
+            backoff: Duration::from_millis(500),
+            pending: Vec::new(),
+        })
+    }
+
+    /// Pause `first` before the first retry (default half a second),
+    /// doubling for each one after.
+    pub fn with_backoff(mut self, first: Duration) -> Self {
+        self.backoff = first;
+        self
+    }
+
+    /// Queue one JSON object, POSTing the batch once it's full.
+    pub fn send(&mut self, json: String) -> Result<(), CashRegisterError> {
+        self.pending.push(json);
+        if self.pending.len() >= self.batch {
+            self.flush()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// POST whatever is queued. A batch that fails is dropped, not retried
+    /// with the next one.
+    pub fn flush(&mut self) -> Result<(), CashRegisterError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let pending = std::mem::take(&mut self.pending);
+        let body = if self.batch == 1 {
+            pending.concat()
+        } else {
+            format!("[{}]", pending.join(","))
+        };
+        self.post(&body)
+    }
+
+    fn post(&self, body: &str) -> Result<(), CashRegisterError> {
+        let mut pause = self.backoff;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let response = self
+                .agent
+                .post(&self.url)
+                .header("Content-Type", "application/json")
+                .send(body);
+            let failure = match response {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    let failure = format!("the server answered {status}");
+                    if !status.is_server_error() && status.as_u16() != 429 {
+                        return Err(webhook_error(failure));
+                    }
+                    failure
+                }
+                Err(e) => e.to_string(),
+            };
+            if attempt > self.retries {
+                let tries = match attempt {
+                    1 => String::new(),
+                    n => format!(" (after {n} attempts)"),
+                };
+                return Err(webhook_error(format!("{failure}{tries}")));
+            }
+            thread::sleep(pause);
+            pause = pause.saturating_mul(2);
+        }
+    }
+}
+
+/// Failed POSTs are I/O errors as far as callers are concerned.
+fn webhook_error(message: String) -> CashRegisterError {
+    CashRegisterError::Io(io::Error::other(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    /// Answer one request per status in `statuses`, and return the bodies
+    /// received, once they've all been answered.
+    fn server(statuses: &'static [u16]) -> (String, thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/sales", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut bodies = Vec::new();
+            for &status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header == "\r\n" {
+                        break;
+                    }
+                    let header = header.to_ascii_lowercase();
+                    if let Some(value) = header.strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                bodies.push(String::from_utf8(body).unwrap());
+                let response = format!(
+                    "HTTP/1.1 {status} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                );
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+            bodies
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn retries_server_errors_and_batches() {
+        let (url, server) = server(&[503, 200, 200]);
+        let batch = NonZeroUsize::new(2).unwrap();
+        let mut webhook = Webhook::new(&url, batch, 1)
+            .unwrap()
+            .with_backoff(Duration::from_millis(1));
+        for line in 1..=3 {
+            webhook.send(format!(r#"{{"line":{line}}}"#)).unwrap();
+        }
+        webhook.flush().unwrap();
+        webhook.flush().unwrap();
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                r#"[{"line":1},{"line":2}]"#,
+                r#"[{"line":1},{"line":2}]"#,
+                r#"[{"line":3}]"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn gives_up_on_client_errors_and_after_the_retries() {
+        let (url, server) = server(&[400, 503, 503]);
+        let mut webhook = Webhook::new(&url, NonZeroUsize::MIN, 1)
+            .unwrap()
+            .with_backoff(Duration::from_millis(1));
+        let e = webhook.send(r#"{"line":1}"#.to_string()).unwrap_err();
+        assert_eq!(e.to_string(), "the server answered 400 Bad Request");
+        let e = webhook.send(r#"{"line":2}"#.to_string()).unwrap_err();
+        assert!(e.to_string().ends_with("(after 2 attempts)"), "{e}");
+        assert_eq!(
+            server.join().unwrap(),
+            [r#"{"line":1}"#, r#"{"line":2}"#, r#"{"line":2}"#]
+        );
+
+        assert!(Webhook::new("ftp://example.com", NonZeroUsize::MIN, 0).is_err());
+    }
+}
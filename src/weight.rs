@@ -0,0 +1,148 @@
+//! How heavy and how bulky change is, from each denomination's weight and
+//! size, so vending operators and cash-in-transit planners know what the
+//! bags will weigh.
+//!
+//! Amounts are whole milligrams and cubic millimeters, like money is whole
+//! cents, and only rounded for display. The built-in currencies carry the
+//! mints' published specifications; a piece's volume is its disc (or, for
+//! a note, its sheet), not the space it takes loose in a bag.
+
+use alloc::{format, string::String};
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+
+use crate::currency::Currency;
+
+/// One piece's weight and size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Physical {
+    pub milligrams: u32,
+    pub cubic_mm: u32,
+}
+
+/// The weight and volume of a number of pieces.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Heft {
+    pub milligrams: u64,
+    pub cubic_mm: u64,
+}
+
+impl Heft {
+    /// The weight and volume of a breakdown's pieces, or `None` if a
+    /// denomination in it has no [`Physical`] data.
+    pub fn of(breakdown: &[(usize, u32)], currency: &Currency) -> Option<Heft> {
+        currency
+            .pieces(breakdown)
+            .map(|(denomination, count)| {
+                let physical = denomination.physical?;
+                Some(Heft {
+                    milligrams: u64::from(physical.milligrams) * u64::from(count),
+                    cubic_mm: u64::from(physical.cubic_mm) * u64::from(count),
+                })
+            })
+            .sum()
+    }
+}
+
+impl Add for Heft {
+    type Output = Heft;
+
+    fn add(self, rhs: Heft) -> Heft {
+        Heft {
+            milligrams: self.milligrams + rhs.milligrams,
+            cubic_mm: self.cubic_mm + rhs.cubic_mm,
+        }
+    }
+}
+
+impl AddAssign for Heft {
+    fn add_assign(&mut self, rhs: Heft) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Heft {
+    type Output = Heft;
+
+    fn sub(self, rhs: Heft) -> Heft {
+        Heft {
+            milligrams: self.milligrams - rhs.milligrams,
+            cubic_mm: self.cubic_mm - rhs.cubic_mm,
+        }
+    }
+}
+
+impl SubAssign for Heft {
+    fn sub_assign(&mut self, rhs: Heft) {
+        *self = *self - rhs;
+    }
+}
+
+impl core::iter::Sum for Heft {
+    fn sum<I: Iterator<Item = Heft>>(iter: I) -> Heft {
+        iter.fold(Heft::default(), Add::add)
+    }
+}
+
+/// A weight to a tenth of a gram, or from a kilogram up, a hundredth of a
+/// kilogram: `17.0 g`, `2.53 kg`.
+pub fn format_weight(milligrams: u64) -> String {
+    if milligrams < 1_000_000 {
+        let tenths = (milligrams + 50) / 100;
+        format!("{}.{} g", tenths / 10, tenths % 10)
+    } else {
+        let hundredths = (milligrams + 5_000) / 10_000;
+        format!("{}.{:02} kg", hundredths / 100, hundredths % 100)
+    }
+}
+
+/// A volume to a tenth of a cubic centimeter, or from a liter up, a
+/// hundredth of a liter: `2.1 cm³`, `1.10 L`.
+pub fn format_volume(cubic_mm: u64) -> String {
+    if cubic_mm < 1_000_000 {
+        let tenths = (cubic_mm + 50) / 100;
+        format!("{}.{} cm³", tenths / 10, tenths % 10)
+    } else {
+        let hundredths = (cubic_mm + 5_000) / 10_000;
+        format!("{}.{:02} L", hundredths / 100, hundredths % 100)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::{Denomination, DenominationKind, EUR, USD};
+    use alloc::vec;
+
+    #[test]
+    fn weighs_a_breakdown() {
+        // 3 quarters, 1 dime, 3 pennies.
+        let heft = Heft::of(&[(1, 3), (2, 1), (4, 3)], &USD).unwrap();
+        assert_eq!(heft.milligrams, 3 * 5_670 + 2_268 + 3 * 2_500);
+        assert_eq!(heft.cubic_mm, 3 * 809 + 340 + 3 * 433);
+        assert_eq!(Heft::of(&[], &EUR), Some(Heft::default()));
+        assert_eq!(heft + heft - heft, heft);
+
+        let unknown = Currency::new(
+            "XTS",
+            "¤",
+            vec![Denomination::new(
+                1,
+                "token",
+                "tokens",
+                50,
+                DenominationKind::Coin,
+            )],
+        )
+        .unwrap();
+        assert_eq!(Heft::of(&[(0, 2)], &unknown), None);
+    }
+
+    #[test]
+    fn formats_by_size() {
+        assert_eq!(format_weight(26_778), "26.8 g");
+        assert_eq!(format_weight(999_949), "999.9 g");
+        assert_eq!(format_weight(2_534_000), "2.53 kg");
+        assert_eq!(format_volume(2_196), "2.2 cm³");
+        assert_eq!(format_volume(1_104_000), "1.10 L");
+    }
+}
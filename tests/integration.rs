@@ -186,6 +186,21 @@ fn unknown_currency_fails() {
     );
 }
 
+#[test]
+fn mistyped_currency_gets_a_suggestion() {
+    let output = cargo_bin()
+        .args(["sample_input.txt", "--currency", "eru"])
+        .output()
+        .expect("failed to run binary");
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Unknown currency: ERU. Did you mean EUR?"),
+        "{stderr}"
+    );
+}
+
 // ─── Error handling tests ───────────────────────────────────────────
 
 #[test]
@@ -195,7 +210,7 @@ fn missing_file_returns_nonzero_exit() {
         .output()
         .expect("failed to run binary");
 
-    assert!(!output.status.success());
+    assert_eq!(output.status.code(), Some(74));
     let stderr = String::from_utf8_lossy(&output.stderr);
     assert!(
         stderr.contains("Error reading"),
@@ -203,6 +218,370 @@ fn missing_file_returns_nonzero_exit() {
     );
 }
 
+#[test]
+fn exit_code_is_the_most_serious_failure() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_exit_codes.txt");
+    let run = |lines: &str| {
+        std::fs::write(&path, lines).unwrap();
+        cargo_bin()
+            .args([&path, "--divisor", "0"])
+            .output()
+            .expect("failed to run binary")
+            .status
+            .code()
+    };
+    let underpaid = run("2.12,3.00\n5.00,3.00\n");
+    let malformed = run("5.00,3.00\nabc\n1.00,2.00\n");
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(underpaid, Some(4));
+    assert_eq!(malformed, Some(2));
+
+    let help = cargo_bin()
+        .arg("--help")
+        .output()
+        .expect("failed to run binary");
+    let stdout = String::from_utf8_lossy(&help.stdout);
+    assert!(stdout.contains("Exit codes:"), "{stdout}");
+    assert!(stdout.contains("74  an input"), "{stdout}");
+}
+
+#[test]
+fn max_amount_rejects_fat_fingered_lines() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_max_amount.txt");
+    std::fs::write(&path, "2.12,3.00\n100000.00,100001.00\n").unwrap();
+
+    let run = |command: &[&str]| {
+        cargo_bin()
+            .args(command)
+            .args([&path, "--max-amount", "1000.00"])
+            .output()
+            .expect("failed to run binary")
+    };
+    let ran = run(&["run", "--divisor", "0"]);
+    let validated = run(&["validate"]);
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(ran.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8_lossy(&ran.stdout),
+        "3 quarters,1 dime,3 pennies\n"
+    );
+    let stderr = String::from_utf8_lossy(&ran.stderr);
+    assert!(
+        stderr.contains("line 2: 100000.00 is over the 1000.00 limit per amount"),
+        "{stderr}"
+    );
+    assert_eq!(validated.status.code(), Some(2));
+}
+
+#[test]
+fn overpayments_are_warned_about_and_summarized() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_overpayment.txt");
+    std::fs::write(&path, "1.97,200.00\n2.12,3.00\n10.00,50.00\n").unwrap();
+
+    let run = |command: &[&str]| {
+        cargo_bin()
+            .args(command)
+            .args([&path, "--warn-overpayment", "3x"])
+            .output()
+            .expect("failed to run binary")
+    };
+    let ran = run(&["run", "--divisor", "0", "--output", "json"]);
+    let validated = run(&["validate"]);
+    std::fs::remove_file(&path).ok();
+
+    // Warnings don't fail the run.
+    assert!(ran.status.success());
+    let stdout = String::from_utf8_lossy(&ran.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines[0].contains(r#""overpaid":true"#), "{stdout}");
+    assert!(!lines[1].contains("overpaid"), "{stdout}");
+    assert!(lines[2].contains(r#""overpaid":true"#), "{stdout}");
+    let stderr = String::from_utf8_lossy(&ran.stderr);
+    assert!(
+        stderr.contains("line 1: warning: unusually large change of 198.03"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.ends_with(
+            "warning: 2 transactions overpaid past 3x (lines 1, 3); check the paid amounts\n"
+        ),
+        "{stderr}"
+    );
+
+    assert!(validated.status.success());
+    assert!(String::from_utf8_lossy(&validated.stdout)
+        .ends_with(": 3 transactions, 0 errors, 0 refused, 2 overpaid\n"));
+}
+
+#[test]
+fn round_input_accepts_over_precise_amounts() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_round_input.txt");
+    std::fs::write(&path, "2.125,3.00\n1.00,2.0049\n").unwrap();
+
+    let run = |extra: &[&str]| {
+        cargo_bin()
+            .args([path.as_str(), "--divisor", "0"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let strict = run(&[]);
+    let verbose = run(&["--round-input", "half-even", "--verbose"]);
+    let json = run(&["--round-input", "half-up", "--output", "json"]);
+
+    // The config file's `rounding` applies unless `--round-input` says
+    // otherwise.
+    let config_dir = format!("{dir}/test_round_config");
+    std::fs::create_dir_all(&config_dir).unwrap();
+    std::fs::write(
+        format!("{config_dir}/cash-register.toml"),
+        "rounding = \"half-down\"\n",
+    )
+    .unwrap();
+    let configured = |extra: &[&str]| {
+        let output = cargo_bin()
+            .current_dir(&config_dir)
+            .env("XDG_CONFIG_HOME", &config_dir)
+            .args([path.as_str(), "--divisor", "0", "--verbose"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let from_config = configured(&[]);
+    let overridden = configured(&["--round-input", "toward-zero"]);
+    std::fs::remove_dir_all(&config_dir).ok();
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(strict.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&strict.stderr).contains("too many decimal places"));
+
+    assert!(verbose.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&verbose.stdout),
+        "Owed $2.12, Paid $3.00 -> 3 quarters,1 dime,3 pennies (owed 2.125 rounded half-even)\n\
+         Owed $1.00, Paid $2.00 -> 1 dollar (paid 2.0049 rounded half-even)\n"
+    );
+
+    assert!(json.status.success());
+    let stdout = String::from_utf8_lossy(&json.stdout);
+    assert!(
+        stdout.contains(r#""owed":"2.13","paid":"3.00","random":false,"rounded":{"mode":"half-up","owed":"2.125"}"#),
+        "{stdout}"
+    );
+
+    assert!(
+        from_config.starts_with("Owed $2.12, Paid $3.00 -> 3 quarters,1 dime,3 pennies (owed 2.125 rounded half-down)\n"),
+        "{from_config}"
+    );
+    assert!(
+        overridden
+            .ends_with("Owed $1.00, Paid $2.00 -> 1 dollar (paid 2.0049 rounded toward-zero)\n"),
+        "{overridden}"
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn unwritable_output_exits_74_and_a_closed_pipe_exits_quietly() {
+    use std::process::Stdio;
+
+    let full = std::fs::OpenOptions::new()
+        .write(true)
+        .open("/dev/full")
+        .unwrap();
+    let output = cargo_bin()
+        .args(["run", "sample_input.txt"])
+        .stdout(full)
+        .output()
+        .expect("failed to run binary");
+    assert_eq!(output.status.code(), Some(74));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Error writing output: No space left on device"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("panicked"), "{stderr}");
+
+    // More than a pipe holds, so the run is still printing when the reader
+    // goes away.
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_closed_pipe.txt");
+    std::fs::write(&path, "2.12,3.00\n".repeat(20_000)).unwrap();
+    let mut child = cargo_bin()
+        .args(["run", &path])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run binary");
+    drop(child.stdout.take());
+    let output = child.wait_with_output().unwrap();
+    std::fs::remove_file(&path).ok();
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+}
+
+#[cfg(unix)]
+#[test]
+fn sigterm_stops_between_lines_and_saves_the_drawer() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::Stdio;
+    use std::time::Duration;
+
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let fifo = format!("{dir}/test_interrupt.fifo");
+    let state_path = format!("{dir}/test_interrupt_state.json");
+    std::fs::remove_file(&fifo).ok();
+    std::fs::remove_file(&state_path).ok();
+    let made = Command::new("mkfifo").arg(&fifo).status().unwrap();
+    assert!(made.success());
+
+    let mut child = cargo_bin()
+        .args([fifo.as_str(), "--divisor", "0"])
+        .args(["--till", "sample_till.txt", "--till-state", &state_path])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run binary");
+    let mut feed = std::fs::OpenOptions::new().write(true).open(&fifo).unwrap();
+    writeln!(feed, "2.12,3.00").unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut first = String::new();
+    stdout.read_line(&mut first).unwrap();
+    assert_eq!(first, "3 quarters,1 dime,3 pennies\n");
+
+    // The run is blocked waiting for line 2; it stops once that arrives.
+    let killed = Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+    std::thread::sleep(Duration::from_millis(200));
+    writeln!(feed, "1.00,2.00").unwrap();
+    drop(feed);
+
+    let output = child.wait_with_output().unwrap();
+    let mut rest = String::new();
+    std::io::Read::read_to_string(&mut stdout, &mut rest).unwrap();
+    let state = std::fs::read_to_string(&state_path);
+    std::fs::remove_file(&fifo).ok();
+    std::fs::remove_file(&state_path).ok();
+
+    assert_eq!(output.status.code(), Some(130));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Interrupted; stopped before line 2"),
+        "{stderr}"
+    );
+    assert!(!rest.contains("1 dollar\n"), "{rest}");
+    assert!(rest.contains("Closing drawer"), "{rest}");
+    assert!(state.is_ok(), "the drawer wasn't saved");
+}
+
+#[test]
+fn unreadable_line_stops_the_run_after_earlier_lines() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_unreadable.txt");
+    std::fs::write(&path, b"2.12,3.00\n\xff\n1.00,2.00\n").unwrap();
+
+    let output = cargo_bin()
+        .arg(&path)
+        .output()
+        .expect("failed to run binary");
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(output.status.code(), Some(74));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "3 quarters,1 dime,3 pennies\n"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Error reading"), "{stderr}");
+}
+
+#[test]
+fn pipeline_output_matches_the_single_thread_run() {
+    let run = |extra: &[&str]| {
+        let output = cargo_bin()
+            .args(["sample_edge_cases.txt", "--seed", "7"])
+            .args(["--till", "sample_till.txt", "--shortage-policy", "partial"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary");
+        (output.status.code(), output.stdout, output.stderr)
+    };
+    assert_eq!(run(&["--pipeline"]), run(&[]));
+    assert_eq!(
+        run(&["--pipeline", "--output", "json"]),
+        run(&["--output", "json"])
+    );
+}
+
+#[test]
+fn jobs_keep_output_in_input_order() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_jobs.txt");
+    // Every third line is random, so the seed matters too.
+    let input: String = (1..=3000)
+        .map(|i| format!("{}.{:02},50.00\n", i / 100, i % 100))
+        .collect();
+    std::fs::write(&path, input).unwrap();
+
+    let run = |extra: &[&str]| {
+        let output = cargo_bin()
+            .args([path.as_str(), "--seed", "11"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary");
+        assert!(output.status.success());
+        output.stdout
+    };
+    let single = run(&[]);
+    let parallel = run(&["--jobs", "4"]);
+    let json = (
+        run(&["--output", "json"]),
+        run(&["--output", "json", "--jobs", "3"]),
+    );
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(String::from_utf8_lossy(&single).lines().count(), 3000);
+    assert!(single == parallel, "--jobs changed the output");
+    assert!(json.0 == json.1, "--jobs changed the JSON output");
+}
+
+#[test]
+fn stats_go_to_stderr_after_the_run() {
+    let output = cargo_bin()
+        .args(["sample_input.txt", "--seed", "42", "--stats"])
+        .output()
+        .expect("failed to run binary");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).lines().count(), 3);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stats = stderr
+        .lines()
+        .find_map(|line| line.strip_prefix("stats: "))
+        .unwrap_or_else(|| panic!("no stats line in {stderr:?}"));
+    let size = std::fs::metadata("sample_input.txt").unwrap().len();
+    assert!(stats.contains(" transactions=3 "), "{stats}");
+    assert!(stats.contains(&format!(" bytes={size} ")), "{stats}");
+    for key in [
+        "elapsed_secs=",
+        "transactions_per_sec=",
+        "bytes_per_sec=",
+        "peak_rss_kib=",
+    ] {
+        assert!(stats.contains(key), "{key} missing from {stats}");
+    }
+}
+
 #[test]
 fn no_args_shows_usage() {
     let output = Command::new("cargo")
@@ -392,49 +771,2553 @@ fn verbose_eur() {
     assert!(lines[1].starts_with("Owed €3.33, Paid €5.00 -> "));
 }
 
-// ─── Helpers ────────────────────────────────────────────────────────
+#[test]
+fn ascii_symbols_spell_out_the_currency() {
+    let output = cargo_bin()
+        .args([
+            "sample_eur.txt",
+            "--currency",
+            "EUR",
+            "--divisor",
+            "0",
+            "--verbose",
+            "--ascii-symbols",
+        ])
+        .output()
+        .expect("failed to run binary");
 
-/// Parse a USD output line like "1 dollar,2 quarters,1 nickel,2 pennies" into total cents.
-fn parse_output_cents(line: &str) -> u32 {
-    line.split(',')
-        .map(|part| {
-            let part = part.trim();
-            let (count_str, name) = part.split_once(' ').expect("expected 'N name' format");
-            let count: u32 = count_str.parse().expect("expected numeric count");
-            let cents_per = match name {
-                "dollar" | "dollars" => 100,
-                "quarter" | "quarters" => 25,
-                "dime" | "dimes" => 10,
-                "nickel" | "nickels" => 5,
-                "penny" | "pennies" => 1,
-                other => panic!("unknown denomination: {other}"),
-            };
-            count * cents_per
-        })
-        .sum()
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.is_ascii(), "{stdout}");
+    assert_eq!(
+        stdout.lines().next(),
+        Some("Owed EUR 1.50, Paid EUR 2.00 -> 1 50 cent coin")
+    );
 }
 
-/// Parse a EUR output line into total cents.
-fn parse_eur_output_cents(line: &str) -> u32 {
-    // EUR format: "1 2 euro coin,2 50 cent coins,..." — split on comma,
-    // then the first token is the count, the rest is the denomination name.
-    line.split(',')
-        .map(|part| {
-            let part = part.trim();
-            let (count_str, name) = part.split_once(' ').expect("expected 'N name' format");
-            let count: u32 = count_str.parse().expect("expected numeric count");
-            let cents_per = match name {
-                "2 euro coin" | "2 euro coins" => 200,
-                "1 euro coin" | "1 euro coins" => 100,
-                "50 cent coin" | "50 cent coins" => 50,
-                "20 cent coin" | "20 cent coins" => 20,
-                "10 cent coin" | "10 cent coins" => 10,
-                "5 cent coin" | "5 cent coins" => 5,
-                "2 cent coin" | "2 cent coins" => 2,
-                "1 cent coin" | "1 cent coins" => 1,
-                other => panic!("unknown EUR denomination: {other}"),
-            };
-            count * cents_per
-        })
-        .sum()
+// ─── Till tracking tests ────────────────────────────────────────────
+
+#[test]
+fn till_prints_closing_report() {
+    let output = cargo_bin()
+        .args([
+            "sample_input.txt",
+            "--divisor",
+            "0",
+            "--till",
+            "sample_till.txt",
+        ])
+        .output()
+        .expect("failed to run binary");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+
+    // Change output is unchanged; the report follows a blank line.
+    assert_eq!(lines[0], "3 quarters,1 dime,3 pennies");
+    assert_eq!(lines[3], "");
+    assert_eq!(lines[4], "Closing drawer (USD):");
+    // 88c + 3c + 167c = $2.58 dispensed from $37.50
+    assert!(lines.contains(&"  19 dollars ($19.00)"), "{stdout}");
+    assert!(lines.contains(&"  35 quarters ($8.75)"), "{stdout}");
+    assert!(lines.contains(&"Total: $34.92"), "{stdout}");
+    assert!(lines.contains(&"Ran dry: none"), "{stdout}");
+}
+
+#[test]
+fn till_report_written_to_file() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let till_path = format!("{dir}/test_till_report_till.txt");
+    let report_path = format!("{dir}/test_till_report_out.txt");
+    std::fs::write(&till_path, "0.25,3\n0.10,1\n0.01,3\n").unwrap();
+
+    let output = cargo_bin()
+        .args(["sample_input.txt", "--divisor", "0", "--till", &till_path])
+        .args(["--till-report", &report_path])
+        .output()
+        .expect("failed to run binary");
+
+    let report = std::fs::read_to_string(&report_path).unwrap_or_default();
+    std::fs::remove_file(&till_path).ok();
+    std::fs::remove_file(&report_path).ok();
+
+    // Only the first line (88c) can be covered; the others run short.
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["3 quarters,1 dime,3 pennies"]
+    );
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("line 2: till has 0 pennies, needed 3"),
+        "expected shortage error, got: {stderr}"
+    );
+    assert!(!output.status.success());
+
+    assert!(report.contains("Total: $0.00"), "{report}");
+    assert!(
+        report.contains("Ran dry: quarters,dimes,pennies"),
+        "{report}"
+    );
+}
+
+#[test]
+fn till_restock_directive_mid_run() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let till_path = format!("{dir}/test_restock_till.txt");
+    let input_path = format!("{dir}/test_restock_input.txt");
+    std::fs::write(&till_path, "0.01,3\n").unwrap();
+    std::fs::write(
+        &input_path,
+        "1.97,2.00\n1.97,2.00\ntill add 0.01,10\n1.97,2.00\ntill remove 0.01,4\n",
+    )
+    .unwrap();
+
+    let output = cargo_bin()
+        .args([&input_path, "--divisor", "0", "--till", &till_path])
+        .output()
+        .expect("failed to run binary");
+
+    std::fs::remove_file(&till_path).ok();
+    std::fs::remove_file(&input_path).ok();
+
+    // Line 2 runs short before the drop on line 3; line 4 succeeds after it.
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "3 pennies");
+    assert_eq!(lines[1], "3 pennies");
+    assert!(lines.contains(&"  3 pennies ($0.03)"), "{stdout}");
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("line 2: till has 0 pennies"), "{stderr}");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn till_state_carries_over_between_runs() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let state_path = format!("{dir}/test_till_state.json");
+    std::fs::remove_file(&state_path).ok();
+
+    let run = || {
+        cargo_bin()
+            .args(["sample_input.txt", "--divisor", "0"])
+            .args(["--till", "sample_till.txt", "--till-state", &state_path])
+            .output()
+            .expect("failed to run binary")
+    };
+
+    // First run starts from the till file; the second from the saved state.
+    let first = run();
+    let second = run();
+    let state = std::fs::read_to_string(&state_path).unwrap_or_default();
+    std::fs::remove_file(&state_path).ok();
+
+    assert!(first.status.success());
+    assert!(second.status.success());
+    let first_out = String::from_utf8_lossy(&first.stdout);
+    let second_out = String::from_utf8_lossy(&second.stdout);
+    assert!(first_out.contains("Total: $34.92"), "{first_out}");
+    assert!(second_out.contains("Total: $32.34"), "{second_out}");
+
+    assert!(state.contains("\"version\": 2"), "{state}");
+    assert!(state.contains("\"currency\": \"USD\""), "{state}");
+}
+
+#[test]
+fn dry_run_previews_without_saving_the_drawer() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let state_path = format!("{dir}/test_till_state_dry_run.json");
+    let journal_path = format!("{dir}/test_journal_dry_run.jsonl");
+    std::fs::remove_file(&state_path).ok();
+    std::fs::remove_file(&journal_path).ok();
+
+    let run = |extra: &[&str]| {
+        cargo_bin()
+            .args(["sample_input.txt", "--divisor", "0"])
+            .args(["--till", "sample_till.txt", "--till-state", &state_path])
+            .args(["--journal", &journal_path])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+
+    let preview = run(&["--dry-run"]);
+    let state_saved = std::path::Path::new(&state_path).exists();
+    let journal_written = std::path::Path::new(&journal_path).exists();
+    let real = run(&[]);
+    std::fs::remove_file(&state_path).ok();
+    std::fs::remove_file(&journal_path).ok();
+
+    assert!(preview.status.success());
+    assert!(!state_saved);
+    assert!(!journal_written);
+    let stderr = String::from_utf8_lossy(&preview.stderr);
+    assert!(stderr.contains("dry run:"), "{stderr}");
+    // The preview shows exactly what the real run then does.
+    assert_eq!(preview.stdout, real.stdout);
+
+    let no_drawer = cargo_bin()
+        .args(["sample_input.txt", "--dry-run"])
+        .output()
+        .expect("failed to run binary");
+    assert_eq!(no_drawer.status.code(), Some(64));
+}
+
+#[test]
+fn dedupe_catches_resubmitted_transactions() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let input_path = format!("{dir}/test_dedupe.txt");
+    let seen_path = format!("{dir}/test_dedupe_seen.txt");
+    std::fs::write(
+        &input_path,
+        "2.12,3.00,id=T1\n1.00,2.00,id=T2\n2.12,3.00,id=T1\n1.00,2.00\n",
+    )
+    .unwrap();
+    std::fs::remove_file(&seen_path).ok();
+
+    let run = |mode: &str| {
+        cargo_bin()
+            .args([&input_path, "--divisor", "0", "--dedupe", mode])
+            .args(["--seen-ids", &seen_path])
+            .output()
+            .expect("failed to run binary")
+    };
+    let flagged = run("flag");
+    let seen = std::fs::read_to_string(&seen_path).unwrap();
+    let again = run("skip");
+    std::fs::remove_file(&input_path).ok();
+    std::fs::remove_file(&seen_path).ok();
+
+    // Lines without an ID are never duplicates.
+    assert_eq!(flagged.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8_lossy(&flagged.stdout),
+        "3 quarters,1 dime,3 pennies\n1 dollar\n1 dollar\n"
+    );
+    let stderr = String::from_utf8_lossy(&flagged.stderr);
+    assert!(
+        stderr.contains("line 3: duplicate transaction id \"T1\" (first seen on line 1)"),
+        "{stderr}"
+    );
+    assert_eq!(seen, "T1\nT2\n");
+
+    // The next run remembers both IDs, and skipping isn't a failure.
+    assert!(again.status.success());
+    assert_eq!(String::from_utf8_lossy(&again.stdout), "1 dollar\n");
+    let stderr = String::from_utf8_lossy(&again.stderr);
+    assert!(
+        stderr.contains("(seen in an earlier run); skipped"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn paranoid_run_checks_without_changing_the_output() {
+    let run = |extra: &[&str]| {
+        cargo_bin()
+            .args([
+                "sample_input.txt",
+                "--seed",
+                "4",
+                "--till",
+                "sample_till.txt",
+            ])
+            .args(["--shortage-policy", "substitute"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let plain = run(&[]);
+    let paranoid = run(&["--paranoid"]);
+
+    assert!(paranoid.status.success());
+    assert_eq!(paranoid.stdout, plain.stdout);
+    assert_eq!(run(&["--paranoid", "--jobs", "2"]).status.code(), Some(64));
+}
+
+#[test]
+fn till_state_currency_mismatch_fails() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let state_path = format!("{dir}/test_till_state_mismatch.json");
+    std::fs::write(
+        &state_path,
+        r#"{"version":1,"currency":"EUR","denominations":[]}"#,
+    )
+    .unwrap();
+
+    let output = cargo_bin()
+        .args(["sample_input.txt", "--till-state", &state_path])
+        .output()
+        .expect("failed to run binary");
+
+    std::fs::remove_file(&state_path).ok();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("invalid till state"), "{stderr}");
+}
+
+#[test]
+fn shortage_policy_partial_and_substitute() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let till_path = format!("{dir}/test_shortage_policy_till.txt");
+    // Enough for 2.12,3.00 (88c) only by substituting for the dime; no pennies.
+    std::fs::write(&till_path, "0.25,3\n0.05,3\n").unwrap();
+
+    let run = |policy: &str| {
+        cargo_bin()
+            .args(["sample_input.txt", "--divisor", "0", "--till", &till_path])
+            .args(["--till-report", &format!("{till_path}.{policy}.report")])
+            .args(["--shortage-policy", policy])
+            .output()
+            .expect("failed to run binary")
+    };
+
+    let partial = run("partial");
+    let substitute = run("substitute");
+    let bogus = run("bogus");
+    for policy in ["partial", "substitute", "bogus"] {
+        std::fs::remove_file(format!("{till_path}.{policy}.report")).ok();
+    }
+    std::fs::remove_file(&till_path).ok();
+
+    assert!(partial.status.success());
+    let stdout = String::from_utf8_lossy(&partial.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "3 quarters,2 nickels (short $0.03)");
+    assert_eq!(lines[1], "no change (short $0.03)");
+
+    assert!(substitute.status.success());
+    let stdout = String::from_utf8_lossy(&substitute.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines[0], "3 quarters,3 nickels (over $0.02)");
+
+    assert!(!bogus.status.success());
+    let stderr = String::from_utf8_lossy(&bogus.stderr);
+    assert!(stderr.contains("unknown shortage policy"), "{stderr}");
+}
+
+#[test]
+fn tendered_cash_is_deposited_before_change() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let till_path = format!("{dir}/test_tendered_till.txt");
+    let input_path = format!("{dir}/test_tendered_input.txt");
+    let report_path = format!("{dir}/test_tendered_report.txt");
+    // Empty drawer: change for line 2 can only come from line 1's quarters.
+    std::fs::write(&till_path, "").unwrap();
+    std::fs::write(
+        &input_path,
+        "0.50,1.00,tendered=0.25x4\n0.75,1.00,tendered=1.00x1\n",
+    )
+    .unwrap();
+
+    let output = cargo_bin()
+        .args([&input_path, "--divisor", "0", "--till", &till_path])
+        .args(["--till-report", &report_path])
+        .output()
+        .expect("failed to run binary");
+
+    let report = std::fs::read_to_string(&report_path).unwrap_or_default();
+    std::fs::remove_file(&till_path).ok();
+    std::fs::remove_file(&input_path).ok();
+    std::fs::remove_file(&report_path).ok();
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        vec!["2 quarters", "1 quarter"]
+    );
+    assert!(report.contains("  1 dollar ($1.00)"), "{report}");
+    assert!(report.contains("  1 quarter ($0.25)"), "{report}");
+}
+
+#[test]
+fn registers_keep_independent_tills() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let till_path = format!("{dir}/test_registers_till.txt");
+    let input_path = format!("{dir}/test_registers_input.txt");
+    std::fs::write(&till_path, "0.25,4\n").unwrap();
+    std::fs::write(
+        &input_path,
+        "0.25,1.00,register=A\n0.25,1.00,register=B\ntill add 0.25,1,register=A\n0.75,1.00,register=A\n",
+    )
+    .unwrap();
+
+    let output = cargo_bin()
+        .args([&input_path, "--divisor", "0", "--till", &till_path])
+        .output()
+        .expect("failed to run binary");
+
+    std::fs::remove_file(&till_path).ok();
+    std::fs::remove_file(&input_path).ok();
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let reports: Vec<&str> = stdout.split("\n\n").skip(1).collect();
+    assert_eq!(reports.len(), 2, "{stdout}");
+    // A: 4 - 3 + 1 - 1 = 1 quarter; B: 4 - 3 = 1 quarter
+    assert!(
+        reports[0].starts_with("Closing drawer A (USD):"),
+        "{stdout}"
+    );
+    assert!(reports[0].contains("  1 quarter ($0.25)"), "{stdout}");
+    assert!(
+        reports[1].starts_with("Closing drawer B (USD):"),
+        "{stdout}"
+    );
+    assert!(reports[1].contains("Ran dry: none"), "{stdout}");
+}
+
+#[test]
+fn journal_appends_till_mutations() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let input_path = format!("{dir}/test_journal_input.txt");
+    let journal_path = format!("{dir}/test_journal.jsonl");
+    std::fs::remove_file(&journal_path).ok();
+    std::fs::write(&input_path, "0.75,1.00,tendered=1.00x1\ntill add 0.01,5\n").unwrap();
+
+    let run = || {
+        cargo_bin()
+            .args([&input_path, "--divisor", "0", "--till", "sample_till.txt"])
+            .args(["--journal", &journal_path])
+            .output()
+            .expect("failed to run binary")
+    };
+    let first = run();
+    let second = run();
+
+    let journal = std::fs::read_to_string(&journal_path).unwrap_or_default();
+    std::fs::remove_file(&input_path).ok();
+    std::fs::remove_file(&journal_path).ok();
+
+    assert!(first.status.success());
+    assert!(second.status.success());
+
+    // Two mutations per run, appended across runs
+    let lines: Vec<&str> = journal.lines().collect();
+    assert_eq!(lines.len(), 4, "{journal}");
+    assert!(lines[0].contains("\"event\":\"sale\""), "{journal}");
+    assert!(
+        lines[0].contains("\"in\":[{\"cents\":100,\"count\":1}]"),
+        "{journal}"
+    );
+    assert!(
+        lines[0].contains("\"out\":[{\"cents\":25,\"count\":1}]"),
+        "{journal}"
+    );
+    // $37.50 float + $1.00 tendered - $0.25 change
+    assert!(lines[0].contains("\"balance_cents\":3825"), "{journal}");
+    assert!(lines[1].contains("\"event\":\"restock\""), "{journal}");
+    assert!(lines[1].contains("\"balance_cents\":3830"), "{journal}");
+}
+
+#[test]
+fn void_comes_out_of_every_summary() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let input_path = format!("{dir}/test_void_summaries_input.txt");
+    std::fs::write(
+        &input_path,
+        "10.00,10.50,id=T1,customer=C1\n5.00,5.00,id=T2,customer=C1\nVOID T1\n",
+    )
+    .unwrap();
+
+    let output = cargo_bin()
+        .args([&input_path, "--divisor", "0", "--totals", "--weights"])
+        .args(["--loyalty-rate", "1", "--roundup", "0.20"])
+        .args(["--report-threshold", "5.00"])
+        .args(["--flag-suspicious", "--suspicious-threshold", "11.00"])
+        .output()
+        .expect("failed to run binary");
+    std::fs::remove_file(&input_path).ok();
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    for summary in [
+        "Donated $0.00 from 0 of 1 sale, rounding change down to $0.20",
+        "Change handed over: 0.0 g, 0.0 cm³ on 1 line",
+        "Loyalty points at 1 per $1.00: 5 on 1 transaction\n  C1  5 points on 1 transaction ($5.00)",
+        "Cash transactions of $5.00 or more to report: 1, $5.00 in all\n  line 2: $5.00 received (id T2)\n",
+        "Voided 1 transaction:\n  line 3: sale of $10.00 on line 1 (id T1)",
+    ] {
+        assert!(stderr.contains(summary), "{stderr}");
+    }
+    assert!(!stderr.contains("suspicious"), "{stderr}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Totals (USD), 1 transaction, 1 voided:\n  Paid          $5.00"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn void_reverses_an_earlier_sale() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let input_path = format!("{dir}/test_void_input.txt");
+    let journal_path = format!("{dir}/test_void_journal.jsonl");
+    std::fs::remove_file(&journal_path).ok();
+    std::fs::write(
+        &input_path,
+        "0.75,1.00,id=T1,tendered=1.00x1\n1.97,2.00,id=T2\nVOID T1\nVOID T1\n",
+    )
+    .unwrap();
+
+    let output = cargo_bin()
+        .args([&input_path, "--divisor", "0", "--till", "sample_till.txt"])
+        .args(["--journal", &journal_path, "--totals"])
+        .output()
+        .expect("failed to run binary");
+
+    let journal = std::fs::read_to_string(&journal_path).unwrap_or_default();
+    std::fs::remove_file(&input_path).ok();
+    std::fs::remove_file(&journal_path).ok();
+
+    // The second VOID has nothing left to void.
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("line 4: nothing to void with id \"T1\" (already voided on line 3)"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("Voided 1 transaction:\n  line 3: sale of $0.75 on line 1 (id T1)"),
+        "{stderr}"
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Totals (USD), 1 transaction, 1 voided:\n  Paid          $2.00"),
+        "{stdout}"
+    );
+
+    let lines: Vec<&str> = journal.lines().collect();
+    assert_eq!(lines.len(), 3, "{journal}");
+    assert!(lines[2].contains("\"event\":\"void\""), "{journal}");
+    assert!(
+        lines[2].contains("\"in\":[{\"cents\":25,\"count\":1}]"),
+        "{journal}"
+    );
+    assert!(
+        lines[2].contains("\"out\":[{\"cents\":100,\"count\":1}]"),
+        "{journal}"
+    );
+    // Back to the $37.50 float, less line 2's three pennies.
+    assert!(lines[2].contains("\"balance_cents\":3747"), "{journal}");
+}
+
+#[test]
+fn refunds_are_journaled_as_refunds() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let input_path = format!("{dir}/test_refund_journal_input.txt");
+    let journal_path = format!("{dir}/test_refund_journal.jsonl");
+    std::fs::remove_file(&journal_path).ok();
+    std::fs::write(&input_path, "2.12,3.00\n-0.35,0\n").unwrap();
+
+    let output = cargo_bin()
+        .args([&input_path, "--divisor", "0", "--till", "sample_till.txt"])
+        .args(["--journal", &journal_path])
+        .output()
+        .expect("failed to run binary");
+
+    let journal = std::fs::read_to_string(&journal_path).unwrap_or_default();
+    std::fs::remove_file(&input_path).ok();
+    std::fs::remove_file(&journal_path).ok();
+
+    assert!(output.status.success());
+    let lines: Vec<serde_json::Value> = journal
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2, "{journal}");
+    assert_eq!(lines[0]["event"], "sale");
+    assert_eq!(lines[1]["event"], "refund");
+    assert_eq!(lines[1]["line"], 2);
+    assert_eq!(lines[1]["in"], serde_json::json!([]));
+    assert_eq!(
+        lines[1]["out"],
+        serde_json::json!([{"cents": 25, "count": 1}, {"cents": 10, "count": 1}])
+    );
+    assert_eq!(lines[1]["balance_cents"], 3627);
+}
+
+#[test]
+fn till_with_unknown_denomination_fails() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let till_path = format!("{dir}/test_till_unknown.txt");
+    std::fs::write(&till_path, "0.03,10\n").unwrap();
+
+    let output = cargo_bin()
+        .args(["sample_input.txt", "--till", &till_path])
+        .output()
+        .expect("failed to run binary");
+
+    std::fs::remove_file(&till_path).ok();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("not a USD denomination"),
+        "expected denomination error, got: {stderr}"
+    );
+}
+
+// ─── Helpers ────────────────────────────────────────────────────────
+
+/// Parse a USD output line like "1 dollar,2 quarters,1 nickel,2 pennies" into total cents.
+fn parse_output_cents(line: &str) -> u32 {
+    line.split(',')
+        .map(|part| {
+            let part = part.trim();
+            let (count_str, name) = part.split_once(' ').expect("expected 'N name' format");
+            let count: u32 = count_str.parse().expect("expected numeric count");
+            let cents_per = match name {
+                "dollar" | "dollars" => 100,
+                "quarter" | "quarters" => 25,
+                "dime" | "dimes" => 10,
+                "nickel" | "nickels" => 5,
+                "penny" | "pennies" => 1,
+                other => panic!("unknown denomination: {other}"),
+            };
+            count * cents_per
+        })
+        .sum()
+}
+
+/// Parse a EUR output line into total cents.
+fn parse_eur_output_cents(line: &str) -> u32 {
+    // EUR format: "1 2 euro coin,2 50 cent coins,..." — split on comma,
+    // then the first token is the count, the rest is the denomination name.
+    line.split(',')
+        .map(|part| {
+            let part = part.trim();
+            let (count_str, name) = part.split_once(' ').expect("expected 'N name' format");
+            let count: u32 = count_str.parse().expect("expected numeric count");
+            let cents_per = match name {
+                "2 euro coin" | "2 euro coins" => 200,
+                "1 euro coin" | "1 euro coins" => 100,
+                "50 cent coin" | "50 cent coins" => 50,
+                "20 cent coin" | "20 cent coins" => 20,
+                "10 cent coin" | "10 cent coins" => 10,
+                "5 cent coin" | "5 cent coins" => 5,
+                "2 cent coin" | "2 cent coins" => 2,
+                "1 cent coin" | "1 cent coins" => 1,
+                other => panic!("unknown EUR denomination: {other}"),
+            };
+            count * cents_per
+        })
+        .sum()
+}
+
+#[test]
+fn interactive_shortage_prompts_operator() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let till_path = format!("{dir}/test_interactive_till.txt");
+    let report_path = format!("{dir}/test_interactive_report.txt");
+    // No dimes or pennies: every sample line runs short.
+    std::fs::write(&till_path, "0.25,3\n0.05,3\n").unwrap();
+
+    let mut child = cargo_bin()
+        .args(["sample_input.txt", "--divisor", "0", "--till", &till_path])
+        .args(["--till-report", &report_path, "--interactive"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run binary");
+    // IOU for line 1, an unknown answer then skip for line 2, and stdin runs
+    // out before line 3 so it fails as usual.
+    child.stdin.take().unwrap().write_all(b"i\nx\nk\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    let report = std::fs::read_to_string(&report_path).unwrap();
+    std::fs::remove_file(&till_path).ok();
+    std::fs::remove_file(&report_path).ok();
+
+    assert_eq!(output.status.code(), Some(5));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout, "3 quarters,2 nickels (IOU $0.03)\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("[s]ubstitute, [i]OU, or s[k]ip?"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("Unknown choice \"x\""), "{stderr}");
+    assert!(stderr.contains("line 2: skipped"), "{stderr}");
+    assert!(
+        stderr.contains("line 3: till has 0 dollars, needed 1"),
+        "{stderr}"
+    );
+    assert!(report.contains("IOUs: $0.03 (line 1)"), "{report}");
+}
+
+#[test]
+fn recommend_float_orders_whole_bundles() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let history_path = format!("{dir}/test_float_history.txt");
+    let till_path = format!("{dir}/test_float_till.txt");
+    // Pennies are drawn 6 deep; every quarter handed out comes from the
+    // quarters the second customer tendered.
+    std::fs::write(
+        &history_path,
+        "1.97,2.00\n0.72,1.00,tendered=0.25x4\n0.75,1.00\n",
+    )
+    .unwrap();
+    std::fs::write(&till_path, "0.01,10\n").unwrap();
+
+    let empty = cargo_bin()
+        .args(["recommend-float", &history_path, "--divisor", "0"])
+        .output()
+        .expect("failed to run binary");
+    let stocked = cargo_bin()
+        .args(["recommend-float", &history_path, "--divisor", "0"])
+        .args(["--till", &till_path])
+        .output()
+        .expect("failed to run binary");
+    std::fs::remove_file(&history_path).ok();
+    std::fs::remove_file(&till_path).ok();
+
+    assert!(empty.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&empty.stdout),
+        "Bank order (USD):\n  1 roll of pennies ($0.50)\nTotal: $0.50\n"
+    );
+    assert!(stocked.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&stocked.stdout),
+        "Bank order (USD):\n  nothing to order\nTotal: $0.00\n"
+    );
+}
+
+#[test]
+fn overfull_slot_suggests_skim() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let till_path = format!("{dir}/test_skim_till.txt");
+    let input_path = format!("{dir}/test_skim_input.txt");
+    let journal_path = format!("{dir}/test_skim_journal.jsonl");
+    std::fs::write(&till_path, "1.00,5,6\n0.25,40\n0.10,50\n0.01,50\n").unwrap();
+    std::fs::write(&input_path, "2.12,3.00,tendered=1.00x3\n").unwrap();
+
+    let output = cargo_bin()
+        .args([&input_path, "--divisor", "0", "--till", &till_path])
+        .args(["--journal", &journal_path])
+        .output()
+        .expect("failed to run binary");
+    let journal = std::fs::read_to_string(&journal_path).unwrap();
+    std::fs::remove_file(&till_path).ok();
+    std::fs::remove_file(&input_path).ok();
+    std::fs::remove_file(&journal_path).ok();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Skim: remove 2 dollars"), "{stdout}");
+    assert!(
+        journal.contains(r#""skim":[{"cents":100,"count":2}]"#),
+        "{journal}"
+    );
+}
+
+#[test]
+fn tender_policy_refuses_lines_with_exit_3() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let input_path = format!("{dir}/test_tender_policy_input.txt");
+    std::fs::write(
+        &input_path,
+        "0.50,1.00,tendered=0.25x4\n1.50,2.00,tendered=1.00x2\n0.10,0.25,tendered=0.01x25\n",
+    )
+    .unwrap();
+
+    let run = |extra: &[&str]| {
+        cargo_bin()
+            .args([&input_path, "--divisor", "0"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let refused = run(&["--max-bill", "0.50", "--max-coins", "10"]);
+    let bad_amount = run(&["--max-bill", "lots"]);
+    std::fs::remove_file(&input_path).ok();
+
+    assert_eq!(refused.status.code(), Some(3));
+    assert_eq!(String::from_utf8_lossy(&refused.stdout), "2 quarters\n");
+    let stderr = String::from_utf8_lossy(&refused.stderr);
+    assert!(
+        stderr.contains("line 2: tender refused: dollars exceed the $0.50 bill limit"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("line 3: tender refused: 25 coins tendered, limit is 10"),
+        "{stderr}"
+    );
+
+    assert_eq!(bad_amount.status.code(), Some(64));
+}
+
+#[test]
+fn simulate_reports_run_outs() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let till_path = format!("{dir}/test_simulate_till.txt");
+    // Customers always round up to the dollar; no coins to make change with.
+    std::fs::write(&till_path, "1.00,10\n").unwrap();
+
+    let run = |extra: &[&str]| {
+        cargo_bin()
+            .args([
+                "simulate",
+                "--till",
+                &till_path,
+                "--seed",
+                "9",
+                "--divisor",
+                "0",
+            ])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let dry = run(&[
+        "--sales",
+        "20",
+        "--payment-mix",
+        "round-up=1",
+        "--max-owed",
+        "5.00",
+    ]);
+    let exact = run(&["--sales", "20", "--payment-mix", "exact=1"]);
+    let bad_mix = run(&["--payment-mix", "cheque=1"]);
+    std::fs::remove_file(&till_path).ok();
+
+    assert!(dry.status.success());
+    let stdout = String::from_utf8_lossy(&dry.stdout);
+    assert!(
+        stdout.starts_with("Simulated 20 sales (USD):\n"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("ran out at sale 1\n"), "{stdout}");
+    assert!(stdout.contains("Closing drawer (USD):"), "{stdout}");
+
+    assert!(exact.status.success());
+    let stdout = String::from_utf8_lossy(&exact.stdout);
+    assert!(
+        stdout.contains("  nothing ran out\nShort of change on 0 sales\n"),
+        "{stdout}"
+    );
+
+    assert_eq!(bad_mix.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&bad_mix.stderr).contains("unknown payment habit"));
+}
+
+#[test]
+fn warnings_do_not_fail_the_run() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_warnings.txt");
+    std::fs::write(&path, "2.12,3.00, \n1.00,500.00\n").unwrap();
+
+    let output = cargo_bin()
+        .args([path.as_str(), "--divisor", "0"])
+        .output()
+        .expect("failed to run binary");
+    std::fs::remove_file(&path).ok();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().next(), Some("3 quarters,1 dime,3 pennies"));
+    assert_eq!(stdout.lines().count(), 2);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("line 1: warning: empty field ignored\n"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("line 2: warning: unusually large change of 499.00\n"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn strategy_and_json_output_flags() {
+    let output = cargo_bin()
+        .args([
+            "sample_input.txt",
+            "--strategy",
+            "greedy",
+            "--output",
+            "json",
+        ])
+        .output()
+        .expect("failed to run binary");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3, "{stdout}");
+    assert_eq!(
+        lines[0],
+        r#"{"change":[{"cents":25,"count":3,"name":"quarters"},{"cents":10,"count":1,"name":"dime"},{"cents":1,"count":3,"name":"pennies"}],"iou_cents":0,"line":1,"owed":"2.12","paid":"3.00","random":false,"shortfall_cents":0}"#
+    );
+    // 3.33 would be random under the default strategy.
+    assert!(lines[2].contains(r#""random":false"#), "{}", lines[2]);
+
+    let bad = cargo_bin()
+        .args(["sample_input.txt", "--strategy", "cheapest"])
+        .output()
+        .expect("failed to run binary");
+    assert_eq!(bad.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&bad.stderr).contains("unknown strategy \"cheapest\""));
+}
+
+#[test]
+fn ledger_output_balances_each_sale() {
+    let output = cargo_bin()
+        .args([
+            "sample_input.txt",
+            "--strategy",
+            "greedy",
+            "--output",
+            "ledger",
+        ])
+        .output()
+        .expect("failed to run binary");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let entries: Vec<&str> = stdout.split("\n\n").filter(|e| !e.is_empty()).collect();
+    assert_eq!(entries.len(), 3, "{stdout}");
+    let first: Vec<&str> = entries[0].lines().collect();
+    assert!(first[0].ends_with(r#" * "line 1""#), "{}", first[0]);
+    assert_eq!(first[0].len(), "2024-01-01 * \"line 1\"".len());
+    assert_eq!(
+        &first[1..],
+        [
+            "    Assets:Cash:Drawer                    3.00 USD",
+            "    Assets:Cash:Drawer                   -0.88 USD",
+            "    Income:Sales                         -2.12 USD",
+        ]
+    );
+}
+
+#[test]
+fn totals_balance_paid_and_owed_against_change() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let till_path = format!("{dir}/test_totals_till.txt");
+    std::fs::write(&till_path, "0.01,2\n").unwrap();
+    let output = cargo_bin()
+        .args([
+            "sample_input.txt",
+            "--divisor",
+            "0",
+            "--totals",
+            "--till",
+            &till_path,
+            "--shortage-policy",
+            "partial",
+            "--till-report",
+            "/dev/null",
+        ])
+        .output()
+        .expect("failed to run binary");
+    std::fs::remove_file(&till_path).ok();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.ends_with(
+            "\nTotals (USD), 3 transactions:\n\
+             \x20 Paid          $10.00\n\
+             \x20 Owed           $7.42\n\
+             \x20 Paid - owed    $2.58\n\
+             \x20 Change given   $0.02\n\
+             \x20 Short          $2.56\n\
+             \x20 Over           $0.00\n\
+             Balanced: paid - owed = change given + short - over\n"
+        ),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn bad_flag_values_are_usage_errors() {
+    let output = cargo_bin()
+        .args(["sample_input.txt", "--divisor", "abc"])
+        .output()
+        .expect("failed to run binary");
+    assert_eq!(output.status.code(), Some(64));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("invalid value 'abc' for '--divisor"),
+        "{stderr}"
+    );
+
+    let help = cargo_bin()
+        .args(["run", "--help"])
+        .output()
+        .expect("failed to run binary");
+    assert!(help.status.success());
+    let stdout = String::from_utf8_lossy(&help.stdout);
+    assert!(stdout.contains("--shortage-policy"), "{stdout}");
+
+    // The file can still come first, without naming the `run` subcommand.
+    let explicit = cargo_bin()
+        .args(["run", "sample_input.txt", "--divisor", "0"])
+        .output()
+        .expect("failed to run binary");
+    let implicit = cargo_bin()
+        .args(["sample_input.txt", "--divisor", "0"])
+        .output()
+        .expect("failed to run binary");
+    assert_eq!(explicit.stdout, implicit.stdout);
+}
+
+#[test]
+fn validate_checks_without_making_change() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_validate.txt");
+    std::fs::write(&path, "2.12,3.00\nabc,1.00\n1.00,5.00\n").unwrap();
+
+    let run = |extra: &[&str]| {
+        cargo_bin()
+            .args(["validate", path.as_str()])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let malformed = run(&[]);
+    let clean = cargo_bin()
+        .args(["validate", "sample_input.txt", "--max-bill", "1.00"])
+        .output()
+        .expect("failed to run binary");
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(malformed.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&malformed.stdout);
+    assert!(
+        stdout.ends_with(": 2 transactions, 1 errors, 0 refused, 0 overpaid\n"),
+        "{stdout}"
+    );
+    assert!(String::from_utf8_lossy(&malformed.stderr).contains("line 2"));
+
+    assert!(clean.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&clean.stdout),
+        "sample_input.txt: 3 transactions, 0 errors, 0 refused, 0 overpaid\n"
+    );
+}
+
+#[test]
+fn max_error_reports_caps_stderr_but_counts_everything() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_many_errors.txt");
+    std::fs::write(&path, format!("{}1.00,2.00\n", "oops\n".repeat(1003))).unwrap();
+
+    let run = |args: &[&str]| {
+        cargo_bin()
+            .args(args)
+            .args([path.as_str(), "--max-error-reports", "3"])
+            .output()
+            .expect("failed to run binary")
+    };
+    let validated = run(&["validate"]);
+    let ran = run(&[]);
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(validated.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&validated.stderr);
+    assert_eq!(stderr.lines().count(), 4, "{stderr}");
+    assert!(stderr.ends_with("…and 1,000 more errors\n"), "{stderr}");
+    assert!(String::from_utf8_lossy(&validated.stdout).contains("1003 errors"));
+
+    assert_eq!(ran.status.code(), Some(2));
+    assert_eq!(String::from_utf8_lossy(&ran.stdout), "1 dollar\n");
+    let stderr = String::from_utf8_lossy(&ran.stderr);
+    assert!(stderr.starts_with("line 1: "), "{stderr}");
+    assert!(stderr.ends_with("…and 1,000 more errors\n"), "{stderr}");
+}
+
+#[test]
+fn extra_fields_are_rejected_dropped_or_carried_through() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_extra_fields.txt");
+    std::fs::write(&path, "2.12,3.00,notes\n1.97,2.00\n").unwrap();
+
+    let run = |args: &[&str]| {
+        cargo_bin()
+            .arg(&path)
+            .args(["--divisor", "0"])
+            .args(args)
+            .output()
+            .expect("failed to run binary")
+    };
+    let rejected = run(&[]);
+    let ignored = run(&["--extra-fields", "ignore"]);
+    let kept = run(&["--extra-fields", "metadata"]);
+    let kept_json = run(&["--extra-fields", "metadata", "--output", "json"]);
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(rejected.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&rejected.stderr);
+    assert!(stderr.contains("unexpected field \"notes\""), "{stderr}");
+
+    assert!(ignored.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&ignored.stdout),
+        "3 quarters,1 dime,3 pennies\n3 pennies\n"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&kept.stdout),
+        "3 quarters,1 dime,3 pennies\tnotes\n3 pennies\n"
+    );
+    let json = String::from_utf8_lossy(&kept_json.stdout);
+    let lines: Vec<&str> = json.lines().collect();
+    assert!(lines[0].contains(r#""extra":["notes"]"#), "{json}");
+    assert!(!lines[1].contains("extra"), "{json}");
+}
+
+#[test]
+fn underpayment_report_keeps_output_aligned_with_input() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_underpayment_report.txt");
+    std::fs::write(&path, "2.12,3.00\n5.00,3.00\n1.97,2.00\n").unwrap();
+
+    let output = cargo_bin()
+        .arg(&path)
+        .args(["--divisor", "0", "--on-underpayment", "report"])
+        .output()
+        .expect("failed to run binary");
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(output.status.code(), Some(4));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "3 quarters,1 dime,3 pennies\n\
+         insufficient payment: short $2.00 (2 dollars)\n\
+         3 pennies\n"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("line 2: paid (3.00) is less than owed (5.00)"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn on_error_policies_apply_per_category() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_on_error.txt");
+    std::fs::write(&path, "2.12,3.00\nabc\n1.97,2.00\n5.00,3.00\n1.00,1.00\n").unwrap();
+
+    let run = |policies: &str| {
+        cargo_bin()
+            .arg(&path)
+            .args(["--divisor", "0", "--on-error", policies])
+            .output()
+            .expect("failed to run binary")
+    };
+    let placeholder = run("malformed=placeholder");
+    let aborted = run("malformed=skip,underpayment=abort");
+    let rejected = run("overdraft=abort");
+    std::fs::remove_file(&path).ok();
+
+    // The malformed line gets a placeholder; the underpayment is skipped.
+    assert_eq!(placeholder.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8_lossy(&placeholder.stdout),
+        "3 quarters,1 dime,3 pennies\nerror: E003\n3 pennies\nno change\n"
+    );
+
+    // The malformed line is skipped; the run stops at the underpayment.
+    assert_eq!(aborted.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8_lossy(&aborted.stdout),
+        "3 quarters,1 dime,3 pennies\n3 pennies\n"
+    );
+    let stderr = String::from_utf8_lossy(&aborted.stderr);
+    assert!(
+        stderr.contains("Aborted at line 4 (--on-error underpayment=abort)"),
+        "{stderr}"
+    );
+
+    assert_eq!(rejected.status.code(), Some(64));
+    let stderr = String::from_utf8_lossy(&rejected.stderr);
+    assert!(stderr.contains("unknown error category"), "{stderr}");
+}
+
+#[test]
+fn max_errors_stops_a_corrupt_file_early() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_max_errors.txt");
+    std::fs::write(&path, "2.12,3.00\nabc\ndef\n1.97,2.00\nghi\n").unwrap();
+
+    let output = cargo_bin()
+        .arg(&path)
+        .args(["--divisor", "0", "--max-errors", "2"])
+        .output()
+        .expect("failed to run binary");
+    let first_error = cargo_bin()
+        .arg(&path)
+        .args(["--divisor", "0", "--max-errors", "1"])
+        .output()
+        .expect("failed to run binary");
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "3 quarters,1 dime,3 pennies\n"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Aborted before line 4: 2 lines failed (--max-errors 2)"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("line 5"), "{stderr}");
+
+    let stderr = String::from_utf8_lossy(&first_error.stderr);
+    assert!(
+        stderr.contains("Aborted before line 3: 1 line failed (--max-errors 1)"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn split_tender_gives_change_from_the_cash() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_split_tender.txt");
+    std::fs::write(
+        &path,
+        "2.12,CARD:1.00+CASH:2.00\n2.12,CARD:5.00+CASH:1.00\n2.12,GIFT:5.00+CASH:1.00\n",
+    )
+    .unwrap();
+
+    let run = |extra: &[&str]| {
+        cargo_bin()
+            .arg(&path)
+            .args(["--divisor", "0"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let verbose = run(&["--verbose"]);
+    let json = run(&["--output", "json"]);
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(verbose.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8_lossy(&verbose.stdout),
+        "Owed $2.12, Paid $3.00 -> 3 quarters,1 dime,3 pennies (paid card $1.00 + cash $2.00)\n\
+         Owed $2.12, Paid $3.12 -> 1 dollar (paid gift $2.12 + cash $1.00, $2.88 back on gift)\n"
+    );
+    let stderr = String::from_utf8_lossy(&verbose.stderr);
+    assert!(
+        stderr.contains("line 2: tender refused: non-cash tender of 5.00"),
+        "{stderr}"
+    );
+
+    let stdout = String::from_utf8_lossy(&json.stdout);
+    assert!(
+        stdout.contains(r#""paid":"3.00""#)
+            && stdout.contains(
+                r#""tender":[{"amount":"1.00","kind":"card"},{"amount":"2.00","kind":"cash"}]"#
+            ),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains(r#""back_on_card":[{"amount":"2.88","kind":"gift"}]"#),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn refunds_are_paid_out_of_the_drawer() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let input = format!("{dir}/test_refund_input.txt");
+    let till_path = format!("{dir}/test_refund_till.txt");
+    let report_path = format!("{dir}/test_refund_report.txt");
+    std::fs::write(&input, "2.12,3.00,tendered=1.00x3\nREFUND,2.12\n-0.30,0\n").unwrap();
+    std::fs::write(&till_path, "1.00,5\n0.25,5\n0.10,5\n0.05,5\n0.01,5\n").unwrap();
+
+    let run = |extra: &[&str]| {
+        cargo_bin()
+            .arg(&input)
+            .args(["--divisor", "3"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let text = run(&["--till", &till_path, "--till-report", &report_path]);
+    let report = std::fs::read_to_string(&report_path).unwrap_or_default();
+    let json = run(&["--output", "json"]);
+    std::fs::remove_file(&input).ok();
+    std::fs::remove_file(&till_path).ok();
+    std::fs::remove_file(&report_path).ok();
+
+    assert!(
+        text.status.success(),
+        "{}",
+        String::from_utf8_lossy(&text.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&text.stdout),
+        "3 quarters,1 dime,3 pennies\n\
+         Refund $2.12, pay out 2 dollars,1 dime,2 pennies\n\
+         Refund $0.30, pay out 1 quarter,1 nickel\n"
+    );
+    // The sale's $3.00 went in; the change and both refunds came out. Owing
+    // nothing, refunds never get random change.
+    assert!(report.contains("Total: $6.75"), "{report}");
+    assert!(report.contains("6 dollars"), "{report}");
+
+    let stdout = String::from_utf8_lossy(&json.stdout);
+    assert!(
+        stdout.contains(
+            r#"{"iou_cents":0,"line":3,"payout":[{"cents":25,"count":1,"name":"quarter"},{"cents":5,"count":1,"name":"nickel"}],"refund":"0.30","shortfall_cents":0}"#
+        ),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn receipts_are_written_as_escpos() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let input = format!("{dir}/test_receipt_input.txt");
+    let receipt = format!("{dir}/test_receipt.bin");
+    std::fs::write(&input, "2.12,3.00\n5.00,3.00\n1.97,2.00\n").unwrap();
+    std::fs::remove_file(&receipt).ok();
+
+    let output = cargo_bin()
+        .arg(&input)
+        .args(["--divisor", "0", "--receipt", &receipt])
+        .output()
+        .expect("failed to run binary");
+    let bytes = std::fs::read(&receipt).unwrap();
+    std::fs::remove_file(&input).ok();
+    std::fs::remove_file(&receipt).ok();
+
+    assert_eq!(output.status.code(), Some(4));
+    let text = String::from_utf8_lossy(&bytes);
+    // One receipt per sale: reset, bold change, and a cut.
+    assert_eq!(text.matches("\x1b@").count(), 2, "{text:?}");
+    assert_eq!(text.matches("\x1dVB\x00").count(), 2, "{text:?}");
+    assert!(
+        text.contains("\x1bE\x01Change                     $0.88\n\x1bE\x00"),
+        "{text:?}"
+    );
+    assert!(
+        text.contains("  3 pennies                $0.03\n"),
+        "{text:?}"
+    );
+}
+
+#[test]
+fn deposit_slip_lists_each_register() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let state_path = format!("{dir}/test_deposit_state.json");
+    std::fs::write(
+        &state_path,
+        r#"{"version":2,"currency":"USD","registers":{"":[{"cents":100,"count":5},{"cents":25,"count":83}],"R2":[{"cents":10,"count":50}]}}"#,
+    )
+    .unwrap();
+
+    let all = cargo_bin()
+        .args(["deposit-slip", &state_path])
+        .output()
+        .expect("failed to run binary");
+    let missing = cargo_bin()
+        .args(["deposit-slip", &state_path, "--register", "R9"])
+        .output()
+        .expect("failed to run binary");
+    std::fs::remove_file(&state_path).ok();
+
+    assert!(all.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&all.stdout),
+        "Deposit slip (USD):\n\
+         Bills:\n  5 dollars ($5.00)\n\
+         Coins:\n  83 quarters: 2 rolls + 3 loose ($20.75)\n\
+         Bills total: $5.00\nCoins total: $20.75\nTotal deposit: $25.75\n\
+         \n\
+         Deposit slip R2 (USD):\n\
+         Bills:\n  none\n\
+         Coins:\n  50 dimes: 1 roll + 0 loose ($5.00)\n\
+         Bills total: $0.00\nCoins total: $5.00\nTotal deposit: $5.00\n"
+    );
+    assert_eq!(missing.status.code(), Some(1));
+}
+
+#[test]
+fn stats_summarizes_a_file() {
+    let output = cargo_bin()
+        .args(["stats", "sample_input.txt", "--divisor", "0"])
+        .output()
+        .expect("failed to run binary");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("Change stats (USD), 3 transactions:\n"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains("\n  under $1.00    2  ########################################\n"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.ends_with("Random rule: 0 of 3 transactions (0.0%)\n"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn export_writes_qif_and_ofx() {
+    let export = |format: &str| {
+        let output = cargo_bin()
+            .args([
+                "export",
+                "sample_input.txt",
+                "--format",
+                format,
+                "--date",
+                "2026-03-09",
+                "--divisor",
+                "0",
+            ])
+            .output()
+            .expect("failed to run binary");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    let qif = export("qif");
+    assert!(
+        qif.starts_with(
+            "!Type:Cash\nD03/09/2026\nT2.12\nPSale\nMline 1\nSSales\n$3.00\n\
+             SChange\n$-0.88\nE3 quarters,1 dime,3 pennies\n^\n"
+        ),
+        "{qif}"
+    );
+    assert_eq!(qif.matches("^\n").count(), 3);
+
+    let ofx = export("OFX");
+    assert!(ofx.contains("<FITID>20260309-3-change</FITID>"), "{ofx}");
+    assert!(ofx.contains("<BALAMT>7.42</BALAMT>"), "{ofx}");
+
+    let bad = cargo_bin()
+        .args(["export", "sample_input.txt", "--format", "csv"])
+        .output()
+        .expect("failed to run binary");
+    assert_eq!(bad.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&bad.stderr).contains("unknown export format \"csv\""));
+}
+
+#[test]
+fn reconcile_compares_count_with_saved_state() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let state_path = format!("{dir}/test_reconcile_state.json");
+    let count_path = format!("{dir}/test_reconcile_count.txt");
+    std::fs::write(
+        &state_path,
+        r#"{"version":2,"currency":"USD","registers":{"":[{"cents":100,"count":5},{"cents":25,"count":40}]}}"#,
+    )
+    .unwrap();
+
+    let run = |count: &str, extra: &[&str]| {
+        std::fs::write(&count_path, count).unwrap();
+        cargo_bin()
+            .args(["reconcile", &count_path, "--till-state", &state_path])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let short = run("1.00,5\n0.25,38\n", &[]);
+    let balanced = run("1.00,5\n0.25,40\n", &[]);
+    let missing = run("1.00,5\n", &["--register", "R9"]);
+    std::fs::remove_file(&state_path).ok();
+    std::fs::remove_file(&count_path).ok();
+
+    assert_eq!(short.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8_lossy(&short.stdout),
+        "Reconciliation (USD):\n  quarters: expected 40, counted 38 (short $0.50)\nNet: short $0.50\n"
+    );
+
+    assert!(balanced.status.success());
+    assert!(String::from_utf8_lossy(&balanced.stdout).ends_with("Net: balanced\n"));
+
+    assert_eq!(missing.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("no register \"R9\""));
+}
+
+#[test]
+fn config_files_layer_under_flags() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let xdg_dir = format!("{dir}/test_config_xdg");
+    let cwd_dir = format!("{dir}/test_config_cwd");
+    std::fs::create_dir_all(&xdg_dir).unwrap();
+    std::fs::create_dir_all(&cwd_dir).unwrap();
+    std::fs::write(
+        format!("{xdg_dir}/cash-register.toml"),
+        "currency = \"EUR\"\nseed = 5\n",
+    )
+    .unwrap();
+    std::fs::write(
+        format!("{cwd_dir}/cash-register.toml"),
+        "currency = \"USD\"\nstrategy = \"greedy\"\n",
+    )
+    .unwrap();
+
+    let run = |args: &[&str]| {
+        cargo_bin()
+            .current_dir(&cwd_dir)
+            .env("XDG_CONFIG_HOME", &xdg_dir)
+            .args(args)
+            .output()
+            .expect("failed to run binary")
+    };
+    let show = run(&["config", "show", "--output", "json"]);
+    let sample = format!("{dir}/sample_input.txt");
+    let greedy = run(&[sample.as_str()]);
+    std::fs::write(
+        format!("{cwd_dir}/cash-register.toml"),
+        "colour = \"red\"\n",
+    )
+    .unwrap();
+    let bad = run(&[sample.as_str()]);
+    std::fs::remove_dir_all(&xdg_dir).ok();
+    std::fs::remove_dir_all(&cwd_dir).ok();
+
+    assert!(show.status.success());
+    let stdout = String::from_utf8_lossy(&show.stdout);
+    assert!(stdout.starts_with("# from "), "{stdout}");
+    // The current dir's file wins over the XDG one; flags win over both.
+    assert!(stdout.contains("currency = \"USD\"\n"), "{stdout}");
+    assert!(stdout.contains("seed = 5\n"), "{stdout}");
+    assert!(stdout.contains("strategy = \"greedy\"\n"), "{stdout}");
+    assert!(stdout.contains("output = \"json\"\n"), "{stdout}");
+
+    assert!(greedy.status.success());
+    let stdout = String::from_utf8_lossy(&greedy.stdout);
+    assert_eq!(
+        stdout.lines().nth(2),
+        Some("1 dollar,2 quarters,1 dime,1 nickel,2 pennies")
+    );
+
+    assert_eq!(bad.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&bad.stderr);
+    assert!(stderr.contains("cash-register.toml"), "{stderr}");
+    assert!(stderr.contains("unknown field `colour`"), "{stderr}");
+}
+
+#[test]
+fn environment_variables_layer_under_flags() {
+    let run = |args: &[&str]| {
+        cargo_bin()
+            .env("CASH_REGISTER_DIVISOR", "0")
+            .env("CASH_REGISTER_OUTPUT", "json")
+            .args(args)
+            .output()
+            .expect("failed to run binary")
+    };
+    let from_env = run(&["sample_input.txt"]);
+    let flag_wins = run(&["sample_input.txt", "--output", "text"]);
+    let show = run(&["config", "show", "--seed", "8"]);
+    let bad = cargo_bin()
+        .env("CASH_REGISTER_DIVISOR", "abc")
+        .arg("sample_input.txt")
+        .output()
+        .expect("failed to run binary");
+
+    assert!(from_env.status.success());
+    let stdout = String::from_utf8_lossy(&from_env.stdout);
+    let third = stdout.lines().nth(2).unwrap_or_default();
+    assert!(third.starts_with(r#"{"change":"#), "{stdout}");
+    assert!(third.contains(r#""random":false"#), "{stdout}");
+
+    assert_eq!(
+        String::from_utf8_lossy(&flag_wins.stdout).lines().nth(2),
+        Some("1 dollar,2 quarters,1 dime,1 nickel,2 pennies")
+    );
+
+    let stdout = String::from_utf8_lossy(&show.stdout);
+    assert!(stdout.contains("divisor = 0\nseed = 8\n"), "{stdout}");
+
+    assert_eq!(bad.status.code(), Some(64));
+    assert!(String::from_utf8_lossy(&bad.stderr).contains("invalid value 'abc'"));
+}
+
+#[test]
+fn log_flags_write_events_to_stderr() {
+    let run = |extra: &[&str]| {
+        cargo_bin()
+            .args(["sample_input.txt", "--divisor", "0"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let quiet = run(&[]);
+    let text = run(&["--log-level", "debug"]);
+    let json = run(&["--log-level", "debug", "--log-json"]);
+    let bad = run(&["--log-level", "chatty"]);
+
+    assert!(quiet.stderr.is_empty());
+    assert_eq!(text.stdout, quiet.stdout);
+    let stderr = String::from_utf8_lossy(&text.stderr);
+    assert!(stderr.contains("selected strategy"), "{stderr}");
+    assert!(stderr.contains("line{line=3}"), "{stderr}");
+
+    let stderr = String::from_utf8_lossy(&json.stderr);
+    assert!(
+        stderr
+            .lines()
+            .all(|l| l.starts_with('{') && l.ends_with('}')),
+        "{stderr}"
+    );
+    assert!(stderr.contains(r#""strategy":"greedy""#), "{stderr}");
+
+    assert_eq!(bad.status.code(), Some(64));
+}
+
+#[test]
+fn version_json_describes_the_build() {
+    let output = cargo_bin()
+        .args(["--version", "--json"])
+        .output()
+        .expect("failed to run binary");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!(r#""version":"{}""#, env!("CARGO_PKG_VERSION"))),
+        "{stdout}"
+    );
+    assert!(stdout.contains(r#""currencies":["USD","EUR"]"#), "{stdout}");
+    assert!(
+        stdout.contains(r#""strategies":["auto","greedy","random"]"#),
+        "{stdout}"
+    );
+    assert!(stdout.contains(r#""output_schema_version":1"#), "{stdout}");
+
+    let plain = cargo_bin()
+        .arg("--version")
+        .output()
+        .expect("failed to run binary");
+    assert_eq!(
+        String::from_utf8_lossy(&plain.stdout),
+        format!("cash-register {}\n", env!("CARGO_PKG_VERSION"))
+    );
+}
+
+#[test]
+fn rpc_mode_answers_each_request_line() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = cargo_bin()
+        .arg("--rpc")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to run binary");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(
+            concat!(
+                r#"{"jsonrpc":"2.0","id":1,"method":"make_change","params":{"owed":"2.12","paid":"3.00"}}"#,
+                "\n",
+                r#"{"jsonrpc":"2.0","method":"set_config","params":{"currency":"EUR"}}"#,
+                "\n\n",
+                r#"{"jsonrpc":"2.0","id":2,"method":"parse_line","params":{"line":"3.00,2.00"}}"#,
+                "\n",
+                r#"{"jsonrpc":"2.0","id":3,"method":"make_change","params":{"owed":"1.00","paid":"3.00"}}"#,
+                "\n",
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3, "{stdout}");
+    assert!(
+        lines[0].contains(r#""text":"3 quarters,1 dime,3 pennies""#),
+        "{stdout}"
+    );
+    assert!(lines[1].contains(r#""data":{"code":"E002"}"#), "{stdout}");
+    assert!(lines[2].contains(r#""text":"1 2 euro coin""#), "{stdout}");
+}
+
+#[test]
+fn rpc_mode_serves_metrics() {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpStream;
+    use std::process::Stdio;
+
+    let mut child = cargo_bin()
+        .args(["--rpc", "--metrics-addr", "127.0.0.1:0"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run binary");
+    let mut stderr = BufReader::new(child.stderr.take().unwrap());
+    let mut serving = String::new();
+    stderr.read_line(&mut serving).unwrap();
+    let addr = serving
+        .trim()
+        .strip_prefix("Serving metrics on http://")
+        .and_then(|url| url.strip_suffix("/metrics"))
+        .unwrap_or_else(|| panic!("{serving}"))
+        .to_string();
+
+    let mut stdin = child.stdin.take().unwrap();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    for request in [
+        r#"{"jsonrpc":"2.0","id":1,"method":"make_change","params":{"owed":"2.12","paid":"3.00"}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"make_change","params":{"owed":"3.00","paid":"2.00"}}"#,
+    ] {
+        writeln!(stdin, "{request}").unwrap();
+        let mut response = String::new();
+        stdout.read_line(&mut response).unwrap();
+    }
+    let scrape = |path: &str| {
+        let mut stream = TcpStream::connect(&addr).unwrap();
+        write!(stream, "GET {path} HTTP/1.1\r\nHost: {addr}\r\n\r\n").unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    // A client that never finishes its request doesn't hold up the others.
+    let mut stalled = TcpStream::connect(&addr).unwrap();
+    write!(stalled, "GET /met").unwrap();
+    let metrics = scrape("/metrics");
+    let missing = scrape("/");
+    // One that never ends its request line is cut off, not read forever:
+    // answered, or reset for the bytes left unread.
+    let mut oversized = TcpStream::connect(&addr).unwrap();
+    oversized.write_all(&[b'A'; 20_000]).ok();
+    let mut cut_off = String::new();
+    let read = oversized.read_to_string(&mut cut_off);
+    drop(stalled);
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+
+    assert!(metrics.starts_with("HTTP/1.1 200 OK\r\n"), "{metrics}");
+    for line in [
+        r#"cash_register_transactions_total{selection="greedy"} 1"#,
+        r#"cash_register_errors_total{kind="E002"} 1"#,
+        r#"cash_register_change_cents_bucket{le="100"} 1"#,
+        "cash_register_change_pieces_sum 7",
+        "cash_register_request_duration_seconds_count 2",
+    ] {
+        assert!(metrics.contains(line), "{line} in:\n{metrics}");
+    }
+    assert!(
+        missing.starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{missing}"
+    );
+    assert!(
+        read.is_err() || cut_off.starts_with("HTTP/1.1 404 Not Found\r\n"),
+        "{cut_off}"
+    );
+}
+
+#[test]
+fn foreign_tender_gives_change_in_either_currency() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_foreign_tender.txt");
+    std::fs::write(
+        &path,
+        "2.12,2.00,currency=EUR,rate=1.08\n1.00,1.00,currency=EUR\n2.00,2.00,currency=USD,rate=1\n",
+    )
+    .unwrap();
+
+    let run = |extra: &[&str]| {
+        cargo_bin()
+            .arg(&path)
+            .args(["--divisor", "0", "--verbose", "--exchange-rate", "1.10"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let register = run(&[]);
+    let tendered = run(&["--change-in", "tendered"]);
+    let with_totals = run(&["--change-in", "tendered", "--totals"]);
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(register.status.code(), Some(2));
+    assert_eq!(
+        String::from_utf8_lossy(&register.stdout),
+        "Owed $2.12, Paid $2.16 -> 4 pennies (paid €2.00 at 1.08)\n\
+         Owed $1.00, Paid $1.10 -> 1 dime (paid €1.00 at 1.1)\n"
+    );
+    let stderr = String::from_utf8_lossy(&register.stderr);
+    assert!(
+        stderr.contains("line 3: currency=USD is the register's own currency"),
+        "{stderr}"
+    );
+    // 4 cents is 3.7 euro cents, and 10 cents is 9.09.
+    assert_eq!(
+        String::from_utf8_lossy(&tendered.stdout),
+        "Owed $2.12, Paid $2.16 -> 2 2 cent coins (paid €2.00 at 1.08, change €0.04)\n\
+         Owed $1.00, Paid $1.10 -> 1 5 cent coin,2 2 cent coins (paid €1.00 at 1.1, change €0.09)\n"
+    );
+    assert_eq!(with_totals.status.code(), Some(64));
+}
+
+#[test]
+fn roundup_donates_the_change_below_the_increment() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_roundup.txt");
+    std::fs::write(&path, "2.12,3.00\n1.97,2.00\n2.00,2.00\nREFUND,1.50\n").unwrap();
+
+    let output = cargo_bin()
+        .arg(&path)
+        .args(["--divisor", "0", "--roundup", "0.25", "--totals"])
+        .output()
+        .expect("failed to run binary");
+    std::fs::remove_file(&path).ok();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // Refunds are paid out in full.
+    assert!(
+        stdout.starts_with(
+            "3 quarters (donated $0.13)\n\
+             no change (donated $0.03)\n\
+             no change\n\
+             Refund $1.50, pay out 1 dollar,2 quarters\n"
+        ),
+        "{stdout}"
+    );
+    assert!(stdout.contains("  Donated       $0.16\n"), "{stdout}");
+    assert!(
+        stdout.contains("Balanced: paid - owed + refunded = change given + short + donated - over"),
+        "{stdout}"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Donated $0.16 from 2 of 3 sales, rounding change down to $0.25"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn generate_prints_lines_run_accepts() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_generate.txt");
+    let generate = |extra: &[&str]| {
+        cargo_bin()
+            .args(["generate", "--sales", "50", "--seed", "5", "--tendered"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let first = generate(&["--amounts", "skewed", "--payment-mix", "next-bill=1"]);
+    let again = generate(&["--amounts", "skewed", "--payment-mix", "next-bill=1"]);
+    let bad = generate(&["--amounts", "normal"]);
+
+    assert!(first.status.success());
+    assert_eq!(first.stdout, again.stdout);
+    let lines = String::from_utf8_lossy(&first.stdout).into_owned();
+    assert_eq!(lines.lines().count(), 50);
+    for line in lines.lines() {
+        let paid = line.split(',').nth(1).unwrap();
+        assert!(["5.00", "10.00", "20.00"].contains(&paid), "{line}");
+        assert!(line.contains(",tendered=1.00x"), "{line}");
+    }
+    assert_eq!(bad.status.code(), Some(64));
+
+    // A till with no coins takes the bills and makes the change from them.
+    std::fs::write(&path, &lines).unwrap();
+    let till_path = format!("{dir}/test_generate_till.txt");
+    std::fs::write(&till_path, "0.25,400\n0.10,400\n0.05,400\n0.01,400\n").unwrap();
+    let run = cargo_bin()
+        .arg(&path)
+        .args(["--till", &till_path, "--divisor", "0"])
+        .output()
+        .expect("failed to run binary");
+    std::fs::remove_file(&path).ok();
+    std::fs::remove_file(&till_path).ok();
+    assert!(
+        run.status.success(),
+        "{}",
+        String::from_utf8_lossy(&run.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&run.stdout);
+    assert!(stdout.contains("\nClosing drawer (USD):\n"), "{stdout}");
+    assert!(!stdout.contains("short"), "{stdout}");
+}
+
+#[test]
+fn generate_with_errors_breaks_some_lines() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_generate_errors.txt");
+    let generate = |extra: &[&str]| {
+        cargo_bin()
+            .args(["generate", "--sales", "200", "--seed", "9"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let plain = generate(&[]);
+    let faulty = generate(&["--with-errors", "malformed=10,underpaid=10"]);
+    let defaults = generate(&["--with-errors"]);
+    let too_many = generate(&["--with-errors", "malformed=80,encoding=30"]);
+
+    assert!(faulty.status.success());
+    assert!(defaults.status.success());
+    assert_eq!(too_many.status.code(), Some(64));
+    let stderr = String::from_utf8_lossy(&faulty.stderr);
+    let summary = stderr.trim_end();
+    let counts: Vec<usize> = summary
+        .split(": ")
+        .nth(1)
+        .unwrap()
+        .split(", ")
+        .map(|item| item.split(' ').next().unwrap().parse().unwrap())
+        .collect();
+    assert_eq!(counts[2..], [0, 0], "{summary}");
+    let broken = counts[0] + counts[1];
+    assert!(summary.starts_with(&format!("Quirks in {broken} of 200 lines: ")));
+
+    // Only the lines with a quirk differ from a plain run's.
+    let plain = String::from_utf8_lossy(&plain.stdout).into_owned();
+    let lines = String::from_utf8_lossy(&faulty.stdout).into_owned();
+    let changed = plain
+        .lines()
+        .zip(lines.lines())
+        .filter(|(a, b)| a != b)
+        .count();
+    assert_eq!(changed, broken);
+
+    std::fs::write(&path, &lines).unwrap();
+    let run = cargo_bin()
+        .arg(&path)
+        .output()
+        .expect("failed to run binary");
+    std::fs::remove_file(&path).ok();
+    let errors = String::from_utf8_lossy(&run.stderr)
+        .lines()
+        .filter(|line| line.starts_with("line "))
+        .count();
+    assert_eq!(errors, broken);
+}
+
+#[test]
+fn weights_add_up_the_change() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_weights.txt");
+    std::fs::write(&path, "2.12,3.00\n1.97,2.00\n").unwrap();
+
+    let run = |extra: &[&str]| {
+        cargo_bin()
+            .arg(&path)
+            .args(["--weights", "--divisor", "0"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let text = run(&[]);
+    let json = run(&["--output", "json"]);
+    let jobs = run(&["--jobs", "2"]);
+    std::fs::remove_file(&path).ok();
+
+    assert!(text.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&text.stdout),
+        "3 quarters,1 dime,3 pennies (26.8 g, 4.1 cm³)\n3 pennies (7.5 g, 1.3 cm³)\n"
+    );
+    let stderr = String::from_utf8_lossy(&text.stderr);
+    assert!(
+        stderr.contains("Change handed over: 34.3 g, 5.4 cm³ on 2 lines"),
+        "{stderr}"
+    );
+    let stdout = String::from_utf8_lossy(&json.stdout);
+    assert!(
+        stdout.contains(r#""volume_mm3":4066,"weight_mg":26778}"#),
+        "{stdout}"
+    );
+    assert_eq!(jobs.status.code(), Some(64));
+}
+
+#[test]
+fn exec_hook_gets_each_transaction_as_json() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_exec_hook.txt");
+    let hooked = format!("{dir}/test_exec_hook.jsonl");
+    std::fs::write(&path, "2.12,3.00\nREFUND,1.50\n").unwrap();
+    std::fs::remove_file(&hooked).ok();
+
+    let run = |extra: &[&str]| {
+        cargo_bin()
+            .arg(&path)
+            .args(["--divisor", "0"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let appended = format!("cat >> '{hooked}'; echo \"line $CASH_REGISTER_LINE\"");
+    let ok = run(&["--exec-hook", &appended]);
+    let lines = std::fs::read_to_string(&hooked).unwrap_or_default();
+    let warned = run(&["--exec-hook", "exit 3"]);
+    let aborted = run(&["--exec-hook", "exit 3", "--hook-failure", "abort"]);
+    let ignored = run(&["--exec-hook", "exit 3", "--hook-failure", "ignore"]);
+    std::fs::remove_file(&path).ok();
+    std::fs::remove_file(&hooked).ok();
+
+    assert!(ok.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&ok.stdout),
+        "3 quarters,1 dime,3 pennies\nRefund $1.50, pay out 1 dollar,2 quarters\n"
+    );
+    assert_eq!(String::from_utf8_lossy(&ok.stderr), "line 1\nline 2\n");
+    let lines: Vec<&str> = lines.lines().collect();
+    assert_eq!(lines.len(), 2, "{lines:?}");
+    assert!(lines[0].contains(r#""line":1,"owed":"2.12","paid":"3.00""#));
+    assert!(lines[1].contains(r#""refund":"1.50""#));
+
+    assert_eq!(warned.status.code(), Some(69));
+    assert_eq!(String::from_utf8_lossy(&warned.stdout).lines().count(), 2);
+    let stderr = String::from_utf8_lossy(&warned.stderr);
+    assert!(
+        stderr.contains("line 1: hook `exit 3` failed (exit status: 3)"),
+        "{stderr}"
+    );
+
+    assert_eq!(aborted.status.code(), Some(69));
+    assert_eq!(
+        String::from_utf8_lossy(&aborted.stdout),
+        "3 quarters,1 dime,3 pennies\n"
+    );
+    let stderr = String::from_utf8_lossy(&aborted.stderr);
+    assert!(
+        stderr.contains("Aborted at line 1 (--hook-failure abort)"),
+        "{stderr}"
+    );
+
+    assert!(ignored.status.success());
+    assert!(ignored.stderr.is_empty());
+}
+
+#[test]
+fn flag_suspicious_lists_patterns_for_review() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_flag_suspicious.txt");
+    std::fs::write(
+        &path,
+        "4.99,5.00\n4.99,10.00\n4.99,5.00\n9500.00,9950.00\n1.00,1.00\n",
+    )
+    .unwrap();
+
+    let run = |extra: &[&str]| {
+        cargo_bin()
+            .arg(&path)
+            .args(["--divisor", "0", "--warn-overpayment", "1000.00"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let plain = run(&[]);
+    let flagged = run(&["--flag-suspicious"]);
+    let json = run(&["--flag-suspicious", "--output", "json"]);
+    let strict = run(&["--flag-suspicious", "--suspicious-repeats", "4"]);
+    std::fs::remove_file(&path).ok();
+
+    assert!(plain.stderr.is_empty());
+    assert!(flagged.status.success());
+    assert_eq!(flagged.stdout, plain.stdout);
+    assert_eq!(
+        String::from_utf8_lossy(&flagged.stderr),
+        "warning: 2 suspicious patterns for loss-prevention review\n  \
+         $4.99 rung up 3 times in a row (lines 1-3)\n  \
+         $9950.00 paid, just under the $10000.00 reporting threshold (line 4)\n"
+    );
+    let stdout = String::from_utf8_lossy(&json.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(!lines[1].contains("suspicious"), "{stdout}");
+    assert!(
+        lines[2].contains(r#""suspicious":["repeated-amount"]"#),
+        "{stdout}"
+    );
+    assert!(
+        lines[3].contains(r#""suspicious":["under-threshold"]"#),
+        "{stdout}"
+    );
+    let stderr = String::from_utf8_lossy(&strict.stderr);
+    assert!(
+        stderr.starts_with("warning: 1 suspicious pattern for"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn loyalty_rate_awards_points_per_sale_and_customer() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_loyalty_rate.txt");
+    std::fs::write(
+        &path,
+        "2.12,3.00,customer=C42\n12.50,20.00,customer=C7\n0.99,1.00\nREFUND,3.00,customer=C42\n",
+    )
+    .unwrap();
+
+    let run = |extra: &[&str]| {
+        cargo_bin()
+            .arg(&path)
+            .args(["--divisor", "0", "--loyalty-rate", "1"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let text = run(&[]);
+    let json = run(&["--output", "json"]);
+    std::fs::remove_file(&path).ok();
+
+    assert!(text.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&text.stdout),
+        "3 quarters,1 dime,3 pennies (2 points)\n\
+         7 dollars,2 quarters (12 points)\n\
+         1 penny (0 points)\n\
+         Refund $3.00, pay out 3 dollars (-3 points)\n"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&text.stderr),
+        "Loyalty points at 1 per $1.00: 11 on 4 transactions\n  \
+         C42     -1 point on 2 transactions (-$0.88)\n  \
+         C7      12 points on 1 transaction ($12.50)\n  \
+         (none)   0 points on 1 transaction ($0.99)\n"
+    );
+    let stdout = String::from_utf8_lossy(&json.stdout);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(lines[0].contains(r#""customer":"C42""#), "{stdout}");
+    assert!(lines[0].contains(r#""points":2"#), "{stdout}");
+    assert!(!lines[2].contains("customer"), "{stdout}");
+    assert!(lines[3].contains(r#""points":-3"#), "{stdout}");
+}
+
+#[test]
+fn report_threshold_lists_large_cash_transactions() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_report_threshold.txt");
+    std::fs::write(
+        &path,
+        "2.12,3.00\n\
+         11500.00,12000.00,id=T1001,time=2026-10-16T14:05:00Z\n\
+         12000.00,CARD:5000.00+CASH:7000.00\n\
+         REFUND,11500.00\n",
+    )
+    .unwrap();
+
+    let run = |extra: &[&str]| {
+        cargo_bin()
+            .arg(&path)
+            .args(["--divisor", "0", "--warn-overpayment", "1000.00"])
+            .args(extra)
+            .output()
+            .expect("failed to run binary")
+    };
+    let plain = run(&[]);
+    let report = run(&["--report-threshold", "10000.00"]);
+    let none = run(&["--report-threshold", "20000"]);
+    std::fs::remove_file(&path).ok();
+
+    assert!(report.status.success());
+    assert_eq!(report.stdout, plain.stdout);
+    assert_eq!(
+        String::from_utf8_lossy(&report.stderr),
+        "Cash transactions of $10000.00 or more to report: 2, $23500.00 in all\n  \
+         line 2: $12000.00 received (id T1001, 2026-10-16T14:05:00Z)\n  \
+         line 4: $11500.00 paid out for a refund\n"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&none.stderr),
+        "No cash transactions of $20000.00 or more to report\n"
+    );
+}
+
+#[test]
+fn csv_output_has_a_column_per_denomination() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = format!("{dir}/test_csv_output.txt");
+    std::fs::write(&path, "2.12,3.00\nREFUND,2.12\n5.00,2.00\n").unwrap();
+    let output = cargo_bin()
+        .arg(&path)
+        .args(["--divisor", "0", "--output", "csv"])
+        .args(["--on-underpayment", "report"])
+        .output()
+        .expect("failed to run binary");
+    let tendered = cargo_bin()
+        .arg(&path)
+        .args(["--output", "csv", "--change-in", "tendered"])
+        .output()
+        .expect("failed to run binary");
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "line,owed,paid,change,dollars,quarters,dimes,nickels,pennies,error\n\
+         1,2.12,3.00,0.88,0,3,1,0,3,\n\
+         2,-2.12,0.00,2.12,2,0,1,0,2,\n\
+         3,5.00,2.00,,,,,,,E002\n"
+    );
+    assert_eq!(tendered.status.code(), Some(64));
+}
+
+#[test]
+fn change_subcommand_makes_change_without_a_file() {
+    let change = |args: &[&str]| {
+        cargo_bin()
+            .arg("change")
+            .args(args)
+            .output()
+            .expect("failed to run binary")
+    };
+    let text = change(&["2.12", "3.00"]);
+    assert!(text.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&text.stdout),
+        "3 quarters,1 dime,3 pennies\n"
+    );
+
+    let json = change(&["2.12", "3.00", "--currency", "EUR", "--output", "json"]);
+    let json = String::from_utf8_lossy(&json.stdout);
+    assert!(json.starts_with(r#"{"change":[{"cents":50,"#), "{json}");
+    assert!(json.contains(r#""owed":"2.12","paid":"3.00""#), "{json}");
+
+    let short = change(&["3.00", "2.00"]);
+    assert_eq!(short.status.code(), Some(4));
+    assert_eq!(
+        String::from_utf8_lossy(&short.stderr),
+        "error: paid (2.00) is less than owed (3.00)\n"
+    );
+    assert_eq!(change(&["abc", "3.00"]).status.code(), Some(64));
+}
+
+#[test]
+fn compare_lists_changed_output_lines() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let saved = format!("{dir}/test_compare_saved.txt");
+    std::fs::write(
+        &saved,
+        "3 quarters,1 dime,3 pennies\n3 pennies\n1 dollar,2 quarters,1 dime,1 nickel,2 pennies\n",
+    )
+    .unwrap();
+
+    let compare = |args: &[&str]| {
+        cargo_bin()
+            .args(["compare", "sample_input.txt"])
+            .args(args)
+            .output()
+            .expect("failed to run binary")
+    };
+    let same = compare(&["--against", &saved, "--", "--divisor", "0"]);
+    let euros = compare(&[
+        "--against",
+        &saved,
+        "--",
+        "--divisor",
+        "0",
+        "--currency",
+        "EUR",
+    ]);
+    let runs = compare(&[
+        "--with",
+        "--strategy random --seed 9",
+        "--",
+        "--divisor",
+        "0",
+    ]);
+    let bad = compare(&["--with", "--bogus"]);
+    std::fs::remove_file(&saved).ok();
+
+    assert!(same.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&same.stdout),
+        "No differences in 3 output lines\n"
+    );
+    assert_eq!(euros.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&euros.stdout);
+    assert!(
+        stdout.starts_with("output line 1:\n  - 3 quarters,1 dime,3 pennies\n  + 1 50 cent coin,"),
+        "{stdout}"
+    );
+    assert!(
+        stdout.ends_with("3 of 3 output lines changed\n"),
+        "{stdout}"
+    );
+    assert_eq!(runs.status.code(), Some(2));
+    assert_eq!(bad.status.code(), Some(64));
+}
+
+#[test]
+fn msgpack_output_encodes_the_json_objects() {
+    let run = |output: &str| {
+        cargo_bin()
+            .args(["sample_input.txt", "--divisor", "0", "--output", output])
+            .output()
+            .expect("failed to run binary")
+    };
+    let json = run("json");
+    let msgpack = run("msgpack");
+    assert!(msgpack.status.success());
+
+    let mut expected = Vec::new();
+    for line in String::from_utf8_lossy(&json.stdout).lines() {
+        let value: serde_json::Value = serde_json::from_str(line).unwrap();
+        expected.extend(rmp_serde::to_vec(&value).unwrap());
+    }
+    assert_eq!(msgpack.stdout, expected);
+    // A map of seven fields: change, iou_cents, line, owed, paid, ...
+    assert_eq!(msgpack.stdout[0], 0x87);
+
+    let totals = cargo_bin()
+        .args(["sample_input.txt", "--output", "msgpack", "--totals"])
+        .output()
+        .expect("failed to run binary");
+    assert_eq!(totals.status.code(), Some(64));
+
+    // The drawer report is text, so it goes to stderr, leaving stdout
+    // nothing but MessagePack.
+    let till = cargo_bin()
+        .args(["sample_input.txt", "--divisor", "0", "--output", "msgpack"])
+        .args(["--till", "sample_till.txt"])
+        .output()
+        .expect("failed to run binary");
+    assert!(till.status.success());
+    let mut stdout = till.stdout.as_slice();
+    let mut objects = 0;
+    while !stdout.is_empty() {
+        let value: serde_json::Value = rmp_serde::from_read(&mut stdout).unwrap();
+        assert!(value.is_object());
+        objects += 1;
+    }
+    assert_eq!(
+        objects,
+        String::from_utf8_lossy(&json.stdout).lines().count()
+    );
+    let stderr = String::from_utf8_lossy(&till.stderr);
+    assert!(stderr.contains("Closing drawer (USD):"), "{stderr}");
+}
+
+#[test]
+fn spoken_output_reads_change_as_words() {
+    let output = cargo_bin()
+        .args(["sample_input.txt", "--divisor", "0", "--output", "spoken"])
+        .args(["--verbose"])
+        .output()
+        .expect("failed to run binary");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [
+            "You owe two dollars and twelve cents and paid three dollars. \
+             Your change is eighty-eight cents: three quarters, one dime, and three pennies.",
+            "You owe one dollar and ninety-seven cents and paid two dollars. \
+             Your change is three cents: three pennies.",
+            "You owe three dollars and thirty-three cents and paid five dollars. \
+             Your change is one dollar and sixty-seven cents: one dollar, two quarters, \
+             one dime, one nickel, and two pennies.",
+        ]
+    );
+}
+
+#[test]
+fn audit_finds_altered_output_lines() {
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let saved = format!("{dir}/test_audit_saved.txt");
+    let run = cargo_bin()
+        .args(["sample_input.txt", "--seed", "7", "--output", "json"])
+        .output()
+        .expect("failed to run binary");
+    assert!(run.status.success());
+    std::fs::write(&saved, &run.stdout).unwrap();
+
+    let audit = || {
+        cargo_bin()
+            .args(["audit", "sample_input.txt", &saved])
+            .args(["--seed", "7", "--output", "json"])
+            .output()
+            .expect("failed to run binary")
+    };
+    let clean = audit();
+    // Someone pockets a penny on line 2.
+    let text = String::from_utf8_lossy(&run.stdout).into_owned();
+    let line2 = text.lines().nth(1).unwrap();
+    std::fs::write(
+        &saved,
+        text.replace(line2, &line2.replace("\"count\":3", "\"count\":2")),
+    )
+    .unwrap();
+    let altered = audit();
+    std::fs::remove_file(&saved).ok();
+
+    assert!(clean.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&clean.stdout),
+        "No mismatches in 3 results\n"
+    );
+    assert_eq!(altered.status.code(), Some(2));
+    let stdout = String::from_utf8_lossy(&altered.stdout);
+    assert!(
+        stdout.starts_with("line 2, output line 2:\n  recorded: {"),
+        "{stdout}"
+    );
+    assert!(stdout.ends_with("1 mismatch in 3 results\n"), "{stdout}");
+}
+
+#[test]
+fn pictogram_output_draws_each_denomination() {
+    let output = cargo_bin()
+        .args([
+            "sample_input.txt",
+            "--divisor",
+            "0",
+            "--output",
+            "pictogram",
+        ])
+        .args(["--glyph", "0.01=c", "--glyph", "bill=[$]"])
+        .output()
+        .expect("failed to run binary");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(
+        stdout.lines().collect::<Vec<_>>(),
+        [
+            "🪙🪙🪙 $0.25  🪙 $0.10  ccc $0.01",
+            "ccc $0.01",
+            "[$] $1.00  🪙🪙 $0.25  🪙 $0.10  🪙 $0.05  cc $0.01",
+        ]
+    );
+
+    let bad = cargo_bin()
+        .args([
+            "sample_input.txt",
+            "--output",
+            "pictogram",
+            "--glyph",
+            "penny=p",
+        ])
+        .output()
+        .expect("failed to run binary");
+    assert_eq!(bad.status.code(), Some(64));
+}
+
+#[test]
+fn signed_output_verifies_until_altered() {
+    let dir = std::env::temp_dir().join(format!("cash-register-sign-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let journal = dir.join("journal.jsonl");
+    let output = cargo_bin()
+        .args(["sample_input.txt", "--divisor", "0", "--sign-key", "s3cret"])
+        .args(["--till", "sample_till.txt", "--journal"])
+        .arg(&journal)
+        .output()
+        .expect("failed to run binary");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("#chain start\t1:"));
+    assert!(stdout.contains("\n3 quarters,1 dime,3 pennies\t2:"));
+    assert!(stdout.lines().last().unwrap().starts_with("#chain end\t"));
+
+    let signed = dir.join("output.txt");
+    std::fs::write(&signed, stdout.as_bytes()).unwrap();
+    let verify = |path: &std::path::Path, key: &str| {
+        cargo_bin()
+            .arg("verify")
+            .arg(path)
+            .args(["--sign-key", key])
+            .output()
+            .expect("failed to run binary")
+    };
+    let clean = verify(&signed, "s3cret");
+    assert!(clean.status.success());
+    assert!(String::from_utf8_lossy(&clean.stdout).starts_with("All "));
+    assert!(verify(&journal, "s3cret").status.success());
+
+    std::fs::write(&signed, stdout.replacen("3 pennies\t3:", "1 nickel\t3:", 1)).unwrap();
+    let altered = verify(&signed, "s3cret");
+    std::fs::remove_dir_all(&dir).ok();
+    assert_eq!(altered.status.code(), Some(2));
+    let report = String::from_utf8_lossy(&altered.stdout);
+    assert!(report.starts_with("line 3: altered (its signature doesn't match)\n"));
+}
+
+#[test]
+fn appended_runs_chain_and_verify_as_one() {
+    let dir = std::env::temp_dir().join(format!("cash-register-chain-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let key = dir.join("key");
+    std::fs::write(&key, "s3cret\n").unwrap();
+    let journal = dir.join("journal.jsonl");
+    let output = dir.join("output.txt");
+    // As `>> output.txt` would leave it before the first run.
+    std::fs::write(&output, "").unwrap();
+    for _ in 0..3 {
+        let run = cargo_bin()
+            .args(["sample_input.txt", "--divisor", "0", "--sign-key-file"])
+            .arg(&key)
+            .args(["--till", "sample_till.txt", "--journal"])
+            .arg(&journal)
+            .arg("--sign-after")
+            .arg(&output)
+            .output()
+            .expect("failed to run binary");
+        assert!(run.status.success());
+        let mut all = std::fs::read(&output).unwrap();
+        all.extend_from_slice(&run.stdout);
+        std::fs::write(&output, all).unwrap();
+    }
+    let verify = |path: &std::path::Path| {
+        cargo_bin()
+            .arg("verify")
+            .arg(path)
+            .arg("--sign-key-file")
+            .arg(&key)
+            .output()
+            .expect("failed to run binary")
+    };
+    assert!(verify(&output).status.success());
+    assert!(verify(&journal).status.success());
+
+    // Take out the middle run: the last one no longer follows on.
+    let text = std::fs::read_to_string(&journal).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    let ends: Vec<usize> = (0..lines.len())
+        .filter(|&i| lines[i].starts_with("#chain end\t"))
+        .map(|i| i + 1)
+        .collect();
+    assert_eq!(ends.len(), 3);
+    let kept = [&lines[..ends[0]], &lines[ends[1]..]].concat();
+    std::fs::write(&journal, kept.join("\n") + "\n").unwrap();
+    let second = ends[0] + 1;
+    let deleted = verify(&journal);
+    // And cut the tail off the output.
+    let text = std::fs::read_to_string(&output).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    std::fs::write(&output, lines[..lines.len() - 2].join("\n")).unwrap();
+    let cut = verify(&output);
+    std::fs::remove_dir_all(&dir).ok();
+
+    assert_eq!(deleted.status.code(), Some(2));
+    let report = String::from_utf8_lossy(&deleted.stdout);
+    assert!(report.starts_with(&format!(
+        "line {second}: starts a run that doesn't follow on from the line before it"
+    )));
+    assert_eq!(cut.status.code(), Some(2));
+    let report = String::from_utf8_lossy(&cut.stdout);
+    assert!(report.contains(": its run has no end record"));
 }
@@ -3,6 +3,9 @@ use rand::rngs::StdRng;
 use rand::SeedableRng;
 
 use cash_register::currency::{EUR, USD};
+use cash_register::format::format_breakdown;
+use cash_register::money::Cents;
+use cash_register::parse::parse_breakdown;
 use cash_register::strategy::greedy::GreedyStrategy;
 use cash_register::strategy::random::RandomStrategy;
 use cash_register::strategy::ChangeStrategy;
@@ -12,9 +15,9 @@ proptest! {
     fn random_always_sums_to_target(cents in 1u32..10_000, seed in any::<u64>()) {
         let rng = StdRng::seed_from_u64(seed);
         let mut strategy = RandomStrategy::new(rng);
-        let breakdown = strategy.make_change(cents, &USD);
+        let breakdown = strategy.make_change(Cents::from(cents), &USD).unwrap();
 
-        let total: u32 = breakdown.iter().map(|(d, c)| d.cents * c).sum();
+        let total: u32 = USD.pieces(&breakdown).map(|(d, c)| d.cents * c).sum();
         prop_assert_eq!(total, cents, "random breakdown must sum to target");
     }
 
@@ -22,14 +25,13 @@ proptest! {
     fn random_uses_only_valid_denominations(cents in 1u32..10_000, seed in any::<u64>()) {
         let rng = StdRng::seed_from_u64(seed);
         let mut strategy = RandomStrategy::new(rng);
-        let breakdown = strategy.make_change(cents, &USD);
+        let breakdown = strategy.make_change(Cents::from(cents), &USD).unwrap();
 
-        let valid_values: Vec<u32> = USD.denominations.iter().map(|d| d.cents).collect();
-        for (denom, _) in &breakdown {
+        for (index, _) in &breakdown {
             prop_assert!(
-                valid_values.contains(&denom.cents),
-                "denomination {} is not in USD",
-                denom.cents
+                *index < USD.denominations.len(),
+                "denomination index {} is not in USD",
+                index
             );
         }
     }
@@ -38,19 +40,19 @@ proptest! {
     fn random_all_counts_positive(cents in 1u32..10_000, seed in any::<u64>()) {
         let rng = StdRng::seed_from_u64(seed);
         let mut strategy = RandomStrategy::new(rng);
-        let breakdown = strategy.make_change(cents, &USD);
+        let breakdown = strategy.make_change(Cents::from(cents), &USD).unwrap();
 
-        for (denom, count) in &breakdown {
-            prop_assert!(*count > 0, "{} has count 0", denom.singular);
+        for (denom, count) in USD.pieces(&breakdown) {
+            prop_assert!(count > 0, "{} has count 0", denom.singular);
         }
     }
 
     #[test]
     fn greedy_always_sums_to_target(cents in 0u32..10_000) {
         let mut strategy = GreedyStrategy;
-        let breakdown = strategy.make_change(cents, &USD);
+        let breakdown = strategy.make_change(Cents::from(cents), &USD).unwrap();
 
-        let total: u32 = breakdown.iter().map(|(d, c)| d.cents * c).sum();
+        let total: u32 = USD.pieces(&breakdown).map(|(d, c)| d.cents * c).sum();
         prop_assert_eq!(total, cents, "greedy breakdown must sum to target");
     }
 
@@ -59,7 +61,7 @@ proptest! {
         // For USD denominations, greedy should never use more coins than
         // just using all pennies
         let mut strategy = GreedyStrategy;
-        let breakdown = strategy.make_change(cents, &USD);
+        let breakdown = strategy.make_change(Cents::from(cents), &USD).unwrap();
 
         let total_coins: u32 = breakdown.iter().map(|(_, c)| c).sum();
         prop_assert!(total_coins <= cents, "greedy should use at most {cents} coins, used {total_coins}");
@@ -71,18 +73,18 @@ proptest! {
     fn eur_random_always_sums_to_target(cents in 1u32..10_000, seed in any::<u64>()) {
         let rng = StdRng::seed_from_u64(seed);
         let mut strategy = RandomStrategy::new(rng);
-        let breakdown = strategy.make_change(cents, &EUR);
+        let breakdown = strategy.make_change(Cents::from(cents), &EUR).unwrap();
 
-        let total: u32 = breakdown.iter().map(|(d, c)| d.cents * c).sum();
+        let total: u32 = EUR.pieces(&breakdown).map(|(d, c)| d.cents * c).sum();
         prop_assert_eq!(total, cents, "EUR random breakdown must sum to target");
     }
 
     #[test]
     fn eur_greedy_always_sums_to_target(cents in 0u32..10_000) {
         let mut strategy = GreedyStrategy;
-        let breakdown = strategy.make_change(cents, &EUR);
+        let breakdown = strategy.make_change(Cents::from(cents), &EUR).unwrap();
 
-        let total: u32 = breakdown.iter().map(|(d, c)| d.cents * c).sum();
+        let total: u32 = EUR.pieces(&breakdown).map(|(d, c)| d.cents * c).sum();
         prop_assert_eq!(total, cents, "EUR greedy breakdown must sum to target");
     }
 
@@ -90,15 +92,24 @@ proptest! {
     fn eur_random_uses_only_valid_denominations(cents in 1u32..10_000, seed in any::<u64>()) {
         let rng = StdRng::seed_from_u64(seed);
         let mut strategy = RandomStrategy::new(rng);
-        let breakdown = strategy.make_change(cents, &EUR);
+        let breakdown = strategy.make_change(Cents::from(cents), &EUR).unwrap();
 
-        let valid_values: Vec<u32> = EUR.denominations.iter().map(|d| d.cents).collect();
-        for (denom, _) in &breakdown {
+        for (index, _) in &breakdown {
             prop_assert!(
-                valid_values.contains(&denom.cents),
-                "denomination {} is not in EUR",
-                denom.cents
+                *index < EUR.denominations.len(),
+                "denomination index {} is not in EUR",
+                index
             );
         }
     }
+
+    #[test]
+    fn formatted_change_parses_back(cents in 0u32..10_000, seed in any::<u64>()) {
+        let rng = StdRng::seed_from_u64(seed);
+        let breakdown = RandomStrategy::new(rng).make_change(Cents::from(cents), &EUR).unwrap();
+        let text = format_breakdown(&breakdown, &EUR);
+
+        let parsed = parse_breakdown(&text, &EUR).unwrap();
+        prop_assert_eq!(format_breakdown(&parsed, &EUR), text);
+    }
 }